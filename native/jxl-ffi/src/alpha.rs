@@ -0,0 +1,218 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! Conversion of associated (premultiplied) alpha output to straight alpha.
+
+use crate::conversions::bytes_per_sample;
+use crate::stats::{f16_to_f32, is_big_endian, read_f32, read_u16};
+use crate::types::{JxlColorType, JxlDataFormat, JxlPixelFormat};
+
+/// Divides the color samples of each pixel by its alpha, in place, reading
+/// the first `bytes_per_row` bytes of each `stride`-byte row. Pixels with
+/// zero alpha are left unchanged, and integer results are clamped to the
+/// sample range of `int_bits` bits. Does nothing for formats without alpha.
+pub(crate) fn unpremultiply(
+    pixels: &mut [u8],
+    bytes_per_row: usize,
+    stride: usize,
+    format: &JxlPixelFormat,
+    int_bits: u32,
+) {
+    let channels = match format.ColorType {
+        JxlColorType::GrayscaleAlpha => 2,
+        JxlColorType::Rgba | JxlColorType::Bgra => 4,
+        JxlColorType::Grayscale | JxlColorType::Rgb | JxlColorType::Bgr => return,
+    };
+    let sample_size = bytes_per_sample(format.DataFormat);
+    let int_max = u32::MAX >> (32 - int_bits);
+    let big_endian = is_big_endian(format.Endianness);
+
+    for row in pixels.chunks_mut(stride) {
+        for pixel in row[..bytes_per_row].chunks_exact_mut(channels * sample_size) {
+            let (color, alpha) = pixel.split_at_mut((channels - 1) * sample_size);
+            match format.DataFormat {
+                JxlDataFormat::Uint8 => {
+                    let alpha = alpha[0] as u32;
+                    if alpha != 0 {
+                        for sample in color.iter_mut() {
+                            *sample = unpremultiply_int(*sample as u32, alpha, int_max) as u8;
+                        }
+                    }
+                }
+                JxlDataFormat::Uint16 => {
+                    let alpha = read_u16(alpha, big_endian) as u32;
+                    if alpha != 0 {
+                        for sample in color.chunks_exact_mut(2) {
+                            let value = read_u16(sample, big_endian) as u32;
+                            let value = unpremultiply_int(value, alpha, int_max) as u16;
+                            write_u16(sample, value, big_endian);
+                        }
+                    }
+                }
+                JxlDataFormat::Float16 => {
+                    let alpha = f16_to_f32(read_u16(alpha, big_endian));
+                    if alpha != 0.0 {
+                        for sample in color.chunks_exact_mut(2) {
+                            let value = f16_to_f32(read_u16(sample, big_endian)) / alpha;
+                            write_u16(sample, f32_to_f16(value), big_endian);
+                        }
+                    }
+                }
+                JxlDataFormat::Float32 => {
+                    let alpha = read_f32(alpha, big_endian);
+                    if alpha != 0.0 {
+                        for sample in color.chunks_exact_mut(4) {
+                            let value = read_f32(sample, big_endian) / alpha;
+                            let bytes = if big_endian {
+                                value.to_be_bytes()
+                            } else {
+                                value.to_le_bytes()
+                            };
+                            sample.copy_from_slice(&bytes);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Divides an integer sample by a nonzero integer alpha, rounding to nearest.
+fn unpremultiply_int(value: u32, alpha: u32, max: u32) -> u32 {
+    ((value * max + alpha / 2) / alpha).min(max)
+}
+
+fn write_u16(bytes: &mut [u8], value: u16, big_endian: bool) {
+    let value = if big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    };
+    bytes.copy_from_slice(&value);
+}
+
+/// Converts `f32` to IEEE 754 half-precision bits, rounding to nearest even.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+
+    if exponent == 0xFF {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7C00 | nan;
+    }
+    let exponent = exponent - 112;
+    if exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    // Subnormal halves keep the implicit leading bit in the mantissa
+    let (half, shift, mantissa) = if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        let shift = (14 - exponent) as u32;
+        let mantissa = mantissa | 0x80_0000;
+        (mantissa >> shift, shift, mantissa)
+    } else {
+        (((exponent as u32) << 10) | (mantissa >> 13), 13, mantissa)
+    };
+    let remainder = mantissa & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    let round_up = remainder > halfway || (remainder == halfway && half & 1 != 0);
+
+    // A carry out of the mantissa correctly bumps the exponent
+    sign | (half + round_up as u32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::JxlEndianness;
+
+    #[test]
+    fn test_f32_to_f16_round_trips() {
+        for bits in (0..=0x7BFFu16).chain(0x8000..=0xFBFF) {
+            assert_eq!(f32_to_f16(f16_to_f32(bits)), bits);
+        }
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7C00);
+        assert_eq!(f32_to_f16(1.0e6), 0x7C00);
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3C00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3C02);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    }
+
+    #[test]
+    fn test_unpremultiply_rgba8() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::Rgba,
+            DataFormat: JxlDataFormat::Uint8,
+            Endianness: JxlEndianness::Native,
+        };
+        // Half-transparent, opaque, and fully transparent pixels, then padding
+        let mut pixels = [64, 128, 0, 128, 10, 20, 30, 255, 5, 6, 7, 0, 0xAA, 0xAA];
+        unpremultiply(&mut pixels, 12, 14, &format, 8);
+
+        assert_eq!(
+            pixels,
+            [128, 255, 0, 128, 10, 20, 30, 255, 5, 6, 7, 0, 0xAA, 0xAA]
+        );
+    }
+
+    #[test]
+    fn test_unpremultiply_rgba16_big_endian() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::Rgba,
+            DataFormat: JxlDataFormat::Uint16,
+            Endianness: JxlEndianness::BigEndian,
+        };
+        // 0.2, 0.1, 0.25 premultiplied by alpha 0.25, out of 65535
+        let samples: [u16; 4] = [3277, 1638, 4096, 16384];
+        let mut pixels: Vec<u8> = samples.iter().flat_map(|v| v.to_be_bytes()).collect();
+        unpremultiply(&mut pixels, 8, 8, &format, 16);
+
+        let samples: Vec<u16> = pixels
+            .chunks_exact(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(samples, [13108, 6552, 16384, 16384]);
+    }
+
+    #[test]
+    fn test_unpremultiply_rgba32f_keeps_out_of_range() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::Rgba,
+            DataFormat: JxlDataFormat::Float32,
+            Endianness: JxlEndianness::LittleEndian,
+        };
+        // Float output keeps out-of-range results, like HDR highlights
+        let mut pixels: Vec<u8> = [0.3f32, 0.6, 0.05, 0.5]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        unpremultiply(&mut pixels, 16, 16, &format, 32);
+
+        let samples: Vec<f32> = pixels
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(samples, [0.6, 1.2, 0.1, 0.5]);
+    }
+
+    #[test]
+    fn test_unpremultiply_gray_alpha_float16() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::GrayscaleAlpha,
+            DataFormat: JxlDataFormat::Float16,
+            Endianness: JxlEndianness::BigEndian,
+        };
+        // 0.25 at alpha 0.5, then 1.0 at alpha 0
+        let mut pixels = [0x34, 0x00, 0x38, 0x00, 0x3C, 0x00, 0x00, 0x00];
+        unpremultiply(&mut pixels, 8, 8, &format, 16);
+
+        assert_eq!(pixels, [0x38, 0x00, 0x38, 0x00, 0x3C, 0x00, 0x00, 0x00]);
+    }
+}
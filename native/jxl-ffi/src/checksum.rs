@@ -0,0 +1,143 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! CRC32 content hashes of JPEG XL input, used as cheap cache keys.
+
+use crate::container::read_box_header;
+
+/// JPEG XL container signature box.
+pub(crate) const CONTAINER_SIGNATURE: [u8; 12] = [
+    0x00, 0x00, 0x00, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A,
+];
+
+/// CRC32 (IEEE 802.3, reflected polynomial) lookup table.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Incremental CRC32 state.
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = CRC32_TABLE[((self.0 ^ byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+/// Computes the CRC32 of `data`.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+/// Computes the CRC32 of the input, optionally over only the codestream bytes.
+///
+/// With `codestream_only`, a container's `jxlc` and `jxlp` payloads are hashed
+/// in order and all other boxes (metadata, signature, level) are skipped, so the
+/// result matches the CRC32 of the equivalent bare codestream. Truncated boxes
+/// are hashed up to the end of the available data.
+pub(crate) fn input_crc32(data: &[u8], codestream_only: bool) -> u32 {
+    if !codestream_only || !data.starts_with(&CONTAINER_SIGNATURE) {
+        return crc32(data);
+    }
+
+    let mut crc = Crc32::new();
+    let mut offset = 0;
+    while let Ok(header) = read_box_header(data, offset) {
+        let payload = &data[header.available_payload(data.len())];
+        match &header.box_type {
+            b"jxlc" => crc.update(payload),
+            // Partial codestream boxes start with a 4-byte sequence index
+            b"jxlp" => crc.update(payload.get(4..).unwrap_or(&[])),
+            _ => {}
+        }
+        match header.end() {
+            Some(end) if end < data.len() => offset = end,
+            _ => break,
+        }
+    }
+    crc.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(box_type);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn test_codestream_only_ignores_metadata_boxes() {
+        let codestream = [0xFF, 0x0A, 1, 2, 3, 4, 5, 6];
+
+        let mut container = CONTAINER_SIGNATURE.to_vec();
+        container.extend(make_box(b"ftyp", b"jxl \0\0\0\0jxl "));
+        container.extend(make_box(b"Exif", b"metadata"));
+        container.extend(make_box(
+            b"jxlp",
+            &[&[0, 0, 0, 0][..], &codestream[..3]].concat(),
+        ));
+        container.extend(make_box(
+            b"jxlp",
+            &[&[0x80, 0, 0, 1][..], &codestream[3..]].concat(),
+        ));
+
+        assert_eq!(input_crc32(&container, true), crc32(&codestream));
+        assert_eq!(input_crc32(&container, false), crc32(&container));
+        assert_eq!(input_crc32(&codestream, true), crc32(&codestream));
+    }
+
+    #[test]
+    fn test_codestream_only_huge_box_size() {
+        let codestream = [0xFF, 0x0A, 1, 2, 3];
+
+        // A 64-bit box size of u64::MAX reaches past the end of the address space
+        let mut container = CONTAINER_SIGNATURE.to_vec();
+        container.extend(make_box(b"ftyp", b"jxl \0\0\0\0jxl "));
+        container.extend([0, 0, 0, 1]);
+        container.extend(b"jxlc");
+        container.extend(u64::MAX.to_be_bytes());
+        container.extend(codestream);
+
+        assert_eq!(input_crc32(&container, true), crc32(&codestream));
+    }
+}
@@ -5,17 +5,638 @@
 
 //! Color Management System implementations.
 
+use crate::error::log;
+use crate::types::JxlLogLevel;
+use jxl::api::{JxlCms, JxlCmsTransformer, JxlColorProfile, JxlTransferFunction};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Counters for pixels clipped at the target peak by tone mapping.
+///
+/// Shared between all transformers of a decoder, which may run on
+/// different threads, so the counts are atomic.
+#[derive(Debug, Default)]
+pub(crate) struct ToneMapClipStats {
+    clipped: AtomicU64,
+    total: AtomicU64,
+}
+
+impl ToneMapClipStats {
+    /// Adds `clipped` of `total` tone-mapped pixels to the counters.
+    #[cfg_attr(not(feature = "tone-mapping"), allow(dead_code))]
+    pub(crate) fn record(&self, clipped: u64, total: u64) {
+        self.clipped.fetch_add(clipped, Ordering::Relaxed);
+        self.total.fetch_add(total, Ordering::Relaxed);
+    }
+
+    /// Returns `(clipped, total)` pixel counts.
+    pub(crate) fn get(&self) -> (u64, u64) {
+        (
+            self.clipped.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Resets both counters to zero.
+    pub(crate) fn reset(&self) {
+        self.clipped.store(0, Ordering::Relaxed);
+        self.total.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Error of a CMS that failed to initialize and was replaced by a
+/// passthrough, see the `CmsErrorFallback` option.
+///
+/// Shared with `FallbackCms`, which the decoder may call from any thread.
+#[derive(Debug, Default)]
+pub(crate) struct CmsFallback {
+    error: Mutex<Option<String>>,
+}
+
+impl CmsFallback {
+    /// Returns the CMS error if the passthrough was used.
+    pub(crate) fn error(&self) -> Option<String> {
+        self.error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Forgets a recorded CMS error.
+    pub(crate) fn reset(&self) {
+        *self.error.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+/// CMS wrapper that outputs pixels unconverted when `inner` fails to
+/// initialize, recording the error in `fallback`.
+///
+/// Only applies when input and output have the same gray or RGB channel
+/// count; other errors, such as for CMYK input, are passed on.
+pub(crate) struct FallbackCms {
+    pub inner: Box<dyn JxlCms>,
+    pub fallback: Arc<CmsFallback>,
+}
+
+impl JxlCms for FallbackCms {
+    fn initialize_transforms(
+        &self,
+        n: usize,
+        max_pixels_per_transform: usize,
+        input: JxlColorProfile,
+        output: JxlColorProfile,
+        intensity_target: f32,
+    ) -> jxl::error::Result<(usize, Vec<Box<dyn JxlCmsTransformer + Send>>)> {
+        let channels = input.channels();
+        let passthrough = channels == output.channels() && matches!(channels, 1 | 3);
+        match self.inner.initialize_transforms(
+            n,
+            max_pixels_per_transform,
+            input,
+            output,
+            intensity_target,
+        ) {
+            Err(e) if passthrough => {
+                log(JxlLogLevel::Warning, || {
+                    format!("CMS failed, passing colors through unconverted: {}", e)
+                });
+                *self
+                    .fallback
+                    .error
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) = Some(format!("{}", e));
+                let transforms = (0..n)
+                    .map(|_| Box::new(PassthroughTransformer) as Box<dyn JxlCmsTransformer + Send>)
+                    .collect();
+                Ok((channels, transforms))
+            }
+            result => result,
+        }
+    }
+}
+
+/// Transformer that leaves samples unchanged.
+struct PassthroughTransformer;
+
+impl JxlCmsTransformer for PassthroughTransformer {
+    fn do_transform(&mut self, input: &[f32], output: &mut [f32]) -> jxl::error::Result<()> {
+        if output.len() < input.len() {
+            return Err(jxl::error::Error::CmsError(format!(
+                "Output buffer too small: expected {}, got {}",
+                input.len(),
+                output.len()
+            )));
+        }
+        output[..input.len()].copy_from_slice(input);
+        Ok(())
+    }
+
+    fn do_transform_inplace(&mut self, _inout: &mut [f32]) -> jxl::error::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns true for profiles of HDR content: simple profiles with a PQ or HLG
+/// transfer function, and ICC profiles whose `cicp` tag signals either.
+pub(crate) fn is_hdr_profile(profile: &JxlColorProfile) -> bool {
+    match profile {
+        JxlColorProfile::Icc(data) => crate::icc::is_hdr(data),
+        JxlColorProfile::Simple(_) => matches!(
+            profile.transfer_function(),
+            Some(JxlTransferFunction::PQ | JxlTransferFunction::HLG)
+        ),
+    }
+}
+
+/// CMS wrapper that skips `inner` when the output has the input's color
+/// encoding and no tone mapping is needed, and counts the conversions
+/// `inner` sets up.
+///
+/// Such conversions are identities, so passthrough transforms stand in for
+/// whatever `inner` would build. HDR input still goes to a tone-mapping
+/// `inner`, which maps it even to its own encoding.
+pub(crate) struct BypassCms {
+    pub inner: Box<dyn JxlCms>,
+    /// Whether `inner` tone maps.
+    pub tone_mapping: bool,
+    /// Number of conversions passed on to `inner`, shared with the decoder.
+    pub setups: Arc<AtomicU64>,
+}
+
+impl JxlCms for BypassCms {
+    fn initialize_transforms(
+        &self,
+        n: usize,
+        max_pixels_per_transform: usize,
+        input: JxlColorProfile,
+        output: JxlColorProfile,
+        intensity_target: f32,
+    ) -> jxl::error::Result<(usize, Vec<Box<dyn JxlCmsTransformer + Send>>)> {
+        let tone_maps = self.tone_mapping && is_hdr_profile(&input);
+        if !tone_maps && input.same_color_encoding(&output) {
+            let transforms = (0..n)
+                .map(|_| Box::new(PassthroughTransformer) as Box<dyn JxlCmsTransformer + Send>)
+                .collect();
+            return Ok((output.channels(), transforms));
+        }
+
+        self.setups.fetch_add(1, Ordering::Relaxed);
+        self.inner.initialize_transforms(
+            n,
+            max_pixels_per_transform,
+            input,
+            output,
+            intensity_target,
+        )
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Chromatic adaptation
+// ---------------------------------------------------------------------------
+
+/// Bradford cone response matrix (XYZ → LMS).
+const BRADFORD: [[f32; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+/// Computes the Bradford chromatic adaptation matrix that maps XYZ values
+/// relative to the source white point to XYZ relative to the destination
+/// white point. Returns `None` if a white point has `y == 0`.
+pub(crate) fn bradford_adaptation_matrix(
+    src_wx: f32,
+    src_wy: f32,
+    dst_wx: f32,
+    dst_wy: f32,
+) -> Option<[[f32; 3]; 3]> {
+    if src_wy == 0.0 || dst_wy == 0.0 {
+        return None;
+    }
+
+    let src_xyz = [src_wx / src_wy, 1.0, (1.0 - src_wx - src_wy) / src_wy];
+    let dst_xyz = [dst_wx / dst_wy, 1.0, (1.0 - dst_wx - dst_wy) / dst_wy];
+
+    let src_lms = mul_3x3_vec(&BRADFORD, src_xyz);
+    let dst_lms = mul_3x3_vec(&BRADFORD, dst_xyz);
+    if src_lms.iter().any(|v| v.abs() < 1e-10) {
+        return None;
+    }
+
+    // diag(dst / src) * BRADFORD
+    let mut scaled = BRADFORD;
+    for (row, (d, s)) in scaled.iter_mut().zip(dst_lms.iter().zip(src_lms)) {
+        for v in row.iter_mut() {
+            *v *= d / s;
+        }
+    }
+
+    Some(mul_3x3(&invert_3x3(&BRADFORD)?, &scaled))
+}
+
+/// Inverts a 3x3 matrix (Cramer's rule). Returns `None` if it is singular.
+fn invert_3x3(m: &[[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let [[m00, m01, m02], [m10, m11, m12], [m20, m21, m22]] = *m;
+
+    let det = m00 * (m11 * m22 - m12 * m21) - m01 * (m10 * m22 - m12 * m20)
+        + m02 * (m10 * m21 - m11 * m20);
+
+    if det.abs() < 1e-10 {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+
+    Some([
+        [
+            (m11 * m22 - m12 * m21) * inv_det,
+            (m02 * m21 - m01 * m22) * inv_det,
+            (m01 * m12 - m02 * m11) * inv_det,
+        ],
+        [
+            (m12 * m20 - m10 * m22) * inv_det,
+            (m00 * m22 - m02 * m20) * inv_det,
+            (m02 * m10 - m00 * m12) * inv_det,
+        ],
+        [
+            (m10 * m21 - m11 * m20) * inv_det,
+            (m01 * m20 - m00 * m21) * inv_det,
+            (m00 * m11 - m01 * m10) * inv_det,
+        ],
+    ])
+}
+
+fn mul_3x3(a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+        }
+    }
+    out
+}
+
+fn mul_3x3_vec(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+// ---------------------------------------------------------------------------
+// Transfer functions
+// ---------------------------------------------------------------------------
+
+/// Converts samples encoded with `tf` to linear light in place.
+///
+/// For PQ, 1.0 in the output represents `intensity_target` nits. HLG is
+/// decoded to scene-referred light, without an OOTF.
+pub(crate) fn transfer_to_linear(
+    tf: &JxlTransferFunction,
+    intensity_target: f32,
+    samples: &mut [f32],
+) {
+    match tf {
+        JxlTransferFunction::Linear => {}
+        JxlTransferFunction::SRGB => jxl::color::tf::srgb_to_linear(samples),
+        JxlTransferFunction::BT709 => jxl::color::tf::bt709_to_linear(samples),
+        JxlTransferFunction::PQ => jxl::color::tf::pq_to_linear_precise(intensity_target, samples),
+        JxlTransferFunction::HLG => jxl::color::tf::hlg_to_scene_precise(samples),
+        JxlTransferFunction::DCI => apply_power(samples, 2.6),
+        JxlTransferFunction::Gamma(g) => apply_power(samples, 1.0 / g),
+    }
+}
+
+/// Encodes linear samples with `tf` in place. Inverse of [`transfer_to_linear`].
+pub(crate) fn linear_to_transfer(
+    tf: &JxlTransferFunction,
+    intensity_target: f32,
+    samples: &mut [f32],
+) {
+    match tf {
+        JxlTransferFunction::Linear => {}
+        JxlTransferFunction::SRGB => {
+            for s in samples {
+                let a = s.abs();
+                *s = if a <= 0.0031308 {
+                    a * 12.92
+                } else {
+                    1.055 * a.powf(1.0 / 2.4) - 0.055
+                }
+                .copysign(*s);
+            }
+        }
+        JxlTransferFunction::BT709 => {
+            for s in samples {
+                let a = s.abs();
+                *s = if a < 0.018 {
+                    a * 4.5
+                } else {
+                    1.099 * a.powf(0.45) - 0.099
+                }
+                .copysign(*s);
+            }
+        }
+        JxlTransferFunction::PQ => jxl::color::tf::linear_to_pq_precise(intensity_target, samples),
+        JxlTransferFunction::HLG => jxl::color::tf::scene_to_hlg_precise(samples),
+        JxlTransferFunction::DCI => apply_power(samples, 1.0 / 2.6),
+        JxlTransferFunction::Gamma(g) => apply_power(samples, *g),
+    }
+}
+
+fn apply_power(samples: &mut [f32], exponent: f32) {
+    for s in samples {
+        *s = s.abs().powf(exponent).copysign(*s);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Built-in CMS: matrix + transfer function conversions without lcms2
+// ---------------------------------------------------------------------------
+
+/// Computes the RGB → XYZ matrix for the given primaries and white point.
+fn rgb_to_xyz_matrix(primaries: [(f32, f32); 3], white: (f32, f32)) -> Option<[[f32; 3]; 3]> {
+    let xyz = |(x, y): (f32, f32)| [x / y, 1.0, (1.0 - x - y) / y];
+    let [r, g, b] = primaries.map(xyz);
+    let m = [[r[0], g[0], b[0]], [r[1], g[1], b[1]], [r[2], g[2], b[2]]];
+    let s = mul_3x3_vec(&invert_3x3(&m)?, xyz(white));
+    Some(m.map(|row| [row[0] * s[0], row[1] * s[1], row[2] * s[2]]))
+}
+
+mod builtin_cms {
+    use super::{
+        bradford_adaptation_matrix, invert_3x3, linear_to_transfer, mul_3x3, mul_3x3_vec,
+        rgb_to_xyz_matrix, transfer_to_linear,
+    };
+    use jxl::api::{
+        JxlCms, JxlCmsTransformer, JxlColorEncoding, JxlColorProfile, JxlTransferFunction,
+    };
+    use jxl::error::{Error, Result};
+
+    /// CMS that converts between simple RGB or grayscale encodings without
+    /// lcms2, using a 3x3 primaries matrix and the transfer functions.
+    ///
+    /// ICC, CMYK and XYB profiles are rejected.
+    pub struct BuiltinCms;
+
+    impl JxlCms for BuiltinCms {
+        fn initialize_transforms(
+            &self,
+            n: usize,
+            _max_pixels_per_transform: usize,
+            input: JxlColorProfile,
+            output: JxlColorProfile,
+            intensity_target: f32,
+        ) -> Result<(usize, Vec<Box<dyn JxlCmsTransformer + Send>>)> {
+            let (JxlColorProfile::Simple(input), JxlColorProfile::Simple(output)) =
+                (&input, &output)
+            else {
+                return Err(Error::CmsError(
+                    "Builtin CMS does not support ICC profiles".into(),
+                ));
+            };
+
+            let (channels, matrix, input_tf, output_tf) = match (input, output) {
+                (
+                    JxlColorEncoding::RgbColorSpace {
+                        white_point: in_wp,
+                        primaries: in_prim,
+                        transfer_function: in_tf,
+                        ..
+                    },
+                    JxlColorEncoding::RgbColorSpace {
+                        white_point: out_wp,
+                        primaries: out_prim,
+                        transfer_function: out_tf,
+                        ..
+                    },
+                ) => {
+                    let matrix = conversion_matrix(
+                        in_prim.to_xy_coords(),
+                        in_wp.to_xy_coords(),
+                        out_prim.to_xy_coords(),
+                        out_wp.to_xy_coords(),
+                    )
+                    .ok_or_else(|| {
+                        Error::CmsError("Builtin CMS got degenerate primaries".into())
+                    })?;
+                    (3, Some(matrix), in_tf.clone(), out_tf.clone())
+                }
+                (
+                    JxlColorEncoding::GrayscaleColorSpace {
+                        transfer_function: in_tf,
+                        ..
+                    },
+                    JxlColorEncoding::GrayscaleColorSpace {
+                        transfer_function: out_tf,
+                        ..
+                    },
+                ) => (1, None, in_tf.clone(), out_tf.clone()),
+                _ => {
+                    return Err(Error::CmsError(
+                        "Builtin CMS only converts RGB to RGB or grayscale to grayscale".into(),
+                    ));
+                }
+            };
+
+            let transforms = (0..n)
+                .map(|_| {
+                    Box::new(BuiltinTransformer {
+                        input_tf: input_tf.clone(),
+                        output_tf: output_tf.clone(),
+                        matrix,
+                        intensity_target,
+                    }) as Box<dyn JxlCmsTransformer + Send>
+                })
+                .collect();
+
+            Ok((channels, transforms))
+        }
+    }
+
+    /// Builds the linear RGB → RGB matrix, adapting white points with Bradford.
+    pub(super) fn conversion_matrix(
+        in_primaries: [(f32, f32); 3],
+        in_white: (f32, f32),
+        out_primaries: [(f32, f32); 3],
+        out_white: (f32, f32),
+    ) -> Option<[[f32; 3]; 3]> {
+        let to_xyz = rgb_to_xyz_matrix(in_primaries, in_white)?;
+        let from_xyz = invert_3x3(&rgb_to_xyz_matrix(out_primaries, out_white)?)?;
+        let adapt = bradford_adaptation_matrix(in_white.0, in_white.1, out_white.0, out_white.1)?;
+        Some(mul_3x3(&from_xyz, &mul_3x3(&adapt, &to_xyz)))
+    }
+
+    struct BuiltinTransformer {
+        input_tf: JxlTransferFunction,
+        output_tf: JxlTransferFunction,
+        /// Linear RGB conversion matrix, or `None` for grayscale.
+        matrix: Option<[[f32; 3]; 3]>,
+        intensity_target: f32,
+    }
+
+    impl JxlCmsTransformer for BuiltinTransformer {
+        fn do_transform(&mut self, input: &[f32], output: &mut [f32]) -> Result<()> {
+            if output.len() < input.len() {
+                return Err(Error::CmsError(format!(
+                    "Output buffer too small: expected {}, got {}",
+                    input.len(),
+                    output.len()
+                )));
+            }
+            output[..input.len()].copy_from_slice(input);
+            self.do_transform_inplace(&mut output[..input.len()])
+        }
+
+        fn do_transform_inplace(&mut self, inout: &mut [f32]) -> Result<()> {
+            transfer_to_linear(&self.input_tf, self.intensity_target, inout);
+            if let Some(matrix) = &self.matrix {
+                for px in inout.chunks_exact_mut(3) {
+                    px.copy_from_slice(&mul_3x3_vec(matrix, [px[0], px[1], px[2]]));
+                }
+            }
+            linear_to_transfer(&self.output_tf, self.intensity_target, inout);
+            Ok(())
+        }
+    }
+}
+
+pub(crate) use builtin_cms::BuiltinCms;
+
+/// Soft-proofing settings for `Lcms2Cms`: output shows how the image would
+/// look on the device described by `icc`, such as a printer.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "cms-lcms2"), allow(dead_code))]
+pub(crate) struct SoftProof {
+    /// ICC profile of the emulated device.
+    pub icc: Vec<u8>,
+    /// Output color (0.0-1.0 per channel) for pixels outside the device's
+    /// gamut, or `None` to leave them unmarked. Grayscale output uses the
+    /// first component.
+    pub gamut_warning: Option<[f32; 3]>,
+}
+
+// ---------------------------------------------------------------------------
+// Gamut compression
+// ---------------------------------------------------------------------------
+
+/// Distance from neutral, relative to the gamut boundary, below which colors
+/// are left unchanged by `compress_gamut`.
+const GAMUT_COMPRESSION_THRESHOLD: f32 = 0.8;
+
+/// Smoothly compresses out-of-gamut RGB colors into [0, 1].
+///
+/// Works like the ACES reference gamut compression: each channel's distance
+/// from the achromatic axis (the largest channel) is left unchanged up to
+/// `GAMUT_COMPRESSION_THRESHOLD` and eased towards the boundary beyond it, so
+/// arbitrarily negative channels approach 0 without the hue shifts of
+/// clipping. Only the most saturated in-gamut colors move, slightly, to make
+/// room. Colors brighter than 1 are then scaled down as a whole.
+/// `samples` holds interleaved RGB pixels; a trailing partial pixel is left
+/// unchanged.
+#[cfg_attr(not(feature = "cms-lcms2"), allow(dead_code))]
+pub(crate) fn compress_gamut(samples: &mut [f32]) {
+    const T: f32 = GAMUT_COMPRESSION_THRESHOLD;
+    for px in samples.chunks_exact_mut(3) {
+        let achromatic = px[0].max(px[1]).max(px[2]);
+        if achromatic <= 0.0 {
+            px.fill(0.0);
+            continue;
+        }
+        for c in px.iter_mut() {
+            let distance = (achromatic - *c) / achromatic;
+            if distance > T {
+                let x = (distance - T) / (1.0 - T);
+                let compressed = T + (1.0 - T) * x / (1.0 + x);
+                *c = achromatic - compressed * achromatic;
+            }
+        }
+        if achromatic > 1.0 {
+            for c in px.iter_mut() {
+                *c /= achromatic;
+            }
+        }
+    }
+}
+
 #[cfg(feature = "cms-lcms2")]
 mod lcms2_cms {
+    use super::{SoftProof, compress_gamut};
     use jxl::api::{JxlCms, JxlCmsTransformer, JxlColorEncoding, JxlColorProfile};
     use jxl::error::{Error, Result};
     use jxl::headers::color_encoding::RenderingIntent;
     use lcms2::{
-        AllowCache, ColorSpaceSignatureExt, Intent, PixelFormat, Profile, ThreadContext, Transform,
+        AllowCache, ColorSpaceSignatureExt, Flags, Intent, PixelFormat, Profile, ThreadContext,
+        Transform,
     };
 
     /// CMS implementation using Little CMS (lcms2).
-    pub struct Lcms2Cms;
+    #[derive(Default)]
+    pub struct Lcms2Cms {
+        /// Builds soft-proofing transforms through this device, if set.
+        pub soft_proof: Option<SoftProof>,
+        /// Rendering intent to use in place of the input profile's, if set.
+        pub intent: Option<Intent>,
+        /// Whether RGB output is passed through `compress_gamut`.
+        pub gamut_compression: bool,
+    }
+
+    /// A single lcms2 transform between two ICC profiles, reusable across
+    /// buffers independently of a decoder.
+    pub(crate) struct CmsTransform {
+        transformer: Box<dyn JxlCmsTransformer + Send>,
+        channels: usize,
+    }
+
+    impl CmsTransform {
+        /// Builds an in-place transform, which needs both profiles to have
+        /// the same channel count.
+        pub fn new(
+            input_icc: &[u8],
+            output_icc: &[u8],
+            intent: RenderingIntent,
+        ) -> std::result::Result<Self, String> {
+            let input = JxlColorProfile::Icc(input_icc.to_vec());
+            let output = JxlColorProfile::Icc(output_icc.to_vec());
+            let channels = input.channels();
+            if channels != output.channels() {
+                return Err(format!(
+                    "Input and output profiles have different channel counts ({} and {})",
+                    channels,
+                    output.channels()
+                ));
+            }
+
+            let cms = Lcms2Cms {
+                soft_proof: None,
+                intent: Some(lcms2_intent(intent)),
+                gamut_compression: false,
+            };
+            let (_, mut transformers) = cms
+                .initialize_transforms(1, 0, input, output, 255.0)
+                .map_err(|e| format!("{}", e))?;
+            let transformer = transformers.pop().ok_or("lcms2 returned no transform")?;
+            Ok(Self {
+                transformer,
+                channels,
+            })
+        }
+
+        /// Channel count of both profiles.
+        pub fn channels(&self) -> usize {
+            self.channels
+        }
+
+        /// Transforms interleaved samples in place. `samples` must hold
+        /// whole pixels.
+        pub fn apply(&mut self, samples: &mut [f32]) -> std::result::Result<(), String> {
+            self.transformer
+                .do_transform_inplace(samples)
+                .map_err(|e| format!("{}", e))
+        }
+    }
 
     impl JxlCms for Lcms2Cms {
         fn initialize_transforms(
@@ -45,14 +666,19 @@ mod lcms2_cms {
 
             let input_format = channels_to_pixel_format(input_channels)?;
             let output_format = channels_to_pixel_format(output_channels)?;
-            let intent = rendering_intent_from_profile(&input);
+            let intent = self
+                .intent
+                .unwrap_or_else(|| rendering_intent_from_profile(&input));
 
             // Create transforms using ThreadContext for thread safety (implements Send).
             // Use u8 pixel type with PixelFormat describing the actual f32 data layout.
             let mut transforms: Vec<Box<dyn JxlCmsTransformer + Send>> = Vec::with_capacity(n);
 
             for _ in 0..n {
-                let context = ThreadContext::new();
+                let mut context = ThreadContext::new();
+                if let Some(color) = self.soft_proof.as_ref().and_then(|p| p.gamut_warning) {
+                    context.set_alarm_codes(alarm_codes(color, output_channels));
+                }
 
                 let input_profile = Profile::new_icc_context(&context, input_icc.as_slice())
                     .map_err(|e| {
@@ -64,22 +690,48 @@ mod lcms2_cms {
                     })?;
 
                 let transform: Transform<u8, u8, ThreadContext, AllowCache> =
-                    Transform::new_context(
-                        context,
-                        &input_profile,
-                        input_format,
-                        &output_profile,
-                        output_format,
-                        intent,
-                    )
+                    match &self.soft_proof {
+                        None => Transform::new_context(
+                            &context,
+                            &input_profile,
+                            input_format,
+                            &output_profile,
+                            output_format,
+                            intent,
+                        ),
+                        Some(proof) => {
+                            let proof_profile = Profile::new_icc_context(&context, &proof.icc)
+                                .map_err(|e| {
+                                    Error::CmsError(format!("lcms2 failed to parse proof ICC: {e}"))
+                                })?;
+                            let flags = if proof.gamut_warning.is_some() {
+                                Flags::SOFT_PROOFING | Flags::GAMUT_CHECK
+                            } else {
+                                Flags::SOFT_PROOFING
+                            };
+                            Transform::new_proofing_context(
+                                &context,
+                                &input_profile,
+                                input_format,
+                                &output_profile,
+                                output_format,
+                                &proof_profile,
+                                intent,
+                                Intent::RelativeColorimetric,
+                                flags,
+                            )
+                        }
+                    }
                     .map_err(|e| {
                         Error::CmsError(format!("lcms2 failed to create transform: {e}"))
                     })?;
 
                 transforms.push(Box::new(Lcms2Transformer {
                     transform,
+                    _context: context,
                     input_channels,
                     output_channels,
+                    compress_gamut: self.gamut_compression && output_channels == 3,
                 }));
             }
 
@@ -87,6 +739,17 @@ mod lcms2_cms {
         }
     }
 
+    /// Encodes a gamut-warning color as lcms2 alarm codes (one 16-bit value
+    /// per channel, up to lcms2's limit of 16) for output with `channels`
+    /// channels.
+    fn alarm_codes(color: [f32; 3], channels: usize) -> [u16; 16] {
+        let mut codes = [0; 16];
+        for (code, value) in codes.iter_mut().zip(color).take(channels) {
+            *code = (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+        }
+        codes
+    }
+
     /// Maps channel count to lcms2 PixelFormat for f32 data.
     fn channels_to_pixel_format(channels: usize) -> Result<PixelFormat> {
         match channels {
@@ -115,12 +778,7 @@ mod lcms2_cms {
                         rendering_intent, ..
                     } => rendering_intent,
                 };
-                match ri {
-                    RenderingIntent::Perceptual => Intent::Perceptual,
-                    RenderingIntent::Relative => Intent::RelativeColorimetric,
-                    RenderingIntent::Saturation => Intent::Saturation,
-                    RenderingIntent::Absolute => Intent::AbsoluteColorimetric,
-                }
+                lcms2_intent(*ri)
             }
             JxlColorProfile::Icc(icc) if icc.len() >= 68 => {
                 // ICC header bytes 64-67 contain the rendering intent (big-endian u32)
@@ -136,11 +794,26 @@ mod lcms2_cms {
         }
     }
 
+    fn lcms2_intent(intent: RenderingIntent) -> Intent {
+        match intent {
+            RenderingIntent::Perceptual => Intent::Perceptual,
+            RenderingIntent::Relative => Intent::RelativeColorimetric,
+            RenderingIntent::Saturation => Intent::Saturation,
+            RenderingIntent::Absolute => Intent::AbsoluteColorimetric,
+        }
+    }
+
     /// Transformer implementation using lcms2 with ThreadContext for thread safety.
     struct Lcms2Transformer {
         transform: Transform<u8, u8, ThreadContext, AllowCache>,
+        /// Context of `transform`, which lcms2 reads settings such as alarm
+        /// codes from while transforming. Declared after it so it is
+        /// dropped last.
+        _context: ThreadContext,
         input_channels: usize,
         output_channels: usize,
+        /// Whether output is passed through `compress_gamut`.
+        compress_gamut: bool,
     }
 
     impl JxlCmsTransformer for Lcms2Transformer {
@@ -166,6 +839,9 @@ mod lcms2_cms {
             let output_bytes: &mut [u8] = bytemuck::cast_slice_mut(output);
 
             self.transform.transform_pixels(input_bytes, output_bytes);
+            if self.compress_gamut {
+                compress_gamut(&mut output[..expected_output_len]);
+            }
 
             Ok(())
         }
@@ -180,6 +856,9 @@ mod lcms2_cms {
             let inout_bytes: &mut [u8] = bytemuck::cast_slice_mut(inout);
 
             self.transform.transform_in_place(inout_bytes);
+            if self.compress_gamut {
+                compress_gamut(inout);
+            }
 
             Ok(())
         }
@@ -187,7 +866,7 @@ mod lcms2_cms {
 }
 
 #[cfg(feature = "cms-lcms2")]
-pub(crate) use lcms2_cms::Lcms2Cms;
+pub(crate) use lcms2_cms::{CmsTransform, Lcms2Cms};
 
 // ---------------------------------------------------------------------------
 // Tone-mapping CMS: applies tone mapping then delegates to lcms2
@@ -196,15 +875,19 @@ pub(crate) use lcms2_cms::Lcms2Cms;
 #[cfg(feature = "tone-mapping")]
 mod tone_mapping_cms {
     use super::lcms2_cms::Lcms2Cms;
-    use crate::tone_mapping::{
-        Bt2446aParams, DEFAULT_SDR_INTENSITY_TARGET, Rec2408Params, ToneMapMethod,
-        tone_map_bt2446a, tone_map_bt2446a_linear, tone_map_bt2446a_perceptual, tone_map_rec2408,
-    };
+    use super::{ToneMapClipStats, invert_3x3, mul_3x3_vec};
+    use crate::sanitize::sanitize_f32_slice;
+    use crate::tone_mapping::{DEFAULT_SDR_INTENSITY_TARGET, ToneMapMethod, ToneMapper};
     use jxl::api::{
         JxlCms, JxlCmsTransformer, JxlColorEncoding, JxlColorProfile, JxlPrimaries,
         JxlTransferFunction, JxlWhitePoint,
     };
     use jxl::error::Result;
+    use std::sync::Arc;
+
+    /// Output luminance (relative to the target peak) at or above which a
+    /// tone-mapped pixel counts as clipped.
+    const CLIP_THRESHOLD: f32 = 1.0 - 1e-4;
 
     /// CMS that applies tone mapping before delegating to lcms2 for color
     /// space conversion.  Supports all [`ToneMapMethod`] variants.
@@ -213,6 +896,18 @@ mod tone_mapping_cms {
         pub desired_intensity_target: f32,
         /// Tone mapping algorithm.
         pub method: ToneMapMethod,
+        /// Source peak luminance in nits. When > 0, used instead of the
+        /// image's `intensity_target`. Defaults to 0 (use the image value).
+        pub source_intensity_target_override: f32,
+        /// RGB luminance coefficients to use instead of deriving them from
+        /// the input profile. Needed for ICC inputs, which otherwise assume
+        /// BT.2020 primaries.
+        pub luminances_override: Option<[f32; 3]>,
+        /// If set, transformers count pixels clipped at the target peak.
+        pub clip_stats: Option<Arc<ToneMapClipStats>>,
+        /// Whether to replace non-finite tone-mapped values before the
+        /// conversion to the output profile.
+        pub sanitize_output: bool,
     }
 
     impl Default for ToneMappingLcms2Cms {
@@ -220,29 +915,33 @@ mod tone_mapping_cms {
             Self {
                 desired_intensity_target: DEFAULT_SDR_INTENSITY_TARGET,
                 method: ToneMapMethod::default(),
+                source_intensity_target_override: 0.0,
+                luminances_override: None,
+                clip_stats: None,
+                sanitize_output: false,
             }
         }
     }
 
-    /// Per-method precomputed config stored in each transformer.
-    #[derive(Clone, Copy)]
-    enum ToneMapConfig {
-        Bt2446a {
-            params: Bt2446aParams,
-            luminances: [f32; 3],
-        },
-        Bt2446aLinear {
-            params: Bt2446aParams,
-            luminances: [f32; 3],
-        },
-        Bt2446aPerceptual {
-            params: Bt2446aParams,
-            source_intensity_target: f32,
-        },
-        Rec2408 {
-            params: Rec2408Params,
-            luminances: [f32; 3],
-        },
+    impl ToneMappingLcms2Cms {
+        /// The method to use for `input`.
+        ///
+        /// `Bt2446aPerceptual` hardcodes BT.2020→LMS matrices, so inputs with
+        /// other (or unknown, e.g. ICC) primaries fall back to `Bt2446aLinear`
+        /// rather than producing shifted colors.
+        fn method_for(&self, input: &JxlColorProfile) -> ToneMapMethod {
+            if self.method == ToneMapMethod::Bt2446aPerceptual && !has_bt2020_primaries(input) {
+                ToneMapMethod::Bt2446aLinear
+            } else {
+                self.method
+            }
+        }
+
+        /// Luminance coefficients for `input`, honoring `luminances_override`.
+        fn luminances_for(&self, input: &JxlColorProfile) -> [f32; 3] {
+            self.luminances_override
+                .unwrap_or_else(|| luminances_from_profile(input))
+        }
     }
 
     impl JxlCms for ToneMappingLcms2Cms {
@@ -254,36 +953,24 @@ mod tone_mapping_cms {
             output: JxlColorProfile,
             intensity_target: f32,
         ) -> Result<(usize, Vec<Box<dyn JxlCmsTransformer + Send>>)> {
-            let luminances = luminances_from_profile(&input);
+            let luminances = self.luminances_for(&input);
+            // Grayscale images reach the CMS as a single interleaved channel.
+            let grayscale = input.channels() == 1;
+            let intensity_target = if self.source_intensity_target_override > 0.0 {
+                self.source_intensity_target_override
+            } else {
+                intensity_target
+            };
 
             let config = if intensity_target > self.desired_intensity_target
                 && self.desired_intensity_target > 0.0
             {
-                let bt2446a =
-                    || Bt2446aParams::new(intensity_target, self.desired_intensity_target);
-
-                Some(match self.method {
-                    ToneMapMethod::Bt2446a => ToneMapConfig::Bt2446a {
-                        params: bt2446a(),
-                        luminances,
-                    },
-                    ToneMapMethod::Bt2446aLinear => ToneMapConfig::Bt2446aLinear {
-                        params: bt2446a(),
-                        luminances,
-                    },
-                    ToneMapMethod::Bt2446aPerceptual => ToneMapConfig::Bt2446aPerceptual {
-                        params: bt2446a(),
-                        source_intensity_target: intensity_target,
-                    },
-                    ToneMapMethod::Rec2408 => ToneMapConfig::Rec2408 {
-                        params: Rec2408Params::new(
-                            [0.0, intensity_target],
-                            [0.0, self.desired_intensity_target],
-                        ),
-                        luminances,
-                    },
-                    ToneMapMethod::CmsOnly => unreachable!("CmsOnly uses plain Lcms2Cms"),
-                })
+                Some(ToneMapper::new(
+                    self.method_for(&input),
+                    luminances,
+                    intensity_target,
+                    self.desired_intensity_target,
+                ))
             } else {
                 None
             };
@@ -308,7 +995,7 @@ mod tone_mapping_cms {
             };
 
             // Delegate to lcms2 for color space conversion.
-            let (output_channels, lcms2_transforms) = Lcms2Cms.initialize_transforms(
+            let (output_channels, lcms2_transforms) = Lcms2Cms::default().initialize_transforms(
                 n,
                 max_pixels_per_transform,
                 cms_input,
@@ -323,6 +1010,10 @@ mod tone_mapping_cms {
                         inner,
                         config,
                         pq_intensity_target,
+                        luminances,
+                        grayscale,
+                        clip_stats: self.clip_stats.clone(),
+                        sanitize_output: self.sanitize_output,
                     })
                 })
                 .collect();
@@ -334,9 +1025,38 @@ mod tone_mapping_cms {
     /// Transformer that applies tone mapping then delegates to lcms2.
     struct ToneMappingLcms2Transformer {
         inner: Box<dyn JxlCmsTransformer + Send>,
-        config: Option<ToneMapConfig>,
+        config: Option<ToneMapper>,
         /// If set, input data is PQ-encoded and needs decoding to linear first.
         pq_intensity_target: Option<f32>,
+        /// Luminance coefficients of the input primaries, used for clip detection.
+        luminances: [f32; 3],
+        /// Whether data is single-channel luminance rather than interleaved RGB.
+        grayscale: bool,
+        /// If set, clipped pixels are counted after tone mapping.
+        clip_stats: Option<Arc<ToneMapClipStats>>,
+        /// Whether non-finite values are replaced after tone mapping.
+        sanitize_output: bool,
+    }
+
+    impl ToneMappingLcms2Transformer {
+        fn tone_map(&self, config: ToneMapper, data: &mut [f32]) {
+            if self.grayscale {
+                config.tone_map_gray(data);
+            } else {
+                config.tone_map_rgb(data);
+            }
+            if self.sanitize_output {
+                sanitize_f32_slice(data);
+            }
+            if let Some(stats) = &self.clip_stats {
+                let (clipped, total) = if self.grayscale {
+                    count_clipped_gray(data)
+                } else {
+                    count_clipped(self.luminances, data)
+                };
+                stats.record(clipped, total);
+            }
+        }
     }
 
     impl JxlCmsTransformer for ToneMappingLcms2Transformer {
@@ -347,7 +1067,7 @@ mod tone_mapping_cms {
                     jxl::color::tf::pq_to_linear_precise(it, &mut output[..input.len()]);
                 }
                 if let Some(config) = self.config {
-                    tone_map_interleaved(config, &mut output[..input.len()]);
+                    self.tone_map(config, &mut output[..input.len()]);
                 }
                 self.inner.do_transform_inplace(output)
             } else {
@@ -360,30 +1080,32 @@ mod tone_mapping_cms {
                 jxl::color::tf::pq_to_linear_precise(it, inout);
             }
             if let Some(config) = self.config {
-                tone_map_interleaved(config, inout);
+                self.tone_map(config, inout);
             }
             self.inner.do_transform_inplace(inout)
         }
     }
 
-    fn tone_map_interleaved(config: ToneMapConfig, data: &mut [f32]) {
-        match config {
-            ToneMapConfig::Bt2446a { params, luminances } => {
-                tone_map_bt2446a(&params, luminances, data);
-            }
-            ToneMapConfig::Bt2446aLinear { params, luminances } => {
-                tone_map_bt2446a_linear(&params, luminances, data);
-            }
-            ToneMapConfig::Bt2446aPerceptual {
-                params,
-                source_intensity_target,
-            } => {
-                tone_map_bt2446a_perceptual(&params, source_intensity_target, data);
-            }
-            ToneMapConfig::Rec2408 { params, luminances } => {
-                tone_map_rec2408(&params, luminances, data);
+    /// Counts pixels in single-channel `data` that reached the target peak.
+    /// Returns `(clipped, total)`.
+    fn count_clipped_gray(data: &[f32]) -> (u64, u64) {
+        let clipped = data.iter().filter(|&&y| y >= CLIP_THRESHOLD).count();
+        (clipped as u64, data.len() as u64)
+    }
+
+    /// Counts pixels in interleaved RGB `data` whose luminance reached the
+    /// target peak. Returns `(clipped, total)`.
+    fn count_clipped(luminances: [f32; 3], data: &[f32]) -> (u64, u64) {
+        let [lr, lg, lb] = luminances;
+        let mut clipped = 0u64;
+        let mut total = 0u64;
+        for px in data.chunks_exact(3) {
+            if lr * px[0] + lg * px[1] + lb * px[2] >= CLIP_THRESHOLD {
+                clipped += 1;
             }
+            total += 1;
         }
+        (clipped, total)
     }
 
     // -----------------------------------------------------------------------
@@ -439,6 +1161,33 @@ mod tone_mapping_cms {
         }
     }
 
+    /// Whether `profile` is known to use BT.2020 primaries, as required by the
+    /// IPTPQc4 matrices. Grayscale is achromatic, so primaries don't matter.
+    fn has_bt2020_primaries(profile: &JxlColorProfile) -> bool {
+        const BT2020: [f32; 6] = [0.708, 0.292, 0.170, 0.797, 0.131, 0.046];
+        match profile {
+            JxlColorProfile::Simple(JxlColorEncoding::RgbColorSpace { primaries, .. }) => {
+                match primaries {
+                    JxlPrimaries::BT2100 => true,
+                    JxlPrimaries::Chromaticities {
+                        rx,
+                        ry,
+                        gx,
+                        gy,
+                        bx,
+                        by,
+                    } => [*rx, *ry, *gx, *gy, *bx, *by]
+                        .iter()
+                        .zip(BT2020)
+                        .all(|(a, b)| (a - b).abs() < 1e-3),
+                    _ => false,
+                }
+            }
+            JxlColorProfile::Simple(JxlColorEncoding::GrayscaleColorSpace { .. }) => true,
+            _ => false,
+        }
+    }
+
     fn white_point_chromaticity(wp: &JxlWhitePoint) -> (f32, f32) {
         match wp {
             JxlWhitePoint::D65 => (0.3127, 0.3290),
@@ -460,46 +1209,25 @@ mod tone_mapping_cms {
         wx: f32,
         wy: f32,
     ) -> [f32; 3] {
-        let rz = 1.0 - rx - ry;
-        let gz = 1.0 - gx - gy;
-        let bz = 1.0 - bx - by;
-
-        let w_x = wx / wy;
-        let w_y = 1.0;
-        let w_z = (1.0 - wx - wy) / wy;
-
-        let m00 = rx / ry;
-        let m01 = gx / gy;
-        let m02 = bx / by;
-        let m10 = 1.0f32;
-        let m11 = 1.0f32;
-        let m12 = 1.0f32;
-        let m20 = rz / ry;
-        let m21 = gz / gy;
-        let m22 = bz / by;
-
-        let det = m00 * (m11 * m22 - m12 * m21) - m01 * (m10 * m22 - m12 * m20)
-            + m02 * (m10 * m21 - m11 * m20);
-
-        if det.abs() < 1e-10 {
-            return [0.2627, 0.6780, 0.0593];
-        }
-
-        let inv_det = 1.0 / det;
-
-        let sr = (w_x * (m11 * m22 - m12 * m21) - m01 * (w_y * m22 - m12 * w_z)
-            + m02 * (w_y * m21 - m11 * w_z))
-            * inv_det;
-        let sg = (m00 * (w_y * m22 - m12 * w_z) - w_x * (m10 * m22 - m12 * m20)
-            + m02 * (m10 * w_z - w_y * m20))
-            * inv_det;
-        let sb = (m00 * (m11 * w_z - w_y * m21) - m01 * (m10 * w_z - w_y * m20)
-            + w_x * (m10 * m21 - m11 * m20))
-            * inv_det;
+        const BT2020: [f32; 3] = [0.2627, 0.6780, 0.0593];
+
+        let m = [
+            [rx / ry, gx / gy, bx / by],
+            [1.0, 1.0, 1.0],
+            [
+                (1.0 - rx - ry) / ry,
+                (1.0 - gx - gy) / gy,
+                (1.0 - bx - by) / by,
+            ],
+        ];
+        let Some(inv) = invert_3x3(&m) else {
+            return BT2020;
+        };
+        let [sr, sg, sb] = mul_3x3_vec(&inv, [wx / wy, 1.0, (1.0 - wx - wy) / wy]);
 
         let sum = sr + sg + sb;
         if sum.abs() < 1e-10 {
-            return [0.2627, 0.6780, 0.0593];
+            return BT2020;
         }
 
         [sr / sum, sg / sum, sb / sum]
@@ -508,6 +1236,10 @@ mod tone_mapping_cms {
     #[cfg(test)]
     mod tests {
         use super::*;
+        use crate::tone_mapping::{
+            Bt2446aParams, Rec2408Params, tone_map_bt2446a, tone_map_bt2446a_linear,
+            tone_map_bt2446a_perceptual, tone_map_rec2408,
+        };
 
         #[test]
         fn test_bt2020_luminances() {
@@ -526,6 +1258,72 @@ mod tone_mapping_cms {
             assert!((lum[2] - 0.0722).abs() < 0.002, "B: {}", lum[2]);
         }
 
+        #[test]
+        fn test_singular_primaries_fall_back_to_bt2020() {
+            // All three primaries at the same point: the matrix has no inverse.
+            let lum = luminances_from_chromaticities(0.3, 0.6, 0.3, 0.6, 0.3, 0.6, 0.3127, 0.3290);
+            assert_eq!(lum, [0.2627, 0.6780, 0.0593]);
+        }
+
+        #[test]
+        fn test_p3_luminances_match_chromaticities() {
+            let lum = luminances_from_primaries(&JxlPrimaries::P3);
+            let expected = luminances_from_chromaticities(
+                0.680, 0.320, 0.265, 0.690, 0.150, 0.060, 0.3127, 0.3290,
+            );
+            for (actual, expected) in lum.iter().zip(expected) {
+                assert!((actual - expected).abs() < 0.002, "{actual} vs {expected}");
+            }
+        }
+
+        #[test]
+        fn test_luminances_override_applies_to_icc() {
+            let icc = JxlColorProfile::Icc(Vec::new());
+            let p3 = luminances_from_primaries(&JxlPrimaries::P3);
+
+            let cms = ToneMappingLcms2Cms::default();
+            assert_eq!(cms.luminances_for(&icc), [0.2627, 0.6780, 0.0593]);
+
+            let cms = ToneMappingLcms2Cms {
+                luminances_override: Some(p3),
+                ..Default::default()
+            };
+            assert_eq!(cms.luminances_for(&icc), p3);
+        }
+
+        #[test]
+        fn test_perceptual_falls_back_for_non_bt2020_primaries() {
+            let cms = ToneMappingLcms2Cms {
+                method: ToneMapMethod::Bt2446aPerceptual,
+                ..Default::default()
+            };
+            let rgb = |primaries| {
+                JxlColorProfile::Simple(JxlColorEncoding::RgbColorSpace {
+                    white_point: JxlWhitePoint::D65,
+                    primaries,
+                    transfer_function: JxlTransferFunction::PQ,
+                    rendering_intent: jxl::headers::color_encoding::RenderingIntent::Relative,
+                })
+            };
+
+            assert_eq!(
+                cms.method_for(&rgb(JxlPrimaries::BT2100)),
+                ToneMapMethod::Bt2446aPerceptual
+            );
+            assert_eq!(
+                cms.method_for(&rgb(JxlPrimaries::P3)),
+                ToneMapMethod::Bt2446aLinear
+            );
+            assert_eq!(
+                cms.method_for(&rgb(JxlPrimaries::SRGB)),
+                ToneMapMethod::Bt2446aLinear
+            );
+            assert_eq!(
+                cms.method_for(&JxlColorProfile::Icc(Vec::new())),
+                ToneMapMethod::Bt2446aLinear
+            );
+        }
+
         #[test]
         fn test_bt2446a_black_unchanged() {
             let params = Bt2446aParams::new(10000.0, 203.0);
@@ -580,6 +1378,77 @@ mod tone_mapping_cms {
             assert!(data[0].is_finite() && data[0] >= 0.0, "R: {}", data[0]);
         }
 
+        #[test]
+        fn test_count_clipped() {
+            let lum = [0.2627, 0.6780, 0.0593];
+            let data = [
+                0.0f32, 0.0, 0.0, 1.0, 1.0, 1.0, 0.5, 0.5, 0.5, 1.2, 1.0, 0.9,
+            ];
+            assert_eq!(count_clipped(lum, &data), (2, 4));
+        }
+
+        #[test]
+        fn test_count_clipped_gray() {
+            assert_eq!(count_clipped_gray(&[0.0, 1.0, 0.5, 1.2]), (2, 4));
+        }
+
+        #[test]
+        fn test_gray_config_maps_every_sample() {
+            let lum = [0.2627, 0.6780, 0.0593];
+            let config = ToneMapper::new(ToneMapMethod::Bt2446a, lum, 10000.0, 203.0);
+            let mut data = [0.5f32; 4];
+            config.tone_map_gray(&mut data);
+            assert!(data.iter().all(|&v| v == data[0] && v > 0.5), "{data:?}");
+        }
+
+        #[test]
+        fn test_sanitize_output_after_tone_mapping() {
+            let lum = [0.2627, 0.6780, 0.0593];
+            let pathological = [
+                f32::NAN,
+                0.5,
+                0.5,
+                f32::INFINITY,
+                f32::INFINITY,
+                f32::INFINITY,
+                f32::NEG_INFINITY,
+                0.0,
+                1.0,
+                -1.0e30,
+                1.0e30,
+                f32::MIN_POSITIVE,
+            ];
+            let tone_mapped = |sanitize_output: bool| {
+                let mut transformer = ToneMappingLcms2Transformer {
+                    inner: Box::new(crate::cms::PassthroughTransformer),
+                    config: Some(ToneMapper::new(ToneMapMethod::Rec2408, lum, 10000.0, 203.0)),
+                    pq_intensity_target: None,
+                    luminances: lum,
+                    grayscale: false,
+                    clip_stats: None,
+                    sanitize_output,
+                };
+                let mut data = pathological;
+                transformer.do_transform_inplace(&mut data).unwrap();
+                data
+            };
+
+            let data = tone_mapped(true);
+            assert!(data.iter().all(|v| v.is_finite()), "{data:?}");
+            let data = tone_mapped(false);
+            assert!(data.iter().any(|v| !v.is_finite()), "{data:?}");
+        }
+
+        #[test]
+        fn test_clip_stats_accumulate_and_reset() {
+            let stats = ToneMapClipStats::default();
+            stats.record(2, 10);
+            stats.record(1, 5);
+            assert_eq!(stats.get(), (3, 15));
+            stats.reset();
+            assert_eq!(stats.get(), (0, 0));
+        }
+
         #[test]
         fn test_perceptual_black_unchanged() {
             let params = Bt2446aParams::new(10000.0, 203.0);
@@ -594,3 +1463,171 @@ mod tone_mapping_cms {
 
 #[cfg(feature = "tone-mapping")]
 pub(crate) use tone_mapping_cms::ToneMappingLcms2Cms;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bradford_same_white_point_is_identity() {
+        let m = bradford_adaptation_matrix(0.3127, 0.3290, 0.3127, 0.3290).unwrap();
+        for (i, row) in m.iter().enumerate() {
+            for (j, &v) in row.iter().enumerate() {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((v - expected).abs() < 1e-5, "m[{i}][{j}] = {v}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_bradford_d65_to_d50() {
+        // Reference values from Lindbloom's Bradford D65 → D50 matrix.
+        let expected = [
+            [1.0478112, 0.0228866, -0.0501270],
+            [0.0295424, 0.9904844, -0.0170491],
+            [-0.0092345, 0.0150436, 0.7521316],
+        ];
+        let m = bradford_adaptation_matrix(0.3127, 0.3290, 0.3457, 0.3585).unwrap();
+        for (actual, expected) in m.iter().flatten().zip(expected.iter().flatten()) {
+            assert!((actual - expected).abs() < 2e-3, "{actual} vs {expected}");
+        }
+    }
+
+    #[test]
+    fn test_bradford_rejects_zero_y() {
+        assert!(bradford_adaptation_matrix(0.3127, 0.0, 0.3457, 0.3585).is_none());
+    }
+
+    #[test]
+    fn test_transfer_function_round_trip() {
+        let tfs = [
+            JxlTransferFunction::SRGB,
+            JxlTransferFunction::BT709,
+            JxlTransferFunction::PQ,
+            JxlTransferFunction::HLG,
+            JxlTransferFunction::DCI,
+            JxlTransferFunction::Gamma(1.0 / 2.2),
+        ];
+        for tf in &tfs {
+            let original = [0.0f32, 0.01, 0.18, 0.5, 1.0];
+            let mut samples = original;
+            linear_to_transfer(tf, 1000.0, &mut samples);
+            transfer_to_linear(tf, 1000.0, &mut samples);
+            for (a, b) in samples.iter().zip(original) {
+                assert!((a - b).abs() < 1e-3, "{tf:?}: {a} vs {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_gamut() {
+        // In-gamut colors away from the boundary are unchanged
+        let original = [0.5f32, 0.4, 0.3, 0.2, 0.2, 0.2, 0.2, 0.9, 0.5];
+        let mut samples = original;
+        compress_gamut(&mut samples);
+        assert_eq!(samples, original);
+
+        // Out-of-gamut colors end up inside, keeping the dominant channel
+        let mut samples = [1.0f32, -0.3, -0.05, 0.8, 1.4, -2.0, 0.0, -0.1, 0.6];
+        compress_gamut(&mut samples);
+        assert!(
+            samples.iter().all(|v| (0.0..=1.0).contains(v)),
+            "{samples:?}"
+        );
+        assert_eq!(samples[0], 1.0);
+        assert!(samples[1] < samples[2], "{samples:?}");
+        assert_eq!(samples[4], 1.0);
+        assert!(samples[3] > samples[5], "{samples:?}");
+        assert_eq!(samples[8], 0.6);
+
+        // Compression is continuous at the threshold
+        let mut below = [1.0f32, 0.2001, 0.2001];
+        let mut above = [1.0f32, 0.1999, 0.1999];
+        compress_gamut(&mut below);
+        compress_gamut(&mut above);
+        assert!((below[1] - above[1]).abs() < 1e-3, "{below:?} vs {above:?}");
+    }
+
+    #[test]
+    fn test_builtin_cms_srgb_to_linear() {
+        use jxl::api::{JxlCms, JxlColorEncoding, JxlColorProfile};
+        let (channels, mut transforms) = BuiltinCms
+            .initialize_transforms(
+                1,
+                0,
+                JxlColorProfile::Simple(JxlColorEncoding::srgb(false)),
+                JxlColorProfile::Simple(JxlColorEncoding::linear_srgb(false)),
+                255.0,
+            )
+            .unwrap();
+        assert_eq!(channels, 3);
+
+        let mut pixel = [0.5f32, 1.0, 0.0];
+        transforms[0].do_transform_inplace(&mut pixel).unwrap();
+        assert!((pixel[0] - 0.2140).abs() < 1e-3, "{pixel:?}");
+        assert!((pixel[1] - 1.0).abs() < 1e-3, "{pixel:?}");
+        assert!(pixel[2].abs() < 1e-3, "{pixel:?}");
+    }
+
+    #[test]
+    fn test_bypass_cms_skips_same_encoding() {
+        use jxl::api::{JxlCms, JxlColorEncoding, JxlColorProfile};
+        let setups = Arc::new(AtomicU64::new(0));
+        let cms = BypassCms {
+            inner: Box::new(BuiltinCms),
+            tone_mapping: false,
+            setups: setups.clone(),
+        };
+        let srgb = || JxlColorProfile::Simple(JxlColorEncoding::srgb(false));
+
+        let (channels, mut transforms) = cms
+            .initialize_transforms(2, 0, srgb(), srgb(), 255.0)
+            .unwrap();
+        assert_eq!(channels, 3);
+        assert_eq!(transforms.len(), 2);
+        let mut pixel = [0.5f32, 1.0, 0.0];
+        transforms[0].do_transform_inplace(&mut pixel).unwrap();
+        assert_eq!(pixel, [0.5, 1.0, 0.0]);
+        assert_eq!(setups.load(Ordering::Relaxed), 0);
+
+        let linear = JxlColorProfile::Simple(JxlColorEncoding::linear_srgb(false));
+        cms.initialize_transforms(1, 0, srgb(), linear, 255.0)
+            .unwrap();
+        assert_eq!(setups.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_builtin_cms_rejects_icc() {
+        use jxl::api::{JxlCms, JxlColorEncoding, JxlColorProfile};
+        let result = BuiltinCms.initialize_transforms(
+            1,
+            0,
+            JxlColorProfile::Icc(vec![0; 132]),
+            JxlColorProfile::Simple(JxlColorEncoding::srgb(false)),
+            255.0,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_builtin_srgb_to_p3_matrix() {
+        use jxl::api::{JxlPrimaries, JxlWhitePoint};
+        // Reference linear sRGB → Display P3 matrix.
+        let expected = [
+            [0.8225, 0.1774, 0.0000],
+            [0.0332, 0.9669, 0.0000],
+            [0.0171, 0.0724, 0.9108],
+        ];
+        let d65 = JxlWhitePoint::D65.to_xy_coords();
+        let m = builtin_cms::conversion_matrix(
+            JxlPrimaries::SRGB.to_xy_coords(),
+            d65,
+            JxlPrimaries::P3.to_xy_coords(),
+            d65,
+        )
+        .unwrap();
+        for (actual, expected) in m.iter().flatten().zip(expected.iter().flatten()) {
+            assert!((actual - expected).abs() < 1e-3, "{actual} vs {expected}");
+        }
+    }
+}
@@ -0,0 +1,796 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! Metadata box scanning for container files, without decoding the codestream,
+//! and parsing of the JUMBF superboxes they carry.
+
+use crate::checksum::CONTAINER_SIGNATURE;
+use crate::error::log;
+use crate::types::JxlLogLevel;
+use std::borrow::Cow;
+
+/// Kind of a metadata box, after unwrapping `brob` compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MetadataKind {
+    Exif,
+    Xml,
+    Jumbf,
+}
+
+impl MetadataKind {
+    fn from_box_type(box_type: &[u8]) -> Option<Self> {
+        match box_type {
+            b"Exif" => Some(Self::Exif),
+            b"xml " => Some(Self::Xml),
+            b"jumb" => Some(Self::Jumbf),
+            _ => None,
+        }
+    }
+}
+
+/// A metadata box found by `scan_metadata_boxes`.
+pub(crate) struct MetadataBox {
+    pub(crate) kind: MetadataKind,
+    /// Box payload; still brotli-compressed for `brob` boxes.
+    pub(crate) data: Vec<u8>,
+    pub(crate) is_brotli_compressed: bool,
+}
+
+/// Result of scanning the boxes before the codestream.
+pub(crate) enum MetadataScan {
+    /// All boxes before the codestream, in file order.
+    Complete(Vec<MetadataBox>),
+    /// The input ends before the first codestream box.
+    NeedMoreInput,
+}
+
+/// Collects the metadata boxes that precede the first `jxlc` or `jxlp` box.
+///
+/// Only box headers are parsed, so this is cheap even for large files. Bare
+/// codestreams have no boxes. Boxes after the codestream are not reported.
+pub(crate) fn scan_metadata_boxes(data: &[u8]) -> Result<MetadataScan, &'static str> {
+    if data.starts_with(&[0xFF, 0x0A]) {
+        return Ok(MetadataScan::Complete(Vec::new()));
+    }
+    if data.len() < CONTAINER_SIGNATURE.len() {
+        return if CONTAINER_SIGNATURE.starts_with(data) || [0xFF].starts_with(data) {
+            Ok(MetadataScan::NeedMoreInput)
+        } else {
+            Err("Not a JPEG XL file")
+        };
+    }
+    if !data.starts_with(&CONTAINER_SIGNATURE) {
+        return Err("Not a JPEG XL file");
+    }
+
+    let mut boxes = Vec::new();
+    let mut offset = CONTAINER_SIGNATURE.len();
+    loop {
+        // The codestream box ends the scan whatever its size
+        let header = read_box_header(data, offset);
+        let box_type = match &header {
+            Ok(header) => Some(header.box_type),
+            Err(e) => e.box_type(),
+        };
+        if box_type.is_some_and(|t| &t == b"jxlc" || &t == b"jxlp") {
+            return Ok(MetadataScan::Complete(boxes));
+        }
+        let header = match header {
+            Ok(header) => header,
+            Err(BoxHeaderError::Truncated(_)) => return Ok(MetadataScan::NeedMoreInput),
+            Err(BoxHeaderError::InvalidSize(..)) => return Err("Invalid container box size"),
+        };
+        let box_type = &header.box_type[..];
+        if header.extends_to_end {
+            return Err("Container has no codestream box");
+        }
+
+        let Some(end) = header.end().filter(|&end| end <= data.len()) else {
+            return Ok(MetadataScan::NeedMoreInput);
+        };
+        let payload = &data[header.payload_start()..end];
+        if box_type == b"brob" {
+            // Compressed boxes start with the type of the box they wrap
+            let Some((inner_type, compressed)) = payload.split_first_chunk::<4>() else {
+                return Err("Invalid brob box");
+            };
+            if let Some(kind) = MetadataKind::from_box_type(inner_type) {
+                boxes.push(MetadataBox {
+                    kind,
+                    data: compressed.to_vec(),
+                    is_brotli_compressed: true,
+                });
+            }
+        } else if let Some(kind) = MetadataKind::from_box_type(box_type) {
+            boxes.push(MetadataBox {
+                kind,
+                data: payload.to_vec(),
+                is_brotli_compressed: false,
+            });
+        } else if !STRUCTURAL_BOX_TYPES.contains(&box_type) {
+            log(JxlLogLevel::Warning, || {
+                format!(
+                    "Skipped unknown box '{}' at offset {offset}",
+                    box_name(box_type)
+                )
+            });
+        }
+        offset = end;
+    }
+}
+
+/// Returns the codestream of a bare codestream or container: the `jxlc`
+/// payload, or the `jxlp` payloads concatenated up to the final one.
+///
+/// Truncated boxes contribute the bytes available, so this also works on
+/// partial input. `jxlp` payloads are copied only until at least `limit`
+/// bytes are gathered, for callers that need just the start of the
+/// codestream. Returns `None` if `data` is not a JPEG XL file.
+pub(crate) fn codestream_bytes(data: &[u8], limit: usize) -> Option<Cow<'_, [u8]>> {
+    if data.starts_with(&[0xFF, 0x0A]) {
+        return Some(Cow::Borrowed(data));
+    }
+    if !data.starts_with(&CONTAINER_SIGNATURE) {
+        return None;
+    }
+
+    let mut codestream = Vec::new();
+    let mut offset = CONTAINER_SIGNATURE.len();
+    while let Ok(header) = read_box_header(data, offset) {
+        let payload = &data[header.available_payload(data.len())];
+        match &header.box_type {
+            b"jxlc" => return Some(Cow::Borrowed(payload)),
+            b"jxlp" => {
+                // Partial codestream boxes start with a 4-byte sequence
+                // index, whose high bit marks the last one
+                let Some((index, part)) = payload.split_first_chunk::<4>() else {
+                    break;
+                };
+                codestream.extend_from_slice(part);
+                if index[0] & 0x80 != 0 || codestream.len() >= limit {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        match header.end() {
+            Some(end) if end < data.len() => offset = end,
+            _ => break,
+        }
+    }
+    Some(Cow::Owned(codestream))
+}
+
+/// Header of a box in the container box format.
+pub(crate) struct BoxHeader {
+    pub(crate) box_type: [u8; 4],
+    /// Offset of the box in the data.
+    pub(crate) offset: usize,
+    /// 8 bytes, or 16 with a 64-bit size.
+    pub(crate) header_size: usize,
+    /// Box size including the header. A declared size of 0 extends the box
+    /// to the end of the data.
+    pub(crate) box_size: u64,
+    /// Whether the declared size was 0.
+    pub(crate) extends_to_end: bool,
+}
+
+impl BoxHeader {
+    pub(crate) fn payload_start(&self) -> usize {
+        // The header was read from the data, so this can't overflow
+        self.offset + self.header_size
+    }
+
+    /// Offset just past the box, or `None` if that doesn't fit in `usize`.
+    pub(crate) fn end(&self) -> Option<usize> {
+        usize::try_from(self.box_size)
+            .ok()
+            .and_then(|size| self.offset.checked_add(size))
+    }
+
+    /// Range of the payload within the first `len` bytes of the data, for
+    /// walkers that accept truncated boxes.
+    pub(crate) fn available_payload(&self, len: usize) -> std::ops::Range<usize> {
+        let end = self.end().map_or(len, |end| end.min(len));
+        self.payload_start()..end
+    }
+}
+
+/// Why `read_box_header` failed, with the box type when it was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BoxHeaderError {
+    /// The data ends inside the header.
+    Truncated(Option<[u8; 4]>),
+    /// The declared size is smaller than the header.
+    InvalidSize([u8; 4], u64),
+}
+
+impl BoxHeaderError {
+    pub(crate) fn box_type(&self) -> Option<[u8; 4]> {
+        match *self {
+            Self::Truncated(box_type) => box_type,
+            Self::InvalidSize(box_type, _) => Some(box_type),
+        }
+    }
+}
+
+/// Reads the header of the box at `offset`, resolving 64-bit and
+/// to-the-end sizes. The box itself may extend past `data`; all offset
+/// arithmetic is checked, so untrusted sizes can't overflow.
+pub(crate) fn read_box_header(data: &[u8], offset: usize) -> Result<BoxHeader, BoxHeaderError> {
+    let header = offset
+        .checked_add(8)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(BoxHeaderError::Truncated(None))?;
+    let box_type: [u8; 4] = header[4..8].try_into().unwrap();
+    let size = u32::from_be_bytes(header[..4].try_into().unwrap());
+    let (header_size, box_size) = match size {
+        0 => (8, (data.len() - offset) as u64),
+        1 => {
+            let large = data
+                .get(offset + 8..offset + 16)
+                .ok_or(BoxHeaderError::Truncated(Some(box_type)))?;
+            (16, u64::from_be_bytes(large.try_into().unwrap()))
+        }
+        size => (8, size as u64),
+    };
+    if box_size < header_size as u64 {
+        return Err(BoxHeaderError::InvalidSize(box_type, box_size));
+    }
+    Ok(BoxHeader {
+        box_type,
+        offset,
+        header_size,
+        box_size,
+        extends_to_end: size == 0,
+    })
+}
+
+/// Reads the header of a box that must lie entirely within `data`, with
+/// errors naming the box and its offset.
+fn read_complete_box(data: &[u8], offset: usize) -> Result<BoxHeader, String> {
+    let header = read_box_header(data, offset).map_err(|e| match e {
+        BoxHeaderError::Truncated(None) => format!("Truncated box header at offset {}", offset),
+        BoxHeaderError::Truncated(Some(box_type)) => format!(
+            "Truncated '{}' box header at offset {}",
+            box_name(&box_type),
+            offset
+        ),
+        BoxHeaderError::InvalidSize(box_type, size) => format!(
+            "Box '{}' at offset {} has invalid size {}",
+            box_name(&box_type),
+            offset,
+            size
+        ),
+    })?;
+    let available = (data.len() - offset) as u64;
+    if header.box_size > available {
+        return Err(format!(
+            "Box '{}' at offset {} is truncated: {} bytes declared, {} available",
+            box_name(&header.box_type),
+            offset,
+            header.box_size,
+            available
+        ));
+    }
+    Ok(header)
+}
+
+/// Non-metadata box types defined by the container format, besides the
+/// codestream boxes.
+const STRUCTURAL_BOX_TYPES: [&[u8]; 5] = [b"JXL ", b"ftyp", b"jxll", b"jxli", b"jbrd"];
+
+/// Box types that may not be wrapped in a `brob` box.
+const UNCOMPRESSIBLE_BOX_TYPES: [&[u8; 4]; 5] = [b"JXL ", b"ftyp", b"jxlc", b"jxlp", b"brob"];
+
+fn box_name(box_type: &[u8]) -> String {
+    String::from_utf8_lossy(box_type).into_owned()
+}
+
+/// Checks the box structure of complete JPEG XL data without decoding it.
+///
+/// A container must start with the signature and `ftyp` boxes, every box must
+/// fit in the data, and the codestream must be either one `jxlc` box or a run
+/// of `jxlp` boxes numbered from 0 with the last one marked final. Bare
+/// codestreams pass as they are. The error names the offending box and its
+/// offset.
+pub(crate) fn validate_container(data: &[u8]) -> Result<(), String> {
+    if data.starts_with(&[0xFF, 0x0A]) {
+        return Ok(());
+    }
+    if !data.starts_with(&CONTAINER_SIGNATURE) {
+        return Err("Not a JPEG XL file".into());
+    }
+
+    let mut offset = CONTAINER_SIGNATURE.len();
+    let mut has_ftyp = false;
+    let mut has_jxlc = false;
+    // Index of the next expected jxlp box, and whether the final one was seen
+    let mut next_jxlp = 0u32;
+    let mut jxlp_done = false;
+    while offset < data.len() {
+        let header = read_complete_box(data, offset)?;
+        let box_type = &header.box_type[..];
+        let name = box_name(box_type);
+        // The box fits in the data, so its end does too
+        let end = offset + header.box_size as usize;
+        let payload = &data[header.payload_start()..end];
+
+        if !has_ftyp {
+            if box_type != b"ftyp" {
+                return Err(format!(
+                    "Expected 'ftyp' box at offset {}, found '{}'",
+                    offset, name
+                ));
+            }
+            has_ftyp = true;
+        } else {
+            match box_type {
+                b"ftyp" | b"JXL " => {
+                    return Err(format!("Duplicate '{}' box at offset {}", name, offset));
+                }
+                b"jxlc" if has_jxlc || next_jxlp > 0 => {
+                    return Err(format!(
+                        "Unexpected second codestream box at offset {}",
+                        offset
+                    ));
+                }
+                b"jxlc" => has_jxlc = true,
+                b"jxlp" => {
+                    if has_jxlc || jxlp_done {
+                        return Err(format!(
+                            "Unexpected 'jxlp' box at offset {} after the codestream",
+                            offset
+                        ));
+                    }
+                    let Some(counter) = payload.first_chunk::<4>() else {
+                        return Err(format!("Box 'jxlp' at offset {} has no index", offset));
+                    };
+                    let counter = u32::from_be_bytes(*counter);
+                    let index = counter & 0x7FFF_FFFF;
+                    if index != next_jxlp {
+                        return Err(format!(
+                            "Box 'jxlp' at offset {} has index {}, expected {}",
+                            offset, index, next_jxlp
+                        ));
+                    }
+                    next_jxlp += 1;
+                    jxlp_done = counter & 0x8000_0000 != 0;
+                }
+                b"brob" => {
+                    let Some(inner_type) = payload.first_chunk::<4>() else {
+                        return Err(format!("Box 'brob' at offset {} has no inner type", offset));
+                    };
+                    if UNCOMPRESSIBLE_BOX_TYPES.contains(&inner_type) {
+                        return Err(format!(
+                            "Box 'brob' at offset {} wraps a '{}' box, which can't be compressed",
+                            offset,
+                            box_name(inner_type)
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+        offset = end;
+    }
+
+    if !has_jxlc && next_jxlp == 0 {
+        return Err("Container has no codestream box".into());
+    }
+    if next_jxlp > 0 && !jxlp_done {
+        return Err("The last 'jxlp' box is not marked as final".into());
+    }
+    Ok(())
+}
+
+/// Deepest superbox nesting accepted by `parse_jumbf`.
+const MAX_JUMBF_DEPTH: u32 = 32;
+
+/// A box found by `parse_jumbf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JumbfBox {
+    pub(crate) box_type: [u8; 4],
+    /// Superbox nesting level, 0 for boxes at the top level of the input.
+    pub(crate) depth: u32,
+    /// Range of the box payload in the input, after the box header.
+    pub(crate) payload: std::ops::Range<usize>,
+    /// Range of the label in the input, for a `jumb` superbox or its `jumd`
+    /// description box when the description has a label.
+    pub(crate) label: Option<std::ops::Range<usize>>,
+}
+
+/// Walks a JUMBF structure (ISO/IEC 19566-5), listing every box in file
+/// order with superbox contents following the superbox.
+///
+/// `data` is either a whole `jumb` superbox or its payload, as returned for
+/// JUMBF metadata boxes. Boxes use the container box format; a size of 0
+/// extends the box to the end of its enclosing superbox.
+pub(crate) fn parse_jumbf(data: &[u8]) -> Result<Vec<JumbfBox>, String> {
+    let mut boxes = Vec::new();
+    parse_jumbf_boxes(data, 0..data.len(), 0, &mut boxes)?;
+    Ok(boxes)
+}
+
+fn parse_jumbf_boxes(
+    data: &[u8],
+    range: std::ops::Range<usize>,
+    depth: u32,
+    boxes: &mut Vec<JumbfBox>,
+) -> Result<(), String> {
+    if depth > MAX_JUMBF_DEPTH {
+        return Err(format!(
+            "JUMBF superboxes are nested deeper than {} levels",
+            MAX_JUMBF_DEPTH
+        ));
+    }
+
+    let mut offset = range.start;
+    while offset < range.end {
+        // Boxes may not extend past their superbox, and a size of 0 extends
+        // a box to the end of it
+        let header = read_complete_box(&data[..range.end], offset)?;
+        let box_type = header.box_type;
+        let end = offset + header.box_size as usize;
+        let payload = header.payload_start()..end;
+
+        let label = match &box_type {
+            b"jumd" => jumbf_label(data, payload.clone(), offset)?,
+            _ => None,
+        };
+        boxes.push(JumbfBox {
+            box_type,
+            depth,
+            payload: payload.clone(),
+            label,
+        });
+        if &box_type == b"jumb" {
+            let superbox = boxes.len() - 1;
+            parse_jumbf_boxes(data, payload, depth + 1, boxes)?;
+            // A superbox is labeled by its description box, which comes first
+            boxes[superbox].label = boxes
+                .get(superbox + 1)
+                .filter(|b| &b.box_type == b"jumd")
+                .and_then(|b| b.label.clone());
+        }
+        offset = end;
+    }
+    Ok(())
+}
+
+/// Finds the label in the payload of a `jumd` box at `offset`: a 16-byte
+/// content type UUID and a toggles byte, followed by a null-terminated label
+/// when toggle bit 1 is set.
+fn jumbf_label(
+    data: &[u8],
+    payload: std::ops::Range<usize>,
+    offset: usize,
+) -> Result<Option<std::ops::Range<usize>>, String> {
+    let description = &data[payload.clone()];
+    let Some(&toggles) = description.get(16) else {
+        return Err(format!("Box 'jumd' at offset {} is too short", offset));
+    };
+    if toggles & 0x02 == 0 {
+        return Ok(None);
+    }
+    let Some(len) = description[17..].iter().position(|&b| b == 0) else {
+        return Err(format!(
+            "Box 'jumd' at offset {} has an unterminated label",
+            offset
+        ));
+    };
+    let start = payload.start + 17;
+    Ok(Some(start..start + len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(box_type);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    #[test]
+    fn test_read_box_header() {
+        let mut data = make_box(b"Exif", b"abcd");
+        data.extend([0, 0, 0, 1]);
+        data.extend(b"jxlc");
+        data.extend(20u64.to_be_bytes());
+        data.extend([0xFF, 0x0A]);
+
+        let header = read_box_header(&data, 0).unwrap();
+        assert_eq!((&header.box_type, header.header_size), (b"Exif", 8));
+        assert_eq!((header.payload_start(), header.end()), (8, Some(12)));
+
+        // A 64-bit size, for a box that extends past the data
+        let header = read_box_header(&data, 12).unwrap();
+        assert_eq!((&header.box_type, header.box_size), (b"jxlc", 20));
+        assert_eq!(header.end(), Some(32));
+        assert_eq!(header.available_payload(data.len()), 28..30);
+
+        assert_eq!(
+            read_box_header(&data[..19], 12).err(),
+            Some(BoxHeaderError::Truncated(None))
+        );
+        assert_eq!(
+            read_box_header(&data[..27], 12).err(),
+            Some(BoxHeaderError::Truncated(Some(*b"jxlc")))
+        );
+        assert_eq!(
+            read_box_header(&[0, 0, 0, 7, b'j', b'x', b'l', b'c'], 0).err(),
+            Some(BoxHeaderError::InvalidSize(*b"jxlc", 7))
+        );
+        // Offsets near the end of the address space don't overflow
+        assert!(read_box_header(&data, usize::MAX - 3).is_err());
+
+        // A size of 0 extends the box to the end of the data
+        let header = read_box_header(&[0, 0, 0, 0, b'j', b'x', b'l', b'c', 1], 0).unwrap();
+        assert!(header.extends_to_end);
+        assert_eq!(header.end(), Some(9));
+    }
+
+    #[test]
+    fn test_scan_stops_at_codestream() {
+        let mut container = CONTAINER_SIGNATURE.to_vec();
+        container.extend(make_box(b"ftyp", b"jxl \0\0\0\0jxl "));
+        container.extend(make_box(b"Exif", b"\0\0\0\0exif"));
+        container.extend(make_box(b"brob", b"xml compressed"));
+        let codestream_start = container.len();
+        container.extend(make_box(b"jxlc", &[0xFF, 0x0A]));
+        container.extend(make_box(b"jumb", b"after"));
+
+        let Ok(MetadataScan::Complete(boxes)) = scan_metadata_boxes(&container) else {
+            panic!("Scan should complete");
+        };
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(boxes[0].kind, MetadataKind::Exif);
+        assert_eq!(boxes[0].data, b"\0\0\0\0exif");
+        assert!(!boxes[0].is_brotli_compressed);
+        assert_eq!(boxes[1].kind, MetadataKind::Xml);
+        assert_eq!(boxes[1].data, b"compressed");
+        assert!(boxes[1].is_brotli_compressed);
+
+        // Everything up to the codestream box header is needed
+        for len in [0, 5, 20, codestream_start + 7] {
+            assert!(matches!(
+                scan_metadata_boxes(&container[..len]),
+                Ok(MetadataScan::NeedMoreInput)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_scan_bare_codestream_and_invalid_input() {
+        assert!(matches!(
+            scan_metadata_boxes(&[0xFF, 0x0A, 0x00]),
+            Ok(MetadataScan::Complete(boxes)) if boxes.is_empty()
+        ));
+        assert!(scan_metadata_boxes(b"\x89PNG\r\n\x1a\n").is_err());
+    }
+
+    fn make_container(boxes: &[Vec<u8>]) -> Vec<u8> {
+        let mut container = CONTAINER_SIGNATURE.to_vec();
+        container.extend(make_box(b"ftyp", b"jxl \0\0\0\0jxl "));
+        for b in boxes {
+            container.extend(b);
+        }
+        container
+    }
+
+    #[test]
+    fn test_validate_accepts_well_formed_containers() {
+        assert_eq!(validate_container(&[0xFF, 0x0A, 0x00]), Ok(()));
+
+        let jxlc = make_container(&[
+            make_box(b"Exif", b"\0\0\0\0exif"),
+            make_box(b"brob", b"xml compressed"),
+            make_box(b"jxlc", &[0xFF, 0x0A]),
+        ]);
+        assert_eq!(validate_container(&jxlc), Ok(()));
+
+        let jxlp = make_container(&[
+            make_box(b"jxlp", &[0, 0, 0, 0, 0xFF, 0x0A]),
+            make_box(b"jumb", b"between"),
+            make_box(b"jxlp", &[0x80, 0, 0, 1, 0x00]),
+        ]);
+        assert_eq!(validate_container(&jxlp), Ok(()));
+
+        // A zero size extends the last box to the end of the data
+        let mut open_ended = make_container(&[]);
+        open_ended.extend([0, 0, 0, 0]);
+        open_ended.extend(b"jxlc\xFF\x0A");
+        assert_eq!(validate_container(&open_ended), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_malformed_boxes() {
+        let codestream = make_box(b"jxlc", &[0xFF, 0x0A]);
+        let truncated = make_container(std::slice::from_ref(&codestream));
+        let truncated = &truncated[..truncated.len() - 1];
+        let cases: [(Vec<u8>, &str); 8] = [
+            (b"\x89PNG\r\n\x1a\n".to_vec(), "Not a JPEG XL file"),
+            (
+                truncated.to_vec(),
+                "Box 'jxlc' at offset 32 is truncated: 10 bytes declared, 9 available",
+            ),
+            (
+                [&CONTAINER_SIGNATURE[..], &codestream].concat(),
+                "Expected 'ftyp' box at offset 12, found 'jxlc'",
+            ),
+            (
+                make_container(&[codestream.clone(), codestream.clone()]),
+                "Unexpected second codestream box at offset 42",
+            ),
+            (
+                make_container(&[make_box(b"Exif", b"")]),
+                "Container has no codestream box",
+            ),
+            (
+                make_container(&[make_box(b"jxlp", &[0, 0, 0, 1])]),
+                "Box 'jxlp' at offset 32 has index 1, expected 0",
+            ),
+            (
+                make_container(&[make_box(b"jxlp", &[0, 0, 0, 0])]),
+                "The last 'jxlp' box is not marked as final",
+            ),
+            (
+                make_container(&[codestream.clone(), make_box(b"brob", b"jxlc")]),
+                "Box 'brob' at offset 42 wraps a 'jxlc' box, which can't be compressed",
+            ),
+        ];
+        for (data, message) in cases {
+            assert_eq!(validate_container(&data), Err(message.to_string()));
+        }
+
+        // A box size smaller than its header
+        let mut undersized = make_container(&[]);
+        undersized.extend([0, 0, 0, 4]);
+        undersized.extend(b"jxlc");
+        assert_eq!(
+            validate_container(&undersized),
+            Err("Box 'jxlc' at offset 32 has invalid size 4".to_string())
+        );
+    }
+
+    #[test]
+    fn test_codestream_bytes() {
+        let codestream = [0xFF, 0x0A, 1, 2, 3, 4, 5, 6];
+        assert_eq!(
+            codestream_bytes(&codestream, usize::MAX).as_deref(),
+            Some(&codestream[..])
+        );
+        assert_eq!(codestream_bytes(b"not jxl", usize::MAX), None);
+
+        let single = make_container(&[make_box(b"jxlc", &codestream)]);
+        assert_eq!(
+            codestream_bytes(&single, 1).as_deref(),
+            Some(&codestream[..])
+        );
+
+        // Boxes after the final partial codestream box are not included
+        let partial = make_container(&[
+            make_box(b"jxlp", &[&[0, 0, 0, 0][..], &codestream[..3]].concat()),
+            make_box(b"Exif", b"metadata"),
+            make_box(b"jxlp", &[&[0x80, 0, 0, 1][..], &codestream[3..]].concat()),
+            make_box(b"jxlp", &[0, 0, 0, 2, 7]),
+        ]);
+        assert_eq!(
+            codestream_bytes(&partial, usize::MAX).as_deref(),
+            Some(&codestream[..])
+        );
+        // Gathering stops at the box that reaches the limit
+        assert_eq!(
+            codestream_bytes(&partial, 3).as_deref(),
+            Some(&codestream[..3])
+        );
+        // A truncated box contributes what is available
+        let truncated = &partial[..partial.len() - 17];
+        assert_eq!(
+            codestream_bytes(truncated, usize::MAX).as_deref(),
+            Some(&codestream[..4])
+        );
+
+        // A 64-bit box size past the end of the address space ends the walk
+        let mut huge = make_container(&[make_box(b"jxlp", &[0, 0, 0, 0, 0xFF])]);
+        huge.extend([0, 0, 0, 1]);
+        huge.extend(b"jxlp");
+        huge.extend(u64::MAX.to_be_bytes());
+        huge.extend([0, 0, 0, 1, 0x0A]);
+        assert_eq!(
+            codestream_bytes(&huge, usize::MAX).as_deref(),
+            Some(&[0xFF, 0x0A][..])
+        );
+    }
+
+    fn make_jumd(label: Option<&str>) -> Vec<u8> {
+        let mut payload = vec![0xAB; 16];
+        match label {
+            Some(label) => {
+                payload.push(0x03);
+                payload.extend_from_slice(label.as_bytes());
+                payload.push(0);
+            }
+            None => payload.push(0x01),
+        }
+        make_box(b"jumd", &payload)
+    }
+
+    #[test]
+    fn test_parse_jumbf_walks_nested_superboxes() {
+        let inner = [make_jumd(None), make_box(b"cbor", b"\xA0")].concat();
+        let payload = [
+            make_jumd(Some("c2pa")),
+            make_box(b"json", b"{}"),
+            make_box(b"jumb", &inner),
+        ]
+        .concat();
+        let superbox = make_box(b"jumb", &payload);
+
+        let boxes = parse_jumbf(&superbox).unwrap();
+        let summary: Vec<_> = boxes
+            .iter()
+            .map(|b| (&b.box_type, b.depth, b.payload.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (b"jumb", 0, 8..90),
+                (b"jumd", 1, 16..38),
+                (b"json", 1, 46..48),
+                (b"jumb", 1, 56..90),
+                (b"jumd", 2, 64..81),
+                (b"cbor", 2, 89..90),
+            ]
+        );
+        assert_eq!(boxes[0].label, Some(33..37));
+        assert_eq!(&superbox[33..37], b"c2pa");
+        assert_eq!(boxes[1].label, boxes[0].label);
+        assert_eq!(boxes[3].label, None);
+
+        // The payload alone, as returned for JUMBF metadata boxes, parses the
+        // same boxes one level up
+        let boxes = parse_jumbf(&payload).unwrap();
+        assert_eq!(boxes.len(), 5);
+        assert_eq!((&boxes[0].box_type, boxes[0].depth), (b"jumd", 0));
+        assert_eq!(boxes[0].label, Some(25..29));
+    }
+
+    #[test]
+    fn test_parse_jumbf_rejects_malformed_boxes() {
+        let truncated = make_box(b"jumb", &make_jumd(Some("label")))[..20].to_vec();
+        assert_eq!(
+            parse_jumbf(&truncated),
+            Err("Box 'jumb' at offset 0 is truncated: 39 bytes declared, 20 available".to_string())
+        );
+        // A child box may not extend past its superbox
+        let mut overflowing = make_box(b"jumb", &make_box(b"json", b"{}"));
+        overflowing[11] += 1;
+        overflowing.push(0);
+        assert_eq!(
+            parse_jumbf(&overflowing),
+            Err("Box 'json' at offset 8 is truncated: 11 bytes declared, 10 available".to_string())
+        );
+        assert_eq!(
+            parse_jumbf(&make_box(b"jumd", &[0; 10])),
+            Err("Box 'jumd' at offset 0 is too short".to_string())
+        );
+        let mut unterminated = vec![0; 16];
+        unterminated.extend(b"\x02label");
+        assert_eq!(
+            parse_jumbf(&make_box(b"jumd", &unterminated)),
+            Err("Box 'jumd' at offset 0 has an unterminated label".to_string())
+        );
+
+        let mut nested = make_jumd(None);
+        for _ in 0..=MAX_JUMBF_DEPTH + 1 {
+            nested = make_box(b"jumb", &nested);
+        }
+        assert!(parse_jumbf(&nested).unwrap_err().contains("nested deeper"));
+    }
+}
@@ -33,10 +33,12 @@ pub(crate) type UpstreamDataFormat = jxl::api::JxlDataFormat;
 /// Converts C-compatible options to upstream decoder options.
 pub(crate) fn convert_options_to_upstream(c_options: &JxlDecodeOptions) -> JxlDecoderOptions {
     let mut options = JxlDecoderOptions::default();
-    options.adjust_orientation = c_options.AdjustOrientation;
+    // Upstream always outputs upright pixels; without AdjustOrientation the
+    // orientation is undone afterwards, see `orientation.rs`
+    options.adjust_orientation = true;
     options.render_spot_colors = c_options.RenderSpotColors;
     options.coalescing = c_options.Coalescing;
-    options.skip_preview = c_options.SkipPreview;
+    options.skip_preview = c_options.SkipPreview && !c_options.PreferPreview;
     options.progressive_mode = match c_options.ProgressiveMode {
         JxlProgressiveMode::Eager => UpstreamProgressiveMode::Eager,
         JxlProgressiveMode::Pass => UpstreamProgressiveMode::Pass,
@@ -90,6 +92,35 @@ pub(crate) fn bytes_per_sample(data_format: JxlDataFormat) -> usize {
     }
 }
 
+/// Returns the effective bit depth of decoded output in `data_format`.
+///
+/// Integer outputs carry at most the source's precision, so a 10-bit source
+/// decoded to `Uint16` has 10 significant bits. Float outputs report their own
+/// format. A float source's precision is its significand width.
+pub(crate) fn output_bit_depth(source: &JxlBitDepth, data_format: JxlDataFormat) -> JxlBitDepth {
+    let source_precision = match source.Type {
+        JxlBitDepthType::Int => source.BitsPerSample,
+        JxlBitDepthType::Float => source.BitsPerSample - source.ExponentBitsPerSample,
+    };
+    let int = |bits: u32| JxlBitDepth {
+        Type: JxlBitDepthType::Int,
+        BitsPerSample: bits.min(source_precision),
+        ExponentBitsPerSample: 0,
+    };
+    let float = |bits: u32, exponent_bits: u32| JxlBitDepth {
+        Type: JxlBitDepthType::Float,
+        BitsPerSample: bits,
+        ExponentBitsPerSample: exponent_bits,
+    };
+
+    match data_format {
+        JxlDataFormat::Uint8 => int(8),
+        JxlDataFormat::Uint16 => int(16),
+        JxlDataFormat::Float16 => float(16, 5),
+        JxlDataFormat::Float32 => float(32, 8),
+    }
+}
+
 /// Calculates samples per pixel based on color type.
 fn samples_per_pixel(color_type: JxlColorType) -> usize {
     match color_type {
@@ -100,18 +131,75 @@ fn samples_per_pixel(color_type: JxlColorType) -> usize {
     }
 }
 
-/// Calculates the bytes per row for the given image info and pixel format.
-pub(crate) fn calculate_bytes_per_row(info: &JxlBasicInfoRaw, pixel_format: &JxlPixelFormat) -> usize {
+/// Calculates the bytes per row for the given image info and pixel format,
+/// or `None` if it overflows `usize`.
+pub(crate) fn calculate_bytes_per_row(
+    info: &JxlBasicInfoRaw,
+    pixel_format: &JxlPixelFormat,
+) -> Option<usize> {
     let width = info.Width as usize;
     let bps = bytes_per_sample(pixel_format.DataFormat);
     let spp = samples_per_pixel(pixel_format.ColorType);
-    width * spp * bps
+    width.checked_mul(spp)?.checked_mul(bps)
+}
+
+/// Rounds a row length up to a multiple of `alignment` bytes (0 or 1 =
+/// packed), or `None` if that overflows `usize`.
+pub(crate) fn align_row(bytes_per_row: usize, alignment: u32) -> Option<usize> {
+    if alignment <= 1 {
+        Some(bytes_per_row)
+    } else {
+        bytes_per_row.checked_next_multiple_of(alignment as usize)
+    }
 }
 
-/// Calculates the required buffer size for the given image info and pixel format.
-pub(crate) fn calculate_buffer_size(info: &JxlBasicInfoRaw, pixel_format: &JxlPixelFormat) -> usize {
-    let height = info.Height as usize;
-    calculate_bytes_per_row(info, pixel_format) * height
+/// Calculates the color buffer size for the given image info and pixel
+/// format with rows padded to `alignment` bytes.
+///
+/// Returns `None` if the size overflows `usize`, which the dimensions of a
+/// malicious header can on 32-bit targets; wrapping would under-allocate.
+pub(crate) fn calculate_buffer_size(
+    info: &JxlBasicInfoRaw,
+    pixel_format: &JxlPixelFormat,
+    alignment: u32,
+) -> Option<usize> {
+    let stride = align_row(calculate_bytes_per_row(info, pixel_format)?, alignment)?;
+    stride.checked_mul(info.Height as usize)
+}
+
+/// Calculates the size of a single-channel buffer for the given image info,
+/// or `None` if it overflows `usize`.
+pub(crate) fn calculate_plane_size(
+    info: &JxlBasicInfoRaw,
+    bytes_per_sample: usize,
+) -> Option<usize> {
+    (info.Width as usize)
+        .checked_mul(info.Height as usize)?
+        .checked_mul(bytes_per_sample)
+}
+
+/// Estimates the bytes allocated to decode one frame.
+///
+/// Counts the output buffer, extra channel buffers when they are decoded,
+/// and one `f32` working plane per color and extra channel inside the decoder.
+pub(crate) fn estimate_decode_memory(
+    info: &JxlBasicInfoRaw,
+    pixel_format: &JxlPixelFormat,
+    decode_extra_channels: bool,
+) -> u64 {
+    let pixels = info.Width as u64 * info.Height as u64;
+    let sample_size = bytes_per_sample(pixel_format.DataFormat) as u64;
+    let color_samples = samples_per_pixel(pixel_format.ColorType) as u64;
+    let output = pixels.saturating_mul(color_samples * sample_size);
+    let extra_outputs = if decode_extra_channels {
+        pixels.saturating_mul(sample_size * info.NumExtraChannels as u64)
+    } else {
+        0
+    };
+    let working_planes = pixels.saturating_mul(4 * (3 + info.NumExtraChannels as u64));
+    output
+        .saturating_add(extra_outputs)
+        .saturating_add(working_planes)
 }
 
 // ============================================================================
@@ -191,14 +279,50 @@ fn convert_orientation(orientation: Orientation) -> JxlOrientation {
 
 pub(crate) fn convert_frame_header(header: &jxl::api::JxlFrameHeader) -> JxlFrameHeader {
     JxlFrameHeader {
-        DurationMs: header.duration.unwrap_or(0.0) as f32,
+        DurationMs: frame_duration_ms(header.duration),
         FrameWidth: header.size.0 as u32,
         FrameHeight: header.size.1 as u32,
         NameLength: header.name.len() as u32,
+        // The render pipeline extends frames to the full image size, so the
+        // output buffer is always positioned at the canvas origin.
+        FrameX0: 0,
+        FrameY0: 0,
+        IsPreview: false,
+        IsOpaque: false,
+    }
+}
+
+/// Converts an upstream frame duration to `JxlFrameHeader.DurationMs`.
+///
+/// Upstream divides by the animation's `tps_numerator`. The header coding
+/// keeps both tick rate terms at 1 or more, but a zero from a malformed
+/// header would make the duration infinite or NaN, so anything that isn't a
+/// finite, non-negative duration is reported as 0.
+pub(crate) fn frame_duration_ms(duration: Option<f64>) -> f32 {
+    match duration {
+        Some(ms) if ms.is_finite() && ms >= 0.0 => ms as f32,
+        _ => 0.0,
+    }
+}
+
+/// Recovers the raw tick count from a frame duration in milliseconds.
+///
+/// Upstream only reports `ticks * 1000 * tps_denominator / tps_numerator`;
+/// the tick count is an integer, so rounding the inverse is exact. Returns 0
+/// for non-animated images, a zero tick rate term or a non-finite duration.
+pub(crate) fn duration_ms_to_ticks(duration_ms: f64, animation: &JxlAnimation) -> u32 {
+    if animation.TpsNumerator == 0 || animation.TpsDenominator == 0 || !duration_ms.is_finite() {
+        return 0;
     }
+    let ticks =
+        duration_ms * animation.TpsNumerator as f64 / (1000.0 * animation.TpsDenominator as f64);
+    ticks.round() as u32
 }
 
-pub(crate) fn convert_extra_channel_info(channel: &jxl::api::JxlExtraChannel) -> JxlExtraChannelInfo {
+pub(crate) fn convert_extra_channel_info(
+    channel: &jxl::api::JxlExtraChannel,
+    dim_shift: u32,
+) -> JxlExtraChannelInfo {
     let channel_type = match channel.ec_type {
         ExtraChannel::Alpha => JxlExtraChannelType::Alpha,
         ExtraChannel::Depth => JxlExtraChannelType::Depth,
@@ -213,6 +337,28 @@ pub(crate) fn convert_extra_channel_info(channel: &jxl::api::JxlExtraChannel) ->
     JxlExtraChannelInfo {
         ChannelType: channel_type,
         AlphaAssociated: channel.alpha_associated,
+        DimShift: dim_shift,
+    }
+}
+
+/// Converts an FFI data format to upstream, with full-width integer samples.
+pub(crate) fn convert_data_format(
+    data_format: JxlDataFormat,
+    endianness: JxlEndianness,
+) -> UpstreamDataFormat {
+    let endianness = match endianness {
+        JxlEndianness::Native => Endianness::native(),
+        JxlEndianness::LittleEndian => Endianness::LittleEndian,
+        JxlEndianness::BigEndian => Endianness::BigEndian,
+    };
+    match data_format {
+        JxlDataFormat::Uint8 => UpstreamDataFormat::U8 { bit_depth: 8 },
+        JxlDataFormat::Uint16 => UpstreamDataFormat::U16 {
+            endianness,
+            bit_depth: 16,
+        },
+        JxlDataFormat::Float16 => UpstreamDataFormat::F16 { endianness },
+        JxlDataFormat::Float32 => UpstreamDataFormat::F32 { endianness },
     }
 }
 
@@ -230,21 +376,7 @@ pub(crate) fn convert_to_jxl_pixel_format(
         JxlColorType::Bgra => UpstreamColorType::Bgra,
     };
 
-    let endianness = match format.Endianness {
-        JxlEndianness::Native => Endianness::native(),
-        JxlEndianness::LittleEndian => Endianness::LittleEndian,
-        JxlEndianness::BigEndian => Endianness::BigEndian,
-    };
-
-    let data_format = match format.DataFormat {
-        JxlDataFormat::Uint8 => Some(UpstreamDataFormat::U8 { bit_depth: 8 }),
-        JxlDataFormat::Uint16 => Some(UpstreamDataFormat::U16 {
-            endianness,
-            bit_depth: 16,
-        }),
-        JxlDataFormat::Float16 => Some(UpstreamDataFormat::F16 { endianness }),
-        JxlDataFormat::Float32 => Some(UpstreamDataFormat::F32 { endianness }),
-    };
+    let data_format = Some(convert_data_format(format.DataFormat, format.Endianness));
 
     // Determine if the color type already includes alpha
     let color_includes_alpha = matches!(
@@ -256,15 +388,7 @@ pub(crate) fn convert_to_jxl_pixel_format(
     let extra_channel_format = if skip_extra_channels {
         vec![None; extra_channels.len()]
     } else {
-        let extra_format = match format.DataFormat {
-            JxlDataFormat::Uint8 => Some(UpstreamDataFormat::U8 { bit_depth: 8 }),
-            JxlDataFormat::Uint16 => Some(UpstreamDataFormat::U16 {
-                endianness,
-                bit_depth: 16,
-            }),
-            JxlDataFormat::Float16 => Some(UpstreamDataFormat::F16 { endianness }),
-            JxlDataFormat::Float32 => Some(UpstreamDataFormat::F32 { endianness }),
-        };
+        let extra_format = data_format;
 
         // Track whether we've skipped the first alpha channel (when color includes alpha)
         let mut first_alpha_skipped = false;
@@ -539,3 +663,19 @@ pub(crate) fn convert_color_profile_to_upstream(raw: &JxlColorProfileRaw, icc_da
         }
     }
 }
+
+// ============================================================================
+// Tone Mapping Conversions
+// ============================================================================
+
+/// Converts a C tone mapping method to the tone mapping module's method.
+#[cfg(feature = "tone-mapping")]
+pub(crate) fn convert_tone_map_method(method: JxlToneMapMethod) -> crate::tone_mapping::ToneMapMethod {
+    use crate::tone_mapping::ToneMapMethod;
+    match method {
+        JxlToneMapMethod::Bt2446a => ToneMapMethod::Bt2446a,
+        JxlToneMapMethod::Bt2446aLinear => ToneMapMethod::Bt2446aLinear,
+        JxlToneMapMethod::Bt2446aPerceptual => ToneMapMethod::Bt2446aPerceptual,
+        JxlToneMapMethod::Rec2408 => ToneMapMethod::Rec2408,
+    }
+}
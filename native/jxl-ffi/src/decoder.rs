@@ -5,19 +5,40 @@
 
 //! Decoder implementation for the C API.
 
+use crate::alpha;
+use crate::checksum;
 use crate::conversions::{
-    bytes_per_sample, calculate_buffer_size, calculate_bytes_per_row, convert_basic_info,
-    convert_color_encoding, convert_color_encoding_to_upstream, convert_color_profile,
+    UpstreamPixelFormat, align_row, bytes_per_sample, calculate_buffer_size,
+    calculate_bytes_per_row, calculate_plane_size, convert_basic_info, convert_color_encoding,
+    convert_color_encoding_to_upstream, convert_color_profile, convert_data_format,
     convert_extra_channel_info, convert_frame_header, convert_options_to_upstream,
-    convert_to_jxl_pixel_format, convert_transfer_function,
+    convert_to_jxl_pixel_format, convert_transfer_function, convert_transfer_function_to_upstream,
+    duration_ms_to_ticks, estimate_decode_memory, output_bit_depth,
 };
-use crate::error::{clear_last_error, set_last_error};
+use crate::cms::{CmsFallback, SoftProof, ToneMapClipStats};
+use crate::container::{
+    MetadataKind, MetadataScan, codestream_bytes, parse_jumbf, scan_metadata_boxes,
+    validate_container,
+};
+use crate::error::{
+    clear_last_error, last_error, last_error_status, log, set_last_error,
+    set_last_error_with_status, write_error_message,
+};
+use crate::icc;
+use crate::orientation::{UprightPlane, is_transposing, undo_orientation, upright_size};
+use crate::sanitize;
+use crate::stats::{LuminanceStats, MAX_HISTOGRAM_BINS, has_transparency, opaque_bounds};
 use crate::types::*;
 use jxl::api::{JxlColorProfile, ProcessingResult};
 use jxl::image::JxlOutputBuffer;
-use std::ffi::CStr;
+use std::borrow::Cow;
+use std::ffi::{CStr, c_void};
+use std::fmt::Write;
+use std::io::Read;
 use std::os::raw::c_char;
 use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
 
 // Type alias for upstream decoder
 type UpstreamDecoder<S> = jxl::api::JxlDecoder<S>;
@@ -62,6 +83,59 @@ macro_rules! get_decoder_ref_silent {
     };
 }
 
+/// Marks a decoder as in use for the duration of a decoding call.
+///
+/// Decoding calls move the state out of the decoder while they run, so a
+/// second call on the same decoder from another thread would see or clobber a
+/// half-updated decoder. Callers must not do this; the guard turns it into a
+/// `Busy` error instead.
+macro_rules! enter_decoder {
+    ($inner:expr, $error_return:expr) => {
+        match CallGuard::enter($inner) {
+            Some(guard) => guard,
+            None => {
+                set_last_error_with_status(
+                    JxlStatus::Busy,
+                    "Decoder is already in use by a concurrent call",
+                );
+                return $error_return;
+            }
+        }
+    };
+}
+
+/// Holds a decoder's busy flag until dropped, see `enter_decoder!`. The
+/// call's outcome is then also recorded on the decoder, for
+/// `jxl_decoder_get_last_error`.
+struct CallGuard {
+    busy: Arc<AtomicBool>,
+    last_error: Arc<Mutex<(JxlStatus, String)>>,
+}
+
+impl CallGuard {
+    fn enter(inner: &DecoderInner) -> Option<Self> {
+        inner
+            .busy
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        Some(Self {
+            busy: inner.busy.clone(),
+            last_error: inner.last_error.clone(),
+        })
+    }
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        *self
+            .last_error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = last_error();
+        let was_busy = self.busy.swap(false, Ordering::Release);
+        debug_assert!(was_busy, "Decoder busy flag was cleared during a call");
+    }
+}
+
 /// Internal decoder state machine.
 enum DecoderState {
     /// Initial state, ready to accept input.
@@ -80,30 +154,118 @@ struct CachedMetadataBox {
     is_brotli_compressed: bool,
 }
 
+/// Input bytes held by the decoder.
+enum InputData {
+    /// Bytes copied into a decoder-owned buffer.
+    Owned(Vec<u8>),
+    /// Caller-owned bytes, see `jxl_decoder_set_input_borrowed`.
+    Borrowed { ptr: *const u8, len: usize },
+}
+
+impl InputData {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Owned(data) => data,
+            // SAFETY: the caller of `jxl_decoder_set_input_borrowed` guarantees the
+            // buffer stays valid and unmodified while the decoder references it.
+            Self::Borrowed { ptr, len } => unsafe { slice::from_raw_parts(*ptr, *len) },
+        }
+    }
+}
+
 /// Internal decoder structure.
 struct DecoderInner {
     /// Current decoder state.
     state: DecoderState,
-    /// Raw JXL data (for one-shot decoding).
-    data: Vec<u8>,
+    /// Raw JXL data, either copied or borrowed from the caller.
+    data: InputData,
     /// Current read offset in data (tracks position between process calls).
     data_offset: usize,
+    /// Offset in data where the current image starts (non-zero for concatenated images).
+    image_start: usize,
     /// Cached basic info (needed for WithFrameInfo state which doesn't expose it).
     basic_info: Option<JxlBasicInfoRaw>,
     /// Cached extra channel info (needed for pixel format conversion).
     extra_channels: Vec<JxlExtraChannelInfo>,
+    /// Channel count of the embedded color profile: 1 for grayscale, which
+    /// gray output requires, 3 for RGB or 4 for CMYK.
+    embedded_channels: u32,
+    /// Orientation from the image header. `basic_info` reports `Identity`
+    /// when it is applied to the output.
+    orientation: JxlOrientation,
+    /// Whether the next or current frame is the preview, which is output at
+    /// the preview size.
+    preview_frame: bool,
+    /// Frames completed in the current image, including a decoded preview.
+    frames_completed: usize,
     /// Desired output pixel format.
     pixel_format: JxlPixelFormat,
+    /// Output pixel format of the preview frame, in place of `pixel_format`.
+    preview_pixel_format: Option<JxlPixelFormat>,
+    /// Extra channel selected by `jxl_decoder_read_single_channel`; later
+    /// frames output only this channel. Kept across rewinds.
+    single_channel: Option<usize>,
+    /// The single channel the current frame was set up to output, if any.
+    frame_single_channel: Option<usize>,
+    /// Data format of each extra channel set by
+    /// `jxl_decoder_set_extra_channel_format`, `None` for the pixel format's.
+    /// Kept across rewinds.
+    extra_channel_formats: Vec<Option<JxlDataFormat>>,
     /// Decoder options (stored for reset).
     options: JxlDecodeOptions,
-    /// CMS type to use for color management.
-    cms_type: JxlCmsType,
+    /// Output color profile set by the caller, re-applied to each new image
+    /// after `jxl_decoder_reset_keep_config`.
+    output_profile: Option<JxlColorProfile>,
+    /// Output color profile applied to every image when its header is read,
+    /// see `jxl_decoder_set_desired_output_color_profile`. Survives resets.
+    desired_output_profile: Option<JxlColorProfile>,
+    /// ICC profile of the device to soft-proof through, see
+    /// `jxl_decoder_set_proof_profile`. Survives resets.
+    proof_profile: Option<Vec<u8>>,
+    /// Color marking pixels outside the proof device's gamut, see
+    /// `jxl_decoder_set_gamut_warning_color`. Survives resets.
+    gamut_warning: Option<[f32; 3]>,
+    /// Whether the current image is output in its embedded color encoding,
+    /// see `jxl_decoder_output_matches_embedded`.
+    output_matches_embedded: bool,
+    /// Clipped-pixel counters shared with the tone-mapping CMS, if enabled.
+    tone_map_clip_stats: Option<Arc<ToneMapClipStats>>,
+    /// CMS error bypassed with the `CmsErrorFallback` option, shared with the
+    /// fallback CMS.
+    cms_fallback: Arc<CmsFallback>,
+    /// Number of color conversions the CMS has set up, shared with the
+    /// bypass CMS, see `jxl_decoder_get_cms_setup_count`.
+    cms_setups: Arc<AtomicU64>,
+    /// SHA-256 of the color buffer from the last decoded frame (`sha2` feature).
+    last_frame_hash: Option<[u8; 32]>,
+    /// Luminance statistics of the last decoded frame (`LuminanceStats` option).
+    last_frame_stats: Option<LuminanceStats>,
+    /// Bounds of opaque content in the last decoded frame (`OpaqueBounds`
+    /// option).
+    last_frame_opaque_bounds: Option<JxlRect>,
+    /// Whether the last decoded frame has alpha below the maximum
+    /// (`DetectTransparency` option).
+    last_frame_transparent: Option<bool>,
+    /// Whether any frame decoded so far has alpha below the maximum
+    /// (`DetectTransparency` option).
+    image_transparent: Option<bool>,
+    /// Header of the last decoded frame, with `IsOpaque` refined by the
+    /// alpha scan when `DetectTransparency` is enabled.
+    decoded_frame_header: Option<JxlFrameHeader>,
+    /// Per-phase decode timings shared with the timed CMS (`timings` feature).
+    #[cfg(feature = "timings")]
+    timings: Arc<crate::timings::DecodeTimings>,
     /// Cached EXIF boxes (avoids re-cloning on repeated access).
     exif_boxes_cache: Option<Vec<CachedMetadataBox>>,
     /// Cached XML boxes (avoids re-cloning on repeated access).
     xml_boxes_cache: Option<Vec<CachedMetadataBox>>,
     /// Cached JUMBF boxes (avoids re-cloning on repeated access).
     jumbf_boxes_cache: Option<Vec<CachedMetadataBox>>,
+    /// Set while a decoding call runs, see `enter_decoder!`.
+    busy: Arc<AtomicBool>,
+    /// Status and message of the last decoding call, readable from any
+    /// thread unlike the thread-local last error.
+    last_error: Arc<Mutex<(JxlStatus, String)>>,
 }
 
 impl DecoderInner {
@@ -112,89 +274,820 @@ impl DecoderInner {
     }
 
     fn with_options(options: JxlDecodeOptions) -> Self {
-        let cms_type = options.CmsType;
-        let mut upstream_opts = convert_options_to_upstream(&options);
-        upstream_opts.cms = create_cms(cms_type);
-        Self {
-            state: DecoderState::Initialized(UpstreamDecoder::new(upstream_opts)),
-            data: Vec::new(),
+        let tone_map_clip_stats = options
+            .ToneMapClipStats
+            .then(|| Arc::new(ToneMapClipStats::default()));
+        let mut inner = Self {
+            state: DecoderState::Processing,
+            data: InputData::Owned(Vec::new()),
             data_offset: 0,
+            image_start: 0,
             basic_info: None,
             extra_channels: Vec::new(),
+            embedded_channels: 0,
+            orientation: JxlOrientation::Identity,
+            preview_frame: false,
+            frames_completed: 0,
             pixel_format: options.PixelFormat,
+            preview_pixel_format: None,
+            single_channel: None,
+            frame_single_channel: None,
+            extra_channel_formats: Vec::new(),
             options,
-            cms_type,
+            output_profile: None,
+            desired_output_profile: None,
+            proof_profile: None,
+            gamut_warning: None,
+            output_matches_embedded: false,
+            tone_map_clip_stats,
+            cms_fallback: Arc::default(),
+            cms_setups: Arc::default(),
+            last_frame_hash: None,
+            last_frame_stats: None,
+            last_frame_opaque_bounds: None,
+            last_frame_transparent: None,
+            image_transparent: None,
+            decoded_frame_header: None,
+            #[cfg(feature = "timings")]
+            timings: Arc::default(),
             exif_boxes_cache: None,
             xml_boxes_cache: None,
             jumbf_boxes_cache: None,
-        }
+            busy: Arc::default(),
+            last_error: Arc::new(Mutex::new((JxlStatus::Success, String::new()))),
+        };
+        inner.reset_state();
+        inner
     }
 
     fn reset(&mut self) {
+        self.reset_keep_config();
+        self.output_profile = None;
+    }
+
+    /// Resets like `reset`, but keeps the output color profile.
+    fn reset_keep_config(&mut self) {
         self.reset_state();
-        self.data.clear();
+        self.data = InputData::Owned(Vec::new());
         self.data_offset = 0;
+        self.image_start = 0;
         self.basic_info = None;
+        self.output_matches_embedded = false;
         self.extra_channels.clear();
+        self.embedded_channels = 0;
+        self.single_channel = None;
+        self.extra_channel_formats.clear();
         self.exif_boxes_cache = None;
         self.xml_boxes_cache = None;
         self.jumbf_boxes_cache = None;
+        self.last_frame_hash = None;
+        self.last_frame_stats = None;
+        self.last_frame_opaque_bounds = None;
+        self.last_frame_transparent = None;
+        self.image_transparent = None;
+        self.reset_clip_stats();
+        self.cms_fallback.reset();
+        self.cms_setups.store(0, Ordering::Relaxed);
+        #[cfg(feature = "timings")]
+        self.timings.reset();
     }
 
     /// Rewinds the decoder to the beginning of the input without clearing the data buffer.
     /// This allows re-decoding the same input without calling SetInput again.
     fn rewind(&mut self) {
         self.reset_state();
+        self.output_profile = None;
         self.data_offset = 0;
+        self.image_start = 0;
         self.basic_info = None;
+        self.output_matches_embedded = false;
         self.extra_channels.clear();
         self.exif_boxes_cache = None;
         self.xml_boxes_cache = None;
         self.jumbf_boxes_cache = None;
+        self.last_frame_hash = None;
+        self.last_frame_stats = None;
+        self.last_frame_opaque_bounds = None;
+        self.last_frame_transparent = None;
+        self.image_transparent = None;
+        self.reset_clip_stats();
+        self.cms_fallback.reset();
+        self.cms_setups.store(0, Ordering::Relaxed);
+        #[cfg(feature = "timings")]
+        self.timings.reset();
     }
 
     /// Resets only the decoder state (used for error recovery).
     fn reset_state(&mut self) {
+        self.preview_frame = false;
+        self.decoded_frame_header = None;
+        self.frames_completed = 0;
+        self.frame_single_channel = None;
         let mut opts = convert_options_to_upstream(&self.options);
-        opts.cms = create_cms(self.cms_type);
+        let soft_proof = self.proof_profile.clone().map(|icc| SoftProof {
+            icc,
+            gamut_warning: self.gamut_warning,
+        });
+        opts.cms = create_cms(&self.options, self.tone_map_clip_stats.as_ref(), soft_proof);
+        opts.cms = opts.cms.take().map(|inner| {
+            Box::new(crate::cms::BypassCms {
+                inner,
+                tone_mapping: is_tone_mapping(self.options.CmsType),
+                setups: self.cms_setups.clone(),
+            }) as Box<dyn jxl::api::JxlCms>
+        });
+        if self.options.CmsErrorFallback {
+            opts.cms = opts.cms.take().map(|inner| {
+                Box::new(crate::cms::FallbackCms {
+                    inner,
+                    fallback: self.cms_fallback.clone(),
+                }) as Box<dyn jxl::api::JxlCms>
+            });
+        }
+        #[cfg(feature = "timings")]
+        {
+            opts.cms = opts.cms.take().map(|inner| {
+                Box::new(crate::timings::TimedCms {
+                    inner,
+                    timings: self.timings.clone(),
+                }) as Box<dyn jxl::api::JxlCms>
+            });
+        }
         self.state = DecoderState::Initialized(UpstreamDecoder::new(opts));
     }
+
+    /// Handles a frame that failed to decode. With `ContinueOnFrameError` the
+    /// frame is skipped and `FrameError` returned; otherwise, or if it can't be
+    /// skipped, the decoder resets and `Error` is returned.
+    fn frame_error(&mut self, message: String) -> JxlDecoderEvent {
+        let failed_frame = self.frames_completed;
+        set_last_error(message.as_str());
+        if self.options.ContinueOnFrameError && self.skip_failed_frame(failed_frame) {
+            log(JxlLogLevel::Warning, || {
+                format!("Skipped frame {failed_frame} that failed to decode: {message}")
+            });
+            JxlDecoderEvent::FrameError
+        } else {
+            self.reset_state();
+            JxlDecoderEvent::Error
+        }
+    }
+
+    /// Re-reads the current image from its start and skips frames up to and
+    /// including `failed_frame`, leaving the decoder before the next frame.
+    /// The upstream decoder is consumed by a failed frame, so there is no way
+    /// to resume it in place.
+    fn skip_failed_frame(&mut self, failed_frame: usize) -> bool {
+        self.reset_state();
+        let DecoderState::Initialized(decoder_init) =
+            std::mem::replace(&mut self.state, DecoderState::Processing)
+        else {
+            return false;
+        };
+
+        let profile = self
+            .output_profile
+            .as_ref()
+            .or(self.desired_output_profile.as_ref())
+            .cloned();
+        let pixel_format = || self.upstream_pixel_format(self.single_channel);
+
+        let mut input_slice: &[u8] = &self.data.as_slice()[self.image_start..];
+        let len_before = input_slice.len();
+        let skip = || {
+            let ProcessingResult::Complete {
+                result: mut decoder,
+            } = decoder_init.process(&mut input_slice).ok()?
+            else {
+                return None;
+            };
+            if let Some(profile) = profile {
+                apply_output_color_profile(&self.options, &mut decoder, profile).ok()?;
+            }
+            for _ in 0..=failed_frame {
+                decoder.set_pixel_format(pixel_format());
+                let ProcessingResult::Complete { result: frame } =
+                    decoder.process(&mut input_slice).ok()?
+                else {
+                    return None;
+                };
+                let ProcessingResult::Complete { result } =
+                    frame.skip_frame(&mut input_slice).ok()?
+                else {
+                    return None;
+                };
+                decoder = result;
+            }
+            Some(decoder)
+        };
+        let result = self.time_frame_decode(skip);
+        let consumed = len_before - input_slice.len();
+
+        let Some(decoder) = result else {
+            return false;
+        };
+        self.state = DecoderState::WithImageInfo(decoder);
+        self.data_offset = self.image_start + consumed;
+        self.frames_completed = failed_frame + 1;
+        true
+    }
+
+    /// Upstream pixel format for a frame: the caller's format, or only the
+    /// extra channel `single_channel` without color.
+    fn upstream_pixel_format(&self, single_channel: Option<usize>) -> UpstreamPixelFormat {
+        let skip_extra = !self.options.DecodeExtraChannels;
+        let pixel_format = self.frame_pixel_format();
+        let mut format =
+            convert_to_jxl_pixel_format(&pixel_format, &self.extra_channels, skip_extra);
+        if let Some(channel) = single_channel {
+            let data_format = format.color_data_format.take();
+            format.extra_channel_format = (0..self.extra_channels.len())
+                .map(|i| if i == channel { data_format } else { None })
+                .collect();
+        }
+        for (i, data_format) in format.extra_channel_format.iter_mut().enumerate() {
+            if let Some(data_format) = data_format {
+                *data_format =
+                    convert_data_format(self.extra_channel_data_format(i), pixel_format.Endianness);
+            }
+        }
+        let data_formats = format
+            .color_data_format
+            .iter_mut()
+            .chain(format.extra_channel_format.iter_mut().flatten());
+        for data_format in data_formats {
+            match data_format {
+                jxl::api::JxlDataFormat::U8 { bit_depth } => {
+                    *bit_depth = self.integer_sample_bits(JxlDataFormat::Uint8) as u8;
+                }
+                jxl::api::JxlDataFormat::U16 { bit_depth, .. } => {
+                    *bit_depth = self.integer_sample_bits(JxlDataFormat::Uint16) as u8;
+                }
+                _ => {}
+            }
+        }
+        format
+    }
+
+    /// Data format of extra channel `index`: the one set with
+    /// `jxl_decoder_set_extra_channel_format`, or the pixel format's. The
+    /// alpha channel in the color output always has the pixel format's.
+    fn extra_channel_data_format(&self, index: usize) -> JxlDataFormat {
+        let color_includes_alpha = matches!(
+            self.frame_pixel_format().ColorType,
+            JxlColorType::Rgba | JxlColorType::Bgra | JxlColorType::GrayscaleAlpha
+        );
+        let first_alpha = self
+            .extra_channels
+            .iter()
+            .position(|ec| ec.ChannelType == JxlExtraChannelType::Alpha);
+        match self.extra_channel_formats.get(index) {
+            Some(&Some(format)) if !(color_includes_alpha && first_alpha == Some(index)) => format,
+            _ => self.frame_pixel_format().DataFormat,
+        }
+    }
+
+    /// Bits per sample of integer output in `data_format`: the format's
+    /// width, or the image's bit depth if smaller and `ScaleToFullRange` is
+    /// off.
+    fn integer_sample_bits(&self, data_format: JxlDataFormat) -> u32 {
+        let format_bits = 8 * bytes_per_sample(data_format) as u32;
+        match &self.basic_info {
+            Some(info)
+                if !self.options.ScaleToFullRange && info.BitDepth.Type == JxlBitDepthType::Int =>
+            {
+                info.BitDepth.BitsPerSample.clamp(1, format_bits)
+            }
+            _ => format_bits,
+        }
+    }
+
+    /// Runs `f`, timing it as header parsing when the `timings` feature is enabled.
+    #[inline]
+    fn time_header<R>(&self, f: impl FnOnce() -> R) -> R {
+        #[cfg(feature = "timings")]
+        {
+            self.timings.time_header(f)
+        }
+        #[cfg(not(feature = "timings"))]
+        {
+            f()
+        }
+    }
+
+    /// Runs `f`, timing it as frame decoding when the `timings` feature is enabled.
+    #[inline]
+    fn time_frame_decode<R>(&self, f: impl FnOnce() -> R) -> R {
+        #[cfg(feature = "timings")]
+        {
+            self.timings.time_frame_decode(f)
+        }
+        #[cfg(not(feature = "timings"))]
+        {
+            f()
+        }
+    }
+
+    /// Converts a decoded color buffer to straight alpha when the
+    /// `UnpremultiplyAlpha` option applies to the image.
+    fn unpremultiply_output(
+        &self,
+        info: &JxlBasicInfoRaw,
+        pixels: &mut [u8],
+        bytes_per_row: usize,
+        stride: usize,
+    ) {
+        if self.options.UnpremultiplyAlpha
+            && !self.options.PremultiplyAlpha
+            && info.AlphaPremultiplied
+        {
+            let format = self.frame_pixel_format();
+            let bits = self.integer_sample_bits(format.DataFormat);
+            alpha::unpremultiply(pixels, bytes_per_row, stride, &format, bits);
+        }
+    }
+
+    /// Replaces non-finite samples in a decoded color buffer when the
+    /// `SanitizeFloatOutput` option is enabled.
+    fn sanitize_output(&self, pixels: &mut [u8], bytes_per_row: usize, stride: usize) {
+        if self.options.SanitizeFloatOutput {
+            let format = self.frame_pixel_format();
+            sanitize::sanitize_float_output(pixels, bytes_per_row, stride, &format);
+        }
+    }
+
+    /// Records the SHA-256 of a decoded color buffer when the `sha2` feature is
+    /// enabled. Only the first `bytes_per_row` bytes of each `stride`-byte row
+    /// are hashed, so row padding does not affect the digest.
+    #[cfg_attr(not(feature = "sha2"), allow(unused_variables))]
+    fn record_frame_hash(&mut self, pixels: &[u8], bytes_per_row: usize, stride: usize) {
+        #[cfg(feature = "sha2")]
+        {
+            use sha2::{Digest, Sha256};
+            let mut hasher = Sha256::new();
+            for row in pixels.chunks(stride) {
+                hasher.update(&row[..bytes_per_row]);
+            }
+            self.last_frame_hash = Some(hasher.finalize().into());
+        }
+    }
+
+    /// Records luminance statistics and opaque bounds of a decoded color
+    /// buffer when the `LuminanceStats` and `OpaqueBounds` options are enabled.
+    fn record_frame_stats(&mut self, pixels: &[u8], bytes_per_row: usize, stride: usize) {
+        let format = self.frame_pixel_format();
+        let int_bits = self.integer_sample_bits(format.DataFormat);
+        if self.options.LuminanceStats {
+            let stats = LuminanceStats::compute(pixels, bytes_per_row, stride, &format, int_bits);
+            self.last_frame_stats = Some(stats);
+        }
+        if self.options.OpaqueBounds {
+            let bounds = opaque_bounds(
+                pixels,
+                bytes_per_row,
+                stride,
+                &format,
+                int_bits,
+                self.options.OpaqueAlphaThreshold,
+            );
+            self.last_frame_opaque_bounds = Some(bounds);
+        }
+        if self.options.DetectTransparency {
+            self.last_frame_transparent =
+                has_transparency(pixels, bytes_per_row, stride, &format, int_bits);
+            if let Some(transparent) = self.last_frame_transparent {
+                self.image_transparent = Some(self.image_transparent == Some(true) || transparent);
+            }
+        }
+    }
+
+    /// Builds the public header of the current frame, or `None` outside
+    /// `WithFrameInfo`. `IsOpaque` comes from the metadata only.
+    fn frame_header(&self) -> Option<JxlFrameHeader> {
+        let DecoderState::WithFrameInfo(ref decoder_with_frame) = self.state else {
+            return None;
+        };
+        let mut header = convert_frame_header(&decoder_with_frame.frame_header());
+        // Upstream reports the upright image size for every frame, which
+        // differs from the output size for the preview and when the
+        // orientation is undone
+        if let Some(info) = self.output_info() {
+            header.FrameWidth = info.Width;
+            header.FrameHeight = info.Height;
+            header.IsPreview = self.preview_frame;
+        }
+        // The preview is not part of the animation, but upstream reports the
+        // duration of the first animation frame for it
+        if self.preview_frame {
+            header.DurationMs = 0.0;
+        }
+        header.IsOpaque = !self
+            .extra_channels
+            .iter()
+            .any(|ec| ec.ChannelType == JxlExtraChannelType::Alpha);
+        Some(header)
+    }
+
+    /// Remembers the header of a frame whose pixels were just read, marking
+    /// it opaque if the alpha scan found no transparency.
+    fn record_decoded_header(&mut self, header: Option<JxlFrameHeader>) {
+        let scanned_opaque = self.last_frame_transparent == Some(false);
+        self.decoded_frame_header = header.map(|mut header| {
+            header.IsOpaque |= scanned_opaque;
+            header
+        });
+    }
+
+    /// Formats a human-readable snapshot of the decoder for bug reports.
+    fn debug_state(&self) -> String {
+        let state = match &self.state {
+            DecoderState::Initialized(_) => "Initialized",
+            DecoderState::WithImageInfo(d) if !d.has_more_frames() => "WithImageInfo (complete)",
+            DecoderState::WithImageInfo(_) => "WithImageInfo",
+            DecoderState::WithFrameInfo(_) => "WithFrameInfo",
+            DecoderState::Processing => "Processing",
+        };
+        let input = match self.data {
+            InputData::Owned(_) => "owned",
+            InputData::Borrowed { .. } => "borrowed",
+        };
+        let cached = |present: bool| if present { "cached" } else { "not cached" };
+
+        let mut out = String::new();
+        let _ = writeln!(out, "state: {state}");
+        let _ = writeln!(
+            out,
+            "input: {} bytes ({input}), offset {}, image start {}",
+            self.data.as_slice().len(),
+            self.data_offset,
+            self.image_start
+        );
+        let _ = writeln!(out, "pixel format: {:?}", self.pixel_format);
+        let _ = writeln!(out, "cms: {:?}", self.options.CmsType);
+        match &self.basic_info {
+            Some(info) => {
+                let _ = writeln!(
+                    out,
+                    "basic info: {}x{}, {} extra channels",
+                    info.Width, info.Height, info.NumExtraChannels
+                );
+            }
+            None => {
+                let _ = writeln!(out, "basic info: none");
+            }
+        }
+        let _ = writeln!(
+            out,
+            "metadata boxes: exif {}, xml {}, jumbf {}",
+            cached(self.exif_boxes_cache.is_some()),
+            cached(self.xml_boxes_cache.is_some()),
+            cached(self.jumbf_boxes_cache.is_some())
+        );
+        let _ = writeln!(
+            out,
+            "tone map clip stats: {}",
+            if self.tone_map_clip_stats.is_some() {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+        let _ = write!(out, "options: {:?}", self.options);
+        out
+    }
+
+    /// Pixel format of the frame being output: the preview's while the
+    /// preview is the next or current frame, if one was set for it.
+    fn frame_pixel_format(&self) -> JxlPixelFormat {
+        match self.preview_pixel_format {
+            Some(format) if self.preview_frame => format,
+            _ => self.pixel_format,
+        }
+    }
+
+    /// Whether a preview in the image is output as the first frame.
+    /// Upstream decodes it unless told to skip it.
+    fn outputs_preview(&self) -> bool {
+        self.options.PreferPreview || !self.options.SkipPreview
+    }
+
+    /// Basic info sized for the preview, or `None` if there is none.
+    fn preview_info(&self) -> Option<JxlBasicInfoRaw> {
+        let mut info = self.basic_info.clone()?;
+        if info.Preview_Width == 0 {
+            return None;
+        }
+        // Like the image size, the preview size is reported upright when the
+        // orientation is applied
+        let transposed = self.options.AdjustOrientation && is_transposing(self.orientation);
+        (info.Width, info.Height) = if transposed {
+            (info.Preview_Height, info.Preview_Width)
+        } else {
+            (info.Preview_Width, info.Preview_Height)
+        };
+        Some(info)
+    }
+
+    /// Basic info sized for the frame being output: the preview size while
+    /// the preview is the next or current frame, otherwise the image size.
+    fn output_info(&self) -> Option<JxlBasicInfoRaw> {
+        if self.preview_frame {
+            return self.preview_info();
+        }
+        self.basic_info.clone()
+    }
+
+    /// Header orientation to undo after decoding, when `AdjustOrientation` is
+    /// off and the image isn't stored upright.
+    fn orientation_to_undo(&self) -> Option<JxlOrientation> {
+        (!self.options.AdjustOrientation && self.orientation != JxlOrientation::Identity)
+            .then_some(self.orientation)
+    }
+
+    /// Size upstream decodes a `width` x `height` output at, which differs
+    /// only when a transposing orientation is undone afterwards.
+    fn upright_output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        match self.orientation_to_undo() {
+            Some(orientation) => upright_size(width, height, orientation),
+            None => (width, height),
+        }
+    }
+
+    /// Basic info for sizing output buffers: `output_info`, or `None` when
+    /// no frame will be output.
+    fn buffer_info(&self) -> Option<JxlBasicInfoRaw> {
+        if self.ends_without_frames() {
+            return None;
+        }
+        self.output_info()
+    }
+
+    /// Whether decoding ends right after the image header: no frame has been
+    /// output and none will be, either because of `MetadataOnly` or because
+    /// upstream reports no frame left.
+    fn ends_without_frames(&self) -> bool {
+        let DecoderState::WithImageInfo(d) = &self.state else {
+            return false;
+        };
+        self.frames_completed == 0 && (self.options.MetadataOnly || !d.has_more_frames())
+    }
+
+    /// Whether the `MaxFrames` limit has been reached in the current image.
+    fn frame_limit_reached(&self) -> bool {
+        let max_frames = self.options.MaxFrames as usize;
+        max_frames != 0 && self.frames_completed >= max_frames
+    }
+
+    /// Row length, stride and total size in bytes of the color output for
+    /// `info`. The stride includes the padding requested by `RowAlignment`.
+    ///
+    /// Returns `None` if the size overflows `usize`, as huge dimensions can
+    /// on 32-bit targets.
+    fn color_layout(&self, info: &JxlBasicInfoRaw) -> Option<(usize, usize, usize)> {
+        let bytes_per_row = calculate_bytes_per_row(info, &self.frame_pixel_format())?;
+        let stride = align_row(bytes_per_row, self.options.RowAlignment)?;
+        let size = stride.checked_mul(info.Height as usize)?;
+        Some((bytes_per_row, stride, size))
+    }
+
+    /// Checks the estimated decode memory against `MemoryLimitBytes`, setting
+    /// the last error and returning false if it is exceeded.
+    fn check_memory_limit(&self) -> bool {
+        let limit = self.options.MemoryLimitBytes;
+        let Some(info) = &self.basic_info else {
+            return true;
+        };
+        if limit == 0 || self.options.MetadataOnly {
+            return true;
+        }
+
+        let estimate =
+            estimate_decode_memory(info, &self.pixel_format, self.options.DecodeExtraChannels);
+        if estimate > limit as u64 {
+            set_last_error_with_status(
+                JxlStatus::MemoryLimitExceeded,
+                format!(
+                    "Decoding needs an estimated {estimate} bytes, over the {limit} byte memory limit"
+                ),
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Checks that `color_type` can be output for the current image, setting
+    /// the last error and returning false if not. Upstream has no color to
+    /// gray conversion, with or without a CMS, so gray output needs a
+    /// grayscale image. Anything goes before the image header is read.
+    fn check_color_type(&self, color_type: JxlColorType) -> bool {
+        let gray_output = matches!(
+            color_type,
+            JxlColorType::Grayscale | JxlColorType::GrayscaleAlpha
+        );
+        if gray_output && self.basic_info.is_some() && self.embedded_channels != 1 {
+            set_last_error_with_status(
+                JxlStatus::InvalidArgument,
+                format!(
+                    "{color_type:?} output requires a grayscale image, but the image is in color"
+                ),
+            );
+            return false;
+        }
+        true
+    }
+
+    fn reset_clip_stats(&self) {
+        if let Some(stats) = &self.tone_map_clip_stats {
+            stats.reset();
+        }
+    }
+
+    /// Checks that soft-proofing settings can be changed, setting the last
+    /// error if not. They are built into the CMS, which needs to be lcms2
+    /// and can only be replaced before the current image starts decoding.
+    fn check_soft_proof_change(&self) -> Result<(), JxlStatus> {
+        if self.options.CmsType != JxlCmsType::Lcms2 {
+            set_last_error_with_status(
+                JxlStatus::InvalidArgument,
+                "Soft proofing requires CmsType Lcms2",
+            );
+            return Err(JxlStatus::InvalidArgument);
+        }
+        let started = !matches!(self.state, DecoderState::Initialized(_))
+            || self.data_offset != self.image_start;
+        if started {
+            set_last_error_with_status(
+                JxlStatus::InvalidState,
+                "Soft proofing must be set before the image starts decoding",
+            );
+            return Err(JxlStatus::InvalidState);
+        }
+        Ok(())
+    }
 }
 
-/// Creates a CMS implementation from the given type.
-fn create_cms(cms_type: JxlCmsType) -> Option<Box<dyn jxl::api::JxlCms>> {
-    match cms_type {
+/// Returns the tone-mapping luminance coefficients set in `options`, or `None`
+/// if they should be derived from the image.
+#[cfg(feature = "tone-mapping")]
+fn tone_map_luminances(options: &JxlDecodeOptions) -> Option<[f32; 3]> {
+    let luminances = [
+        options.ToneMapLuminanceR,
+        options.ToneMapLuminanceG,
+        options.ToneMapLuminanceB,
+    ];
+    if luminances == [0.0; 3] {
+        None
+    } else {
+        Some(luminances)
+    }
+}
+
+/// Returns true for the `CmsType`s that tone map HDR input.
+fn is_tone_mapping(cms_type: JxlCmsType) -> bool {
+    matches!(
+        cms_type,
+        JxlCmsType::Bt2446a
+            | JxlCmsType::Bt2446aLinear
+            | JxlCmsType::Bt2446aPerceptual
+            | JxlCmsType::ToneMap
+    )
+}
+
+/// Creates a CMS implementation for the `CmsType` in `options`.
+///
+/// `clip_stats` is only used by the tone-mapping CMS types, and `soft_proof`
+/// only by lcms2.
+#[cfg_attr(not(feature = "tone-mapping"), allow(unused_variables))]
+fn create_cms(
+    options: &JxlDecodeOptions,
+    clip_stats: Option<&Arc<ToneMapClipStats>>,
+    soft_proof: Option<SoftProof>,
+) -> Option<Box<dyn jxl::api::JxlCms>> {
+    #[cfg(feature = "tone-mapping")]
+    let tone_mapping = |method| -> Option<Box<dyn jxl::api::JxlCms>> {
+        Some(Box::new(crate::cms::ToneMappingLcms2Cms {
+            desired_intensity_target: 203.0,
+            method,
+            source_intensity_target_override: options.SourceIntensityTargetOverride,
+            luminances_override: tone_map_luminances(options),
+            clip_stats: clip_stats.cloned(),
+            sanitize_output: options.SanitizeFloatOutput,
+        }))
+    };
+
+    match options.CmsType {
         JxlCmsType::None => None,
+        JxlCmsType::Builtin => Some(Box::new(crate::cms::BuiltinCms)),
         #[cfg(feature = "cms-lcms2")]
-        JxlCmsType::Lcms2 => Some(Box::new(crate::cms::Lcms2Cms)),
+        JxlCmsType::Lcms2 => Some(Box::new(crate::cms::Lcms2Cms {
+            soft_proof,
+            intent: None,
+            gamut_compression: options.GamutCompression,
+        })),
         #[cfg(not(feature = "cms-lcms2"))]
         JxlCmsType::Lcms2 => {
             set_last_error("lcms2 support not compiled in");
             None
         }
         #[cfg(feature = "tone-mapping")]
-        JxlCmsType::Bt2446a => Some(Box::new(crate::cms::ToneMappingLcms2Cms {
-            desired_intensity_target: 203.0,
-            method: crate::tone_mapping::ToneMapMethod::Bt2446a,
-        })),
+        JxlCmsType::Bt2446a => tone_mapping(crate::tone_mapping::ToneMapMethod::Bt2446a),
         #[cfg(feature = "tone-mapping")]
-        JxlCmsType::Bt2446aLinear => Some(Box::new(crate::cms::ToneMappingLcms2Cms {
-            desired_intensity_target: 203.0,
-            method: crate::tone_mapping::ToneMapMethod::Bt2446aLinear,
-        })),
+        JxlCmsType::Bt2446aLinear => {
+            tone_mapping(crate::tone_mapping::ToneMapMethod::Bt2446aLinear)
+        }
         #[cfg(feature = "tone-mapping")]
-        JxlCmsType::Bt2446aPerceptual => Some(Box::new(crate::cms::ToneMappingLcms2Cms {
-            desired_intensity_target: 203.0,
-            method: crate::tone_mapping::ToneMapMethod::Bt2446aPerceptual,
-        })),
+        JxlCmsType::Bt2446aPerceptual => {
+            tone_mapping(crate::tone_mapping::ToneMapMethod::Bt2446aPerceptual)
+        }
+        #[cfg(feature = "tone-mapping")]
+        JxlCmsType::ToneMap => tone_mapping(crate::tone_mapping::ToneMapMethod::default()),
         #[cfg(not(feature = "tone-mapping"))]
-        JxlCmsType::Bt2446a | JxlCmsType::Bt2446aLinear | JxlCmsType::Bt2446aPerceptual => {
+        JxlCmsType::Bt2446a
+        | JxlCmsType::Bt2446aLinear
+        | JxlCmsType::Bt2446aPerceptual
+        | JxlCmsType::ToneMap => {
             set_last_error("tone-mapping support not compiled in");
             None
         }
     }
 }
 
+/// Returns an error message if `options` yields no CMS, naming the reason.
+fn missing_cms_reason(options: &JxlDecodeOptions) -> Option<&'static str> {
+    match options.CmsType {
+        JxlCmsType::None => Some("CmsType is None"),
+        JxlCmsType::Lcms2 if !cfg!(feature = "cms-lcms2") => {
+            Some("this build lacks the `cms-lcms2` feature")
+        }
+        JxlCmsType::Bt2446a
+        | JxlCmsType::Bt2446aLinear
+        | JxlCmsType::Bt2446aPerceptual
+        | JxlCmsType::ToneMap
+            if !cfg!(feature = "tone-mapping") =>
+        {
+            Some("this build lacks the `tone-mapping` feature")
+        }
+        _ => None,
+    }
+}
+
+/// Reads the image header from the codestream at the start of `data`, or
+/// returns `None` if `data` is not JPEG XL.
+///
+/// Partial codestream boxes are gathered only as far as the header needs:
+/// starting small, the amount doubles while the header runs past it.
+fn read_file_header(data: &[u8]) -> Option<jxl::error::Result<jxl::headers::FileHeader>> {
+    use jxl::headers::{FileHeader, JxlHeader};
+
+    let mut limit = 4096usize;
+    loop {
+        let codestream = codestream_bytes(data, limit)?;
+        let mut br = jxl::bit_reader::BitReader::new(&codestream);
+        let result = FileHeader::read(&mut br);
+        let cut_short = matches!(codestream, Cow::Owned(_)) && codestream.len() >= limit;
+        if cut_short && matches!(result, Err(jxl::error::Error::OutOfBounds(_))) {
+            limit = limit.saturating_mul(2);
+            continue;
+        }
+        return Some(result);
+    }
+}
+
+/// Reads each extra channel's downsampling shift from the image header at
+/// the start of `data`, since the upstream basic info doesn't expose it.
+/// Returns an empty list if the header can't be parsed.
+fn extra_channel_dim_shifts(data: &[u8]) -> Vec<u32> {
+    let Some(header) = read_file_header(data) else {
+        return Vec::new();
+    };
+    header
+        .map(|header| {
+            header
+                .image_metadata
+                .extra_channel_info
+                .iter()
+                .map(|ec| ec.dim_shift())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns true if decoding to `output` requires a CMS.
+///
+/// XYB-encoded images can be converted to any simple encoding natively; other
+/// images can only be output in their embedded color space.
+fn output_needs_cms(
+    uses_original_profile: bool,
+    embedded: &JxlColorProfile,
+    output: &JxlColorProfile,
+) -> bool {
+    if uses_original_profile {
+        !embedded.same_color_encoding(output)
+    } else {
+        !output.can_output_to()
+    }
+}
+
 // ============================================================================
 // Decoder Lifecycle
 // ============================================================================
@@ -241,6 +1134,46 @@ pub unsafe extern "C" fn jxl_decoder_create_with_options(
     Box::into_raw(decoder) as *mut NativeDecoderHandle
 }
 
+/// Creates an independent decoder for the same input and configuration.
+///
+/// The clone gets its own copy of the input (borrowed input is copied too),
+/// the options, the pixel format, extra channel data formats and selected
+/// channel, and the settings that survive resets: the desired output color
+/// profile and soft proofing. It starts fresh at the beginning of the input,
+/// not at the source decoder's current position, so several clones can each
+/// skip to and decode different frames concurrently on separate threads. The
+/// source decoder is unchanged.
+///
+/// # Returns
+/// A new decoder that must be destroyed with `jxl_decoder_destroy`, or null
+/// if `decoder` is null or in use by a concurrent call.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_clone(
+    decoder: *const NativeDecoderHandle,
+) -> *mut NativeDecoderHandle {
+    let inner = get_decoder_ref!(decoder, std::ptr::null_mut());
+    let _busy = enter_decoder!(inner, std::ptr::null_mut());
+
+    clear_last_error();
+
+    let mut clone = DecoderInner::with_options(inner.options.clone());
+    clone.data = InputData::Owned(inner.data.as_slice().to_vec());
+    clone.pixel_format = inner.pixel_format;
+    clone.preview_pixel_format = inner.preview_pixel_format;
+    clone.single_channel = inner.single_channel;
+    clone.extra_channel_formats = inner.extra_channel_formats.clone();
+    clone.desired_output_profile = inner.desired_output_profile.clone();
+    clone.proof_profile = inner.proof_profile.clone();
+    clone.gamut_warning = inner.gamut_warning;
+    // Rebuild the CMS with the soft-proofing settings
+    clone.reset_state();
+
+    Box::into_raw(Box::new(clone)) as *mut NativeDecoderHandle
+}
+
 /// Destroys a decoder instance and frees its resources.
 ///
 /// # Safety
@@ -257,6 +1190,8 @@ pub unsafe extern "C" fn jxl_decoder_destroy(decoder: *mut NativeDecoderHandle)
 
 /// Resets the decoder to its initial state, allowing it to decode a new image.
 ///
+/// Options and pixel format are kept; any output color profile is cleared.
+///
 /// # Safety
 /// The decoder pointer must be valid.
 #[unsafe(no_mangle)]
@@ -269,8 +1204,33 @@ pub unsafe extern "C" fn jxl_decoder_reset(decoder: *mut NativeDecoderHandle) ->
     JxlStatus::Success
 }
 
+/// Resets the decoder for a new image, keeping its configuration.
+///
+/// Like `jxl_decoder_reset`, clears input, decoder state and cached metadata,
+/// but also keeps the output color profile set with
+/// `jxl_decoder_set_output_color_profile`. It is re-applied when the next
+/// image reaches `HaveBasicInfo`, so pooled decoders handling same-config
+/// images need only one setup. If it cannot be applied to that image (for
+/// example, it needs a CMS that is not configured), `jxl_decoder_process`
+/// returns `Error`.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_reset_keep_config(
+    decoder: *mut NativeDecoderHandle,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    clear_last_error();
+    inner.reset_keep_config();
+
+    JxlStatus::Success
+}
+
 /// Rewinds the decoder to the beginning of the input without clearing the data buffer.
 /// This allows re-decoding the same input without calling SetInput again.
+/// A channel selected by `jxl_decoder_read_single_channel` stays selected.
 ///
 /// # Safety
 /// The decoder pointer must be valid.
@@ -314,9 +1274,51 @@ pub unsafe extern "C" fn jxl_decoder_append_input(
 
     // Append data without resetting
     if size > 0 {
-        inner
-            .data
-            .extend_from_slice(unsafe { slice::from_raw_parts(data, size) });
+        match &mut inner.data {
+            InputData::Owned(buffer) => {
+                buffer.extend_from_slice(unsafe { slice::from_raw_parts(data, size) })
+            }
+            InputData::Borrowed { .. } => {
+                set_last_error("Cannot append to borrowed input; reset the decoder first");
+                return JxlStatus::InvalidState;
+            }
+        }
+    }
+
+    JxlStatus::Success
+}
+
+/// Sets input data that the decoder reads in place, without copying.
+///
+/// Resets the decoder, then decodes directly from `data`. This avoids holding a
+/// second copy of large inputs. `jxl_decoder_append_input` fails with
+/// `InvalidState` until the decoder is reset or given new input.
+///
+/// # Safety
+/// - `decoder` must be a valid decoder pointer.
+/// - `data` must point to `size` readable bytes.
+/// - The buffer must stay valid and unmodified until the decoder is destroyed,
+///   reset, or given new input.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_set_input_borrowed(
+    decoder: *mut NativeDecoderHandle,
+    data: *const u8,
+    size: usize,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    if data.is_null() && size > 0 {
+        set_last_error("Null data pointer with non-zero size");
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+    inner.reset();
+    if size > 0 {
+        inner.data = InputData::Borrowed {
+            ptr: data,
+            len: size,
+        };
     }
 
     JxlStatus::Success
@@ -355,7 +1357,7 @@ pub unsafe extern "C" fn jxl_decoder_set_input_file(
     match std::fs::read(path_str) {
         Ok(data) => {
             inner.reset();
-            inner.data = data;
+            inner.data = InputData::Owned(data);
             JxlStatus::Success
         }
         Err(e) => {
@@ -365,10 +1367,100 @@ pub unsafe extern "C" fn jxl_decoder_set_input_file(
     }
 }
 
-/// Processes the current input data and returns the next decoder event.
+/// Gets the number of input bytes the decoder has consumed so far.
 ///
-/// This is the main function for streaming decoding. Call it repeatedly,
-/// handling each event appropriately:
+/// Callers multiplexing a stream can use this to advance their own cursor.
+/// Resets to 0 on `jxl_decoder_reset`, `jxl_decoder_rewind` and when new
+/// input is set from a file.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_bytes_consumed(decoder: *const NativeDecoderHandle) -> usize {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+    inner.data_offset
+}
+
+/// Checks whether input remains after the bytes consumed so far.
+///
+/// After `Complete`, this indicates trailing data such as another concatenated
+/// image or trailing metadata. The upstream decoder may read ahead while
+/// buffering, so short trailing data can already count as consumed.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_has_trailing_data(
+    decoder: *const NativeDecoderHandle,
+) -> bool {
+    let inner = get_decoder_ref_silent!(decoder, false);
+    inner.data_offset < inner.data.as_slice().len()
+}
+
+/// Computes a CRC32 of the decoder's entire input buffer.
+///
+/// See `jxl_signature_crc32` for the meaning of `codestream_only`.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_input_crc32(
+    decoder: *const NativeDecoderHandle,
+    codestream_only: bool,
+) -> u32 {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+    checksum::input_crc32(inner.data.as_slice(), codestream_only)
+}
+
+/// Finds the offset of the first JPEG XL signature in `data`.
+fn find_next_signature(data: &[u8]) -> Option<usize> {
+    (0..data.len()).find(|&i| {
+        matches!(data[i], 0xFF | 0x00)
+            && matches!(
+                jxl::api::check_signature(&data[i..]),
+                ProcessingResult::Complete { result: Some(_) }
+            )
+    })
+}
+
+/// Advances to the next JPEG XL signature at or after the consumed input,
+/// so a concatenated image can be decoded.
+///
+/// On success the decoder restarts at the signature, as if the input began
+/// there. `jxl_decoder_rewind` still returns to the start of the whole input.
+/// Codestream signatures are only two bytes, so arbitrary trailing data can
+/// produce false matches.
+///
+/// # Returns
+/// - `Success` if a signature was found.
+/// - `NeedMoreInput` if no complete signature is in the remaining input.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_skip_to_next_image(
+    decoder: *mut NativeDecoderHandle,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    clear_last_error();
+
+    let start = inner.data_offset;
+    let Some(found) = find_next_signature(&inner.data.as_slice()[start..]) else {
+        return JxlStatus::NeedMoreInput;
+    };
+
+    inner.rewind();
+    inner.data_offset = start + found;
+    inner.image_start = inner.data_offset;
+
+    JxlStatus::Success
+}
+
+/// Processes the current input data and returns the next decoder event.
+///
+/// This is the main function for streaming decoding. Call it repeatedly,
+/// handling each event appropriately:
 /// - `NeedMoreInput`: Call `jxl_decoder_append_input` with more data
 /// - `HaveBasicInfo`: Image info is available, call `jxl_decoder_get_basic_info`
 /// - `HaveFrameHeader`: Frame header is available, call `jxl_decoder_get_frame_header`
@@ -377,6 +1469,22 @@ pub unsafe extern "C" fn jxl_decoder_set_input_file(
 /// - `Complete`: All frames decoded, decoding is finished
 /// - `Error`: Check `jxl_get_last_error` for details
 ///
+/// For an image with frames the events are `HaveBasicInfo`, then
+/// `HaveFrameHeader`, `NeedOutputBuffer` and `FrameComplete` for each frame,
+/// then `Complete`. With the `MetadataOnly` option, `Complete` directly
+/// follows `HaveBasicInfo`, with no frame at all. Callers waiting for
+/// `NeedOutputBuffer` must treat it as the end, and buffer size queries then
+/// return 0. A codestream that ends right after the image header can't be
+/// told from a truncated one, so it returns `NeedMoreInput` rather than
+/// `Complete` or `Error`. Once returned, `Complete` is
+/// returned again by every later call. With the `MaxFrames` option, it
+/// follows the last allowed frame even if the image has more.
+///
+/// A decoder must only be used from one thread at a time. If this or a pixel
+/// read is called while another such call on the same decoder is still
+/// running, it fails with `Error` and `jxl_get_last_error_status` returns
+/// `Busy`; the running call is unaffected.
+///
 /// # Safety
 /// The decoder pointer must be valid.
 #[unsafe(no_mangle)]
@@ -384,6 +1492,7 @@ pub unsafe extern "C" fn jxl_decoder_process(
     decoder: *mut NativeDecoderHandle,
 ) -> JxlDecoderEvent {
     let inner = get_decoder_mut!(decoder, JxlDecoderEvent::Error);
+    let _busy = enter_decoder!(inner, JxlDecoderEvent::Error);
 
     clear_last_error();
 
@@ -393,23 +1502,67 @@ pub unsafe extern "C" fn jxl_decoder_process(
     match state {
         DecoderState::Initialized(decoder_init) => {
             // Try to get image info
-            let mut input_slice: &[u8] = &inner.data[inner.data_offset..];
+            let mut input_slice: &[u8] = &inner.data.as_slice()[inner.data_offset..];
             let len_before = input_slice.len();
-            let result = decoder_init.process(&mut input_slice);
+            let result = inner.time_header(|| decoder_init.process(&mut input_slice));
             inner.data_offset += len_before - input_slice.len();
 
             match result {
                 Ok(ProcessingResult::Complete { result: decoder_with_info }) => {
                     // Cache basic info
                     let jxl_info = decoder_with_info.basic_info();
-                    let basic_info = convert_basic_info(jxl_info);
+                    let mut basic_info = convert_basic_info(jxl_info);
+                    inner.orientation = basic_info.Orientation;
+                    if inner.options.AdjustOrientation {
+                        basic_info.Orientation = JxlOrientation::Identity;
+                    } else if is_transposing(basic_info.Orientation) {
+                        // Upstream reports the upright size; output keeps the stored size
+                        (basic_info.Width, basic_info.Height) =
+                            (basic_info.Height, basic_info.Width);
+                    }
+                    let dim_shifts =
+                        extra_channel_dim_shifts(&inner.data.as_slice()[inner.image_start..]);
                     inner.extra_channels = jxl_info
                         .extra_channels
                         .iter()
-                        .map(convert_extra_channel_info)
+                        .enumerate()
+                        .map(|(i, ec)| {
+                            convert_extra_channel_info(ec, dim_shifts.get(i).copied().unwrap_or(0))
+                        })
                         .collect();
+                    inner.embedded_channels =
+                        decoder_with_info.embedded_color_profile().channels() as u32;
+                    inner.preview_frame = inner.outputs_preview() && basic_info.Preview_Width > 0;
                     inner.basic_info = Some(basic_info);
+                    if !inner.check_memory_limit() {
+                        inner.reset_state();
+                        return JxlDecoderEvent::Error;
+                    }
+                    let mut decoder_with_info = decoder_with_info;
+                    let profile = inner
+                        .output_profile
+                        .as_ref()
+                        .or(inner.desired_output_profile.as_ref());
+                    if let Some(profile) = profile.cloned()
+                        && let Err(e) = apply_output_color_profile(
+                            &inner.options,
+                            &mut decoder_with_info,
+                            profile,
+                        )
+                    {
+                        inner.reset_state();
+                        set_last_error(e);
+                        return JxlDecoderEvent::Error;
+                    }
+                    inner.output_matches_embedded = output_matches_embedded(&decoder_with_info);
                     inner.state = DecoderState::WithImageInfo(decoder_with_info);
+                    log(JxlLogLevel::Info, || {
+                        let info = inner.basic_info.as_ref().expect("basic info was just set");
+                        format!(
+                            "Read basic info: {}x{}, {} extra channels",
+                            info.Width, info.Height, info.NumExtraChannels
+                        )
+                    });
                     JxlDecoderEvent::HaveBasicInfo
                 }
                 Ok(ProcessingResult::NeedsMoreInput { fallback, .. }) => {
@@ -424,27 +1577,51 @@ pub unsafe extern "C" fn jxl_decoder_process(
             }
         }
         DecoderState::WithImageInfo(mut decoder_with_info) => {
-            // Check if there are more frames
-            if !decoder_with_info.has_more_frames() {
+            // Check if there are more frames to decode
+            if inner.options.MetadataOnly
+                || inner.frame_limit_reached()
+                || !decoder_with_info.has_more_frames()
+            {
                 inner.state = DecoderState::WithImageInfo(decoder_with_info);
+                let frames = inner.frames_completed;
+                log(JxlLogLevel::Info, || {
+                    format!("Image complete, frame count {frames}")
+                });
                 return JxlDecoderEvent::Complete;
             }
 
+            // Re-check with the final pixel format before any frame is decoded
+            if !inner.check_memory_limit() {
+                inner.state = DecoderState::WithImageInfo(decoder_with_info);
+                return JxlDecoderEvent::Error;
+            }
+            if inner.single_channel.is_none()
+                && !inner.check_color_type(inner.frame_pixel_format().ColorType)
+            {
+                inner.state = DecoderState::WithImageInfo(decoder_with_info);
+                return JxlDecoderEvent::Error;
+            }
+
             // Set pixel format before processing frame
             // Skip extra channels unless DecodeExtraChannels is enabled
-            let skip_extra = !inner.options.DecodeExtraChannels;
-            let pixel_format = convert_to_jxl_pixel_format(&inner.pixel_format, &inner.extra_channels, skip_extra);
+            let pixel_format = inner.upstream_pixel_format(inner.single_channel);
             decoder_with_info.set_pixel_format(pixel_format);
+            inner.frame_single_channel = inner.single_channel;
 
             // Try to get frame info
-            let mut input_slice: &[u8] = &inner.data[inner.data_offset..];
+            let mut input_slice: &[u8] = &inner.data.as_slice()[inner.data_offset..];
             let len_before = input_slice.len();
-            let result = decoder_with_info.process(&mut input_slice);
+            let result = inner.time_header(|| decoder_with_info.process(&mut input_slice));
             inner.data_offset += len_before - input_slice.len();
 
             match result {
                 Ok(ProcessingResult::Complete { result: decoder_with_frame }) => {
                     inner.state = DecoderState::WithFrameInfo(decoder_with_frame);
+                    inner.decoded_frame_header = None;
+                    let frame = inner.frames_completed;
+                    log(JxlLogLevel::Debug, || {
+                        format!("Read header of frame {frame}")
+                    });
                     JxlDecoderEvent::HaveFrameHeader
                 }
                 Ok(ProcessingResult::NeedsMoreInput { fallback, .. }) => {
@@ -470,6 +1647,47 @@ pub unsafe extern "C" fn jxl_decoder_process(
     }
 }
 
+/// Continues with the next image of a concatenated multi-image stream.
+///
+/// Only valid after `jxl_decoder_process` returns `Complete`. The decoder
+/// restarts at the end of the current image without discarding the buffer,
+/// then processes as `jxl_decoder_process` does, so the next event is usually
+/// `HaveBasicInfo`, or `NeedMoreInput` if no further image data is buffered yet.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_next_image(
+    decoder: *mut NativeDecoderHandle,
+) -> JxlDecoderEvent {
+    let inner = get_decoder_mut!(decoder, JxlDecoderEvent::Error);
+
+    let image_length = match &inner.state {
+        DecoderState::WithImageInfo(d) if !d.has_more_frames() => d.file_length(),
+        _ => {
+            set_last_error_with_status(
+                JxlStatus::InvalidState,
+                "Next image is only available after the current image is complete",
+            );
+            return JxlDecoderEvent::Error;
+        }
+    };
+
+    // The upstream decoder may read ahead of the image end, so the consumed
+    // offset can't be used directly
+    let Some(image_length) = image_length else {
+        set_last_error("Length of the current image is unknown");
+        return JxlDecoderEvent::Error;
+    };
+
+    let next_start = inner.image_start + image_length as usize;
+    inner.rewind();
+    inner.image_start = next_start.min(inner.data.as_slice().len());
+    inner.data_offset = inner.image_start;
+
+    unsafe { jxl_decoder_process(decoder) }
+}
+
 /// Gets the basic image info (streaming API).
 ///
 /// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
@@ -498,7 +1716,9 @@ pub unsafe extern "C" fn jxl_decoder_get_basic_info(
 
 /// Gets the current frame header (streaming API).
 ///
-/// Only valid after `jxl_decoder_process` returns `HaveFrameHeader`.
+/// Valid after `jxl_decoder_process` returns `HaveFrameHeader`, and after the
+/// frame's pixels are read until the next frame header. `IsOpaque` is only
+/// refined by the alpha scan in the latter case.
 ///
 /// # Safety
 /// - `decoder` must be valid.
@@ -510,14 +1730,108 @@ pub unsafe extern "C" fn jxl_decoder_get_frame_header(
 ) -> JxlStatus {
     let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
 
-    let DecoderState::WithFrameInfo(ref decoder_with_frame) = inner.state else {
+    let current = match inner.state {
+        DecoderState::WithFrameInfo(_) => inner.frame_header(),
+        DecoderState::WithImageInfo(_) => inner.decoded_frame_header.clone(),
+        _ => None,
+    };
+    let Some(current) = current else {
         set_last_error("Frame header not yet available - call jxl_decoder_process until HaveFrameHeader");
         return JxlStatus::InvalidState;
     };
 
     if let Some(out_header) = unsafe { header.as_mut() } {
-        let jxl_header = decoder_with_frame.frame_header();
-        *out_header = convert_frame_header(&jxl_header);
+        *out_header = current;
+    }
+
+    JxlStatus::Success
+}
+
+/// Gets the current frame's duration in animation ticks.
+///
+/// `JxlFrameHeader.DurationMs` is a rounded `f32`; players that schedule
+/// frames exactly should use this together with `Animation.TpsNumerator` and
+/// `Animation.TpsDenominator` from the basic info, where one tick lasts
+/// `TpsDenominator / TpsNumerator` seconds.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveFrameHeader`.
+/// Returns 0 if no frame header is available, the image is not animated or
+/// the frame is the preview.
+///
+/// # Safety
+/// `decoder` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_frame_duration_ticks(
+    decoder: *const NativeDecoderHandle,
+) -> u32 {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+
+    let DecoderState::WithFrameInfo(ref decoder_with_frame) = inner.state else {
+        return 0;
+    };
+    let Some(info) = &inner.basic_info else {
+        return 0;
+    };
+    if inner.preview_frame {
+        return 0;
+    }
+
+    let header = decoder_with_frame.frame_header();
+    duration_ms_to_ticks(header.duration.unwrap_or(0.0), &info.Animation)
+}
+
+/// Gets the current frame header with the fields of `JxlFrameHeaderEx`, for
+/// compositors and other callers that need more than `JxlFrameHeader`.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveFrameHeader`.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidState` if no frame header is available.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `header` must point to a writable `JxlFrameHeaderEx`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_frame_header_ex(
+    decoder: *const NativeDecoderHandle,
+    header: *mut JxlFrameHeaderEx,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+
+    // The base getter also serves decoded frames, whose index has moved on
+    if !matches!(inner.state, DecoderState::WithFrameInfo(_)) {
+        set_last_error(
+            "Frame header not yet available - call jxl_decoder_process until HaveFrameHeader",
+        );
+        return JxlStatus::InvalidState;
+    }
+
+    let mut base = JxlFrameHeader::default();
+    let status = unsafe { jxl_decoder_get_frame_header(decoder, &mut base) };
+    if status != JxlStatus::Success {
+        return status;
+    }
+
+    if let Some(out_header) = unsafe { header.as_mut() } {
+        // frames_completed counts a decoded or skipped preview, which is not
+        // one of the image's frames
+        let preview_output = inner.basic_info.as_ref().is_some_and(|info| {
+            (inner.options.PreferPreview || !inner.options.SkipPreview) && info.Preview_Width > 0
+        });
+        let frame_index = if inner.preview_frame {
+            0
+        } else {
+            inner
+                .frames_completed
+                .saturating_sub(preview_output as usize)
+        };
+
+        *out_header = JxlFrameHeaderEx {
+            Header: base,
+            DurationTicks: unsafe { jxl_decoder_get_frame_duration_ticks(decoder) },
+            FrameIndex: frame_index as u32,
+        };
     }
 
     JxlStatus::Success
@@ -537,6 +1851,10 @@ pub unsafe extern "C" fn jxl_decoder_get_frame_header(
 /// The number of bytes written, or the required buffer size if buffer is null or too small.
 /// Returns 0 if no frame header is available or the frame has no name.
 ///
+/// The name is valid UTF-8 and is only copied whole, so a too-small buffer is
+/// left untouched rather than receiving a split multi-byte sequence. Use
+/// `jxl_decoder_get_frame_name_utf16_len` to size UTF-16 buffers.
+///
 /// # Safety
 /// - `decoder` must be valid.
 /// - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
@@ -574,12 +1892,41 @@ pub unsafe extern "C" fn jxl_decoder_get_frame_name(
     name_len
 }
 
+/// Gets the length of the current frame's name in UTF-16 code units.
+///
+/// `jxl_decoder_get_frame_name` and `JxlFrameHeader.NameLength` count UTF-8
+/// bytes, which differs from the UTF-16 length for non-ASCII names. Callers
+/// converting to UTF-16 strings (e.g. .NET) can size buffers from this.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveFrameHeader`.
+/// Returns 0 if no frame header is available or the frame has no name.
+///
+/// # Safety
+/// `decoder` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_frame_name_utf16_len(
+    decoder: *const NativeDecoderHandle,
+) -> u32 {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+
+    let DecoderState::WithFrameInfo(ref decoder_with_frame) = inner.state else {
+        return 0;
+    };
+
+    let header = decoder_with_frame.frame_header();
+    header.name.encode_utf16().count() as u32
+}
+
 /// Decodes pixels into the provided buffer (streaming API).
 ///
 /// Call this after `jxl_decoder_process` returns `NeedOutputBuffer`.
 /// After successful completion, call `jxl_decoder_process` again to
 /// get `FrameComplete` or continue with the next frame.
 ///
+/// With the `ContinueOnFrameError` option, a frame that fails to decode
+/// returns `FrameError` instead of `Error` when it can be skipped; the
+/// buffer contents are then unspecified.
+///
 /// # Safety
 /// - `decoder` must be valid.
 /// - `buffer` must be valid for writes of `buffer_size` bytes.
@@ -590,18 +1937,33 @@ pub unsafe extern "C" fn jxl_decoder_read_pixels(
     buffer_size: usize,
 ) -> JxlDecoderEvent {
     let inner = get_decoder_mut!(decoder, JxlDecoderEvent::Error);
+    let _busy = enter_decoder!(inner, JxlDecoderEvent::Error);
 
     if buffer.is_null() {
         set_last_error("Null buffer pointer");
         return JxlDecoderEvent::Error;
     }
 
-    let Some(ref info) = inner.basic_info else {
+    if inner.frame_single_channel.is_some() {
+        set_last_error("Frame outputs a single channel, use jxl_decoder_read_single_channel");
+        return JxlDecoderEvent::Error;
+    }
+
+    let Some(info) = inner.output_info() else {
         set_last_error("Basic info not available");
         return JxlDecoderEvent::Error;
     };
+    // Upstream output buffers need at least one row
+    if info.Width == 0 || info.Height == 0 {
+        set_last_error("Output has zero width or height");
+        return JxlDecoderEvent::Error;
+    }
 
-    let required_size = calculate_buffer_size(info, &inner.pixel_format);
+    let height = info.Height as usize;
+    let Some((bytes_per_row, stride, required_size)) = inner.color_layout(&info) else {
+        set_last_error("Output buffer size overflows the address space");
+        return JxlDecoderEvent::Error;
+    };
     if buffer_size < required_size {
         set_last_error(format!(
             "Buffer too small: {} bytes provided, {} required",
@@ -612,8 +1974,8 @@ pub unsafe extern "C" fn jxl_decoder_read_pixels(
 
     clear_last_error();
 
-    let height = info.Height as usize;
-    let bytes_per_row = calculate_bytes_per_row(info, &inner.pixel_format);
+    // Kept for jxl_decoder_get_frame_header once the frame is decoded
+    let header = inner.frame_header();
 
     // Take ownership of decoder state
     let state = std::mem::replace(&mut inner.state, DecoderState::Processing);
@@ -627,30 +1989,50 @@ pub unsafe extern "C" fn jxl_decoder_read_pixels(
         }
     };
 
-    // Decode pixels
-    let buffer_slice = unsafe { slice::from_raw_parts_mut(buffer, buffer_size) };
-    let output_buffer = JxlOutputBuffer::new(buffer_slice, height, bytes_per_row);
+    // Decode pixels, upright into scratch space if the orientation is undone
+    let width = info.Width as usize;
+    let mut upright = inner
+        .orientation_to_undo()
+        .map(|orientation| UprightPlane::new(width, height, bytes_per_row / width, orientation));
+    let output_buffer = match &mut upright {
+        Some(plane) => plane.output_buffer(),
+        None => {
+            let buffer_slice = unsafe { slice::from_raw_parts_mut(buffer, buffer_size) };
+            JxlOutputBuffer::new_with_stride(buffer_slice, height, bytes_per_row, stride)
+        }
+    };
     let mut buffers = [output_buffer];
 
-    let mut input_slice: &[u8] = &inner.data[inner.data_offset..];
+    let mut input_slice: &[u8] = &inner.data.as_slice()[inner.data_offset..];
     let len_before = input_slice.len();
-    let result = decoder_with_frame.process(&mut input_slice, &mut buffers);
+    let result =
+        inner.time_frame_decode(|| decoder_with_frame.process(&mut input_slice, &mut buffers));
     inner.data_offset += len_before - input_slice.len();
 
     match result {
         Ok(ProcessingResult::Complete { result }) => {
             inner.state = DecoderState::WithImageInfo(result);
+            let frame = inner.frames_completed;
+            inner.frames_completed += 1;
+            log(JxlLogLevel::Debug, || format!("Decoded frame {frame}"));
+            let pixels = unsafe { slice::from_raw_parts_mut(buffer, required_size) };
+            if let Some(plane) = &upright {
+                plane.write_back(pixels, stride);
+            }
+            inner.unpremultiply_output(&info, pixels, bytes_per_row, stride);
+            inner.sanitize_output(pixels, bytes_per_row, stride);
+            inner.record_frame_hash(pixels, bytes_per_row, stride);
+            inner.record_frame_stats(pixels, bytes_per_row, stride);
+            inner.record_decoded_header(header);
+            // Post-processing above still uses the preview's pixel format
+            inner.preview_frame = false;
             JxlDecoderEvent::FrameComplete
         }
         Ok(ProcessingResult::NeedsMoreInput { fallback, .. }) => {
             inner.state = DecoderState::WithFrameInfo(fallback);
             JxlDecoderEvent::NeedMoreInput
         }
-        Err(e) => {
-            inner.reset_state();
-            set_last_error(format!("Pixel decode error: {}", e));
-            JxlDecoderEvent::Error
-        }
+        Err(e) => inner.frame_error(format!("Pixel decode error: {}", e)),
     }
 }
 
@@ -665,6 +2047,8 @@ pub unsafe extern "C" fn jxl_decoder_has_more_frames(
     let inner = get_decoder_ref_silent!(decoder, false);
 
     match &inner.state {
+        DecoderState::WithImageInfo(_) if inner.options.MetadataOnly => false,
+        DecoderState::WithImageInfo(_) if inner.frame_limit_reached() => false,
         DecoderState::WithImageInfo(d) => d.has_more_frames(),
         DecoderState::WithFrameInfo(_) => true, // We have a frame, so there's at least one more
         _ => false,
@@ -688,6 +2072,7 @@ pub unsafe extern "C" fn jxl_decoder_skip_frame(
     decoder: *mut NativeDecoderHandle,
 ) -> JxlDecoderEvent {
     let inner = get_decoder_mut!(decoder, JxlDecoderEvent::Error);
+    let _busy = enter_decoder!(inner, JxlDecoderEvent::Error);
 
     clear_last_error();
 
@@ -704,7 +2089,7 @@ pub unsafe extern "C" fn jxl_decoder_skip_frame(
     };
 
     // Skip frame without decoding pixels
-    let mut input_slice: &[u8] = &inner.data[inner.data_offset..];
+    let mut input_slice: &[u8] = &inner.data.as_slice()[inner.data_offset..];
     let len_before = input_slice.len();
     let result = decoder_with_frame.skip_frame(&mut input_slice);
     inner.data_offset += len_before - input_slice.len();
@@ -712,6 +2097,10 @@ pub unsafe extern "C" fn jxl_decoder_skip_frame(
     match result {
         Ok(ProcessingResult::Complete { result }) => {
             inner.state = DecoderState::WithImageInfo(result);
+            inner.preview_frame = false;
+            let frame = inner.frames_completed;
+            inner.frames_completed += 1;
+            log(JxlLogLevel::Debug, || format!("Skipped frame {frame}"));
             JxlDecoderEvent::FrameComplete
         }
         Ok(ProcessingResult::NeedsMoreInput { fallback, .. }) => {
@@ -732,6 +2121,11 @@ pub unsafe extern "C" fn jxl_decoder_skip_frame(
 
 /// Calculates the required buffer size for a specific extra channel.
 ///
+/// Samples are in the channel's data format, see
+/// `jxl_decoder_set_extra_channel_format`. Channels stored at reduced
+/// resolution (`JxlExtraChannelInfo.DimShift`) are upsampled while decoding,
+/// so the buffer always covers the full image.
+///
 /// # Arguments
 /// * `decoder` - The decoder instance.
 /// * `index` - The extra channel index (0-based).
@@ -748,7 +2142,7 @@ pub unsafe extern "C" fn jxl_decoder_get_extra_channel_buffer_size(
 ) -> usize {
     let inner = get_decoder_ref_silent!(decoder, 0);
 
-    let Some(ref info) = inner.basic_info else {
+    let Some(info) = inner.buffer_info() else {
         return 0;
     };
 
@@ -757,11 +2151,8 @@ pub unsafe extern "C" fn jxl_decoder_get_extra_channel_buffer_size(
     }
 
     // Extra channels are single-plane, so calculate based on width * height * bytes_per_sample
-    let width = info.Width as usize;
-    let height = info.Height as usize;
-    let bytes_per_sample = bytes_per_sample(inner.pixel_format.DataFormat);
-    
-    width * height * bytes_per_sample
+    let bytes_per_sample = bytes_per_sample(inner.extra_channel_data_format(index as usize));
+    calculate_plane_size(&info, bytes_per_sample).unwrap_or(0)
 }
 
 /// Decodes pixels with extra channels into separate buffers.
@@ -769,19 +2160,43 @@ pub unsafe extern "C" fn jxl_decoder_get_extra_channel_buffer_size(
 /// The first buffer receives color data (RGB/RGBA/etc.), subsequent buffers
 /// receive extra channels in order. Set buffer to null to skip that channel.
 ///
+/// When the color type has alpha, the first alpha channel is the one in the
+/// color output and its extra buffer is left untouched, unless the
+/// `SeparateFirstAlpha` option is set, in which case the buffer receives a
+/// copy of that alpha. Any further alpha channels are written to their own
+/// buffers like other extra channels.
+///
+/// Returns `FrameError` for a skipped frame like `jxl_decoder_read_pixels`.
+///
 /// # Arguments
 /// * `decoder` - The decoder instance.
 /// * `color_buffer` - Output buffer for color data.
 /// * `color_buffer_size` - Size of color buffer in bytes.
 /// * `extra_buffers` - Array of pointers to extra channel buffers (can contain nulls to skip).
 /// * `extra_buffer_sizes` - Array of buffer sizes for each extra channel.
-/// * `num_extra_buffers` - Number of extra buffers provided.
+/// * `num_extra_buffers` - Number of extra buffers provided, at most the
+///   number of extra channels; channels past the last buffer are skipped.
+///   With no extra channels, this behaves like `jxl_decoder_read_pixels`.
+///
+/// * `buffers_written` - Optional output array of `1 + num_extra_buffers`
+///   flags, set to whether the color buffer and each extra buffer received
+///   data. An extra buffer is written only when it is non-null and its
+///   channel is decoded, so null buffers, the alpha channel in the color
+///   output (without `SeparateFirstAlpha`), and channels not decoded without
+///   `DecodeExtraChannels` all report false. All flags are false unless `FrameComplete` is returned.
+///   May be null.
+///
+/// Returns `Error` if more buffers than extra channels are passed, or if a
+/// non-null buffer for a channel that is output is smaller than
+/// `jxl_decoder_get_extra_channel_buffer_size`.
 ///
 /// # Safety
 /// - `decoder` must be valid.
 /// - `color_buffer` must be valid for writes of `color_buffer_size` bytes.
 /// - `extra_buffers` must point to `num_extra_buffers` pointers.
 /// - Each non-null buffer must be valid for writes of its corresponding size.
+/// - `buffers_written` must be null or valid for writes of
+///   `1 + num_extra_buffers` flags.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn jxl_decoder_read_pixels_with_extra_channels(
     decoder: *mut NativeDecoderHandle,
@@ -790,20 +2205,46 @@ pub unsafe extern "C" fn jxl_decoder_read_pixels_with_extra_channels(
     extra_buffers: *const *mut u8,
     extra_buffer_sizes: *const usize,
     num_extra_buffers: usize,
+    buffers_written: *mut bool,
 ) -> JxlDecoderEvent {
+    let written = if buffers_written.is_null() {
+        None
+    } else {
+        let written = unsafe { slice::from_raw_parts_mut(buffers_written, 1 + num_extra_buffers) };
+        written.fill(false);
+        Some(written)
+    };
+
     let inner = get_decoder_mut!(decoder, JxlDecoderEvent::Error);
+    let _busy = enter_decoder!(inner, JxlDecoderEvent::Error);
 
     if color_buffer.is_null() {
         set_last_error("Null color buffer pointer");
         return JxlDecoderEvent::Error;
     }
 
-    let Some(ref info) = inner.basic_info else {
+    if inner.frame_single_channel.is_some() {
+        set_last_error("Frame outputs a single channel, use jxl_decoder_read_single_channel");
+        return JxlDecoderEvent::Error;
+    }
+
+    let Some(info) = inner.output_info() else {
         set_last_error("Basic info not available");
         return JxlDecoderEvent::Error;
     };
+    if info.Width == 0 || info.Height == 0 {
+        set_last_error("Output has zero width or height");
+        return JxlDecoderEvent::Error;
+    }
 
-    let required_color_size = calculate_buffer_size(info, &inner.pixel_format);
+    let height = info.Height as usize;
+    let width = info.Width as usize;
+    let pixel_format = inner.frame_pixel_format();
+    let Some((color_bytes_per_row, color_stride, required_color_size)) = inner.color_layout(&info)
+    else {
+        set_last_error("Output buffer size overflows the address space");
+        return JxlDecoderEvent::Error;
+    };
     if color_buffer_size < required_color_size {
         set_last_error(format!(
             "Color buffer too small: {} bytes provided, {} required",
@@ -812,12 +2253,93 @@ pub unsafe extern "C" fn jxl_decoder_read_pixels_with_extra_channels(
         return JxlDecoderEvent::Error;
     }
 
+    let num_extra = inner.extra_channels.len();
+    if num_extra_buffers > num_extra {
+        set_last_error(format!(
+            "{} extra channel buffers provided, but the image has {} extra channels",
+            num_extra_buffers, num_extra
+        ));
+        return JxlDecoderEvent::Error;
+    }
+
+    let extra_sample_sizes: Vec<usize> = (0..num_extra)
+        .map(|i| bytes_per_sample(inner.extra_channel_data_format(i)))
+        .collect();
+    let Some(extra_plane_sizes) = extra_sample_sizes
+        .iter()
+        .map(|&sample_size| calculate_plane_size(&info, sample_size))
+        .collect::<Option<Vec<usize>>>()
+    else {
+        set_last_error("Output buffer size overflows the address space");
+        return JxlDecoderEvent::Error;
+    };
+    let extra_bytes_per_sample = |i: usize| extra_sample_sizes[i];
+    let extra_bytes_per_row = |i: usize| width * extra_bytes_per_sample(i);
+    let extra_size = |i: usize| extra_plane_sizes[i];
+
+    let extra_buffer_ptrs = if !extra_buffers.is_null() && num_extra_buffers > 0 {
+        unsafe { slice::from_raw_parts(extra_buffers, num_extra_buffers) }
+    } else {
+        &[]
+    };
+
+    let extra_sizes = if !extra_buffer_sizes.is_null() && num_extra_buffers > 0 {
+        unsafe { slice::from_raw_parts(extra_buffer_sizes, num_extra_buffers) }
+    } else {
+        &[]
+    };
+
+    // Upstream takes buffers only for the extra channels it outputs, in order,
+    // so caller buffers are matched by channel index: the alpha channel folded
+    // into the color output gets none, and channels the caller skips decode
+    // into scratch space.
+    let skip_extra = !inner.options.DecodeExtraChannels;
+    let outputs: Vec<bool> =
+        convert_to_jxl_pixel_format(&pixel_format, &inner.extra_channels, skip_extra)
+            .extra_channel_format
+            .iter()
+            .map(Option::is_some)
+            .collect();
+    let has_buffer = |i: usize| {
+        let ptr = extra_buffer_ptrs.get(i).copied().unwrap_or(std::ptr::null_mut());
+        !ptr.is_null()
+    };
+    // With SeparateFirstAlpha, the alpha in the color output is copied to its
+    // channel's buffer after decoding, as upstream can't output it twice
+    let color_samples = match pixel_format.ColorType {
+        JxlColorType::Grayscale => 1,
+        JxlColorType::GrayscaleAlpha => 2,
+        JxlColorType::Rgb | JxlColorType::Bgr => 3,
+        JxlColorType::Rgba | JxlColorType::Bgra => 4,
+    };
+    let separate_alpha = inner
+        .extra_channels
+        .iter()
+        .position(|ec| ec.ChannelType == JxlExtraChannelType::Alpha)
+        .filter(|&i| {
+            inner.options.SeparateFirstAlpha
+                && matches!(color_samples, 2 | 4)
+                && !outputs[i]
+                && has_buffer(i)
+        });
+    let writes = |i: usize| (outputs[i] && has_buffer(i)) || separate_alpha == Some(i);
+    for i in (0..num_extra).filter(|&i| writes(i)) {
+        let size = extra_sizes.get(i).copied().unwrap_or(0);
+        if size < extra_size(i) {
+            set_last_error(format!(
+                "Extra channel buffer {} too small: {} bytes provided, {} required",
+                i,
+                size,
+                extra_size(i)
+            ));
+            return JxlDecoderEvent::Error;
+        }
+    }
+
     clear_last_error();
 
-    let height = info.Height as usize;
-    let width = info.Width as usize;
-    let color_bytes_per_row = calculate_bytes_per_row(info, &inner.pixel_format);
-    let num_extra = inner.extra_channels.len();
+    // Kept for jxl_decoder_get_frame_header once the frame is decoded
+    let header = inner.frame_header();
 
     // Take ownership of decoder state
     let state = std::mem::replace(&mut inner.state, DecoderState::Processing);
@@ -832,38 +2354,64 @@ pub unsafe extern "C" fn jxl_decoder_read_pixels_with_extra_channels(
     };
 
     // Build output buffers - one for color, one for each extra channel
-    let color_slice = unsafe { slice::from_raw_parts_mut(color_buffer, color_buffer_size) };
-    let color_output = JxlOutputBuffer::new(color_slice, height, color_bytes_per_row);
-    
-    // Build extra channel buffers
-    let extra_bytes_per_sample = bytes_per_sample(inner.pixel_format.DataFormat);
-    let extra_bytes_per_row = width * extra_bytes_per_sample;
-    
-    let extra_buffer_ptrs = if !extra_buffers.is_null() && num_extra_buffers > 0 {
-        unsafe { slice::from_raw_parts(extra_buffers, num_extra_buffers) }
-    } else {
-        &[]
-    };
-    
-    let extra_sizes = if !extra_buffer_sizes.is_null() && num_extra_buffers > 0 {
-        unsafe { slice::from_raw_parts(extra_buffer_sizes, num_extra_buffers) }
-    } else {
-        &[]
+    // With the orientation undone, buffers the caller passed are decoded
+    // upright into scratch space and written back afterwards
+    let undo = inner.orientation_to_undo();
+    let mut color_upright = undo.map(|orientation| {
+        UprightPlane::new(width, height, color_bytes_per_row / width, orientation)
+    });
+    let color_output = match &mut color_upright {
+        Some(plane) => plane.output_buffer(),
+        None => {
+            let color_slice = unsafe { slice::from_raw_parts_mut(color_buffer, color_buffer_size) };
+            JxlOutputBuffer::new_with_stride(color_slice, height, color_bytes_per_row, color_stride)
+        }
     };
     
+    // Build extra channel buffers
+    let (upright_width, upright_height) = inner.upright_output_size(width, height);
+    let mut scratch_buffers: Vec<Vec<u8>> = (0..num_extra)
+        .filter(|&i| outputs[i] && !has_buffer(i))
+        .map(|i| vec![0u8; extra_size(i)])
+        .collect();
+    let mut scratch = scratch_buffers.iter_mut();
+    let mut extra_upright: Vec<Option<UprightPlane>> = (0..num_extra)
+        .map(|i| {
+            let orientation = undo.filter(|_| outputs[i] && has_buffer(i))?;
+            Some(UprightPlane::new(
+                width,
+                height,
+                extra_bytes_per_sample(i),
+                orientation,
+            ))
+        })
+        .collect();
+
     // Create a vector of output buffers - color first, then extras
-    // Note: We need to handle the case where not all extra channels have buffers
-    let mut all_buffers: Vec<JxlOutputBuffer> = Vec::with_capacity(1 + num_extra.min(num_extra_buffers));
+    let mut all_buffers: Vec<JxlOutputBuffer> = Vec::with_capacity(1 + num_extra);
     all_buffers.push(color_output);
-    
-    for i in 0..num_extra.min(num_extra_buffers) {
-        let ptr = extra_buffer_ptrs.get(i).copied().unwrap_or(std::ptr::null_mut());
-        let size = extra_sizes.get(i).copied().unwrap_or(0);
-        
-        if !ptr.is_null() && size >= height * extra_bytes_per_row {
-            let slice = unsafe { slice::from_raw_parts_mut(ptr, size) };
-            all_buffers.push(JxlOutputBuffer::new(slice, height, extra_bytes_per_row));
+
+    for (i, plane) in extra_upright.iter_mut().enumerate() {
+        if !outputs[i] {
+            continue;
         }
+        if let Some(plane) = plane {
+            all_buffers.push(plane.output_buffer());
+            continue;
+        }
+        let slice = if has_buffer(i) {
+            unsafe { slice::from_raw_parts_mut(extra_buffer_ptrs[i], extra_sizes[i]) }
+        } else {
+            scratch
+                .next()
+                .expect("scratch buffer for skipped channel")
+                .as_mut_slice()
+        };
+        all_buffers.push(JxlOutputBuffer::new(
+            slice,
+            upright_height,
+            upright_width * extra_bytes_per_sample(i),
+        ));
     }
 
     // Note: The pixel format (including extra channel format) was already set when
@@ -871,27 +2419,304 @@ pub unsafe extern "C" fn jxl_decoder_read_pixels_with_extra_channels(
     // flag must be set before that transition.
 
     // Decode pixels
-    let mut input_slice: &[u8] = &inner.data[inner.data_offset..];
+    let mut input_slice: &[u8] = &inner.data.as_slice()[inner.data_offset..];
     let len_before = input_slice.len();
     
     // We need to use a mutable borrow of all_buffers
-    let result = decoder_with_frame.process(&mut input_slice, &mut all_buffers);
+    let result =
+        inner.time_frame_decode(|| decoder_with_frame.process(&mut input_slice, &mut all_buffers));
     inner.data_offset += len_before - input_slice.len();
 
     match result {
         Ok(ProcessingResult::Complete { result }) => {
             inner.state = DecoderState::WithImageInfo(result);
+            let frame = inner.frames_completed;
+            inner.frames_completed += 1;
+            log(JxlLogLevel::Debug, || format!("Decoded frame {frame}"));
+            let pixels = unsafe { slice::from_raw_parts_mut(color_buffer, required_color_size) };
+            if let Some(plane) = &color_upright {
+                plane.write_back(pixels, color_stride);
+            }
+            for (i, plane) in extra_upright.iter().enumerate() {
+                if let Some(plane) = plane {
+                    let out =
+                        unsafe { slice::from_raw_parts_mut(extra_buffer_ptrs[i], extra_size(i)) };
+                    plane.write_back(out, extra_bytes_per_row(i));
+                }
+            }
+            inner.unpremultiply_output(&info, pixels, color_bytes_per_row, color_stride);
+            inner.sanitize_output(pixels, color_bytes_per_row, color_stride);
+            if let Some(alpha) = separate_alpha {
+                // The alpha in the color output has the color data format
+                let out = unsafe {
+                    slice::from_raw_parts_mut(extra_buffer_ptrs[alpha], extra_size(alpha))
+                };
+                let sample_size = extra_bytes_per_sample(alpha);
+                let pixel_size = color_samples * sample_size;
+                let alpha_offset = pixel_size - sample_size;
+                for (row, out_row) in pixels
+                    .chunks(color_stride)
+                    .zip(out.chunks_exact_mut(extra_bytes_per_row(alpha)))
+                {
+                    for (pixel, sample) in row[..color_bytes_per_row]
+                        .chunks_exact(pixel_size)
+                        .zip(out_row.chunks_exact_mut(sample_size))
+                    {
+                        sample.copy_from_slice(&pixel[alpha_offset..]);
+                    }
+                }
+            }
+            inner.record_frame_hash(pixels, color_bytes_per_row, color_stride);
+            inner.record_frame_stats(pixels, color_bytes_per_row, color_stride);
+            inner.record_decoded_header(header);
+            if let Some(written) = written {
+                written[0] = true;
+                for (i, flag) in written[1..].iter_mut().enumerate() {
+                    *flag = writes(i);
+                }
+            }
+            inner.preview_frame = false;
             JxlDecoderEvent::FrameComplete
         }
         Ok(ProcessingResult::NeedsMoreInput { fallback, .. }) => {
             inner.state = DecoderState::WithFrameInfo(fallback);
             JxlDecoderEvent::NeedMoreInput
         }
-        Err(e) => {
-            inner.reset_state();
-            set_last_error(format!("Pixel decode error: {}", e));
-            JxlDecoderEvent::Error
+        Err(e) => inner.frame_error(format!("Pixel decode error: {}", e)),
+    }
+}
+
+/// Decodes a single extra channel, such as alpha, into `buffer`.
+///
+/// Call this after `jxl_decoder_process` returns `NeedOutputBuffer`, in place
+/// of `jxl_decoder_read_pixels`. The channel is written as packed rows in its
+/// data format (see `jxl_decoder_set_extra_channel_format`), sized by
+/// `jxl_decoder_get_extra_channel_buffer_size`. Use
+/// `jxl_decoder_get_extra_channel_info` to find the alpha channel's index.
+///
+/// A frame's output layout is fixed when its header is read, so this also
+/// selects the channel for later frames: from the next frame on, upstream is
+/// set up to output only that channel and no color, and frames must be read
+/// with this function until the pixel format is set or the decoder is reset;
+/// a rewind keeps the selection. The frame
+/// already under way still has its color decoded, into scratch space, and
+/// the channel must be one it outputs: the first alpha channel when the
+/// color type has alpha, or any channel with `DecodeExtraChannels`.
+/// Even without color output, upstream may reconstruct color channels that
+/// the rendering of the selected channel depends on, so the time saved
+/// varies by image.
+///
+/// Returns `FrameError` for a skipped frame like `jxl_decoder_read_pixels`.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `channel_index` - The extra channel index (0-based).
+/// * `buffer` - Output buffer for the channel.
+/// * `buffer_size` - Size of `buffer` in bytes.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `buffer` must be valid for writes of `buffer_size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_read_single_channel(
+    decoder: *mut NativeDecoderHandle,
+    channel_index: u32,
+    buffer: *mut u8,
+    buffer_size: usize,
+) -> JxlDecoderEvent {
+    let inner = get_decoder_mut!(decoder, JxlDecoderEvent::Error);
+    let _busy = enter_decoder!(inner, JxlDecoderEvent::Error);
+
+    if buffer.is_null() {
+        set_last_error("Null buffer pointer");
+        return JxlDecoderEvent::Error;
+    }
+
+    let Some(info) = inner.output_info() else {
+        set_last_error("Basic info not available");
+        return JxlDecoderEvent::Error;
+    };
+    if info.Width == 0 || info.Height == 0 {
+        set_last_error("Output has zero width or height");
+        return JxlDecoderEvent::Error;
+    }
+
+    let channel = channel_index as usize;
+    if channel >= inner.extra_channels.len() {
+        set_last_error(format!(
+            "Extra channel index {} out of range (image has {})",
+            channel,
+            inner.extra_channels.len()
+        ));
+        return JxlDecoderEvent::Error;
+    }
+
+    let height = info.Height as usize;
+    let sample_size = bytes_per_sample(inner.extra_channel_data_format(channel));
+    let Some(required_size) = calculate_plane_size(&info, sample_size) else {
+        set_last_error("Output buffer size overflows the address space");
+        return JxlDecoderEvent::Error;
+    };
+    let bytes_per_row = info.Width as usize * sample_size;
+    if buffer_size < required_size {
+        set_last_error(format!(
+            "Buffer too small: {} bytes provided, {} required",
+            buffer_size, required_size
+        ));
+        return JxlDecoderEvent::Error;
+    }
+
+    if !matches!(inner.state, DecoderState::WithFrameInfo(_)) {
+        set_last_error("Must call jxl_decoder_process until NeedOutputBuffer first");
+        return JxlDecoderEvent::Error;
+    }
+
+    // Later frames output only this channel
+    inner.single_channel = Some(channel);
+
+    // The current frame outputs what its header was set up with: the channel
+    // either has its own buffer or is the alpha folded into the color output
+    let frame_format = inner.upstream_pixel_format(inner.frame_single_channel);
+    let first_alpha = inner
+        .extra_channels
+        .iter()
+        .position(|ec| ec.ChannelType == JxlExtraChannelType::Alpha);
+    let color_samples = match inner.frame_pixel_format().ColorType {
+        JxlColorType::Grayscale => 1,
+        JxlColorType::GrayscaleAlpha => 2,
+        JxlColorType::Rgb | JxlColorType::Bgr => 3,
+        JxlColorType::Rgba | JxlColorType::Bgra => 4,
+    };
+    let has_color = frame_format.color_data_format.is_some();
+    let from_color = has_color
+        && matches!(color_samples, 2 | 4)
+        && first_alpha == Some(channel)
+        && frame_format.extra_channel_format[channel].is_none();
+    if !from_color && frame_format.extra_channel_format[channel].is_none() {
+        set_last_error(format!(
+            "Extra channel {} is not decoded for this frame; it is from the next frame on",
+            channel
+        ));
+        return JxlDecoderEvent::Error;
+    }
+
+    // Everything but the requested channel decodes into scratch space, as
+    // does the channel itself when the orientation is undone
+    let width = info.Width as usize;
+    let undo = inner.orientation_to_undo();
+    let (upright_width, upright_height) = inner.upright_output_size(width, height);
+    let color_sample_size = bytes_per_sample(inner.frame_pixel_format().DataFormat);
+    let color_bytes_per_row = upright_width * color_samples * color_sample_size;
+    let color_size = if has_color {
+        calculate_plane_size(&info, color_samples * color_sample_size)
+    } else {
+        Some(0)
+    };
+    let extra_scratch_sizes: Option<Vec<usize>> = frame_format
+        .extra_channel_format
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != channel)
+        .filter_map(|(_, format)| format.as_ref())
+        .map(|format| calculate_plane_size(&info, format.bytes_per_sample()))
+        .collect();
+    let (Some(color_size), Some(extra_scratch_sizes)) = (color_size, extra_scratch_sizes) else {
+        set_last_error("Output buffer size overflows the address space");
+        return JxlDecoderEvent::Error;
+    };
+
+    clear_last_error();
+
+    let DecoderState::WithFrameInfo(decoder_with_frame) =
+        std::mem::replace(&mut inner.state, DecoderState::Processing)
+    else {
+        unreachable!("state checked above");
+    };
+
+    let mut color_scratch = vec![0u8; color_size];
+    let mut extra_scratch: Vec<Vec<u8>> = extra_scratch_sizes
+        .into_iter()
+        .map(|size| vec![0u8; size])
+        .collect();
+    let mut scratch = extra_scratch.iter_mut();
+    let mut upright = undo
+        .filter(|_| !from_color)
+        .map(|orientation| UprightPlane::new(width, height, sample_size, orientation));
+
+    let mut buffers: Vec<JxlOutputBuffer> = Vec::with_capacity(1 + inner.extra_channels.len());
+    if has_color {
+        buffers.push(JxlOutputBuffer::new(
+            &mut color_scratch,
+            upright_height,
+            color_bytes_per_row,
+        ));
+    }
+    for (i, format) in frame_format.extra_channel_format.iter().enumerate() {
+        let Some(format) = format else {
+            continue;
+        };
+        if i == channel {
+            buffers.push(match &mut upright {
+                Some(plane) => plane.output_buffer(),
+                None => {
+                    let slice = unsafe { slice::from_raw_parts_mut(buffer, buffer_size) };
+                    JxlOutputBuffer::new(slice, height, bytes_per_row)
+                }
+            });
+            continue;
+        }
+        let slice = scratch
+            .next()
+            .expect("scratch buffer for other channel")
+            .as_mut_slice();
+        buffers.push(JxlOutputBuffer::new(
+            slice,
+            upright_height,
+            upright_width * format.bytes_per_sample(),
+        ));
+    }
+
+    let mut input_slice: &[u8] = &inner.data.as_slice()[inner.data_offset..];
+    let len_before = input_slice.len();
+    let result =
+        inner.time_frame_decode(|| decoder_with_frame.process(&mut input_slice, &mut buffers));
+    inner.data_offset += len_before - input_slice.len();
+
+    match result {
+        Ok(ProcessingResult::Complete { result }) => {
+            inner.state = DecoderState::WithImageInfo(result);
+            inner.preview_frame = false;
+            let frame = inner.frames_completed;
+            inner.frames_completed += 1;
+            log(JxlLogLevel::Debug, || format!("Decoded frame {frame}"));
+            let out = unsafe { slice::from_raw_parts_mut(buffer, required_size) };
+            if from_color {
+                let alpha_offset = (color_samples - 1) * sample_size;
+                let alpha: Vec<u8> = color_scratch
+                    .chunks_exact(color_samples * sample_size)
+                    .flat_map(|pixel| &pixel[alpha_offset..alpha_offset + sample_size])
+                    .copied()
+                    .collect();
+                let orientation = undo.unwrap_or(JxlOrientation::Identity);
+                undo_orientation(
+                    &alpha,
+                    out,
+                    width,
+                    height,
+                    sample_size,
+                    bytes_per_row,
+                    orientation,
+                );
+            } else if let Some(plane) = &upright {
+                plane.write_back(out, bytes_per_row);
+            }
+            JxlDecoderEvent::FrameComplete
+        }
+        Ok(ProcessingResult::NeedsMoreInput { fallback, .. }) => {
+            inner.state = DecoderState::WithFrameInfo(fallback);
+            JxlDecoderEvent::NeedMoreInput
         }
+        Err(e) => inner.frame_error(format!("Pixel decode error: {}", e)),
     }
 }
 
@@ -901,6 +2726,15 @@ pub unsafe extern "C" fn jxl_decoder_read_pixels_with_extra_channels(
 
 /// Sets the desired output pixel format.
 ///
+/// Also clears a channel selected by `jxl_decoder_read_single_channel`, so
+/// later frames output color again.
+///
+/// Once the image header is read, a color type the image can't be output in
+/// is rejected with `InvalidArgument`: gray output needs a grayscale image, as
+/// color images are never converted to gray. A format set before that, such
+/// as `JxlDecodeOptions.PixelFormat`, is checked when the first frame is set
+/// up instead, where `jxl_decoder_process` fails with the same status.
+///
 /// # Safety
 /// The decoder pointer must be valid.
 #[unsafe(no_mangle)]
@@ -914,9 +2748,130 @@ pub unsafe extern "C" fn jxl_decoder_set_pixel_format(
         set_last_error("Null format pointer");
         return JxlStatus::InvalidArgument;
     };
+    if !inner.check_color_type(format.ColorType) {
+        return JxlStatus::InvalidArgument;
+    }
 
     clear_last_error();
     inner.pixel_format = *format;
+    inner.single_channel = None;
+
+    JxlStatus::Success
+}
+
+/// Sets the output pixel format of the preview frame, or clears it when
+/// `format` is null so the preview uses the main pixel format.
+///
+/// Lets a thumbnail be output as e.g. `Uint8` RGBA while the main image is
+/// decoded as float. The preview is output as the first frame unless
+/// `SkipPreview` is set; `jxl_decoder_get_buffer_size` reports its size in
+/// this format while it is the next or current frame. The setting is kept
+/// across resets and rewinds, like the main pixel format.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidArgument` if the image can't be output in the color type.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `format` must be null or point to a valid `JxlPixelFormat`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_set_preview_pixel_format(
+    decoder: *mut NativeDecoderHandle,
+    format: *const JxlPixelFormat,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    let format = unsafe { format.as_ref() }.copied();
+    if let Some(format) = format
+        && !inner.check_color_type(format.ColorType)
+    {
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+    inner.preview_pixel_format = format;
+
+    JxlStatus::Success
+}
+
+/// Sets the data format of one extra channel, in place of the pixel format's.
+///
+/// Lets each channel be output at the precision it needs, e.g. a depth map
+/// as `Float32` next to a selection mask as `Uint8`. This also applies to
+/// `Optional` and `Unknown` channels, whose contents the image doesn't
+/// describe. The pixel format's endianness is used. The alpha channel in the
+/// color output keeps the pixel format's data format.
+///
+/// Like `jxl_decoder_set_pixel_format`, this takes effect from the next frame
+/// header. The setting is kept across rewinds and cleared by a reset.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidState` if the image header has not been read.
+/// - `InvalidArgument` if `index` is out of range.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_set_extra_channel_format(
+    decoder: *mut NativeDecoderHandle,
+    index: u32,
+    data_format: JxlDataFormat,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    if inner.basic_info.is_none() {
+        set_last_error("Basic info not available");
+        return JxlStatus::InvalidState;
+    }
+    let index = index as usize;
+    let num_extra = inner.extra_channels.len();
+    if index >= num_extra {
+        set_last_error(format!(
+            "Extra channel index {} out of range (image has {})",
+            index, num_extra
+        ));
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+    inner.extra_channel_formats.resize(num_extra, None);
+    inner.extra_channel_formats[index] = Some(data_format);
+
+    JxlStatus::Success
+}
+
+/// Gets the effective bit depth of decoded output in the current pixel format.
+///
+/// Integer formats report at most the source precision, e.g. 10 bits for a
+/// 10-bit image decoded to `Uint16`; float formats report their own layout.
+/// Samples span the format's full range unless the `ScaleToFullRange` option
+/// is off, in which case integer samples are at most 2^bits - 1.
+/// Must be called after basic info is available (after `HaveBasicInfo` event).
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `bit_depth` must point to a writable `JxlBitDepth`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_output_bit_depth(
+    decoder: *const NativeDecoderHandle,
+    bit_depth: *mut JxlBitDepth,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+
+    let Some(out) = (unsafe { bit_depth.as_mut() }) else {
+        set_last_error("Null bit depth pointer");
+        return JxlStatus::InvalidArgument;
+    };
+
+    let Some(ref info) = inner.basic_info else {
+        set_last_error("Basic info not available");
+        return JxlStatus::InvalidState;
+    };
+
+    clear_last_error();
+    *out = output_bit_depth(&info.BitDepth, inner.frame_pixel_format().DataFormat);
 
     JxlStatus::Success
 }
@@ -965,982 +2920,3903 @@ pub unsafe extern "C" fn jxl_decoder_get_extra_channel_info(
 
 /// Calculates the required buffer size for decoded pixels.
 ///
+/// With `PreferPreview`, this is the preview's size until the preview frame
+/// has been read or skipped. Returns 0 when no frame will be decoded, as with
+/// `MetadataOnly`, where `jxl_decoder_process` returns `Complete` right after
+/// `HaveBasicInfo`.
+/// Also returns 0 if the size overflows `usize`, as the dimensions of a huge
+/// or malicious header can on 32-bit targets; reading pixels then fails.
+///
 /// # Safety
 /// `decoder` must be valid and basic info must be available (after `HaveBasicInfo` event).
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn jxl_decoder_get_buffer_size(decoder: *const NativeDecoderHandle) -> usize {
     let inner = get_decoder_ref_silent!(decoder, 0);
 
-    let Some(ref info) = inner.basic_info else {
+    let Some(info) = inner.buffer_info() else {
         return 0;
     };
 
-    calculate_buffer_size(info, &inner.pixel_format)
+    inner.color_layout(&info).map_or(0, |(_, _, size)| size)
 }
 
-// ============================================================================
-// Color Profiles
-// ============================================================================
+/// Calculates the buffer size for decoded pixels with each row padded to a
+/// multiple of `alignment` bytes.
+///
+/// Use this to size a buffer for a decoder created with `RowAlignment` set to
+/// `alignment`; rows then start `stride = size / height` bytes apart. Only
+/// row lengths are padded: for aligned rows the buffer itself must also be
+/// allocated at that alignment. An `alignment` of 0 or 1 gives the packed
+/// size. Returns 0 if the size overflows `usize`.
+///
+/// # Safety
+/// `decoder` must be valid and basic info must be available (after `HaveBasicInfo` event).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_aligned_buffer_size(
+    decoder: *const NativeDecoderHandle,
+    alignment: u32,
+) -> usize {
+    let inner = get_decoder_ref_silent!(decoder, 0);
 
-/// Internal structure to hold a cloned color profile for FFI access.
-struct ColorProfileHandle {
-    profile: JxlColorProfile,
-    /// Cached ICC data (if profile is ICC type)
-    icc_cache: Option<Vec<u8>>,
+    let Some(info) = inner.buffer_info() else {
+        return 0;
+    };
+
+    calculate_buffer_size(&info, &inner.frame_pixel_format(), alignment).unwrap_or(0)
 }
 
-/// Creates a new color profile handle from an existing profile.
-/// The handle must be freed with `jxl_color_profile_free`.
-fn create_profile_handle(profile: JxlColorProfile) -> *mut JxlColorProfileHandle {
-    let icc_cache = match &profile {
-        JxlColorProfile::Icc(data) => Some(data.clone()),
-        JxlColorProfile::Simple(_) => None,
+/// Calculates the buffer size for the decoded preview, in the format set with
+/// `jxl_decoder_set_preview_pixel_format` or else the main pixel format.
+///
+/// Unlike `jxl_decoder_get_buffer_size`, this is available whichever frame
+/// is next, so a thumbnail buffer can be sized right after `HaveBasicInfo`.
+/// Returns 0 if the image has no preview, `SkipPreview` is set without
+/// `PreferPreview`, or the size overflows `usize`.
+///
+/// # Safety
+/// `decoder` must be valid and basic info must be available (after `HaveBasicInfo` event).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_preview_buffer_size(
+    decoder: *const NativeDecoderHandle,
+) -> usize {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+
+    if !inner.outputs_preview() {
+        return 0;
+    }
+    let Some(info) = inner.preview_info() else {
+        return 0;
     };
-    let handle = Box::new(ColorProfileHandle { profile, icc_cache });
-    Box::into_raw(handle) as *mut JxlColorProfileHandle
+
+    let format = inner.preview_pixel_format.unwrap_or(inner.pixel_format);
+    calculate_buffer_size(&info, &format, inner.options.RowAlignment).unwrap_or(0)
 }
 
-/// Gets the embedded color profile from the image.
+/// Gets the number of pixels clipped at the target peak by tone mapping.
 ///
-/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+/// Counts accumulate over all frames decoded since the decoder was created,
+/// reset, or rewound. Requires the `ToneMapClipStats` option; with a
+/// non-tone-mapping `CmsType`, or for images that need no tone mapping,
+/// both counts stay 0.
 ///
 /// # Arguments
-/// * `decoder` - The decoder instance.
-/// * `profile_out` - Output for the profile raw data.
-/// * `icc_data_out` - Output pointer for ICC data (only set if profile is ICC type).
-/// * `handle_out` - Output for the profile handle (for calling helper methods).
+/// * `clipped_out` - Receives the number of pixels whose mapped luminance
+///   reached the target peak.
+/// * `total_out` - Receives the total number of tone-mapped pixels.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidState` if the `ToneMapClipStats` option was not enabled.
 ///
 /// # Safety
-/// - `decoder` must be valid.
-/// - `profile_out` must point to a writable `JxlColorProfileRaw`.
-/// - `icc_data_out` must point to a writable pointer.
-/// - `handle_out` must point to a writable pointer.
+/// - `decoder` must be a valid decoder pointer.
+/// - `clipped_out` and `total_out` must be valid for writes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_get_embedded_color_profile(
+pub unsafe extern "C" fn jxl_decoder_get_tone_map_clip_stats(
     decoder: *const NativeDecoderHandle,
-    profile_out: *mut JxlColorProfileRaw,
-    icc_data_out: *mut *const u8,
-    handle_out: *mut *mut JxlColorProfileHandle,
+    clipped_out: *mut u64,
+    total_out: *mut u64,
 ) -> JxlStatus {
     let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
 
-    let profile = match &inner.state {
-        DecoderState::WithImageInfo(d) => d.embedded_color_profile(),
-        DecoderState::WithFrameInfo(_) => {
-            set_last_error("Color profile not accessible in WithFrameInfo state");
-            return JxlStatus::InvalidState;
-        }
-        _ => {
-            set_last_error("Basic info not yet available - call jxl_decoder_process first");
-            return JxlStatus::InvalidState;
-        }
+    if clipped_out.is_null() || total_out.is_null() {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    }
+
+    let Some(stats) = &inner.tone_map_clip_stats else {
+        set_last_error("ToneMapClipStats option is not enabled");
+        return JxlStatus::InvalidState;
     };
 
-    clear_last_error();
+    let (clipped, total) = stats.get();
+    unsafe {
+        *clipped_out = clipped;
+        *total_out = total;
+    }
 
-    // Convert profile to raw format
-    let (raw, _icc_data) = convert_color_profile(profile);
+    JxlStatus::Success
+}
 
-    // Create a handle with cloned profile
-    let handle = create_profile_handle(profile.clone());
+/// Checks whether the CMS failed and was bypassed with the
+/// `CmsErrorFallback` option.
+///
+/// When true, pixels decoded since the decoder was created, reset, or
+/// rewound may be in the image's own color encoding rather than the output
+/// color profile, and the CMS error is available from `jxl_get_last_error`.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_cms_fell_back(decoder: *const NativeDecoderHandle) -> bool {
+    let inner = get_decoder_ref!(decoder, false);
 
-    // Write outputs
-    if let Some(out) = unsafe { profile_out.as_mut() } {
-        *out = raw;
+    match inner.cms_fallback.error() {
+        Some(message) => {
+            set_last_error(message);
+            true
+        }
+        None => {
+            clear_last_error();
+            false
+        }
     }
+}
 
-    if let Some(out) = unsafe { icc_data_out.as_mut() } {
-        // Get ICC data from handle's cache
-        let handle_ref = unsafe { &*(handle as *const ColorProfileHandle) };
-        *out = handle_ref.icc_cache.as_ref()
-            .map(|v| v.as_ptr())
-            .unwrap_or(std::ptr::null());
-    }
-
-    if let Some(out) = unsafe { handle_out.as_mut() } {
-        *out = handle;
-    } else {
-        // If no handle output, free it
-        unsafe { drop(Box::from_raw(handle as *mut ColorProfileHandle)) };
-    }
-
-    JxlStatus::Success
-}
-
-/// Gets the current output color profile.
+/// Gets the number of color conversions the CMS has set up since the decoder
+/// was created, reset, or rewound.
 ///
-/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+/// Images output in their embedded color encoding, such as an sRGB image
+/// decoded to sRGB, need no CMS: conversions between profiles with the same
+/// color encoding are skipped without building any transform, unless a
+/// tone-mapping `CmsType` has HDR input to map. This count shows whether the
+/// CMS was bypassed.
 ///
 /// # Safety
-/// Same as `jxl_decoder_get_embedded_color_profile`.
+/// The decoder pointer must be valid.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_get_output_color_profile(
+pub unsafe extern "C" fn jxl_decoder_get_cms_setup_count(
     decoder: *const NativeDecoderHandle,
-    profile_out: *mut JxlColorProfileRaw,
-    icc_data_out: *mut *const u8,
-    handle_out: *mut *mut JxlColorProfileHandle,
-) -> JxlStatus {
-    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+) -> u64 {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+    inner.cms_setups.load(Ordering::Relaxed)
+}
 
-    let profile = match &inner.state {
-        DecoderState::WithImageInfo(d) => d.output_color_profile(),
-        DecoderState::WithFrameInfo(_) => {
-            set_last_error("Color profile not accessible in WithFrameInfo state");
-            return JxlStatus::InvalidState;
-        }
-        _ => {
-            set_last_error("Basic info not yet available - call jxl_decoder_process first");
-            return JxlStatus::InvalidState;
-        }
-    };
+// ============================================================================
+// One-Shot Decoding
+// ============================================================================
 
-    clear_last_error();
+/// Runs `f` with a temporary decoder that borrows `data`.
+/// The decoder is destroyed when `f` returns, before the borrow ends.
+fn with_one_shot_decoder<R>(
+    data: &[u8],
+    options: Option<&JxlDecodeOptions>,
+    f: impl FnOnce(*mut NativeDecoderHandle) -> R,
+) -> R {
+    let mut inner = DecoderInner::with_options(options.cloned().unwrap_or_default());
+    inner.data = InputData::Borrowed {
+        ptr: data.as_ptr(),
+        len: data.len(),
+    };
+    let handle = Box::into_raw(Box::new(inner)) as *mut NativeDecoderHandle;
 
-    let (raw, _icc_data) = convert_color_profile(profile);
-    let handle = create_profile_handle(profile.clone());
+    let result = f(handle);
 
-    if let Some(out) = unsafe { profile_out.as_mut() } {
-        *out = raw;
-    }
+    unsafe { jxl_decoder_destroy(handle) };
+    result
+}
 
-    if let Some(out) = unsafe { icc_data_out.as_mut() } {
-        let handle_ref = unsafe { &*(handle as *const ColorProfileHandle) };
-        *out = handle_ref.icc_cache.as_ref()
-            .map(|v| v.as_ptr())
-            .unwrap_or(std::ptr::null());
+/// Processes complete input until the first frame is ready for pixel output.
+fn advance_to_first_frame(handle: *mut NativeDecoderHandle) -> JxlStatus {
+    loop {
+        match unsafe { jxl_decoder_process(handle) } {
+            JxlDecoderEvent::HaveBasicInfo
+            | JxlDecoderEvent::HaveFrameHeader
+            | JxlDecoderEvent::FrameComplete => continue,
+            JxlDecoderEvent::NeedOutputBuffer => return JxlStatus::Success,
+            JxlDecoderEvent::NeedMoreInput => {
+                set_last_error("Incomplete input - one-shot decoding requires the complete file");
+                return JxlStatus::NeedMoreInput;
+            }
+            JxlDecoderEvent::Complete => {
+                set_last_error("Image does not contain any frames");
+                return JxlStatus::Error;
+            }
+            JxlDecoderEvent::Error | JxlDecoderEvent::FrameError => return last_error_status(),
+        }
     }
+}
 
-    if let Some(out) = unsafe { handle_out.as_mut() } {
-        *out = handle;
-    } else {
-        unsafe { drop(Box::from_raw(handle as *mut ColorProfileHandle)) };
+/// Reads the pending first frame into `buffer` and maps the result to a status.
+fn read_first_frame(
+    handle: *mut NativeDecoderHandle,
+    buffer: *mut u8,
+    buffer_size: usize,
+) -> JxlStatus {
+    match unsafe { jxl_decoder_read_pixels(handle, buffer, buffer_size) } {
+        JxlDecoderEvent::FrameComplete => JxlStatus::Success,
+        JxlDecoderEvent::NeedMoreInput => {
+            set_last_error("Incomplete input - one-shot decoding requires the complete file");
+            JxlStatus::NeedMoreInput
+        }
+        _ => JxlStatus::Error,
     }
-
-    JxlStatus::Success
 }
 
-/// Sets the output color profile for decoding.
+/// Decodes only the first frame of an image in a single call.
 ///
-/// Must be called after `HaveBasicInfo` and before decoding pixels.
+/// Intended for viewers that need a still image from a (possibly animated)
+/// file. Decoding stops after frame 0; remaining frames are never parsed.
+/// Orientation and color management follow `options` exactly as for the
+/// streaming API.
 ///
 /// # Arguments
-/// * `decoder` - The decoder instance.
-/// * `profile` - The color profile raw data.
-/// * `icc_data` - ICC data pointer (required if profile tag is Icc).
+/// * `data` - The complete JXL file contents.
+/// * `size` - Size of `data` in bytes.
+/// * `options` - Decoder options, or null to use defaults. The output layout
+///   is taken from `options.PixelFormat`.
+/// * `buffer` - Output buffer for the decoded pixels, or null to query the size.
+/// * `buffer_size` - Size of `buffer` in bytes.
+/// * `out_info` - Output for the basic image info, or null if not needed.
+///
+/// # Returns
+/// - `Success` if the first frame was decoded into `buffer`.
+/// - `BufferTooSmall` if `buffer` is null or smaller than required. `out_info`
+///   is still filled, so the required size can be computed from it.
+/// - `NeedMoreInput` if `data` is truncated.
+/// - `InvalidArgument` if `data` is null or empty.
+/// - `Error` on decode failure. Call `jxl_get_last_error` for details.
 ///
 /// # Safety
-/// - `decoder` must be valid.
-/// - `profile` must point to a valid `JxlColorProfileRaw`.
-/// - If profile is ICC, `icc_data` must point to `profile.IccLength` bytes.
+/// - `data` must be valid for reads of `size` bytes.
+/// - If `options` is not null, it must point to a valid `JxlDecodeOptions` struct.
+/// - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
+/// - If `out_info` is not null, it must point to a writable `JxlBasicInfoRaw`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_set_output_color_profile(
-    decoder: *mut NativeDecoderHandle,
-    profile: *const JxlColorProfileRaw,
-    icc_data: *const u8,
+pub unsafe extern "C" fn jxl_decode_first_frame(
+    data: *const u8,
+    size: usize,
+    options: *const JxlDecodeOptions,
+    buffer: *mut u8,
+    buffer_size: usize,
+    out_info: *mut JxlBasicInfoRaw,
 ) -> JxlStatus {
-    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
-
-    let Some(raw) = (unsafe { profile.as_ref() }) else {
-        set_last_error("Null profile pointer");
+    if data.is_null() || size == 0 {
+        set_last_error("Null or empty input data");
         return JxlStatus::InvalidArgument;
-    };
+    }
 
-    // Convert raw to upstream profile
-    let icc_slice = if raw.Tag == JxlColorProfileTag::Icc && raw.IccLength > 0 {
-        if icc_data.is_null() {
-            set_last_error("ICC profile specified but icc_data is null");
-            return JxlStatus::InvalidArgument;
-        }
-        Some(unsafe { slice::from_raw_parts(icc_data, raw.IccLength) })
-    } else {
-        None
-    };
+    clear_last_error();
 
-    let upstream_profile = crate::conversions::convert_color_profile_to_upstream(raw, icc_slice);
+    let input = unsafe { slice::from_raw_parts(data, size) };
+    let options = unsafe { options.as_ref() };
 
-    // Set the profile on the decoder
-    let state = std::mem::replace(&mut inner.state, DecoderState::Processing);
+    with_one_shot_decoder(input, options, |handle| {
+        let status = advance_to_first_frame(handle);
+        if status != JxlStatus::Success {
+            return status;
+        }
 
-    match state {
-        DecoderState::WithImageInfo(mut d) => {
-            match d.set_output_color_profile(upstream_profile) {
-                Ok(()) => {
-                    clear_last_error();
-                    inner.state = DecoderState::WithImageInfo(d);
-                    JxlStatus::Success
-                }
-                Err(e) => {
-                    inner.state = DecoderState::WithImageInfo(d);
-                    set_last_error(format!("Failed to set output color profile: {}", e));
-                    JxlStatus::Error
-                }
-            }
+        if !out_info.is_null() {
+            unsafe { jxl_decoder_get_basic_info(handle, out_info) };
         }
-        other => {
-            inner.state = other;
-            set_last_error("Must be in WithImageInfo state to set output color profile");
-            JxlStatus::InvalidState
+
+        let required_size = unsafe { jxl_decoder_get_buffer_size(handle) };
+        if buffer.is_null() || buffer_size < required_size {
+            set_last_error(format!(
+                "Buffer too small: {} bytes provided, {} required",
+                buffer_size, required_size
+            ));
+            return JxlStatus::BufferTooSmall;
         }
-    }
-}
 
-/// Frees a color profile handle.
-///
-/// # Safety
-/// The handle must have been created by a color profile function.
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_free(handle: *mut JxlColorProfileHandle) {
-    if !handle.is_null() {
-        unsafe { drop(Box::from_raw(handle as *mut ColorProfileHandle)) };
-    }
+        read_first_frame(handle, buffer, buffer_size)
+    })
 }
 
-/// Clones a color profile handle.
+/// Decodes the first frame into crate-allocated memory.
+///
+/// Like `jxl_decode_first_frame`, but the output buffer is allocated by the
+/// library, so callers don't need to query the size first. On success the
+/// caller owns the buffer and must release it with `jxl_free_buffer`.
+///
+/// # Arguments
+/// * `data` - The complete JXL file contents.
+/// * `size` - Size of `data` in bytes.
+/// * `options` - Decoder options, or null to use defaults.
+/// * `out_ptr` - Receives a pointer to the decoded pixels.
+/// * `out_len` - Receives the length of the pixel buffer in bytes.
+/// * `out_info` - Output for the basic image info, or null if not needed.
 ///
 /// # Returns
-/// A new handle that must be freed with `jxl_color_profile_free`, or null on failure.
+/// - `Success` on success. `*out_ptr` and `*out_len` describe the buffer.
+/// - `InvalidArgument` if `data` is empty or an output pointer is null.
+/// - `NeedMoreInput` if `data` is truncated.
+/// - `Error` on decode failure. Call `jxl_get_last_error` for details.
+///
+/// On failure `*out_ptr` is set to null and `*out_len` to 0.
 ///
 /// # Safety
-/// The handle must be valid.
+/// - `data` must be valid for reads of `size` bytes.
+/// - If `options` is not null, it must point to a valid `JxlDecodeOptions` struct.
+/// - `out_ptr` and `out_len` must be valid for writes.
+/// - If `out_info` is not null, it must point to a writable `JxlBasicInfoRaw`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_clone(
-    handle: *const JxlColorProfileHandle,
-) -> *mut JxlColorProfileHandle {
-    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
-        return std::ptr::null_mut();
-    };
+pub unsafe extern "C" fn jxl_decode_alloc(
+    data: *const u8,
+    size: usize,
+    options: *const JxlDecodeOptions,
+    out_ptr: *mut *mut u8,
+    out_len: *mut usize,
+    out_info: *mut JxlBasicInfoRaw,
+) -> JxlStatus {
+    if out_ptr.is_null() || out_len.is_null() {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    }
 
-    create_profile_handle(inner.profile.clone())
+    unsafe {
+        *out_ptr = std::ptr::null_mut();
+        *out_len = 0;
+    }
+
+    if data.is_null() || size == 0 {
+        set_last_error("Null or empty input data");
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+
+    let input = unsafe { slice::from_raw_parts(data, size) };
+    let options = unsafe { options.as_ref() };
+
+    with_one_shot_decoder(input, options, |handle| {
+        let status = advance_to_first_frame(handle);
+        if status != JxlStatus::Success {
+            return status;
+        }
+
+        if !out_info.is_null() {
+            unsafe { jxl_decoder_get_basic_info(handle, out_info) };
+        }
+
+        let required_size = unsafe { jxl_decoder_get_buffer_size(handle) };
+        let mut pixels = vec![0u8; required_size].into_boxed_slice();
+
+        let status = read_first_frame(handle, pixels.as_mut_ptr(), pixels.len());
+        if status == JxlStatus::Success {
+            let len = pixels.len();
+            unsafe {
+                *out_ptr = Box::into_raw(pixels) as *mut u8;
+                *out_len = len;
+            }
+        }
+        status
+    })
 }
 
-/// Attempts to get ICC profile data from a color profile.
+/// Decodes every frame of an animation in a single call, passing each to
+/// `frame_cb`.
 ///
-/// Returns true if ICC data is available (either native or converted).
+/// A "play this animation" primitive for media players: frames are decoded in
+/// order into an internal buffer, and `frame_cb` is called once per frame
+/// with its index, pixels, size and duration in milliseconds, plus
+/// `user_data`. Returning false from `frame_cb` stops decoding. Still images
+/// produce a single frame with a duration of 0; with `PreferPreview` the
+/// preview comes first, as frame 0.
+///
+/// The pixels follow `options.PixelFormat` with tightly packed rows
+/// (`options.RowAlignment` is ignored), and are only valid during the
+/// callback. With `ContinueOnFrameError`, a frame that fails to decode is
+/// skipped without a callback, and its index is not reused.
 ///
 /// # Arguments
-/// * `handle` - The color profile handle.
-/// * `data_out` - Output pointer for ICC data.
-/// * `length_out` - Output for ICC data length.
+/// * `data` - The complete JXL file contents.
+/// * `size` - Size of `data` in bytes.
+/// * `options` - Decoder options, or null to use defaults.
+/// * `frame_cb` - Called with each decoded frame.
+/// * `user_data` - Passed through to `frame_cb`.
+///
+/// # Returns
+/// - `Success` once all frames were delivered, or `frame_cb` returned false.
+/// - `InvalidArgument` if `data` is empty or `frame_cb` is null.
+/// - `NeedMoreInput` if `data` is truncated. Frames before the cut have been
+///   delivered.
+/// - `Error` on decode failure. Call `jxl_get_last_error` for details.
 ///
 /// # Safety
-/// - `handle` must be valid.
-/// - `data_out` and `length_out` must be writable.
+/// - `data` must be valid for reads of `size` bytes.
+/// - If `options` is not null, it must point to a valid `JxlDecodeOptions` struct.
+/// - `frame_cb` must be safe to call with `user_data`, and must not keep the
+///   pixel pointer after returning.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_try_as_icc(
-    handle: *mut JxlColorProfileHandle,
-    data_out: *mut *const u8,
-    length_out: *mut usize,
-) -> bool {
-    let Some(inner) = (unsafe { (handle as *mut ColorProfileHandle).as_mut() }) else {
-        return false;
+pub unsafe extern "C" fn jxl_decode_animation(
+    data: *const u8,
+    size: usize,
+    options: *const JxlDecodeOptions,
+    frame_cb: Option<
+        unsafe extern "C" fn(
+            frame_index: u32,
+            pixels: *const u8,
+            width: u32,
+            height: u32,
+            duration_ms: f32,
+            user_data: *mut c_void,
+        ) -> bool,
+    >,
+    user_data: *mut c_void,
+) -> JxlStatus {
+    let Some(frame_cb) = frame_cb else {
+        set_last_error("Null frame callback");
+        return JxlStatus::InvalidArgument;
     };
 
-    // Try to get ICC data
-    match inner.profile.try_as_icc() {
-        Some(cow) => {
-            // Cache the ICC data if it was generated
-            if inner.icc_cache.is_none() {
-                inner.icc_cache = Some(cow.into_owned());
-            }
+    if data.is_null() || size == 0 {
+        set_last_error("Null or empty input data");
+        return JxlStatus::InvalidArgument;
+    }
 
-            if let Some(ref data) = inner.icc_cache {
-                if let Some(out) = unsafe { data_out.as_mut() } {
-                    *out = data.as_ptr();
+    clear_last_error();
+
+    let input = unsafe { slice::from_raw_parts(data, size) };
+    let options = JxlDecodeOptions {
+        RowAlignment: 0,
+        ..unsafe { options.as_ref() }.cloned().unwrap_or_default()
+    };
+
+    with_one_shot_decoder(input, Some(&options), |handle| {
+        let mut pixels = Vec::new();
+        let mut header = JxlFrameHeader::default();
+        let mut frame_index = 0;
+        loop {
+            match unsafe { jxl_decoder_process(handle) } {
+                JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::FrameComplete => {}
+                JxlDecoderEvent::HaveFrameHeader => {
+                    unsafe { jxl_decoder_get_frame_header(handle, &mut header) };
                 }
-                if let Some(out) = unsafe { length_out.as_mut() } {
-                    *out = data.len();
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    pixels.resize(unsafe { jxl_decoder_get_buffer_size(handle) }, 0);
+                    let event = unsafe {
+                        jxl_decoder_read_pixels(handle, pixels.as_mut_ptr(), pixels.len())
+                    };
+                    match event {
+                        JxlDecoderEvent::FrameComplete => {}
+                        JxlDecoderEvent::FrameError => {
+                            frame_index += 1;
+                            continue;
+                        }
+                        JxlDecoderEvent::NeedMoreInput => {
+                            set_last_error(
+                                "Incomplete input - one-shot decoding requires the complete file",
+                            );
+                            return JxlStatus::NeedMoreInput;
+                        }
+                        _ => return last_error_status(),
+                    }
+                    let keep_going = unsafe {
+                        frame_cb(
+                            frame_index,
+                            pixels.as_ptr(),
+                            header.FrameWidth,
+                            header.FrameHeight,
+                            header.DurationMs,
+                            user_data,
+                        )
+                    };
+                    if !keep_going {
+                        return JxlStatus::Success;
+                    }
+                    frame_index += 1;
                 }
-                true
-            } else {
-                false
+                JxlDecoderEvent::NeedMoreInput => {
+                    set_last_error(
+                        "Incomplete input - one-shot decoding requires the complete file",
+                    );
+                    return JxlStatus::NeedMoreInput;
+                }
+                JxlDecoderEvent::Complete => return JxlStatus::Success,
+                JxlDecoderEvent::Error | JxlDecoderEvent::FrameError => return last_error_status(),
             }
         }
-        None => false,
-    }
+    })
 }
 
-/// Gets the number of color channels for a profile.
+/// Frees a buffer returned by `jxl_decode_alloc`, `jxl_decode_rgba8_srgb`,
+/// `jxl_thumbnail`, `jxl_encode_lossless_rgba8` or `jxl_brotli_decompress`.
 ///
-/// # Returns
-/// 1 for grayscale, 3 for RGB, 4 for CMYK.
+/// # Safety
+/// `ptr` and `len` must be exactly the values returned by the allocating call,
+/// and the buffer must not be freed twice. Passing a null `ptr` is a no-op.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_channels(
-    handle: *const JxlColorProfileHandle,
-) -> u32 {
-    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
-        return 0;
-    };
+pub unsafe extern "C" fn jxl_free_buffer(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        unsafe { drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len))) };
+    }
+}
 
-    inner.profile.channels() as u32
+/// Switches the output color profile to sRGB, keeping grayscale images gray.
+fn set_srgb_output(handle: *mut NativeDecoderHandle) -> JxlStatus {
+    unsafe { jxl_decoder_set_output_named(handle, JxlNamedColorSpace::Srgb) }
 }
 
-/// Checks if a profile represents a CMYK color space.
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_is_cmyk(
-    handle: *const JxlColorProfileHandle,
-) -> bool {
-    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
-        return false;
+/// Returns the size of a `width`x`height` image scaled to fit in a
+/// `max_dim`x`max_dim` box. Images are never upscaled, and each side is at
+/// least 1 pixel.
+fn thumbnail_size(width: u32, height: u32, max_dim: u32) -> (u32, u32) {
+    let longest = width.max(height);
+    if longest <= max_dim {
+        return (width, height);
+    }
+
+    let scale = |side: u32| {
+        let scaled = (side as u64 * max_dim as u64 + longest as u64 / 2) / longest as u64;
+        (scaled as u32).max(1)
     };
+    (scale(width), scale(height))
+}
 
-    inner.profile.is_cmyk()
+/// Decodes an 8-bit sRGB-encoded value to linear light.
+fn srgb8_to_linear(v: u8) -> f32 {
+    let v = v as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
 }
 
-/// Checks if the decoder can output to this profile without a CMS.
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_can_output_to(
-    handle: *const JxlColorProfileHandle,
-) -> bool {
-    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
-        return false;
+/// Encodes a linear-light value in `[0, 1]` to 8-bit sRGB.
+fn linear_to_srgb8(v: f32) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let encoded = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
     };
-
-    inner.profile.can_output_to()
+    (encoded * 255.0).round() as u8
 }
 
-/// Checks if two profiles represent the same color encoding.
+/// Downscales straight-alpha sRGB RGBA8 pixels with a box filter.
+///
+/// Color is averaged in linear light and re-encoded to sRGB, so that fine
+/// detail such as text or dithering doesn't darken when it's averaged away.
+/// Color is also weighted by alpha so that fully transparent pixels don't
+/// bleed their (arbitrary) color into visible neighbors. Alpha itself is
+/// already linear and is averaged as-is.
+fn downscale_rgba8(src: &[u8], width: u32, height: u32, dst_w: u32, dst_h: u32) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let (dst_w, dst_h) = (dst_w as usize, dst_h as usize);
+    let mut dst = vec![0u8; dst_w * dst_h * 4];
+    let to_linear: [f32; 256] = std::array::from_fn(|v| srgb8_to_linear(v as u8));
+
+    for dy in 0..dst_h {
+        let y0 = dy * height / dst_h;
+        let y1 = ((dy + 1) * height / dst_h).max(y0 + 1);
+        for dx in 0..dst_w {
+            let x0 = dx * width / dst_w;
+            let x1 = ((dx + 1) * width / dst_w).max(x0 + 1);
+
+            let mut weighted = [0f32; 3];
+            let mut plain = [0f32; 3];
+            let mut alpha = 0u64;
+            for y in y0..y1 {
+                for px in src[(y * width + x0) * 4..(y * width + x1) * 4].chunks_exact(4) {
+                    let a = px[3] as u64;
+                    for c in 0..3 {
+                        let linear = to_linear[px[c] as usize];
+                        weighted[c] += linear * a as f32;
+                        plain[c] += linear;
+                    }
+                    alpha += a;
+                }
+            }
+
+            let count = ((y1 - y0) * (x1 - x0)) as u64;
+            let out = &mut dst[(dy * dst_w + dx) * 4..][..4];
+            for c in 0..3 {
+                out[c] = linear_to_srgb8(if alpha > 0 {
+                    weighted[c] / alpha as f32
+                } else {
+                    plain[c] / count as f32
+                });
+            }
+            out[3] = ((alpha + count / 2) / count) as u8;
+        }
+    }
+    dst
+}
+
+/// Decodes a small sRGB RGBA8 preview of an image in a single call.
+///
+/// Intended for file-manager thumbnailers. The first frame is decoded with
+/// default options (orientation applied), converted to sRGB, and box-filtered
+/// down to fit within `max_dim`x`max_dim` while preserving the aspect ratio.
+/// Averaging happens in linear light, so fine detail keeps its brightness.
+/// Images that already fit are returned at full size. The decoder has no
+/// DC-only mode, so the whole frame is decoded before downscaling; cost is
+/// that of a full first-frame decode.
+///
+/// On success the caller owns the buffer and must release it with
+/// `jxl_free_buffer`, passing `*out_len`.
+///
+/// # Arguments
+/// * `data` - The complete JXL file contents.
+/// * `size` - Size of `data` in bytes.
+/// * `max_dim` - Maximum width and height of the thumbnail, in pixels.
+/// * `out_ptr` - Receives a pointer to the RGBA8 pixels (tightly packed).
+/// * `out_w` - Receives the thumbnail width.
+/// * `out_h` - Receives the thumbnail height.
+/// * `out_len` - Receives the length of the pixel buffer in bytes.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidArgument` if `data` is empty, `max_dim` is 0, or an output
+///   pointer is null.
+/// - `NeedMoreInput` if `data` is truncated.
+/// - `Error` on decode failure, including images that need a CMS to reach
+///   sRGB in builds without one. Call `jxl_get_last_error` for details.
+///
+/// On failure `*out_ptr` is set to null and the other outputs to 0.
+///
+/// # Safety
+/// - `data` must be valid for reads of `size` bytes.
+/// - `out_ptr`, `out_w`, `out_h` and `out_len` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_thumbnail(
+    data: *const u8,
+    size: usize,
+    max_dim: u32,
+    out_ptr: *mut *mut u8,
+    out_w: *mut u32,
+    out_h: *mut u32,
+    out_len: *mut usize,
+) -> JxlStatus {
+    if out_ptr.is_null() || out_w.is_null() || out_h.is_null() || out_len.is_null() {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    }
+
+    unsafe {
+        *out_ptr = std::ptr::null_mut();
+        *out_w = 0;
+        *out_h = 0;
+        *out_len = 0;
+    }
+
+    if data.is_null() || size == 0 {
+        set_last_error("Null or empty input data");
+        return JxlStatus::InvalidArgument;
+    }
+
+    if max_dim == 0 {
+        set_last_error("max_dim must be greater than 0");
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+
+    let input = unsafe { slice::from_raw_parts(data, size) };
+    let (mut pixels, width, height) = match decode_rgba8_srgb(input, JxlDecodeOptions::default()) {
+        Ok(decoded) => decoded,
+        Err(status) => return status,
+    };
+
+    let (thumb_w, thumb_h) = thumbnail_size(width, height, max_dim);
+    if (thumb_w, thumb_h) != (width, height) {
+        pixels = downscale_rgba8(&pixels, width, height, thumb_w, thumb_h);
+    }
+
+    let pixels = pixels.into_boxed_slice();
+    let len = pixels.len();
+    unsafe {
+        *out_ptr = Box::into_raw(pixels) as *mut u8;
+        *out_w = thumb_w;
+        *out_h = thumb_h;
+        *out_len = len;
+    }
+    JxlStatus::Success
+}
+
+/// Decodes the first frame of complete JXL data to upright, tightly packed
+/// sRGB RGBA8, returning the pixels and their width and height.
+///
+/// `options` supplies everything but the output layout, which is forced. A
+/// `None` CMS is replaced by lcms2 (or the built-in CMS in builds without
+/// it), since most images need one to reach sRGB.
+fn decode_rgba8_srgb(
+    data: &[u8],
+    mut options: JxlDecodeOptions,
+) -> Result<(Vec<u8>, u32, u32), JxlStatus> {
+    options.PixelFormat = JxlPixelFormat {
+        DataFormat: JxlDataFormat::Uint8,
+        ColorType: JxlColorType::Rgba,
+        Endianness: JxlEndianness::Native,
+    };
+    options.RowAlignment = 0;
+    options.AdjustOrientation = true;
+    options.ScaleToFullRange = true;
+    if options.CmsType == JxlCmsType::None {
+        options.CmsType = if cfg!(feature = "cms-lcms2") {
+            JxlCmsType::Lcms2
+        } else {
+            JxlCmsType::Builtin
+        };
+    }
+
+    with_one_shot_decoder(data, Some(&options), |handle| {
+        match unsafe { jxl_decoder_process(handle) } {
+            JxlDecoderEvent::HaveBasicInfo => {}
+            JxlDecoderEvent::NeedMoreInput => {
+                set_last_error("Incomplete input - one-shot decoding requires the complete file");
+                return Err(JxlStatus::NeedMoreInput);
+            }
+            JxlDecoderEvent::Error => return Err(last_error_status()),
+            event => {
+                set_last_error(format!("Unexpected decoder event {:?}", event));
+                return Err(JxlStatus::Error);
+            }
+        }
+
+        let status = set_srgb_output(handle);
+        if status != JxlStatus::Success {
+            return Err(status);
+        }
+
+        let status = advance_to_first_frame(handle);
+        if status != JxlStatus::Success {
+            return Err(status);
+        }
+
+        let inner = get_decoder_ref!(handle, Err(JxlStatus::InvalidArgument));
+        let Some((width, height)) = inner.basic_info.as_ref().map(|i| (i.Width, i.Height)) else {
+            set_last_error("Basic info not available");
+            return Err(JxlStatus::Error);
+        };
+
+        let mut pixels = vec![0u8; unsafe { jxl_decoder_get_buffer_size(handle) }];
+        let status = read_first_frame(handle, pixels.as_mut_ptr(), pixels.len());
+        if status != JxlStatus::Success {
+            return Err(status);
+        }
+        Ok((pixels, width, height))
+    })
+}
+
+/// Decodes the first frame to sRGB RGBA8 in a single call.
+///
+/// A turnkey decode for game and UI loaders that upload textures: whatever
+/// the source color space, bit depth or channel layout, the output is 8-bit
+/// straight-alpha RGBA in sRGB (gray images are expanded), upright
+/// (orientation applied), with tightly packed rows. HDR images are tone
+/// mapped if `options.CmsType` selects a tone-mapping CMS.
+///
+/// On success the caller owns the buffer and must release it with
+/// `jxl_free_buffer`, passing `*out_len`.
+///
+/// # Arguments
+/// * `data` - The complete JXL file contents.
+/// * `size` - Size of `data` in bytes.
+/// * `options` - Decoder options, or null to use defaults. `PixelFormat`,
+///   `RowAlignment` and `AdjustOrientation` are ignored, and a `None` CMS
+///   is replaced by lcms2.
+/// * `out_ptr` - Receives a pointer to the RGBA8 pixels.
+/// * `out_w` - Receives the image width.
+/// * `out_h` - Receives the image height.
+/// * `out_len` - Receives the length of the pixel buffer in bytes.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidArgument` if `data` is empty or an output pointer is null.
+/// - `NeedMoreInput` if `data` is truncated.
+/// - `Error` on decode failure. Call `jxl_get_last_error` for details.
+///
+/// On failure `*out_ptr` is set to null and the other outputs to 0.
+///
+/// # Safety
+/// - `data` must be valid for reads of `size` bytes.
+/// - If `options` is not null, it must point to a valid `JxlDecodeOptions` struct.
+/// - `out_ptr`, `out_w`, `out_h` and `out_len` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decode_rgba8_srgb(
+    data: *const u8,
+    size: usize,
+    options: *const JxlDecodeOptions,
+    out_ptr: *mut *mut u8,
+    out_w: *mut u32,
+    out_h: *mut u32,
+    out_len: *mut usize,
+) -> JxlStatus {
+    if out_ptr.is_null() || out_w.is_null() || out_h.is_null() || out_len.is_null() {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    }
+
+    unsafe {
+        *out_ptr = std::ptr::null_mut();
+        *out_w = 0;
+        *out_h = 0;
+        *out_len = 0;
+    }
+
+    if data.is_null() || size == 0 {
+        set_last_error("Null or empty input data");
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+
+    let input = unsafe { slice::from_raw_parts(data, size) };
+    let options = unsafe { options.as_ref() }.cloned().unwrap_or_default();
+    let (pixels, width, height) = match decode_rgba8_srgb(input, options) {
+        Ok(decoded) => decoded,
+        Err(status) => return status,
+    };
+
+    let pixels = pixels.into_boxed_slice();
+    let len = pixels.len();
+    unsafe {
+        *out_ptr = Box::into_raw(pixels) as *mut u8;
+        *out_w = width;
+        *out_h = height;
+        *out_len = len;
+    }
+    JxlStatus::Success
+}
+
+// ============================================================================
+// Encoding
+// ============================================================================
+
+/// Encodes 8-bit RGBA pixels as a lossless JPEG XL file.
+///
+/// The pixels are written as sRGB with an 8-bit alpha channel in a single
+/// modular frame, and decode back bit-exactly. Compression is minimal; this
+/// is meant for re-saving decoded or edited pixels, not as a general encoder.
+///
+/// On success the caller owns the buffer and must release it with
+/// `jxl_free_buffer`, passing `*out_len`.
+///
+/// # Arguments
+/// * `data` - RGBA8 pixels, tightly packed, `width * height * 4` bytes.
+/// * `width` - Image width in pixels.
+/// * `height` - Image height in pixels.
+/// * `out_ptr` - Receives a pointer to the encoded JXL file.
+/// * `out_len` - Receives the length of the encoded file in bytes.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidArgument` if `data` or an output pointer is null, or a
+///   dimension is 0 or too large.
+/// - `Error` if the `encode` feature is not compiled in.
+///
+/// On failure `*out_ptr` is set to null and `*out_len` to 0.
+///
+/// # Safety
+/// - `data` must be valid for reads of `width * height * 4` bytes.
+/// - `out_ptr` and `out_len` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_encode_lossless_rgba8(
+    data: *const u8,
+    width: u32,
+    height: u32,
+    out_ptr: *mut *mut u8,
+    out_len: *mut usize,
+) -> JxlStatus {
+    if out_ptr.is_null() || out_len.is_null() {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    }
+
+    unsafe {
+        *out_ptr = std::ptr::null_mut();
+        *out_len = 0;
+    }
+
+    if data.is_null() {
+        set_last_error("Null input data");
+        return JxlStatus::InvalidArgument;
+    }
+
+    // The codestream stores each dimension in at most 30 bits.
+    if width == 0 || height == 0 || width > 1 << 30 || height > 1 << 30 {
+        set_last_error(format!("Invalid image dimensions {}x{}", width, height));
+        return JxlStatus::InvalidArgument;
+    }
+
+    #[cfg(feature = "encode")]
+    {
+        let Some(size) = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|n| n.checked_mul(4))
+        else {
+            set_last_error("Image too large");
+            return JxlStatus::InvalidArgument;
+        };
+
+        clear_last_error();
+        let pixels = unsafe { slice::from_raw_parts(data, size) };
+        let encoded = crate::encoder::encode_lossless_rgba8(pixels, width, height);
+        let encoded = encoded.into_boxed_slice();
+        let len = encoded.len();
+        unsafe {
+            *out_ptr = Box::into_raw(encoded) as *mut u8;
+            *out_len = len;
+        }
+        JxlStatus::Success
+    }
+
+    #[cfg(not(feature = "encode"))]
+    {
+        set_last_error("encode support not compiled in");
+        JxlStatus::Error
+    }
+}
+
+// ============================================================================
+// Color Profiles
+// ============================================================================
+
+/// Internal structure to hold a cloned color profile for FFI access.
+struct ColorProfileHandle {
+    profile: JxlColorProfile,
+    /// Cached ICC data (if profile is ICC type)
+    icc_cache: Option<Vec<u8>>,
+    /// Cached string representation, computed on first request
+    string_cache: OnceLock<String>,
+}
+
+/// Creates a new color profile handle from an existing profile.
+/// The handle must be freed with `jxl_color_profile_free`.
+fn create_profile_handle(profile: JxlColorProfile) -> *mut JxlColorProfileHandle {
+    let icc_cache = match &profile {
+        JxlColorProfile::Icc(data) => Some(data.clone()),
+        JxlColorProfile::Simple(_) => None,
+    };
+    let handle = Box::new(ColorProfileHandle {
+        profile,
+        icc_cache,
+        string_cache: OnceLock::new(),
+    });
+    Box::into_raw(handle) as *mut JxlColorProfileHandle
+}
+
+/// Gets the embedded color profile from the image.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `profile_out` - Output for the profile raw data.
+/// * `icc_data_out` - Output pointer for ICC data (only set if profile is ICC type).
+/// * `handle_out` - Output for the profile handle (for calling helper methods).
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `profile_out` must point to a writable `JxlColorProfileRaw`.
+/// - `icc_data_out` must point to a writable pointer.
+/// - `handle_out` must point to a writable pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_embedded_color_profile(
+    decoder: *const NativeDecoderHandle,
+    profile_out: *mut JxlColorProfileRaw,
+    icc_data_out: *mut *const u8,
+    handle_out: *mut *mut JxlColorProfileHandle,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+
+    let profile = match &inner.state {
+        DecoderState::WithImageInfo(d) => d.embedded_color_profile(),
+        DecoderState::WithFrameInfo(_) => {
+            set_last_error("Color profile not accessible in WithFrameInfo state");
+            return JxlStatus::InvalidState;
+        }
+        _ => {
+            set_last_error("Basic info not yet available - call jxl_decoder_process first");
+            return JxlStatus::InvalidState;
+        }
+    };
+
+    clear_last_error();
+
+    // Convert profile to raw format
+    let (raw, _icc_data) = convert_color_profile(profile);
+
+    // Create a handle with cloned profile
+    let handle = create_profile_handle(profile.clone());
+
+    // Write outputs
+    if let Some(out) = unsafe { profile_out.as_mut() } {
+        *out = raw;
+    }
+
+    if let Some(out) = unsafe { icc_data_out.as_mut() } {
+        // Get ICC data from handle's cache
+        let handle_ref = unsafe { &*(handle as *const ColorProfileHandle) };
+        *out = handle_ref.icc_cache.as_ref()
+            .map(|v| v.as_ptr())
+            .unwrap_or(std::ptr::null());
+    }
+
+    if let Some(out) = unsafe { handle_out.as_mut() } {
+        *out = handle;
+    } else {
+        // If no handle output, free it
+        unsafe { drop(Box::from_raw(handle as *mut ColorProfileHandle)) };
+    }
+
+    JxlStatus::Success
+}
+
+/// Gets the simple color encoding of the embedded color profile.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`. Unlike
+/// `jxl_decoder_get_embedded_color_profile`, no profile handle is created.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `encoding_out` - Output for the encoding.
+///
+/// # Returns
+/// `Success` with `encoding_out` filled, or `IccProfile` if the image embeds an
+/// ICC profile instead; use `jxl_decoder_get_embedded_color_profile` to get
+/// its bytes.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `encoding_out` must point to a writable `JxlColorEncodingRaw`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_embedded_encoding(
+    decoder: *const NativeDecoderHandle,
+    encoding_out: *mut JxlColorEncodingRaw,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+
+    let Some(out) = (unsafe { encoding_out.as_mut() }) else {
+        set_last_error("Null encoding output pointer");
+        return JxlStatus::InvalidArgument;
+    };
+
+    let profile = match &inner.state {
+        DecoderState::WithImageInfo(d) => d.embedded_color_profile(),
+        DecoderState::WithFrameInfo(_) => {
+            set_last_error("Color profile not accessible in WithFrameInfo state");
+            return JxlStatus::InvalidState;
+        }
+        _ => {
+            set_last_error("Basic info not yet available - call jxl_decoder_process first");
+            return JxlStatus::InvalidState;
+        }
+    };
+
+    match profile {
+        JxlColorProfile::Simple(encoding) => {
+            clear_last_error();
+            *out = convert_color_encoding(encoding);
+            JxlStatus::Success
+        }
+        JxlColorProfile::Icc(_) => {
+            set_last_error("Embedded color profile is an ICC profile");
+            JxlStatus::IccProfile
+        }
+    }
+}
+
+/// Gets the transfer function of the embedded color profile.
+///
+/// A cheap way to tell whether an image is HDR, and so whether it needs tone
+/// mapping: PQ and HLG are HDR transfer functions. No profile handle or
+/// encoding is built.
+///
+/// # Returns
+/// True with `out` filled for a simple RGB or grayscale profile. False for an
+/// ICC or XYB profile, and before `jxl_decoder_process` returns
+/// `HaveBasicInfo` or while a frame is being decoded.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `out` must point to a writable `JxlTransferFunctionRaw`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_embedded_transfer_function(
+    decoder: *const NativeDecoderHandle,
+    out: *mut JxlTransferFunctionRaw,
+) -> bool {
+    let inner = get_decoder_ref_silent!(decoder, false);
+    let Some(out) = (unsafe { out.as_mut() }) else {
+        return false;
+    };
+
+    let DecoderState::WithImageInfo(d) = &inner.state else {
+        return false;
+    };
+    match d.embedded_color_profile() {
+        JxlColorProfile::Simple(
+            jxl::api::JxlColorEncoding::RgbColorSpace {
+                transfer_function, ..
+            }
+            | jxl::api::JxlColorEncoding::GrayscaleColorSpace {
+                transfer_function, ..
+            },
+        ) => {
+            *out = convert_transfer_function(transfer_function);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Gets the current output color profile.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+///
+/// # Safety
+/// Same as `jxl_decoder_get_embedded_color_profile`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_output_color_profile(
+    decoder: *const NativeDecoderHandle,
+    profile_out: *mut JxlColorProfileRaw,
+    icc_data_out: *mut *const u8,
+    handle_out: *mut *mut JxlColorProfileHandle,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+
+    let profile = match &inner.state {
+        DecoderState::WithImageInfo(d) => d.output_color_profile(),
+        DecoderState::WithFrameInfo(_) => {
+            set_last_error("Color profile not accessible in WithFrameInfo state");
+            return JxlStatus::InvalidState;
+        }
+        _ => {
+            set_last_error("Basic info not yet available - call jxl_decoder_process first");
+            return JxlStatus::InvalidState;
+        }
+    };
+
+    clear_last_error();
+
+    let (raw, _icc_data) = convert_color_profile(profile);
+    let handle = create_profile_handle(profile.clone());
+
+    if let Some(out) = unsafe { profile_out.as_mut() } {
+        *out = raw;
+    }
+
+    if let Some(out) = unsafe { icc_data_out.as_mut() } {
+        let handle_ref = unsafe { &*(handle as *const ColorProfileHandle) };
+        *out = handle_ref.icc_cache.as_ref()
+            .map(|v| v.as_ptr())
+            .unwrap_or(std::ptr::null());
+    }
+
+    if let Some(out) = unsafe { handle_out.as_mut() } {
+        *out = handle;
+    } else {
+        unsafe { drop(Box::from_raw(handle as *mut ColorProfileHandle)) };
+    }
+
+    JxlStatus::Success
+}
+
+/// Returns true if the decoder outputs in the embedded color encoding.
+fn output_matches_embedded(decoder: &UpstreamDecoder<jxl::api::states::WithImageInfo>) -> bool {
+    decoder
+        .output_color_profile()
+        .same_color_encoding(decoder.embedded_color_profile())
+}
+
+/// Checks whether the current image is output in its embedded color profile.
+///
+/// This holds by default for images stored in their original color space,
+/// and after setting an output profile with the same color encoding as the
+/// embedded one (see `jxl_color_profile_same_color_encoding`). Such images
+/// are then decoded as-is, without any color conversion or CMS, which suits
+/// callers that do their own color management. XYB-encoded images are always
+/// converted from XYB, but need no CMS when this returns true for a
+/// non-ICC embedded profile.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`. Returns
+/// false if no image header has been read.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_output_matches_embedded(
+    decoder: *const NativeDecoderHandle,
+) -> bool {
+    let inner = get_decoder_ref_silent!(decoder, false);
+    inner.basic_info.is_some() && inner.output_matches_embedded
+}
+
+/// Checks whether the current image is stored in the XYB color space.
+///
+/// XYB images are lossy-encoded in JPEG XL's internal perceptual space and
+/// reconstructed into the embedded (or requested output) color space while
+/// decoding, rather than stored in their original profile. This is the
+/// image header's `xyb_encoded` flag, the inverse of `UsesOriginalProfile`:
+/// the embedded color encoding describes the original space either way.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`. Returns
+/// false if no image header has been read.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_is_xyb(decoder: *const NativeDecoderHandle) -> bool {
+    let inner = get_decoder_ref_silent!(decoder, false);
+    inner
+        .basic_info
+        .as_ref()
+        .is_some_and(|info| !info.UsesOriginalProfile)
+}
+
+/// Gets the channel count of the embedded color profile.
+///
+/// A cheaper alternative to `jxl_decoder_get_embedded_color_profile` followed
+/// by `jxl_color_profile_channels` when only the count is needed, e.g. to
+/// size buffers. Unlike that, it also works while a frame is being decoded.
+///
+/// # Returns
+/// 1 for grayscale, 3 for RGB, 4 for CMYK, or 0 before `jxl_decoder_process`
+/// returns `HaveBasicInfo`.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_embedded_profile_channels(
+    decoder: *const NativeDecoderHandle,
+) -> u32 {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+    if inner.basic_info.is_none() {
+        return 0;
+    }
+    inner.embedded_channels
+}
+
+/// Converts a caller-supplied profile to the upstream type.
+///
+/// Returns `None` and sets the last error if an ICC profile has no data pointer.
+///
+/// # Safety
+/// If `raw` is an ICC profile, `icc_data` must be null or point to
+/// `raw.IccLength` bytes.
+unsafe fn upstream_profile_from_raw(
+    raw: &JxlColorProfileRaw,
+    icc_data: *const u8,
+) -> Option<JxlColorProfile> {
+    let icc_slice = if raw.Tag == JxlColorProfileTag::Icc && raw.IccLength > 0 {
+        if icc_data.is_null() {
+            set_last_error("ICC profile specified but icc_data is null");
+            return None;
+        }
+        Some(unsafe { slice::from_raw_parts(icc_data, raw.IccLength) })
+    } else {
+        None
+    };
+
+    let profile = crate::conversions::convert_color_profile_to_upstream(raw, icc_slice);
+    Some(profile)
+}
+
+/// Sets `profile` as the decoder's output color profile.
+///
+/// A profile with the embedded color encoding is replaced by the embedded
+/// profile itself, so images stored in their original color space can be
+/// output as-is without going through the CMS. Fails if the conversion needs a CMS that `options` doesn't
+/// provide, since the decoder would otherwise silently skip it.
+fn apply_output_color_profile(
+    options: &JxlDecodeOptions,
+    decoder: &mut UpstreamDecoder<jxl::api::states::WithImageInfo>,
+    profile: JxlColorProfile,
+) -> Result<(), String> {
+    let embedded = decoder.embedded_color_profile();
+    let profile = if embedded.same_color_encoding(&profile) {
+        embedded.clone()
+    } else {
+        profile
+    };
+
+    if let Some(reason) = missing_cms_reason(options)
+        && output_needs_cms(
+            decoder.basic_info().uses_original_profile,
+            decoder.embedded_color_profile(),
+            &profile,
+        )
+    {
+        return Err(format!("Output color profile requires a CMS, but {reason}"));
+    }
+
+    decoder
+        .set_output_color_profile(profile)
+        .map_err(|e| format!("Failed to set output color profile: {}", e))
+}
+
+/// Sets the output color profile for decoding.
+///
+/// Must be called after `HaveBasicInfo` and before decoding pixels. The
+/// profile is kept across `jxl_decoder_reset_keep_config`. To choose the
+/// output profile before the header is read, use
+/// `jxl_decoder_set_desired_output_color_profile`.
+///
+/// The profile must be grayscale for grayscale images and color (RGB or
+/// CMYK) otherwise; a mismatch returns `InvalidArgument`.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `profile` - The color profile raw data.
+/// * `icc_data` - ICC data pointer (required if profile tag is Icc).
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `profile` must point to a valid `JxlColorProfileRaw`.
+/// - If profile is ICC, `icc_data` must point to `profile.IccLength` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_set_output_color_profile(
+    decoder: *mut NativeDecoderHandle,
+    profile: *const JxlColorProfileRaw,
+    icc_data: *const u8,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    let Some(raw) = (unsafe { profile.as_ref() }) else {
+        set_last_error("Null profile pointer");
+        return JxlStatus::InvalidArgument;
+    };
+
+    let Some(upstream_profile) = (unsafe { upstream_profile_from_raw(raw, icc_data) }) else {
+        return JxlStatus::InvalidArgument;
+    };
+
+    // Set the profile on the decoder
+    let state = std::mem::replace(&mut inner.state, DecoderState::Processing);
+
+    match state {
+        DecoderState::WithImageInfo(d)
+            if (inner.embedded_channels == 1) != (upstream_profile.channels() == 1) =>
+        {
+            inner.state = DecoderState::WithImageInfo(d);
+            set_last_error(format!(
+                "Output color profile has {} channel(s) but the image has {}; \
+                 grayscale and color profiles can't be converted into each other",
+                upstream_profile.channels(),
+                inner.embedded_channels
+            ));
+            JxlStatus::InvalidArgument
+        }
+        DecoderState::WithImageInfo(mut d) => {
+            let result =
+                apply_output_color_profile(&inner.options, &mut d, upstream_profile.clone());
+            inner.output_matches_embedded = output_matches_embedded(&d);
+            inner.state = DecoderState::WithImageInfo(d);
+            match result {
+                Ok(()) => {
+                    clear_last_error();
+                    inner.output_profile = Some(upstream_profile);
+                    JxlStatus::Success
+                }
+                Err(e) => {
+                    set_last_error(e);
+                    JxlStatus::Error
+                }
+            }
+        }
+        other => {
+            inner.state = other;
+            set_last_error("Must be in WithImageInfo state to set output color profile");
+            JxlStatus::InvalidState
+        }
+    }
+}
+
+/// Registers an output color profile to apply to every image automatically.
+///
+/// Unlike `jxl_decoder_set_output_color_profile`, this can be called in any
+/// state, including before the first `jxl_decoder_process`. The profile is
+/// applied each time an image header is read, before `HaveBasicInfo` is
+/// returned, so pipelines that always convert (e.g. to sRGB) need no
+/// round-trip per image. It persists across resets and new input; pass a null
+/// `profile` to clear it.
+///
+/// A profile set with `jxl_decoder_set_output_color_profile` still overrides
+/// it for the current image. If the profile cannot be applied to an image (for
+/// example, it needs a CMS that is not configured), `jxl_decoder_process`
+/// returns `Error`.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `profile` - The color profile raw data, or null to clear.
+/// * `icc_data` - ICC data pointer (required if profile tag is Icc).
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `profile` must be null or point to a valid `JxlColorProfileRaw`.
+/// - If profile is ICC, `icc_data` must point to `profile.IccLength` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_set_desired_output_color_profile(
+    decoder: *mut NativeDecoderHandle,
+    profile: *const JxlColorProfileRaw,
+    icc_data: *const u8,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    let desired = match unsafe { profile.as_ref() } {
+        Some(raw) => match unsafe { upstream_profile_from_raw(raw, icc_data) } {
+            Some(p) => Some(p),
+            None => return JxlStatus::InvalidArgument,
+        },
+        None => None,
+    };
+
+    clear_last_error();
+    inner.desired_output_profile = desired;
+
+    JxlStatus::Success
+}
+
+/// Builds the encoding of `named`. Grayscale encodings keep its white point
+/// and transfer function.
+fn named_color_encoding(named: JxlNamedColorSpace, grayscale: bool) -> jxl::api::JxlColorEncoding {
+    use jxl::api::{JxlPrimaries, JxlTransferFunction};
+
+    let (primaries, transfer_function) = match named {
+        JxlNamedColorSpace::Srgb => return jxl::api::JxlColorEncoding::srgb(grayscale),
+        JxlNamedColorSpace::LinearSrgb => {
+            return jxl::api::JxlColorEncoding::linear_srgb(grayscale);
+        }
+        JxlNamedColorSpace::DisplayP3 => (JxlPrimaries::P3, JxlTransferFunction::SRGB),
+        JxlNamedColorSpace::Rec2020Pq => (JxlPrimaries::BT2100, JxlTransferFunction::PQ),
+        JxlNamedColorSpace::Rec2020Hlg => (JxlPrimaries::BT2100, JxlTransferFunction::HLG),
+        JxlNamedColorSpace::AdobeRgb => (
+            JxlPrimaries::Chromaticities {
+                rx: 0.64,
+                ry: 0.33,
+                gx: 0.21,
+                gy: 0.71,
+                bx: 0.15,
+                by: 0.06,
+            },
+            // Stored as the encoding exponent, like jxl_color_encoding_gamma
+            JxlTransferFunction::Gamma(256.0 / 563.0),
+        ),
+    };
+    if grayscale {
+        jxl::api::JxlColorEncoding::GrayscaleColorSpace {
+            white_point: jxl::api::JxlWhitePoint::D65,
+            transfer_function,
+            rendering_intent: jxl::headers::color_encoding::RenderingIntent::Relative,
+        }
+    } else {
+        standard_rgb_encoding(primaries, transfer_function)
+    }
+}
+
+/// Sets the output color profile to a common color space.
+///
+/// A shorthand for `jxl_decoder_set_output_color_profile` that builds the
+/// encoding internally, with the same requirements: it must be called after
+/// `HaveBasicInfo` and before decoding pixels, and conversions need a CMS.
+/// Grayscale images stay gray, with the color space's transfer function.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_set_output_named(
+    decoder: *mut NativeDecoderHandle,
+    named: JxlNamedColorSpace,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+    let grayscale = inner.basic_info.is_some() && inner.embedded_channels == 1;
+
+    let profile = JxlColorProfileRaw {
+        Tag: JxlColorProfileTag::Simple,
+        IccLength: 0,
+        Encoding: convert_color_encoding(&named_color_encoding(named, grayscale)),
+    };
+    unsafe { jxl_decoder_set_output_color_profile(decoder, &profile, std::ptr::null()) }
+}
+
+/// Soft-proofs output through the device described by `profile`.
+///
+/// The lcms2 CMS then converts to the output color profile by way of the
+/// proof device, so the output previews how the image would look there, e.g.
+/// on a printer. Pixels the device can't reproduce can be marked with
+/// `jxl_decoder_set_gamut_warning_color`. Pass a null `profile` to stop
+/// soft proofing.
+///
+/// Requires `CmsType` `Lcms2`, and only affects images that go through the
+/// CMS, i.e. whose output color profile differs from the embedded one. Must
+/// be called before the current image starts decoding: before the first
+/// `jxl_decoder_process`, or after a reset or rewind. The setting survives
+/// resets.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `profile` - The proof device's color profile, or null to clear.
+/// * `icc_data` - ICC data pointer (required if profile tag is Icc).
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `profile` must be null or point to a valid `JxlColorProfileRaw`.
+/// - If profile is ICC, `icc_data` must point to `profile.IccLength` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_set_proof_profile(
+    decoder: *mut NativeDecoderHandle,
+    profile: *const JxlColorProfileRaw,
+    icc_data: *const u8,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    let proof = match unsafe { profile.as_ref() } {
+        Some(raw) => match unsafe { upstream_profile_from_raw(raw, icc_data) } {
+            Some(p) => match p.try_as_icc() {
+                Some(icc) => Some(icc.into_owned()),
+                None => {
+                    set_last_error_with_status(
+                        JxlStatus::InvalidArgument,
+                        "Cannot create ICC for proof profile",
+                    );
+                    return JxlStatus::InvalidArgument;
+                }
+            },
+            None => return JxlStatus::InvalidArgument,
+        },
+        None => None,
+    };
+    if let Err(status) = inner.check_soft_proof_change() {
+        return status;
+    }
+
+    clear_last_error();
+    inner.proof_profile = proof;
+    inner.reset_state();
+
+    JxlStatus::Success
+}
+
+/// Sets the color that marks pixels outside the proof device's gamut.
+///
+/// Only has an effect while soft proofing with
+/// `jxl_decoder_set_proof_profile`. `color` holds one value per output
+/// channel, from 0.0 to 1.0 in the output color space; grayscale output uses
+/// the first. Pass null to leave out-of-gamut pixels unmarked, the default.
+/// Has the same timing requirements as `jxl_decoder_set_proof_profile`.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `color` must be null or point to 3 floats.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_set_gamut_warning_color(
+    decoder: *mut NativeDecoderHandle,
+    color: *const f32,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    if let Err(status) = inner.check_soft_proof_change() {
+        return status;
+    }
+
+    clear_last_error();
+    inner.gamut_warning = unsafe { color.cast::<[f32; 3]>().as_ref() }.copied();
+    inner.reset_state();
+
+    JxlStatus::Success
+}
+
+/// Frees a color profile handle.
+///
+/// # Safety
+/// The handle must have been created by a color profile function.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_free(handle: *mut JxlColorProfileHandle) {
+    if !handle.is_null() {
+        unsafe { drop(Box::from_raw(handle as *mut ColorProfileHandle)) };
+    }
+}
+
+/// Clones a color profile handle.
+///
+/// # Returns
+/// A new handle that must be freed with `jxl_color_profile_free`, or null on failure.
+///
+/// # Safety
+/// The handle must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_clone(
+    handle: *const JxlColorProfileHandle,
+) -> *mut JxlColorProfileHandle {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+
+    create_profile_handle(inner.profile.clone())
+}
+
+/// Attempts to get ICC profile data from a color profile.
+///
+/// Returns true if ICC data is available (either native or converted).
+///
+/// # Arguments
+/// * `handle` - The color profile handle.
+/// * `data_out` - Output pointer for ICC data.
+/// * `length_out` - Output for ICC data length.
+///
+/// # Safety
+/// - `handle` must be valid.
+/// - `data_out` and `length_out` must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_try_as_icc(
+    handle: *mut JxlColorProfileHandle,
+    data_out: *mut *const u8,
+    length_out: *mut usize,
+) -> bool {
+    let Some(inner) = (unsafe { (handle as *mut ColorProfileHandle).as_mut() }) else {
+        return false;
+    };
+
+    // Try to get ICC data
+    match inner.profile.try_as_icc() {
+        Some(cow) => {
+            // Cache the ICC data if it was generated
+            if inner.icc_cache.is_none() {
+                inner.icc_cache = Some(cow.into_owned());
+            }
+
+            if let Some(ref data) = inner.icc_cache {
+                if let Some(out) = unsafe { data_out.as_mut() } {
+                    *out = data.as_ptr();
+                }
+                if let Some(out) = unsafe { length_out.as_mut() } {
+                    *out = data.len();
+                }
+                true
+            } else {
+                false
+            }
+        }
+        None => false,
+    }
+}
+
+/// Returns the size in bytes of the profile's ICC data, without generating it
+/// when possible.
+///
+/// The result is exact for ICC profiles and for any profile whose ICC data has
+/// already been generated by `jxl_color_profile_try_as_icc`. For simple RGB and
+/// grayscale encodings with a matrix/curve profile, the size is estimated from
+/// the tag layout without allocating the profile. XYB, PQ and HLG RGB encodings
+/// need LUT-based tags, so their profile is generated and cached and the exact
+/// size is returned.
+///
+/// # Returns
+/// The ICC size in bytes, or 0 if no ICC data can be produced.
+///
+/// # Safety
+/// The handle must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_icc_size_hint(
+    handle: *mut JxlColorProfileHandle,
+) -> usize {
+    let Some(inner) = (unsafe { (handle as *mut ColorProfileHandle).as_mut() }) else {
+        return 0;
+    };
+
+    if let Some(ref data) = inner.icc_cache {
+        return data.len();
+    }
+
+    if let JxlColorProfile::Simple(encoding) = &inner.profile
+        && let Some(size) = estimate_simple_icc_size(encoding)
+    {
+        return size;
+    }
+
+    match inner.profile.try_as_icc() {
+        Some(cow) => {
+            let len = cow.len();
+            inner.icc_cache = Some(cow.into_owned());
+            len
+        }
+        None => 0,
+    }
+}
+
+/// Estimates the size of the ICC profile generated for a simple encoding by
+/// mirroring the generator's tag layout.
+///
+/// Returns `None` for encodings that are emitted with LUT-based tags.
+fn estimate_simple_icc_size(encoding: &jxl::api::JxlColorEncoding) -> Option<usize> {
+    use jxl::api::{JxlColorEncoding, JxlPrimaries, JxlTransferFunction, JxlWhitePoint};
+
+    const HEADER_SIZE: usize = 128;
+    const XYZ_TAG_SIZE: usize = 20;
+    const CHAD_TAG_SIZE: usize = 44;
+    const CICP_TAG_SIZE: usize = 12;
+    let mluc_size = |text: &str| (28 + 2 * text.len()).next_multiple_of(4);
+    let trc_size = |tf: &JxlTransferFunction| match tf {
+        JxlTransferFunction::Gamma(_) => 16,
+        JxlTransferFunction::PQ | JxlTransferFunction::HLG => 12 + 4 * 64,
+        _ => 32,
+    };
+
+    let mut tags_size =
+        mluc_size(&encoding.get_color_encoding_description()) + mluc_size("CC0") + XYZ_TAG_SIZE;
+    let tag_count = match encoding {
+        JxlColorEncoding::XYB { .. } => return None,
+        JxlColorEncoding::GrayscaleColorSpace {
+            transfer_function, ..
+        } => {
+            tags_size += trc_size(transfer_function);
+            4
+        }
+        JxlColorEncoding::RgbColorSpace {
+            white_point,
+            primaries,
+            transfer_function,
+            ..
+        } => {
+            if matches!(
+                transfer_function,
+                JxlTransferFunction::PQ | JxlTransferFunction::HLG
+            ) {
+                return None;
+            }
+            let has_cicp = !matches!(transfer_function, JxlTransferFunction::Gamma(_))
+                && matches!(
+                    (white_point, primaries),
+                    (JxlWhitePoint::D65, JxlPrimaries::SRGB)
+                        | (JxlWhitePoint::D65, JxlPrimaries::BT2100)
+                        | (JxlWhitePoint::D65, JxlPrimaries::P3)
+                        | (JxlWhitePoint::DCI, JxlPrimaries::P3)
+                );
+            tags_size += CHAD_TAG_SIZE + 3 * XYZ_TAG_SIZE + trc_size(transfer_function);
+            if has_cicp {
+                tags_size += CICP_TAG_SIZE;
+                11
+            } else {
+                10
+            }
+        }
+    };
+
+    Some(HEADER_SIZE + 4 + 12 * tag_count + tags_size)
+}
+
+/// Gets the number of color channels for a profile.
+///
+/// # Returns
+/// 1 for grayscale, 3 for RGB, 4 for CMYK.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_channels(
+    handle: *const JxlColorProfileHandle,
+) -> u32 {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return 0;
+    };
+
+    inner.profile.channels() as u32
+}
+
+/// Checks if a profile represents a CMYK color space.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_is_cmyk(
+    handle: *const JxlColorProfileHandle,
+) -> bool {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return false;
+    };
+
+    inner.profile.is_cmyk()
+}
+
+/// Checks if a profile describes HDR content.
+///
+/// True for simple profiles with a PQ or HLG transfer function, and for ICC
+/// profiles whose `cicp` tag signals PQ or HLG. Useful to decide whether a
+/// tone mapping CMS is needed at all.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_is_hdr(handle: *const JxlColorProfileHandle) -> bool {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return false;
+    };
+
+    crate::cms::is_hdr_profile(&inner.profile)
+}
+
+/// Checks if the decoder can output to this profile without a CMS.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_can_output_to(
+    handle: *const JxlColorProfileHandle,
+) -> bool {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return false;
+    };
+
+    inner.profile.can_output_to()
+}
+
+/// Checks if two profiles represent the same color encoding.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_same_color_encoding(
+    handle_a: *const JxlColorProfileHandle,
+    handle_b: *const JxlColorProfileHandle,
+) -> bool {
+    let (Some(a), Some(b)) = (
+        unsafe { (handle_a as *const ColorProfileHandle).as_ref() },
+        unsafe { (handle_b as *const ColorProfileHandle).as_ref() },
+    ) else {
+        return false;
+    };
+
+    a.profile.same_color_encoding(&b.profile)
+}
+
+/// Resolves a profile to a simple encoding, parsing ICC profiles that match a
+/// known color space.
+fn resolve_simple_encoding(profile: &JxlColorProfile) -> Option<jxl::api::JxlColorEncoding> {
+    match profile {
+        JxlColorProfile::Simple(encoding) => Some(encoding.clone()),
+        JxlColorProfile::Icc(data) => icc::to_simple_encoding(data),
+    }
+}
+
+/// Checks if a profile is equivalent to sRGB (RGB or grayscale).
+///
+/// Unlike `jxl_color_profile_same_color_encoding`, an embedded ICC profile is
+/// parsed and compared semantically, so an sRGB ICC profile matches the simple
+/// sRGB encoding.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_is_equivalent_srgb(
+    handle: *const JxlColorProfileHandle,
+) -> bool {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return false;
+    };
+
+    let Some(encoding) = resolve_simple_encoding(&inner.profile) else {
+        return false;
+    };
+    let grayscale = matches!(
+        encoding,
+        jxl::api::JxlColorEncoding::GrayscaleColorSpace { .. }
+    );
+    icc::same_encoding(&encoding, &jxl::api::JxlColorEncoding::srgb(grayscale))
+}
+
+/// Checks if two profiles describe the same color space, even if one is an
+/// ICC profile and the other a simple encoding.
+///
+/// ICC profiles are parsed into a simple encoding when they match a known
+/// color space; otherwise this falls back to `jxl_color_profile_same_color_encoding`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_is_equivalent(
+    handle_a: *const JxlColorProfileHandle,
+    handle_b: *const JxlColorProfileHandle,
+) -> bool {
+    let (Some(a), Some(b)) = (
+        unsafe { (handle_a as *const ColorProfileHandle).as_ref() },
+        unsafe { (handle_b as *const ColorProfileHandle).as_ref() },
+    ) else {
+        return false;
+    };
+
+    if a.profile.same_color_encoding(&b.profile) {
+        return true;
+    }
+
+    match (
+        resolve_simple_encoding(&a.profile),
+        resolve_simple_encoding(&b.profile),
+    ) {
+        (Some(enc_a), Some(enc_b)) => icc::same_encoding(&enc_a, &enc_b),
+        _ => false,
+    }
+}
+
+/// Creates a copy of a profile with linear transfer function.
+///
+/// # Returns
+/// A new handle, or null if not possible (e.g., for ICC profiles).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_with_linear_tf(
+    handle: *const JxlColorProfileHandle,
+) -> *mut JxlColorProfileHandle {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+
+    match inner.profile.with_linear_tf() {
+        Some(new_profile) => create_profile_handle(new_profile),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Gets the transfer function from a color profile.
+///
+/// For ICC profiles this is a best-effort match: the `cicp` tag is used if
+/// present, otherwise the TRC curves are classified as linear, sRGB, BT.709 or
+/// a pure gamma.
+///
+/// # Returns
+/// True if a transfer function was found, false for XYB or ICC profiles whose
+/// curves cannot be represented.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_get_transfer_function(
+    handle: *const JxlColorProfileHandle,
+    tf_out: *mut JxlTransferFunctionRaw,
+) -> bool {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return false;
+    };
+
+    let tf = match &inner.profile {
+        JxlColorProfile::Icc(data) => icc::transfer_function(data),
+        JxlColorProfile::Simple(_) => inner.profile.transfer_function().cloned(),
+    };
+
+    match tf {
+        Some(tf) => {
+            if let Some(out) = unsafe { tf_out.as_mut() } {
+                *out = convert_transfer_function(&tf);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Gets the string representation of a color profile.
+///
+/// The string is computed on the first call and cached on the handle, so
+/// repeated queries don't allocate.
+///
+/// # Arguments
+/// * `handle` - The color profile handle.
+/// * `buffer` - Output buffer for the string, or null to query required size.
+/// * `buffer_size` - Size of the buffer in bytes.
+///
+/// # Returns
+/// The number of bytes written (excluding null terminator), or required size if buffer is null/too small.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_to_string(
+    handle: *const JxlColorProfileHandle,
+    buffer: *mut u8,
+    buffer_size: usize,
+) -> usize {
+    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
+        return 0;
+    };
+
+    let s = inner
+        .string_cache
+        .get_or_init(|| format!("{}", inner.profile));
+    let bytes = s.as_bytes();
+
+    if buffer.is_null() || buffer_size < bytes.len() {
+        return bytes.len();
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
+    }
+
+    bytes.len()
+}
+
+/// Gets the description string for a color encoding.
+///
+/// This returns human-readable names like "sRGB", "DisplayP3", "Rec2100PQ" for known
+/// profiles, or a detailed encoding string for custom profiles.
+///
+/// # Returns
+/// The number of bytes written, or required size if buffer is null/too small.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_encoding_get_description(
+    encoding: *const JxlColorEncodingRaw,
+    buffer: *mut u8,
+    buffer_size: usize,
+) -> usize {
+    let Some(raw) = (unsafe { encoding.as_ref() }) else {
+        return 0;
+    };
+
+    let upstream = convert_color_encoding_to_upstream(raw);
+    let s = upstream.get_color_encoding_description();
+    let bytes = s.as_bytes();
+
+    if buffer.is_null() || buffer_size < bytes.len() {
+        return bytes.len();
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
+    }
+
+    bytes.len()
+}
+
+/// Creates a color profile handle from a simple color encoding.
+///
+/// # Returns
+/// A new handle that must be freed with `jxl_color_profile_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_from_encoding(
+    encoding: *const JxlColorEncodingRaw,
+) -> *mut JxlColorProfileHandle {
+    let Some(raw) = (unsafe { encoding.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+
+    let upstream = convert_color_encoding_to_upstream(raw);
+    create_profile_handle(JxlColorProfile::Simple(upstream))
+}
+
+/// Creates a color profile handle from ICC data.
+///
+/// # Safety
+/// `icc_data` must point to `icc_length` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_profile_from_icc(
+    icc_data: *const u8,
+    icc_length: usize,
+) -> *mut JxlColorProfileHandle {
+    if icc_data.is_null() || icc_length == 0 {
+        return std::ptr::null_mut();
+    }
+
+    let data = unsafe { slice::from_raw_parts(icc_data, icc_length) }.to_vec();
+    create_profile_handle(JxlColorProfile::Icc(data))
+}
+
+/// Creates a standard sRGB color encoding.
+///
+/// # Arguments
+/// * `grayscale` - If true, creates grayscale sRGB; otherwise RGB sRGB.
+/// * `encoding_out` - Output for the encoding data.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_encoding_srgb(
+    grayscale: bool,
+    encoding_out: *mut JxlColorEncodingRaw,
+) {
+    let encoding = jxl::api::JxlColorEncoding::srgb(grayscale);
+    if let Some(out) = unsafe { encoding_out.as_mut() } {
+        *out = convert_color_encoding(&encoding);
+    }
+}
+
+/// Creates a linear sRGB color encoding.
+///
+/// # Arguments
+/// * `grayscale` - If true, creates grayscale linear sRGB; otherwise RGB linear sRGB.
+/// * `encoding_out` - Output for the encoding data.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_encoding_linear_srgb(
+    grayscale: bool,
+    encoding_out: *mut JxlColorEncodingRaw,
+) {
+    let encoding = jxl::api::JxlColorEncoding::linear_srgb(grayscale);
+    if let Some(out) = unsafe { encoding_out.as_mut() } {
+        *out = convert_color_encoding(&encoding);
+    }
+}
+
+/// Builds a D65 RGB encoding with relative intent, as used by the standard
+/// display encodings below.
+fn standard_rgb_encoding(
+    primaries: jxl::api::JxlPrimaries,
+    transfer_function: jxl::api::JxlTransferFunction,
+) -> jxl::api::JxlColorEncoding {
+    jxl::api::JxlColorEncoding::RgbColorSpace {
+        white_point: jxl::api::JxlWhitePoint::D65,
+        primaries,
+        transfer_function,
+        rendering_intent: jxl::headers::color_encoding::RenderingIntent::Relative,
+    }
+}
+
+/// Creates a Display P3 color encoding (P3 primaries, D65, sRGB transfer function).
+///
+/// # Arguments
+/// * `encoding_out` - Output for the encoding data.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_encoding_display_p3(encoding_out: *mut JxlColorEncodingRaw) {
+    let encoding = standard_rgb_encoding(
+        jxl::api::JxlPrimaries::P3,
+        jxl::api::JxlTransferFunction::SRGB,
+    );
+    if let Some(out) = unsafe { encoding_out.as_mut() } {
+        *out = convert_color_encoding(&encoding);
+    }
+}
+
+/// Creates a Rec. 2100 PQ color encoding (BT.2100 primaries, D65, PQ transfer function).
+///
+/// # Arguments
+/// * `encoding_out` - Output for the encoding data.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_encoding_rec2020_pq(encoding_out: *mut JxlColorEncodingRaw) {
+    let encoding = standard_rgb_encoding(
+        jxl::api::JxlPrimaries::BT2100,
+        jxl::api::JxlTransferFunction::PQ,
+    );
+    if let Some(out) = unsafe { encoding_out.as_mut() } {
+        *out = convert_color_encoding(&encoding);
+    }
+}
+
+/// Creates a Rec. 2100 HLG color encoding (BT.2100 primaries, D65, HLG transfer function).
+///
+/// # Arguments
+/// * `encoding_out` - Output for the encoding data.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_encoding_rec2020_hlg(encoding_out: *mut JxlColorEncodingRaw) {
+    let encoding = standard_rgb_encoding(
+        jxl::api::JxlPrimaries::BT2100,
+        jxl::api::JxlTransferFunction::HLG,
+    );
+    if let Some(out) = unsafe { encoding_out.as_mut() } {
+        *out = convert_color_encoding(&encoding);
+    }
+}
+
+/// Creates a color encoding with a pure power-law transfer function.
+///
+/// Useful as an output profile for displays that expect a specific gamma
+/// (e.g. 2.2) rather than the piecewise sRGB curve. Set it with
+/// `jxl_decoder_set_output_color_profile`.
+///
+/// # Arguments
+/// * `grayscale` - If true, creates a grayscale encoding; otherwise RGB.
+/// * `gamma` - Display gamma (decoding exponent), e.g. 2.2. The encoding stores
+///   its reciprocal, following the JPEG XL convention.
+/// * `primaries_tag` - Standard primaries for RGB encodings. Ignored for grayscale.
+/// * `encoding_out` - Output for the encoding data. White point is D65.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidArgument` if `gamma` is not a finite value >= 1, `primaries_tag`
+///   is `Chromaticities`, or `encoding_out` is null.
+///
+/// # Safety
+/// `encoding_out` must be a valid pointer to a writable `JxlColorEncodingRaw`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_color_encoding_gamma(
+    grayscale: bool,
+    gamma: f32,
+    primaries_tag: JxlPrimariesTag,
+    encoding_out: *mut JxlColorEncodingRaw,
+) -> JxlStatus {
+    let Some(out) = (unsafe { encoding_out.as_mut() }) else {
+        set_last_error("Null encoding output");
+        return JxlStatus::InvalidArgument;
+    };
+
+    if !(gamma.is_finite() && gamma >= 1.0) {
+        set_last_error(format!("Invalid gamma: {}", gamma));
+        return JxlStatus::InvalidArgument;
+    }
+
+    if !grayscale && primaries_tag == JxlPrimariesTag::Chromaticities {
+        set_last_error("Custom primaries require jxl_color_profile_from_encoding");
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+    *out = JxlColorEncodingRaw {
+        Tag: if grayscale {
+            JxlColorEncodingTag::Grayscale
+        } else {
+            JxlColorEncodingTag::Rgb
+        },
+        WhitePoint: JxlWhitePointRaw::default(),
+        Primaries: JxlPrimariesRaw {
+            Tag: primaries_tag,
+            ..JxlPrimariesRaw::default()
+        },
+        TransferFunction: JxlTransferFunctionRaw {
+            Tag: JxlTransferFunctionTag::Gamma,
+            Gamma: 1.0 / gamma,
+        },
+        RenderingIntent: JxlRenderingIntent::Relative,
+    };
+
+    JxlStatus::Success
+}
+
+/// Computes a Bradford chromatic adaptation matrix between two white points.
+///
+/// The matrix converts XYZ values relative to the source white point into
+/// XYZ relative to the destination white point (e.g. DCI → D65).
+///
+/// # Arguments
+/// * `src_wx`, `src_wy` - Source white point chromaticity.
+/// * `dst_wx`, `dst_wy` - Destination white point chromaticity.
+/// * `out_matrix` - Output for the 3x3 matrix in row-major order (9 floats).
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidArgument` if `out_matrix` is null or a white point is degenerate.
+///
+/// # Safety
+/// `out_matrix` must be valid for writes of 9 `f32` values.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_chromatic_adapt_matrix(
+    src_wx: f32,
+    src_wy: f32,
+    dst_wx: f32,
+    dst_wy: f32,
+    out_matrix: *mut f32,
+) -> JxlStatus {
+    if out_matrix.is_null() {
+        set_last_error("Null output matrix");
+        return JxlStatus::InvalidArgument;
+    }
+
+    let Some(matrix) = crate::cms::bradford_adaptation_matrix(src_wx, src_wy, dst_wx, dst_wy)
+    else {
+        set_last_error("Degenerate white point chromaticity");
+        return JxlStatus::InvalidArgument;
+    };
+
+    clear_last_error();
+    let out = unsafe { slice::from_raw_parts_mut(out_matrix, 9) };
+    for (dst, src) in out.iter_mut().zip(matrix.iter().flatten()) {
+        *dst = *src;
+    }
+
+    JxlStatus::Success
+}
+
+// ============================================================================
+// Metadata Box Access
+// ============================================================================
+
+/// Reads the metadata boxes that precede the codestream, without decoding
+/// the image header.
+///
+/// For a fast metadata-only scan: this can be called in any state once input
+/// is set, including before the first `jxl_decoder_process`. Only container
+/// box headers are parsed, up to the first codestream box, and the EXIF, XML
+/// and JUMBF boxes found are cached for `jxl_decoder_get_*_box_count` and
+/// `jxl_decoder_get_*_box_at`. Bare codestreams have no metadata boxes.
+/// Metadata boxes placed after the codestream are not found.
+///
+/// Box kinds already read (by an earlier scan or after `HaveBasicInfo`) are
+/// left as they are, so pointers returned for them stay valid.
+///
+/// # Returns
+/// - `Success` once all boxes before the codestream have been read.
+/// - `NeedMoreInput` if the input ends before the codestream; append more
+///   and call again.
+/// - `Error` if the input is not a JPEG XL file or a box is malformed.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_scan_metadata(decoder: *mut NativeDecoderHandle) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    let data = &inner.data.as_slice()[inner.image_start..];
+    let boxes = match scan_metadata_boxes(data) {
+        Ok(MetadataScan::Complete(boxes)) => boxes,
+        Ok(MetadataScan::NeedMoreInput) => {
+            set_last_error("Input ends before the codestream");
+            return JxlStatus::NeedMoreInput;
+        }
+        Err(e) => {
+            set_last_error(e);
+            return JxlStatus::Error;
+        }
+    };
+
+    clear_last_error();
+
+    let of_kind = |kind: MetadataKind| {
+        boxes
+            .iter()
+            .filter(|b| b.kind == kind)
+            .map(|b| CachedMetadataBox {
+                data: b.data.clone(),
+                is_brotli_compressed: b.is_brotli_compressed,
+            })
+            .collect()
+    };
+    inner
+        .exif_boxes_cache
+        .get_or_insert_with(|| of_kind(MetadataKind::Exif));
+    inner
+        .xml_boxes_cache
+        .get_or_insert_with(|| of_kind(MetadataKind::Xml));
+    inner
+        .jumbf_boxes_cache
+        .get_or_insert_with(|| of_kind(MetadataKind::Jumbf));
+
+    JxlStatus::Success
+}
+
+/// Gets the number of EXIF boxes in the image.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+/// `jxl_decoder_scan_metadata` succeeds.
+///
+/// # Returns
+/// The number of EXIF boxes, or 0 if none or not accessible.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_exif_box_count(
+    decoder: *const NativeDecoderHandle,
+) -> u32 {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+
+    if let Some(cached) = &inner.exif_boxes_cache {
+        return cached.len() as u32;
+    }
+    match &inner.state {
+        DecoderState::WithImageInfo(d) => {
+            d.exif_boxes().map_or(0, |boxes| boxes.len() as u32)
+        }
+        _ => 0,
+    }
+}
+
+/// Gets the number of XML/XMP boxes in the image.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+/// `jxl_decoder_scan_metadata` succeeds.
+///
+/// # Returns
+/// The number of XML boxes, or 0 if none or not accessible.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_xml_box_count(
+    decoder: *const NativeDecoderHandle,
+) -> u32 {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+
+    if let Some(cached) = &inner.xml_boxes_cache {
+        return cached.len() as u32;
+    }
+    match &inner.state {
+        DecoderState::WithImageInfo(d) => {
+            d.xmp_boxes().map_or(0, |boxes| boxes.len() as u32)
+        }
+        _ => 0,
+    }
+}
+
+/// Gets the number of JUMBF boxes in the image.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+/// `jxl_decoder_scan_metadata` succeeds.
+///
+/// # Returns
+/// The number of JUMBF boxes, or 0 if none or not accessible.
+///
+/// # Safety
+/// The decoder pointer must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_jumbf_box_count(
+    decoder: *const NativeDecoderHandle,
+) -> u32 {
+    let inner = get_decoder_ref_silent!(decoder, 0);
+
+    if let Some(cached) = &inner.jumbf_boxes_cache {
+        return cached.len() as u32;
+    }
+    match &inner.state {
+        DecoderState::WithImageInfo(d) => {
+            d.jumbf_boxes().map_or(0, |boxes| boxes.len() as u32)
+        }
+        _ => 0,
+    }
+}
+
+/// Gets EXIF data from a specific box by index.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+/// `jxl_decoder_scan_metadata` succeeds.
+/// The returned pointer is valid until the decoder is reset, rewound, or freed.
+/// Use `jxl_decoder_copy_exif_box_at` to copy the data instead.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance (mutable for caching).
+/// * `index` - Zero-based box index.
+/// * `data_out` - Output pointer for EXIF data bytes.
+/// * `length_out` - Output for EXIF data length.
+/// * `is_brotli_compressed` - Output for brotli compression flag (true if brob box).
+///
+/// # Returns
+/// - `Success` if EXIF data is available.
+/// - `InvalidState` if called before basic info is available and without a
+///   metadata scan.
+/// - `InvalidArgument` if index is out of range.
+/// - `Error` if no EXIF data exists in the image.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - Output pointers must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_exif_box_at(
+    decoder: *mut NativeDecoderHandle,
+    index: u32,
+    data_out: *mut *const u8,
+    length_out: *mut usize,
+    is_brotli_compressed: *mut bool,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    // Populate cache if needed
+    if inner.exif_boxes_cache.is_none() {
+        let boxes = match &inner.state {
+            DecoderState::WithImageInfo(d) => d.exif_boxes(),
+            _ => {
+                set_last_error("EXIF data not accessible - call jxl_decoder_process until HaveBasicInfo");
+                return JxlStatus::InvalidState;
+            }
+        };
+
+        let Some(boxes) = boxes else {
+            set_last_error("Image does not contain EXIF data");
+            return JxlStatus::Error;
+        };
+
+        if boxes.is_empty() {
+            set_last_error("Image does not contain EXIF data");
+            return JxlStatus::Error;
+        }
+
+        // Cache all boxes with compression flag
+        inner.exif_boxes_cache = Some(
+            boxes
+                .iter()
+                .map(|b| CachedMetadataBox {
+                    data: b.data.clone(),
+                    is_brotli_compressed: b.is_brotli_compressed,
+                })
+                .collect(),
+        );
+    }
+
+    let cached = inner.exif_boxes_cache.as_ref().unwrap();
+    let idx = index as usize;
+
+    // A scan that found no boxes of this kind caches an empty list
+    if cached.is_empty() {
+        set_last_error("Image does not contain EXIF data");
+        return JxlStatus::Error;
+    }
+
+    if idx >= cached.len() {
+        set_last_error(format!("EXIF box index {} out of range (max {})", index, cached.len() - 1));
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+
+    let cached_box = &cached[idx];
+
+    if let Some(out) = unsafe { data_out.as_mut() } {
+        *out = cached_box.data.as_ptr();
+    }
+    if let Some(out) = unsafe { length_out.as_mut() } {
+        *out = cached_box.data.len();
+    }
+    if let Some(out) = unsafe { is_brotli_compressed.as_mut() } {
+        *out = cached_box.is_brotli_compressed;
+    }
+
+    JxlStatus::Success
+}
+
+/// Copies EXIF data from a specific box by index into a caller buffer.
+///
+/// A copying alternative to `jxl_decoder_get_exif_box_at` for callers that
+/// can't hold on to a pointer into decoder memory, such as garbage-collected
+/// languages. The data is only copied whole: a buffer that is null or too
+/// small is left untouched and the required size is returned.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance (mutable for caching).
+/// * `index` - Zero-based box index.
+/// * `buffer` - Output buffer for the EXIF data, or null to query the size.
+/// * `buffer_size` - Size of `buffer` in bytes.
+///
+/// # Returns
+/// The size of the box data in bytes, whether or not it was copied. Returns 0
+/// in the cases where `jxl_decoder_get_exif_box_at` fails; check
+/// `jxl_get_last_error` for the reason. The compression flag is available
+/// from `jxl_decoder_get_exif_box_at` with a null `data_out`.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_copy_exif_box_at(
+    decoder: *mut NativeDecoderHandle,
+    index: u32,
+    buffer: *mut u8,
+    buffer_size: usize,
+) -> usize {
+    let mut data = std::ptr::null();
+    let mut len = 0;
+    let status = unsafe {
+        jxl_decoder_get_exif_box_at(decoder, index, &mut data, &mut len, std::ptr::null_mut())
+    };
+    if status != JxlStatus::Success {
+        return 0;
+    }
+
+    if !buffer.is_null() && buffer_size >= len {
+        unsafe { std::ptr::copy_nonoverlapping(data, buffer, len) };
+    }
+    len
+}
+
+/// Gets XML/XMP data from a specific box by index.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+/// `jxl_decoder_scan_metadata` succeeds.
+/// The returned pointer is valid until the decoder is reset, rewound, or freed.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance (mutable for caching).
+/// * `index` - Zero-based box index.
+/// * `data_out` - Output pointer for XML data bytes.
+/// * `length_out` - Output for XML data length.
+/// * `is_brotli_compressed` - Output for brotli compression flag (true if brob box).
+///
+/// # Returns
+/// - `Success` if XML data is available.
+/// - `InvalidState` if called before basic info is available and without a
+///   metadata scan.
+/// - `InvalidArgument` if index is out of range.
+/// - `Error` if no XML data exists in the image.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - Output pointers must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_xml_box_at(
+    decoder: *mut NativeDecoderHandle,
+    index: u32,
+    data_out: *mut *const u8,
+    length_out: *mut usize,
+    is_brotli_compressed: *mut bool,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    // Populate cache if needed
+    if inner.xml_boxes_cache.is_none() {
+        let boxes = match &inner.state {
+            DecoderState::WithImageInfo(d) => d.xmp_boxes(),
+            _ => {
+                set_last_error("XML data not accessible - call jxl_decoder_process until HaveBasicInfo");
+                return JxlStatus::InvalidState;
+            }
+        };
+
+        let Some(boxes) = boxes else {
+            set_last_error("Image does not contain XML data");
+            return JxlStatus::Error;
+        };
+
+        if boxes.is_empty() {
+            set_last_error("Image does not contain XML data");
+            return JxlStatus::Error;
+        }
+
+        // Cache all boxes with compression flag
+        inner.xml_boxes_cache = Some(
+            boxes
+                .iter()
+                .map(|b| CachedMetadataBox {
+                    data: b.data.clone(),
+                    is_brotli_compressed: b.is_brotli_compressed,
+                })
+                .collect(),
+        );
+    }
+
+    let cached = inner.xml_boxes_cache.as_ref().unwrap();
+    let idx = index as usize;
+
+    // A scan that found no boxes of this kind caches an empty list
+    if cached.is_empty() {
+        set_last_error("Image does not contain XML data");
+        return JxlStatus::Error;
+    }
+
+    if idx >= cached.len() {
+        set_last_error(format!("XML box index {} out of range (max {})", index, cached.len() - 1));
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+
+    let cached_box = &cached[idx];
+
+    if let Some(out) = unsafe { data_out.as_mut() } {
+        *out = cached_box.data.as_ptr();
+    }
+    if let Some(out) = unsafe { length_out.as_mut() } {
+        *out = cached_box.data.len();
+    }
+    if let Some(out) = unsafe { is_brotli_compressed.as_mut() } {
+        *out = cached_box.is_brotli_compressed;
+    }
+
+    JxlStatus::Success
+}
+
+/// Gets JUMBF data from a specific box by index.
+///
+/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+/// `jxl_decoder_scan_metadata` succeeds.
+/// The returned pointer is valid until the decoder is reset, rewound, or freed.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance (mutable for caching).
+/// * `index` - Zero-based box index.
+/// * `data_out` - Output pointer for JUMBF data bytes.
+/// * `length_out` - Output for JUMBF data length.
+/// * `is_brotli_compressed` - Output for brotli compression flag (true if brob box).
+///
+/// # Returns
+/// - `Success` if JUMBF data is available.
+/// - `InvalidState` if called before basic info is available and without a
+///   metadata scan.
+/// - `InvalidArgument` if index is out of range.
+/// - `Error` if no JUMBF data exists in the image.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - Output pointers must be writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_jumbf_box_at(
+    decoder: *mut NativeDecoderHandle,
+    index: u32,
+    data_out: *mut *const u8,
+    length_out: *mut usize,
+    is_brotli_compressed: *mut bool,
+) -> JxlStatus {
+    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
+
+    // Populate cache if needed
+    if inner.jumbf_boxes_cache.is_none() {
+        let boxes = match &inner.state {
+            DecoderState::WithImageInfo(d) => d.jumbf_boxes(),
+            _ => {
+                set_last_error("JUMBF data not accessible - call jxl_decoder_process until HaveBasicInfo");
+                return JxlStatus::InvalidState;
+            }
+        };
+
+        let Some(boxes) = boxes else {
+            set_last_error("Image does not contain JUMBF data");
+            return JxlStatus::Error;
+        };
+
+        if boxes.is_empty() {
+            set_last_error("Image does not contain JUMBF data");
+            return JxlStatus::Error;
+        }
+
+        // Cache all boxes with compression flag
+        inner.jumbf_boxes_cache = Some(
+            boxes
+                .iter()
+                .map(|b| CachedMetadataBox {
+                    data: b.data.clone(),
+                    is_brotli_compressed: b.is_brotli_compressed,
+                })
+                .collect(),
+        );
+    }
+
+    let cached = inner.jumbf_boxes_cache.as_ref().unwrap();
+    let idx = index as usize;
+
+    // A scan that found no boxes of this kind caches an empty list
+    if cached.is_empty() {
+        set_last_error("Image does not contain JUMBF data");
+        return JxlStatus::Error;
+    }
+
+    if idx >= cached.len() {
+        set_last_error(format!("JUMBF box index {} out of range (max {})", index, cached.len() - 1));
+        return JxlStatus::InvalidArgument;
+    }
+
+    clear_last_error();
+
+    let cached_box = &cached[idx];
+
+    if let Some(out) = unsafe { data_out.as_mut() } {
+        *out = cached_box.data.as_ptr();
+    }
+    if let Some(out) = unsafe { length_out.as_mut() } {
+        *out = cached_box.data.len();
+    }
+    if let Some(out) = unsafe { is_brotli_compressed.as_mut() } {
+        *out = cached_box.is_brotli_compressed;
+    }
+
+    JxlStatus::Success
+}
+
+// ============================================================================
+// Metadata Box Compression Status (deprecated - use get_*_box_at with is_brotli_compressed)
+// ============================================================================
+
+/// Returns whether the EXIF box at the given index is brotli-compressed.
+///
+/// Only valid after `jxl_decoder_get_exif_box_at` has been called to populate the cache.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `index` - Zero-based box index.
+///
+/// # Returns
+/// - `true` if the box was brotli-compressed in the file (brob box).
+/// - `false` if uncompressed or if cache not populated.
+///
+/// # Safety
+/// - `decoder` must be valid.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_same_color_encoding(
-    handle_a: *const JxlColorProfileHandle,
-    handle_b: *const JxlColorProfileHandle,
+pub unsafe extern "C" fn jxl_decoder_is_exif_box_compressed(
+    decoder: *const NativeDecoderHandle,
+    index: u32,
 ) -> bool {
-    let (Some(a), Some(b)) = (
-        unsafe { (handle_a as *const ColorProfileHandle).as_ref() },
-        unsafe { (handle_b as *const ColorProfileHandle).as_ref() },
-    ) else {
-        return false;
-    };
-
-    a.profile.same_color_encoding(&b.profile)
+    let inner = get_decoder_ref_silent!(decoder, false);
+    inner
+        .exif_boxes_cache
+        .as_ref()
+        .and_then(|boxes| boxes.get(index as usize))
+        .map(|b| b.is_brotli_compressed)
+        .unwrap_or(false)
 }
 
-/// Creates a copy of a profile with linear transfer function.
+/// Returns whether the XML box at the given index is brotli-compressed.
+///
+/// Only valid after `jxl_decoder_get_xml_box_at` has been called to populate the cache.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `index` - Zero-based box index.
 ///
 /// # Returns
-/// A new handle, or null if not possible (e.g., for ICC profiles).
+/// - `true` if the box was brotli-compressed in the file (brob box).
+/// - `false` if uncompressed or if cache not populated.
+///
+/// # Safety
+/// - `decoder` must be valid.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_with_linear_tf(
-    handle: *const JxlColorProfileHandle,
-) -> *mut JxlColorProfileHandle {
-    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
-        return std::ptr::null_mut();
-    };
-
-    match inner.profile.with_linear_tf() {
-        Some(new_profile) => create_profile_handle(new_profile),
-        None => std::ptr::null_mut(),
-    }
+pub unsafe extern "C" fn jxl_decoder_is_xml_box_compressed(
+    decoder: *const NativeDecoderHandle,
+    index: u32,
+) -> bool {
+    let inner = get_decoder_ref_silent!(decoder, false);
+    inner
+        .xml_boxes_cache
+        .as_ref()
+        .and_then(|boxes| boxes.get(index as usize))
+        .map(|b| b.is_brotli_compressed)
+        .unwrap_or(false)
 }
 
-/// Gets the transfer function from a simple color profile.
+/// Returns whether the JUMBF box at the given index is brotli-compressed.
+///
+/// Only valid after `jxl_decoder_get_jumbf_box_at` has been called to populate the cache.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `index` - Zero-based box index.
 ///
 /// # Returns
-/// True if the profile has a transfer function, false otherwise (ICC or XYB).
+/// - `true` if the box was brotli-compressed in the file (brob box).
+/// - `false` if uncompressed or if cache not populated.
+///
+/// # Safety
+/// - `decoder` must be valid.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_get_transfer_function(
-    handle: *const JxlColorProfileHandle,
-    tf_out: *mut JxlTransferFunctionRaw,
+pub unsafe extern "C" fn jxl_decoder_is_jumbf_box_compressed(
+    decoder: *const NativeDecoderHandle,
+    index: u32,
 ) -> bool {
-    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
-        return false;
-    };
-
-    match inner.profile.transfer_function() {
-        Some(tf) => {
-            if let Some(out) = unsafe { tf_out.as_mut() } {
-                *out = convert_transfer_function(tf);
-            }
-            true
-        }
-        None => false,
-    }
+    let inner = get_decoder_ref_silent!(decoder, false);
+    inner
+        .jumbf_boxes_cache
+        .as_ref()
+        .and_then(|boxes| boxes.get(index as usize))
+        .map(|b| b.is_brotli_compressed)
+        .unwrap_or(false)
 }
 
-/// Gets the string representation of a color profile.
+/// Decompresses brotli data, such as the payload of a brotli-compressed
+/// metadata box.
+///
+/// Uses the same decompressor as the decoder's own `brob` box handling.
+/// Brotli can expand a small input enormously, so the output is streamed and
+/// decompression stops once it exceeds `max_output_size`.
 ///
 /// # Arguments
-/// * `handle` - The color profile handle.
-/// * `buffer` - Output buffer for the string, or null to query required size.
-/// * `buffer_size` - Size of the buffer in bytes.
+/// * `src` - Brotli-compressed data.
+/// * `src_len` - Length of `src` in bytes.
+/// * `max_output_size` - Maximum size of the decompressed data in bytes.
+///   0 = no limit.
+/// * `out_ptr` - Receives a pointer to the decompressed data. Free it with
+///   `jxl_free_buffer`.
+/// * `out_len` - Receives the length of the decompressed data in bytes.
 ///
 /// # Returns
-/// The number of bytes written (excluding null terminator), or required size if buffer is null/too small.
+/// - `Success` on success.
+/// - `InvalidArgument` if `src` or an output pointer is null.
+/// - `BufferTooSmall` if the decompressed data exceeds `max_output_size`.
+/// - `Error` if the data is not a complete, valid brotli stream.
+///
+/// On failure `*out_ptr` is set to null and `*out_len` to 0.
+///
+/// # Safety
+/// - `src` must be valid for reads of `src_len` bytes.
+/// - `out_ptr` and `out_len` must be valid for writes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_to_string(
-    handle: *const JxlColorProfileHandle,
-    buffer: *mut u8,
-    buffer_size: usize,
-) -> usize {
-    let Some(inner) = (unsafe { (handle as *const ColorProfileHandle).as_ref() }) else {
-        return 0;
-    };
+pub unsafe extern "C" fn jxl_brotli_decompress(
+    src: *const u8,
+    src_len: usize,
+    max_output_size: usize,
+    out_ptr: *mut *mut u8,
+    out_len: *mut usize,
+) -> JxlStatus {
+    if out_ptr.is_null() || out_len.is_null() {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    }
 
-    let s = format!("{}", inner.profile);
-    let bytes = s.as_bytes();
+    unsafe {
+        *out_ptr = std::ptr::null_mut();
+        *out_len = 0;
+    }
 
-    if buffer.is_null() || buffer_size < bytes.len() {
-        return bytes.len();
+    if src.is_null() {
+        set_last_error("Null input data");
+        return JxlStatus::InvalidArgument;
     }
 
-    unsafe {
-        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
+    let compressed = unsafe { slice::from_raw_parts(src, src_len) };
+    // One byte past the limit tells an oversized output from one that fits
+    let read_limit = match max_output_size {
+        0 => u64::MAX,
+        max => (max as u64).saturating_add(1),
+    };
+    let mut decompressed = Vec::new();
+    let result = brotli_decompressor::Decompressor::new(compressed, 4096)
+        .take(read_limit)
+        .read_to_end(&mut decompressed);
+    if let Err(e) = result {
+        set_last_error(format!("Brotli decompression failed: {}", e));
+        return JxlStatus::Error;
+    }
+    if max_output_size != 0 && decompressed.len() > max_output_size {
+        set_last_error(format!("Brotli output exceeds {max_output_size} bytes"));
+        return JxlStatus::BufferTooSmall;
     }
 
-    bytes.len()
+    clear_last_error();
+    let decompressed = decompressed.into_boxed_slice();
+    let len = decompressed.len();
+    unsafe {
+        *out_ptr = Box::into_raw(decompressed) as *mut u8;
+        *out_len = len;
+    }
+    JxlStatus::Success
 }
 
-/// Gets the description string for a color encoding.
+// ============================================================================
+// Tone Mapping
+// ============================================================================
+
+/// Tone maps an interleaved linear RGB buffer in place.
 ///
-/// This returns human-readable names like "sRGB", "DisplayP3", "Rec2100PQ" for known
-/// profiles, or a detailed encoding string for custom profiles.
+/// Applies the crate's HDR→SDR tone mapping without a decoder or CMS,
+/// for HDR pixels obtained elsewhere.
+///
+/// # Arguments
+/// * `method` - Tone mapping algorithm.
+/// * `luminances` - Pointer to 3 RGB luminance coefficients for the data's
+///   primaries, or null for BT.2020. Ignored by `Bt2446aPerceptual`.
+/// * `source_nits` - Peak luminance of the input; 1.0 in `data` equals this many nits.
+/// * `target_nits` - Peak luminance of the target display.
+/// * `data` - Interleaved `[R, G, B, ...]` linear-light samples.
+/// * `len` - Number of `f32` values in `data` (must be a multiple of 3).
 ///
 /// # Returns
-/// The number of bytes written, or required size if buffer is null/too small.
+/// - `Success` on success.
+/// - `InvalidArgument` if a pointer is null, `len` is not a multiple of 3,
+///   or a luminance target is not positive.
+/// - `Error` if tone-mapping support is not compiled in.
+///
+/// # Safety
+/// - If `luminances` is not null, it must point to 3 readable `f32` values.
+/// - `data` must be valid for reads and writes of `len` `f32` values.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_encoding_get_description(
-    encoding: *const JxlColorEncodingRaw,
-    buffer: *mut u8,
-    buffer_size: usize,
-) -> usize {
-    let Some(raw) = (unsafe { encoding.as_ref() }) else {
-        return 0;
-    };
+#[cfg_attr(not(feature = "tone-mapping"), allow(unused_variables))]
+pub unsafe extern "C" fn jxl_tone_map_rgb_f32(
+    method: JxlToneMapMethod,
+    luminances: *const f32,
+    source_nits: f32,
+    target_nits: f32,
+    data: *mut f32,
+    len: usize,
+) -> JxlStatus {
+    if data.is_null() && len > 0 {
+        set_last_error("Null data pointer");
+        return JxlStatus::InvalidArgument;
+    }
 
-    let upstream = convert_color_encoding_to_upstream(raw);
-    let s = upstream.get_color_encoding_description();
-    let bytes = s.as_bytes();
+    if len % 3 != 0 {
+        set_last_error(format!("Length {} is not a multiple of 3", len));
+        return JxlStatus::InvalidArgument;
+    }
 
-    if buffer.is_null() || buffer_size < bytes.len() {
-        return bytes.len();
+    if !(source_nits > 0.0 && target_nits > 0.0) {
+        set_last_error("Source and target luminance must be positive");
+        return JxlStatus::InvalidArgument;
     }
 
-    unsafe {
-        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
+    #[cfg(feature = "tone-mapping")]
+    {
+        clear_last_error();
+
+        let luminances = match unsafe { luminances.cast::<[f32; 3]>().as_ref() } {
+            Some(l) => *l,
+            None => [0.2627, 0.6780, 0.0593],
+        };
+
+        if len > 0 {
+            let pixels = unsafe { slice::from_raw_parts_mut(data, len) };
+            crate::tone_mapping::tone_map_rgb(
+                crate::conversions::convert_tone_map_method(method),
+                luminances,
+                source_nits,
+                target_nits,
+                pixels,
+            );
+        }
+
+        JxlStatus::Success
     }
 
-    bytes.len()
+    #[cfg(not(feature = "tone-mapping"))]
+    {
+        set_last_error("tone-mapping support not compiled in");
+        JxlStatus::Error
+    }
 }
 
-/// Creates a color profile handle from a simple color encoding.
+/// Expands an interleaved linear SDR RGB buffer toward HDR in place.
+///
+/// Inverts `jxl_tone_map_rgb_f32` with the `Bt2446aLinear` method: tone
+/// mapping from `target_nits` down to `source_nits` and then calling this
+/// function returns the original samples, up to float rounding. Luminance
+/// uses BT.2020 coefficients.
+///
+/// # Arguments
+/// * `source_nits` - Peak luminance of the SDR input; 1.0 in `data` equals this many nits.
+/// * `target_nits` - Peak luminance of the HDR output; 1.0 in the result equals this many nits.
+/// * `data` - Interleaved `[R, G, B, ...]` linear-light samples.
+/// * `len` - Number of `f32` values in `data` (must be a multiple of 3).
 ///
 /// # Returns
-/// A new handle that must be freed with `jxl_color_profile_free`.
+/// - `Success` on success.
+/// - `InvalidArgument` if `data` is null, `len` is not a multiple of 3,
+///   or a luminance target is not positive.
+/// - `Error` if tone-mapping support is not compiled in.
+///
+/// # Safety
+/// `data` must be valid for reads and writes of `len` `f32` values.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_from_encoding(
-    encoding: *const JxlColorEncodingRaw,
-) -> *mut JxlColorProfileHandle {
-    let Some(raw) = (unsafe { encoding.as_ref() }) else {
-        return std::ptr::null_mut();
-    };
+#[cfg_attr(not(feature = "tone-mapping"), allow(unused_variables))]
+pub unsafe extern "C" fn jxl_inverse_tone_map_bt2446a(
+    source_nits: f32,
+    target_nits: f32,
+    data: *mut f32,
+    len: usize,
+) -> JxlStatus {
+    if data.is_null() && len > 0 {
+        set_last_error("Null data pointer");
+        return JxlStatus::InvalidArgument;
+    }
 
-    let upstream = convert_color_encoding_to_upstream(raw);
-    create_profile_handle(JxlColorProfile::Simple(upstream))
+    if len % 3 != 0 {
+        set_last_error(format!("Length {} is not a multiple of 3", len));
+        return JxlStatus::InvalidArgument;
+    }
+
+    if !(source_nits > 0.0 && target_nits > 0.0) {
+        set_last_error("Source and target luminance must be positive");
+        return JxlStatus::InvalidArgument;
+    }
+
+    #[cfg(feature = "tone-mapping")]
+    {
+        clear_last_error();
+
+        if len > 0 {
+            let pixels = unsafe { slice::from_raw_parts_mut(data, len) };
+            crate::tone_mapping::inverse_tone_map_bt2446a_linear(
+                &crate::tone_mapping::Bt2446aParams::new(target_nits, source_nits),
+                [0.2627, 0.6780, 0.0593],
+                pixels,
+            );
+        }
+
+        JxlStatus::Success
+    }
+
+    #[cfg(not(feature = "tone-mapping"))]
+    {
+        set_last_error("tone-mapping support not compiled in");
+        JxlStatus::Error
+    }
 }
 
-/// Creates a color profile handle from ICC data.
+// ============================================================================
+// Transfer Functions
+// ============================================================================
+
+/// Re-encodes a float buffer from one transfer function to another in place.
+///
+/// Samples are decoded to linear light with `from_tf` and encoded with
+/// `to_tf`, without a CMS or output profile. Primaries are left untouched, so
+/// this is only correct when the input and output primaries already match.
+///
+/// # Arguments
+/// * `data` - Samples to convert; any channel layout, alpha excluded.
+/// * `len` - Number of `f32` values in `data`.
+/// * `from_tf` - Transfer function the samples are currently encoded with.
+/// * `to_tf` - Transfer function to encode the samples with.
+/// * `intensity_target` - Nits represented by linear 1.0, used for PQ.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidArgument` if `data` is null, `intensity_target` is not positive,
+///   or a gamma is outside (0, 1].
 ///
 /// # Safety
-/// `icc_data` must point to `icc_length` readable bytes.
+/// `data` must be valid for reads and writes of `len` `f32` values.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_profile_from_icc(
-    icc_data: *const u8,
-    icc_length: usize,
-) -> *mut JxlColorProfileHandle {
-    if icc_data.is_null() || icc_length == 0 {
-        return std::ptr::null_mut();
+pub unsafe extern "C" fn jxl_apply_transfer_function(
+    data: *mut f32,
+    len: usize,
+    from_tf: JxlTransferFunctionRaw,
+    to_tf: JxlTransferFunctionRaw,
+    intensity_target: f32,
+) -> JxlStatus {
+    if data.is_null() && len > 0 {
+        set_last_error("Null data pointer");
+        return JxlStatus::InvalidArgument;
     }
 
-    let data = unsafe { slice::from_raw_parts(icc_data, icc_length) }.to_vec();
-    create_profile_handle(JxlColorProfile::Icc(data))
+    if !(intensity_target > 0.0 && intensity_target.is_finite()) {
+        set_last_error("Intensity target must be positive");
+        return JxlStatus::InvalidArgument;
+    }
+
+    for tf in [&from_tf, &to_tf] {
+        if tf.Tag == JxlTransferFunctionTag::Gamma && !(tf.Gamma > 0.0 && tf.Gamma <= 1.0) {
+            set_last_error(format!("Gamma {} must be in (0, 1]", tf.Gamma));
+            return JxlStatus::InvalidArgument;
+        }
+    }
+
+    clear_last_error();
+
+    if len > 0 {
+        let samples = unsafe { slice::from_raw_parts_mut(data, len) };
+        crate::cms::transfer_to_linear(
+            &convert_transfer_function_to_upstream(&from_tf),
+            intensity_target,
+            samples,
+        );
+        crate::cms::linear_to_transfer(
+            &convert_transfer_function_to_upstream(&to_tf),
+            intensity_target,
+            samples,
+        );
+    }
+
+    JxlStatus::Success
 }
 
-/// Creates a standard sRGB color encoding.
+// ============================================================================
+// CMS Transforms
+// ============================================================================
+
+/// Creates a color transform between two ICC profiles with lcms2.
+///
+/// The transform is built once and can then be applied to any number of
+/// buffers in the input color space, independently of a decoder. Both
+/// profiles must have the same channel count, as transforms run in place.
 ///
 /// # Arguments
-/// * `grayscale` - If true, creates grayscale sRGB; otherwise RGB sRGB.
-/// * `encoding_out` - Output for the encoding data.
+/// * `input_icc` - ICC profile the samples are in.
+/// * `input_len` - Size of `input_icc` in bytes.
+/// * `output_icc` - ICC profile to convert the samples to.
+/// * `output_len` - Size of `output_icc` in bytes.
+/// * `intent` - Rendering intent of the transform.
+///
+/// # Returns
+/// A transform that must be freed with `jxl_cms_transform_destroy`, or null
+/// on failure, including in builds without the `cms-lcms2` feature. Call
+/// `jxl_get_last_error` for details.
+///
+/// # Safety
+/// `input_icc` and `output_icc` must be valid for reads of `input_len` and
+/// `output_len` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_encoding_srgb(
-    grayscale: bool,
-    encoding_out: *mut JxlColorEncodingRaw,
-) {
-    let encoding = jxl::api::JxlColorEncoding::srgb(grayscale);
-    if let Some(out) = unsafe { encoding_out.as_mut() } {
-        *out = convert_color_encoding(&encoding);
+pub unsafe extern "C" fn jxl_cms_transform_create(
+    input_icc: *const u8,
+    input_len: usize,
+    output_icc: *const u8,
+    output_len: usize,
+    intent: JxlRenderingIntent,
+) -> *mut JxlCmsTransformHandle {
+    if input_icc.is_null() || input_len == 0 || output_icc.is_null() || output_len == 0 {
+        set_last_error_with_status(JxlStatus::InvalidArgument, "Null or empty ICC profile");
+        return std::ptr::null_mut();
+    }
+
+    #[cfg(feature = "cms-lcms2")]
+    {
+        let input = unsafe { slice::from_raw_parts(input_icc, input_len) };
+        let output = unsafe { slice::from_raw_parts(output_icc, output_len) };
+        let intent = crate::conversions::convert_rendering_intent_to_upstream(intent);
+        match crate::cms::CmsTransform::new(input, output, intent) {
+            Ok(transform) => {
+                clear_last_error();
+                Box::into_raw(Box::new(transform)) as *mut JxlCmsTransformHandle
+            }
+            Err(e) => {
+                set_last_error(e);
+                std::ptr::null_mut()
+            }
+        }
+    }
+    #[cfg(not(feature = "cms-lcms2"))]
+    {
+        let _ = intent;
+        set_last_error("lcms2 support not compiled in");
+        std::ptr::null_mut()
     }
 }
 
-/// Creates a linear sRGB color encoding.
+/// Applies a color transform to interleaved float samples in place.
+///
+/// A transform may be applied to any number of buffers, but not from
+/// several threads at once.
 ///
 /// # Arguments
-/// * `grayscale` - If true, creates grayscale linear sRGB; otherwise RGB linear sRGB.
-/// * `encoding_out` - Output for the encoding data.
+/// * `transform` - Transform from `jxl_cms_transform_create`.
+/// * `data` - Samples to convert, interleaved with the profiles' channel
+///   count and nominally in [0, 1].
+/// * `len` - Number of `f32` values in `data`.
+///
+/// # Returns
+/// - `Success` on success.
+/// - `InvalidArgument` if a pointer is null or `len` is not a multiple of the
+///   channel count.
+/// - `Error` if the transform fails.
+///
+/// # Safety
+/// - `transform` must be valid and not in use on another thread.
+/// - `data` must be valid for reads and writes of `len` `f32` values.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_color_encoding_linear_srgb(
-    grayscale: bool,
-    encoding_out: *mut JxlColorEncodingRaw,
-) {
-    let encoding = jxl::api::JxlColorEncoding::linear_srgb(grayscale);
-    if let Some(out) = unsafe { encoding_out.as_mut() } {
-        *out = convert_color_encoding(&encoding);
+pub unsafe extern "C" fn jxl_cms_transform_apply(
+    transform: *mut JxlCmsTransformHandle,
+    data: *mut f32,
+    len: usize,
+) -> JxlStatus {
+    #[cfg(feature = "cms-lcms2")]
+    {
+        let Some(transform) = (unsafe { (transform as *mut crate::cms::CmsTransform).as_mut() })
+        else {
+            set_last_error("Null transform pointer");
+            return JxlStatus::InvalidArgument;
+        };
+        if data.is_null() && len > 0 {
+            set_last_error("Null data pointer");
+            return JxlStatus::InvalidArgument;
+        }
+        if len % transform.channels() != 0 {
+            set_last_error(format!(
+                "Sample count {} is not a multiple of the channel count {}",
+                len,
+                transform.channels()
+            ));
+            return JxlStatus::InvalidArgument;
+        }
+        if len == 0 {
+            clear_last_error();
+            return JxlStatus::Success;
+        }
+
+        let samples = unsafe { slice::from_raw_parts_mut(data, len) };
+        match transform.apply(samples) {
+            Ok(()) => {
+                clear_last_error();
+                JxlStatus::Success
+            }
+            Err(e) => {
+                set_last_error(e);
+                JxlStatus::Error
+            }
+        }
+    }
+    #[cfg(not(feature = "cms-lcms2"))]
+    {
+        let _ = (transform, data, len);
+        set_last_error("lcms2 support not compiled in");
+        JxlStatus::Error
+    }
+}
+
+/// Frees a color transform.
+///
+/// # Safety
+/// `transform` must be null or have been created by
+/// `jxl_cms_transform_create`, and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_cms_transform_destroy(transform: *mut JxlCmsTransformHandle) {
+    #[cfg(feature = "cms-lcms2")]
+    if !transform.is_null() {
+        unsafe { drop(Box::from_raw(transform as *mut crate::cms::CmsTransform)) };
     }
+    #[cfg(not(feature = "cms-lcms2"))]
+    let _ = transform;
 }
 
 // ============================================================================
-// Metadata Box Access
+// Diagnostics
 // ============================================================================
 
-/// Gets the number of EXIF boxes in the image.
+/// Writes a human-readable snapshot of the decoder state for bug reports.
 ///
-/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+/// The snapshot covers the state machine variant, input offsets, pixel format,
+/// CMS type, cached info and options. It contains no image data. The format
+/// is not stable and should not be parsed.
 ///
 /// # Returns
-/// The number of EXIF boxes, or 0 if none or not accessible.
+/// The length of the snapshot in bytes (UTF-8, not null-terminated). If
+/// `buffer` is null or smaller than this, nothing is written.
 ///
 /// # Safety
-/// The decoder pointer must be valid.
+/// - `decoder` must be valid.
+/// - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_get_exif_box_count(
+pub unsafe extern "C" fn jxl_decoder_debug_state(
     decoder: *const NativeDecoderHandle,
-) -> u32 {
+    buffer: *mut u8,
+    buffer_size: usize,
+) -> usize {
     let inner = get_decoder_ref_silent!(decoder, 0);
 
-    match &inner.state {
-        DecoderState::WithImageInfo(d) => {
-            d.exif_boxes().map_or(0, |boxes| boxes.len() as u32)
+    let text = inner.debug_state();
+    let bytes = text.as_bytes();
+
+    if !buffer.is_null() && buffer_size >= bytes.len() {
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer, bytes.len());
         }
-        _ => 0,
     }
+
+    bytes.len()
 }
 
-/// Gets the number of XML/XMP boxes in the image.
+/// Gets the stage of the decoder's state machine.
 ///
-/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+/// Lets callers check preconditions, such as basic info being available,
+/// without tracking the events returned so far.
 ///
 /// # Returns
-/// The number of XML boxes, or 0 if none or not accessible.
+/// The current state, or `Processing` while a decoding call is running on
+/// another thread. A null decoder also returns `Processing`, as no call can
+/// be made on it.
 ///
 /// # Safety
-/// The decoder pointer must be valid.
+/// `decoder` must be null or valid.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_get_xml_box_count(
+pub unsafe extern "C" fn jxl_decoder_get_state(
     decoder: *const NativeDecoderHandle,
-) -> u32 {
-    let inner = get_decoder_ref_silent!(decoder, 0);
+) -> JxlDecoderStateTag {
+    let inner = get_decoder_ref!(decoder, JxlDecoderStateTag::Processing);
 
+    // The state is moved out during decoding calls, so don't read it then
+    if inner.busy.load(Ordering::Acquire) {
+        return JxlDecoderStateTag::Processing;
+    }
     match &inner.state {
-        DecoderState::WithImageInfo(d) => {
-            d.xmp_boxes().map_or(0, |boxes| boxes.len() as u32)
-        }
-        _ => 0,
+        DecoderState::Initialized(_) => JxlDecoderStateTag::Initialized,
+        DecoderState::WithImageInfo(_) => JxlDecoderStateTag::WithImageInfo,
+        DecoderState::WithFrameInfo(_) => JxlDecoderStateTag::WithFrameInfo,
+        DecoderState::Processing => JxlDecoderStateTag::Processing,
     }
 }
 
-/// Gets the number of JUMBF boxes in the image.
+/// Gets the error message of the last decoding call on this decoder.
 ///
-/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+/// `jxl_get_last_error` is per thread, so it can't be read from a different
+/// thread than the failing call, as happens when an async continuation
+/// resumes elsewhere. This copy is kept on the decoder: it is updated by every
+/// `jxl_decoder_process`, `jxl_decoder_read_pixels`,
+/// `jxl_decoder_read_pixels_with_extra_channels`,
+/// `jxl_decoder_read_single_channel` and `jxl_decoder_skip_frame` call, and
+/// can be read from any thread. A successful call clears it, and a call
+/// rejected as `Busy` leaves it as it is.
+///
+/// # Arguments
+/// * `decoder` - The decoder instance.
+/// * `buffer` - Buffer to write the error message to, or null to query the length.
+/// * `buffer_size` - Size of the buffer in bytes.
 ///
 /// # Returns
-/// The number of JUMBF boxes, or 0 if none or not accessible.
+/// The length of the error message (excluding null terminator), or 0 if the
+/// last decoding call succeeded. If the buffer is too small, the message is
+/// truncated.
 ///
 /// # Safety
-/// The decoder pointer must be valid.
+/// - `decoder` must be valid.
+/// - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_get_jumbf_box_count(
+pub unsafe extern "C" fn jxl_decoder_get_last_error(
     decoder: *const NativeDecoderHandle,
-) -> u32 {
+    buffer: *mut c_char,
+    buffer_size: usize,
+) -> usize {
     let inner = get_decoder_ref_silent!(decoder, 0);
 
-    match &inner.state {
-        DecoderState::WithImageInfo(d) => {
-            d.jumbf_boxes().map_or(0, |boxes| boxes.len() as u32)
-        }
-        _ => 0,
-    }
+    let last_error = inner
+        .last_error
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    unsafe { write_error_message(&last_error.1, buffer, buffer_size) }
 }
 
-/// Gets EXIF data from a specific box by index.
+/// Gets the status of the last decoding call on this decoder, like
+/// `jxl_get_last_error_status` but readable from any thread. See
+/// `jxl_decoder_get_last_error`.
 ///
-/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
-/// The returned pointer is valid until the decoder is reset, rewound, or freed.
+/// # Safety
+/// `decoder` must be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_last_error_status(
+    decoder: *const NativeDecoderHandle,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+
+    inner
+        .last_error
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .0
+}
+
+/// Gets the SHA-256 digest of the color buffer from the last decoded frame.
 ///
-/// # Arguments
-/// * `decoder` - The decoder instance (mutable for caching).
-/// * `index` - Zero-based box index.
-/// * `data_out` - Output pointer for EXIF data bytes.
-/// * `length_out` - Output for EXIF data length.
-/// * `is_brotli_compressed` - Output for brotli compression flag (true if brob box).
+/// The digest covers the pixel rows written by `jxl_decoder_read_pixels`,
+/// without any `RowAlignment` padding, so it depends on the pixel format but
+/// not the stride. Intended for
+/// comparing against reference hashes in conformance tests. Requires the
+/// `sha2` feature.
 ///
 /// # Returns
-/// - `Success` if EXIF data is available.
-/// - `InvalidState` if called before basic info is available.
-/// - `InvalidArgument` if index is out of range.
-/// - `Error` if no EXIF data exists in the image.
+/// - `Success` with the digest written to `out`.
+/// - `InvalidState` if no frame has been decoded since the last reset or rewind.
+/// - `Error` if the `sha2` feature is not compiled in.
 ///
 /// # Safety
 /// - `decoder` must be valid.
-/// - Output pointers must be writable.
+/// - `out` must be valid for writes of 32 bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_get_exif_box_at(
-    decoder: *mut NativeDecoderHandle,
-    index: u32,
-    data_out: *mut *const u8,
-    length_out: *mut usize,
-    is_brotli_compressed: *mut bool,
+pub unsafe extern "C" fn jxl_decoder_last_frame_hash(
+    decoder: *const NativeDecoderHandle,
+    out: *mut u8,
 ) -> JxlStatus {
-    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
-
-    // Populate cache if needed
-    if inner.exif_boxes_cache.is_none() {
-        let boxes = match &inner.state {
-            DecoderState::WithImageInfo(d) => d.exif_boxes(),
-            _ => {
-                set_last_error("EXIF data not accessible - call jxl_decoder_process until HaveBasicInfo");
-                return JxlStatus::InvalidState;
-            }
-        };
-
-        let Some(boxes) = boxes else {
-            set_last_error("Image does not contain EXIF data");
-            return JxlStatus::Error;
-        };
-
-        if boxes.is_empty() {
-            set_last_error("Image does not contain EXIF data");
-            return JxlStatus::Error;
-        }
-
-        // Cache all boxes with compression flag
-        inner.exif_boxes_cache = Some(
-            boxes
-                .iter()
-                .map(|b| CachedMetadataBox {
-                    data: b.data.clone(),
-                    is_brotli_compressed: b.is_brotli_compressed,
-                })
-                .collect(),
-        );
-    }
-
-    let cached = inner.exif_boxes_cache.as_ref().unwrap();
-    let idx = index as usize;
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
 
-    if idx >= cached.len() {
-        set_last_error(format!("EXIF box index {} out of range (max {})", index, cached.len() - 1));
+    if out.is_null() {
+        set_last_error("Null output pointer");
         return JxlStatus::InvalidArgument;
     }
 
-    clear_last_error();
+    if !cfg!(feature = "sha2") {
+        set_last_error("sha2 support not compiled in");
+        return JxlStatus::Error;
+    }
 
-    let cached_box = &cached[idx];
+    let Some(hash) = inner.last_frame_hash else {
+        set_last_error("No frame has been decoded");
+        return JxlStatus::InvalidState;
+    };
 
-    if let Some(out) = unsafe { data_out.as_mut() } {
-        *out = cached_box.data.as_ptr();
-    }
-    if let Some(out) = unsafe { length_out.as_mut() } {
-        *out = cached_box.data.len();
-    }
-    if let Some(out) = unsafe { is_brotli_compressed.as_mut() } {
-        *out = cached_box.is_brotli_compressed;
+    clear_last_error();
+    unsafe {
+        std::ptr::copy_nonoverlapping(hash.as_ptr(), out, hash.len());
     }
 
     JxlStatus::Success
 }
 
-/// Gets XML/XMP data from a specific box by index.
+/// Computes a luminance histogram of the last decoded frame.
 ///
-/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
-/// The returned pointer is valid until the decoder is reset, rewound, or freed.
+/// Luminance is taken from the color output as written, using BT.709
+/// weights for RGB, with integer formats normalized to [0, 1]. The histogram
+/// has `bins` equal-width bins over [0, 1]; float values outside the range
+/// are counted in the first or last bin. Alpha is ignored. Requires the
+/// `LuminanceStats` option, which records the statistics as each frame is
+/// decoded, so no further pass over the pixels is needed.
 ///
 /// # Arguments
-/// * `decoder` - The decoder instance (mutable for caching).
-/// * `index` - Zero-based box index.
-/// * `data_out` - Output pointer for XML data bytes.
-/// * `length_out` - Output for XML data length.
-/// * `is_brotli_compressed` - Output for brotli compression flag (true if brob box).
+/// * `decoder` - The decoder instance.
+/// * `bins` - Number of bins, from 1 to 65536.
+/// * `out` - Receives `bins` pixel counts.
 ///
 /// # Returns
-/// - `Success` if XML data is available.
-/// - `InvalidState` if called before basic info is available.
-/// - `InvalidArgument` if index is out of range.
-/// - `Error` if no XML data exists in the image.
+/// - `Success` with the histogram written to `out`.
+/// - `InvalidArgument` if `bins` is out of range.
+/// - `InvalidState` if the option is not enabled or no frame has been
+///   decoded since the last reset or rewind.
 ///
 /// # Safety
 /// - `decoder` must be valid.
-/// - Output pointers must be writable.
+/// - `out` must be valid for writes of `bins` values.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_get_xml_box_at(
-    decoder: *mut NativeDecoderHandle,
-    index: u32,
-    data_out: *mut *const u8,
-    length_out: *mut usize,
-    is_brotli_compressed: *mut bool,
+pub unsafe extern "C" fn jxl_decoder_compute_histogram(
+    decoder: *const NativeDecoderHandle,
+    bins: u32,
+    out: *mut u32,
 ) -> JxlStatus {
-    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
-
-    // Populate cache if needed
-    if inner.xml_boxes_cache.is_none() {
-        let boxes = match &inner.state {
-            DecoderState::WithImageInfo(d) => d.xmp_boxes(),
-            _ => {
-                set_last_error("XML data not accessible - call jxl_decoder_process until HaveBasicInfo");
-                return JxlStatus::InvalidState;
-            }
-        };
-
-        let Some(boxes) = boxes else {
-            set_last_error("Image does not contain XML data");
-            return JxlStatus::Error;
-        };
-
-        if boxes.is_empty() {
-            set_last_error("Image does not contain XML data");
-            return JxlStatus::Error;
-        }
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
 
-        // Cache all boxes with compression flag
-        inner.xml_boxes_cache = Some(
-            boxes
-                .iter()
-                .map(|b| CachedMetadataBox {
-                    data: b.data.clone(),
-                    is_brotli_compressed: b.is_brotli_compressed,
-                })
-                .collect(),
-        );
+    if out.is_null() {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
     }
 
-    let cached = inner.xml_boxes_cache.as_ref().unwrap();
-    let idx = index as usize;
-
-    if idx >= cached.len() {
-        set_last_error(format!("XML box index {} out of range (max {})", index, cached.len() - 1));
+    if bins == 0 || bins as usize > MAX_HISTOGRAM_BINS {
+        set_last_error(format!(
+            "Bin count must be between 1 and {}",
+            MAX_HISTOGRAM_BINS
+        ));
         return JxlStatus::InvalidArgument;
     }
 
+    if !inner.options.LuminanceStats {
+        set_last_error("LuminanceStats option is not enabled");
+        return JxlStatus::InvalidState;
+    }
+
+    let Some(stats) = &inner.last_frame_stats else {
+        set_last_error("No frame has been decoded");
+        return JxlStatus::InvalidState;
+    };
+
     clear_last_error();
+    stats.histogram(unsafe { slice::from_raw_parts_mut(out, bins as usize) });
 
-    let cached_box = &cached[idx];
+    JxlStatus::Success
+}
 
-    if let Some(out) = unsafe { data_out.as_mut() } {
-        *out = cached_box.data.as_ptr();
+/// Gets the luminance range and mean of the last decoded frame.
+///
+/// Luminance is computed as for `jxl_decoder_compute_histogram`, but values
+/// are not clamped: with a float output format, HDR highlights keep their
+/// magnitude, so a linear float output gives the peak to pick a tone-mapping
+/// `SourceIntensityTargetOverride` from. NaN and infinite values are left
+/// out. Requires the `LuminanceStats` option.
+///
+/// # Returns
+/// - `Success` with the statistics written to `out`.
+/// - `InvalidState` if the option is not enabled or no frame has been
+///   decoded since the last reset or rewind.
+///
+/// # Safety
+/// - `decoder` must be valid.
+/// - `out` must point to a writable `JxlLuminanceStats`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_get_luminance_stats(
+    decoder: *const NativeDecoderHandle,
+    out: *mut JxlLuminanceStats,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+
+    if out.is_null() {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
     }
-    if let Some(out) = unsafe { length_out.as_mut() } {
-        *out = cached_box.data.len();
+
+    if !inner.options.LuminanceStats {
+        set_last_error("LuminanceStats option is not enabled");
+        return JxlStatus::InvalidState;
     }
-    if let Some(out) = unsafe { is_brotli_compressed.as_mut() } {
-        *out = cached_box.is_brotli_compressed;
+
+    let Some(stats) = &inner.last_frame_stats else {
+        set_last_error("No frame has been decoded");
+        return JxlStatus::InvalidState;
+    };
+
+    clear_last_error();
+    unsafe {
+        *out = stats.summary();
     }
 
     JxlStatus::Success
 }
 
-/// Gets JUMBF data from a specific box by index.
-///
-/// Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
-/// The returned pointer is valid until the decoder is reset, rewound, or freed.
+/// Gets the bounding box of opaque content in the last decoded frame.
 ///
-/// # Arguments
-/// * `decoder` - The decoder instance (mutable for caching).
-/// * `index` - Zero-based box index.
-/// * `data_out` - Output pointer for JUMBF data bytes.
-/// * `length_out` - Output for JUMBF data length.
-/// * `is_brotli_compressed` - Output for brotli compression flag (true if brob box).
+/// Covers the pixels whose alpha exceeds the `OpaqueAlphaThreshold` option,
+/// in output coordinates, so a sprite can be cropped to it without scanning
+/// the buffer again. A frame with no such pixels gives an empty rectangle,
+/// and a color type without alpha gives the whole frame. Requires the
+/// `OpaqueBounds` option.
 ///
 /// # Returns
-/// - `Success` if JUMBF data is available.
-/// - `InvalidState` if called before basic info is available.
-/// - `InvalidArgument` if index is out of range.
-/// - `Error` if no JUMBF data exists in the image.
+/// - `Success` with the bounds written to `out`.
+/// - `InvalidState` if the option is not enabled or no frame has been
+///   decoded since the last reset or rewind.
 ///
 /// # Safety
 /// - `decoder` must be valid.
-/// - Output pointers must be writable.
+/// - `out` must point to a writable `JxlRect`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_get_jumbf_box_at(
-    decoder: *mut NativeDecoderHandle,
-    index: u32,
-    data_out: *mut *const u8,
-    length_out: *mut usize,
-    is_brotli_compressed: *mut bool,
+pub unsafe extern "C" fn jxl_decoder_get_opaque_bounds(
+    decoder: *const NativeDecoderHandle,
+    out: *mut JxlRect,
 ) -> JxlStatus {
-    let inner = get_decoder_mut!(decoder, JxlStatus::InvalidArgument);
-
-    // Populate cache if needed
-    if inner.jumbf_boxes_cache.is_none() {
-        let boxes = match &inner.state {
-            DecoderState::WithImageInfo(d) => d.jumbf_boxes(),
-            _ => {
-                set_last_error("JUMBF data not accessible - call jxl_decoder_process until HaveBasicInfo");
-                return JxlStatus::InvalidState;
-            }
-        };
-
-        let Some(boxes) = boxes else {
-            set_last_error("Image does not contain JUMBF data");
-            return JxlStatus::Error;
-        };
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
 
-        if boxes.is_empty() {
-            set_last_error("Image does not contain JUMBF data");
-            return JxlStatus::Error;
-        }
+    let Some(out) = (unsafe { out.as_mut() }) else {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    };
 
-        // Cache all boxes with compression flag
-        inner.jumbf_boxes_cache = Some(
-            boxes
-                .iter()
-                .map(|b| CachedMetadataBox {
-                    data: b.data.clone(),
-                    is_brotli_compressed: b.is_brotli_compressed,
-                })
-                .collect(),
-        );
+    if !inner.options.OpaqueBounds {
+        set_last_error("OpaqueBounds option is not enabled");
+        return JxlStatus::InvalidState;
     }
 
-    let cached = inner.jumbf_boxes_cache.as_ref().unwrap();
-    let idx = index as usize;
-
-    if idx >= cached.len() {
-        set_last_error(format!("JUMBF box index {} out of range (max {})", index, cached.len() - 1));
-        return JxlStatus::InvalidArgument;
-    }
+    let Some(bounds) = inner.last_frame_opaque_bounds else {
+        set_last_error("No frame has been decoded");
+        return JxlStatus::InvalidState;
+    };
 
     clear_last_error();
+    *out = bounds;
 
-    let cached_box = &cached[idx];
+    JxlStatus::Success
+}
 
-    if let Some(out) = unsafe { data_out.as_mut() } {
-        *out = cached_box.data.as_ptr();
+/// Gets whether the image has transparency, e.g. to decide whether to draw a
+/// checkerboard behind it.
+///
+/// An image without an alpha extra channel is opaque. With the
+/// `DetectTransparency` option, once a frame has been decoded with alpha in
+/// the color output, the answer is refined from the pixels: the image is
+/// opaque while every frame decoded so far has alpha at the maximum
+/// everywhere. Otherwise an alpha channel counts as transparency.
+///
+/// # Returns
+/// - `-1` if unknown: basic info is not yet available or `decoder` is null.
+/// - `0` if the image is fully opaque.
+/// - `1` if the image has transparency.
+///
+/// # Safety
+/// `decoder` must be valid or null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_decoder_has_transparency(decoder: *const NativeDecoderHandle) -> i32 {
+    let inner = get_decoder_ref_silent!(decoder, -1);
+
+    if inner.basic_info.is_none() {
+        return -1;
     }
-    if let Some(out) = unsafe { length_out.as_mut() } {
-        *out = cached_box.data.len();
+    let has_alpha = inner
+        .extra_channels
+        .iter()
+        .any(|ec| ec.ChannelType == JxlExtraChannelType::Alpha);
+    if !has_alpha {
+        return 0;
     }
-    if let Some(out) = unsafe { is_brotli_compressed.as_mut() } {
-        *out = cached_box.is_brotli_compressed;
+    match inner.image_transparent {
+        Some(false) => 0,
+        Some(true) | None => 1,
     }
-
-    JxlStatus::Success
 }
 
-// ============================================================================
-// Metadata Box Compression Status (deprecated - use get_*_box_at with is_brotli_compressed)
-// ============================================================================
-
-/// Returns whether the EXIF box at the given index is brotli-compressed.
+/// Gets the time spent in each decoding phase.
 ///
-/// Only valid after `jxl_decoder_get_exif_box_at` has been called to populate the cache.
-///
-/// # Arguments
-/// * `decoder` - The decoder instance.
-/// * `index` - Zero-based box index.
+/// Times accumulate over all `jxl_decoder_process`, `jxl_decoder_read_pixels`
+/// and `jxl_decoder_read_pixels_with_extra_channels` calls since the decoder
+/// was created, reset, or rewound. Requires the `timings` feature; builds
+/// without it carry no timing overhead.
 ///
 /// # Returns
-/// - `true` if the box was brotli-compressed in the file (brob box).
-/// - `false` if uncompressed or if cache not populated.
+/// - `Success` with the timings written to `out`.
+/// - `Error` if the `timings` feature is not compiled in.
 ///
 /// # Safety
 /// - `decoder` must be valid.
+/// - `out` must point to a writable `JxlDecodeTimings`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_is_exif_box_compressed(
+pub unsafe extern "C" fn jxl_decoder_get_timings(
     decoder: *const NativeDecoderHandle,
-    index: u32,
-) -> bool {
-    let inner = get_decoder_ref_silent!(decoder, false);
-    inner
-        .exif_boxes_cache
-        .as_ref()
-        .and_then(|boxes| boxes.get(index as usize))
-        .map(|b| b.is_brotli_compressed)
-        .unwrap_or(false)
+    out: *mut JxlDecodeTimings,
+) -> JxlStatus {
+    let inner = get_decoder_ref!(decoder, JxlStatus::InvalidArgument);
+
+    let Some(out) = (unsafe { out.as_mut() }) else {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    };
+
+    #[cfg(feature = "timings")]
+    {
+        clear_last_error();
+        *out = inner.timings.get();
+        JxlStatus::Success
+    }
+    #[cfg(not(feature = "timings"))]
+    {
+        let _ = (inner, out);
+        set_last_error("timings support not compiled in");
+        JxlStatus::Error
+    }
 }
 
-/// Returns whether the XML box at the given index is brotli-compressed.
+// ============================================================================
+// Signature Check
+// ============================================================================
+
+/// Returns the number of leading bytes that always suffice for
+/// `jxl_signature_check` to return a result other than `NotEnoughBytes`.
 ///
-/// Only valid after `jxl_decoder_get_xml_box_at` has been called to populate the cache.
+/// This is the length of the container signature box; a codestream is
+/// recognized from its first 2 bytes.
+#[unsafe(no_mangle)]
+pub extern "C" fn jxl_signature_min_bytes() -> usize {
+    checksum::CONTAINER_SIGNATURE.len()
+}
+
+/// Checks if data appears to be a JPEG XL file.
 ///
-/// # Arguments
-/// * `decoder` - The decoder instance.
-/// * `index` - Zero-based box index.
+/// Only needs the first `jxl_signature_min_bytes` bytes to determine.
 ///
-/// # Returns
-/// - `true` if the box was brotli-compressed in the file (brob box).
-/// - `false` if uncompressed or if cache not populated.
+/// # Arguments
+/// * `data` - The start of the file.
+/// * `size` - Number of bytes available at `data`.
+/// * `box_start_out` - Optional output for where parsing continues: for a
+///   `Container`, the offset of the first box after the signature box (12);
+///   otherwise 0. May be null.
 ///
 /// # Safety
-/// - `decoder` must be valid.
+/// - `data` must be valid for reads of `size` bytes.
+/// - If `box_start_out` is not null, it must point to a writable `usize`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_is_xml_box_compressed(
-    decoder: *const NativeDecoderHandle,
-    index: u32,
-) -> bool {
-    let inner = get_decoder_ref_silent!(decoder, false);
-    inner
-        .xml_boxes_cache
-        .as_ref()
-        .and_then(|boxes| boxes.get(index as usize))
-        .map(|b| b.is_brotli_compressed)
-        .unwrap_or(false)
+pub unsafe extern "C" fn jxl_signature_check(
+    data: *const u8,
+    size: usize,
+    box_start_out: *mut usize,
+) -> JxlSignature {
+    let signature = if data.is_null() || size == 0 {
+        JxlSignature::NotEnoughBytes
+    } else {
+        let bytes = unsafe { slice::from_raw_parts(data, size) };
+        match jxl::api::check_signature(bytes) {
+            ProcessingResult::Complete {
+                result: Some(sig_type),
+            } => match sig_type {
+                jxl::api::JxlSignatureType::Codestream => JxlSignature::Codestream,
+                jxl::api::JxlSignatureType::Container => JxlSignature::Container,
+            },
+            ProcessingResult::Complete { result: None } => JxlSignature::Invalid,
+            ProcessingResult::NeedsMoreInput { .. } => JxlSignature::NotEnoughBytes,
+        }
+    };
+
+    if let Some(out) = unsafe { box_start_out.as_mut() } {
+        *out = match signature {
+            JxlSignature::Container => checksum::CONTAINER_SIGNATURE.len(),
+            _ => 0,
+        };
+    }
+
+    signature
 }
 
-/// Returns whether the JUMBF box at the given index is brotli-compressed.
+/// Computes a CRC32 (IEEE) of JPEG XL data, for use as a cheap cache key.
 ///
-/// Only valid after `jxl_decoder_get_jumbf_box_at` has been called to populate the cache.
+/// With `codestream_only`, container boxes other than `jxlc`/`jxlp` are
+/// skipped, so metadata edits don't change the result and a container hashes
+/// the same as its bare codestream. Otherwise all bytes are hashed.
 ///
-/// # Arguments
-/// * `decoder` - The decoder instance.
-/// * `index` - Zero-based box index.
+/// # Safety
+/// `data` must be valid for reads of `size` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_signature_crc32(
+    data: *const u8,
+    size: usize,
+    codestream_only: bool,
+) -> u32 {
+    if data.is_null() || size == 0 {
+        return 0;
+    }
+
+    let bytes = unsafe { slice::from_raw_parts(data, size) };
+    checksum::input_crc32(bytes, codestream_only)
+}
+
+/// Determines whether JPEG XL data is animated from its image header alone.
+///
+/// Only the header at the start of the codestream is parsed, so directory
+/// scanners can flag animations without creating a decoder. Bare codestreams
+/// and containers (including split `jxlp` codestreams) are supported.
 ///
 /// # Returns
-/// - `true` if the box was brotli-compressed in the file (brob box).
-/// - `false` if uncompressed or if cache not populated.
+/// - `Success` with `out` set if the header was parsed.
+/// - `NeedMoreInput` if the header isn't fully contained in `data`.
+/// - `InvalidArgument` if `out` is null, or `data` is null with a nonzero
+///   `size`.
+/// - `Error` if `data` is not JPEG XL or the header is malformed.
 ///
 /// # Safety
-/// - `decoder` must be valid.
+/// - `data` must be valid for reads of `size` bytes.
+/// - `out` must point to a writable `bool`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_decoder_is_jumbf_box_compressed(
-    decoder: *const NativeDecoderHandle,
-    index: u32,
-) -> bool {
-    let inner = get_decoder_ref_silent!(decoder, false);
-    inner
-        .jumbf_boxes_cache
-        .as_ref()
-        .and_then(|boxes| boxes.get(index as usize))
-        .map(|b| b.is_brotli_compressed)
-        .unwrap_or(false)
-}
+pub unsafe extern "C" fn jxl_peek_is_animated(
+    data: *const u8,
+    size: usize,
+    out: *mut bool,
+) -> JxlStatus {
+    if data.is_null() && size > 0 {
+        set_last_error("Null input data");
+        return JxlStatus::InvalidArgument;
+    }
+    let Some(out) = (unsafe { out.as_mut() }) else {
+        set_last_error("Null output pointer");
+        return JxlStatus::InvalidArgument;
+    };
 
-// ============================================================================
-// Signature Check
-// ============================================================================
+    let bytes = if size == 0 {
+        &[][..]
+    } else {
+        unsafe { slice::from_raw_parts(data, size) }
+    };
+    let Some(header) = read_file_header(bytes) else {
+        return match jxl::api::check_signature(bytes) {
+            ProcessingResult::NeedsMoreInput { .. } => {
+                clear_last_error();
+                JxlStatus::NeedMoreInput
+            }
+            _ => {
+                set_last_error("Data is not JPEG XL");
+                JxlStatus::Error
+            }
+        };
+    };
 
-/// Checks if data appears to be a JPEG XL file.
+    match header {
+        Ok(header) => {
+            clear_last_error();
+            *out = header.image_metadata.animation.is_some();
+            JxlStatus::Success
+        }
+        Err(jxl::error::Error::OutOfBounds(_)) => {
+            clear_last_error();
+            JxlStatus::NeedMoreInput
+        }
+        Err(e) => {
+            set_last_error(format!("Invalid image header: {}", e));
+            JxlStatus::Error
+        }
+    }
+}
+
+/// Checks the container structure of complete JPEG XL data before decoding.
 ///
-/// Only needs the first 12 bytes to determine.
+/// Only box headers are parsed, so ingestion pipelines can cheaply reject
+/// truncated or malformed files with a precise reason instead of a generic
+/// decode error later. The codestream itself is not validated. Bare
+/// codestreams always pass.
+///
+/// # Returns
+/// - `Success` if the container is well-formed.
+/// - `InvalidArgument` if `data` is null or empty.
+/// - `Error` otherwise; `jxl_get_last_error` names the offending box and its
+///   offset.
 ///
 /// # Safety
 /// `data` must be valid for reads of `size` bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn jxl_signature_check(data: *const u8, size: usize) -> JxlSignature {
+pub unsafe extern "C" fn jxl_validate_container(data: *const u8, size: usize) -> JxlStatus {
     if data.is_null() || size == 0 {
-        return JxlSignature::NotEnoughBytes;
+        set_last_error("Null or empty input data");
+        return JxlStatus::InvalidArgument;
     }
 
     let bytes = unsafe { slice::from_raw_parts(data, size) };
-    
-    match jxl::api::check_signature(bytes) {
-        ProcessingResult::Complete { result: Some(sig_type) } => {
-            match sig_type {
-                jxl::api::JxlSignatureType::Codestream => JxlSignature::Codestream,
-                jxl::api::JxlSignatureType::Container => JxlSignature::Container,
-            }
+    match validate_container(bytes) {
+        Ok(()) => {
+            clear_last_error();
+            JxlStatus::Success
+        }
+        Err(e) => {
+            set_last_error(e);
+            JxlStatus::Error
+        }
+    }
+}
+
+/// Walks a JUMBF structure, listing its boxes in file order.
+///
+/// `data` may be a whole `jumb` superbox or a JUMBF box payload as returned
+/// by `jxl_decoder_get_jumbf_box_at`. Superbox contents are listed after the
+/// superbox, one `Depth` level deeper. Offsets in the entries are relative to
+/// `data`, so labels and payloads are read from the caller's buffer.
+///
+/// Call with `max_entries` of 0 to count the boxes, then again with a buffer
+/// of that size.
+///
+/// # Returns
+/// The total number of boxes, which may exceed `max_entries`; only the first
+/// `max_entries` are written. Returns 0 if `data` is null, empty or
+/// malformed; `jxl_get_last_error` names the offending box and its offset.
+///
+/// # Safety
+/// - `data` must be valid for reads of `len` bytes.
+/// - `out_entries` must be valid for writes of `max_entries` entries, or
+///   null if `max_entries` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_jumbf_parse(
+    data: *const u8,
+    len: usize,
+    out_entries: *mut JxlJumbfEntry,
+    max_entries: usize,
+) -> usize {
+    if data.is_null() || len == 0 {
+        set_last_error("Null or empty input data");
+        return 0;
+    }
+    if out_entries.is_null() && max_entries > 0 {
+        set_last_error("Null output entries");
+        return 0;
+    }
+
+    let bytes = unsafe { slice::from_raw_parts(data, len) };
+    let boxes = match parse_jumbf(bytes) {
+        Ok(boxes) => boxes,
+        Err(e) => {
+            set_last_error(e);
+            return 0;
         }
-        ProcessingResult::Complete { result: None } => JxlSignature::Invalid,
-        ProcessingResult::NeedsMoreInput { .. } => JxlSignature::NotEnoughBytes,
+    };
+
+    for (i, b) in boxes.iter().take(max_entries).enumerate() {
+        let label = b.label.clone().unwrap_or_default();
+        let entry = JxlJumbfEntry {
+            Offset: b.payload.start as u64,
+            Length: b.payload.len() as u64,
+            LabelOffset: label.start as u64,
+            LabelLength: label.len() as u32,
+            Depth: b.depth,
+            Type: b.box_type,
+        };
+        unsafe { out_entries.add(i).write(entry) };
     }
+
+    clear_last_error();
+    boxes.len()
 }
 
 #[cfg(test)]
 #[path = "decoder_tests.rs"]
-mod tests;
\ No newline at end of file
+mod tests;
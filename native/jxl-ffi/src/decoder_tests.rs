@@ -6,6 +6,7 @@
 //! Unit tests for the decoder module.
 
 use super::*;
+use crate::conversions::frame_duration_ms;
 
 #[test]
 fn test_convert_to_jxl_pixel_format_rgba_with_alpha() {
@@ -18,6 +19,7 @@ fn test_convert_to_jxl_pixel_format_rgba_with_alpha() {
     let extra_channels = vec![JxlExtraChannelInfo {
         ChannelType: JxlExtraChannelType::Alpha,
         AlphaAssociated: false,
+        DimShift: 0,
     }];
 
     // When using RGBA with alpha as extra channel, alpha should be None
@@ -42,6 +44,7 @@ fn test_convert_to_jxl_pixel_format_rgb_with_alpha() {
     let extra_channels = vec![JxlExtraChannelInfo {
         ChannelType: JxlExtraChannelType::Alpha,
         AlphaAssociated: false,
+        DimShift: 0,
     }];
 
     // When using RGB (no alpha in color), alpha should be Some
@@ -54,3 +57,2934 @@ fn test_convert_to_jxl_pixel_format_rgb_with_alpha() {
         "Alpha should be Some when using RGB"
     );
 }
+
+#[test]
+fn test_convert_to_jxl_pixel_format_bgra_skips_only_first_alpha() {
+    let format = JxlPixelFormat {
+        ColorType: JxlColorType::Bgra,
+        DataFormat: JxlDataFormat::Uint8,
+        Endianness: JxlEndianness::Native,
+    };
+
+    let alpha = JxlExtraChannelInfo {
+        ChannelType: JxlExtraChannelType::Alpha,
+        AlphaAssociated: false,
+        DimShift: 0,
+    };
+    let extra_channels = vec![alpha.clone(), alpha];
+
+    // BGRA carries the first alpha channel interleaved like RGBA; a second
+    // alpha channel still needs its own buffer
+    let pixel_format = convert_to_jxl_pixel_format(&format, &extra_channels, false);
+
+    assert_eq!(pixel_format.extra_channel_format.len(), 2);
+    assert!(
+        pixel_format.extra_channel_format[0].is_none(),
+        "First alpha should be None when using BGRA"
+    );
+    assert!(
+        pixel_format.extra_channel_format[1].is_some(),
+        "Second alpha should be Some when using BGRA"
+    );
+}
+
+fn int_bit_depth(bits: u32) -> JxlBitDepth {
+    JxlBitDepth {
+        Type: JxlBitDepthType::Int,
+        BitsPerSample: bits,
+        ExponentBitsPerSample: 0,
+    }
+}
+
+#[test]
+fn test_output_bit_depth_10_bit_source() {
+    let source = int_bit_depth(10);
+
+    let u16_depth = output_bit_depth(&source, JxlDataFormat::Uint16);
+    assert_eq!(u16_depth.Type, JxlBitDepthType::Int);
+    assert_eq!(u16_depth.BitsPerSample, 10);
+
+    let u8_depth = output_bit_depth(&source, JxlDataFormat::Uint8);
+    assert_eq!(u8_depth.BitsPerSample, 8);
+}
+
+#[test]
+fn test_output_bit_depth_12_bit_source() {
+    let source = int_bit_depth(12);
+
+    assert_eq!(
+        output_bit_depth(&source, JxlDataFormat::Uint16).BitsPerSample,
+        12
+    );
+    assert_eq!(
+        output_bit_depth(&source, JxlDataFormat::Uint8).BitsPerSample,
+        8
+    );
+
+    let f16_depth = output_bit_depth(&source, JxlDataFormat::Float16);
+    assert_eq!(f16_depth.Type, JxlBitDepthType::Float);
+    assert_eq!(f16_depth.BitsPerSample, 16);
+    assert_eq!(f16_depth.ExponentBitsPerSample, 5);
+}
+
+#[test]
+fn test_output_bit_depth_float_source() {
+    let source = JxlBitDepth {
+        Type: JxlBitDepthType::Float,
+        BitsPerSample: 16,
+        ExponentBitsPerSample: 5,
+    };
+
+    // A half-float source has an 11-bit significand
+    assert_eq!(
+        output_bit_depth(&source, JxlDataFormat::Uint16).BitsPerSample,
+        11
+    );
+}
+
+#[test]
+fn test_scale_to_full_range() {
+    // A 10-bit image decoded to Uint16
+    let data = include_bytes!("../../../test/TestData/hdr_pq_test.jxl");
+    let decode = |scale_to_full_range| {
+        let options = JxlDecodeOptions {
+            PixelFormat: JxlPixelFormat {
+                DataFormat: JxlDataFormat::Uint16,
+                ColorType: JxlColorType::Rgb,
+                Endianness: JxlEndianness::Native,
+            },
+            ScaleToFullRange: scale_to_full_range,
+            ..JxlDecodeOptions::default()
+        };
+        decode_all_frames_with(data, &options)
+            .chunks_exact(2)
+            .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+            .collect::<Vec<_>>()
+    };
+
+    let full = decode(true);
+    let source = decode(false);
+    assert_eq!(full.iter().max(), Some(&65535));
+    assert_eq!(source.iter().max(), Some(&1023));
+    for (&full, &source) in full.iter().zip(&source) {
+        assert!((source as f32 * 65535.0 / 1023.0 - full as f32).abs() <= 0.5);
+    }
+}
+
+#[test]
+fn test_duration_ms_to_ticks_inverts_upstream_duration() {
+    // NTSC rate: one tick is 1001/30000 s, which has no exact millisecond value
+    let animation = JxlAnimation {
+        TpsNumerator: 30000,
+        TpsDenominator: 1001,
+        ..JxlAnimation::default()
+    };
+    for ticks in [0u32, 1, 7, 1001, 30000, u32::MAX] {
+        let duration_ms = ticks as f64 * 1000.0 * 1001.0 / 30000.0;
+        assert_eq!(duration_ms_to_ticks(duration_ms, &animation), ticks);
+    }
+}
+
+#[test]
+fn test_duration_ms_to_ticks_without_animation() {
+    assert_eq!(duration_ms_to_ticks(100.0, &JxlAnimation::default()), 0);
+}
+
+#[test]
+fn test_zero_tick_rate_terms() {
+    // Crafted headers with a zero tick rate term, which the bitstream can't
+    // encode but callers can pass in
+    for (numerator, denominator) in [(30000, 0), (0, 1001)] {
+        let animation = JxlAnimation {
+            TpsNumerator: numerator,
+            TpsDenominator: denominator,
+            ..JxlAnimation::default()
+        };
+        assert_eq!(duration_ms_to_ticks(100.0, &animation), 0);
+    }
+
+    // The durations upstream computes from them
+    let animation = JxlAnimation {
+        TpsNumerator: 30000,
+        TpsDenominator: 1001,
+        ..JxlAnimation::default()
+    };
+    for duration in [f64::INFINITY, f64::NAN] {
+        assert_eq!(duration_ms_to_ticks(duration, &animation), 0);
+        assert_eq!(frame_duration_ms(Some(duration)), 0.0);
+    }
+    assert_eq!(frame_duration_ms(Some(-0.5)), 0.0);
+    assert_eq!(frame_duration_ms(None), 0.0);
+    assert_eq!(frame_duration_ms(Some(33.5)), 33.5);
+}
+
+#[test]
+fn test_thumbnail_size_fits_longest_side() {
+    assert_eq!(thumbnail_size(800, 600, 256), (256, 192));
+    assert_eq!(thumbnail_size(600, 800, 256), (192, 256));
+    assert_eq!(thumbnail_size(1000, 1, 100), (100, 1));
+}
+
+#[test]
+fn test_thumbnail_size_never_upscales() {
+    assert_eq!(thumbnail_size(3, 3, 256), (3, 3));
+    assert_eq!(thumbnail_size(256, 100, 256), (256, 100));
+}
+
+#[test]
+fn test_downscale_rgba8_ignores_transparent_color() {
+    // 2x1 image: opaque red next to a fully transparent green pixel
+    let src = [255, 0, 0, 255, 0, 255, 0, 0];
+    let dst = downscale_rgba8(&src, 2, 1, 1, 1);
+    assert_eq!(dst, [255, 0, 0, 128]);
+}
+
+#[test]
+fn test_downscale_rgba8_averages_box() {
+    let src: Vec<u8> = [10u8, 20, 30, 40]
+        .iter()
+        .flat_map(|&v| [v, v, v, 255])
+        .collect();
+    let dst = downscale_rgba8(&src, 2, 2, 1, 1);
+    assert_eq!(dst, [27, 27, 27, 255]);
+}
+
+#[test]
+fn test_downscale_rgba8_is_gamma_correct() {
+    // Black/white checkerboard: half the light of white, which is ~188 in
+    // sRGB. A naive average of the encoded values would give a darker 128.
+    let src: Vec<u8> = [0u8, 255, 255, 0]
+        .iter()
+        .flat_map(|&v| [v, v, v, 255])
+        .collect();
+    let naive = src.chunks_exact(4).map(|px| px[0] as u32).sum::<u32>() / 4;
+    let dst = downscale_rgba8(&src, 2, 2, 1, 1);
+    assert_eq!(naive, 127);
+    assert_eq!(dst, [188, 188, 188, 255]);
+}
+
+// ============================================================================
+// Golden Images
+// ============================================================================
+//
+// End-to-end checks that decode bundled fixtures through the FFI and compare
+// every frame against a raw reference in `test/TestData/golden/`. References
+// for libjxl-encoded fixtures are trusted decodes; the synthetic `*_lossless`
+// fixtures are lossless, so their references are the source pixels.
+
+/// Loads `test/TestData/<name>.jxl` and its `golden/<name>.raw` reference.
+macro_rules! golden {
+    ($name:literal) => {
+        (
+            &include_bytes!(concat!("../../../test/TestData/", $name, ".jxl"))[..],
+            &include_bytes!(concat!("../../../test/TestData/golden/", $name, ".raw"))[..],
+        )
+    };
+}
+
+/// 8-bit decode options for `color_type`, otherwise defaults.
+fn uint8_options(color_type: JxlColorType) -> JxlDecodeOptions {
+    JxlDecodeOptions {
+        PixelFormat: JxlPixelFormat {
+            DataFormat: JxlDataFormat::Uint8,
+            ColorType: color_type,
+            Endianness: JxlEndianness::Native,
+        },
+        ..JxlDecodeOptions::default()
+    }
+}
+
+/// Decodes every frame of `data` as 8-bit `color_type`, frames concatenated.
+fn decode_all_frames(data: &[u8], color_type: JxlColorType) -> Vec<u8> {
+    decode_all_frames_with(data, &uint8_options(color_type))
+}
+
+/// Decodes every frame of `data` with `options`, frames concatenated.
+fn decode_all_frames_with(data: &[u8], options: &JxlDecodeOptions) -> Vec<u8> {
+    let mut pixels = Vec::new();
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(options);
+        assert_eq!(
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len()),
+            JxlStatus::Success
+        );
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveBasicInfo
+                | JxlDecoderEvent::HaveFrameHeader
+                | JxlDecoderEvent::FrameComplete => {}
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    let mut frame = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+                    let event = jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+                    assert_eq!(event, JxlDecoderEvent::FrameComplete);
+                    pixels.extend_from_slice(&frame);
+                }
+                JxlDecoderEvent::Complete => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        jxl_decoder_destroy(decoder);
+    }
+    pixels
+}
+
+/// Asserts that `actual` matches `expected` within `tolerance[c]` for channel `c`.
+fn assert_within_tolerance(actual: &[u8], expected: &[u8], tolerance: &[u8]) {
+    assert_eq!(actual.len(), expected.len(), "Decoded size differs");
+    for (i, (&a, &e)) in actual.iter().zip(expected).enumerate() {
+        let channel = i % tolerance.len();
+        assert!(
+            a.abs_diff(e) <= tolerance[channel],
+            "Sample {} (channel {}) is {}, expected {} ± {}",
+            i,
+            channel,
+            a,
+            e,
+            tolerance[channel]
+        );
+    }
+}
+
+#[test]
+fn test_golden_srgb() {
+    let (data, reference) = golden!("3x3_srgb_lossless");
+    let pixels = decode_all_frames(data, JxlColorType::Rgb);
+    assert_within_tolerance(&pixels, reference, &[0, 0, 0]);
+}
+
+#[test]
+fn test_golden_srgb_aligned_rows() {
+    // 3 pixels of RGB8 is 9 bytes, padded to a 16-byte stride
+    let (data, reference) = golden!("3x3_srgb_lossless");
+    let options = JxlDecodeOptions {
+        RowAlignment: 16,
+        ..uint8_options(JxlColorType::Rgb)
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(jxl_decoder_get_buffer_size(decoder), 48);
+        assert_eq!(jxl_decoder_get_aligned_buffer_size(decoder, 16), 48);
+        assert_eq!(jxl_decoder_get_aligned_buffer_size(decoder, 0), 27);
+
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveFrameHeader => {}
+                JxlDecoderEvent::NeedOutputBuffer => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        let mut pixels = vec![0xAAu8; 48];
+        assert_eq!(
+            jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len()),
+            JxlDecoderEvent::FrameComplete
+        );
+        jxl_decoder_destroy(decoder);
+
+        for (row, expected) in pixels.chunks_exact(16).zip(reference.chunks_exact(9)) {
+            assert_within_tolerance(&row[..9], expected, &[0, 0, 0]);
+            assert!(row[9..].iter().all(|&b| b == 0xAA), "Padding was written");
+        }
+    }
+}
+
+#[test]
+fn test_golden_single_pixel() {
+    let (data, reference) = golden!("1x1_rgba_lossless");
+    assert_eq!(decode_all_frames(data, JxlColorType::Rgba), reference);
+    assert_eq!(
+        decode_all_frames(data, JxlColorType::Rgb),
+        reference[..3].to_vec()
+    );
+}
+
+#[test]
+fn test_golden_single_column() {
+    // One 4-byte pixel per row, padded to a 16-byte stride
+    let (data, reference) = golden!("1x7_rgba_lossless");
+    assert_eq!(decode_all_frames(data, JxlColorType::Rgba), reference);
+
+    let options = JxlDecodeOptions {
+        RowAlignment: 16,
+        ..uint8_options(JxlColorType::Rgba)
+    };
+    let pixels = decode_all_frames_with(data, &options);
+    assert_eq!(pixels.len(), 7 * 16);
+    for (row, expected) in pixels.chunks_exact(16).zip(reference.chunks_exact(4)) {
+        assert_eq!(&row[..4], expected);
+    }
+}
+
+#[test]
+fn test_golden_single_row() {
+    let (data, reference) = golden!("7x1_rgba_lossless");
+    assert_eq!(decode_all_frames(data, JxlColorType::Rgba), reference);
+
+    let expected: Vec<u8> = reference
+        .chunks_exact(4)
+        .flat_map(|px| [px[2], px[1], px[0], px[3]])
+        .collect();
+    assert_eq!(decode_all_frames(data, JxlColorType::Bgra), expected);
+}
+
+#[test]
+fn test_bgr_channel_order() {
+    let (data, reference) = golden!("3x3_srgb_lossless");
+    let swap_red_blue = |pixels: &[u8], channels: usize, sample_size: usize| -> Vec<u8> {
+        let mut swapped = pixels.to_vec();
+        for pixel in swapped.chunks_exact_mut(channels * sample_size) {
+            let (red, rest) = pixel.split_at_mut(sample_size);
+            red.swap_with_slice(&mut rest[sample_size..2 * sample_size]);
+        }
+        swapped
+    };
+
+    let rgb: Vec<u8> = reference
+        .chunks_exact(4)
+        .flat_map(|px| &px[..3])
+        .copied()
+        .collect();
+    assert_eq!(
+        decode_all_frames(data, JxlColorType::Bgr),
+        swap_red_blue(&rgb, 3, 1)
+    );
+    assert_eq!(
+        decode_all_frames(data, JxlColorType::Bgra),
+        swap_red_blue(reference, 4, 1)
+    );
+
+    let uint16 = |color_type| {
+        let options = JxlDecodeOptions {
+            PixelFormat: JxlPixelFormat {
+                DataFormat: JxlDataFormat::Uint16,
+                ColorType: color_type,
+                Endianness: JxlEndianness::Native,
+            },
+            ..JxlDecodeOptions::default()
+        };
+        decode_all_frames_with(data, &options)
+    };
+    assert_eq!(
+        uint16(JxlColorType::Bgr),
+        swap_red_blue(&uint16(JxlColorType::Rgb), 3, 2)
+    );
+    assert_eq!(
+        uint16(JxlColorType::Bgra),
+        swap_red_blue(&uint16(JxlColorType::Rgba), 4, 2)
+    );
+}
+
+#[test]
+fn test_golden_orientation() {
+    // A 3x2 image stored with a 90 degree clockwise rotation in its header
+    let (data, reference) = golden!("rotate90_lossless");
+    let decode = |adjust: bool| {
+        let options = JxlDecodeOptions {
+            AdjustOrientation: adjust,
+            ..uint8_options(JxlColorType::Rgba)
+        };
+        let mut info = JxlBasicInfoRaw::default();
+        unsafe {
+            let decoder = jxl_decoder_create_with_options(&options);
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+            assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+            jxl_decoder_get_basic_info(decoder, &mut info);
+            jxl_decoder_destroy(decoder);
+        }
+        (info, decode_all_frames_with(data, &options))
+    };
+
+    // Without adjustment, pixels keep their stored layout and the header
+    // orientation is left for the caller to apply
+    let (info, pixels) = decode(false);
+    assert_eq!((info.Width, info.Height), (3, 2));
+    assert_eq!(info.Orientation, JxlOrientation::Rotate90Cw);
+    assert_eq!(pixels, reference);
+
+    // With adjustment, pixels are upright and there is nothing left to apply
+    let (info, pixels) = decode(true);
+    assert_eq!((info.Width, info.Height), (2, 3));
+    assert_eq!(info.Orientation, JxlOrientation::Identity);
+    let upright: Vec<u8> = [3, 0, 4, 1, 5, 2]
+        .iter()
+        .flat_map(|&i| &reference[i * 4..i * 4 + 4])
+        .copied()
+        .collect();
+    assert_eq!(pixels, upright);
+}
+
+#[test]
+fn test_decode_rgba8_srgb() {
+    let decode = |data: &[u8], options: &JxlDecodeOptions| {
+        let mut ptr = std::ptr::null_mut();
+        let (mut width, mut height, mut len) = (0u32, 0u32, 0usize);
+        let status = unsafe {
+            jxl_decode_rgba8_srgb(
+                data.as_ptr(),
+                data.len(),
+                options,
+                &mut ptr,
+                &mut width,
+                &mut height,
+                &mut len,
+            )
+        };
+        assert_eq!(status, JxlStatus::Success);
+        let pixels = unsafe { slice::from_raw_parts(ptr, len) }.to_vec();
+        unsafe { jxl_free_buffer(ptr, len) };
+        (width, height, pixels)
+    };
+
+    // Format and orientation settings in the options are overridden
+    let (data, reference) = golden!("rotate90_lossless");
+    let options = JxlDecodeOptions {
+        AdjustOrientation: false,
+        ..uint8_options(JxlColorType::Bgr)
+    };
+    let (width, height, pixels) = decode(data, &options);
+    assert_eq!((width, height), (2, 3));
+    let upright: Vec<u8> = [3, 0, 4, 1, 5, 2]
+        .iter()
+        .flat_map(|&i| &reference[i * 4..i * 4 + 4])
+        .copied()
+        .collect();
+    assert_eq!(pixels, upright);
+
+    // Grayscale is expanded to opaque RGBA
+    let (data, reference) = golden!("gray_lossless");
+    let (_, _, pixels) = decode(data, &JxlDecodeOptions::default());
+    let expected: Vec<u8> = reference.iter().flat_map(|&v| [v, v, v, 255]).collect();
+    assert_within_tolerance(&pixels, &expected, &[1, 1, 1, 0]);
+
+    let status = unsafe {
+        jxl_decode_rgba8_srgb(
+            data.as_ptr(),
+            data.len(),
+            std::ptr::null(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    assert_eq!(status, JxlStatus::InvalidArgument);
+}
+
+#[test]
+fn test_golden_display_p3() {
+    // Non-XYB images decode to their embedded color space by default
+    let (data, reference) = golden!("p3_lossless");
+    let pixels = decode_all_frames(data, JxlColorType::Rgb);
+    assert_within_tolerance(&pixels, reference, &[0, 0, 0]);
+}
+
+#[test]
+fn test_golden_grayscale() {
+    let (data, reference) = golden!("gray_lossless");
+    let pixels = decode_all_frames(data, JxlColorType::Grayscale);
+    assert_within_tolerance(&pixels, reference, &[0]);
+}
+
+#[test]
+fn test_grayscale_output_of_color_image_rejected() {
+    let (data, _) = golden!("3x3_srgb_lossless");
+    let options = uint8_options(JxlColorType::Grayscale);
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+
+        // The format from the options is checked before the first frame
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::Error);
+        assert_eq!(
+            crate::error::jxl_get_last_error_status(),
+            JxlStatus::InvalidArgument
+        );
+
+        // Once the header is known, setting it is rejected up front
+        let mut format = options.PixelFormat;
+        format.ColorType = JxlColorType::GrayscaleAlpha;
+        assert_eq!(
+            jxl_decoder_set_pixel_format(decoder, &format),
+            JxlStatus::InvalidArgument
+        );
+        format.ColorType = JxlColorType::Rgb;
+        assert_eq!(
+            jxl_decoder_set_pixel_format(decoder, &format),
+            JxlStatus::Success
+        );
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::HaveFrameHeader
+        );
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_golden_alpha() {
+    // Color may move by a rounding step across pipeline changes; alpha must not
+    let (data, reference) = golden!("extra_channels");
+    let pixels = decode_all_frames(data, JxlColorType::Rgba);
+    assert_within_tolerance(&pixels, reference, &[1, 1, 1, 0]);
+}
+
+#[test]
+fn test_golden_bgra_premultiplied() {
+    // BGRA for GDI: channels swapped and color scaled by the first alpha channel
+    let (data, reference) = golden!("extra_channels");
+    let options = JxlDecodeOptions {
+        PremultiplyAlpha: true,
+        ..uint8_options(JxlColorType::Bgra)
+    };
+    let pixels = decode_all_frames_with(data, &options);
+
+    let expected: Vec<u8> = reference
+        .chunks_exact(4)
+        .flat_map(|px| {
+            let premultiply = |c: u8| ((c as u32 * px[3] as u32 + 127) / 255) as u8;
+            [
+                premultiply(px[2]),
+                premultiply(px[1]),
+                premultiply(px[0]),
+                px[3],
+            ]
+        })
+        .collect();
+    assert!(
+        reference.chunks_exact(4).any(|px| px[3] > 0 && px[3] < 255),
+        "Fixture should contain semi-transparent pixels"
+    );
+    assert_within_tolerance(&pixels, &expected, &[2, 2, 2, 0]);
+}
+
+#[test]
+fn test_golden_unpremultiply_straight_alpha_unchanged() {
+    // The fixture's alpha is not associated, so there is nothing to divide out
+    let (data, reference) = golden!("extra_channels");
+    let options = JxlDecodeOptions {
+        UnpremultiplyAlpha: true,
+        ..uint8_options(JxlColorType::Rgba)
+    };
+    let pixels = decode_all_frames_with(data, &options);
+    assert_within_tolerance(&pixels, reference, &[1, 1, 1, 0]);
+}
+
+#[test]
+fn test_golden_animation() {
+    let (data, reference) = golden!("animation_lossless");
+    let pixels = decode_all_frames(data, JxlColorType::Rgb);
+    assert_eq!(pixels.len(), 3 * 8 * 8 * 3, "Expected three 8x8 frames");
+    assert_within_tolerance(&pixels, reference, &[0, 0, 0]);
+}
+
+#[test]
+fn test_max_frames_ends_animation_early() {
+    let (data, reference) = golden!("animation_lossless");
+    let options = JxlDecodeOptions {
+        MaxFrames: 2,
+        ..uint8_options(JxlColorType::Rgb)
+    };
+    let pixels = decode_all_frames_with(data, &options);
+    assert_eq!(pixels.len(), 2 * 8 * 8 * 3, "Expected two of three frames");
+    assert_within_tolerance(&pixels, &reference[..pixels.len()], &[0, 0, 0]);
+
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let mut frame = vec![0u8; 8 * 8 * 3];
+        for _ in 0..2 {
+            let mut event = jxl_decoder_process(decoder);
+            while event != JxlDecoderEvent::NeedOutputBuffer {
+                assert_ne!(event, JxlDecoderEvent::Error);
+                event = jxl_decoder_process(decoder);
+            }
+            assert!(jxl_decoder_has_more_frames(decoder));
+            let event = jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+            assert_eq!(event, JxlDecoderEvent::FrameComplete);
+        }
+        assert!(!jxl_decoder_has_more_frames(decoder));
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::Complete);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_clone_decodes_frames_in_parallel() {
+    let (data, reference) = golden!("animation_lossless");
+    let options = uint8_options(JxlColorType::Rgb);
+    let input = data.to_vec();
+    let clones: Vec<usize> = unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_set_input_borrowed(decoder, input.as_ptr(), input.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        // Clones start from the beginning, whatever the source's position
+        let clones = (0..3)
+            .map(|_| jxl_decoder_clone(decoder) as usize)
+            .collect();
+        jxl_decoder_destroy(decoder);
+        clones
+    };
+    // The clones own their input
+    drop(input);
+
+    let threads: Vec<_> = clones
+        .into_iter()
+        .enumerate()
+        .map(|(index, decoder)| {
+            std::thread::spawn(move || unsafe {
+                let decoder = decoder as *mut NativeDecoderHandle;
+                let mut frame = 0;
+                let pixels = loop {
+                    match jxl_decoder_process(decoder) {
+                        JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::HaveFrameHeader => {}
+                        JxlDecoderEvent::NeedOutputBuffer if frame < index => {
+                            assert_eq!(
+                                jxl_decoder_skip_frame(decoder),
+                                JxlDecoderEvent::FrameComplete
+                            );
+                            frame += 1;
+                        }
+                        JxlDecoderEvent::NeedOutputBuffer => {
+                            let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+                            let event =
+                                jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len());
+                            assert_eq!(event, JxlDecoderEvent::FrameComplete);
+                            break pixels;
+                        }
+                        event => panic!("Unexpected decoder event {:?}", event),
+                    }
+                };
+                jxl_decoder_destroy(decoder);
+                pixels
+            })
+        })
+        .collect();
+
+    for (index, thread) in threads.into_iter().enumerate() {
+        let pixels = thread.join().unwrap();
+        let frame_size = 8 * 8 * 3;
+        assert_eq!(pixels, reference[index * frame_size..][..frame_size]);
+    }
+    assert!(unsafe { jxl_decoder_clone(std::ptr::null()) }.is_null());
+}
+
+/// Collects the frames passed by `jxl_decode_animation`, stopping after
+/// `Frames::limit` of them.
+#[derive(Default)]
+struct Frames {
+    limit: usize,
+    frames: Vec<(u32, Vec<u8>, u32, u32, f32)>,
+}
+
+unsafe extern "C" fn collect_frame(
+    frame_index: u32,
+    pixels: *const u8,
+    width: u32,
+    height: u32,
+    duration_ms: f32,
+    user_data: *mut std::ffi::c_void,
+) -> bool {
+    let frames = unsafe { &mut *(user_data as *mut Frames) };
+    let len = (width * height * 3) as usize;
+    let pixels = unsafe { slice::from_raw_parts(pixels, len) }.to_vec();
+    frames
+        .frames
+        .push((frame_index, pixels, width, height, duration_ms));
+    frames.frames.len() < frames.limit
+}
+
+#[test]
+fn test_decode_animation_callback() {
+    let (data, reference) = golden!("animation_lossless");
+    let options = JxlDecodeOptions {
+        // Ignored, rows are passed tightly packed
+        RowAlignment: 64,
+        ..uint8_options(JxlColorType::Rgb)
+    };
+    for limit in [usize::MAX, 2] {
+        let mut frames = Frames {
+            limit,
+            ..Frames::default()
+        };
+        let status = unsafe {
+            jxl_decode_animation(
+                data.as_ptr(),
+                data.len(),
+                &options,
+                Some(collect_frame),
+                &mut frames as *mut Frames as *mut std::ffi::c_void,
+            )
+        };
+        assert_eq!(status, JxlStatus::Success);
+        assert_eq!(frames.frames.len(), limit.min(3));
+        for (i, (index, pixels, width, height, duration_ms)) in frames.frames.iter().enumerate() {
+            assert_eq!(*index, i as u32);
+            assert_eq!((*width, *height, *duration_ms), (8, 8, 100.0));
+            assert_within_tolerance(pixels, &reference[i * 8 * 8 * 3..][..8 * 8 * 3], &[0, 0, 0]);
+        }
+    }
+
+    let mut frames = Frames::default();
+    let user_data = &mut frames as *mut Frames as *mut std::ffi::c_void;
+    let status = unsafe {
+        jxl_decode_animation(data.as_ptr(), 40, &options, Some(collect_frame), user_data)
+    };
+    assert_eq!(status, JxlStatus::NeedMoreInput);
+    let status =
+        unsafe { jxl_decode_animation(data.as_ptr(), data.len(), &options, None, user_data) };
+    assert_eq!(status, JxlStatus::InvalidArgument);
+}
+
+#[test]
+fn test_golden_prefer_preview() {
+    // A 4x4 preview is output ahead of the 16x16 image
+    let (data, reference) = golden!("preview_lossless");
+    let options = JxlDecodeOptions {
+        PreferPreview: true,
+        ..uint8_options(JxlColorType::Rgb)
+    };
+    let pixels = decode_all_frames_with(data, &options);
+    assert_eq!(pixels.len(), (4 * 4 + 16 * 16) * 3);
+    assert_within_tolerance(&pixels, reference, &[0, 0, 0]);
+
+    // Without it, only the image is output
+    let pixels = decode_all_frames(data, JxlColorType::Rgb);
+    assert_within_tolerance(&pixels, &reference[4 * 4 * 3..], &[0, 0, 0]);
+}
+
+#[test]
+fn test_preview_pixel_format() {
+    // An 8-bit RGBA thumbnail ahead of the float RGB image
+    let (data, reference) = golden!("preview_lossless");
+    let options = JxlDecodeOptions {
+        PreferPreview: true,
+        PixelFormat: JxlPixelFormat {
+            DataFormat: JxlDataFormat::Float32,
+            ColorType: JxlColorType::Rgb,
+            Endianness: JxlEndianness::Native,
+        },
+        ..JxlDecodeOptions::default()
+    };
+    let preview_format = uint8_options(JxlColorType::Rgba).PixelFormat;
+
+    let mut frames = Vec::new();
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let status = jxl_decoder_set_preview_pixel_format(decoder, &preview_format);
+        assert_eq!(status, JxlStatus::Success);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(jxl_decoder_get_preview_buffer_size(decoder), 4 * 4 * 4);
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveFrameHeader | JxlDecoderEvent::FrameComplete => {}
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    let mut frame = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+                    jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+                    frames.push(frame);
+                }
+                JxlDecoderEvent::Complete => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        jxl_decoder_destroy(decoder);
+    }
+
+    let (preview_reference, image_reference) = reference.split_at(4 * 4 * 3);
+    let expected: Vec<u8> = preview_reference
+        .chunks_exact(3)
+        .flat_map(|px| [px[0], px[1], px[2], 255])
+        .collect();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0], expected);
+    let image: Vec<u8> = frames[1]
+        .chunks_exact(4)
+        .map(|b| (f32::from_ne_bytes([b[0], b[1], b[2], b[3]]) * 255.0).round() as u8)
+        .collect();
+    assert_within_tolerance(&image, image_reference, &[1, 1, 1]);
+}
+
+#[test]
+fn test_prefer_preview_frame_headers() {
+    let (data, _) = golden!("preview_lossless");
+    let options = JxlDecodeOptions {
+        PreferPreview: true,
+        ..JxlDecodeOptions::default()
+    };
+    let mut headers = Vec::new();
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        assert_eq!(
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len()),
+            JxlStatus::Success
+        );
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::FrameComplete => {}
+                JxlDecoderEvent::HaveFrameHeader => {
+                    let mut header = std::mem::zeroed::<JxlFrameHeader>();
+                    assert_eq!(
+                        jxl_decoder_get_frame_header(decoder, &mut header),
+                        JxlStatus::Success
+                    );
+                    headers.push((header.IsPreview, header.FrameWidth, header.FrameHeight));
+                }
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    assert_eq!(
+                        jxl_decoder_skip_frame(decoder),
+                        JxlDecoderEvent::FrameComplete
+                    );
+                }
+                JxlDecoderEvent::Complete => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        jxl_decoder_destroy(decoder);
+    }
+    assert_eq!(headers, [(true, 4, 4), (false, 16, 16)]);
+}
+
+#[test]
+fn test_preview_animation_event_sequence() {
+    // A 4x4 preview ahead of three 8x8 animation frames
+    let (data, reference) = golden!("preview_animation_lossless");
+    for prefer_preview in [true, false] {
+        let options = JxlDecodeOptions {
+            PreferPreview: prefer_preview,
+            ..uint8_options(JxlColorType::Rgb)
+        };
+        let mut events = Vec::new();
+        let mut headers = Vec::new();
+        let mut pixels = Vec::new();
+        unsafe {
+            let decoder = jxl_decoder_create_with_options(&options);
+            assert_eq!(
+                jxl_decoder_append_input(decoder, data.as_ptr(), data.len()),
+                JxlStatus::Success
+            );
+            loop {
+                let event = jxl_decoder_process(decoder);
+                events.push(event);
+                match event {
+                    JxlDecoderEvent::HaveBasicInfo => {}
+                    JxlDecoderEvent::HaveFrameHeader => {
+                        assert!(jxl_decoder_has_more_frames(decoder));
+                        let mut header = std::mem::zeroed::<JxlFrameHeader>();
+                        assert_eq!(
+                            jxl_decoder_get_frame_header(decoder, &mut header),
+                            JxlStatus::Success
+                        );
+                        headers.push((
+                            header.IsPreview,
+                            header.FrameWidth,
+                            header.DurationMs,
+                            jxl_decoder_get_frame_duration_ticks(decoder),
+                        ));
+                    }
+                    JxlDecoderEvent::NeedOutputBuffer => {
+                        let mut frame = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+                        let event =
+                            jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+                        events.push(event);
+                        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+                        pixels.extend(frame);
+                    }
+                    JxlDecoderEvent::Complete => break,
+                    event => panic!("Unexpected decoder event {:?}", event),
+                }
+            }
+            assert!(!jxl_decoder_has_more_frames(decoder));
+            jxl_decoder_destroy(decoder);
+        }
+
+        let frame_events = [
+            JxlDecoderEvent::HaveFrameHeader,
+            JxlDecoderEvent::NeedOutputBuffer,
+            JxlDecoderEvent::FrameComplete,
+        ];
+        let frames = if prefer_preview { 4 } else { 3 };
+        let mut expected_events = vec![JxlDecoderEvent::HaveBasicInfo];
+        for _ in 0..frames {
+            expected_events.extend(frame_events);
+        }
+        expected_events.push(JxlDecoderEvent::Complete);
+        assert_eq!(events, expected_events);
+
+        // The preview has no duration of its own
+        let mut expected_headers = vec![(false, 8, 100.0, 10); 3];
+        if prefer_preview {
+            expected_headers.insert(0, (true, 4, 0.0, 0));
+            assert_within_tolerance(&pixels, reference, &[0, 0, 0]);
+        } else {
+            assert_within_tolerance(&pixels, &reference[4 * 4 * 3..], &[0, 0, 0]);
+        }
+        assert_eq!(headers, expected_headers);
+    }
+}
+
+#[test]
+fn test_frame_header_ex_indexes_frames() {
+    let (data, _) = golden!("preview_animation_lossless");
+    for coalescing in [true, false] {
+        let options = JxlDecodeOptions {
+            PreferPreview: true,
+            Coalescing: coalescing,
+            ..uint8_options(JxlColorType::Rgb)
+        };
+        let mut headers = Vec::new();
+        unsafe {
+            let decoder = jxl_decoder_create_with_options(&options);
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+            loop {
+                match jxl_decoder_process(decoder) {
+                    JxlDecoderEvent::HaveBasicInfo => {
+                        let mut header = JxlFrameHeaderEx::default();
+                        assert_eq!(
+                            jxl_decoder_get_frame_header_ex(decoder, &mut header),
+                            JxlStatus::InvalidState
+                        );
+                    }
+                    JxlDecoderEvent::HaveFrameHeader => {
+                        let mut header = JxlFrameHeaderEx::default();
+                        assert_eq!(
+                            jxl_decoder_get_frame_header_ex(decoder, &mut header),
+                            JxlStatus::Success
+                        );
+                        headers.push((
+                            header.Header.IsPreview,
+                            header.FrameIndex,
+                            header.DurationTicks,
+                        ));
+                    }
+                    JxlDecoderEvent::NeedOutputBuffer => {
+                        let mut frame = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+                        let event =
+                            jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+                        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+                    }
+                    JxlDecoderEvent::Complete => break,
+                    event => panic!("Unexpected decoder event {:?}", event),
+                }
+            }
+            jxl_decoder_destroy(decoder);
+        }
+
+        // The preview is not one of the image's frames
+        assert_eq!(
+            headers,
+            [(true, 0, 0), (false, 0, 10), (false, 1, 10), (false, 2, 10)]
+        );
+    }
+}
+
+/// Decodes the first frame of `data` as RGBA8 with extra channels, passing
+/// `extra` as the extra channel buffers (`None` for a null pointer). Returns
+/// the color output and the flags of which buffers were written.
+fn decode_rgba_with_extra_channels(
+    data: &[u8],
+    extra: &mut [Option<Vec<u8>>],
+    separate_first_alpha: bool,
+) -> (Vec<u8>, Vec<bool>) {
+    let options = JxlDecodeOptions {
+        DecodeExtraChannels: true,
+        SeparateFirstAlpha: separate_first_alpha,
+        ..uint8_options(JxlColorType::Rgba)
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        assert_eq!(
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len()),
+            JxlStatus::Success
+        );
+        let mut event = jxl_decoder_process(decoder);
+        while event != JxlDecoderEvent::NeedOutputBuffer {
+            assert_ne!(event, JxlDecoderEvent::Error);
+            event = jxl_decoder_process(decoder);
+        }
+
+        let mut color = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        let ptrs: Vec<*mut u8> = extra
+            .iter_mut()
+            .map(|b| b.as_mut().map_or(std::ptr::null_mut(), |b| b.as_mut_ptr()))
+            .collect();
+        let sizes: Vec<usize> = extra
+            .iter()
+            .map(|b| b.as_ref().map_or(0, Vec::len))
+            .collect();
+        let mut written = vec![false; 1 + extra.len()];
+        let event = jxl_decoder_read_pixels_with_extra_channels(
+            decoder,
+            color.as_mut_ptr(),
+            color.len(),
+            ptrs.as_ptr(),
+            sizes.as_ptr(),
+            ptrs.len(),
+            written.as_mut_ptr(),
+        );
+        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+        jxl_decoder_destroy(decoder);
+        (color, written)
+    }
+}
+
+#[test]
+fn test_two_alpha_channels_primary_folded_into_color() {
+    // Reference samples are R, G, B, first alpha, second alpha per pixel
+    let (data, reference) = golden!("two_alpha_lossless");
+    let mut extra = [Some(vec![0xAAu8; 64]), Some(vec![0u8; 64])];
+    let (color, written) = decode_rgba_with_extra_channels(data, &mut extra, false);
+
+    let expected_color: Vec<u8> = reference
+        .chunks_exact(5)
+        .flat_map(|px| &px[..4])
+        .copied()
+        .collect();
+    let expected_second: Vec<u8> = reference.chunks_exact(5).map(|px| px[4]).collect();
+    assert_eq!(color, expected_color);
+    assert_eq!(extra[1].as_deref(), Some(&expected_second[..]));
+    // The primary alpha lives in the color output, so its buffer is untouched
+    assert_eq!(extra[0].as_deref(), Some(&[0xAAu8; 64][..]));
+    assert_eq!(written, [true, false, true]);
+}
+
+#[test]
+fn test_separate_first_alpha() {
+    // Reference samples are R, G, B, first alpha, second alpha per pixel
+    let (data, reference) = golden!("two_alpha_lossless");
+    let mut extra = [Some(vec![0xAAu8; 64]), Some(vec![0u8; 64])];
+    let (color, written) = decode_rgba_with_extra_channels(data, &mut extra, true);
+
+    let color_alpha: Vec<u8> = color.chunks_exact(4).map(|px| px[3]).collect();
+    let expected_first: Vec<u8> = reference.chunks_exact(5).map(|px| px[3]).collect();
+    let expected_second: Vec<u8> = reference.chunks_exact(5).map(|px| px[4]).collect();
+    assert_eq!(color_alpha, expected_first);
+    assert_eq!(extra[0].as_deref(), Some(&expected_first[..]));
+    assert_eq!(extra[1].as_deref(), Some(&expected_second[..]));
+    assert_eq!(written, [true, true, true]);
+
+    // A null buffer still opts the channel out
+    let mut extra = [None, Some(vec![0u8; 64])];
+    let (_, written) = decode_rgba_with_extra_channels(data, &mut extra, true);
+    assert_eq!(written, [true, false, true]);
+}
+
+#[test]
+fn test_set_extra_channel_format() {
+    // Reference samples are R, G, B, first alpha, second alpha per pixel
+    let (data, reference) = golden!("two_alpha_lossless");
+    let options = JxlDecodeOptions {
+        DecodeExtraChannels: true,
+        ..uint8_options(JxlColorType::Rgba)
+    };
+    let pixels = reference.len() / 5;
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let status = jxl_decoder_set_extra_channel_format(decoder, 1, JxlDataFormat::Float32);
+        assert_eq!(status, JxlStatus::InvalidState);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let status = jxl_decoder_set_extra_channel_format(decoder, 2, JxlDataFormat::Float32);
+        assert_eq!(status, JxlStatus::InvalidArgument);
+
+        // The alpha channel in the color output keeps the color data format
+        for channel in [0, 1] {
+            let status =
+                jxl_decoder_set_extra_channel_format(decoder, channel, JxlDataFormat::Float32);
+            assert_eq!(status, JxlStatus::Success);
+        }
+        assert_eq!(
+            jxl_decoder_get_extra_channel_buffer_size(decoder, 0),
+            pixels
+        );
+        assert_eq!(
+            jxl_decoder_get_extra_channel_buffer_size(decoder, 1),
+            pixels * 4
+        );
+
+        let mut event = jxl_decoder_process(decoder);
+        while event != JxlDecoderEvent::NeedOutputBuffer {
+            assert_ne!(event, JxlDecoderEvent::Error);
+            event = jxl_decoder_process(decoder);
+        }
+        let mut color = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        let mut second = vec![0u8; pixels * 4];
+        let ptrs = [std::ptr::null_mut(), second.as_mut_ptr()];
+        let sizes = [0, second.len()];
+        let event = jxl_decoder_read_pixels_with_extra_channels(
+            decoder,
+            color.as_mut_ptr(),
+            color.len(),
+            ptrs.as_ptr(),
+            sizes.as_ptr(),
+            ptrs.len(),
+            std::ptr::null_mut(),
+        );
+        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+
+        let color_alpha: Vec<u8> = color.chunks_exact(4).map(|px| px[3]).collect();
+        let expected_first: Vec<u8> = reference.chunks_exact(5).map(|px| px[3]).collect();
+        assert_eq!(color_alpha, expected_first);
+        for (sample, px) in second.chunks_exact(4).zip(reference.chunks_exact(5)) {
+            let value = f32::from_ne_bytes(sample.try_into().unwrap());
+            assert!((value - px[4] as f32 / 255.0).abs() < 1e-3);
+        }
+
+        // Copied to clones
+        let clone = jxl_decoder_clone(decoder);
+        assert_eq!(jxl_decoder_process(clone), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(
+            jxl_decoder_get_extra_channel_buffer_size(clone, 1),
+            pixels * 4
+        );
+        jxl_decoder_destroy(clone);
+
+        // Kept across rewinds, cleared by a reset
+        assert_eq!(jxl_decoder_rewind(decoder), JxlStatus::Success);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(
+            jxl_decoder_get_extra_channel_buffer_size(decoder, 1),
+            pixels * 4
+        );
+        assert_eq!(jxl_decoder_reset(decoder), JxlStatus::Success);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(
+            jxl_decoder_get_extra_channel_buffer_size(decoder, 1),
+            pixels
+        );
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_extra_channels_requested_but_image_has_none() {
+    let (data, reference) = golden!("gray_lossless");
+    let options = JxlDecodeOptions {
+        DecodeExtraChannels: true,
+        ..uint8_options(JxlColorType::Grayscale)
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let mut event = jxl_decoder_process(decoder);
+        while event != JxlDecoderEvent::NeedOutputBuffer {
+            assert_ne!(event, JxlDecoderEvent::Error);
+            event = jxl_decoder_process(decoder);
+        }
+
+        // A buffer for a channel that doesn't exist is rejected up front
+        let mut color = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        let mut extra = vec![0u8; color.len()];
+        let ptrs = [extra.as_mut_ptr()];
+        let sizes = [extra.len()];
+        let event = jxl_decoder_read_pixels_with_extra_channels(
+            decoder,
+            color.as_mut_ptr(),
+            color.len(),
+            ptrs.as_ptr(),
+            sizes.as_ptr(),
+            1,
+            std::ptr::null_mut(),
+        );
+        assert_eq!(event, JxlDecoderEvent::Error);
+
+        // Without extra buffers, this is a plain pixel read
+        let event = jxl_decoder_read_pixels_with_extra_channels(
+            decoder,
+            color.as_mut_ptr(),
+            color.len(),
+            std::ptr::null(),
+            std::ptr::null(),
+            0,
+            std::ptr::null_mut(),
+        );
+        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+        assert_within_tolerance(&color, reference, &[0]);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_extra_channel_buffer_too_small() {
+    let (data, _) = golden!("two_alpha_lossless");
+    let options = JxlDecodeOptions {
+        DecodeExtraChannels: true,
+        ..uint8_options(JxlColorType::Rgba)
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let mut event = jxl_decoder_process(decoder);
+        while event != JxlDecoderEvent::NeedOutputBuffer {
+            assert_ne!(event, JxlDecoderEvent::Error);
+            event = jxl_decoder_process(decoder);
+        }
+
+        let mut color = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        let mut second = vec![0u8; jxl_decoder_get_extra_channel_buffer_size(decoder, 1) - 1];
+        let ptrs = [std::ptr::null_mut(), second.as_mut_ptr()];
+        let sizes = [0, second.len()];
+        let mut written = [true; 3];
+        let event = jxl_decoder_read_pixels_with_extra_channels(
+            decoder,
+            color.as_mut_ptr(),
+            color.len(),
+            ptrs.as_ptr(),
+            sizes.as_ptr(),
+            2,
+            written.as_mut_ptr(),
+        );
+        assert_eq!(event, JxlDecoderEvent::Error);
+        assert_eq!(written, [false; 3]);
+
+        // The frame is still ready for a valid read
+        second.push(0);
+        let ptrs = [std::ptr::null_mut(), second.as_mut_ptr()];
+        let sizes = [0, second.len()];
+        let event = jxl_decoder_read_pixels_with_extra_channels(
+            decoder,
+            color.as_mut_ptr(),
+            color.len(),
+            ptrs.as_ptr(),
+            sizes.as_ptr(),
+            2,
+            std::ptr::null_mut(),
+        );
+        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_read_single_channel_alpha() {
+    // Reference samples are R, G, B, first alpha, second alpha per pixel
+    let (data, reference) = golden!("two_alpha_lossless");
+    let expected: Vec<u8> = reference.chunks_exact(5).map(|px| px[3]).collect();
+    let options = uint8_options(JxlColorType::Rgba);
+
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+
+        // The first pass takes alpha from the color output; after the rewind,
+        // the frame is set up to output alpha alone
+        for pass in 0..2 {
+            let mut event = jxl_decoder_process(decoder);
+            while event != JxlDecoderEvent::NeedOutputBuffer {
+                assert_ne!(event, JxlDecoderEvent::Error);
+                event = jxl_decoder_process(decoder);
+            }
+            let mut alpha = vec![0u8; jxl_decoder_get_extra_channel_buffer_size(decoder, 0)];
+            assert_eq!(
+                jxl_decoder_read_single_channel(decoder, 0, alpha.as_mut_ptr(), alpha.len()),
+                JxlDecoderEvent::FrameComplete,
+                "pass {}",
+                pass
+            );
+            assert_eq!(alpha, expected, "pass {}", pass);
+            jxl_decoder_rewind(decoder);
+        }
+
+        // A frame set up for alpha alone has no color to read
+        let mut event = jxl_decoder_process(decoder);
+        while event != JxlDecoderEvent::NeedOutputBuffer {
+            event = jxl_decoder_process(decoder);
+        }
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        assert_eq!(
+            jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len()),
+            JxlDecoderEvent::Error
+        );
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_skipped_extra_channel_still_decodes() {
+    let (data, reference) = golden!("two_alpha_lossless");
+    let mut extra = [None, None];
+    let (color, written) = decode_rgba_with_extra_channels(data, &mut extra, false);
+
+    let expected_color: Vec<u8> = reference
+        .chunks_exact(5)
+        .flat_map(|px| &px[..4])
+        .copied()
+        .collect();
+    assert_eq!(color, expected_color);
+    // Null buffers are skipped, not written
+    assert_eq!(written, [true, false, false]);
+}
+
+#[test]
+fn test_frame_duration_ticks() {
+    // animation_lossless runs at 100 ticks per second with 10-tick frames
+    let (data, _) = golden!("animation_lossless");
+    let mut durations = Vec::new();
+    unsafe {
+        let decoder = jxl_decoder_create();
+        assert_eq!(
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len()),
+            JxlStatus::Success
+        );
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::FrameComplete => {}
+                JxlDecoderEvent::HaveFrameHeader => {
+                    durations.push(jxl_decoder_get_frame_duration_ticks(decoder));
+                }
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    assert_eq!(
+                        jxl_decoder_skip_frame(decoder),
+                        JxlDecoderEvent::FrameComplete
+                    );
+                }
+                JxlDecoderEvent::Complete => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        jxl_decoder_destroy(decoder);
+    }
+    assert_eq!(durations, [10, 10, 10]);
+}
+
+/// Reads the embedded encoding of `data` after basic info.
+fn embedded_encoding(data: &[u8]) -> (JxlStatus, JxlColorEncodingRaw) {
+    let mut encoding = JxlColorEncodingRaw::default();
+    unsafe {
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let status = jxl_decoder_get_embedded_encoding(decoder, &mut encoding);
+        jxl_decoder_destroy(decoder);
+        (status, encoding)
+    }
+}
+
+#[test]
+fn test_embedded_encoding_simple_profile() {
+    let (data, _) = golden!("p3_lossless");
+    let (status, encoding) = embedded_encoding(data);
+    assert_eq!(status, JxlStatus::Success);
+    assert_eq!(encoding.Tag, JxlColorEncodingTag::Rgb);
+    assert_eq!(encoding.Primaries.Tag, JxlPrimariesTag::P3);
+}
+
+#[test]
+fn test_embedded_encoding_icc_profile() {
+    let data = include_bytes!("../../../test/TestData/with_icc.jxl");
+    let (status, _) = embedded_encoding(data);
+    assert_eq!(status, JxlStatus::IccProfile);
+}
+
+#[test]
+fn test_embedded_transfer_function() {
+    let transfer_function = |data: &[u8]| unsafe {
+        let mut tf = JxlTransferFunctionRaw::default();
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let found = jxl_decoder_get_embedded_transfer_function(decoder, &mut tf);
+        assert!(!found);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let found = jxl_decoder_get_embedded_transfer_function(decoder, &mut tf);
+        jxl_decoder_destroy(decoder);
+        found.then_some(tf.Tag)
+    };
+
+    let hdr = include_bytes!("../../../test/TestData/hdr_pq_test.jxl");
+    assert_eq!(transfer_function(hdr), Some(JxlTransferFunctionTag::Pq));
+    let icc = include_bytes!("../../../test/TestData/with_icc.jxl");
+    assert_eq!(transfer_function(icc), None);
+    let mut tf = JxlTransferFunctionRaw::default();
+    assert!(!unsafe { jxl_decoder_get_embedded_transfer_function(std::ptr::null(), &mut tf) });
+}
+
+#[test]
+fn test_extra_channel_dim_shifts() {
+    // 8x8 header with a depth channel at quarter resolution (dim_shift 2)
+    let header = [0xFF, 0x0A, 0x41, 0xC0, 0x84, 0xC3, 0x04, 0x00, 0x00, 0x00];
+    assert_eq!(extra_channel_dim_shifts(&header), vec![2]);
+    assert!(extra_channel_dim_shifts(&header[..4]).is_empty());
+
+    let data = include_bytes!("../../../test/TestData/spot.jxl");
+    assert_eq!(extra_channel_dim_shifts(data), vec![0, 0, 0]);
+    unsafe {
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let mut info = std::mem::MaybeUninit::<JxlExtraChannelInfo>::uninit();
+        let status = jxl_decoder_get_extra_channel_info(decoder, 2, info.as_mut_ptr());
+        assert_eq!(status, JxlStatus::Success);
+        assert_eq!(info.assume_init().DimShift, 0);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_is_xyb() {
+    let lossy = include_bytes!("../../../test/TestData/dice.jxl");
+    let lossless = include_bytes!("../../../test/TestData/3x3_srgb_lossless.jxl");
+    for (data, xyb) in [(&lossy[..], true), (&lossless[..], false)] {
+        unsafe {
+            let decoder = jxl_decoder_create();
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+            assert!(!jxl_decoder_is_xyb(decoder));
+            assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+            assert_eq!(jxl_decoder_is_xyb(decoder), xyb);
+            jxl_decoder_destroy(decoder);
+        }
+    }
+}
+
+#[test]
+fn test_embedded_profile_channels() {
+    let gray = include_bytes!("../../../test/TestData/gray_lossless.jxl");
+    let rgb = include_bytes!("../../../test/TestData/3x3_srgb_lossless.jxl");
+    let cmyk = include_bytes!("../../../test/TestData/cmyk_layers.jxl");
+    for (data, channels) in [(&gray[..], 1), (&rgb[..], 3), (&cmyk[..], 4)] {
+        unsafe {
+            let decoder = jxl_decoder_create();
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+            assert_eq!(jxl_decoder_get_embedded_profile_channels(decoder), 0);
+            assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+            assert_eq!(jxl_decoder_get_embedded_profile_channels(decoder), channels);
+            // Still available once a frame is being decoded
+            assert_eq!(
+                jxl_decoder_process(decoder),
+                JxlDecoderEvent::HaveFrameHeader
+            );
+            assert_eq!(jxl_decoder_get_embedded_profile_channels(decoder), channels);
+            jxl_decoder_destroy(decoder);
+        }
+    }
+    assert_eq!(
+        unsafe { jxl_decoder_get_embedded_profile_channels(std::ptr::null()) },
+        0
+    );
+}
+
+#[test]
+fn test_output_matches_embedded_icc_passthrough() {
+    // with_icc is stored in its original, ICC-described color space
+    let data = include_bytes!("../../../test/TestData/with_icc.jxl");
+    let options = JxlDecodeOptions {
+        CmsType: JxlCmsType::None,
+        ..uint8_options(JxlColorType::Rgb)
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        assert!(!jxl_decoder_output_matches_embedded(decoder));
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert!(jxl_decoder_output_matches_embedded(decoder));
+
+        // Requesting the embedded profile needs no CMS
+        let mut profile = std::mem::zeroed::<JxlColorProfileRaw>();
+        let mut icc_data = std::ptr::null();
+        let mut handle = std::ptr::null_mut();
+        let status = jxl_decoder_get_embedded_color_profile(
+            decoder,
+            &mut profile,
+            &mut icc_data,
+            &mut handle,
+        );
+        assert_eq!(status, JxlStatus::Success);
+        assert_eq!(profile.Tag, JxlColorProfileTag::Icc);
+        let status = jxl_decoder_set_output_color_profile(decoder, &profile, icc_data);
+        jxl_color_profile_free(handle);
+        assert_eq!(status, JxlStatus::Success);
+        assert!(jxl_decoder_output_matches_embedded(decoder));
+
+        // Converting to sRGB would need one
+        assert_eq!(set_srgb_output(decoder), JxlStatus::Error);
+        assert!(jxl_decoder_output_matches_embedded(decoder));
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_cms_error_fallback() {
+    // The builtin CMS can't convert with_icc's ICC profile to sRGB
+    let data = include_bytes!("../../../test/TestData/with_icc.jxl");
+    let unconverted = decode_all_frames_with(
+        data,
+        &JxlDecodeOptions {
+            CmsType: JxlCmsType::None,
+            ..uint8_options(JxlColorType::Rgb)
+        },
+    );
+    let decode = |decoder: *mut NativeDecoderHandle| -> Option<Vec<u8>> {
+        let mut pixels = Vec::new();
+        loop {
+            match unsafe { jxl_decoder_process(decoder) } {
+                JxlDecoderEvent::HaveFrameHeader | JxlDecoderEvent::FrameComplete => {}
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    let mut frame = vec![0u8; unsafe { jxl_decoder_get_buffer_size(decoder) }];
+                    let event = unsafe {
+                        jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len())
+                    };
+                    if event != JxlDecoderEvent::FrameComplete {
+                        return None;
+                    }
+                    pixels.extend_from_slice(&frame);
+                }
+                JxlDecoderEvent::Complete => return Some(pixels),
+                JxlDecoderEvent::Error => return None,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+    };
+
+    for fallback in [false, true] {
+        let options = JxlDecodeOptions {
+            CmsType: JxlCmsType::Builtin,
+            CmsErrorFallback: fallback,
+            ..uint8_options(JxlColorType::Rgb)
+        };
+        unsafe {
+            let decoder = jxl_decoder_create_with_options(&options);
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+            assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+            assert_eq!(set_srgb_output(decoder), JxlStatus::Success);
+            assert!(!jxl_decoder_cms_fell_back(decoder));
+
+            let pixels = decode(decoder);
+            assert_eq!(jxl_decoder_cms_fell_back(decoder), fallback);
+            if fallback {
+                assert_eq!(pixels.as_ref(), Some(&unconverted));
+                let mut message = [0u8; 128];
+                let len =
+                    crate::error::jxl_get_last_error(message.as_mut_ptr().cast(), message.len());
+                assert!(message[..len].ends_with(b"does not support ICC profiles"));
+
+                assert_eq!(jxl_decoder_rewind(decoder), JxlStatus::Success);
+                assert!(!jxl_decoder_cms_fell_back(decoder));
+            } else {
+                assert!(pixels.is_none());
+            }
+            jxl_decoder_destroy(decoder);
+        }
+    }
+    assert!(!unsafe { jxl_decoder_cms_fell_back(std::ptr::null()) });
+}
+
+#[test]
+fn test_set_output_named() {
+    let (data, _) = golden!("3x3_srgb_lossless");
+    let options = uint8_options(JxlColorType::Rgb);
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let status = jxl_decoder_set_output_named(decoder, JxlNamedColorSpace::AdobeRgb);
+        assert_eq!(status, JxlStatus::InvalidState);
+
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let status = jxl_decoder_set_output_named(decoder, JxlNamedColorSpace::AdobeRgb);
+        assert_eq!(status, JxlStatus::Success);
+        assert!(!jxl_decoder_output_matches_embedded(decoder));
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::HaveFrameHeader
+        );
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::NeedOutputBuffer
+        );
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        let event = jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len());
+        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+        jxl_decoder_destroy(decoder);
+
+        // sRGB green lies inside the wider Adobe RGB gamut
+        assert_within_tolerance(&pixels[3..6], &[144, 255, 60], &[2, 0, 2]);
+    }
+
+    // Grayscale images stay gray
+    let (data, _) = golden!("gray_lossless");
+    unsafe {
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let status = jxl_decoder_set_output_named(decoder, JxlNamedColorSpace::LinearSrgb);
+        assert_eq!(status, JxlStatus::Success);
+        let mut profile = std::mem::zeroed::<JxlColorProfileRaw>();
+        let mut handle = std::ptr::null_mut();
+        let status = jxl_decoder_get_output_color_profile(
+            decoder,
+            &mut profile,
+            std::ptr::null_mut(),
+            &mut handle,
+        );
+        assert_eq!(status, JxlStatus::Success);
+        jxl_color_profile_free(handle);
+        assert_eq!(profile.Encoding.Tag, JxlColorEncodingTag::Grayscale);
+        assert_eq!(
+            profile.Encoding.TransferFunction.Tag,
+            JxlTransferFunctionTag::Linear
+        );
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_output_profile_channel_mismatch() {
+    let simple = |grayscale| JxlColorProfileRaw {
+        Tag: JxlColorProfileTag::Simple,
+        IccLength: 0,
+        Encoding: convert_color_encoding(&jxl::api::JxlColorEncoding::srgb(grayscale)),
+    };
+    let images = [
+        (golden!("gray_lossless").0, true),
+        (golden!("3x3_srgb_lossless").0, false),
+    ];
+    for (data, grayscale) in images {
+        unsafe {
+            let decoder = jxl_decoder_create();
+            jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+            assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+
+            let mismatched = simple(!grayscale);
+            let status =
+                jxl_decoder_set_output_color_profile(decoder, &mismatched, std::ptr::null());
+            assert_eq!(status, JxlStatus::InvalidArgument);
+            let mut message = [0u8; 128];
+            let len = crate::error::jxl_get_last_error(message.as_mut_ptr().cast(), message.len());
+            assert!(message[..len].starts_with(b"Output color profile has"));
+
+            // The decoder is left usable
+            let matching = simple(grayscale);
+            let status = jxl_decoder_set_output_color_profile(decoder, &matching, std::ptr::null());
+            assert_eq!(status, JxlStatus::Success);
+            assert_eq!(
+                jxl_decoder_process(decoder),
+                JxlDecoderEvent::HaveFrameHeader
+            );
+            jxl_decoder_destroy(decoder);
+        }
+    }
+}
+
+#[test]
+fn test_soft_proof_gamut_warning() {
+    // Pure red, green and blue, then neutrals in the last row
+    let (data, _) = golden!("3x3_srgb_lossless");
+    let options = uint8_options(JxlColorType::Rgb);
+    // Linear output goes through the CMS
+    let linear = JxlColorProfileRaw {
+        Tag: JxlColorProfileTag::Simple,
+        IccLength: 0,
+        Encoding: JxlColorEncodingRaw {
+            TransferFunction: JxlTransferFunctionRaw {
+                Tag: JxlTransferFunctionTag::Linear,
+                Gamma: 0.0,
+            },
+            ..Default::default()
+        },
+    };
+    // A device with primaries well inside sRGB
+    let narrow = JxlColorProfileRaw {
+        Tag: JxlColorProfileTag::Simple,
+        IccLength: 0,
+        Encoding: JxlColorEncodingRaw {
+            Primaries: JxlPrimariesRaw {
+                Tag: JxlPrimariesTag::Chromaticities,
+                Rx: 0.45,
+                Ry: 0.33,
+                Gx: 0.31,
+                Gy: 0.45,
+                Bx: 0.22,
+                By: 0.2,
+            },
+            ..Default::default()
+        },
+    };
+    let decode = |proof: bool| unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_set_desired_output_color_profile(decoder, &linear, std::ptr::null());
+        if proof {
+            let status = jxl_decoder_set_proof_profile(decoder, &narrow, std::ptr::null());
+            assert_eq!(status, JxlStatus::Success);
+            let status = jxl_decoder_set_gamut_warning_color(decoder, [0.0, 1.0, 0.0].as_ptr());
+            assert_eq!(status, JxlStatus::Success);
+        }
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+
+        // The CMS can't change once decoding has started
+        let status = jxl_decoder_set_proof_profile(decoder, std::ptr::null(), std::ptr::null());
+        assert_eq!(status, JxlStatus::InvalidState);
+
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::HaveFrameHeader
+        );
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::NeedOutputBuffer
+        );
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        let event = jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len());
+        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+        jxl_decoder_destroy(decoder);
+        pixels
+    };
+
+    let pixels = decode(false);
+    assert_eq!(pixels[..9], [255, 0, 0, 0, 255, 0, 0, 0, 255]);
+
+    let pixels = decode(true);
+    assert_eq!(pixels[..9], [0, 255, 0, 0, 255, 0, 0, 255, 0]);
+    for px in pixels[18..].chunks_exact(3) {
+        assert!(
+            px[0].abs_diff(px[1]) <= 1 && px[1].abs_diff(px[2]) <= 1,
+            "{px:?}"
+        );
+    }
+}
+
+#[test]
+fn test_gamut_compression() {
+    // Pure red, green and blue, then neutrals in the last row
+    let (data, _) = golden!("3x3_srgb_lossless");
+    // Output primaries well inside sRGB, so the pure colors fall outside
+    let narrow = JxlColorProfileRaw {
+        Tag: JxlColorProfileTag::Simple,
+        IccLength: 0,
+        Encoding: JxlColorEncodingRaw {
+            Primaries: JxlPrimariesRaw {
+                Tag: JxlPrimariesTag::Chromaticities,
+                Rx: 0.45,
+                Ry: 0.33,
+                Gx: 0.31,
+                Gy: 0.45,
+                Bx: 0.22,
+                By: 0.2,
+            },
+            ..Default::default()
+        },
+    };
+    let decode = |gamut_compression: bool| unsafe {
+        let options = JxlDecodeOptions {
+            GamutCompression: gamut_compression,
+            PixelFormat: JxlPixelFormat {
+                DataFormat: JxlDataFormat::Float32,
+                ColorType: JxlColorType::Rgb,
+                Endianness: JxlEndianness::Native,
+            },
+            ..JxlDecodeOptions::default()
+        };
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_set_desired_output_color_profile(decoder, &narrow, std::ptr::null());
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::HaveFrameHeader
+        );
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::NeedOutputBuffer
+        );
+        let mut pixels = vec![0f32; jxl_decoder_get_buffer_size(decoder) / 4];
+        let event = jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr().cast(), pixels.len() * 4);
+        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+        jxl_decoder_destroy(decoder);
+        pixels
+    };
+
+    let clipped = decode(false);
+    let compressed = decode(true);
+
+    for (i, px) in compressed.chunks_exact(3).enumerate() {
+        assert!(px.iter().all(|v| (0.0..=1.0).contains(v)), "{i}: {px:?}");
+    }
+    // The primaries keep their dominant channel
+    for (i, px) in compressed[..9].chunks_exact(3).enumerate() {
+        let dominant = px[i];
+        assert!(px.iter().all(|&v| v <= dominant), "{i}: {px:?}");
+    }
+    // Neutrals are in gamut and unchanged
+    for (a, b) in compressed[18..].iter().zip(&clipped[18..]) {
+        assert!((a - b).abs() < 1e-4, "{a} vs {b}");
+    }
+}
+
+#[test]
+fn test_soft_proof_requires_lcms2() {
+    let options = JxlDecodeOptions {
+        CmsType: JxlCmsType::Builtin,
+        ..Default::default()
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        let status = jxl_decoder_set_gamut_warning_color(decoder, std::ptr::null());
+        assert_eq!(status, JxlStatus::InvalidArgument);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+/// Decodes every frame of `data` with `options`, returning the event from
+/// each pixel read and stopping at the first `Error`.
+fn read_pixels_events(data: &[u8], options: &JxlDecodeOptions) -> Vec<JxlDecoderEvent> {
+    let mut events = Vec::new();
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::HaveFrameHeader => {}
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    let mut frame = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+                    let event = jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+                    events.push(event);
+                    if event == JxlDecoderEvent::Error {
+                        break;
+                    }
+                }
+                JxlDecoderEvent::Complete => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        jxl_decoder_destroy(decoder);
+    }
+    events
+}
+
+#[test]
+fn test_concurrent_call_reports_busy() {
+    let (data, _) = golden!("3x3_srgb_lossless");
+    unsafe {
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+
+        // Stands in for another thread being inside a decoding call
+        let busy = (*(decoder as *const DecoderInner)).busy.clone();
+        busy.store(true, Ordering::Relaxed);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::Error);
+        assert_eq!(crate::error::jxl_get_last_error_status(), JxlStatus::Busy);
+
+        // The decoder was left untouched
+        busy.store(false, Ordering::Relaxed);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert!(!busy.load(Ordering::Relaxed));
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_decoder_last_error_readable_from_other_thread() {
+    let (data, _) = golden!("3x3_srgb_lossless");
+    unsafe {
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        while jxl_decoder_process(decoder) != JxlDecoderEvent::NeedOutputBuffer {}
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        assert_eq!(
+            jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), 1),
+            JxlDecoderEvent::Error
+        );
+
+        // Another thread has no thread-local error, but sees the decoder's
+        let handle = decoder as usize;
+        let (status, message) = std::thread::spawn(move || {
+            let decoder = handle as *const NativeDecoderHandle;
+            assert_eq!(crate::error::jxl_get_last_error(std::ptr::null_mut(), 0), 0);
+            let mut message = [0u8; 128];
+            let len = jxl_decoder_get_last_error(decoder, message.as_mut_ptr().cast(), 128);
+            let status = jxl_decoder_get_last_error_status(decoder);
+            (status, String::from_utf8(message[..len].to_vec()).unwrap())
+        })
+        .join()
+        .unwrap();
+        assert_eq!(status, JxlStatus::Error);
+        assert!(message.starts_with("Buffer too small"), "{}", message);
+
+        // A successful call clears it
+        assert_eq!(
+            jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len()),
+            JxlDecoderEvent::FrameComplete
+        );
+        assert_eq!(
+            jxl_decoder_get_last_error(decoder, std::ptr::null_mut(), 0),
+            0
+        );
+        assert_eq!(
+            jxl_decoder_get_last_error_status(decoder),
+            JxlStatus::Success
+        );
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_continue_on_frame_error_cannot_skip_corrupt_bitstream() {
+    // corrupt_frame is animation_lossless with the second frame's sections
+    // damaged, so that frame fails even when read without output
+    let data = include_bytes!("../../../test/TestData/corrupt_frame.jxl");
+    for continue_on_error in [false, true] {
+        let options = JxlDecodeOptions {
+            ContinueOnFrameError: continue_on_error,
+            ..uint8_options(JxlColorType::Rgb)
+        };
+        assert_eq!(
+            read_pixels_events(data, &options),
+            [JxlDecoderEvent::FrameComplete, JxlDecoderEvent::Error]
+        );
+    }
+}
+
+#[test]
+fn test_continue_on_frame_error_resumes_at_next_frame() {
+    let (data, reference) = golden!("animation_lossless");
+    let frame_size = 8 * 8 * 3;
+    let decode_frames = |continue_on_error: bool| unsafe {
+        let options = JxlDecodeOptions {
+            ContinueOnFrameError: continue_on_error,
+            ..uint8_options(JxlColorType::Rgb)
+        };
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let mut frame = vec![0u8; frame_size];
+        let mut decoded = Vec::new();
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::HaveFrameHeader => {}
+                // Stands in for the second frame failing while producing output
+                JxlDecoderEvent::NeedOutputBuffer if decoded.len() == 1 => {
+                    let inner = &mut *(decoder as *mut DecoderInner);
+                    let event = inner.frame_error("Injected frame error".to_string());
+                    decoded.push(None);
+                    if event == JxlDecoderEvent::Error {
+                        break;
+                    }
+                    assert_eq!(event, JxlDecoderEvent::FrameError);
+                }
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    let event = jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+                    assert_eq!(event, JxlDecoderEvent::FrameComplete);
+                    decoded.push(Some(frame.clone()));
+                }
+                JxlDecoderEvent::Complete => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        jxl_decoder_destroy(decoder);
+        decoded
+    };
+
+    // The third frame still decodes, matching a decode without the failure
+    let decoded = decode_frames(true);
+    assert_eq!(decoded.len(), 3);
+    assert!(decoded[1].is_none());
+    for index in [0, 2] {
+        let expected = &reference[index * frame_size..][..frame_size];
+        assert_eq!(decoded[index].as_deref(), Some(expected));
+    }
+
+    // Without the option the failure ends the decode
+    let decoded = decode_frames(false);
+    assert_eq!(decoded.len(), 2);
+    assert!(decoded[1].is_none());
+}
+
+#[test]
+fn test_metadata_only_stops_after_header() {
+    let data = include_bytes!("../../../test/TestData/all_metadata.jxl");
+    let options = JxlDecodeOptions {
+        MetadataOnly: true,
+        // No frame is decoded, so the limit doesn't apply
+        MemoryLimitBytes: 1,
+        ..Default::default()
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+
+        let mut info = JxlBasicInfoRaw::default();
+        assert_eq!(
+            jxl_decoder_get_basic_info(decoder, &mut info),
+            JxlStatus::Success
+        );
+        assert!(info.Width > 0 && info.Height > 0);
+        assert_eq!(jxl_decoder_get_exif_box_count(decoder), 1);
+        assert_eq!(jxl_decoder_get_xml_box_count(decoder), 1);
+        assert_eq!(jxl_decoder_get_jumbf_box_count(decoder), 1);
+
+        assert!(!jxl_decoder_has_more_frames(decoder));
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::Complete);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::Complete);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_complete_without_frames_needs_no_buffer() {
+    // MetadataOnly completes right after the header without any frame
+    let data = include_bytes!("../../../test/TestData/dice.jxl");
+    let options = JxlDecodeOptions {
+        MetadataOnly: true,
+        DecodeExtraChannels: true,
+        ..Default::default()
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(jxl_decoder_get_buffer_size(decoder), 0);
+        assert_eq!(jxl_decoder_get_aligned_buffer_size(decoder, 64), 0);
+        assert_eq!(jxl_decoder_get_extra_channel_buffer_size(decoder, 0), 0);
+
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::Complete);
+        assert_eq!(jxl_decoder_get_buffer_size(decoder), 0);
+        let mut header = JxlFrameHeader::default();
+        assert_eq!(
+            jxl_decoder_get_frame_header(decoder, &mut header),
+            JxlStatus::InvalidState
+        );
+        let mut pixel = [0u8; 4];
+        assert_eq!(
+            jxl_decoder_read_pixels(decoder, pixel.as_mut_ptr(), pixel.len()),
+            JxlDecoderEvent::Error
+        );
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::Complete);
+        jxl_decoder_destroy(decoder);
+    }
+
+    // A normal decode still reports the image size up to the first frame
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&JxlDecodeOptions::default());
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert!(jxl_decoder_get_buffer_size(decoder) > 0);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_color_profile_to_string_is_cached() {
+    let profile = JxlColorProfile::Simple(jxl::api::JxlColorEncoding::srgb(false));
+    let expected = format!("{}", profile);
+    let handle = create_profile_handle(profile);
+    unsafe {
+        let inner = &*(handle as *const ColorProfileHandle);
+        assert!(inner.string_cache.get().is_none());
+        let size = jxl_color_profile_to_string(handle, std::ptr::null_mut(), 0);
+        assert_eq!(size, expected.len());
+        let cached = inner.string_cache.get().unwrap().as_ptr();
+
+        let mut buffer = vec![0u8; size];
+        let written = jxl_color_profile_to_string(handle, buffer.as_mut_ptr(), buffer.len());
+        assert_eq!(&buffer[..written], expected.as_bytes());
+        // The second call reuses the string computed by the first
+        assert_eq!(inner.string_cache.get().unwrap().as_ptr(), cached);
+        jxl_color_profile_free(handle);
+    }
+}
+
+#[test]
+fn test_codestream_ending_after_header() {
+    let data = include_bytes!("../../../test/TestData/dice.jxl");
+    // The shortest prefix that holds the whole image header
+    let header_len = (1..data.len())
+        .find(|&len| unsafe {
+            let decoder = jxl_decoder_create();
+            jxl_decoder_append_input(decoder, data.as_ptr(), len);
+            let event = jxl_decoder_process(decoder);
+            jxl_decoder_destroy(decoder);
+            event == JxlDecoderEvent::HaveBasicInfo
+        })
+        .unwrap();
+
+    unsafe {
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), header_len);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+
+        // Without a frame the codestream reads as truncated, not as complete
+        // or broken, and the buffer is still sized for the first frame
+        for _ in 0..2 {
+            assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::NeedMoreInput);
+            assert_eq!(
+                jxl_decoder_get_last_error_status(decoder),
+                JxlStatus::Success
+            );
+        }
+        assert!(jxl_decoder_has_more_frames(decoder));
+        assert!(jxl_decoder_get_buffer_size(decoder) > 0);
+
+        // The rest of the input resumes at the first frame
+        let rest = &data[header_len..];
+        jxl_decoder_append_input(decoder, rest.as_ptr(), rest.len());
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::HaveFrameHeader
+        );
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_signature_check_reports_box_start() {
+    let mut container = checksum::CONTAINER_SIGNATURE.to_vec();
+    container.extend_from_slice(b"\0\0\0\x14ftypjxl ");
+    let codestream = [0xFF, 0x0A, 0x00];
+
+    let mut box_start = usize::MAX;
+    let signature =
+        unsafe { jxl_signature_check(container.as_ptr(), container.len(), &mut box_start) };
+    assert_eq!(signature, JxlSignature::Container);
+    assert_eq!(box_start, jxl_signature_min_bytes());
+    assert_eq!(&container[box_start + 4..box_start + 8], b"ftyp");
+
+    let signature =
+        unsafe { jxl_signature_check(codestream.as_ptr(), codestream.len(), &mut box_start) };
+    assert_eq!(signature, JxlSignature::Codestream);
+    assert_eq!(box_start, 0);
+
+    // A truncated container signature can't be classified yet
+    let signature = unsafe {
+        jxl_signature_check(
+            container.as_ptr(),
+            jxl_signature_min_bytes() - 1,
+            &mut box_start,
+        )
+    };
+    assert_eq!(signature, JxlSignature::NotEnoughBytes);
+}
+
+#[test]
+fn test_peek_is_animated() {
+    let animation = include_bytes!("../../../test/TestData/animation_lossless.jxl");
+    let still = include_bytes!("../../../test/TestData/3x3_srgb_lossless.jxl");
+    let peek = |data: &[u8]| {
+        let mut animated = None;
+        let mut out = false;
+        let status = unsafe { jxl_peek_is_animated(data.as_ptr(), data.len(), &mut out) };
+        if status == JxlStatus::Success {
+            animated = Some(out);
+        }
+        (status, animated)
+    };
+
+    assert_eq!(peek(animation), (JxlStatus::Success, Some(true)));
+    assert_eq!(peek(still), (JxlStatus::Success, Some(false)));
+    // The header ends well before the frame data
+    assert_eq!(peek(&still[..10]), (JxlStatus::Success, Some(false)));
+    assert_eq!(peek(&still[..5]).0, JxlStatus::NeedMoreInput);
+    assert_eq!(peek(&animation[..8]).0, JxlStatus::NeedMoreInput);
+    assert_eq!(peek(&animation[..40]).0, JxlStatus::NeedMoreInput);
+    assert_eq!(peek(b"not jxl").0, JxlStatus::Error);
+    unsafe {
+        let mut out = false;
+        assert_eq!(
+            jxl_peek_is_animated(std::ptr::null(), 0, &mut out),
+            JxlStatus::NeedMoreInput
+        );
+        assert_eq!(
+            jxl_peek_is_animated(still.as_ptr(), still.len(), std::ptr::null_mut()),
+            JxlStatus::InvalidArgument
+        );
+    }
+}
+
+#[test]
+fn test_validate_container() {
+    let data = include_bytes!("../../../test/TestData/mixed_compression.jxl");
+    unsafe {
+        assert_eq!(
+            jxl_validate_container(data.as_ptr(), data.len()),
+            JxlStatus::Success
+        );
+        // Cut inside the brotli-compressed EXIF box at offset 88
+        assert_eq!(jxl_validate_container(data.as_ptr(), 100), JxlStatus::Error);
+        let mut message = [0u8; 128];
+        let len = crate::error::jxl_get_last_error(message.as_mut_ptr().cast(), message.len());
+        assert!(message[..len].starts_with(b"Box 'brob' at offset 88 is truncated"));
+        assert_eq!(
+            jxl_validate_container(std::ptr::null(), 0),
+            JxlStatus::InvalidArgument
+        );
+    }
+}
+
+#[test]
+fn test_copy_exif_box_at() {
+    let data = include_bytes!("../../../test/TestData/mixed_compression.jxl");
+    unsafe {
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_scan_metadata(decoder), JxlStatus::Success);
+
+        let mut ptr = std::ptr::null();
+        let mut len = 0;
+        let status =
+            jxl_decoder_get_exif_box_at(decoder, 1, &mut ptr, &mut len, std::ptr::null_mut());
+        assert_eq!(status, JxlStatus::Success);
+        let size = jxl_decoder_copy_exif_box_at(decoder, 1, std::ptr::null_mut(), 0);
+        assert_eq!(size, len);
+
+        // A too-small buffer is left untouched
+        let mut buffer = vec![0xAA; size + 1];
+        let copied = jxl_decoder_copy_exif_box_at(decoder, 1, buffer.as_mut_ptr(), size - 1);
+        assert_eq!(copied, size);
+        assert!(buffer.iter().all(|&b| b == 0xAA));
+
+        let copied = jxl_decoder_copy_exif_box_at(decoder, 1, buffer.as_mut_ptr(), buffer.len());
+        assert_eq!(copied, size);
+        assert_eq!(&buffer[..size], slice::from_raw_parts(ptr, len));
+        assert_eq!(buffer[size], 0xAA);
+
+        let copied = jxl_decoder_copy_exif_box_at(decoder, 2, buffer.as_mut_ptr(), buffer.len());
+        assert_eq!(copied, 0);
+        let mut message = [0u8; 128];
+        let len = crate::error::jxl_get_last_error(message.as_mut_ptr().cast(), message.len());
+        assert!(message[..len].starts_with(b"EXIF box index 2 out of range"));
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+/// Scans `data` for metadata and copies its first JUMBF box.
+unsafe fn first_jumbf_box(data: &[u8]) -> (Vec<u8>, bool) {
+    unsafe {
+        let decoder = jxl_decoder_create();
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_scan_metadata(decoder), JxlStatus::Success);
+        let mut ptr = std::ptr::null();
+        let mut len = 0;
+        let mut compressed = false;
+        let status = jxl_decoder_get_jumbf_box_at(decoder, 0, &mut ptr, &mut len, &mut compressed);
+        assert_eq!(status, JxlStatus::Success);
+        let jumbf = slice::from_raw_parts(ptr, len).to_vec();
+        jxl_decoder_destroy(decoder);
+        (jumbf, compressed)
+    }
+}
+
+#[test]
+fn test_brotli_decompress_metadata_box() {
+    let plain = include_bytes!("../../../test/TestData/single_jumbf.jxl");
+    let brob = include_bytes!("../../../test/TestData/single_jumbf_brob.jxl");
+    unsafe {
+        let (plain, compressed) = first_jumbf_box(plain);
+        assert!(!compressed);
+        let (brob, compressed) = first_jumbf_box(brob);
+        assert!(compressed);
+
+        let mut ptr = std::ptr::null_mut();
+        let mut len = 0;
+        for max_output_size in [0, plain.len(), usize::MAX] {
+            assert_eq!(
+                jxl_brotli_decompress(
+                    brob.as_ptr(),
+                    brob.len(),
+                    max_output_size,
+                    &mut ptr,
+                    &mut len
+                ),
+                JxlStatus::Success
+            );
+            assert_eq!(slice::from_raw_parts(ptr, len), plain);
+            jxl_free_buffer(ptr, len);
+        }
+
+        // Output over the limit fails and leaves the outputs cleared
+        assert_eq!(
+            jxl_brotli_decompress(
+                brob.as_ptr(),
+                brob.len(),
+                plain.len() - 1,
+                &mut ptr,
+                &mut len
+            ),
+            JxlStatus::BufferTooSmall
+        );
+        assert!(ptr.is_null());
+        assert_eq!(len, 0);
+
+        // So does a truncated stream
+        assert_eq!(
+            jxl_brotli_decompress(brob.as_ptr(), brob.len() - 3, 0, &mut ptr, &mut len),
+            JxlStatus::Error
+        );
+        assert!(ptr.is_null());
+        assert_eq!(len, 0);
+        assert_eq!(
+            jxl_brotli_decompress(brob.as_ptr(), brob.len(), 0, std::ptr::null_mut(), &mut len),
+            JxlStatus::InvalidArgument
+        );
+    }
+}
+
+#[test]
+fn test_scan_metadata_before_header() {
+    let data = include_bytes!("../../../test/TestData/mixed_compression.jxl");
+    unsafe {
+        let decoder = jxl_decoder_create();
+        // Input cut inside the second, brotli-compressed EXIF box
+        jxl_decoder_append_input(decoder, data.as_ptr(), 100);
+        assert_eq!(jxl_decoder_scan_metadata(decoder), JxlStatus::NeedMoreInput);
+        assert_eq!(jxl_decoder_get_exif_box_count(decoder), 0);
+
+        jxl_decoder_append_input(decoder, data[100..].as_ptr(), data.len() - 100);
+        assert_eq!(jxl_decoder_scan_metadata(decoder), JxlStatus::Success);
+        assert_eq!(jxl_decoder_get_exif_box_count(decoder), 2);
+        assert_eq!(jxl_decoder_get_xml_box_count(decoder), 0);
+
+        let mut scanned = std::ptr::null();
+        let mut compressed = false;
+        let status = jxl_decoder_get_exif_box_at(
+            decoder,
+            1,
+            &mut scanned,
+            std::ptr::null_mut(),
+            &mut compressed,
+        );
+        assert_eq!(status, JxlStatus::Success);
+        assert!(compressed);
+        let status = jxl_decoder_get_xml_box_at(
+            decoder,
+            0,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+        assert_eq!(status, JxlStatus::Error);
+
+        // Reading the header keeps the scanned boxes
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let mut data_out = std::ptr::null();
+        let status = jxl_decoder_get_exif_box_at(
+            decoder,
+            1,
+            &mut data_out,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        );
+        assert_eq!(status, JxlStatus::Success);
+        assert_eq!(data_out, scanned);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_luminance_histogram_of_grayscale_frame() {
+    // 256 bins over 8-bit gray put each sample value in its own bin
+    let (data, reference) = golden!("gray_lossless");
+    let options = JxlDecodeOptions {
+        LuminanceStats: true,
+        ..uint8_options(JxlColorType::Grayscale)
+    };
+    let mut expected = [0u32; 256];
+    for &value in reference {
+        expected[value as usize] += 1;
+    }
+
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let mut histogram = [0u32; 256];
+        assert_eq!(
+            jxl_decoder_compute_histogram(decoder, 256, histogram.as_mut_ptr()),
+            JxlStatus::InvalidState
+        );
+
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::HaveFrameHeader => {}
+                JxlDecoderEvent::NeedOutputBuffer => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len());
+
+        assert_eq!(
+            jxl_decoder_compute_histogram(decoder, 256, histogram.as_mut_ptr()),
+            JxlStatus::Success
+        );
+        assert_eq!(histogram, expected);
+
+        let mut halves = [0u32; 2];
+        assert_eq!(
+            jxl_decoder_compute_histogram(decoder, 2, halves.as_mut_ptr()),
+            JxlStatus::Success
+        );
+        let dark = expected[..128].iter().sum::<u32>();
+        assert_eq!(halves, [dark, reference.len() as u32 - dark]);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_opaque_bounds_of_rgba_frame() {
+    // Reference samples are R, G, B, first alpha, second alpha per pixel; the
+    // first alpha fades from left to right
+    let (data, reference) = golden!("two_alpha_lossless");
+    let options = JxlDecodeOptions {
+        OpaqueBounds: true,
+        OpaqueAlphaThreshold: 0.9,
+        ..uint8_options(JxlColorType::Rgba)
+    };
+
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let mut bounds = JxlRect::default();
+        assert_eq!(
+            jxl_decoder_get_opaque_bounds(decoder, &mut bounds),
+            JxlStatus::InvalidState
+        );
+
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::HaveFrameHeader => {}
+                JxlDecoderEvent::NeedOutputBuffer => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        let mut info = std::mem::zeroed::<JxlBasicInfoRaw>();
+        assert_eq!(
+            jxl_decoder_get_basic_info(decoder, &mut info),
+            JxlStatus::Success
+        );
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len());
+
+        let width = info.Width as usize;
+        let opaque: Vec<(usize, usize)> = reference
+            .chunks_exact(5)
+            .enumerate()
+            .filter(|(_, px)| px[3] as f32 / 255.0 > 0.9)
+            .map(|(i, _)| (i % width, i / width))
+            .collect();
+        assert!(!opaque.is_empty() && opaque.len() < reference.len() / 5);
+        let left = opaque.iter().map(|p| p.0).min().unwrap();
+        let right = opaque.iter().map(|p| p.0).max().unwrap();
+        let top = opaque.iter().map(|p| p.1).min().unwrap();
+        let bottom = opaque.iter().map(|p| p.1).max().unwrap();
+        let expected = JxlRect {
+            X: left as u32,
+            Y: top as u32,
+            Width: (right - left + 1) as u32,
+            Height: (bottom - top + 1) as u32,
+        };
+
+        assert_eq!(
+            jxl_decoder_get_opaque_bounds(decoder, &mut bounds),
+            JxlStatus::Success
+        );
+        assert_eq!(bounds, expected);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_log_callback() {
+    // The callback is global, so other tests running in parallel may log too
+    static MESSAGES: Mutex<Vec<(JxlLogLevel, String)>> = Mutex::new(Vec::new());
+    unsafe extern "C" fn record(level: JxlLogLevel, message: *const c_char, _: *mut c_void) {
+        let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+        MESSAGES.lock().unwrap().push((level, message.into_owned()));
+    }
+
+    let (data, _) = golden!("1x1_rgba_lossless");
+    unsafe { crate::error::jxl_set_log_callback(Some(record), std::ptr::null_mut()) };
+    decode_all_frames(data, JxlColorType::Rgba);
+    unsafe { crate::error::jxl_set_log_callback(None, std::ptr::null_mut()) };
+
+    let messages = MESSAGES.lock().unwrap();
+    let logged = |level, message: &str| messages.contains(&(level, message.to_string()));
+    let basic_info = "Read basic info: 1x1, 1 extra channels";
+    assert!(logged(JxlLogLevel::Info, basic_info));
+    assert!(logged(JxlLogLevel::Debug, "Decoded frame 0"));
+    assert!(logged(JxlLogLevel::Info, "Image complete, frame count 1"));
+    drop(messages);
+
+    // A callback can clear itself without deadlocking
+    unsafe extern "C" fn clear(_: JxlLogLevel, _: *const c_char, _: *mut c_void) {
+        unsafe { crate::error::jxl_set_log_callback(None, std::ptr::null_mut()) };
+    }
+    unsafe { crate::error::jxl_set_log_callback(Some(clear), std::ptr::null_mut()) };
+    log(JxlLogLevel::Debug, || "Clears the callback".to_string());
+}
+
+#[test]
+fn test_cms_transform_reused_across_buffers() {
+    use jxl::api::JxlColorEncoding;
+    let icc = |encoding| {
+        let profile = JxlColorProfile::Simple(encoding);
+        profile.try_as_icc().unwrap().to_vec()
+    };
+    let srgb = icc(JxlColorEncoding::srgb(false));
+    let create = |output: &[u8]| unsafe {
+        let (input, intent) = (srgb.as_ptr(), JxlRenderingIntent::Relative);
+        jxl_cms_transform_create(input, srgb.len(), output.as_ptr(), output.len(), intent)
+    };
+
+    let transform = create(&icc(JxlColorEncoding::linear_srgb(false)));
+    assert!(!transform.is_null());
+    for value in [0.5f32, 1.0] {
+        let mut samples = [value; 6];
+        let status =
+            unsafe { jxl_cms_transform_apply(transform, samples.as_mut_ptr(), samples.len()) };
+        assert_eq!(status, JxlStatus::Success);
+        let expected = ((value + 0.055) / 1.055).powf(2.4);
+        for sample in samples {
+            assert!((sample - expected).abs() < 1e-3, "{sample} != {expected}");
+        }
+    }
+    // Partial pixels are rejected
+    let mut samples = [0.5f32; 4];
+    let status = unsafe { jxl_cms_transform_apply(transform, samples.as_mut_ptr(), 4) };
+    assert_eq!(status, JxlStatus::InvalidArgument);
+    unsafe { jxl_cms_transform_destroy(transform) };
+
+    // Transforms run in place, so the channel counts must match
+    assert!(create(&icc(JxlColorEncoding::srgb(true))).is_null());
+}
+
+#[test]
+fn test_has_transparency() {
+    // Returns the answer after basic info and after decoding every frame
+    let transparency = |data: &[u8], detect: bool| unsafe {
+        let options = JxlDecodeOptions {
+            DetectTransparency: detect,
+            ..uint8_options(JxlColorType::Rgba)
+        };
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_has_transparency(decoder), -1);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let before = jxl_decoder_has_transparency(decoder);
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveFrameHeader | JxlDecoderEvent::FrameComplete => {}
+                JxlDecoderEvent::NeedOutputBuffer => {
+                    let mut frame = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+                    jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+                }
+                JxlDecoderEvent::Complete => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        let after = jxl_decoder_has_transparency(decoder);
+        jxl_decoder_destroy(decoder);
+        (before, after)
+    };
+
+    // No alpha channel
+    let (data, _) = golden!("3x3_srgb_lossless");
+    assert_eq!(transparency(data, true), (0, 0));
+    // Alpha channel that is opaque everywhere
+    let (data, _) = golden!("rotate90_lossless");
+    assert_eq!(transparency(data, false), (1, 1));
+    assert_eq!(transparency(data, true), (1, 0));
+    // Alpha channel that fades out
+    let (data, _) = golden!("two_alpha_lossless");
+    assert_eq!(transparency(data, true), (1, 1));
+    let decoder = std::ptr::null();
+    assert_eq!(unsafe { jxl_decoder_has_transparency(decoder) }, -1);
+}
+
+#[test]
+fn test_has_transparency_covers_every_frame() {
+    let (data, _) = golden!("rotate90_lossless");
+    let options = JxlDecodeOptions {
+        DetectTransparency: true,
+        ..uint8_options(JxlColorType::Rgba)
+    };
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+
+        // Stands in for a two-frame animation whose first frame has a
+        // transparent pixel and whose last frame is opaque
+        let inner = &mut *(decoder as *mut DecoderInner);
+        inner.record_frame_stats(&[0, 0, 0, 0, 0, 0, 0, 255], 8, 8);
+        inner.record_frame_stats(&[0, 0, 0, 255, 0, 0, 0, 255], 8, 8);
+        assert_eq!(inner.last_frame_transparent, Some(false));
+        assert_eq!(jxl_decoder_has_transparency(decoder), 1);
+
+        // Rewinding forgets the frames scanned so far
+        assert_eq!(jxl_decoder_rewind(decoder), JxlStatus::Success);
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        let inner = &mut *(decoder as *mut DecoderInner);
+        inner.record_frame_stats(&[0, 0, 0, 255, 0, 0, 0, 255], 8, 8);
+        assert_eq!(jxl_decoder_has_transparency(decoder), 0);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_frame_header_is_opaque() {
+    // Returns IsOpaque at the frame header and after reading the first frame
+    let opaque = |data: &[u8], detect: bool| unsafe {
+        let options = JxlDecodeOptions {
+            DetectTransparency: detect,
+            ..uint8_options(JxlColorType::Rgba)
+        };
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let mut header = JxlFrameHeader::default();
+        assert_eq!(
+            jxl_decoder_get_frame_header(decoder, &mut header),
+            JxlStatus::InvalidState
+        );
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::HaveFrameHeader
+        );
+        assert_eq!(
+            jxl_decoder_get_frame_header(decoder, &mut header),
+            JxlStatus::Success
+        );
+        let before = header.IsOpaque;
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::NeedOutputBuffer
+        );
+        let mut frame = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        jxl_decoder_read_pixels(decoder, frame.as_mut_ptr(), frame.len());
+        let mut decoded = JxlFrameHeader::default();
+        assert_eq!(
+            jxl_decoder_get_frame_header(decoder, &mut decoded),
+            JxlStatus::Success
+        );
+        assert_eq!(decoded.FrameWidth, header.FrameWidth);
+        jxl_decoder_destroy(decoder);
+        (before, decoded.IsOpaque)
+    };
+
+    // No alpha channel: exact from the metadata
+    let (data, _) = golden!("3x3_srgb_lossless");
+    assert_eq!(opaque(data, false), (true, true));
+    // Alpha channel that is opaque everywhere: only the scan can tell
+    let (data, _) = golden!("rotate90_lossless");
+    assert_eq!(opaque(data, false), (false, false));
+    assert_eq!(opaque(data, true), (false, true));
+    // Alpha channel that fades out
+    let (data, _) = golden!("two_alpha_lossless");
+    assert_eq!(opaque(data, true), (false, false));
+}
+
+#[test]
+fn test_luminance_stats_of_grayscale_frame() {
+    let (data, reference) = golden!("gray_lossless");
+    let options = JxlDecodeOptions {
+        LuminanceStats: true,
+        ..uint8_options(JxlColorType::Grayscale)
+    };
+    let min = *reference.iter().min().unwrap() as f32 / 255.0;
+    let max = *reference.iter().max().unwrap() as f32 / 255.0;
+    let sum = reference.iter().map(|&v| v as f64 / 255.0).sum::<f64>();
+    let mean = (sum / reference.len() as f64) as f32;
+
+    unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        let mut stats = JxlLuminanceStats::default();
+        assert_eq!(
+            jxl_decoder_get_luminance_stats(decoder, &mut stats),
+            JxlStatus::InvalidState
+        );
+
+        loop {
+            match jxl_decoder_process(decoder) {
+                JxlDecoderEvent::HaveBasicInfo | JxlDecoderEvent::HaveFrameHeader => {}
+                JxlDecoderEvent::NeedOutputBuffer => break,
+                event => panic!("Unexpected decoder event {:?}", event),
+            }
+        }
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len());
+
+        assert_eq!(
+            jxl_decoder_get_luminance_stats(decoder, &mut stats),
+            JxlStatus::Success
+        );
+        assert_eq!(stats.Min, min);
+        assert_eq!(stats.Max, max);
+        assert!((stats.Mean - mean).abs() < 1e-6);
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_get_state() {
+    let (data, _) = golden!("3x3_srgb_lossless");
+    unsafe {
+        assert_eq!(
+            jxl_decoder_get_state(std::ptr::null()),
+            JxlDecoderStateTag::Processing
+        );
+
+        let decoder = jxl_decoder_create();
+        assert_eq!(
+            jxl_decoder_get_state(decoder),
+            JxlDecoderStateTag::Initialized
+        );
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        assert_eq!(jxl_decoder_process(decoder), JxlDecoderEvent::HaveBasicInfo);
+        assert_eq!(
+            jxl_decoder_get_state(decoder),
+            JxlDecoderStateTag::WithImageInfo
+        );
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::HaveFrameHeader
+        );
+        assert_eq!(
+            jxl_decoder_get_state(decoder),
+            JxlDecoderStateTag::WithFrameInfo
+        );
+        assert_eq!(
+            jxl_decoder_process(decoder),
+            JxlDecoderEvent::NeedOutputBuffer
+        );
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        assert_eq!(
+            jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len()),
+            JxlDecoderEvent::FrameComplete
+        );
+        assert_eq!(
+            jxl_decoder_get_state(decoder),
+            JxlDecoderStateTag::WithImageInfo
+        );
+        jxl_decoder_destroy(decoder);
+    }
+}
+
+#[test]
+fn test_sanitize_float_output_through_read_pixels() {
+    // Decoding yields only finite samples, which must pass through unchanged
+    let data = include_bytes!("../../../test/TestData/hdr_pq_test.jxl");
+    let decode = |enabled: bool| {
+        let options = JxlDecodeOptions {
+            PixelFormat: JxlPixelFormat {
+                DataFormat: JxlDataFormat::Float32,
+                ColorType: JxlColorType::Rgb,
+                Endianness: JxlEndianness::LittleEndian,
+            },
+            SanitizeFloatOutput: enabled,
+            ..JxlDecodeOptions::default()
+        };
+        decode_all_frames_with(data, &options)
+    };
+
+    let sanitized = decode(true);
+    assert!(!sanitized.is_empty());
+    assert!(
+        sanitized
+            .chunks_exact(4)
+            .all(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]).is_finite())
+    );
+    assert_eq!(sanitized, decode(false));
+}
+
+#[test]
+fn test_sanitize_float_output() {
+    // Non-finite samples a malformed HDR frame could decode to, then padding
+    let values = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 0.5];
+    let sanitized = |enabled: bool| unsafe {
+        let options = JxlDecodeOptions {
+            PixelFormat: JxlPixelFormat {
+                DataFormat: JxlDataFormat::Float32,
+                ColorType: JxlColorType::Rgb,
+                Endianness: JxlEndianness::BigEndian,
+            },
+            SanitizeFloatOutput: enabled,
+            ..JxlDecodeOptions::default()
+        };
+        let decoder = jxl_decoder_create_with_options(&options);
+        let mut pixels: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        (*(decoder as *const DecoderInner)).sanitize_output(&mut pixels, 12, 16);
+        jxl_decoder_destroy(decoder);
+        pixels
+            .chunks_exact(4)
+            .map(|b| f32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>()
+    };
+
+    let samples = sanitized(true);
+    assert_eq!(samples[..3], [0.0, f32::MAX, f32::MIN]);
+    assert_eq!(samples[3], 0.5);
+    // Without the option the buffer is left as decoded
+    let samples = sanitized(false);
+    assert!(samples[0].is_nan());
+    assert_eq!(samples[1..], values[1..]);
+}
+
+#[test]
+fn test_buffer_size_overflow() {
+    let info = |width, height| JxlBasicInfoRaw {
+        Width: width,
+        Height: height,
+        ..unsafe { std::mem::zeroed() }
+    };
+    let rgba8 = uint8_options(JxlColorType::Rgba).PixelFormat;
+    let rgba_f32 = JxlPixelFormat {
+        DataFormat: JxlDataFormat::Float32,
+        ..rgba8
+    };
+
+    // 65536x65536 RGBA8 needs 2^34 bytes, which overflows a 32-bit usize
+    let large = info(65536, 65536);
+    let expected = usize::try_from(1u64 << 34).ok();
+    assert_eq!(calculate_buffer_size(&large, &rgba8, 0), expected);
+    assert_eq!(calculate_buffer_size(&large, &rgba8, 64), expected);
+    assert_eq!(calculate_plane_size(&large, 4), expected);
+
+    // The largest dimensions a header can declare overflow any usize as
+    // float RGBA, 2^64 bytes
+    let huge = info(1 << 30, 1 << 30);
+    assert_eq!(calculate_buffer_size(&huge, &rgba_f32, 0), None);
+    assert_eq!(calculate_plane_size(&huge, 16), None);
+    assert_eq!(
+        estimate_decode_memory(&huge, &rgba_f32, true),
+        u64::MAX,
+        "The memory estimate saturates"
+    );
+}
+
+#[test]
+fn test_cms_bypassed_for_embedded_encoding() {
+    let (data, reference) = golden!("3x3_srgb_lossless");
+    let options = uint8_options(JxlColorType::Rgb);
+    let srgb = JxlColorProfileRaw {
+        Tag: JxlColorProfileTag::Simple,
+        IccLength: 0,
+        Encoding: convert_color_encoding(&jxl::api::JxlColorEncoding::srgb(false)),
+    };
+    let linear = JxlColorProfileRaw {
+        Encoding: convert_color_encoding(&jxl::api::JxlColorEncoding::linear_srgb(false)),
+        ..srgb
+    };
+    let decode = |profile: &JxlColorProfileRaw| unsafe {
+        let decoder = jxl_decoder_create_with_options(&options);
+        jxl_decoder_set_desired_output_color_profile(decoder, profile, std::ptr::null());
+        jxl_decoder_append_input(decoder, data.as_ptr(), data.len());
+        while jxl_decoder_process(decoder) != JxlDecoderEvent::NeedOutputBuffer {}
+        let matches_embedded = jxl_decoder_output_matches_embedded(decoder);
+        let mut pixels = vec![0u8; jxl_decoder_get_buffer_size(decoder)];
+        let event = jxl_decoder_read_pixels(decoder, pixels.as_mut_ptr(), pixels.len());
+        assert_eq!(event, JxlDecoderEvent::FrameComplete);
+        let setups = jxl_decoder_get_cms_setup_count(decoder);
+        jxl_decoder_destroy(decoder);
+        (pixels, matches_embedded, setups)
+    };
+
+    // sRGB to sRGB builds no transform
+    let (pixels, matches_embedded, setups) = decode(&srgb);
+    assert!(matches_embedded);
+    assert_eq!(setups, 0);
+    assert_eq!(pixels, reference);
+
+    // A different encoding goes through the CMS
+    let (_, matches_embedded, setups) = decode(&linear);
+    assert!(!matches_embedded);
+    assert!(setups > 0);
+}
@@ -0,0 +1,424 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! Minimal lossless JPEG XL encoder for 8-bit RGBA pixels.
+//!
+//! jxl-rs only decodes, so this writes the bitstream directly: a single
+//! modular frame with no transforms, one gradient predictor shared by every
+//! channel, and flat ANS histograms. The output is valid and bit-exact but
+//! not small; it exists to re-wrap decoded pixels, not to compete with libjxl.
+
+use crate::checksum::CONTAINER_SIGNATURE;
+
+/// Side length of a modular group (group_size_shift = 1).
+const GROUP_DIM: usize = 256;
+
+/// Side length of an LF group, in pixels.
+const LF_GROUP_DIM: usize = GROUP_DIM * 8;
+
+/// ANS state the decoder expects once a stream is fully consumed.
+const ANS_FINAL_STATE: u32 = 0x130000;
+
+/// Modular predictor id for the clamped gradient predictor.
+const GRADIENT_PREDICTOR: u32 = 5;
+
+/// Number of contexts used to code the MA tree.
+const NUM_TREE_CONTEXTS: usize = 6;
+
+/// LSB-first bit writer matching the decoder's bit reader.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            buffer: 0,
+            bits: 0,
+        }
+    }
+
+    fn write(&mut self, num_bits: usize, value: u32) {
+        debug_assert!(num_bits == 32 || value >> num_bits == 0);
+        self.buffer |= (value as u64) << self.bits;
+        self.bits += num_bits;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn write_bool(&mut self, value: bool) {
+        self.write(1, value as u32);
+    }
+
+    fn zero_pad_to_byte(&mut self) {
+        if self.bits > 0 {
+            self.write(8 - self.bits, 0);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.zero_pad_to_byte();
+        self.bytes
+    }
+}
+
+/// Encodes 8-bit RGBA pixels as a lossless JPEG XL file.
+///
+/// `rgba` must hold exactly `width * height * 4` bytes. The codestream is
+/// wrapped in a minimal ISOBMFF container (signature, `ftyp`, `jxlc`).
+pub(crate) fn encode_lossless_rgba8(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let codestream = write_codestream(rgba, width as usize, height as usize);
+
+    let mut out = Vec::with_capacity(codestream.len() + 40);
+    out.extend_from_slice(&CONTAINER_SIGNATURE);
+    write_box(&mut out, b"ftyp", b"jxl \0\0\0\0jxl ");
+    write_box(&mut out, b"jxlc", &codestream);
+    out
+}
+
+fn write_box(out: &mut Vec<u8>, box_type: &[u8; 4], payload: &[u8]) {
+    let size = (payload.len() + 8) as u64;
+    if size <= u32::MAX as u64 {
+        out.extend_from_slice(&(size as u32).to_be_bytes());
+        out.extend_from_slice(box_type);
+    } else {
+        out.extend_from_slice(&1u32.to_be_bytes());
+        out.extend_from_slice(box_type);
+        out.extend_from_slice(&(size + 8).to_be_bytes());
+    }
+    out.extend_from_slice(payload);
+}
+
+fn write_codestream(rgba: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut header = BitWriter::new();
+    header.write(8, 0xFF);
+    header.write(8, 0x0A);
+    write_size_header(&mut header, width as u32, height as u32);
+    write_image_metadata(&mut header);
+    header.write_bool(true); // transform_data all_default
+    header.zero_pad_to_byte();
+    write_frame_header(&mut header);
+
+    let channels: Vec<Vec<u8>> = (0..4)
+        .map(|c| rgba.iter().skip(c).step_by(4).copied().collect())
+        .collect();
+    let sections = write_sections(&channels, width, height);
+
+    // Table of contents: not permuted, one entry per section.
+    header.write_bool(false);
+    header.zero_pad_to_byte();
+    for section in &sections {
+        write_toc_entry(&mut header, section.len() as u32);
+    }
+
+    let mut out = header.finish();
+    for section in &sections {
+        out.extend_from_slice(section);
+    }
+    out
+}
+
+fn write_size_header(w: &mut BitWriter, width: u32, height: u32) {
+    w.write_bool(false); // small
+    write_dimension(w, height);
+    w.write(3, 0); // ratio: explicit xsize
+    write_dimension(w, width);
+}
+
+fn write_dimension(w: &mut BitWriter, dim: u32) {
+    let value = dim - 1;
+    let (selector, num_bits) = if value < 1 << 9 {
+        (0, 9)
+    } else if value < 1 << 13 {
+        (1, 13)
+    } else if value < 1 << 18 {
+        (2, 18)
+    } else {
+        (3, 30)
+    };
+    w.write(2, selector);
+    w.write(num_bits, value);
+}
+
+fn write_image_metadata(w: &mut BitWriter) {
+    w.write_bool(false); // all_default
+    w.write_bool(false); // extra_fields
+    w.write_bool(false); // bit_depth: integer samples
+    w.write(2, 0); // bit_depth: 8 bits
+    w.write_bool(true); // modular_16bit_sufficient
+    w.write(2, 1); // one extra channel
+    w.write_bool(true); // extra channel all_default: 8-bit alpha
+    w.write_bool(false); // xyb_encoded
+    w.write_bool(true); // color_encoding all_default: sRGB
+    w.write(2, 0); // extensions
+}
+
+fn write_frame_header(w: &mut BitWriter) {
+    w.write_bool(false); // all_default
+    w.write(2, 0); // frame_type: regular
+    w.write(1, 1); // encoding: modular
+    w.write(2, 0); // flags
+    w.write_bool(false); // do_ycbcr
+    w.write(2, 0); // upsampling: 1
+    w.write(2, 0); // alpha upsampling: 1
+    w.write(2, 1); // group_size_shift: 256
+    w.write(2, 0); // num_passes: 1
+    w.write_bool(false); // have_crop
+    w.write(2, 0); // blending: replace
+    w.write(2, 0); // alpha blending: replace
+    w.write_bool(true); // is_last
+    w.write(2, 0); // name: empty
+    w.write_bool(false); // restoration_filter all_default
+    w.write_bool(false); // gaborish
+    w.write(2, 0); // epf_iters
+    w.write(2, 0); // restoration_filter extensions
+    w.write(2, 0); // extensions
+}
+
+fn write_toc_entry(w: &mut BitWriter, size: u32) {
+    if size < 1024 {
+        w.write(2, 0);
+        w.write(10, size);
+    } else if size < 17408 {
+        w.write(2, 1);
+        w.write(14, size - 1024);
+    } else if size < 4211712 {
+        w.write(2, 2);
+        w.write(22, size - 17408);
+    } else {
+        w.write(2, 3);
+        w.write(30, size - 4211712);
+    }
+}
+
+/// Writes every frame section; a single section when the image fits one group.
+fn write_sections(channels: &[Vec<u8>], width: usize, height: usize) -> Vec<Vec<u8>> {
+    let groups_x = width.div_ceil(GROUP_DIM);
+    let groups_y = height.div_ceil(GROUP_DIM);
+    let num_lf_groups = width.div_ceil(LF_GROUP_DIM) * height.div_ceil(LF_GROUP_DIM);
+
+    let mut lf_global = BitWriter::new();
+    lf_global.write_bool(true); // LF dequantization all_default
+    lf_global.write_bool(true); // global tree present
+    write_tree(&mut lf_global);
+    write_flat_histograms(&mut lf_global, 1);
+    write_group_header(&mut lf_global);
+
+    if groups_x * groups_y == 1 {
+        // Channels no larger than a group are coded entirely in LfGlobal, and
+        // the remaining sections are empty.
+        let mut residuals = Vec::with_capacity(width * height * channels.len());
+        for channel in channels {
+            push_residuals(&mut residuals, channel, width, (0, 0, width, height));
+        }
+        write_ans_stream(&mut lf_global, &residuals);
+        return vec![lf_global.finish()];
+    }
+    // Otherwise every channel is split across the pass groups.
+
+    let mut sections = Vec::with_capacity(2 + num_lf_groups + groups_x * groups_y);
+    sections.push(lf_global.finish());
+    // No channel is downsampled and there are no VarDCT coefficients, so the
+    // LF groups and HfGlobal have nothing to code.
+    sections.extend(std::iter::repeat_n(Vec::new(), num_lf_groups + 1));
+    for gy in 0..groups_y {
+        for gx in 0..groups_x {
+            let x0 = gx * GROUP_DIM;
+            let y0 = gy * GROUP_DIM;
+            let rect = (
+                x0,
+                y0,
+                GROUP_DIM.min(width - x0),
+                GROUP_DIM.min(height - y0),
+            );
+            let mut group = BitWriter::new();
+            write_group_header(&mut group);
+            let mut residuals = Vec::with_capacity(rect.2 * rect.3 * channels.len());
+            for channel in channels {
+                push_residuals(&mut residuals, channel, width, rect);
+            }
+            write_ans_stream(&mut group, &residuals);
+            sections.push(group.finish());
+        }
+    }
+    sections
+}
+
+/// Writes a single-leaf MA tree selecting the gradient predictor.
+fn write_tree(w: &mut BitWriter) {
+    write_flat_histograms(w, NUM_TREE_CONTEXTS);
+    // property (0 = leaf), predictor, offset, multiplier log, multiplier bits.
+    write_ans_stream(w, &[0, GRADIENT_PREDICTOR, 0, 0, 0]);
+}
+
+fn write_group_header(w: &mut BitWriter) {
+    w.write_bool(true); // use_global_tree
+    w.write_bool(true); // weighted predictor header all_default
+    w.write(2, 0); // no transforms
+}
+
+/// Writes ANS histograms that share one evenly distributed 32-symbol cluster.
+fn write_flat_histograms(w: &mut BitWriter, num_contexts: usize) {
+    w.write_bool(false); // LZ77 disabled
+    if num_contexts > 1 {
+        w.write_bool(true); // simple context map
+        w.write(2, 0); // zero bits per entry: every context uses cluster 0
+    }
+    w.write_bool(false); // ANS rather than prefix codes
+    w.write(2, 0); // log_alpha_size: 5
+    w.write(3, 0); // hybrid uint split_exponent 0: tokens are bit lengths
+    w.write(2, 0b10); // evenly distributed histogram
+    w.write(1, 1); // alphabet size - 1 = 31 as an ANS u8
+    w.write(3, 4);
+    w.write(4, 15);
+}
+
+/// Splits a value into its hybrid uint token and raw extra bits.
+fn hybrid_uint(value: u32) -> (u32, usize, u32) {
+    if value == 0 {
+        return (0, 0, 0);
+    }
+    let num_bits = 31 - value.leading_zeros();
+    (num_bits + 1, num_bits as usize, value - (1 << num_bits))
+}
+
+/// Writes values as one ANS stream coded with the flat 32-symbol histogram.
+fn write_ans_stream(w: &mut BitWriter, values: &[u32]) {
+    let tokens: Vec<_> = values.iter().map(|&v| hybrid_uint(v)).collect();
+
+    // ANS is last-in first-out, so encode in reverse and record where the
+    // decoder will refill its state.
+    let mut state = ANS_FINAL_STATE;
+    let mut refills = vec![None; tokens.len()];
+    for (i, &(token, _, _)) in tokens.iter().enumerate().rev() {
+        if state >= 1 << 27 {
+            refills[i] = Some(state & 0xFFFF);
+            state >>= 16;
+        }
+        state = ((state >> 7) << 12) | (token << 7) | (state & 0x7F);
+    }
+
+    w.write(32, state);
+    for (refill, &(_, num_bits, bits)) in refills.iter().zip(&tokens) {
+        if let Some(refill) = refill {
+            w.write(16, *refill);
+        }
+        w.write(num_bits, bits);
+    }
+}
+
+/// Appends gradient-predicted residuals for one channel cropped to `rect`.
+fn push_residuals(
+    residuals: &mut Vec<u32>,
+    channel: &[u8],
+    stride: usize,
+    (x0, y0, width, height): (usize, usize, usize, usize),
+) {
+    let px = |x: usize, y: usize| channel[(y0 + y) * stride + x0 + x] as i32;
+    for y in 0..height {
+        for x in 0..width {
+            let left = if x > 0 {
+                px(x - 1, y)
+            } else if y > 0 {
+                px(0, y - 1)
+            } else {
+                0
+            };
+            let top = if y > 0 { px(x, y - 1) } else { left };
+            let topleft = if x > 0 && y > 0 {
+                px(x - 1, y - 1)
+            } else {
+                left
+            };
+            let residual = px(x, y) - clamped_gradient(left, top, topleft);
+            residuals.push(if residual >= 0 {
+                (residual as u32) << 1
+            } else {
+                ((-residual as u32) << 1) - 1
+            });
+        }
+    }
+}
+
+fn clamped_gradient(left: i32, top: i32, topleft: i32) -> i32 {
+    let min = left.min(top);
+    let max = left.max(top);
+    if topleft < min {
+        max
+    } else if topleft > max {
+        min
+    } else {
+        left + top - topleft
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::{jxl_decode_alloc, jxl_free_buffer};
+    use crate::types::*;
+
+    fn decode_rgba8(data: &[u8]) -> Vec<u8> {
+        let options = JxlDecodeOptions {
+            PixelFormat: JxlPixelFormat {
+                DataFormat: JxlDataFormat::Uint8,
+                ColorType: JxlColorType::Rgba,
+                Endianness: JxlEndianness::Native,
+            },
+            ..JxlDecodeOptions::default()
+        };
+        let mut ptr = std::ptr::null_mut();
+        let mut len = 0;
+        let status = unsafe {
+            jxl_decode_alloc(
+                data.as_ptr(),
+                data.len(),
+                &options,
+                &mut ptr,
+                &mut len,
+                std::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, JxlStatus::Success);
+        let pixels = unsafe { std::slice::from_raw_parts(ptr, len) }.to_vec();
+        unsafe { jxl_free_buffer(ptr, len) };
+        pixels
+    }
+
+    fn test_pattern(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height * 4)
+            .map(|i| ((i * 37) ^ (i / 4 / width * 11)) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_hybrid_uint_splits_bit_length() {
+        assert_eq!(hybrid_uint(0), (0, 0, 0));
+        assert_eq!(hybrid_uint(1), (1, 0, 0));
+        assert_eq!(hybrid_uint(5), (3, 2, 1));
+        assert_eq!(hybrid_uint(510), (9, 8, 254));
+    }
+
+    #[test]
+    fn test_round_trip_single_group() {
+        let pixels = test_pattern(5, 3);
+        let encoded = encode_lossless_rgba8(&pixels, 5, 3);
+        assert!(encoded.starts_with(&CONTAINER_SIGNATURE));
+        assert_eq!(decode_rgba8(&encoded), pixels);
+    }
+
+    #[test]
+    fn test_round_trip_multiple_groups() {
+        let pixels = test_pattern(300, 260);
+        let encoded = encode_lossless_rgba8(&pixels, 300, 260);
+        assert_eq!(decode_rgba8(&encoded), pixels);
+    }
+}
@@ -3,20 +3,63 @@
 // Use of this source code is governed by a BSD-style
 // license that can be found in the LICENSE file.
 
-//! Error handling for the C API.
+//! Error handling and diagnostics for the C API.
 
-use std::cell::RefCell;
-use std::ffi::c_char;
+use crate::types::{JxlLogLevel, JxlStatus};
+use std::cell::{Cell, RefCell};
+use std::ffi::{CString, c_char, c_void};
+use std::sync::{PoisonError, RwLock};
 
 thread_local! {
     static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+    static LAST_ERROR_STATUS: Cell<JxlStatus> = const { Cell::new(JxlStatus::Success) };
+}
+
+type LogCallback = unsafe extern "C" fn(JxlLogLevel, *const c_char, *mut c_void);
+
+/// Callback and user data set with `jxl_set_log_callback`. The user data is
+/// kept as an address so the pair can be shared between threads.
+static LOG_CALLBACK: RwLock<Option<(LogCallback, usize)>> = RwLock::new(None);
+
+/// Passes a diagnostic message to the log callback, if one is set. The
+/// message is only formatted when it will be delivered.
+pub(crate) fn log(level: JxlLogLevel, message: impl FnOnce() -> String) {
+    // Copied out so the lock is released before the call, letting the
+    // callback replace itself
+    let callback = {
+        let guard = LOG_CALLBACK.read().unwrap_or_else(PoisonError::into_inner);
+        *guard
+    };
+    let Some((callback, user_data)) = callback else {
+        return;
+    };
+
+    let message = CString::new(message().replace('\0', " ")).unwrap_or_default();
+    unsafe { callback(level, message.as_ptr(), user_data as *mut c_void) };
 }
 
 /// Sets the last error message for the current thread.
 pub(crate) fn set_last_error(msg: impl Into<String>) {
+    set_last_error_with_status(JxlStatus::Error, msg);
+}
+
+/// Sets the last error message along with a specific status code, so that
+/// functions returning a `JxlDecoderEvent` can still report the cause.
+pub(crate) fn set_last_error_with_status(status: JxlStatus, msg: impl Into<String>) {
     LAST_ERROR.with(|e| {
         *e.borrow_mut() = msg.into();
     });
+    LAST_ERROR_STATUS.with(|s| s.set(status));
+}
+
+/// Gets the status code of the last error.
+pub(crate) fn last_error_status() -> JxlStatus {
+    LAST_ERROR_STATUS.with(|s| s.get())
+}
+
+/// Gets the status and message of the last error.
+pub(crate) fn last_error() -> (JxlStatus, String) {
+    (last_error_status(), LAST_ERROR.with(|e| e.borrow().clone()))
 }
 
 /// Clears the last error message.
@@ -24,6 +67,7 @@ pub(crate) fn clear_last_error() {
     LAST_ERROR.with(|e| {
         e.borrow_mut().clear();
     });
+    LAST_ERROR_STATUS.with(|s| s.set(JxlStatus::Success));
 }
 
 /// Gets the last error message.
@@ -41,28 +85,48 @@ pub(crate) fn clear_last_error() {
 /// The buffer must be valid for writes of `buffer_size` bytes.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn jxl_get_last_error(buffer: *mut c_char, buffer_size: usize) -> usize {
+    LAST_ERROR.with(|e| unsafe { write_error_message(&e.borrow(), buffer, buffer_size) })
+}
+
+/// Copies `message` into a caller buffer as a null-terminated string,
+/// truncating it to fit. Returns the full message length.
+///
+/// # Safety
+/// If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
+pub(crate) unsafe fn write_error_message(
+    message: &str,
+    buffer: *mut c_char,
+    buffer_size: usize,
+) -> usize {
     if buffer.is_null() || buffer_size == 0 {
-        return LAST_ERROR.with(|e| e.borrow().len());
+        return message.len();
     }
 
-    LAST_ERROR.with(|e| {
-        let error = e.borrow();
-        let bytes = error.as_bytes();
-        let copy_len = bytes.len().min(buffer_size - 1);
-
-        if copy_len > 0 {
-            unsafe {
-                std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer as *mut u8, copy_len);
-            }
-        }
+    let bytes = message.as_bytes();
+    let copy_len = bytes.len().min(buffer_size - 1);
 
-        // Null terminate
+    if copy_len > 0 {
         unsafe {
-            *buffer.add(copy_len) = 0;
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), buffer as *mut u8, copy_len);
         }
+    }
+
+    // Null terminate
+    unsafe {
+        *buffer.add(copy_len) = 0;
+    }
 
-        error.len()
-    })
+    message.len()
+}
+
+/// Gets the status code of the last error.
+///
+/// Functions that return a `JxlDecoderEvent` report every failure as `Error`;
+/// this returns the specific cause, such as `MemoryLimitExceeded`. Other
+/// failures report `Error`, and `Success` means no error is set.
+#[unsafe(no_mangle)]
+pub extern "C" fn jxl_get_last_error_status() -> JxlStatus {
+    last_error_status()
 }
 
 /// Clears the last error message.
@@ -70,3 +134,32 @@ pub unsafe extern "C" fn jxl_get_last_error(buffer: *mut c_char, buffer_size: us
 pub extern "C" fn jxl_clear_last_error() {
     clear_last_error();
 }
+
+/// Sets a callback for diagnostic messages, or clears it when `callback` is
+/// null.
+///
+/// The library reports decode milestones and recoverable problems that don't
+/// fail the call, such as a skipped frame, a CMS fallback, or an unknown
+/// container box, which helps when tracking down problems in the field. The
+/// callback is shared by all decoders and may be called from any thread that
+/// decodes.
+///
+/// # Arguments
+/// * `callback` - Called with the level, a null-terminated UTF-8 message and
+///   `user_data`. The message is only valid during the call.
+/// * `user_data` - Passed through to `callback`.
+///
+/// # Safety
+/// `callback` must be safe to call with `user_data` from any thread until it
+/// is replaced or cleared, and must not call back into the decoder that logged.
+/// It may call `jxl_set_log_callback`, e.g. to clear itself.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jxl_set_log_callback(
+    callback: Option<
+        unsafe extern "C" fn(level: JxlLogLevel, message: *const c_char, user_data: *mut c_void),
+    >,
+    user_data: *mut c_void,
+) {
+    *LOG_CALLBACK.write().unwrap_or_else(PoisonError::into_inner) =
+        callback.map(|callback| (callback, user_data as usize));
+}
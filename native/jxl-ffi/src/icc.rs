@@ -0,0 +1,477 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! Minimal ICC profile parsing, used to classify profiles without a CMS.
+
+use jxl::api::{JxlColorEncoding, JxlPrimaries, JxlTransferFunction, JxlWhitePoint};
+use jxl::headers::color_encoding::RenderingIntent;
+
+/// Size of the fixed ICC header that precedes the tag table.
+const HEADER_SIZE: usize = 128;
+
+/// CICP transfer characteristics value for PQ (ITU-T H.273).
+const CICP_TC_PQ: u8 = 16;
+/// CICP transfer characteristics value for HLG (ITU-T H.273).
+const CICP_TC_HLG: u8 = 18;
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Returns the data of the tag with the given signature, if present and in bounds.
+pub(crate) fn find_tag<'a>(icc: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    let count = read_u32(icc, HEADER_SIZE)? as usize;
+    // The count is untrusted, so only entries that fit in the profile are read
+    let max_count = (icc.len() - HEADER_SIZE - 4) / 12;
+    (0..count.min(max_count)).find_map(|i| {
+        let entry = HEADER_SIZE + 4 + i * 12;
+        if icc.get(entry..entry + 4)? != signature {
+            return None;
+        }
+        let offset = read_u32(icc, entry + 4)? as usize;
+        let size = read_u32(icc, entry + 8)? as usize;
+        icc.get(offset..offset.checked_add(size)?)
+    })
+}
+
+/// Returns the transfer characteristics from the `cicp` tag, if present.
+pub(crate) fn cicp_transfer_characteristics(icc: &[u8]) -> Option<u8> {
+    let tag = find_tag(icc, b"cicp")?;
+    if tag.len() < 12 || &tag[0..4] != b"cicp" {
+        return None;
+    }
+    Some(tag[9])
+}
+
+/// Returns true if the profile's tags indicate a PQ or HLG curve.
+pub(crate) fn is_hdr(icc: &[u8]) -> bool {
+    matches!(
+        cicp_transfer_characteristics(icc),
+        Some(CICP_TC_PQ | CICP_TC_HLG)
+    )
+}
+
+/// Returns the transfer function signalled by the `cicp` tag, if representable.
+fn cicp_transfer_function(icc: &[u8]) -> Option<JxlTransferFunction> {
+    match cicp_transfer_characteristics(icc)? {
+        1 | 6 | 14 | 15 => Some(JxlTransferFunction::BT709),
+        8 => Some(JxlTransferFunction::Linear),
+        13 => Some(JxlTransferFunction::SRGB),
+        CICP_TC_PQ => Some(JxlTransferFunction::PQ),
+        17 => Some(JxlTransferFunction::DCI),
+        CICP_TC_HLG => Some(JxlTransferFunction::HLG),
+        _ => None,
+    }
+}
+
+/// A decoded `curv` or `para` tone reproduction curve.
+enum Curve {
+    /// Sampled table, interpolated linearly.
+    Table(Vec<f32>),
+    /// Parametric curve: function type and its parameters.
+    Parametric(u16, [f32; 7]),
+}
+
+impl Curve {
+    fn parse(tag: &[u8]) -> Option<Curve> {
+        match tag.get(0..4)? {
+            b"curv" => {
+                let count = read_u32(tag, 8)? as usize;
+                match count {
+                    0 => Some(Curve::Parametric(0, [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0])),
+                    // A single entry is a u8Fixed8 gamma value.
+                    1 => {
+                        let gamma = u16::from_be_bytes([*tag.get(12)?, *tag.get(13)?]);
+                        let mut params = [0.0; 7];
+                        params[0] = gamma as f32 / 256.0;
+                        Some(Curve::Parametric(0, params))
+                    }
+                    _ => {
+                        // The count is untrusted; tables past the tag are rejected
+                        let end = count.checked_mul(2)?.checked_add(12)?;
+                        let entries = tag.get(12..end)?;
+                        let table = entries
+                            .chunks_exact(2)
+                            .map(|b| u16::from_be_bytes([b[0], b[1]]) as f32 / 65535.0)
+                            .collect();
+                        Some(Curve::Table(table))
+                    }
+                }
+            }
+            b"para" => {
+                let function_type = u16::from_be_bytes([*tag.get(8)?, *tag.get(9)?]);
+                let param_count = match function_type {
+                    0 => 1,
+                    1 => 3,
+                    2 => 4,
+                    3 => 5,
+                    4 => 7,
+                    _ => return None,
+                };
+                let mut params = [0.0; 7];
+                for (i, param) in params.iter_mut().take(param_count).enumerate() {
+                    *param = read_u32(tag, 12 + i * 4)? as i32 as f32 / 65536.0;
+                }
+                Some(Curve::Parametric(function_type, params))
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluates the curve (device value to linear light) at `x` in [0, 1].
+    fn eval(&self, x: f32) -> f32 {
+        match self {
+            Curve::Table(table) => {
+                let pos = x * (table.len() - 1) as f32;
+                let i = (pos as usize).min(table.len() - 2);
+                let t = pos - i as f32;
+                table[i] + (table[i + 1] - table[i]) * t
+            }
+            Curve::Parametric(function_type, p) => {
+                let [g, a, b, c, d, e, f] = *p;
+                let power = |v: f32| if v > 0.0 { v.powf(g) } else { 0.0 };
+                match function_type {
+                    0 => power(x),
+                    1 if x >= -b / a => power(a * x + b),
+                    1 => 0.0,
+                    2 if x >= -b / a => power(a * x + b) + c,
+                    2 => c,
+                    3 if x >= d => power(a * x + b),
+                    3 => c * x,
+                    _ if x >= d => power(a * x + b) + e,
+                    _ => c * x + f,
+                }
+            }
+        }
+    }
+}
+
+/// Number of points at which curves are compared.
+const CURVE_SAMPLES: usize = 64;
+/// Maximum absolute difference for two curves to be considered equal.
+const CURVE_TOLERANCE: f32 = 2e-3;
+
+fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn bt709_to_linear(x: f32) -> f32 {
+    if x < 0.081 {
+        x / 4.5
+    } else {
+        ((x + 0.099) / 1.099).powf(1.0 / 0.45)
+    }
+}
+
+fn curve_matches(curve: &Curve, reference: impl Fn(f32) -> f32) -> bool {
+    (0..=CURVE_SAMPLES).all(|i| {
+        let x = i as f32 / CURVE_SAMPLES as f32;
+        (curve.eval(x) - reference(x)).abs() <= CURVE_TOLERANCE
+    })
+}
+
+/// Classifies a single curve as linear, sRGB, BT.709 or a pure gamma.
+fn classify_curve(curve: &Curve) -> Option<JxlTransferFunction> {
+    if curve_matches(curve, |x| x) {
+        return Some(JxlTransferFunction::Linear);
+    }
+    if curve_matches(curve, srgb_to_linear) {
+        return Some(JxlTransferFunction::SRGB);
+    }
+    if curve_matches(curve, bt709_to_linear) {
+        return Some(JxlTransferFunction::BT709);
+    }
+
+    // Fit a pure power curve through the midpoint and check it holds everywhere.
+    let mid = curve.eval(0.5);
+    if mid <= 0.0 || mid >= 1.0 {
+        return None;
+    }
+    let gamma = mid.ln() / 0.5f32.ln();
+    // JPEG XL only signals gammas that darken, i.e. a decoding exponent >= 1.
+    if gamma < 1.0 || !curve_matches(curve, |x| x.powf(gamma)) {
+        return None;
+    }
+    Some(JxlTransferFunction::Gamma(1.0 / gamma))
+}
+
+/// Returns a best-effort transfer function for an ICC profile.
+///
+/// The `cicp` tag is used when present. Otherwise the `rTRC`, `gTRC` and
+/// `bTRC` curves (or `kTRC` for grayscale) are sampled and matched against
+/// linear, sRGB, BT.709 and pure gamma curves; all channels must agree.
+pub(crate) fn transfer_function(icc: &[u8]) -> Option<JxlTransferFunction> {
+    if let Some(tf) = cicp_transfer_function(icc) {
+        return Some(tf);
+    }
+
+    let signatures: &[&[u8; 4]] = if find_tag(icc, b"kTRC").is_some() {
+        &[b"kTRC"]
+    } else {
+        &[b"rTRC", b"gTRC", b"bTRC"]
+    };
+    let mut result: Option<JxlTransferFunction> = None;
+    for signature in signatures {
+        let tf = classify_curve(&Curve::parse(find_tag(icc, signature)?)?)?;
+        match &result {
+            None => result = Some(tf),
+            Some(prev) if same_transfer_function(prev, &tf) => {}
+            Some(_) => return None,
+        }
+    }
+    result
+}
+
+/// Returns true if two transfer functions are equal, allowing for the
+/// fixed-point rounding of gammas read back from ICC curves.
+pub(crate) fn same_transfer_function(a: &JxlTransferFunction, b: &JxlTransferFunction) -> bool {
+    match (a, b) {
+        (JxlTransferFunction::Gamma(a), JxlTransferFunction::Gamma(b)) => (a - b).abs() <= 1e-3,
+        _ => a == b,
+    }
+}
+
+/// Returns true if two simple encodings describe the same color space,
+/// ignoring rendering intent.
+pub(crate) fn same_encoding(a: &JxlColorEncoding, b: &JxlColorEncoding) -> bool {
+    match (a, b) {
+        (
+            JxlColorEncoding::RgbColorSpace {
+                white_point: wp_a,
+                primaries: prim_a,
+                transfer_function: tf_a,
+                ..
+            },
+            JxlColorEncoding::RgbColorSpace {
+                white_point: wp_b,
+                primaries: prim_b,
+                transfer_function: tf_b,
+                ..
+            },
+        ) => wp_a == wp_b && prim_a == prim_b && same_transfer_function(tf_a, tf_b),
+        (
+            JxlColorEncoding::GrayscaleColorSpace {
+                white_point: wp_a,
+                transfer_function: tf_a,
+                ..
+            },
+            JxlColorEncoding::GrayscaleColorSpace {
+                white_point: wp_b,
+                transfer_function: tf_b,
+                ..
+            },
+        ) => wp_a == wp_b && same_transfer_function(tf_a, tf_b),
+        _ => false,
+    }
+}
+
+/// Maximum difference between colorant XYZ values of matching primaries.
+const COLORANT_TOLERANCE: f32 = 2e-3;
+
+fn rendering_intent(icc: &[u8]) -> RenderingIntent {
+    match read_u32(icc, 64) {
+        Some(0) => RenderingIntent::Perceptual,
+        Some(2) => RenderingIntent::Saturation,
+        Some(3) => RenderingIntent::Absolute,
+        _ => RenderingIntent::Relative,
+    }
+}
+
+fn read_xyz(icc: &[u8], signature: &[u8; 4]) -> Option<[f32; 3]> {
+    let tag = find_tag(icc, signature)?;
+    if tag.get(0..4)? != b"XYZ " {
+        return None;
+    }
+    let value = |i: usize| Some(read_u32(tag, 8 + i * 4)? as i32 as f32 / 65536.0);
+    Some([value(0)?, value(1)?, value(2)?])
+}
+
+fn read_colorants(icc: &[u8]) -> Option<[[f32; 3]; 3]> {
+    Some([
+        read_xyz(icc, b"rXYZ")?,
+        read_xyz(icc, b"gXYZ")?,
+        read_xyz(icc, b"bXYZ")?,
+    ])
+}
+
+/// Finds the D65 primaries whose colorants match the profile's `rXYZ`,
+/// `gXYZ` and `bXYZ` tags.
+fn matching_primaries(icc: &[u8]) -> Option<JxlPrimaries> {
+    let colorants = read_colorants(icc)?;
+    [JxlPrimaries::SRGB, JxlPrimaries::P3, JxlPrimaries::BT2100]
+        .into_iter()
+        .find(|primaries| {
+            // Generate the reference colorants with the same math used for
+            // the decoder's own ICC output.
+            let reference = JxlColorEncoding::RgbColorSpace {
+                white_point: JxlWhitePoint::D65,
+                primaries: primaries.clone(),
+                transfer_function: JxlTransferFunction::Linear,
+                rendering_intent: RenderingIntent::Relative,
+            }
+            .maybe_create_profile();
+            let Some(reference) = reference.ok().flatten().and_then(|p| read_colorants(&p)) else {
+                return false;
+            };
+            colorants
+                .iter()
+                .flatten()
+                .zip(reference.iter().flatten())
+                .all(|(a, b)| (a - b).abs() <= COLORANT_TOLERANCE)
+        })
+}
+
+/// Returns the simple encoding an ICC profile is equivalent to, if it
+/// matches a known color space.
+///
+/// RGB profiles are matched by their `cicp` primaries or by comparing their
+/// colorants against sRGB, Display P3 and BT.2100 primaries with a D65 white
+/// point. Grayscale profiles are assumed to use a D65 white point.
+pub(crate) fn to_simple_encoding(icc: &[u8]) -> Option<JxlColorEncoding> {
+    let transfer_function = transfer_function(icc)?;
+    let rendering_intent = rendering_intent(icc);
+
+    match icc.get(16..20)? {
+        b"GRAY" => Some(JxlColorEncoding::GrayscaleColorSpace {
+            white_point: JxlWhitePoint::D65,
+            transfer_function,
+            rendering_intent,
+        }),
+        b"RGB " => {
+            let cicp = find_tag(icc, b"cicp").filter(|tag| tag.len() >= 12);
+            let (white_point, primaries) = match cicp.map(|tag| tag[8]) {
+                Some(1) => (JxlWhitePoint::D65, JxlPrimaries::SRGB),
+                Some(9) => (JxlWhitePoint::D65, JxlPrimaries::BT2100),
+                Some(11) => (JxlWhitePoint::DCI, JxlPrimaries::P3),
+                Some(12) => (JxlWhitePoint::D65, JxlPrimaries::P3),
+                _ => (JxlWhitePoint::D65, matching_primaries(icc)?),
+            };
+            Some(JxlColorEncoding::RgbColorSpace {
+                white_point,
+                primaries,
+                transfer_function,
+                rendering_intent,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icc_for(transfer_function: JxlTransferFunction) -> Vec<u8> {
+        JxlColorEncoding::RgbColorSpace {
+            white_point: JxlWhitePoint::D65,
+            primaries: JxlPrimaries::BT2100,
+            transfer_function,
+            rendering_intent: RenderingIntent::Relative,
+        }
+        .maybe_create_profile()
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_is_hdr_from_cicp() {
+        assert!(is_hdr(&icc_for(JxlTransferFunction::PQ)));
+        assert!(is_hdr(&icc_for(JxlTransferFunction::HLG)));
+        assert!(!is_hdr(&icc_for(JxlTransferFunction::SRGB)));
+    }
+
+    #[test]
+    fn test_find_tag_rejects_truncated_profile() {
+        let icc = icc_for(JxlTransferFunction::PQ);
+        assert!(find_tag(&icc, b"desc").is_some());
+        assert!(find_tag(&icc[..HEADER_SIZE + 8], b"desc").is_none());
+        assert!(find_tag(&icc[..64], b"desc").is_none());
+
+        // A tag count far beyond the profile size only reads the entries present
+        let mut icc = icc[..HEADER_SIZE + 16].to_vec();
+        icc[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(find_tag(&icc, b"none").is_none());
+    }
+
+    fn gray_icc_for(transfer_function: JxlTransferFunction) -> Vec<u8> {
+        JxlColorEncoding::GrayscaleColorSpace {
+            white_point: JxlWhitePoint::D65,
+            transfer_function,
+            rendering_intent: RenderingIntent::Relative,
+        }
+        .maybe_create_profile()
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_transfer_function_from_trc() {
+        // Grayscale profiles carry no cicp tag, so the curve itself is classified.
+        for tf in [
+            JxlTransferFunction::Linear,
+            JxlTransferFunction::SRGB,
+            JxlTransferFunction::BT709,
+        ] {
+            assert_eq!(transfer_function(&gray_icc_for(tf.clone())), Some(tf));
+        }
+        match transfer_function(&gray_icc_for(JxlTransferFunction::Gamma(1.0 / 2.2))) {
+            Some(JxlTransferFunction::Gamma(g)) => assert!((g - 1.0 / 2.2).abs() < 1e-3),
+            other => panic!("expected gamma, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_transfer_function_from_table() {
+        let mut tag = b"curv\0\0\0\0".to_vec();
+        tag.extend_from_slice(&256u32.to_be_bytes());
+        for i in 0..256 {
+            let v = srgb_to_linear(i as f32 / 255.0);
+            tag.extend_from_slice(&((v * 65535.0).round() as u16).to_be_bytes());
+        }
+        let curve = Curve::parse(&tag).unwrap();
+        assert_eq!(classify_curve(&curve), Some(JxlTransferFunction::SRGB));
+
+        // An entry count beyond the tag is rejected
+        tag[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(Curve::parse(&tag).is_none());
+    }
+
+    #[test]
+    fn test_transfer_function_prefers_cicp() {
+        let icc = icc_for(JxlTransferFunction::PQ);
+        assert_eq!(transfer_function(&icc), Some(JxlTransferFunction::PQ));
+    }
+
+    #[test]
+    fn test_to_simple_encoding_matches_colorants() {
+        // Custom gamma has no cicp code, so primaries come from the colorants.
+        let encoding = JxlColorEncoding::RgbColorSpace {
+            white_point: JxlWhitePoint::D65,
+            primaries: JxlPrimaries::P3,
+            transfer_function: JxlTransferFunction::Gamma(1.0 / 2.2),
+            rendering_intent: RenderingIntent::Perceptual,
+        };
+        let icc = encoding.maybe_create_profile().unwrap().unwrap();
+        assert!(find_tag(&icc, b"cicp").is_none());
+        let parsed = to_simple_encoding(&icc).unwrap();
+        assert!(same_encoding(&parsed, &encoding));
+        assert!(!same_encoding(&parsed, &JxlColorEncoding::srgb(false)));
+    }
+
+    #[test]
+    fn test_to_simple_encoding_srgb() {
+        let icc = JxlColorEncoding::srgb(false)
+            .maybe_create_profile()
+            .unwrap()
+            .unwrap();
+        let parsed = to_simple_encoding(&icc).unwrap();
+        assert!(same_encoding(&parsed, &JxlColorEncoding::srgb(false)));
+    }
+}
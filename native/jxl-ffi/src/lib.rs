@@ -8,10 +8,21 @@
 //! This crate provides a C-compatible API for decoding JPEG XL images,
 //! designed for FFI bindings to languages like C#.
 
+mod alpha;
+mod checksum;
 mod cms;
+mod container;
 mod conversions;
 mod decoder;
+#[cfg(feature = "encode")]
+mod encoder;
 mod error;
+mod icc;
+mod orientation;
+mod sanitize;
+mod stats;
+#[cfg(feature = "timings")]
+mod timings;
 #[cfg(feature = "tone-mapping")]
 mod tone_mapping;
 mod types;
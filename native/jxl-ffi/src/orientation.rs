@@ -0,0 +1,144 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! Undoing the header orientation for callers that apply it themselves.
+//!
+//! Upstream always outputs upright pixels. With `AdjustOrientation` off, each
+//! frame is decoded upright into scratch space and written back in stored
+//! (encoded) orientation here.
+
+use crate::types::JxlOrientation;
+use jxl::image::JxlOutputBuffer;
+
+/// Whether `orientation` swaps the image's width and height.
+pub(crate) fn is_transposing(orientation: JxlOrientation) -> bool {
+    matches!(
+        orientation,
+        JxlOrientation::Transpose
+            | JxlOrientation::Rotate90Cw
+            | JxlOrientation::AntiTranspose
+            | JxlOrientation::Rotate90Ccw
+    )
+}
+
+/// Size of the upright image for a stored `width` x `height` image.
+pub(crate) fn upright_size(
+    width: usize,
+    height: usize,
+    orientation: JxlOrientation,
+) -> (usize, usize) {
+    if is_transposing(orientation) {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+/// Upright scratch space for one output plane of a frame whose orientation
+/// is undone after decoding.
+pub(crate) struct UprightPlane {
+    pixels: Vec<u8>,
+    width: usize,
+    height: usize,
+    pixel_size: usize,
+    orientation: JxlOrientation,
+}
+
+impl UprightPlane {
+    /// Allocates a plane for a stored `width` x `height` output.
+    pub(crate) fn new(
+        width: usize,
+        height: usize,
+        pixel_size: usize,
+        orientation: JxlOrientation,
+    ) -> Self {
+        Self {
+            pixels: vec![0u8; width * height * pixel_size],
+            width,
+            height,
+            pixel_size,
+            orientation,
+        }
+    }
+
+    /// Output buffer upstream decodes the upright plane into.
+    pub(crate) fn output_buffer(&mut self) -> JxlOutputBuffer<'_> {
+        let (width, height) = upright_size(self.width, self.height, self.orientation);
+        JxlOutputBuffer::new(&mut self.pixels, height, width * self.pixel_size)
+    }
+
+    /// Writes the decoded plane to `out` in stored orientation.
+    pub(crate) fn write_back(&self, out: &mut [u8], out_stride: usize) {
+        undo_orientation(
+            &self.pixels,
+            out,
+            self.width,
+            self.height,
+            self.pixel_size,
+            out_stride,
+            self.orientation,
+        );
+    }
+}
+
+/// Writes the packed `upright` pixels to `out` in stored orientation.
+///
+/// `width` and `height` are the stored size, and `out` rows start
+/// `out_stride` bytes apart; bytes past each row's pixels are untouched.
+pub(crate) fn undo_orientation(
+    upright: &[u8],
+    out: &mut [u8],
+    width: usize,
+    height: usize,
+    pixel_size: usize,
+    out_stride: usize,
+    orientation: JxlOrientation,
+) {
+    let (upright_width, _) = upright_size(width, height, orientation);
+    for y in 0..height {
+        let row = &mut out[y * out_stride..][..width * pixel_size];
+        for (x, pixel) in row.chunks_exact_mut(pixel_size).enumerate() {
+            // Where the stored pixel (x, y) lands in the upright image
+            let (u, v) = match orientation {
+                JxlOrientation::Identity => (x, y),
+                JxlOrientation::FlipHorizontal => (width - 1 - x, y),
+                JxlOrientation::Rotate180 => (width - 1 - x, height - 1 - y),
+                JxlOrientation::FlipVertical => (x, height - 1 - y),
+                JxlOrientation::Transpose => (y, x),
+                JxlOrientation::Rotate90Cw => (height - 1 - y, x),
+                JxlOrientation::AntiTranspose => (height - 1 - y, width - 1 - x),
+                JxlOrientation::Rotate90Ccw => (y, width - 1 - x),
+            };
+            let start = (v * upright_width + u) * pixel_size;
+            pixel.copy_from_slice(&upright[start..start + pixel_size]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_orientation_of_3x2() {
+        // Upright images as upstream outputs them for a stored 3x2 image whose
+        // pixels are numbered 0..6 in row order
+        let cases = [
+            (JxlOrientation::Identity, [0, 1, 2, 3, 4, 5]),
+            (JxlOrientation::FlipHorizontal, [2, 1, 0, 5, 4, 3]),
+            (JxlOrientation::Rotate180, [5, 4, 3, 2, 1, 0]),
+            (JxlOrientation::FlipVertical, [3, 4, 5, 0, 1, 2]),
+            (JxlOrientation::Transpose, [0, 3, 1, 4, 2, 5]),
+            (JxlOrientation::Rotate90Cw, [3, 0, 4, 1, 5, 2]),
+            (JxlOrientation::AntiTranspose, [5, 2, 4, 1, 3, 0]),
+            (JxlOrientation::Rotate90Ccw, [2, 5, 1, 4, 0, 3]),
+        ];
+        for (orientation, upright) in cases {
+            let mut out = [0xAAu8; 8];
+            undo_orientation(&upright, &mut out, 3, 2, 1, 4, orientation);
+            assert_eq!(out, [0, 1, 2, 0xAA, 3, 4, 5, 0xAA], "{:?}", orientation);
+        }
+    }
+}
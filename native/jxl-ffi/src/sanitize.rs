@@ -0,0 +1,133 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! Replacement of non-finite samples in float output.
+
+use crate::stats::{is_big_endian, read_f32, read_u16};
+use crate::types::{JxlDataFormat, JxlPixelFormat};
+
+/// Largest finite half-precision value (65504), as bits.
+const F16_MAX_BITS: u16 = 0x7BFF;
+
+/// Returns 0 for NaN and the largest finite value of the same sign for
+/// ±infinity; finite values are returned unchanged.
+pub(crate) fn sanitize_f32(value: f32) -> f32 {
+    if value.is_nan() {
+        0.0
+    } else if value.is_infinite() {
+        f32::MAX.copysign(value)
+    } else {
+        value
+    }
+}
+
+/// Applies `sanitize_f32` to every value in `data`.
+pub(crate) fn sanitize_f32_slice(data: &mut [f32]) {
+    for value in data.iter_mut() {
+        *value = sanitize_f32(*value);
+    }
+}
+
+/// Half-precision equivalent of `sanitize_f32`, on raw bits.
+fn sanitize_f16_bits(bits: u16) -> u16 {
+    if bits & 0x7C00 != 0x7C00 {
+        bits
+    } else if bits & 0x03FF != 0 {
+        0
+    } else {
+        (bits & 0x8000) | F16_MAX_BITS
+    }
+}
+
+/// Sanitizes every `Float32` or `Float16` sample in place, reading the first
+/// `bytes_per_row` bytes of each `stride`-byte row. Does nothing for integer
+/// formats, which can't hold non-finite values.
+pub(crate) fn sanitize_float_output(
+    pixels: &mut [u8],
+    bytes_per_row: usize,
+    stride: usize,
+    format: &JxlPixelFormat,
+) {
+    let big_endian = is_big_endian(format.Endianness);
+
+    for row in pixels.chunks_mut(stride) {
+        let row = &mut row[..bytes_per_row];
+        match format.DataFormat {
+            JxlDataFormat::Uint8 | JxlDataFormat::Uint16 => return,
+            JxlDataFormat::Float16 => {
+                for sample in row.chunks_exact_mut(2) {
+                    let value = sanitize_f16_bits(read_u16(sample, big_endian));
+                    let value = if big_endian {
+                        value.to_be_bytes()
+                    } else {
+                        value.to_le_bytes()
+                    };
+                    sample.copy_from_slice(&value);
+                }
+            }
+            JxlDataFormat::Float32 => {
+                for sample in row.chunks_exact_mut(4) {
+                    let value = sanitize_f32(read_f32(sample, big_endian));
+                    let value = if big_endian {
+                        value.to_be_bytes()
+                    } else {
+                        value.to_le_bytes()
+                    };
+                    sample.copy_from_slice(&value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{JxlColorType, JxlEndianness};
+
+    #[test]
+    fn test_sanitize_f32() {
+        assert_eq!(sanitize_f32(f32::NAN), 0.0);
+        assert_eq!(sanitize_f32(f32::INFINITY), f32::MAX);
+        assert_eq!(sanitize_f32(f32::NEG_INFINITY), f32::MIN);
+        assert_eq!(sanitize_f32(-2.5), -2.5);
+    }
+
+    #[test]
+    fn test_sanitize_float_output_float32_skips_padding() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::Rgb,
+            DataFormat: JxlDataFormat::Float32,
+            Endianness: JxlEndianness::LittleEndian,
+        };
+        let mut pixels: Vec<u8> = [f32::NAN, f32::NEG_INFINITY, 0.5]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        pixels.extend_from_slice(&f32::NAN.to_le_bytes());
+        sanitize_float_output(&mut pixels, 12, 16, &format);
+
+        let samples: Vec<f32> = pixels
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(samples[..3], [0.0, f32::MIN, 0.5]);
+        assert!(samples[3].is_nan());
+    }
+
+    #[test]
+    fn test_sanitize_float_output_float16_big_endian() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::GrayscaleAlpha,
+            DataFormat: JxlDataFormat::Float16,
+            Endianness: JxlEndianness::BigEndian,
+        };
+        // NaN, +inf, -inf, 1.0
+        let mut pixels = [0x7E, 0x00, 0x7C, 0x00, 0xFC, 0x00, 0x3C, 0x00];
+        sanitize_float_output(&mut pixels, 8, 8, &format);
+
+        assert_eq!(pixels, [0x00, 0x00, 0x7B, 0xFF, 0xFB, 0xFF, 0x3C, 0x00]);
+    }
+}
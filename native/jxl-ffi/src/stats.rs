@@ -0,0 +1,376 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! Luminance statistics and opaque bounds of decoded frames, for
+//! auto-exposure, thumbnail and cropping heuristics that would otherwise need
+//! another pass over the pixels.
+
+use crate::conversions::bytes_per_sample;
+use crate::types::{
+    JxlColorType, JxlDataFormat, JxlEndianness, JxlLuminanceStats, JxlPixelFormat, JxlRect,
+};
+
+/// Number of bins in the recorded histogram. Requested histograms are
+/// rebinned from it, which is exact when the requested count divides it.
+pub(crate) const MAX_HISTOGRAM_BINS: usize = 1 << 16;
+
+/// BT.709 luminance weights for red, green and blue.
+const LUMINANCE_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// Luminance statistics of one decoded color buffer.
+pub(crate) struct LuminanceStats {
+    histogram: Vec<u32>,
+    min: f32,
+    max: f32,
+    sum: f64,
+    /// Number of finite luminance values in `min`, `max` and `sum`.
+    count: u64,
+}
+
+impl LuminanceStats {
+    /// Computes the statistics of `pixels` in `format`, reading the first
+    /// `bytes_per_row` bytes of each `stride`-byte row. Integer samples have
+    /// `int_bits` significant bits. Alpha is ignored.
+    pub(crate) fn compute(
+        pixels: &[u8],
+        bytes_per_row: usize,
+        stride: usize,
+        format: &JxlPixelFormat,
+        int_bits: u32,
+    ) -> Self {
+        let sample_size = bytes_per_sample(format.DataFormat);
+        let int_max = (u32::MAX >> (32 - int_bits)) as f32;
+        let big_endian = is_big_endian(format.Endianness);
+        let (pixel_size, channels) = match format.ColorType {
+            JxlColorType::Grayscale => (1, None),
+            JxlColorType::GrayscaleAlpha => (2, None),
+            JxlColorType::Rgb => (3, Some([0, 1, 2])),
+            JxlColorType::Rgba => (4, Some([0, 1, 2])),
+            JxlColorType::Bgr => (3, Some([2, 1, 0])),
+            JxlColorType::Bgra => (4, Some([2, 1, 0])),
+        };
+        let sample = |pixel: &[u8], channel: usize| {
+            let bytes = &pixel[channel * sample_size..(channel + 1) * sample_size];
+            read_sample(bytes, format.DataFormat, big_endian, int_max)
+        };
+
+        let mut stats = Self {
+            histogram: vec![0u32; MAX_HISTOGRAM_BINS],
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            sum: 0.0,
+            count: 0,
+        };
+        for row in pixels.chunks(stride) {
+            for pixel in row[..bytes_per_row].chunks_exact(pixel_size * sample_size) {
+                let luminance = match channels {
+                    None => sample(pixel, 0),
+                    Some(rgb) => rgb
+                        .iter()
+                        .zip(LUMINANCE_WEIGHTS)
+                        .map(|(&channel, weight)| sample(pixel, channel) * weight)
+                        .sum(),
+                };
+                stats.add(luminance);
+            }
+        }
+
+        stats
+    }
+
+    fn add(&mut self, luminance: f32) {
+        // NaN saturates to bin 0
+        let bin = (luminance.clamp(0.0, 1.0) * MAX_HISTOGRAM_BINS as f32) as usize;
+        self.histogram[bin.min(MAX_HISTOGRAM_BINS - 1)] += 1;
+
+        if luminance.is_finite() {
+            self.min = self.min.min(luminance);
+            self.max = self.max.max(luminance);
+            self.sum += luminance as f64;
+            self.count += 1;
+        }
+    }
+
+    /// Returns the luminance range and mean, ignoring non-finite values, or
+    /// all zeros if there are none.
+    pub(crate) fn summary(&self) -> JxlLuminanceStats {
+        if self.count == 0 {
+            return JxlLuminanceStats::default();
+        }
+        JxlLuminanceStats {
+            Min: self.min,
+            Max: self.max,
+            Mean: (self.sum / self.count as f64) as f32,
+        }
+    }
+
+    /// Writes a histogram of `out.len()` equal-width bins over [0, 1] to
+    /// `out`. Values outside the range are counted in the first or last bin.
+    /// `out.len()` must be between 1 and `MAX_HISTOGRAM_BINS`.
+    pub(crate) fn histogram(&self, out: &mut [u32]) {
+        let bins = out.len();
+        out.fill(0);
+        for (fine_bin, &count) in self.histogram.iter().enumerate() {
+            out[fine_bin * bins / MAX_HISTOGRAM_BINS] += count;
+        }
+    }
+}
+
+/// Returns the bounding box of the pixels whose alpha exceeds `threshold`, or
+/// an empty rectangle if there are none. Color types without alpha are opaque
+/// throughout. The pixels are read as for `LuminanceStats::compute`.
+pub(crate) fn opaque_bounds(
+    pixels: &[u8],
+    bytes_per_row: usize,
+    stride: usize,
+    format: &JxlPixelFormat,
+    int_bits: u32,
+    threshold: f32,
+) -> JxlRect {
+    let sample_size = bytes_per_sample(format.DataFormat);
+    let int_max = (u32::MAX >> (32 - int_bits)) as f32;
+    let big_endian = is_big_endian(format.Endianness);
+    let (samples, has_alpha) = match format.ColorType {
+        JxlColorType::Grayscale => (1, false),
+        JxlColorType::GrayscaleAlpha => (2, true),
+        JxlColorType::Rgb | JxlColorType::Bgr => (3, false),
+        JxlColorType::Rgba | JxlColorType::Bgra => (4, true),
+    };
+    let pixel_size = samples * sample_size;
+    let rows = pixels.chunks(stride).map(|row| &row[..bytes_per_row]);
+    if !has_alpha {
+        return JxlRect {
+            X: 0,
+            Y: 0,
+            Width: (bytes_per_row / pixel_size) as u32,
+            Height: rows.count() as u32,
+        };
+    }
+
+    let alpha_offset = pixel_size - sample_size;
+    let data_format = format.DataFormat;
+    let opaque = |pixel: &[u8]| {
+        read_sample(&pixel[alpha_offset..], data_format, big_endian, int_max) > threshold
+    };
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for (y, row) in rows.enumerate() {
+        let Some(first) = row.chunks_exact(pixel_size).position(opaque) else {
+            continue;
+        };
+        let last = row
+            .chunks_exact(pixel_size)
+            .rposition(opaque)
+            .expect("row has an opaque pixel");
+        bounds = Some(match bounds {
+            None => (first, y, last, y),
+            Some((left, top, right, _)) => (left.min(first), top, right.max(last), y),
+        });
+    }
+
+    bounds.map_or(JxlRect::default(), |(left, top, right, bottom)| JxlRect {
+        X: left as u32,
+        Y: top as u32,
+        Width: (right - left + 1) as u32,
+        Height: (bottom - top + 1) as u32,
+    })
+}
+
+/// Returns whether any pixel has alpha below the maximum, or `None` for a
+/// color type without alpha. The pixels are read as for
+/// `LuminanceStats::compute`.
+pub(crate) fn has_transparency(
+    pixels: &[u8],
+    bytes_per_row: usize,
+    stride: usize,
+    format: &JxlPixelFormat,
+    int_bits: u32,
+) -> Option<bool> {
+    let sample_size = bytes_per_sample(format.DataFormat);
+    let int_max = (u32::MAX >> (32 - int_bits)) as f32;
+    let big_endian = is_big_endian(format.Endianness);
+    let pixel_size = match format.ColorType {
+        JxlColorType::GrayscaleAlpha => 2 * sample_size,
+        JxlColorType::Rgba | JxlColorType::Bgra => 4 * sample_size,
+        JxlColorType::Grayscale | JxlColorType::Rgb | JxlColorType::Bgr => return None,
+    };
+
+    let alpha_offset = pixel_size - sample_size;
+    let data_format = format.DataFormat;
+    let transparent = pixels
+        .chunks(stride)
+        .flat_map(|row| row[..bytes_per_row].chunks_exact(pixel_size))
+        .any(|pixel| read_sample(&pixel[alpha_offset..], data_format, big_endian, int_max) < 1.0);
+    Some(transparent)
+}
+
+/// Whether samples in `endianness` are stored big-endian on this target.
+pub(crate) fn is_big_endian(endianness: JxlEndianness) -> bool {
+    match endianness {
+        JxlEndianness::Native => cfg!(target_endian = "big"),
+        JxlEndianness::LittleEndian => false,
+        JxlEndianness::BigEndian => true,
+    }
+}
+
+/// Reads one sample as a float, with integer formats normalized to [0, 1] by
+/// dividing by `int_max`.
+pub(crate) fn read_sample(
+    bytes: &[u8],
+    data_format: JxlDataFormat,
+    big_endian: bool,
+    int_max: f32,
+) -> f32 {
+    match data_format {
+        JxlDataFormat::Uint8 => bytes[0] as f32 / int_max,
+        JxlDataFormat::Uint16 => read_u16(bytes, big_endian) as f32 / int_max,
+        JxlDataFormat::Float16 => f16_to_f32(read_u16(bytes, big_endian)),
+        JxlDataFormat::Float32 => read_f32(bytes, big_endian),
+    }
+}
+
+/// Reads a 16-bit sample, as raw bits for `Float16`.
+pub(crate) fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    let bytes = [bytes[0], bytes[1]];
+    if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+/// Reads a `Float32` sample.
+pub(crate) fn read_f32(bytes: &[u8], big_endian: bool) -> f32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        f32::from_be_bytes(bytes)
+    } else {
+        f32::from_le_bytes(bytes)
+    }
+}
+
+/// Converts IEEE 754 half-precision bits to `f32`.
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let exponent = (bits >> 10) & 0x1F;
+    let mantissa = (bits & 0x3FF) as u32;
+    let magnitude = match exponent {
+        0 => mantissa as f32 * 2f32.powi(-24),
+        0x1F if mantissa == 0 => f32::INFINITY,
+        0x1F => f32::NAN,
+        _ => f32::from_bits(((exponent as u32 + 112) << 23) | (mantissa << 13)),
+    };
+    if bits & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16_to_f32() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3C00), 1.0);
+        assert_eq!(f16_to_f32(0xC000), -2.0);
+        assert_eq!(f16_to_f32(0x3555), 1365.0 / 4096.0);
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x7C00), f32::INFINITY);
+        assert!(f16_to_f32(0x7E00).is_nan());
+    }
+
+    #[test]
+    fn test_histogram_rebins_rgb_luminance() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::Rgb,
+            DataFormat: JxlDataFormat::Uint8,
+            Endianness: JxlEndianness::Native,
+        };
+        // Black, white, and pure green (luminance 0.7152), one row of three
+        let pixels = [0, 0, 0, 255, 255, 255, 0, 255, 0];
+        let stats = LuminanceStats::compute(&pixels, 9, 9, &format, 8);
+
+        let mut out = [0u32; 4];
+        stats.histogram(&mut out);
+        assert_eq!(out, [1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_opaque_bounds() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::GrayscaleAlpha,
+            DataFormat: JxlDataFormat::Uint8,
+            Endianness: JxlEndianness::Native,
+        };
+        // 4x3 alpha with a padded stride; only (1, 1) and (2, 2) are visible,
+        // and (3, 0) only below the threshold
+        let alpha = [[0, 0, 0, 64], [0, 255, 0, 0], [0, 0, 128, 0]];
+        let pixels: Vec<u8> = alpha
+            .iter()
+            .flat_map(|row| row.iter().flat_map(|&a| [200, a]).chain([0; 2]))
+            .collect();
+        let bounds = opaque_bounds(&pixels, 8, 10, &format, 8, 0.5);
+        let expected = JxlRect {
+            X: 1,
+            Y: 1,
+            Width: 2,
+            Height: 2,
+        };
+        assert_eq!(bounds, expected);
+
+        let bounds = opaque_bounds(&pixels, 8, 10, &format, 8, 0.0);
+        assert_eq!(
+            (bounds.X, bounds.Y, bounds.Width, bounds.Height),
+            (1, 0, 3, 3)
+        );
+        let bounds = opaque_bounds(&pixels, 8, 10, &format, 8, 1.0);
+        assert_eq!(bounds, JxlRect::default());
+    }
+
+    #[test]
+    fn test_has_transparency() {
+        let mut format = JxlPixelFormat {
+            ColorType: JxlColorType::Rgba,
+            DataFormat: JxlDataFormat::Uint16,
+            Endianness: JxlEndianness::BigEndian,
+        };
+        // One opaque 16-bit pixel per row, with zeroed padding that is not alpha
+        let opaque = [0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0, 0];
+        let pixels = [opaque, opaque].concat();
+        assert_eq!(has_transparency(&pixels, 8, 10, &format, 16), Some(false));
+
+        let mut pixels = pixels;
+        pixels[17] = 0xFE;
+        assert_eq!(has_transparency(&pixels, 8, 10, &format, 16), Some(true));
+
+        format.ColorType = JxlColorType::Rgb;
+        assert_eq!(has_transparency(&pixels, 6, 10, &format, 16), None);
+    }
+
+    #[test]
+    fn test_summary_of_hdr_float_pixels() {
+        let format = JxlPixelFormat {
+            ColorType: JxlColorType::Grayscale,
+            DataFormat: JxlDataFormat::Float32,
+            Endianness: JxlEndianness::LittleEndian,
+        };
+        // Values above 1.0 keep their magnitude; NaN is left out of the summary
+        let pixels: Vec<u8> = [0.25f32, 4.0, f32::NAN, 1.75]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let stats = LuminanceStats::compute(&pixels, 16, 16, &format, 32);
+
+        let summary = stats.summary();
+        assert_eq!(summary.Min, 0.25);
+        assert_eq!(summary.Max, 4.0);
+        assert_eq!(summary.Mean, 2.0);
+
+        let mut out = [0u32; 2];
+        stats.histogram(&mut out);
+        assert_eq!(out, [2, 2]);
+    }
+}
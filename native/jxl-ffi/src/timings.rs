@@ -0,0 +1,113 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+//
+// Use of this source code is governed by a BSD-style
+// license that can be found in the LICENSE file.
+
+//! Per-phase decode timings, compiled in with the `timings` feature.
+
+use crate::types::JxlDecodeTimings;
+use jxl::api::{JxlCms, JxlCmsTransformer, JxlColorProfile};
+use jxl::error::Result;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Accumulated nanoseconds per decoding phase.
+///
+/// Shared with the CMS transformers, which may run on different threads,
+/// so the counters are atomic.
+#[derive(Debug, Default)]
+pub(crate) struct DecodeTimings {
+    header: AtomicU64,
+    frame_decode: AtomicU64,
+    cms: AtomicU64,
+}
+
+impl DecodeTimings {
+    /// Runs `f`, adding its duration to the header-parse counter.
+    pub(crate) fn time_header<R>(&self, f: impl FnOnce() -> R) -> R {
+        time(&self.header, f)
+    }
+
+    /// Runs `f`, adding its duration to the frame-decode counter.
+    pub(crate) fn time_frame_decode<R>(&self, f: impl FnOnce() -> R) -> R {
+        time(&self.frame_decode, f)
+    }
+
+    /// Returns the accumulated timings in milliseconds.
+    pub(crate) fn get(&self) -> JxlDecodeTimings {
+        let ms = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        JxlDecodeTimings {
+            HeaderMs: ms(&self.header),
+            FrameDecodeMs: ms(&self.frame_decode),
+            CmsMs: ms(&self.cms),
+        }
+    }
+
+    /// Resets all counters to zero.
+    pub(crate) fn reset(&self) {
+        self.header.store(0, Ordering::Relaxed);
+        self.frame_decode.store(0, Ordering::Relaxed);
+        self.cms.store(0, Ordering::Relaxed);
+    }
+}
+
+fn time<R>(counter: &AtomicU64, f: impl FnOnce() -> R) -> R {
+    let start = Instant::now();
+    let result = f();
+    counter.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    result
+}
+
+/// CMS wrapper that adds the time spent in `inner` to the CMS counter.
+pub(crate) struct TimedCms {
+    pub inner: Box<dyn JxlCms>,
+    pub timings: Arc<DecodeTimings>,
+}
+
+impl JxlCms for TimedCms {
+    fn initialize_transforms(
+        &self,
+        n: usize,
+        max_pixels_per_transform: usize,
+        input: JxlColorProfile,
+        output: JxlColorProfile,
+        intensity_target: f32,
+    ) -> Result<(usize, Vec<Box<dyn JxlCmsTransformer + Send>>)> {
+        let (channels, transformers) = time(&self.timings.cms, || {
+            self.inner.initialize_transforms(
+                n,
+                max_pixels_per_transform,
+                input,
+                output,
+                intensity_target,
+            )
+        })?;
+
+        let transformers = transformers
+            .into_iter()
+            .map(|inner| {
+                Box::new(TimedTransformer {
+                    inner,
+                    timings: self.timings.clone(),
+                }) as Box<dyn JxlCmsTransformer + Send>
+            })
+            .collect();
+        Ok((channels, transformers))
+    }
+}
+
+struct TimedTransformer {
+    inner: Box<dyn JxlCmsTransformer + Send>,
+    timings: Arc<DecodeTimings>,
+}
+
+impl JxlCmsTransformer for TimedTransformer {
+    fn do_transform(&mut self, input: &[f32], output: &mut [f32]) -> Result<()> {
+        time(&self.timings.cms, || self.inner.do_transform(input, output))
+    }
+
+    fn do_transform_inplace(&mut self, inout: &mut [f32]) -> Result<()> {
+        time(&self.timings.cms, || self.inner.do_transform_inplace(inout))
+    }
+}
@@ -11,6 +11,7 @@
 //!
 //! This is the reference method from ITU-R BT.2446-1 Method A.
 
+use super::bt2446a_linear::bt2446a_map;
 use super::common::{Bt2446aParams, bt2446a_knee};
 
 /// BT.2446a spec-compliant tone mapping on interleaved RGB data.
@@ -42,3 +43,17 @@ pub fn tone_map_bt2446a(params: &Bt2446aParams, luminances: [f32; 3], data: &mut
         data[base + 2] = (b_prime * ratio).max(0.0).powf(2.4);
     }
 }
+
+/// BT.2446a tone mapping on single-channel (grayscale) data.
+///
+/// For one channel, Y' is the gamma-encoded sample itself, so this is the
+/// knee curve applied directly to each value.
+///
+/// `data` is `[Y, Y, …]` in linear light, where 1.0 = source peak luminance.
+pub fn tone_map_bt2446a_gray(params: &Bt2446aParams, data: &mut [f32]) {
+    for y in data.iter_mut() {
+        if *y > 0.0 {
+            *y = bt2446a_map(params, *y);
+        }
+    }
+}
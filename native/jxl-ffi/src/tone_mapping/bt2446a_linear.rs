@@ -42,6 +42,13 @@ pub fn bt2446a_map(params: &common::Bt2446aParams, y: f32) -> f32 {
     common::bt2446a_knee(params, y_prime).powf(2.4)
 }
 
+/// Inverse of [`bt2446a_map`] for linear SDR luminance values.
+#[inline]
+pub fn bt2446a_unmap(params: &common::Bt2446aParams, y: f32) -> f32 {
+    let y_prime = y.powf(1.0 / 2.4);
+    common::bt2446a_knee_inverse(params, y_prime).powf(2.4)
+}
+
 /// BT.2446a-linear tone mapping on interleaved RGB data.
 ///
 /// Computes linear luminance, applies `bt2446a_map`, scales all channels
@@ -74,3 +81,42 @@ pub fn tone_map_bt2446a_linear(
         data[base + 2] = b * ratio;
     }
 }
+
+/// BT.2446a-linear tone mapping on single-channel (grayscale) data.
+///
+/// `data` is `[Y, Y, …]` in linear light, where 1.0 = source peak luminance.
+/// For achromatic input this is identical to `tone_map_bt2446a_gray`.
+pub fn tone_map_bt2446a_linear_gray(params: &common::Bt2446aParams, data: &mut [f32]) {
+    for y in data.iter_mut() {
+        if *y > 0.0 {
+            *y = bt2446a_map(params, *y);
+        }
+    }
+}
+
+/// Inverse BT.2446a-linear tone mapping (SDR→HDR) on interleaved RGB data.
+///
+/// Computes linear luminance, applies `bt2446a_unmap`, scales all channels
+/// by the ratio, undoing `tone_map_bt2446a_linear` with the same `params`.
+///
+/// `data` is interleaved `[R, G, B, R, G, B, …]` in linear light, where
+/// 1.0 = SDR peak luminance on input and HDR peak luminance on output.
+pub fn inverse_tone_map_bt2446a_linear(
+    params: &common::Bt2446aParams,
+    luminances: [f32; 3],
+    data: &mut [f32],
+) {
+    let [lr, lg, lb] = luminances;
+
+    for pixel in data.chunks_exact_mut(3) {
+        let y_lin = lr * pixel[0] + lg * pixel[1] + lb * pixel[2];
+        if y_lin <= 0.0 {
+            continue;
+        }
+
+        let ratio = bt2446a_unmap(params, y_lin) / y_lin;
+        for sample in pixel.iter_mut() {
+            *sample *= ratio;
+        }
+    }
+}
@@ -13,7 +13,8 @@
 //! HDR content, at the cost of additional computation (two matrix
 //! multiplies + PQ encode/decode per pixel).
 //!
-//! Requires BT.2020 primaries (validated at pipeline construction time).
+//! Requires BT.2020 primaries. `ToneMappingLcms2Cms` checks the input
+//! profile and falls back to `Bt2446aLinear` for other primaries.
 //! The tone mapping stage runs before CMS conversion, so input is in the
 //! image's native primaries.
 
@@ -132,3 +133,29 @@ pub fn tone_map_bt2446a_perceptual(
         data[base + 2] = rgb_out[2];
     }
 }
+
+/// BT.2446a-perceptual tone mapping on single-channel (grayscale) data.
+///
+/// Achromatic pixels have zero P and T, and I equals the PQ-encoded
+/// luminance, so the knee curve is applied to PQ(Y) directly.
+///
+/// `data` is `[Y, Y, …]` in linear light, where 1.0 = source peak luminance.
+pub fn tone_map_bt2446a_perceptual_gray(
+    params: &super::common::Bt2446aParams,
+    source_it: f32,
+    data: &mut [f32],
+) {
+    use super::common::bt2446a_knee;
+    use jxl::color::tf;
+
+    for y in data.iter_mut() {
+        if *y <= 0.0 {
+            continue;
+        }
+        let mut v = [*y];
+        tf::linear_to_pq(source_it, &mut v);
+        v[0] = bt2446a_knee(params, v[0]);
+        tf::pq_to_linear(source_it, &mut v);
+        *y = v[0];
+    }
+}
@@ -50,3 +50,29 @@ pub fn bt2446a_knee(params: &Bt2446aParams, y_prime: f32) -> f32 {
     // Inverse logarithmic expansion
     (params.rho_sdr.powf(x) - 1.0) / (params.rho_sdr - 1.0)
 }
+
+/// Inverse of [`bt2446a_knee`]: maps a gamma-encoded SDR value back to the
+/// gamma-encoded HDR value that the knee curve would map to it.
+///
+/// Each step of the forward curve is strictly increasing, so it is inverted
+/// piecewise: log-compress with ρ_SDR → inverse knee → inverse-log with ρ_HDR.
+#[inline]
+pub fn bt2446a_knee_inverse(params: &Bt2446aParams, y_prime: f32) -> f32 {
+    // Undo the inverse logarithmic expansion → knee output
+    let y = (1.0 + (params.rho_sdr - 1.0) * y_prime).ln() / params.rho_sdr.ln();
+
+    // Invert each piece of the knee; the bounds are the knee outputs at
+    // x = 0.7399 and x = 0.9909. The quadratic is increasing over its piece,
+    // so the smaller root is the one in range.
+    let x = if y <= 1.0770 * 0.7399 {
+        y / 1.0770
+    } else if y < 0.5 * 0.9909 + 0.5 {
+        let c = 0.6302 + y;
+        (2.7811 - (2.7811 * 2.7811 - 4.0 * 1.1510 * c).max(0.0).sqrt()) / (2.0 * 1.1510)
+    } else {
+        2.0 * y - 1.0
+    };
+
+    // Undo the logarithmic HDR compression
+    (params.ln_rho_hdr * x).exp_m1() / (params.rho_hdr - 1.0)
+}
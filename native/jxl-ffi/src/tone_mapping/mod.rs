@@ -11,16 +11,21 @@ pub mod bt2446a_perceptual;
 pub mod common;
 pub mod rec2408;
 
-pub use bt2446a::tone_map_bt2446a;
-pub use bt2446a_linear::tone_map_bt2446a_linear;
-pub use bt2446a_perceptual::tone_map_bt2446a_perceptual;
+pub use bt2446a::{tone_map_bt2446a, tone_map_bt2446a_gray};
+pub use bt2446a_linear::{
+    inverse_tone_map_bt2446a_linear, tone_map_bt2446a_linear, tone_map_bt2446a_linear_gray,
+};
+pub use bt2446a_perceptual::{tone_map_bt2446a_perceptual, tone_map_bt2446a_perceptual_gray};
 pub use common::Bt2446aParams;
-pub use rec2408::{Rec2408Params, tone_map_rec2408};
+pub use rec2408::{Rec2408Params, tone_map_rec2408, tone_map_rec2408_gray};
 
 /// Standard SDR reference white per ITU-R BT.2408 (cd/m² / nits).
 pub const DEFAULT_SDR_INTENSITY_TARGET: f32 = 203.0;
 
 /// Tone mapping algorithm.
+///
+/// The default is `Bt2446aPerceptual`, or the cheaper `Bt2446aLinear` when
+/// built with the `tone-map-default-linear` feature.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 #[allow(dead_code)]
 pub enum ToneMapMethod {
@@ -29,10 +34,11 @@ pub enum ToneMapMethod {
     Bt2446a,
     /// BT.2446a curve applied to linear RGB luminance. Fast approximation —
     /// same curve but luminance is computed in linear domain instead of Y'CbCr'.
+    #[cfg_attr(feature = "tone-map-default-linear", default)]
     Bt2446aLinear,
     /// BT.2446a curve in IPTPQc4 perceptual space (libplacebo-style).
     /// Best color preservation for saturated HDR content.
-    #[default]
+    #[cfg_attr(not(feature = "tone-map-default-linear"), default)]
     Bt2446aPerceptual,
     /// Rec. 2408 / BT.2390-style tone mapping matching libjxl's Rec2408ToneMapperBase.
     /// Operates in PQ domain with Hermite spline knee, followed by gamut mapping.
@@ -60,5 +66,113 @@ impl ToneMapMethod {
     }
 }
 
+/// A tone mapping method with its parameters precomputed for one source and
+/// target luminance, applied to interleaved RGB or single-channel data.
+#[derive(Debug, Clone, Copy)]
+pub enum ToneMapper {
+    Bt2446a {
+        params: Bt2446aParams,
+        luminances: [f32; 3],
+    },
+    Bt2446aLinear {
+        params: Bt2446aParams,
+        luminances: [f32; 3],
+    },
+    Bt2446aPerceptual {
+        params: Bt2446aParams,
+        source_intensity_target: f32,
+    },
+    Rec2408 {
+        params: Rec2408Params,
+        luminances: [f32; 3],
+    },
+}
+
+impl ToneMapper {
+    /// Prepares `method` for data with 1.0 = `source_intensity_target` nits.
+    /// `luminances` are the RGB luminance coefficients of the data's
+    /// primaries (ignored by `Bt2446aPerceptual`).
+    pub fn new(
+        method: ToneMapMethod,
+        luminances: [f32; 3],
+        source_intensity_target: f32,
+        desired_intensity_target: f32,
+    ) -> Self {
+        let bt2446a = || Bt2446aParams::new(source_intensity_target, desired_intensity_target);
+        match method {
+            ToneMapMethod::Bt2446a => Self::Bt2446a {
+                params: bt2446a(),
+                luminances,
+            },
+            ToneMapMethod::Bt2446aLinear => Self::Bt2446aLinear {
+                params: bt2446a(),
+                luminances,
+            },
+            ToneMapMethod::Bt2446aPerceptual => Self::Bt2446aPerceptual {
+                params: bt2446a(),
+                source_intensity_target,
+            },
+            ToneMapMethod::Rec2408 => Self::Rec2408 {
+                params: Rec2408Params::new(
+                    [0.0, source_intensity_target],
+                    [0.0, desired_intensity_target],
+                ),
+                luminances,
+            },
+            ToneMapMethod::CmsOnly => unreachable!("CmsOnly uses plain Lcms2Cms"),
+        }
+    }
+
+    /// Tone maps interleaved linear RGB `data` in place.
+    pub fn tone_map_rgb(&self, data: &mut [f32]) {
+        match *self {
+            Self::Bt2446a { params, luminances } => tone_map_bt2446a(&params, luminances, data),
+            Self::Bt2446aLinear { params, luminances } => {
+                tone_map_bt2446a_linear(&params, luminances, data)
+            }
+            Self::Bt2446aPerceptual {
+                params,
+                source_intensity_target,
+            } => tone_map_bt2446a_perceptual(&params, source_intensity_target, data),
+            Self::Rec2408 { params, luminances } => tone_map_rec2408(&params, luminances, data),
+        }
+    }
+
+    /// Tone maps single-channel (grayscale) linear `data` in place, where
+    /// each value is a luminance.
+    pub fn tone_map_gray(&self, data: &mut [f32]) {
+        match *self {
+            Self::Bt2446a { params, .. } => tone_map_bt2446a_gray(&params, data),
+            Self::Bt2446aLinear { params, .. } => tone_map_bt2446a_linear_gray(&params, data),
+            Self::Bt2446aPerceptual {
+                params,
+                source_intensity_target,
+            } => tone_map_bt2446a_perceptual_gray(&params, source_intensity_target, data),
+            Self::Rec2408 { params, .. } => tone_map_rec2408_gray(&params, data),
+        }
+    }
+}
+
+/// Tone maps interleaved linear RGB `data` in place with `method`.
+///
+/// `data` uses 1.0 = `source_intensity_target` nits. `luminances` are the
+/// RGB luminance coefficients of the data's primaries (ignored by
+/// `Bt2446aPerceptual`).
+pub fn tone_map_rgb(
+    method: ToneMapMethod,
+    luminances: [f32; 3],
+    source_intensity_target: f32,
+    desired_intensity_target: f32,
+    data: &mut [f32],
+) {
+    ToneMapper::new(
+        method,
+        luminances,
+        source_intensity_target,
+        desired_intensity_target,
+    )
+    .tone_map_rgb(data);
+}
+
 #[cfg(test)]
 mod test;
@@ -16,6 +16,9 @@
 /// libjxl's default `preserve_saturation` for the render pipeline path.
 pub const PRESERVE_SATURATION: f32 = 0.1;
 
+/// Luminance (nits) below which a pixel is treated as black.
+const MIN_LUMINANCE: f32 = 1e-6;
+
 /// Precomputed Rec. 2408 / BT.2390 parameters for PQ-domain tone mapping.
 ///
 /// Matches libjxl's `Rec2408ToneMapperBase` (see also `Rec2408ToneMapper` in `api/color.rs`).
@@ -60,6 +63,28 @@ impl Rec2408Params {
         }
     }
 
+    /// Maps an absolute luminance (nits) through the PQ-domain knee.
+    /// The result is clamped to `[0, target_peak]`.
+    #[inline]
+    pub fn map_luminance(&self, luminance: f32) -> f32 {
+        let normalized_pq = ((pq_encode_nits(luminance) - self.pq_mastering_min)
+            * self.inv_pq_mastering_range)
+            .min(1.0);
+
+        let e2 = if normalized_pq < self.ks {
+            normalized_pq
+        } else {
+            self.hermite_spline(normalized_pq)
+        };
+
+        let one_minus_e2 = 1.0 - e2;
+        let one_minus_e2_2 = one_minus_e2 * one_minus_e2;
+        let e3 = self.min_lum * (one_minus_e2_2 * one_minus_e2_2) + e2;
+
+        let e4 = e3 * self.pq_mastering_range + self.pq_mastering_min;
+        pq_decode_nits(e4).clamp(0.0, self.target_peak)
+    }
+
     /// Hermite spline knee curve (BT.2390 §5.4).
     #[inline]
     pub fn hermite_spline(&self, b: f32) -> f32 {
@@ -153,25 +178,8 @@ pub fn tone_map_rec2408(params: &Rec2408Params, luminances: [f32; 3], data: &mut
         let b = data[base + 2];
 
         let luminance = params.source_peak * (lr * r + lg * g + lb * b);
+        let new_luminance = params.map_luminance(luminance);
 
-        let normalized_pq = ((pq_encode_nits(luminance) - params.pq_mastering_min)
-            * params.inv_pq_mastering_range)
-            .min(1.0);
-
-        let e2 = if normalized_pq < params.ks {
-            normalized_pq
-        } else {
-            params.hermite_spline(normalized_pq)
-        };
-
-        let one_minus_e2 = 1.0 - e2;
-        let one_minus_e2_2 = one_minus_e2 * one_minus_e2;
-        let e3 = params.min_lum * (one_minus_e2_2 * one_minus_e2_2) + e2;
-
-        let e4 = e3 * params.pq_mastering_range + params.pq_mastering_min;
-        let new_luminance = pq_decode_nits(e4).clamp(0.0, params.target_peak);
-
-        const MIN_LUMINANCE: f32 = 1e-6;
         if luminance <= MIN_LUMINANCE {
             let cap = new_luminance * params.inv_target_peak;
             data[base] = cap;
@@ -191,3 +199,16 @@ pub fn tone_map_rec2408(params: &Rec2408Params, luminances: [f32; 3], data: &mut
         data[base + 2] = gb;
     }
 }
+
+/// Rec. 2408 / BT.2390 tone mapping on single-channel (grayscale) data.
+///
+/// Gamut mapping is a no-op for achromatic pixels, so only the knee is applied.
+/// Output is re-normalized so 1.0 = target peak.
+///
+/// `data` is `[Y, Y, …]` in linear light, where 1.0 = source peak luminance.
+pub fn tone_map_rec2408_gray(params: &Rec2408Params, data: &mut [f32]) {
+    for y in data.iter_mut() {
+        let luminance = params.source_peak * *y;
+        *y = params.map_luminance(luminance) * params.inv_target_peak;
+    }
+}
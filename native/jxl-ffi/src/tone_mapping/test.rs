@@ -4,9 +4,9 @@
 // license that can be found in the LICENSE file.
 
 use super::*;
-use super::bt2446a_linear::bt2446a_map;
+use super::bt2446a_linear::{bt2446a_map, bt2446a_unmap};
 use super::bt2446a_perceptual::{IPT_TO_LMS_PQ, LMS_PQ_TO_IPT, LMS_TO_RGB, RGB_TO_LMS, mat_mul};
-use super::common::bt2446a_knee;
+use super::common::{bt2446a_knee, bt2446a_knee_inverse};
 use super::rec2408::{pq_decode_nits, pq_encode_nits};
 
 const LUMINANCE_BT2020: [f32; 3] = [0.2627, 0.678, 0.0593];
@@ -147,6 +147,56 @@ fn bt2446a_linear_compression_reduces_absolute_nits() {
     );
 }
 
+#[test]
+fn bt2446a_knee_inverse_round_trip() {
+    let params = Bt2446aParams::new(4000.0, 203.0);
+
+    // Dense sweep so every piece of the knee, and both boundaries, is hit
+    for i in 0..=1000 {
+        let y_prime = i as f32 / 1000.0;
+        let round_trip = bt2446a_knee_inverse(&params, bt2446a_knee(&params, y_prime));
+        assert!(
+            (round_trip - y_prime).abs() < 1e-4,
+            "knee inverse should round-trip {y_prime}, got {round_trip}"
+        );
+    }
+}
+
+#[test]
+fn bt2446a_linear_inverse_round_trip() {
+    let params = Bt2446aParams::new(10000.0, 203.0);
+    let test_colors: &[[f32; 3]] = &[
+        [0.0, 0.0, 0.0],
+        [0.001, 0.001, 0.001],
+        [0.02, 0.01, 0.005],
+        [0.3, 0.5, 0.1],
+        [1.0, 0.2, 0.05],
+        [1.0, 1.0, 1.0],
+    ];
+
+    for &original in test_colors {
+        let mut pixel = original;
+        tone_map_bt2446a_linear(&params, LUMINANCE_BT2020, &mut pixel);
+        inverse_tone_map_bt2446a_linear(&params, LUMINANCE_BT2020, &mut pixel);
+
+        for c in 0..3 {
+            assert!(
+                (pixel[c] - original[c]).abs() <= 1e-3 * original[c].max(1e-3),
+                "forward+inverse should return {original:?}, got {pixel:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn bt2446a_unmap_expands_sdr() {
+    let params = Bt2446aParams::new(1000.0, 203.0);
+    // SDR peak maps back to the HDR peak, mid-tones are darkened relative
+    // to it since the forward curve brightened them
+    assert!((bt2446a_unmap(&params, 1.0) - 1.0).abs() < 1e-4);
+    assert!(bt2446a_unmap(&params, 0.5) < 0.5);
+}
+
 // ============================================================================
 // Bt2446a (Y'CbCr') tests
 // ============================================================================
@@ -596,3 +646,95 @@ fn lms_pq_to_ipt_inverse_roundtrip() {
         );
     }
 }
+
+// ============================================================================
+// tone_map_rgb dispatch tests
+// ============================================================================
+
+#[test]
+fn tone_map_rgb_matches_direct_calls() {
+    let input = [0.8f32, 0.5, 0.2, 0.01, 0.02, 0.03];
+    let params = Bt2446aParams::new(4000.0, 203.0);
+
+    let mut expected = input;
+    tone_map_bt2446a(&params, LUMINANCE_BT2020, &mut expected);
+    let mut actual = input;
+    tone_map_rgb(
+        ToneMapMethod::Bt2446a,
+        LUMINANCE_BT2020,
+        4000.0,
+        203.0,
+        &mut actual,
+    );
+    assert_eq!(actual, expected);
+
+    let mut expected = input;
+    tone_map_rec2408(
+        &Rec2408Params::new([0.0, 4000.0], [0.0, 203.0]),
+        LUMINANCE_BT2020,
+        &mut expected,
+    );
+    let mut actual = input;
+    tone_map_rgb(
+        ToneMapMethod::Rec2408,
+        LUMINANCE_BT2020,
+        4000.0,
+        203.0,
+        &mut actual,
+    );
+    assert_eq!(actual, expected);
+}
+
+// ============================================================================
+// Grayscale tests
+// ============================================================================
+
+#[test]
+fn gray_matches_achromatic_rgb_for_all_methods() {
+    let methods = [
+        ToneMapMethod::Bt2446a,
+        ToneMapMethod::Bt2446aLinear,
+        ToneMapMethod::Bt2446aPerceptual,
+        ToneMapMethod::Rec2408,
+    ];
+    let values = [0.0f32, 0.001, 0.05, 0.2, 0.5, 0.8, 1.0];
+
+    for method in methods {
+        let mut gray = values;
+        ToneMapper::new(method, LUMINANCE_BT2020, 4000.0, 203.0).tone_map_gray(&mut gray);
+
+        for (&v, &g) in values.iter().zip(&gray) {
+            let mut rgb = [v, v, v];
+            tone_map_rgb(method, LUMINANCE_BT2020, 4000.0, 203.0, &mut rgb);
+            assert!(
+                (rgb[1] - g).abs() < 1e-3,
+                "{method:?} at {v}: gray {g} vs rgb {rgb:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn gray_processes_every_sample() {
+    // A 3-channel operator would only map len / 3 samples.
+    let mut gray = [1.0f32; 4];
+    ToneMapper::new(ToneMapMethod::Rec2408, LUMINANCE_BT2020, 4000.0, 203.0)
+        .tone_map_gray(&mut gray);
+    for g in gray {
+        assert!((g - 1.0).abs() < 0.02, "peak should map to ~1.0, got {g}");
+    }
+
+    let mut gray = [0.5f32; 4];
+    tone_map_bt2446a_gray(&Bt2446aParams::new(4000.0, 203.0), &mut gray);
+    assert!(gray.iter().all(|&g| g == gray[0] && g != 0.5));
+}
+
+#[test]
+fn default_method_follows_build_feature() {
+    let expected = if cfg!(feature = "tone-map-default-linear") {
+        ToneMapMethod::Bt2446aLinear
+    } else {
+        ToneMapMethod::Bt2446aPerceptual
+    };
+    assert_eq!(ToneMapMethod::default(), expected);
+}
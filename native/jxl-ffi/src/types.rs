@@ -29,6 +29,13 @@ pub enum JxlStatus {
     InvalidState = 5,
     /// An I/O error occurred (e.g., file not found).
     IoError = 6,
+    /// Decoding would exceed the `MemoryLimitBytes` option.
+    MemoryLimitExceeded = 7,
+    /// The color profile is an ICC profile with no simple encoding.
+    IccProfile = 8,
+    /// The decoder is already in use by a call on another thread. A decoder
+    /// must only be used from one thread at a time.
+    Busy = 9,
 }
 
 /// Pixel data format.
@@ -57,7 +64,8 @@ pub enum JxlColorType {
     GrayscaleAlpha = 2,
     /// Red, green, blue.
     Rgb = 3,
-    /// Blue, green, red (Windows bitmap order).
+    /// Blue, green, red (Windows bitmap order). Swizzled by the upstream decoder
+    /// while writing rows, so no extra pass over the output is needed.
     Bgr = 4,
     /// Blue, green, red, alpha.
     Bgra = 5,
@@ -186,7 +194,8 @@ pub struct JxlBasicInfoRaw {
     pub Width: u32,
     /// Image height in pixels.
     pub Height: u32,
-    /// Bit depth specification.
+    /// Bit depth of the source image, independent of the output pixel format.
+    /// See `jxl_decoder_get_output_bit_depth` for the precision of decoded output.
     pub BitDepth: JxlBitDepth,
     /// Number of extra channels (alpha, depth, etc.).
     pub NumExtraChannels: u32,
@@ -198,7 +207,8 @@ pub struct JxlBasicInfoRaw {
     pub Preview_Height: u32,
     /// Tone mapping parameters for HDR content.
     pub ToneMapping: JxlToneMapping,
-    /// Image orientation.
+    /// Orientation still to be applied to the output: the header orientation
+    /// with `AdjustOrientation` off, otherwise `Identity`.
     pub Orientation: JxlOrientation,
     /// Whether alpha is premultiplied.
     pub AlphaPremultiplied: bool,
@@ -233,9 +243,10 @@ pub enum JxlExtraChannelType {
 }
 
 /// Information about an extra channel.
-/// Note: jxl-rs API only exposes channel type and alpha_associated.
-/// Other fields like bits_per_sample, name, spot_color are in the lower-level
-/// ExtraChannelInfo but not exposed through the public API.
+/// Note: jxl-rs API only exposes channel type and alpha_associated; `DimShift`
+/// is read from the image header. Other fields like bits_per_sample, name,
+/// spot_color are in the lower-level ExtraChannelInfo but not exposed through
+/// the public API.
 #[repr(C)]
 #[derive(Debug, Clone)]
 #[allow(non_snake_case)]
@@ -244,16 +255,22 @@ pub struct JxlExtraChannelInfo {
     pub ChannelType: JxlExtraChannelType,
     /// Whether alpha is associated/premultiplied (only for alpha channels).
     pub AlphaAssociated: bool,
+    /// Log2 of the channel's downsampling factor (0-3): the channel is stored
+    /// at `ceil(width / 2^DimShift)` x `ceil(height / 2^DimShift)`, e.g. a
+    /// half-resolution depth map has 1. The decoder upsamples it to the image
+    /// size, so extra channel buffers are always full size.
+    pub DimShift: u32,
 }
 
 /// Frame header information.
 /// Note: jxl-rs API exposes name, duration, and size.
 /// is_last is in the lower-level FrameHeader but not exposed through the API.
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 #[allow(non_snake_case)]
 pub struct JxlFrameHeader {
-    /// Frame duration in milliseconds (for animation).
+    /// Frame duration in milliseconds (for animation). Always 0 for the
+    /// preview.
     pub DurationMs: f32,
     /// Frame width in pixels.
     pub FrameWidth: u32,
@@ -261,6 +278,44 @@ pub struct JxlFrameHeader {
     pub FrameHeight: u32,
     /// Frame name length in bytes. Use jxl_decoder_get_frame_name to get the actual name.
     pub NameLength: u32,
+    /// X origin of the decoded frame on the image canvas, in pixels.
+    /// jxl-rs extends every frame to the image dimensions (even with coalescing
+    /// disabled), so the decoded buffer always starts at the canvas origin.
+    pub FrameX0: i32,
+    /// Y origin of the decoded frame on the image canvas, in pixels.
+    /// See `FrameX0`.
+    pub FrameY0: i32,
+    /// Whether this frame is the preview image (see `PreferPreview`).
+    /// The frame size is then the preview size.
+    pub IsPreview: bool,
+    /// Whether every pixel of the frame is fully opaque. At
+    /// `HaveFrameHeader` this comes from the metadata: it is exact when the
+    /// image has no alpha channel and conservatively `false` otherwise. Once
+    /// the frame's pixels are read with `DetectTransparency` enabled and
+    /// alpha in the output format, it reflects a scan of the decoded alpha
+    /// and is exact.
+    pub IsOpaque: bool,
+}
+
+/// Frame header with fields beyond `JxlFrameHeader`, for compositors and
+/// other advanced callers. Get it with `jxl_decoder_get_frame_header_ex`.
+///
+/// jxl-rs decodes DC frames, reference-only frames and other frames that are
+/// never displayed internally, so every header describes a displayed frame
+/// or, with `Coalescing` disabled, a layer. Blend and reference slot details
+/// are not exposed by jxl-rs.
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+#[allow(non_snake_case)]
+pub struct JxlFrameHeaderEx {
+    /// The fields reported by `jxl_decoder_get_frame_header`.
+    pub Header: JxlFrameHeader,
+    /// Frame duration in animation ticks, as returned by
+    /// `jxl_decoder_get_frame_duration_ticks`.
+    pub DurationTicks: u32,
+    /// Zero-based index of the frame in the image, or of the layer when
+    /// `Coalescing` is disabled. The preview is not counted and reports 0.
+    pub FrameIndex: u32,
 }
 
 impl Default for JxlBasicInfoRaw {
@@ -337,6 +392,29 @@ impl Default for JxlMetadataCaptureOptions {
     }
 }
 
+/// A box found by `jxl_jumbf_parse` in a JUMBF structure.
+/// Fields are ordered by size (largest first) to minimize padding.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(non_snake_case)]
+pub struct JxlJumbfEntry {
+    /// Offset of the box payload (after its header) in the parsed data.
+    pub Offset: u64,
+    /// Length of the box payload in bytes.
+    pub Length: u64,
+    /// Offset of the UTF-8 label in the parsed data. Only meaningful when
+    /// `LabelLength` is non-zero.
+    pub LabelOffset: u64,
+    /// Length of the label in bytes, excluding its null terminator; 0 if
+    /// the box is unlabeled. Set on `jumb` superboxes and their `jumd`
+    /// description boxes.
+    pub LabelLength: u32,
+    /// Superbox nesting level, 0 for boxes at the top level of the data.
+    pub Depth: u32,
+    /// Four-character box type, e.g. `jumb`, `jumd` or `json`.
+    pub Type: [u8; 4],
+}
+
 /// Decoder options.
 /// All options should be set before decoding begins.
 /// Fields are ordered by size (largest first) to minimize padding.
@@ -347,9 +425,33 @@ pub struct JxlDecodeOptions {
     /// Maximum number of pixels to decode.
     /// 0 = no limit.
     pub PixelLimit: usize,
+    /// Maximum estimated bytes to allocate while decoding a frame: the output
+    /// buffers plus the decoder's internal working planes. Images over the
+    /// limit are rejected with `MemoryLimitExceeded` before any frame is
+    /// decoded.
+    /// 0 = no limit.
+    pub MemoryLimitBytes: usize,
     /// Progressive decoding mode.
     pub ProgressiveMode: JxlProgressiveMode,
-    /// Whether to adjust image orientation based on EXIF data.
+    /// Source peak luminance (nits) assumed by tone mapping.
+    /// When > 0, replaces the image's `intensity_target`, e.g. for content
+    /// with missing or incorrect mastering metadata.
+    /// 0 = use the value from the image header.
+    pub SourceIntensityTargetOverride: f32,
+    /// Red luminance coefficient used by tone mapping.
+    /// When `ToneMapLuminanceR`, `ToneMapLuminanceG` and `ToneMapLuminanceB`
+    /// are all 0, coefficients are derived from the image's primaries, which
+    /// falls back to BT.2020 for ICC profiles. Set them for ICC-profiled
+    /// content with other primaries (e.g. P3: 0.2290, 0.6917, 0.0793).
+    pub ToneMapLuminanceR: f32,
+    /// Green luminance coefficient used by tone mapping. See `ToneMapLuminanceR`.
+    pub ToneMapLuminanceG: f32,
+    /// Blue luminance coefficient used by tone mapping. See `ToneMapLuminanceR`.
+    pub ToneMapLuminanceB: f32,
+    /// Whether to apply the header orientation, outputting upright pixels.
+    /// When false, pixels keep their stored layout and size, and
+    /// `JxlBasicInfoRaw.Orientation` tells the caller how to display them,
+    /// e.g. as a texture transform.
     pub AdjustOrientation: bool,
     /// Whether to render spot colors.
     pub RenderSpotColors: bool,
@@ -357,14 +459,101 @@ pub struct JxlDecodeOptions {
     pub Coalescing: bool,
     /// Whether to skip the preview image.
     pub SkipPreview: bool,
+    /// Whether to output the preview image before the main image, overriding
+    /// `SkipPreview`. When the image has a preview, the first frame is the
+    /// preview: its `HaveFrameHeader` reports `IsPreview`, and until it has
+    /// been read or skipped the buffer sizes are for `Preview_Width` x
+    /// `Preview_Height` rather than the image size. The image frames follow.
+    pub PreferPreview: bool,
     /// Whether to use high precision mode for decoding.
+    /// When false, the render pipeline may use faster, lower-precision
+    /// approximations (e.g. for XYB→RGB and transfer functions).
+    /// Differences are typically at most 1-2 code values in 8-bit output,
+    /// so enable this only for float/16-bit output or reference comparisons.
     pub HighPrecision: bool,
     /// Whether to premultiply alpha in the output.
     pub PremultiplyAlpha: bool,
+    /// Whether to convert associated (premultiplied) alpha to straight alpha
+    /// in the output, dividing each color sample by alpha. Only has an effect
+    /// when the image's alpha is associated and `PremultiplyAlpha` is off;
+    /// pixels with zero alpha are left unchanged.
+    pub UnpremultiplyAlpha: bool,
+    /// Whether to replace non-finite samples in float output: NaN becomes 0
+    /// and ±infinity the largest finite value of the same sign. Applies to
+    /// `Float32` and `Float16` color output and, with a tone-mapping
+    /// `CmsType`, to the tone-mapped values before the final conversion.
+    /// Malformed or extreme HDR content can otherwise yield samples that
+    /// break GPU uploads.
+    pub SanitizeFloatOutput: bool,
+    /// Whether integer output is scaled to the full range of the data format
+    /// when the image has fewer bits per sample, e.g. a 10-bit image decoded
+    /// to `Uint16` spans 0-65535. Otherwise samples keep the image's
+    /// magnitude, 0-1023 in that example, and are never scaled beyond the
+    /// format's width. Float images are always scaled.
+    pub ScaleToFullRange: bool,
     /// Whether to decode extra channels into separate buffers.
     pub DecodeExtraChannels: bool,
+    /// Whether the first alpha channel is also written to its own extra
+    /// channel buffer when the color type has alpha. By default it is only
+    /// part of the color output and its extra buffer is left untouched.
+    pub SeparateFirstAlpha: bool,
+    /// Whether to count pixels clipped at the target peak by tone mapping.
+    /// Only has an effect with a tone-mapping `CmsType`. Read the counts with
+    /// `jxl_decoder_get_tone_map_clip_stats`.
+    pub ToneMapClipStats: bool,
+    /// Whether to record luminance statistics of each decoded frame. Read
+    /// them with `jxl_decoder_compute_histogram` and
+    /// `jxl_decoder_get_luminance_stats`.
+    pub LuminanceStats: bool,
+    /// Whether to record the bounding box of opaque content in each decoded
+    /// frame, for cropping transparent borders. Read it with
+    /// `jxl_decoder_get_opaque_bounds`.
+    pub OpaqueBounds: bool,
+    /// Alpha above which a pixel counts as opaque for `OpaqueBounds`, from 0
+    /// to 1. The default 0 counts every pixel that isn't fully transparent.
+    pub OpaqueAlphaThreshold: f32,
+    /// Whether to scan each decoded frame for alpha below the maximum, so
+    /// `jxl_decoder_has_transparency` can tell an image whose alpha channel
+    /// is fully opaque from one with visible transparency.
+    pub DetectTransparency: bool,
+    /// Whether a frame that fails to decode is skipped instead of ending the
+    /// decode. Pixel reads then return `FrameError` and decoding continues
+    /// with the next frame. Skipping re-reads the image through the failed
+    /// frame without output buffers, so it only succeeds when the failure was
+    /// in producing output; a frame whose bitstream is corrupt can't be
+    /// skipped, and `Error` is returned as without this option.
+    pub ContinueOnFrameError: bool,
+    /// Whether a CMS that fails to set up a color conversion is bypassed
+    /// instead of ending the decode, so viewers can still show the image.
+    /// Pixels are then output unconverted, in the image's own color encoding,
+    /// and `jxl_decoder_cms_fell_back` reports the CMS error. Only gray to
+    /// gray and RGB to RGB conversions can be bypassed.
+    pub CmsErrorFallback: bool,
+    /// Whether colors outside the output gamut are smoothly compressed into
+    /// it instead of clipped, e.g. when converting Rec. 2020 content to sRGB.
+    /// Hue is kept and colors well inside the gamut are unchanged; only the
+    /// most saturated in-gamut colors shift slightly to make room. Only has
+    /// an effect with the `Lcms2` `CmsType` and RGB output.
+    pub GamutCompression: bool,
+    /// Whether to stop after the image header, for indexing and other
+    /// metadata-only uses. `jxl_decoder_process` returns `Complete` right
+    /// after `HaveBasicInfo` and never decodes a frame, so no pixel buffer is
+    /// needed and `MemoryLimitBytes` is not checked. Basic info, color
+    /// profiles and metadata boxes before the codestream remain available.
+    pub MetadataOnly: bool,
+    /// Maximum number of frames to decode, 0 = no limit. Once that many
+    /// frames have been read, a decoded preview included,
+    /// `jxl_decoder_process` returns `Complete` and the rest of the image is
+    /// ignored. Bounds the work on untrusted animations with huge numbers of
+    /// small frames, which `PixelLimit` doesn't catch.
+    pub MaxFrames: u32,
     /// Desired output pixel format.
     pub PixelFormat: JxlPixelFormat,
+    /// Pads each color output row to a multiple of this many bytes, e.g. 16
+    /// or 32 for SIMD post-processing. 0 = rows are tightly packed.
+    /// `jxl_decoder_get_buffer_size` includes the padding; padding bytes are
+    /// left untouched. Extra channel buffers are always packed.
+    pub RowAlignment: u32,
     /// Options for capturing metadata boxes (EXIF, XML, JUMBF).
     pub MetadataCapture: JxlMetadataCaptureOptions,
     /// Color management system to use for color space conversions.
@@ -375,15 +564,36 @@ impl Default for JxlDecodeOptions {
     fn default() -> Self {
         Self {
             PixelLimit: 0,
+            MemoryLimitBytes: 0,
             ProgressiveMode: JxlProgressiveMode::Pass,
+            SourceIntensityTargetOverride: 0.0,
+            ToneMapLuminanceR: 0.0,
+            ToneMapLuminanceG: 0.0,
+            ToneMapLuminanceB: 0.0,
             AdjustOrientation: true,
             RenderSpotColors: true,
             Coalescing: true,
             SkipPreview: true,
+            PreferPreview: false,
             HighPrecision: false,
             PremultiplyAlpha: false,
+            UnpremultiplyAlpha: false,
+            SanitizeFloatOutput: false,
+            ScaleToFullRange: true,
             DecodeExtraChannels: false,
+            SeparateFirstAlpha: false,
+            ToneMapClipStats: false,
+            LuminanceStats: false,
+            OpaqueBounds: false,
+            OpaqueAlphaThreshold: 0.0,
+            DetectTransparency: false,
+            ContinueOnFrameError: false,
+            CmsErrorFallback: false,
+            GamutCompression: false,
+            MetadataOnly: false,
+            MaxFrames: 0,
             PixelFormat: JxlPixelFormat::default(),
+            RowAlignment: 0,
             MetadataCapture: JxlMetadataCaptureOptions::default(),
             CmsType: JxlCmsType::Lcms2,
         }
@@ -409,6 +619,81 @@ pub enum JxlDecoderEvent {
     FrameComplete = 5,
     /// All frames have been decoded. The decoder is finished.
     Complete = 6,
+    /// The current frame failed to decode and was skipped; only returned with
+    /// the `ContinueOnFrameError` option. Call `jxl_get_last_error` for
+    /// details, then `jxl_decoder_process` to continue with the next frame.
+    FrameError = 7,
+}
+
+/// Stage of the decoder's state machine, see `jxl_decoder_get_state`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JxlDecoderStateTag {
+    /// No basic info has been read yet; input and configuration can be set.
+    Initialized = 0,
+    /// The basic info is available, and the next frame header isn't read
+    /// yet. Also the state once the image is complete.
+    WithImageInfo = 1,
+    /// A frame header is available, and pixels can be read.
+    WithFrameInfo = 2,
+    /// A decoding call is running on the decoder.
+    Processing = 3,
+}
+
+/// Severity of a message passed to the callback set with
+/// `jxl_set_log_callback`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JxlLogLevel {
+    /// Decode progress, such as each frame completing.
+    Debug = 0,
+    /// Milestones, such as reading the basic info.
+    Info = 1,
+    /// Recoverable problems, such as a skipped frame or a CMS fallback.
+    Warning = 2,
+}
+
+/// Time spent in each decoding phase, accumulated since the decoder was
+/// created, reset, or rewound.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(non_snake_case)]
+pub struct JxlDecodeTimings {
+    /// Milliseconds spent parsing image and frame headers.
+    pub HeaderMs: f64,
+    /// Milliseconds spent decoding frame pixels. Includes `CmsMs`, since
+    /// color conversion runs inside the render pipeline.
+    pub FrameDecodeMs: f64,
+    /// Milliseconds spent in CMS transforms, including tone mapping.
+    pub CmsMs: f64,
+}
+
+/// Luminance range of a decoded frame, see `jxl_decoder_get_luminance_stats`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[allow(non_snake_case)]
+pub struct JxlLuminanceStats {
+    /// Lowest pixel luminance.
+    pub Min: f32,
+    /// Highest pixel luminance.
+    pub Max: f32,
+    /// Mean pixel luminance.
+    pub Mean: f32,
+}
+
+/// Rectangle in output pixel coordinates, see `jxl_decoder_get_opaque_bounds`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[allow(non_snake_case)]
+pub struct JxlRect {
+    /// Left edge.
+    pub X: u32,
+    /// Top edge.
+    pub Y: u32,
+    /// Width in pixels.
+    pub Width: u32,
+    /// Height in pixels.
+    pub Height: u32,
 }
 
 /// Signature check result.
@@ -436,6 +721,13 @@ pub struct JxlColorProfileHandle {
     _private: [u8; 0],
 }
 
+/// Opaque handle to a standalone color transform between two ICC profiles.
+/// Must be freed with `jxl_cms_transform_destroy`.
+#[repr(C)]
+pub struct JxlCmsTransformHandle {
+    _private: [u8; 0],
+}
+
 /// Rendering intent for color management.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -596,6 +888,24 @@ pub struct JxlColorProfileRaw {
     pub Encoding: JxlColorEncodingRaw,
 }
 
+/// Common output color spaces, see `jxl_decoder_set_output_named`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JxlNamedColorSpace {
+    /// sRGB (sRGB primaries and transfer function, D65).
+    Srgb = 0,
+    /// sRGB primaries with a linear transfer function, D65.
+    LinearSrgb = 1,
+    /// Display P3 (P3 primaries, sRGB transfer function, D65).
+    DisplayP3 = 2,
+    /// Rec. 2100 PQ (BT.2100 primaries, PQ transfer function, D65).
+    Rec2020Pq = 3,
+    /// Rec. 2100 HLG (BT.2100 primaries, HLG transfer function, D65).
+    Rec2020Hlg = 4,
+    /// Adobe RGB (1998) (Adobe primaries, gamma 563/256, D65).
+    AdobeRgb = 5,
+}
+
 impl Default for JxlWhitePointRaw {
     fn default() -> Self {
         Self {
@@ -655,6 +965,21 @@ impl Default for JxlColorProfileRaw {
 // CMS Types
 // ============================================================================
 
+/// Tone mapping algorithm for `jxl_tone_map_rgb_f32`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JxlToneMapMethod {
+    /// BT.2446a in Y'CbCr' domain per ITU-R BT.2446-1 Method A.
+    Bt2446a = 0,
+    /// BT.2446a curve applied to linear RGB luminance. Fast approximation.
+    Bt2446aLinear = 1,
+    /// BT.2446a curve in IPTPQc4 perceptual space. Ignores luminance coefficients.
+    Bt2446aPerceptual = 2,
+    /// Rec. 2408 / BT.2390 PQ-domain knee with gamut mapping (libjxl's default).
+    /// Output is re-normalized so 1.0 = target peak.
+    Rec2408 = 3,
+}
+
 /// Color Management System type.
 ///
 /// Specifies which CMS implementation to use for color space conversions
@@ -675,6 +1000,14 @@ pub enum JxlCmsType {
     /// Same curve as Bt2446a but computed in linear domain. ~40% faster.
     Bt2446aLinear = 3,
     /// BT.2446a tone mapping (IPTPQc4 perceptual space) with lcms2 color management.
-    /// Best color preservation for saturated HDR content.
+    /// Best color preservation for saturated HDR content. Requires BT.2020
+    /// primaries; other inputs (including ICC profiles) use `Bt2446aLinear`.
     Bt2446aPerceptual = 4,
+    /// Built-in matrix and transfer function conversion between simple RGB or
+    /// grayscale encodings. Needs no lcms2; ICC and CMYK profiles are rejected.
+    Builtin = 5,
+    /// HDR to SDR tone mapping with lcms2 color management, using the build's
+    /// default method: `Bt2446aPerceptual`, or `Bt2446aLinear` when built with
+    /// the `tone-map-default-linear` feature.
+    ToneMap = 6,
 }
@@ -158,9 +158,13 @@ fn wrap_in_container(codestream: &[u8]) -> Vec<u8> {
 }
 
 /// Create a minimal EXIF box with a TIFF header
-fn create_minimal_exif(content: &str) -> Vec<u8> {
-    // EXIF data starts with 4-byte TIFF offset (usually 0)
-    let mut data = vec![0u8; 4];
+///
+/// `tiff_offset` zero bytes are inserted between the offset prefix and the
+/// TIFF header, as some writers do.
+fn create_minimal_exif(content: &str, tiff_offset: u32) -> Vec<u8> {
+    // EXIF data starts with 4-byte big-endian TIFF offset (usually 0)
+    let mut data = tiff_offset.to_be_bytes().to_vec();
+    data.resize(4 + tiff_offset as usize, 0);
 
     // TIFF header (little-endian)
     data.extend_from_slice(b"II");   // Little-endian byte order
@@ -275,6 +279,7 @@ fn print_usage() {
     eprintln!("  --exif-file <path>  Add EXIF box from file (can repeat)");
     eprintln!("  --xml-file <path>   Add XML box from file (can repeat)");
     eprintln!("  --jumbf-file <path> Add JUMBF box from file (can repeat)");
+    eprintln!("  --exif-offset <n>   TIFF header offset for following --exif boxes (default 0)");
     eprintln!("  --brotli            Enable brotli compression (brob) for following metadata boxes");
     eprintln!("  --no-brotli         Disable brotli compression (default)");
     eprintln!();
@@ -282,6 +287,7 @@ fn print_usage() {
     eprintln!("  create-test-metadata input.jxl output.jxl --exif 'test1' --exif 'test2'");
     eprintln!("  create-test-metadata input.jxl output.jxl --brotli --exif 'compressed'");
     eprintln!("  create-test-metadata input.jxl output.jxl --exif 'plain' --brotli --exif 'compressed'");
+    eprintln!("  create-test-metadata input.jxl output.jxl --exif-offset 8 --exif 'offset'");
 }
 
 fn main() -> io::Result<()> {
@@ -301,6 +307,7 @@ fn main() -> io::Result<()> {
     let mut xml_boxes: Vec<(Vec<u8>, bool)> = Vec::new();
     let mut jumbf_boxes: Vec<(Vec<u8>, bool)> = Vec::new();
     let mut use_brotli = false;
+    let mut exif_offset = 0u32;
 
     let mut i = 3;
     while i < args.len() {
@@ -317,7 +324,17 @@ fn main() -> io::Result<()> {
                     eprintln!("Error: --exif requires a content argument");
                     std::process::exit(1);
                 }
-                exif_boxes.push((create_minimal_exif(&args[i]), use_brotli));
+                exif_boxes.push((create_minimal_exif(&args[i], exif_offset), use_brotli));
+            }
+            "--exif-offset" => {
+                i += 1;
+                exif_offset = match args.get(i).map(|n| n.parse()) {
+                    Some(Ok(n)) => n,
+                    _ => {
+                        eprintln!("Error: --exif-offset requires a non-negative integer");
+                        std::process::exit(1);
+                    }
+                };
             }
             "--xml" => {
                 i += 1;
@@ -289,11 +289,15 @@ public static class ExifDataParser
         if (data.Length < 12)
             return false;
 
-        // The first 4 bytes are the TIFF offset (usually 0)
-        tiffBase = 4;
+        // The first 4 bytes are the big-endian offset of the TIFF header from
+        // the end of the prefix (usually 0)
+        var tiffOffset = BinaryPrimitives.ReadUInt32BigEndian(data);
+        if (tiffOffset > (uint)(data.Length - 12))
+            return false;
+        tiffBase = 4 + (int)tiffOffset;
 
-        // Check for "Exif\0\0" header (some formats include this)
-        if (data.Length >= 10 && data[4] == 'E' && data[5] == 'x' && data[6] == 'i' && data[7] == 'f')
+        // Check for "Exif\0\0" header (some writers include it without counting it in the offset)
+        if (tiffOffset == 0 && data[4] == 'E' && data[5] == 'x' && data[6] == 'i' && data[7] == 'f')
         {
             tiffBase = 10; // Skip "Exif\0\0"
         }
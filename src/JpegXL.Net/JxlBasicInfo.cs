@@ -17,12 +17,16 @@ public class JxlBasicInfo
     public (nuint Width, nuint Height) Size { get; init; }
 
     /// <summary>
-    /// Bit depth of the image.
+    /// Bit depth of the source image, independent of the output pixel format.
     /// </summary>
+    /// <remarks>
+    /// Use <see cref="JxlDecoder.GetOutputBitDepth"/> for the precision of decoded output.
+    /// </remarks>
     public required JxlBitDepth BitDepth { get; init; }
 
     /// <summary>
-    /// Image orientation.
+    /// Orientation still to be applied to the decoded pixels: the header orientation when
+    /// <see cref="JxlDecodeOptions.AdjustOrientation"/> is false, otherwise <see cref="JxlOrientation.Identity"/>.
     /// </summary>
     public JxlOrientation Orientation { get; init; }
 
@@ -39,6 +43,9 @@ public class JxlBasicInfo
     /// <summary>
     /// Whether the original color profile is used.
     /// </summary>
+    /// <remarks>
+    /// False for images stored in the XYB color space; see <see cref="JxlDecoder.IsXyb"/>.
+    /// </remarks>
     public bool UsesOriginalProfile { get; init; }
 
     /// <summary>
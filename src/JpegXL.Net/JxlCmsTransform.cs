@@ -0,0 +1,99 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license.
+
+namespace JpegXL.Net;
+
+/// <summary>
+/// A color transform between two ICC profiles that can be applied to many buffers.
+/// </summary>
+/// <remarks>
+/// Building a transform is far more expensive than applying it, so create one per profile pair
+/// and reuse it. A transform must not be applied from several threads at once.
+/// Requires the native library to be built with lcms2 support.
+/// </remarks>
+public sealed unsafe class JxlCmsTransform : IDisposable
+{
+    private JxlCmsTransformHandle* _handle;
+
+    private JxlCmsTransform(JxlCmsTransformHandle* handle)
+    {
+        _handle = handle;
+    }
+
+    /// <summary>
+    /// Creates a transform from one ICC profile to another.
+    /// </summary>
+    /// <param name="inputIcc">The ICC profile the samples are in.</param>
+    /// <param name="outputIcc">The ICC profile to convert the samples to.</param>
+    /// <param name="intent">The rendering intent of the transform.</param>
+    /// <exception cref="JxlException">
+    /// The profiles are invalid, have different channel counts, or lcms2 support is not compiled in.
+    /// </exception>
+    public static JxlCmsTransform Create(
+        ReadOnlySpan<byte> inputIcc,
+        ReadOnlySpan<byte> outputIcc,
+        RenderingIntent intent = RenderingIntent.Perceptual)
+    {
+        if (inputIcc.IsEmpty)
+        {
+            throw new ArgumentException("ICC data cannot be empty", nameof(inputIcc));
+        }
+        if (outputIcc.IsEmpty)
+        {
+            throw new ArgumentException("ICC data cannot be empty", nameof(outputIcc));
+        }
+
+        fixed (byte* inputPtr = inputIcc)
+        fixed (byte* outputPtr = outputIcc)
+        {
+            var handle = NativeMethods.jxl_cms_transform_create(
+                inputPtr, (UIntPtr)inputIcc.Length,
+                outputPtr, (UIntPtr)outputIcc.Length,
+                (JxlRenderingIntent)intent);
+            if (handle == null)
+            {
+                throw new JxlException(
+                    JxlDecoder.GetLastErrorStatus(),
+                    JxlDecoder.GetLastError() ?? "Failed to create CMS transform");
+            }
+            return new JxlCmsTransform(handle);
+        }
+    }
+
+    /// <summary>
+    /// Converts interleaved samples in place.
+    /// </summary>
+    /// <param name="samples">
+    /// Samples interleaved with the profiles' channel count, nominally in [0, 1].
+    /// </param>
+    /// <exception cref="JxlException">
+    /// The sample count is not a multiple of the channel count, or the transform failed.
+    /// </exception>
+    public void Apply(Span<float> samples)
+    {
+        ThrowIfDisposed();
+
+        fixed (float* ptr = samples)
+        {
+            var status = NativeMethods.jxl_cms_transform_apply(_handle, ptr, (UIntPtr)samples.Length);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+    }
+
+    private void ThrowIfDisposed()
+    {
+        if (_handle == null)
+        {
+            throw new ObjectDisposedException(nameof(JxlCmsTransform));
+        }
+    }
+
+    public void Dispose()
+    {
+        if (_handle != null)
+        {
+            NativeMethods.jxl_cms_transform_destroy(_handle);
+            _handle = null;
+        }
+    }
+}
@@ -193,8 +193,18 @@ public sealed unsafe class JxlColorProfile : IDisposable
     /// <summary>Gets whether this profile uses HDR HLG transfer function.</summary>
     public bool IsHlg => TransferFunctionType == JxlTransferFunctionType.Hlg;
 
-    /// <summary>Gets whether this is an HDR profile (PQ or HLG).</summary>
-    public bool IsHdr => IsPq || IsHlg;
+    /// <summary>
+    /// Gets whether this is an HDR profile (PQ or HLG). ICC profiles count as HDR
+    /// when their cicp tag signals PQ or HLG.
+    /// </summary>
+    public bool IsHdr
+    {
+        get
+        {
+            ThrowIfDisposed();
+            return NativeMethods.jxl_color_profile_is_hdr(_handle);
+        }
+    }
 
     /// <summary>Gets whether this profile uses linear transfer function.</summary>
     public bool IsLinear => TransferFunctionType == JxlTransferFunctionType.Linear;
@@ -303,17 +313,7 @@ public sealed unsafe class JxlColorProfile : IDisposable
                 }
 
                 // Parse transfer function
-                TransferFunctionType = encoding.TransferFunction.Tag switch
-                {
-                    JxlTransferFunctionTag.Bt709 => JxlTransferFunctionType.Bt709,
-                    JxlTransferFunctionTag.Linear => JxlTransferFunctionType.Linear,
-                    JxlTransferFunctionTag.Srgb => JxlTransferFunctionType.Srgb,
-                    JxlTransferFunctionTag.Pq => JxlTransferFunctionType.Pq,
-                    JxlTransferFunctionTag.Dci => JxlTransferFunctionType.Dci,
-                    JxlTransferFunctionTag.Hlg => JxlTransferFunctionType.Hlg,
-                    JxlTransferFunctionTag.Gamma => JxlTransferFunctionType.Gamma,
-                    _ => throw new ArgumentException($"Unknown transfer function tag: {encoding.TransferFunction.Tag}")
-                };
+                TransferFunctionType = ToTransferFunctionType(encoding.TransferFunction.Tag);
 
                 if (TransferFunctionType == JxlTransferFunctionType.Gamma)
                 {
@@ -400,6 +400,97 @@ public sealed unsafe class JxlColorProfile : IDisposable
             handle);
     }
 
+    /// <summary>
+    /// Creates a Display P3 color profile (P3 primaries, D65 white point, sRGB transfer function).
+    /// </summary>
+    public static JxlColorProfile CreateDisplayP3()
+    {
+        var raw = new JxlColorEncodingRaw();
+        NativeMethods.jxl_color_encoding_display_p3(&raw);
+        return FromStandardEncoding(raw, "Display P3");
+    }
+
+    /// <summary>
+    /// Creates a Rec. 2100 PQ color profile (BT.2100 primaries, D65 white point, PQ transfer function).
+    /// </summary>
+    public static JxlColorProfile CreateRec2020Pq()
+    {
+        var raw = new JxlColorEncodingRaw();
+        NativeMethods.jxl_color_encoding_rec2020_pq(&raw);
+        return FromStandardEncoding(raw, "Rec. 2100 PQ");
+    }
+
+    /// <summary>
+    /// Creates a Rec. 2100 HLG color profile (BT.2100 primaries, D65 white point, HLG transfer function).
+    /// </summary>
+    public static JxlColorProfile CreateRec2020Hlg()
+    {
+        var raw = new JxlColorEncodingRaw();
+        NativeMethods.jxl_color_encoding_rec2020_hlg(&raw);
+        return FromStandardEncoding(raw, "Rec. 2100 HLG");
+    }
+
+    private static JxlColorProfile FromStandardEncoding(JxlColorEncodingRaw raw, string name)
+    {
+        var handle = NativeMethods.jxl_color_profile_from_encoding(&raw);
+        if (handle == null)
+        {
+            throw new JxlException(JxlStatus.Error, $"Failed to create {name} color profile");
+        }
+
+        return new JxlColorProfile(
+            new JxlColorProfileRaw
+            {
+                Tag = JxlColorProfileTag.Simple,
+                IccLength = UIntPtr.Zero,
+                Encoding = raw
+            },
+            null,
+            handle);
+    }
+
+    /// <summary>
+    /// Creates a color profile with a pure power-law transfer function and D65 white point.
+    /// </summary>
+    /// <param name="gamma">Display gamma (decoding exponent), e.g. 2.2. Must be at least 1.</param>
+    /// <param name="primaries">Standard primaries for RGB profiles. Custom primaries are not supported.</param>
+    /// <param name="grayscale">If true, creates a grayscale profile; otherwise RGB.</param>
+    /// <exception cref="ArgumentException">Thrown if <paramref name="primaries"/> is <see cref="JxlPrimariesType.Custom"/>.</exception>
+    /// <exception cref="JxlException">Thrown if <paramref name="gamma"/> is invalid.</exception>
+    public static JxlColorProfile CreateGamma(
+        float gamma,
+        JxlPrimariesType primaries = JxlPrimariesType.Srgb,
+        bool grayscale = false)
+    {
+        var tag = primaries switch
+        {
+            JxlPrimariesType.Srgb => JxlPrimariesTag.Srgb,
+            JxlPrimariesType.Bt2100 => JxlPrimariesTag.Bt2100,
+            JxlPrimariesType.P3 => JxlPrimariesTag.P3,
+            _ => throw new ArgumentException("Use FromEncoding() for custom primaries", nameof(primaries))
+        };
+
+        var raw = new JxlColorEncodingRaw();
+        var status = NativeMethods.jxl_color_encoding_gamma(grayscale, gamma, tag, &raw);
+        JxlDecoder.ThrowIfFailed(status);
+
+        var handle = NativeMethods.jxl_color_profile_from_encoding(&raw);
+        if (handle == null)
+        {
+            throw new JxlException(JxlStatus.Error, "Failed to create gamma color profile");
+        }
+
+        return new JxlColorProfile(
+            new JxlColorProfileRaw
+            {
+                Tag = JxlColorProfileTag.Simple,
+                IccLength = UIntPtr.Zero,
+                Encoding = raw
+            },
+            null,
+            handle);
+    }
+
     /// <summary>
     /// Creates a color profile from a simple color encoding.
     /// </summary>
@@ -530,6 +621,31 @@ public sealed unsafe class JxlColorProfile : IDisposable
         }
     }
 
+    /// <summary>
+    /// Computes a Bradford chromatic adaptation matrix between two white points.
+    /// </summary>
+    /// <param name="sourceWx">Source white point x chromaticity.</param>
+    /// <param name="sourceWy">Source white point y chromaticity.</param>
+    /// <param name="targetWx">Target white point x chromaticity.</param>
+    /// <param name="targetWy">Target white point y chromaticity.</param>
+    /// <returns>
+    /// A 3x3 matrix in row-major order that maps XYZ relative to the source white
+    /// point to XYZ relative to the target white point.
+    /// </returns>
+    /// <exception cref="JxlException">Thrown if a white point is degenerate.</exception>
+    public static float[] GetChromaticAdaptationMatrix(
+        float sourceWx, float sourceWy, float targetWx, float targetWy)
+    {
+        var matrix = new float[9];
+        fixed (float* ptr = matrix)
+        {
+            var status = NativeMethods.jxl_chromatic_adapt_matrix(
+                sourceWx, sourceWy, targetWx, targetWy, ptr);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+        return matrix;
+    }
+
     // ========================================================================
     // Helper methods
     // ========================================================================
@@ -557,6 +673,69 @@ public sealed unsafe class JxlColorProfile : IDisposable
         return null;
     }
 
+    /// <summary>
+    /// Gets the size of this profile's ICC data in bytes without copying it.
+    /// </summary>
+    /// <remarks>
+    /// Exact for ICC profiles and once <see cref="TryAsIcc"/> has been called. For simple
+    /// matrix/curve encodings the size is estimated from the profile layout; XYB, PQ and HLG
+    /// encodings are generated on demand to get the exact size.
+    /// </remarks>
+    /// <returns>The ICC size in bytes, or 0 if no ICC data can be produced.</returns>
+    public int GetIccSizeHint()
+    {
+        ThrowIfDisposed();
+        return (int)NativeMethods.jxl_color_profile_icc_size_hint(_handle);
+    }
+
+    /// <summary>
+    /// Checks if this profile is equivalent to sRGB (RGB or grayscale). ICC profiles are
+    /// parsed, so an embedded sRGB ICC profile is recognized and a CMS pass can be skipped.
+    /// </summary>
+    public bool IsEquivalentToSrgb()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_color_profile_is_equivalent_srgb(_handle);
+    }
+
+    /// <summary>
+    /// Checks if this profile and another describe the same color space, even if one is an
+    /// ICC profile and the other a simple encoding.
+    /// </summary>
+    public bool IsEquivalentTo(JxlColorProfile other)
+    {
+        ThrowIfDisposed();
+        if (other == null || other._disposed)
+        {
+            return false;
+        }
+        return NativeMethods.jxl_color_profile_is_equivalent(_handle, other._handle);
+    }
+
+    /// <summary>
+    /// Gets the transfer function of this profile. For ICC profiles this is a best-effort
+    /// match from the cicp tag or the TRC curves (linear, sRGB, BT.709 or pure gamma).
+    /// </summary>
+    /// <param name="type">The detected transfer function.</param>
+    /// <param name="gamma">The gamma value when <paramref name="type"/> is <see cref="JxlTransferFunctionType.Gamma"/>.</param>
+    /// <returns>True if a transfer function could be determined.</returns>
+    public bool TryGetTransferFunction(out JxlTransferFunctionType type, out float gamma)
+    {
+        ThrowIfDisposed();
+
+        JxlTransferFunctionRaw raw;
+        if (!NativeMethods.jxl_color_profile_get_transfer_function(_handle, &raw))
+        {
+            type = default;
+            gamma = 0;
+            return false;
+        }
+
+        type = ToTransferFunctionType(raw.Tag);
+        gamma = raw.Gamma;
+        return true;
+    }
+
     /// <summary>
     /// Checks if this profile and another represent the same color encoding.
     /// </summary>
@@ -749,6 +928,18 @@ public sealed unsafe class JxlColorProfile : IDisposable
         };
     }
 
+    private static JxlTransferFunctionType ToTransferFunctionType(JxlTransferFunctionTag tag) => tag switch
+    {
+        JxlTransferFunctionTag.Bt709 => JxlTransferFunctionType.Bt709,
+        JxlTransferFunctionTag.Linear => JxlTransferFunctionType.Linear,
+        JxlTransferFunctionTag.Srgb => JxlTransferFunctionType.Srgb,
+        JxlTransferFunctionTag.Pq => JxlTransferFunctionType.Pq,
+        JxlTransferFunctionTag.Dci => JxlTransferFunctionType.Dci,
+        JxlTransferFunctionTag.Hlg => JxlTransferFunctionType.Hlg,
+        JxlTransferFunctionTag.Gamma => JxlTransferFunctionType.Gamma,
+        _ => throw new ArgumentException($"Unknown transfer function tag: {tag}")
+    };
+
     private void ThrowIfDisposed()
     {
         if (_disposed)
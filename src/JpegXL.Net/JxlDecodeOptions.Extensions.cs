@@ -18,12 +18,31 @@ public partial struct JxlDecodeOptions
     /// <item><description>RenderSpotColors: true</description></item>
     /// <item><description>Coalescing: true</description></item>
     /// <item><description>SkipPreview: true</description></item>
+    /// <item><description>PreferPreview: false</description></item>
     /// <item><description>ProgressiveMode: Pass</description></item>
+    /// <item><description>SourceIntensityTargetOverride: 0 (use image metadata)</description></item>
+    /// <item><description>ToneMapLuminanceR/G/B: 0 (derive from image primaries)</description></item>
     /// <item><description>PixelLimit: 0 (no limit)</description></item>
+    /// <item><description>MemoryLimitBytes: 0 (no limit)</description></item>
     /// <item><description>HighPrecision: false</description></item>
     /// <item><description>PremultiplyAlpha: false</description></item>
+    /// <item><description>UnpremultiplyAlpha: false</description></item>
+    /// <item><description>SanitizeFloatOutput: false</description></item>
+    /// <item><description>ScaleToFullRange: true</description></item>
     /// <item><description>DecodeExtraChannels: false</description></item>
+    /// <item><description>SeparateFirstAlpha: false</description></item>
+    /// <item><description>ToneMapClipStats: false</description></item>
+    /// <item><description>LuminanceStats: false</description></item>
+    /// <item><description>OpaqueBounds: false</description></item>
+    /// <item><description>OpaqueAlphaThreshold: 0</description></item>
+    /// <item><description>DetectTransparency: false</description></item>
+    /// <item><description>ContinueOnFrameError: false</description></item>
+    /// <item><description>CmsErrorFallback: false</description></item>
+    /// <item><description>GamutCompression: false</description></item>
+    /// <item><description>MetadataOnly: false</description></item>
+    /// <item><description>MaxFrames: 0 (no limit)</description></item>
     /// <item><description>PixelFormat: RGBA8 (default)</description></item>
+    /// <item><description>RowAlignment: 0 (packed rows)</description></item>
     /// <item><description>MetadataCapture: Default (all enabled with limits)</description></item>
     /// <item><description>CmsType: Lcms2</description></item>
     /// </list>
@@ -31,15 +50,36 @@ public partial struct JxlDecodeOptions
     public static JxlDecodeOptions Default => new()
     {
         PixelLimit = UIntPtr.Zero,
+        MemoryLimitBytes = UIntPtr.Zero,
         ProgressiveMode = JxlProgressiveMode.Pass,
+        SourceIntensityTargetOverride = 0f,
+        ToneMapLuminanceR = 0f,
+        ToneMapLuminanceG = 0f,
+        ToneMapLuminanceB = 0f,
         AdjustOrientation = true,
         RenderSpotColors = true,
         Coalescing = true,
         SkipPreview = true,
+        PreferPreview = false,
         HighPrecision = false,
         PremultiplyAlpha = false,
+        UnpremultiplyAlpha = false,
+        SanitizeFloatOutput = false,
+        ScaleToFullRange = true,
         DecodeExtraChannels = false,
+        SeparateFirstAlpha = false,
+        ToneMapClipStats = false,
+        LuminanceStats = false,
+        OpaqueBounds = false,
+        OpaqueAlphaThreshold = 0f,
+        DetectTransparency = false,
+        ContinueOnFrameError = false,
+        CmsErrorFallback = false,
+        GamutCompression = false,
+        MetadataOnly = false,
+        MaxFrames = 0,
         PixelFormat = JxlPixelFormat.Default,
+        RowAlignment = 0,
         MetadataCapture = JxlMetadataCaptureOptions.Default,
         CmsType = JxlCmsType.Lcms2,
     };
@@ -15,6 +15,10 @@ namespace JpegXL.Net;
 /// This class wraps the native jxl_ffi decoder and provides a managed interface
 /// for decoding JPEG XL images. It implements <see cref="IDisposable"/> and
 /// should be disposed when no longer needed.
+/// <para>
+/// A decoder is not thread-safe. Overlapping decode calls on the same instance from
+/// different threads throw a <see cref="JxlException"/> with <see cref="JxlStatus.Busy"/>.
+/// </para>
 /// </remarks>
 public sealed unsafe class JxlDecoder : IDisposable
 {
@@ -22,6 +26,7 @@ public sealed unsafe class JxlDecoder : IDisposable
     private bool _disposed;
     private JxlBasicInfo? _basicInfo;
     private JxlMetadata? _metadata;
+    private System.Buffers.MemoryHandle _borrowedInput;
 
     internal NativeDecoderHandle* Handle => _handle;
 
@@ -57,6 +62,35 @@ public sealed unsafe class JxlDecoder : IDisposable
         }
     }
 
+    private JxlDecoder(NativeDecoderHandle* handle)
+    {
+        _handle = handle;
+    }
+
+    /// <summary>
+    /// Creates an independent decoder over a copy of this decoder's input.
+    /// </summary>
+    /// <returns>A new decoder that must be disposed separately.</returns>
+    /// <remarks>
+    /// The clone copies the input, options, pixel and extra channel formats and output color
+    /// settings, but starts fresh from the beginning of the input regardless of this decoder's
+    /// position. Clones share no state, so each can seek to and decode a different frame on its
+    /// own thread.
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if cloning fails.</exception>
+    public JxlDecoder Clone()
+    {
+        ThrowIfDisposed();
+
+        var handle = NativeMethods.jxl_decoder_clone(_handle);
+        if (handle == null)
+        {
+            throw new JxlException(GetLastErrorStatus(), GetLastError() ?? "Failed to clone decoder");
+        }
+
+        return new JxlDecoder(handle);
+    }
+
     /// <summary>
     /// Gets the basic image information after decoding the header.
     /// </summary>
@@ -92,6 +126,35 @@ public sealed unsafe class JxlDecoder : IDisposable
         AppendInput(data);
     }
 
+    /// <summary>
+    /// Sets the input data for decoding without copying it.
+    /// </summary>
+    /// <param name="data">The JXL-encoded image data.</param>
+    /// <remarks>
+    /// The decoder pins <paramref name="data"/> and reads it in place until it is reset,
+    /// given new input, or disposed. The contents must not be modified during that time.
+    /// This avoids a second copy of large inputs. <see cref="AppendInput(ReadOnlySpan{byte})"/>
+    /// cannot be combined with borrowed input.
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if setting input fails.</exception>
+    public void SetInputBorrowed(ReadOnlyMemory<byte> data)
+    {
+        ThrowIfDisposed();
+
+        var handle = data.Pin();
+        var status = NativeMethods.jxl_decoder_set_input_borrowed(_handle, (byte*)handle.Pointer, (UIntPtr)data.Length);
+        if (status != JxlStatus.Success)
+        {
+            handle.Dispose();
+            ThrowIfFailed(status);
+        }
+
+        // The native reset released the previous buffer
+        ReleaseBorrowedInput();
+        _borrowedInput = handle;
+        _basicInfo = null;
+    }
+
     /// <summary>
     /// Sets the input by reading directly from a file.
     /// </summary>
@@ -119,6 +182,7 @@ public sealed unsafe class JxlDecoder : IDisposable
             var status = NativeMethods.jxl_decoder_set_input_file(_handle, ptr);
             ThrowIfFailed(status);
         }
+        ReleaseBorrowedInput();
         _basicInfo = null;
     }
 
@@ -145,7 +209,15 @@ public sealed unsafe class JxlDecoder : IDisposable
     /// Sets the desired output pixel format.
     /// </summary>
     /// <param name="format">The pixel format to use for decoded output.</param>
-    /// <exception cref="JxlException">Thrown if setting pixel format fails.</exception>
+    /// <remarks>
+    /// Color images are never converted to gray, so grayscale color types require a
+    /// grayscale image. After <see cref="ReadInfo"/> such a format is rejected here; a
+    /// format set earlier is rejected by <see cref="Process"/> before the first frame.
+    /// </remarks>
+    /// <exception cref="JxlException">
+    /// Thrown if setting pixel format fails, with <see cref="JxlStatus.InvalidArgument"/> if the
+    /// image can't be output in the requested color type.
+    /// </exception>
     public void SetPixelFormat(JxlPixelFormat format)
     {
         ThrowIfDisposed();
@@ -153,6 +225,31 @@ public sealed unsafe class JxlDecoder : IDisposable
         ThrowIfFailed(status);
     }
 
+    /// <summary>
+    /// Sets the output pixel format of the preview frame, independent of the main image.
+    /// </summary>
+    /// <param name="format">The preview's pixel format, or null to use the main pixel format.</param>
+    /// <remarks>
+    /// Lets a thumbnail be decoded as e.g. <see cref="JxlPixelFormat.Rgba8"/> while the main
+    /// image is decoded as float. The preview is the first frame with
+    /// <see cref="JxlDecodeOptions.PreferPreview"/>; <see cref="GetBufferSize"/> reports its
+    /// size in this format until it has been read.
+    /// </remarks>
+    /// <exception cref="JxlException">
+    /// Thrown with <see cref="JxlStatus.InvalidArgument"/> if the image can't be output in the
+    /// requested color type.
+    /// </exception>
+    public void SetPreviewPixelFormat(JxlPixelFormat? format)
+    {
+        ThrowIfDisposed();
+        JxlStatus status;
+        if (format is { } value)
+            status = NativeMethods.jxl_decoder_set_preview_pixel_format(_handle, &value);
+        else
+            status = NativeMethods.jxl_decoder_set_preview_pixel_format(_handle, null);
+        ThrowIfFailed(status);
+    }
+
     /// <summary>
     /// Reads the image header and basic info.
     /// </summary>
@@ -186,7 +283,10 @@ public sealed unsafe class JxlDecoder : IDisposable
     /// <summary>
     /// Gets the required buffer size for decoded pixels.
     /// </summary>
-    /// <returns>The required buffer size in bytes.</returns>
+    /// <returns>
+    /// The required buffer size in bytes, or 0 if decoding ends without any frame, as with
+    /// <see cref="JxlDecodeOptions.MetadataOnly"/>.
+    /// </returns>
     /// <remarks>
     /// <see cref="ReadInfo"/> must be called before this method.
     /// </remarks>
@@ -196,6 +296,44 @@ public sealed unsafe class JxlDecoder : IDisposable
         return (int)(uint)NativeMethods.jxl_decoder_get_buffer_size(_handle);
     }
 
+    /// <summary>
+    /// Gets the buffer size for decoded pixels with each row padded to a multiple of
+    /// <paramref name="alignment"/> bytes.
+    /// </summary>
+    /// <param name="alignment">The row alignment in bytes, e.g. 16 or 32. 0 or 1 gives the packed size.</param>
+    /// <returns>The buffer size in bytes; rows start <c>size / height</c> bytes apart.</returns>
+    /// <remarks>
+    /// Decoding writes rows at this stride when the decoder was created with
+    /// <see cref="JxlDecodeOptions.RowAlignment"/> set to <paramref name="alignment"/>.
+    /// Only row lengths are padded; the buffer itself must also be allocated at the alignment.
+    /// <see cref="ReadInfo"/> must be called before this method.
+    /// </remarks>
+    public int GetAlignedBufferSize(int alignment)
+    {
+        ThrowIfDisposed();
+        if (alignment < 0)
+            throw new ArgumentOutOfRangeException(nameof(alignment), "Alignment must not be negative");
+
+        return (int)(uint)NativeMethods.jxl_decoder_get_aligned_buffer_size(_handle, (uint)alignment);
+    }
+
+    /// <summary>
+    /// Gets the buffer size for the decoded preview, in the format set with
+    /// <see cref="SetPreviewPixelFormat"/> or else the main pixel format.
+    /// </summary>
+    /// <returns>
+    /// The buffer size in bytes, or 0 if the image has no preview or it is skipped.
+    /// </returns>
+    /// <remarks>
+    /// <see cref="ReadInfo"/> must be called before this method. Unlike <see cref="GetBufferSize"/>,
+    /// this doesn't depend on which frame is next.
+    /// </remarks>
+    public int GetPreviewBufferSize()
+    {
+        ThrowIfDisposed();
+        return (int)(uint)NativeMethods.jxl_decoder_get_preview_buffer_size(_handle);
+    }
+
     /// <summary>
     /// Decodes pixels into a new byte array.
     /// </summary>
@@ -258,6 +396,10 @@ public sealed unsafe class JxlDecoder : IDisposable
             evt = Process();
         }
 
+        if (evt == JxlDecoderEvent.Complete)
+        {
+            throw new JxlException(JxlStatus.InvalidState, "No frame left to decode");
+        }
         if (evt != JxlDecoderEvent.NeedOutputBuffer)
         {
             throw new JxlException(JxlStatus.Error, $"Unexpected decoder event: {evt}");
@@ -269,12 +411,37 @@ public sealed unsafe class JxlDecoder : IDisposable
         {
             throw new JxlException(JxlStatus.NeedMoreInput, "Incomplete pixel data");
         }
+        if (evt == JxlDecoderEvent.FrameError)
+        {
+            throw new JxlException(JxlStatus.Error, GetLastError() ?? "Frame failed to decode");
+        }
         if (evt != JxlDecoderEvent.FrameComplete)
         {
             throw new JxlException(JxlStatus.Error, $"Unexpected decoder event after ReadPixels: {evt}");
         }
     }
 
+    /// <summary>
+    /// Gets the effective bit depth of decoded output in the current pixel format.
+    /// </summary>
+    /// <returns>The output bit depth.</returns>
+    /// <remarks>
+    /// Integer formats report at most the source precision: a 10-bit image decoded to
+    /// <see cref="JxlDataFormat.Uint16"/> has 10 significant bits. Its samples still span
+    /// 0-65535 unless <see cref="JxlDecodeOptions.ScaleToFullRange"/> is off, in which case
+    /// they span 0-1023. Float formats report their own layout. <see cref="ReadInfo"/> must
+    /// be called before this method.
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if basic info is not available.</exception>
+    public JxlBitDepth GetOutputBitDepth()
+    {
+        ThrowIfDisposed();
+        JxlBitDepth bitDepth;
+        var status = NativeMethods.jxl_decoder_get_output_bit_depth(_handle, &bitDepth);
+        ThrowIfFailed(status);
+        return bitDepth;
+    }
+
     /// <summary>
     /// Gets the number of extra channels in the image.
     /// </summary>
@@ -301,6 +468,162 @@ public sealed unsafe class JxlDecoder : IDisposable
         return info;
     }
 
+    /// <summary>
+    /// Gets the number of pixels clipped at the target peak by tone mapping.
+    /// </summary>
+    /// <returns>
+    /// The number of clipped pixels and the total number of tone-mapped pixels,
+    /// accumulated since the decoder was created, reset, or rewound.
+    /// </returns>
+    /// <exception cref="JxlException">
+    /// Thrown if <see cref="JxlDecodeOptions.ToneMapClipStats"/> was not enabled.
+    /// </exception>
+    /// <remarks>
+    /// Counts are only collected with a tone-mapping <see cref="JxlDecodeOptions.CmsType"/>,
+    /// and only for images whose intensity target exceeds the display target.
+    /// </remarks>
+    public (ulong Clipped, ulong Total) GetToneMapClipStats()
+    {
+        ThrowIfDisposed();
+
+        ulong clipped, total;
+        var status = NativeMethods.jxl_decoder_get_tone_map_clip_stats(_handle, &clipped, &total);
+        ThrowIfFailed(status);
+        return (clipped, total);
+    }
+
+    /// <summary>
+    /// Gets the error of a CMS that was bypassed with <see cref="JxlDecodeOptions.CmsErrorFallback"/>.
+    /// </summary>
+    /// <returns>
+    /// The CMS error, or null if the CMS has not failed since the decoder was created,
+    /// reset, or rewound.
+    /// </returns>
+    /// <remarks>
+    /// When this is not null, decoded pixels may be in the image's own color encoding
+    /// rather than the output color profile, since the color conversion was skipped.
+    /// </remarks>
+    public string? GetCmsFallbackError()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_cms_fell_back(_handle)
+            ? GetLastError() ?? "CMS failed"
+            : null;
+    }
+
+    /// <summary>
+    /// Gets the number of color conversions the CMS has set up since the decoder was created,
+    /// reset, or rewound.
+    /// </summary>
+    /// <remarks>
+    /// Images output in their embedded color encoding, such as an sRGB image decoded to sRGB,
+    /// bypass the CMS without building any transform, so this stays 0. HDR input still goes
+    /// through a tone-mapping <see cref="JxlDecodeOptions.CmsType"/>.
+    /// </remarks>
+    public ulong GetCmsSetupCount()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_get_cms_setup_count(_handle);
+    }
+
+    /// <summary>
+    /// Computes a luminance histogram of the last decoded frame.
+    /// </summary>
+    /// <param name="bins">Number of equal-width bins over [0, 1], from 1 to 65536.</param>
+    /// <returns>The pixel count of each bin.</returns>
+    /// <exception cref="ArgumentOutOfRangeException">Thrown if bins is out of range.</exception>
+    /// <exception cref="JxlException">
+    /// Thrown if <see cref="JxlDecodeOptions.LuminanceStats"/> was not enabled or no frame has been decoded.
+    /// </exception>
+    /// <remarks>
+    /// Luminance is taken from the decoded pixels using BT.709 weights, with integer formats
+    /// normalized to [0, 1]; float values outside the range fall in the first or last bin.
+    /// The statistics are recorded while the frame is decoded, so this does not read the
+    /// pixel buffer again.
+    /// </remarks>
+    public uint[] ComputeHistogram(int bins)
+    {
+        ThrowIfDisposed();
+        if (bins < 1 || bins > 65536)
+            throw new ArgumentOutOfRangeException(nameof(bins), "Bin count must be between 1 and 65536");
+
+        var histogram = new uint[bins];
+        fixed (uint* ptr = histogram)
+        {
+            var status = NativeMethods.jxl_decoder_compute_histogram(_handle, (uint)bins, ptr);
+            ThrowIfFailed(status);
+        }
+        return histogram;
+    }
+
+    /// <summary>
+    /// Gets the luminance range and mean of the last decoded frame.
+    /// </summary>
+    /// <returns>The minimum, maximum and mean luminance.</returns>
+    /// <exception cref="JxlException">
+    /// Thrown if <see cref="JxlDecodeOptions.LuminanceStats"/> was not enabled or no frame has been decoded.
+    /// </exception>
+    /// <remarks>
+    /// Values are not clamped to [0, 1]: decoding to linear float output keeps HDR highlights at
+    /// their true magnitude, giving a peak to choose <see cref="JxlDecodeOptions.SourceIntensityTargetOverride"/>
+    /// from. NaN and infinite values are ignored.
+    /// </remarks>
+    public JxlLuminanceStats GetLuminanceStats()
+    {
+        ThrowIfDisposed();
+        JxlLuminanceStats stats;
+        var status = NativeMethods.jxl_decoder_get_luminance_stats(_handle, &stats);
+        ThrowIfFailed(status);
+        return stats;
+    }
+
+    /// <summary>
+    /// Gets the bounding box of opaque content in the last decoded frame.
+    /// </summary>
+    /// <returns>
+    /// The bounds of pixels whose alpha exceeds <see cref="JxlDecodeOptions.OpaqueAlphaThreshold"/>,
+    /// empty if there are none, or the whole frame for a color type without alpha.
+    /// </returns>
+    /// <exception cref="JxlException">
+    /// Thrown if <see cref="JxlDecodeOptions.OpaqueBounds"/> was not enabled or no frame has been decoded.
+    /// </exception>
+    /// <remarks>
+    /// The bounds are recorded while the frame is decoded, so cropping transparent borders,
+    /// e.g. for sprite packing, needs no scan of the pixel buffer.
+    /// </remarks>
+    public JxlRect GetOpaqueBounds()
+    {
+        ThrowIfDisposed();
+        JxlRect bounds;
+        var status = NativeMethods.jxl_decoder_get_opaque_bounds(_handle, &bounds);
+        ThrowIfFailed(status);
+        return bounds;
+    }
+
+    /// <summary>
+    /// Gets whether the image has transparency, e.g. to decide whether to draw a checkerboard
+    /// behind it.
+    /// </summary>
+    /// <returns>
+    /// True if the image has transparency, false if it is fully opaque, or null before
+    /// <see cref="ReadInfo"/>.
+    /// </returns>
+    /// <remarks>
+    /// An image without an alpha channel is opaque. With <see cref="JxlDecodeOptions.DetectTransparency"/>,
+    /// the image is also reported as opaque while every frame decoded so far has alpha at the
+    /// maximum everywhere; otherwise any alpha channel counts as transparency.
+    /// </remarks>
+    public bool? HasTransparency()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_has_transparency(_handle) switch
+        {
+            0 => false,
+            1 => true,
+            _ => null,
+        };
+    }
+
     // ========================================================================
     // Color Profiles
     // ========================================================================
@@ -328,6 +651,48 @@ public sealed unsafe class JxlDecoder : IDisposable
         return new JxlColorProfile(raw, iccData, handle);
     }
 
+    /// <summary>
+    /// Gets the simple color encoding of the embedded color profile.
+    /// </summary>
+    /// <returns>The encoding, or null if the image embeds an ICC profile.</returns>
+    /// <exception cref="JxlException">Thrown if the encoding cannot be retrieved.</exception>
+    /// <remarks>
+    /// <see cref="ReadInfo"/> must be called before this method. Unlike
+    /// <see cref="GetEmbeddedColorProfile"/>, no native profile is allocated. When this
+    /// returns null, use <see cref="GetEmbeddedColorProfile"/> for the ICC bytes.
+    /// </remarks>
+    public JxlColorEncodingRaw? GetEmbeddedEncoding()
+    {
+        ThrowIfDisposed();
+
+        JxlColorEncodingRaw encoding;
+        var status = NativeMethods.jxl_decoder_get_embedded_encoding(_handle, &encoding);
+        if (status == JxlStatus.IccProfile)
+            return null;
+        ThrowIfFailed(status);
+
+        return encoding;
+    }
+
+    /// <summary>
+    /// Gets the transfer function of the embedded color profile.
+    /// </summary>
+    /// <returns>The transfer function, or null for an ICC or XYB profile.</returns>
+    /// <remarks>
+    /// <see cref="ReadInfo"/> must be called before this method. A cheap check for HDR
+    /// content (PQ or HLG) before choosing whether to tone map; no native profile is allocated.
+    /// </remarks>
+    public JxlTransferFunctionRaw? GetEmbeddedTransferFunction()
+    {
+        ThrowIfDisposed();
+
+        JxlTransferFunctionRaw transferFunction;
+        if (!NativeMethods.jxl_decoder_get_embedded_transfer_function(_handle, &transferFunction))
+            return null;
+
+        return transferFunction;
+    }
+
     /// <summary>
     /// Gets the current output color profile.
     /// </summary>
@@ -351,11 +716,65 @@ public sealed unsafe class JxlDecoder : IDisposable
         return new JxlColorProfile(raw, iccData, handle);
     }
 
+    /// <summary>
+    /// Gets the number of color channels of the embedded color profile.
+    /// </summary>
+    /// <returns>1 for grayscale, 3 for RGB, 4 for CMYK, or 0 before <see cref="ReadInfo"/>.</returns>
+    /// <remarks>
+    /// Cheaper than <see cref="GetEmbeddedColorProfile"/> when only the channel count is needed,
+    /// e.g. to size buffers, and also available while a frame is being decoded.
+    /// </remarks>
+    public int GetEmbeddedProfileChannels()
+    {
+        ThrowIfDisposed();
+        return (int)NativeMethods.jxl_decoder_get_embedded_profile_channels(_handle);
+    }
+
+    /// <summary>
+    /// Gets whether the current image is output in its embedded color profile.
+    /// </summary>
+    /// <returns>True if pixels are decoded in the embedded color space, false otherwise.</returns>
+    /// <remarks>
+    /// <para><see cref="ReadInfo"/> must be called before this method.</para>
+    /// <para>This is the default for images stored in their original color space, and
+    /// passing an output profile with the same color encoding as the embedded one to
+    /// <see cref="SetOutputColorProfile"/> keeps it so: such images are decoded as-is,
+    /// without any color conversion, even when the embedded profile is a non-standard ICC
+    /// profile. Callers doing their own color management can use this to assert that.</para>
+    /// </remarks>
+    public bool OutputMatchesEmbedded()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_output_matches_embedded(_handle);
+    }
+
+    /// <summary>
+    /// Gets whether the current image is stored in the XYB color space.
+    /// </summary>
+    /// <returns>True if the image is XYB-encoded, false if it is stored in its original profile.</returns>
+    /// <remarks>
+    /// <para><see cref="ReadInfo"/> must be called before this method.</para>
+    /// <para>XYB images are lossy-encoded in JPEG XL's internal perceptual color space and
+    /// reconstructed into the output color space while decoding. This is the inverse of
+    /// <see cref="JxlBasicInfo.UsesOriginalProfile"/>, for quality decisions such as
+    /// avoiding re-encoding an image that was already lossy.</para>
+    /// </remarks>
+    public bool IsXyb()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_is_xyb(_handle);
+    }
+
     /// <summary>
     /// Sets the output color profile for decoding.
     /// </summary>
     /// <param name="profile">The color profile to use for output.</param>
-    /// <exception cref="JxlException">Thrown if setting the profile fails.</exception>
+    /// <exception cref="JxlException">
+    /// Thrown if setting the profile fails, if the conversion needs a CMS and
+    /// <see cref="JxlDecodeOptions.CmsType"/> provides none, or with
+    /// <see cref="JxlStatus.InvalidArgument"/> if a grayscale profile is set for a color
+    /// image or vice versa.
+    /// </exception>
     /// <remarks>
     /// <para>Must be called after <see cref="ReadInfo"/> and before decoding pixels.</para>
     /// <para>Setting the output color profile allows converting the decoded pixels to a
@@ -370,44 +789,153 @@ public sealed unsafe class JxlDecoder : IDisposable
         ArgumentNullException.ThrowIfNull(profile);
 #endif
 
+        SetProfile(profile, &NativeMethods.jxl_decoder_set_output_color_profile);
+    }
+
+    /// <summary>
+    /// Sets the output color profile to a common color space.
+    /// </summary>
+    /// <param name="colorSpace">The color space to use for output.</param>
+    /// <exception cref="JxlException">
+    /// Thrown if setting the profile fails, or if the conversion needs a CMS and
+    /// <see cref="JxlDecodeOptions.CmsType"/> provides none.
+    /// </exception>
+    /// <remarks>
+    /// <para>A shorthand for <see cref="SetOutputColorProfile"/> with the same
+    /// requirements. Grayscale images stay gray, with the color space's transfer function.</para>
+    /// </remarks>
+    public void SetOutputColorSpace(JxlNamedColorSpace colorSpace)
+    {
+        ThrowIfDisposed();
+        var status = NativeMethods.jxl_decoder_set_output_named(_handle, colorSpace);
+        ThrowIfFailed(status);
+    }
+
+    /// <summary>
+    /// Registers an output color profile to apply to every image automatically.
+    /// </summary>
+    /// <param name="profile">The color profile to use for output, or null to clear it.</param>
+    /// <exception cref="JxlException">Thrown if the profile cannot be registered.</exception>
+    /// <remarks>
+    /// <para>Unlike <see cref="SetOutputColorProfile"/>, this can be called before
+    /// <see cref="ReadInfo"/>. The profile is applied whenever an image header is read and
+    /// persists across <see cref="Reset"/> and new input, so an "always convert to sRGB"
+    /// pipeline needs to configure it only once.</para>
+    /// <para>A profile set with <see cref="SetOutputColorProfile"/> still overrides it for the
+    /// current image. If the profile needs a CMS that <see cref="JxlDecodeOptions.CmsType"/>
+    /// does not provide, reading the image header throws.</para>
+    /// </remarks>
+    public void SetDesiredOutputColorProfile(JxlColorProfile? profile)
+    {
+        ThrowIfDisposed();
+
+        if (profile == null)
+        {
+            var status = NativeMethods.jxl_decoder_set_desired_output_color_profile(_handle, null, null);
+            ThrowIfFailed(status);
+            return;
+        }
+
+        SetProfile(profile, &NativeMethods.jxl_decoder_set_desired_output_color_profile);
+    }
+
+    /// <summary>
+    /// Soft-proofs output through the device described by a color profile, e.g. a printer.
+    /// </summary>
+    /// <param name="profile">The proof device's color profile, or null to stop soft proofing.</param>
+    /// <exception cref="JxlException">
+    /// Thrown if <see cref="JxlDecodeOptions.CmsType"/> is not <see cref="JxlCmsType.Lcms2"/>,
+    /// or if the current image has started decoding.
+    /// </exception>
+    /// <remarks>
+    /// <para>The output is converted by way of the proof device, previewing how the image would
+    /// look there. Pixels the device can't reproduce can be marked with
+    /// <see cref="SetGamutWarningColor"/>.</para>
+    /// <para>Must be called before <see cref="ReadInfo"/>, or after <see cref="Reset"/> or
+    /// <see cref="Rewind"/>, and persists across resets. Only images that go through the CMS are
+    /// affected, i.e. those with an output color profile other than the embedded one.</para>
+    /// </remarks>
+    public void SetProofProfile(JxlColorProfile? profile)
+    {
+        ThrowIfDisposed();
+
+        if (profile == null)
+        {
+            var status = NativeMethods.jxl_decoder_set_proof_profile(_handle, null, null);
+            ThrowIfFailed(status);
+            return;
+        }
+
+        SetProfile(profile, &NativeMethods.jxl_decoder_set_proof_profile);
+    }
+
+    /// <summary>
+    /// Marks pixels outside the proof device's gamut with a fixed color while soft proofing.
+    /// </summary>
+    /// <param name="red">Red component, from 0 to 1 in the output color space.</param>
+    /// <param name="green">Green component, from 0 to 1 in the output color space.</param>
+    /// <param name="blue">Blue component, from 0 to 1 in the output color space.</param>
+    /// <exception cref="JxlException">Thrown under the same conditions as <see cref="SetProofProfile"/>.</exception>
+    /// <remarks>
+    /// Only has an effect with a profile set by <see cref="SetProofProfile"/>. Grayscale output
+    /// uses <paramref name="red"/>.
+    /// </remarks>
+    public void SetGamutWarningColor(float red, float green, float blue)
+    {
+        ThrowIfDisposed();
+        var color = stackalloc float[] { red, green, blue };
+        var status = NativeMethods.jxl_decoder_set_gamut_warning_color(_handle, color);
+        ThrowIfFailed(status);
+    }
+
+    /// <summary>
+    /// Stops marking out-of-gamut pixels set by <see cref="SetGamutWarningColor"/>.
+    /// </summary>
+    /// <exception cref="JxlException">Thrown under the same conditions as <see cref="SetProofProfile"/>.</exception>
+    public void ClearGamutWarningColor()
+    {
+        ThrowIfDisposed();
+        var status = NativeMethods.jxl_decoder_set_gamut_warning_color(_handle, null);
+        ThrowIfFailed(status);
+    }
+
+    private void SetProfile(
+        JxlColorProfile profile,
+        delegate*<NativeDecoderHandle*, JxlColorProfileRaw*, byte*, JxlStatus> setProfile)
+    {
+        JxlStatus status;
         if (profile.IsIcc)
         {
             // ICC profile
             var iccBytes = profile.IccData;
-            if (iccBytes != null && iccBytes.Length > 0)
+            if (iccBytes == null || iccBytes.Length == 0)
+                throw new ArgumentException("ICC profile has no data", nameof(profile));
+
+            var raw = new JxlColorProfileRaw
             {
-                var raw = new JxlColorProfileRaw
-                {
-                    Tag = JxlColorProfileTag.Icc,
-                    IccLength = (UIntPtr)iccBytes.Length,
-                    Encoding = default
-                };
+                Tag = JxlColorProfileTag.Icc,
+                IccLength = (UIntPtr)iccBytes.Length,
+                Encoding = default
+            };
 
-                fixed (byte* dataPtr = iccBytes)
-                {
-                    var status = NativeMethods.jxl_decoder_set_output_color_profile(_handle, &raw, dataPtr);
-                    ThrowIfFailed(status);
-                }
-            }
-            else
+            fixed (byte* dataPtr = iccBytes)
             {
-                throw new ArgumentException("ICC profile has no data", nameof(profile));
+                status = setProfile(_handle, &raw, dataPtr);
             }
         }
         else
         {
             // Simple encoding - use the profile's ToEncodingRaw method
-            var encodingRaw = profile.ToEncodingRaw();
             var raw = new JxlColorProfileRaw
             {
                 Tag = JxlColorProfileTag.Simple,
                 IccLength = UIntPtr.Zero,
-                Encoding = encodingRaw
+                Encoding = profile.ToEncodingRaw()
             };
 
-            var status = NativeMethods.jxl_decoder_set_output_color_profile(_handle, &raw, null);
-            ThrowIfFailed(status);
+            status = setProfile(_handle, &raw, null);
         }
+        ThrowIfFailed(status);
     }
 
     // ========================================================================
@@ -423,6 +951,27 @@ public sealed unsafe class JxlDecoder : IDisposable
         ThrowIfDisposed();
         var status = NativeMethods.jxl_decoder_reset(_handle);
         ThrowIfFailed(status);
+        ReleaseBorrowedInput();
+        _basicInfo = null;
+    }
+
+    /// <summary>
+    /// Resets the decoder for a new image, keeping its configuration.
+    /// </summary>
+    /// <remarks>
+    /// Like <see cref="Reset"/>, but an output color profile set with
+    /// <see cref="SetOutputColorProfile"/> is kept and re-applied when the next image's
+    /// header is read, so a pooled decoder can process same-config images with one setup.
+    /// Supply the next image with <see cref="AppendInput(ReadOnlySpan{byte})"/>;
+    /// <see cref="SetInput(ReadOnlySpan{byte})"/> performs a full <see cref="Reset"/>.
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if reset fails.</exception>
+    public void ResetKeepConfig()
+    {
+        ThrowIfDisposed();
+        var status = NativeMethods.jxl_decoder_reset_keep_config(_handle);
+        ThrowIfFailed(status);
+        ReleaseBorrowedInput();
         _basicInfo = null;
     }
 
@@ -444,6 +993,30 @@ public sealed unsafe class JxlDecoder : IDisposable
         _basicInfo = null;
     }
 
+    /// <summary>
+    /// Gets a human-readable snapshot of the decoder state for bug reports.
+    /// </summary>
+    /// <returns>A multi-line description of the decoder state.</returns>
+    /// <remarks>
+    /// Covers the state machine, input offsets, pixel format, CMS type, cached info and
+    /// options, but no image data. The format is not stable and should not be parsed.
+    /// </remarks>
+    public string GetDebugState()
+    {
+        ThrowIfDisposed();
+
+        var length = (int)(uint)NativeMethods.jxl_decoder_debug_state(_handle, null, UIntPtr.Zero);
+        if (length == 0)
+            return string.Empty;
+
+        var buffer = new byte[length];
+        fixed (byte* ptr = buffer)
+        {
+            NativeMethods.jxl_decoder_debug_state(_handle, ptr, (UIntPtr)length);
+        }
+        return System.Text.Encoding.UTF8.GetString(buffer);
+    }
+
     // ========================================================================
     // Streaming API
     // ========================================================================
@@ -484,6 +1057,74 @@ public sealed unsafe class JxlDecoder : IDisposable
         AppendInput(data.AsSpan());
     }
 
+    /// <summary>
+    /// Gets the number of input bytes the decoder has consumed so far.
+    /// </summary>
+    /// <returns>The offset of the next unread input byte.</returns>
+    /// <remarks>
+    /// Callers demultiplexing a larger stream can use this to advance their own
+    /// cursor. The count resets to 0 on <see cref="Reset"/> and <see cref="Rewind"/>.
+    /// </remarks>
+    public long GetBytesConsumed()
+    {
+        ThrowIfDisposed();
+        return (long)(ulong)NativeMethods.jxl_decoder_bytes_consumed(_handle);
+    }
+
+    /// <summary>
+    /// Computes a CRC32 of the decoder's entire input.
+    /// </summary>
+    /// <param name="codestreamOnly">
+    /// If true, container boxes other than the codestream are skipped.
+    /// See <see cref="JxlImage.ComputeCrc32"/>.
+    /// </param>
+    /// <returns>The CRC32, or 0 if there is no input.</returns>
+    public uint GetInputCrc32(bool codestreamOnly = false)
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_input_crc32(_handle, codestreamOnly);
+    }
+
+    /// <summary>
+    /// Gets whether input remains after the bytes consumed so far.
+    /// </summary>
+    /// <returns>True if unread input remains, false otherwise.</returns>
+    /// <remarks>
+    /// After <see cref="JxlDecoderEvent.Complete"/>, this indicates trailing data such as
+    /// another concatenated image. Use <see cref="SkipToNextImage"/> to decode it.
+    /// The decoder may read ahead while buffering, so short trailing data can already
+    /// count as consumed.
+    /// </remarks>
+    public bool HasTrailingData()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_has_trailing_data(_handle);
+    }
+
+    /// <summary>
+    /// Advances to the next JPEG XL signature at or after the consumed input.
+    /// </summary>
+    /// <returns>True if a signature was found and the decoder restarted there; false if none remains.</returns>
+    /// <remarks>
+    /// Use this to decode multi-image streams. <see cref="Rewind"/> still returns to the start
+    /// of the whole input. Codestream signatures are only two bytes, so arbitrary trailing
+    /// data can produce false matches.
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if skipping fails.</exception>
+    public bool SkipToNextImage()
+    {
+        ThrowIfDisposed();
+        var status = NativeMethods.jxl_decoder_skip_to_next_image(_handle);
+        if (status == JxlStatus.NeedMoreInput)
+        {
+            return false;
+        }
+
+        ThrowIfFailed(status);
+        _basicInfo = null;
+        return true;
+    }
+
     /// <summary>
     /// Processes the current input data and returns the next decoder event.
     /// </summary>
@@ -518,7 +1159,32 @@ public sealed unsafe class JxlDecoder : IDisposable
         if (evt == JxlDecoderEvent.Error)
         {
             var message = GetLastError();
-            throw new JxlException(JxlStatus.Error, message);
+            throw new JxlException(GetLastErrorStatus(), message);
+        }
+        return evt;
+    }
+
+    /// <summary>
+    /// Continues with the next image of a concatenated multi-image stream.
+    /// </summary>
+    /// <returns>
+    /// The next event, usually <see cref="JxlDecoderEvent.HaveBasicInfo"/>, or
+    /// <see cref="JxlDecoderEvent.NeedMoreInput"/> if no further image data is buffered yet.
+    /// </returns>
+    /// <remarks>
+    /// Only valid after <see cref="Process"/> returns <see cref="JxlDecoderEvent.Complete"/>.
+    /// The decoder restarts at the end of the current image without discarding its input.
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if the current image is not complete or processing fails.</exception>
+    public JxlDecoderEvent NextImage()
+    {
+        ThrowIfDisposed();
+        _basicInfo = null;
+        var evt = NativeMethods.jxl_decoder_next_image(_handle);
+        if (evt == JxlDecoderEvent.Error)
+        {
+            var message = GetLastError();
+            throw new JxlException(GetLastErrorStatus(), message);
         }
         return evt;
     }
@@ -573,6 +1239,11 @@ public sealed unsafe class JxlDecoder : IDisposable
     /// Gets the current frame header after <see cref="Process"/> returns
     /// <see cref="JxlDecoderEvent.HaveFrameHeader"/>.
     /// </summary>
+    /// <remarks>
+    /// The header stays available after the frame's pixels are read, until the next frame header.
+    /// <see cref="JxlFrameHeader.IsOpaque"/> is then exact when
+    /// <see cref="JxlDecodeOptions.DetectTransparency"/> is enabled and the output has alpha.
+    /// </remarks>
     /// <returns>The frame header information.</returns>
     /// <exception cref="JxlException">Thrown if frame header is not yet available.</exception>
     public JxlFrameHeader GetFrameHeader()
@@ -586,6 +1257,39 @@ public sealed unsafe class JxlDecoder : IDisposable
         return header;
     }
 
+    /// <summary>
+    /// Gets the current frame header with additional fields for compositors after
+    /// <see cref="Process"/> returns <see cref="JxlDecoderEvent.HaveFrameHeader"/>.
+    /// </summary>
+    /// <returns>The extended frame header, including the fields of <see cref="GetFrameHeader"/>.</returns>
+    /// <exception cref="JxlException">Thrown if frame header is not yet available.</exception>
+    public JxlFrameHeaderEx GetFrameHeaderEx()
+    {
+        ThrowIfDisposed();
+
+        JxlFrameHeaderEx header;
+        var status = NativeMethods.jxl_decoder_get_frame_header_ex(_handle, &header);
+        ThrowIfFailed(status);
+
+        return header;
+    }
+
+    /// <summary>
+    /// Gets the current frame's duration in animation ticks after <see cref="Process"/> returns
+    /// <see cref="JxlDecoderEvent.HaveFrameHeader"/>.
+    /// </summary>
+    /// <returns>The duration in ticks, or 0 if the image is not animated.</returns>
+    /// <remarks>
+    /// <see cref="JxlFrameHeader.DurationMs"/> is rounded to a <see cref="float"/>. One tick lasts
+    /// <see cref="JxlAnimation.TpsDenominator"/> / <see cref="JxlAnimation.TpsNumerator"/> seconds,
+    /// so players can schedule frames exactly without accumulating rounding error.
+    /// </remarks>
+    public uint GetFrameDurationTicks()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_get_frame_duration_ticks(_handle);
+    }
+
     /// <summary>
     /// Gets the current frame's name after <see cref="Process"/> returns
     /// <see cref="JxlDecoderEvent.HaveFrameHeader"/>.
@@ -621,6 +1325,66 @@ public sealed unsafe class JxlDecoder : IDisposable
         }
     }
 
+    /// <summary>
+    /// Gets the length of the current frame's name in UTF-16 code units.
+    /// </summary>
+    /// <returns>The length <see cref="GetFrameName"/> will return, or 0 if the frame has no name.</returns>
+    /// <remarks>
+    /// <see cref="JxlFrameHeader.NameLength"/> counts UTF-8 bytes, which is larger than the
+    /// string length for non-ASCII names. Use this to size <see cref="char"/> buffers.
+    /// </remarks>
+    public int GetFrameNameLength()
+    {
+        ThrowIfDisposed();
+        return (int)NativeMethods.jxl_decoder_get_frame_name_utf16_len(_handle);
+    }
+
+    /// <summary>
+    /// Gets the SHA-256 digest of the color buffer from the last decoded frame.
+    /// </summary>
+    /// <returns>The 32-byte digest.</returns>
+    /// <remarks>
+    /// The digest covers the decoded pixel rows without any <c>RowAlignment</c> padding, so it
+    /// depends on the pixel format but not the stride. Intended for comparing against reference hashes in conformance tests.
+    /// Requires the native library to be built with the <c>sha2</c> feature.
+    /// </remarks>
+    /// <exception cref="JxlException">
+    /// Thrown if no frame has been decoded or the native library was built without <c>sha2</c>.
+    /// </exception>
+    public byte[] GetLastFrameHash()
+    {
+        ThrowIfDisposed();
+
+        var hash = new byte[32];
+        fixed (byte* ptr = hash)
+        {
+            var status = NativeMethods.jxl_decoder_last_frame_hash(_handle, ptr);
+            ThrowIfFailed(status);
+        }
+        return hash;
+    }
+
+    /// <summary>
+    /// Gets the time spent in each decoding phase since the decoder was created, reset, or rewound.
+    /// </summary>
+    /// <returns>Header parse, frame decode and CMS times in milliseconds.</returns>
+    /// <remarks>
+    /// <see cref="JxlDecodeTimings.FrameDecodeMs"/> includes <see cref="JxlDecodeTimings.CmsMs"/>,
+    /// since color conversion and tone mapping run inside frame decoding.
+    /// Requires the native library to be built with the <c>timings</c> feature.
+    /// </remarks>
+    /// <exception cref="JxlException">
+    /// Thrown if the native library was built without <c>timings</c>.
+    /// </exception>
+    public JxlDecodeTimings GetTimings()
+    {
+        ThrowIfDisposed();
+        JxlDecodeTimings timings;
+        var status = NativeMethods.jxl_decoder_get_timings(_handle, &timings);
+        ThrowIfFailed(status);
+        return timings;
+    }
+
     /// <summary>
     /// Decodes pixels into the provided buffer during streaming decode.
     /// </summary>
@@ -629,6 +1393,9 @@ public sealed unsafe class JxlDecoder : IDisposable
     /// <remarks>
     /// Call this method after <see cref="Process"/> returns <see cref="JxlDecoderEvent.NeedOutputBuffer"/>.
     /// The returned event indicates whether more data is needed or if the frame is complete.
+    /// With <see cref="JxlDecodeOptions.ContinueOnFrameError"/>, a frame that fails to decode
+    /// but can be skipped returns <see cref="JxlDecoderEvent.FrameError"/> instead of throwing;
+    /// call <see cref="Process"/> to continue with the next frame.
     /// </remarks>
     /// <exception cref="JxlException">Thrown if decoding fails.</exception>
     public JxlDecoderEvent ReadPixels(Span<byte> buffer)
@@ -641,7 +1408,7 @@ public sealed unsafe class JxlDecoder : IDisposable
             if (evt == JxlDecoderEvent.Error)
             {
                 var message = GetLastError();
-                throw new JxlException(JxlStatus.Error, message);
+                throw new JxlException(GetLastErrorStatus(), message);
             }
             return evt;
         }
@@ -681,7 +1448,7 @@ public sealed unsafe class JxlDecoder : IDisposable
         if (evt == JxlDecoderEvent.Error)
         {
             var message = GetLastError();
-            throw new JxlException(JxlStatus.Error, message);
+            throw new JxlException(GetLastErrorStatus(), message);
         }
         return evt;
     }
@@ -735,7 +1502,7 @@ public sealed unsafe class JxlDecoder : IDisposable
             }
             else if (evt == JxlDecoderEvent.Error)
             {
-                throw new JxlException(JxlStatus.Error, GetLastError() ?? "Failed to seek to frame");
+                throw new JxlException(GetLastErrorStatus(), GetLastError() ?? "Failed to seek to frame");
             }
         }
 
@@ -751,6 +1518,30 @@ public sealed unsafe class JxlDecoder : IDisposable
         }
     }
 
+    /// <summary>
+    /// Sets the data format of one extra channel, in place of the pixel format's.
+    /// </summary>
+    /// <param name="index">The extra channel index (0-based).</param>
+    /// <param name="dataFormat">The data format to output the channel in.</param>
+    /// <remarks>
+    /// <para>Lets each channel be output at the precision it needs, e.g. a depth map as
+    /// <see cref="JxlDataFormat.Float32"/> next to a selection mask as <see cref="JxlDataFormat.Uint8"/>.
+    /// The pixel format's endianness is used, and the alpha channel in the color output keeps
+    /// the pixel format's data format. <see cref="GetExtraChannelBufferSize"/> reflects the setting.</para>
+    /// <para>Takes effect from the next frame header, like <see cref="SetPixelFormat"/>. The setting is
+    /// kept by <see cref="Rewind"/> and cleared by <see cref="Reset"/>.</para>
+    /// </remarks>
+    /// <exception cref="JxlException">
+    /// Thrown if <see cref="ReadInfo"/> has not been called, or with
+    /// <see cref="JxlStatus.InvalidArgument"/> if the index is out of range.
+    /// </exception>
+    public void SetExtraChannelFormat(uint index, JxlDataFormat dataFormat)
+    {
+        ThrowIfDisposed();
+        var status = NativeMethods.jxl_decoder_set_extra_channel_format(_handle, index, dataFormat);
+        ThrowIfFailed(status);
+    }
+
     /// <summary>
     /// Gets the required buffer size for a specific extra channel.
     /// </summary>
@@ -774,14 +1565,61 @@ public sealed unsafe class JxlDecoder : IDisposable
     /// to enable extra channel decoding.
     /// </para>
     /// <para>
-    /// Extra channels are decoded in order. Pass null for a buffer to skip that channel.
+    /// Extra channels are decoded in order. Pass null for a buffer to skip that channel, and
+    /// pass fewer buffers than channels to skip the trailing ones. Each buffer must hold at
+    /// least <see cref="GetExtraChannelBufferSize"/> bytes.
+    /// </para>
+    /// <para>
+    /// When the pixel format has alpha, the first alpha channel is written to the color buffer
+    /// and its entry in <paramref name="extraBuffers"/> is left untouched, unless
+    /// <see cref="JxlDecodeOptions.SeparateFirstAlpha"/> is set, in which case that entry also
+    /// receives the alpha. Further alpha channels are written to their own buffers.
     /// </para>
     /// </remarks>
+    /// <exception cref="JxlException">
+    /// Thrown if decoding fails, if more buffers than extra channels are passed, or if a buffer
+    /// for a decoded channel is too small; the message names the channel and the required size.
+    /// </exception>
     public JxlDecoderEvent ReadPixelsWithExtraChannels(Span<byte> colorBuffer, Span<byte[]?> extraBuffers)
+        => ReadPixelsWithExtraChannels(colorBuffer, extraBuffers, Span<bool>.Empty);
+
+    /// <summary>
+    /// Decodes pixels with extra channels into separate buffers, reporting which buffers were written.
+    /// </summary>
+    /// <param name="colorBuffer">Buffer for color data (RGB/RGBA/etc.).</param>
+    /// <param name="extraBuffers">Array of buffers for extra channels.</param>
+    /// <param name="buffersWritten">
+    /// Receives whether the color buffer (index 0) and each extra buffer (index 1 onwards)
+    /// received data. Must hold at least <c>1 + extraBuffers.Length</c> entries, or be empty.
+    /// </param>
+    /// <returns>The event indicating what happened during pixel decoding.</returns>
+    /// <remarks>
+    /// <para>
+    /// Behaves like <see cref="ReadPixelsWithExtraChannels(Span{byte}, Span{byte[]})"/>. An extra
+    /// buffer is reported as written only when it is non-null and its channel is decoded, which
+    /// tells channels skipped with a null buffer apart from decoded ones. The alpha channel in the
+    /// color output reports false unless <see cref="JxlDecodeOptions.SeparateFirstAlpha"/> is set.
+    /// All entries are false unless
+    /// <see cref="JxlDecoderEvent.FrameComplete"/> is returned.
+    /// </para>
+    /// </remarks>
+    /// <exception cref="ArgumentException">Thrown if <paramref name="buffersWritten"/> is too short.</exception>
+    /// <exception cref="JxlException">
+    /// Thrown if decoding fails, if more buffers than extra channels are passed, or if a buffer
+    /// for a decoded channel is too small.
+    /// </exception>
+    public JxlDecoderEvent ReadPixelsWithExtraChannels(
+        Span<byte> colorBuffer, Span<byte[]?> extraBuffers, Span<bool> buffersWritten)
     {
         ThrowIfDisposed();
 
         var numExtra = extraBuffers.Length;
+        if (!buffersWritten.IsEmpty && buffersWritten.Length < 1 + numExtra)
+        {
+            throw new ArgumentException(
+                $"Needs at least {1 + numExtra} entries, got {buffersWritten.Length}", nameof(buffersWritten));
+        }
+
         var extraPtrs = stackalloc byte*[numExtra];
         var extraSizes = stackalloc nuint[numExtra];
         
@@ -806,6 +1644,7 @@ public sealed unsafe class JxlDecoder : IDisposable
             }
 
             fixed (byte* colorPtr = colorBuffer)
+            fixed (bool* writtenPtr = buffersWritten)
             {
                 var evt = NativeMethods.jxl_decoder_read_pixels_with_extra_channels(
                     _handle,
@@ -813,12 +1652,13 @@ public sealed unsafe class JxlDecoder : IDisposable
                     (UIntPtr)colorBuffer.Length,
                     extraPtrs,
                     extraSizes,
-                    (UIntPtr)numExtra);
+                    (UIntPtr)numExtra,
+                    writtenPtr);
                 
                 if (evt == JxlDecoderEvent.Error)
                 {
                     var message = GetLastError();
-                    throw new JxlException(JxlStatus.Error, message);
+                    throw new JxlException(GetLastErrorStatus(), message);
                 }
                 return evt;
             }
@@ -836,6 +1676,65 @@ public sealed unsafe class JxlDecoder : IDisposable
         }
     }
 
+    /// <summary>
+    /// Decodes a single extra channel, such as alpha, during streaming decode.
+    /// </summary>
+    /// <param name="channelIndex">The extra channel index (0-based).</param>
+    /// <param name="buffer">The buffer to write the channel to, sized by <see cref="GetExtraChannelBufferSize"/>.</param>
+    /// <returns>The event indicating what happened during pixel decoding.</returns>
+    /// <remarks>
+    /// <para>
+    /// Call this method in place of <see cref="ReadPixels"/> after <see cref="Process"/> returns
+    /// <see cref="JxlDecoderEvent.NeedOutputBuffer"/>. Use <see cref="GetExtraChannelInfo"/> to find
+    /// the alpha channel's index.
+    /// </para>
+    /// <para>
+    /// A frame's output layout is fixed when its header is read, so this also selects the channel
+    /// for later frames, which then skip color output and must be read with this method until
+    /// <see cref="SetPixelFormat"/> or <see cref="Reset"/> is called. The current frame still
+    /// decodes color internally, and the channel must be one it outputs: the first alpha channel
+    /// when the pixel format has alpha, or any channel with
+    /// <see cref="JxlDecodeOptions.DecodeExtraChannels"/>. Color channels may still be partially
+    /// reconstructed when the selected channel depends on them.
+    /// </para>
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if decoding fails.</exception>
+    public JxlDecoderEvent ReadSingleChannel(int channelIndex, Span<byte> buffer)
+    {
+        ThrowIfDisposed();
+        if (channelIndex < 0)
+            throw new ArgumentOutOfRangeException(nameof(channelIndex));
+
+        fixed (byte* ptr = buffer)
+        {
+            var evt = NativeMethods.jxl_decoder_read_single_channel(
+                _handle, (uint)channelIndex, ptr, (UIntPtr)buffer.Length);
+            if (evt == JxlDecoderEvent.Error)
+            {
+                var message = GetLastError();
+                throw new JxlException(GetLastErrorStatus(), message);
+            }
+            return evt;
+        }
+    }
+
+    /// <summary>
+    /// Gets the stage of the native decoder's state machine.
+    /// </summary>
+    /// <returns>
+    /// The current state, or <see cref="JxlDecoderStateTag.Processing"/> while a decoding call
+    /// is running on another thread.
+    /// </returns>
+    /// <remarks>
+    /// Useful for checking preconditions, such as basic info being available, without tracking
+    /// the events returned so far.
+    /// </remarks>
+    public JxlDecoderStateTag GetState()
+    {
+        ThrowIfDisposed();
+        return NativeMethods.jxl_decoder_get_state(_handle);
+    }
+
     /// <summary>
     /// Gets whether there are more frames to decode in an animated image.
     /// </summary>
@@ -920,9 +1819,13 @@ public sealed unsafe class JxlDecoder : IDisposable
             if (evt == JxlDecoderEvent.HaveFrameHeader)
             {
                 var header = GetFrameHeader();
-                frames.Add(header);
 
-                frameNames?.Add(header.NameLength > 0 ? GetFrameName() : string.Empty);
+                // A preview output with PreferPreview is not an animation frame
+                if (!header.IsPreview)
+                {
+                    frames.Add(header);
+                    frameNames?.Add(header.NameLength > 0 ? GetFrameName() : string.Empty);
+                }
 
                 evt = Process();
             }
@@ -949,10 +1852,17 @@ public sealed unsafe class JxlDecoder : IDisposable
                 NativeMethods.jxl_decoder_destroy(_handle);
                 _handle = null;
             }
+            ReleaseBorrowedInput();
             _disposed = true;
         }
     }
 
+    private void ReleaseBorrowedInput()
+    {
+        _borrowedInput.Dispose();
+        _borrowedInput = default;
+    }
+
     internal void ThrowIfDisposed()
     {
 #if NET7_0_OR_GREATER
@@ -971,7 +1881,49 @@ public sealed unsafe class JxlDecoder : IDisposable
         }
     }
 
-    private static string? GetLastError()
+    /// <summary>
+    /// Gets the error of the last decoding call on this decoder, from any thread.
+    /// </summary>
+    /// <returns>
+    /// The error of the last <see cref="Process"/>, pixel read or <see cref="SkipFrame"/> call,
+    /// or null if that call succeeded.
+    /// </returns>
+    /// <remarks>
+    /// Native errors are otherwise kept per thread, so they are lost when an async continuation
+    /// resumes on a different thread than the failing call. This copy is stored on the decoder
+    /// itself and can be read from any thread.
+    /// </remarks>
+    public JxlException? GetLastDecodeError()
+    {
+        ThrowIfDisposed();
+
+        var status = NativeMethods.jxl_decoder_get_last_error_status(_handle);
+        if (status == JxlStatus.Success)
+        {
+            return null;
+        }
+
+        var length = (int)(uint)NativeMethods.jxl_decoder_get_last_error(_handle, null, UIntPtr.Zero);
+        var buffer = stackalloc byte[length + 1];
+        NativeMethods.jxl_decoder_get_last_error(_handle, buffer, (UIntPtr)(length + 1));
+#if NETSTANDARD2_0
+        var message = Marshal.PtrToStringAnsi((IntPtr)buffer);
+#else
+        var message = Marshal.PtrToStringUTF8((IntPtr)buffer);
+#endif
+        return new JxlException(status, length > 0 ? message : null);
+    }
+
+    /// <summary>
+    /// Gets the specific status behind a <see cref="JxlDecoderEvent.Error"/> event.
+    /// </summary>
+    internal static JxlStatus GetLastErrorStatus()
+    {
+        var status = NativeMethods.jxl_get_last_error_status();
+        return status == JxlStatus.Success ? JxlStatus.Error : status;
+    }
+
+    internal static string? GetLastError()
     {
         // Get required length
         var length = (int)(uint)NativeMethods.jxl_get_last_error(null, UIntPtr.Zero);
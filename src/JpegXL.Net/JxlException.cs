@@ -41,6 +41,9 @@ public class JxlException : Exception
         JxlStatus.InvalidArgument => "Invalid argument",
         JxlStatus.BufferTooSmall => "Buffer too small",
         JxlStatus.InvalidState => "Invalid decoder state",
+        JxlStatus.MemoryLimitExceeded => "Decoding would exceed the memory limit",
+        JxlStatus.IccProfile => "The color profile is an ICC profile",
+        JxlStatus.Busy => "The decoder is already in use on another thread",
         _ => $"Unknown error (status {(int)status})"
     };
 }
@@ -0,0 +1,30 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license.
+
+namespace JpegXL.Net;
+
+/// <summary>
+/// Extension properties for JxlExtraChannelInfo.
+/// </summary>
+public partial struct JxlExtraChannelInfo
+{
+    /// <summary>
+    /// Gets the factor by which the channel is downsampled in the file (1, 2, 4 or 8).
+    /// </summary>
+    /// <remarks>
+    /// The decoder upsamples the channel to the image size, so extra channel buffers are always full size.
+    /// </remarks>
+    public int DownsampleFactor => 1 << (int)DimShift;
+
+    /// <summary>
+    /// Gets the resolution the channel is stored at in the file, for an image of the given size.
+    /// </summary>
+    /// <param name="width">The image width in pixels.</param>
+    /// <param name="height">The image height in pixels.</param>
+    /// <returns>The stored width and height, rounded up.</returns>
+    public (int Width, int Height) GetStoredSize(int width, int height)
+    {
+        var factor = DownsampleFactor;
+        return ((width + factor - 1) / factor, (height + factor - 1) / factor);
+    }
+}
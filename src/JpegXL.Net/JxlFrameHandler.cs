@@ -0,0 +1,17 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license.
+
+namespace JpegXL.Net;
+
+/// <summary>
+/// Receives one decoded frame from <see cref="JxlImage.DecodeAnimation"/>.
+/// </summary>
+/// <param name="frameIndex">Index of the frame in the file.</param>
+/// <param name="pixels">
+/// The frame's pixels with tightly packed rows. Only valid during the call; copy them to keep them.
+/// </param>
+/// <param name="width">Frame width in pixels.</param>
+/// <param name="height">Frame height in pixels.</param>
+/// <param name="durationMs">How long to show the frame, in milliseconds; 0 for still images.</param>
+/// <returns>True to continue with the next frame, false to stop decoding.</returns>
+public delegate bool JxlFrameHandler(int frameIndex, ReadOnlySpan<byte> pixels, int width, int height, float durationMs);
@@ -2,6 +2,8 @@
 // Use of this source code is governed by a BSD-style license.
 
 using System;
+using System.Runtime.ExceptionServices;
+using System.Runtime.InteropServices;
 
 namespace JpegXL.Net;
 
@@ -51,7 +53,11 @@ public sealed class JxlImage : IDisposable
     /// <summary>
     /// Gets the stride (bytes per row) of the image.
     /// </summary>
-    public int Stride => Width * BytesPerPixel;
+    /// <remarks>
+    /// Larger than <see cref="Width"/> * <see cref="BytesPerPixel"/> when decoded with
+    /// <see cref="JxlDecodeOptions.RowAlignment"/>.
+    /// </remarks>
+    public int Stride { get; }
 
     /// <summary>
     /// Gets whether the image has an alpha channel.
@@ -68,6 +74,7 @@ public sealed class JxlImage : IDisposable
         _pixels = pixels;
         Width = (int)info.Size.Width;
         Height = (int)info.Size.Height;
+        Stride = Height > 0 ? pixels.Length / Height : 0;
         PixelFormat = format;
         BasicInfo = info;
     }
@@ -186,17 +193,279 @@ public sealed class JxlImage : IDisposable
         return new JxlImage(pixels, info, format);
     }
 
+    /// <summary>
+    /// Decodes a small sRGB preview of a JPEG XL image, e.g. for file-manager thumbnails.
+    /// </summary>
+    /// <param name="data">The JXL-encoded image data.</param>
+    /// <param name="maxDimension">Maximum width and height of the thumbnail, in pixels.</param>
+    /// <param name="width">Receives the thumbnail width.</param>
+    /// <param name="height">Receives the thumbnail height.</param>
+    /// <returns>Tightly packed 8-bit sRGB RGBA pixels.</returns>
+    /// <remarks>
+    /// The first frame is decoded with default options and box-filtered down to fit
+    /// within <paramref name="maxDimension"/> while preserving the aspect ratio. Pixels
+    /// are averaged in linear light, so fine detail doesn't darken. Images that already
+    /// fit are returned at full size.
+    /// </remarks>
+    /// <exception cref="ArgumentOutOfRangeException">Thrown if maxDimension is not positive.</exception>
+    /// <exception cref="JxlException">Thrown if decoding fails.</exception>
+    public static unsafe byte[] DecodeThumbnail(ReadOnlySpan<byte> data, int maxDimension, out int width, out int height)
+    {
+        if (maxDimension <= 0)
+            throw new ArgumentOutOfRangeException(nameof(maxDimension));
+
+        byte* pixels;
+        uint w, h;
+        UIntPtr length;
+        fixed (byte* ptr = data)
+        {
+            var status = NativeMethods.jxl_thumbnail(
+                ptr, (UIntPtr)data.Length, (uint)maxDimension, &pixels, &w, &h, &length);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+
+        try
+        {
+            width = (int)w;
+            height = (int)h;
+            return new ReadOnlySpan<byte>(pixels, (int)(uint)length).ToArray();
+        }
+        finally
+        {
+            NativeMethods.jxl_free_buffer(pixels, length);
+        }
+    }
+
+    /// <summary>
+    /// Decodes the first frame of a JPEG XL image to upright 8-bit sRGB RGBA in a single call.
+    /// </summary>
+    /// <param name="data">The JXL-encoded image data.</param>
+    /// <param name="width">Receives the image width, after orientation.</param>
+    /// <param name="height">Receives the image height, after orientation.</param>
+    /// <param name="options">The decode options, or null for defaults.</param>
+    /// <returns>Tightly packed 8-bit sRGB RGBA pixels.</returns>
+    /// <remarks>
+    /// The pixel format, <see cref="JxlDecodeOptions.RowAlignment"/> and
+    /// <see cref="JxlDecodeOptions.AdjustOrientation"/> in <paramref name="options"/> are
+    /// overridden. Images in other color spaces are converted to sRGB, using lcms2 when
+    /// <see cref="JxlDecodeOptions.CmsType"/> is <see cref="JxlCmsType.None"/> and it's available.
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if decoding fails.</exception>
+    public static unsafe byte[] DecodeRgba8Srgb(
+        ReadOnlySpan<byte> data, out int width, out int height, JxlDecodeOptions? options = null)
+    {
+        var nativeOptions = options ?? JxlDecodeOptions.Default;
+        byte* pixels;
+        uint w, h;
+        UIntPtr length;
+        fixed (byte* ptr = data)
+        {
+            var status = NativeMethods.jxl_decode_rgba8_srgb(
+                ptr, (UIntPtr)data.Length, &nativeOptions, &pixels, &w, &h, &length);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+
+        try
+        {
+            width = (int)w;
+            height = (int)h;
+            return new ReadOnlySpan<byte>(pixels, (int)(uint)length).ToArray();
+        }
+        finally
+        {
+            NativeMethods.jxl_free_buffer(pixels, length);
+        }
+    }
+
+    [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+    [return: MarshalAs(UnmanagedType.U1)]
+    private unsafe delegate bool NativeFrameCallback(
+        uint frameIndex, byte* pixels, uint width, uint height, float durationMs, void* userData);
+
+    /// <summary>
+    /// Decodes every frame of an animation in order, passing each to <paramref name="onFrame"/>.
+    /// </summary>
+    /// <param name="data">The JXL-encoded image data.</param>
+    /// <param name="format">The output pixel format of the frames.</param>
+    /// <param name="options">The decode options, or null for defaults.</param>
+    /// <param name="onFrame">Called with each decoded frame; return false to stop decoding.</param>
+    /// <remarks>
+    /// Frames are decoded one at a time into a reused buffer, so playing an animation needs no
+    /// more memory than a single frame. Still images produce a single frame with a duration of 0.
+    /// <see cref="JxlDecodeOptions.RowAlignment"/> is ignored. With
+    /// <see cref="JxlDecodeOptions.ContinueOnFrameError"/>, frames that fail to decode are skipped.
+    /// </remarks>
+    /// <exception cref="ArgumentNullException">Thrown if onFrame is null.</exception>
+    /// <exception cref="JxlException">Thrown if decoding fails.</exception>
+    public static unsafe void DecodeAnimation(
+        ReadOnlySpan<byte> data, JxlPixelFormat format, JxlDecodeOptions? options, JxlFrameHandler onFrame)
+    {
+        if (onFrame == null)
+            throw new ArgumentNullException(nameof(onFrame));
+
+        var nativeOptions = options ?? JxlDecodeOptions.Default;
+        nativeOptions.PixelFormat = format;
+        var bytesPerPixel = CalculateBytesPerPixel(format);
+
+        // Exceptions can't unwind through native frames, so they stop decoding and are rethrown here
+        ExceptionDispatchInfo? callbackError = null;
+        NativeFrameCallback callback = (frameIndex, pixels, width, height, durationMs, _) =>
+        {
+            try
+            {
+                var length = (int)(width * height) * bytesPerPixel;
+                return onFrame((int)frameIndex, new ReadOnlySpan<byte>(pixels, length), (int)width, (int)height, durationMs);
+            }
+            catch (Exception ex)
+            {
+                callbackError = ExceptionDispatchInfo.Capture(ex);
+                return false;
+            }
+        };
+
+        var callbackPtr = Marshal.GetFunctionPointerForDelegate(callback);
+        JxlStatus status;
+        fixed (byte* ptr = data)
+        {
+            status = NativeMethods.jxl_decode_animation(
+                ptr,
+                (UIntPtr)data.Length,
+                &nativeOptions,
+                (delegate* unmanaged[Cdecl]<uint, byte*, uint, uint, float, void*, bool>)callbackPtr,
+                null);
+        }
+        GC.KeepAlive(callback);
+
+        callbackError?.Throw();
+        JxlDecoder.ThrowIfFailed(status);
+    }
+
+    [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+    private unsafe delegate void NativeLogCallback(JxlLogLevel level, byte* message, void* userData);
+
+    // Kept alive while registered with the native library
+    private static NativeLogCallback? _logCallback;
+
+    /// <summary>
+    /// Sets a handler for diagnostic messages from the native library, or removes it.
+    /// </summary>
+    /// <param name="handler">Called with each message, or null to stop logging.</param>
+    /// <remarks>
+    /// Messages cover decode milestones and recoverable problems that don't fail a call,
+    /// such as a skipped frame, a CMS fallback, or an unknown container box. The handler is
+    /// shared by all decoders and may be called on any thread that decodes. Exceptions
+    /// thrown by the handler are ignored.
+    /// </remarks>
+    public static unsafe void SetLogHandler(JxlLogHandler? handler)
+    {
+        if (handler == null)
+        {
+            NativeMethods.jxl_set_log_callback(null, null);
+            _logCallback = null;
+            return;
+        }
+
+        NativeLogCallback callback = (level, message, _) =>
+        {
+            try
+            {
+#if NETSTANDARD2_0
+                handler(level, Marshal.PtrToStringAnsi((IntPtr)message) ?? string.Empty);
+#else
+                handler(level, Marshal.PtrToStringUTF8((IntPtr)message) ?? string.Empty);
+#endif
+            }
+            catch
+            {
+                // Exceptions can't unwind through native frames
+            }
+        };
+
+        var callbackPtr = Marshal.GetFunctionPointerForDelegate(callback);
+        NativeMethods.jxl_set_log_callback(
+            (delegate* unmanaged[Cdecl]<JxlLogLevel, byte*, void*, void>)callbackPtr, null);
+        _logCallback = callback;
+    }
+
+    /// <summary>
+    /// Encodes 8-bit RGBA pixels as a lossless JPEG XL file.
+    /// </summary>
+    /// <param name="rgba">Tightly packed sRGB RGBA pixels, <c>width * height * 4</c> bytes.</param>
+    /// <param name="width">Image width in pixels.</param>
+    /// <param name="height">Image height in pixels.</param>
+    /// <returns>The encoded JXL file.</returns>
+    /// <remarks>
+    /// The output decodes back bit-exactly but is only lightly compressed. It is meant
+    /// for re-saving decoded or edited pixels, and requires the native library to be
+    /// built with the <c>encode</c> feature.
+    /// </remarks>
+    /// <exception cref="ArgumentOutOfRangeException">Thrown if width or height is not positive.</exception>
+    /// <exception cref="ArgumentException">Thrown if rgba is not width * height * 4 bytes.</exception>
+    /// <exception cref="JxlException">Thrown if encoding fails or is not compiled in.</exception>
+    public static unsafe byte[] EncodeLossless(ReadOnlySpan<byte> rgba, int width, int height)
+    {
+        if (width <= 0)
+            throw new ArgumentOutOfRangeException(nameof(width));
+        if (height <= 0)
+            throw new ArgumentOutOfRangeException(nameof(height));
+        if (rgba.Length != (long)width * height * 4)
+            throw new ArgumentException("Pixel data must be width * height * 4 bytes.", nameof(rgba));
+
+        byte* encoded;
+        UIntPtr length;
+        fixed (byte* ptr = rgba)
+        {
+            var status = NativeMethods.jxl_encode_lossless_rgba8(
+                ptr, (uint)width, (uint)height, &encoded, &length);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+
+        try
+        {
+            return new ReadOnlySpan<byte>(encoded, (int)(uint)length).ToArray();
+        }
+        finally
+        {
+            NativeMethods.jxl_free_buffer(encoded, length);
+        }
+    }
+
+    /// <summary>
+    /// Gets the number of leading bytes that always suffice for <see cref="CheckSignature(ReadOnlySpan{byte})"/>
+    /// to return a result other than <see cref="JxlSignature.NotEnoughBytes"/>.
+    /// </summary>
+    public static int SignatureMinBytes => (int)NativeMethods.jxl_signature_min_bytes();
+
     /// <summary>
     /// Checks if data appears to be a JPEG XL file.
     /// </summary>
     /// <param name="data">The data to check (only first 12 bytes are needed).</param>
     /// <returns>The signature check result.</returns>
-    public static unsafe JxlSignature CheckSignature(ReadOnlySpan<byte> data)
+    public static JxlSignature CheckSignature(ReadOnlySpan<byte> data)
+    {
+        return CheckSignature(data, out _);
+    }
+
+    /// <summary>
+    /// Checks if data appears to be a JPEG XL file and where parsing continues.
+    /// </summary>
+    /// <param name="data">The data to check (only first 12 bytes are needed).</param>
+    /// <param name="boxStart">
+    /// Receives, for a <see cref="JxlSignature.Container"/>, the offset of the first box after
+    /// the signature box; otherwise 0. Streaming demuxers can resume box parsing there.
+    /// </param>
+    /// <returns>The signature check result.</returns>
+    public static unsafe JxlSignature CheckSignature(ReadOnlySpan<byte> data, out int boxStart)
     {
+        nuint start;
+        JxlSignature signature;
         fixed (byte* ptr = data)
         {
-            return NativeMethods.jxl_signature_check(ptr, (UIntPtr)data.Length);
+            signature = NativeMethods.jxl_signature_check(ptr, (UIntPtr)data.Length, &start);
         }
+
+        boxStart = (int)start;
+        return signature;
     }
 
     /// <summary>
@@ -210,6 +479,74 @@ public sealed class JxlImage : IDisposable
         return sig == JxlSignature.Codestream || sig == JxlSignature.Container;
     }
 
+    /// <summary>
+    /// Determines whether JPEG XL data is animated from its image header alone.
+    /// </summary>
+    /// <param name="data">The start of the JXL file; the first few dozen bytes usually suffice.</param>
+    /// <param name="isAnimated">Receives whether the image is an animation.</param>
+    /// <returns>True if the header was parsed; false if <paramref name="data"/> doesn't contain all of it.</returns>
+    /// <remarks>
+    /// Only the image header is parsed, without creating a decoder, so directory scanners can
+    /// cheaply flag animations.
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if the data is not JPEG XL or the header is malformed.</exception>
+    public static unsafe bool TryPeekIsAnimated(ReadOnlySpan<byte> data, out bool isAnimated)
+    {
+        bool animated;
+        JxlStatus status;
+        fixed (byte* ptr = data)
+        {
+            status = NativeMethods.jxl_peek_is_animated(ptr, (UIntPtr)data.Length, &animated);
+        }
+
+        isAnimated = animated;
+        if (status == JxlStatus.NeedMoreInput)
+        {
+            return false;
+        }
+
+        JxlDecoder.ThrowIfFailed(status);
+        return true;
+    }
+
+    /// <summary>
+    /// Checks the container structure of complete JPEG XL data without decoding it.
+    /// </summary>
+    /// <param name="data">The complete JXL file.</param>
+    /// <remarks>
+    /// Only box headers are parsed, so malformed files can be rejected cheaply before decoding.
+    /// Truncated boxes, a missing or misplaced <c>ftyp</c> box, duplicate or misnumbered codestream
+    /// boxes and invalid compressed boxes are reported. The codestream itself is not checked, and
+    /// bare codestreams always pass.
+    /// </remarks>
+    /// <exception cref="JxlException">
+    /// Thrown if the container is malformed; the message names the offending box and its offset.
+    /// </exception>
+    public static unsafe void ValidateContainer(ReadOnlySpan<byte> data)
+    {
+        fixed (byte* ptr = data)
+        {
+            JxlDecoder.ThrowIfFailed(NativeMethods.jxl_validate_container(ptr, (UIntPtr)data.Length));
+        }
+    }
+
+    /// <summary>
+    /// Computes a CRC32 (IEEE) of JPEG XL data, for use as a cheap cache key.
+    /// </summary>
+    /// <param name="data">The JXL-encoded data.</param>
+    /// <param name="codestreamOnly">
+    /// If true, container boxes other than the codestream are skipped, so metadata edits
+    /// don't change the result and a container hashes the same as its bare codestream.
+    /// </param>
+    /// <returns>The CRC32, or 0 for empty data.</returns>
+    public static unsafe uint ComputeCrc32(ReadOnlySpan<byte> data, bool codestreamOnly = false)
+    {
+        fixed (byte* ptr = data)
+        {
+            return NativeMethods.jxl_signature_crc32(ptr, (UIntPtr)data.Length, codestreamOnly);
+        }
+    }
+
     /// <summary>
     /// Releases the pixel buffer.
     /// </summary>
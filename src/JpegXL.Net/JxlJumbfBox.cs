@@ -0,0 +1,53 @@
+namespace JpegXL.Net;
+
+/// <summary>
+/// Represents a box found in a JUMBF structure by <see cref="JxlMetadata.ParseJumbf"/>.
+/// </summary>
+/// <remarks>
+/// <see cref="Offset"/> and <see cref="Length"/> locate the box payload in the parsed data,
+/// so the content of a box can be sliced from the caller's buffer.
+/// </remarks>
+public readonly struct JxlJumbfBox
+{
+    /// <summary>
+    /// The four-character box type, e.g. <c>jumb</c>, <c>jumd</c> or <c>json</c>.
+    /// </summary>
+    public string Type { get; }
+
+    /// <summary>
+    /// The superbox nesting level, 0 for boxes at the top level of the parsed data.
+    /// </summary>
+    public int Depth { get; }
+
+    /// <summary>
+    /// The offset of the box payload, after its header, in the parsed data.
+    /// </summary>
+    public int Offset { get; }
+
+    /// <summary>
+    /// The length of the box payload in bytes.
+    /// </summary>
+    public int Length { get; }
+
+    /// <summary>
+    /// The label of a <c>jumb</c> superbox or its <c>jumd</c> description box, or null if unlabeled.
+    /// </summary>
+    public string? Label { get; }
+
+    /// <summary>
+    /// Creates a new JUMBF box entry.
+    /// </summary>
+    /// <param name="type">The four-character box type.</param>
+    /// <param name="depth">The superbox nesting level.</param>
+    /// <param name="offset">The offset of the box payload in the parsed data.</param>
+    /// <param name="length">The length of the box payload in bytes.</param>
+    /// <param name="label">The box label, or null if unlabeled.</param>
+    public JxlJumbfBox(string type, int depth, int offset, int length, string? label)
+    {
+        Type = type;
+        Depth = depth;
+        Offset = offset;
+        Length = length;
+        Label = label;
+    }
+}
@@ -0,0 +1,11 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license.
+
+namespace JpegXL.Net;
+
+/// <summary>
+/// Receives a diagnostic message from the native library; see <see cref="JxlImage.SetLogHandler"/>.
+/// </summary>
+/// <param name="level">The severity of the message.</param>
+/// <param name="message">The message text.</param>
+public delegate void JxlLogHandler(JxlLogLevel level, string message);
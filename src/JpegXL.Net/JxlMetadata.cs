@@ -3,6 +3,7 @@
 
 using System;
 using System.Runtime.InteropServices;
+using System.Text;
 
 namespace JpegXL.Net;
 
@@ -11,7 +12,8 @@ namespace JpegXL.Net;
 /// </summary>
 /// <remarks>
 /// <para>This class is accessed via <see cref="JxlDecoder.Metadata"/>.</para>
-/// <para><see cref="JxlDecoder.ReadInfo"/> must be called before accessing metadata.</para>
+/// <para><see cref="JxlDecoder.ReadInfo"/> or <see cref="Scan"/> must be called before accessing
+/// metadata.</para>
 /// </remarks>
 public sealed unsafe class JxlMetadata
 {
@@ -19,6 +21,29 @@ public sealed unsafe class JxlMetadata
 
     internal JxlMetadata(JxlDecoder decoder) => _decoder = decoder;
 
+    /// <summary>
+    /// Reads the metadata boxes that precede the codestream, without decoding the image header.
+    /// </summary>
+    /// <returns>True once all boxes before the codestream have been read; false if more input is needed.</returns>
+    /// <remarks>
+    /// <para>For fast metadata-only scans: this can be called right after setting input,
+    /// instead of <see cref="JxlDecoder.ReadInfo"/>. Only container box headers are parsed.</para>
+    /// <para>Boxes placed after the codestream are not found.</para>
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if the input is not a JPEG XL file or a box is malformed.</exception>
+    public bool Scan()
+    {
+        _decoder.ThrowIfDisposed();
+        var status = NativeMethods.jxl_decoder_scan_metadata(_decoder.Handle);
+        if (status == JxlStatus.NeedMoreInput)
+        {
+            return false;
+        }
+
+        JxlDecoder.ThrowIfFailed(status);
+        return true;
+    }
+
     // ========================================================================
     // EXIF
     // ========================================================================
@@ -59,12 +84,11 @@ public sealed unsafe class JxlMetadata
     {
         _decoder.ThrowIfDisposed();
 
-        byte* dataPtr;
         UIntPtr length;
         bool isCompressed;
 
         var status = NativeMethods.jxl_decoder_get_exif_box_at(
-            _decoder.Handle, (uint)index, &dataPtr, &length, &isCompressed);
+            _decoder.Handle, (uint)index, null, &length, &isCompressed);
 
         if (status == JxlStatus.Error || status == JxlStatus.InvalidArgument)
         {
@@ -73,9 +97,12 @@ public sealed unsafe class JxlMetadata
 
         JxlDecoder.ThrowIfFailed(status);
 
-        var len = (int)(nuint)length;
-        var data = new byte[len];
-        Marshal.Copy((IntPtr)dataPtr, data, 0, len);
+        // Copied natively so no pointer into decoder memory is held
+        var data = new byte[(int)(nuint)length];
+        fixed (byte* ptr = data)
+        {
+            NativeMethods.jxl_decoder_copy_exif_box_at(_decoder.Handle, (uint)index, ptr, length);
+        }
         return new JxlMetadataBox(data, isCompressed);
     }
 
@@ -198,4 +225,45 @@ public sealed unsafe class JxlMetadata
         Marshal.Copy((IntPtr)dataPtr, data, 0, len);
         return new JxlMetadataBox(data, isCompressed);
     }
+
+    /// <summary>
+    /// Lists the boxes of a JUMBF structure in file order.
+    /// </summary>
+    /// <param name="data">A whole <c>jumb</c> superbox, or JUMBF box data from <see cref="GetJumbfBox"/>.</param>
+    /// <returns>The boxes, with the contents of each superbox following it one <see cref="JxlJumbfBox.Depth"/> level deeper.</returns>
+    /// <remarks>
+    /// <para>Brotli-compressed JUMBF box data must be decompressed before parsing.</para>
+    /// <para>Superboxes are labeled with the label of their <c>jumd</c> description box, so content such as
+    /// C2PA manifests can be found without a full JUMBF parser.</para>
+    /// </remarks>
+    /// <exception cref="JxlException">Thrown if the data is empty or a box is malformed; the message names the offending box and its offset.</exception>
+    public static JxlJumbfBox[] ParseJumbf(ReadOnlySpan<byte> data)
+    {
+        fixed (byte* ptr = data)
+        {
+            var count = (int)(nuint)NativeMethods.jxl_jumbf_parse(ptr, (UIntPtr)data.Length, null, UIntPtr.Zero);
+            if (count == 0)
+            {
+                JxlDecoder.ThrowIfFailed(JxlStatus.Error);
+            }
+
+            var entries = new JxlJumbfEntry[count];
+            fixed (JxlJumbfEntry* entriesPtr = entries)
+            {
+                NativeMethods.jxl_jumbf_parse(ptr, (UIntPtr)data.Length, entriesPtr, (UIntPtr)count);
+
+                var boxes = new JxlJumbfBox[count];
+                for (var i = 0; i < count; i++)
+                {
+                    var entry = &entriesPtr[i];
+                    var type = Encoding.ASCII.GetString(entry->Type, 4);
+                    var label = entry->LabelLength > 0
+                        ? Encoding.UTF8.GetString(ptr + entry->LabelOffset, (int)entry->LabelLength)
+                        : null;
+                    boxes[i] = new JxlJumbfBox(type, (int)entry->Depth, (int)entry->Offset, (int)entry->Length, label);
+                }
+                return boxes;
+            }
+        }
+    }
 }
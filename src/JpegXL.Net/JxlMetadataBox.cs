@@ -6,7 +6,7 @@ namespace JpegXL.Net;
 /// <remarks>
 /// Metadata boxes can be stored uncompressed or brotli-compressed (brob boxes) in the file.
 /// When <see cref="IsBrotliCompressed"/> is true, the <see cref="Data"/> contains the raw
-/// brotli-compressed bytes. The caller is responsible for decompression if needed.
+/// brotli-compressed bytes; use <see cref="Decompress"/> to get the original metadata.
 /// </remarks>
 public readonly struct JxlMetadataBox
 {
@@ -15,7 +15,7 @@ public readonly struct JxlMetadataBox
     /// </summary>
     /// <remarks>
     /// If <see cref="IsBrotliCompressed"/> is true, this contains brotli-compressed data
-    /// that must be decompressed to access the original metadata.
+    /// that must be decompressed with <see cref="Decompress"/> to access the original metadata.
     /// </remarks>
     public byte[] Data { get; }
 
@@ -24,7 +24,7 @@ public readonly struct JxlMetadataBox
     /// </summary>
     /// <remarks>
     /// When true, the <see cref="Data"/> contains compressed bytes.
-    /// The caller must decompress the data to access the original metadata content.
+    /// Use <see cref="Decompress"/> to access the original metadata content.
     /// </remarks>
     public bool IsBrotliCompressed { get; }
 
@@ -38,4 +38,69 @@ public readonly struct JxlMetadataBox
         Data = data;
         IsBrotliCompressed = isBrotliCompressed;
     }
+
+    /// <summary>
+    /// The default limit on the decompressed size used by <see cref="Decompress()"/>: 16 MB.
+    /// </summary>
+    public const int DefaultMaxDecompressedSize = 16 * 1024 * 1024;
+
+    /// <summary>
+    /// Gets the original metadata content, decompressing it if the box was brotli-compressed.
+    /// </summary>
+    /// <returns>The decompressed data, or <see cref="Data"/> itself if the box was not compressed.</returns>
+    /// <remarks>
+    /// Uses the native library's brotli decompressor, so no managed Brotli implementation is needed.
+    /// The decompressed data is limited to <see cref="DefaultMaxDecompressedSize"/> bytes.
+    /// </remarks>
+    /// <exception cref="JxlException">
+    /// Thrown if the compressed data is not a valid brotli stream or decompresses to more than
+    /// <see cref="DefaultMaxDecompressedSize"/> bytes.
+    /// </exception>
+    public byte[] Decompress()
+    {
+        return Decompress(DefaultMaxDecompressedSize);
+    }
+
+    /// <summary>
+    /// Gets the original metadata content, decompressing it if the box was brotli-compressed.
+    /// </summary>
+    /// <param name="maxDecompressedSize">
+    /// The maximum size of the decompressed data in bytes. A small brotli stream can expand
+    /// enormously, so bound this when the file is untrusted.
+    /// </param>
+    /// <returns>The decompressed data, or <see cref="Data"/> itself if the box was not compressed.</returns>
+    /// <exception cref="ArgumentOutOfRangeException">Thrown if <paramref name="maxDecompressedSize"/> is not positive.</exception>
+    /// <exception cref="JxlException">
+    /// Thrown if the compressed data is not a valid brotli stream, or with
+    /// <see cref="JxlStatus.BufferTooSmall"/> if it decompresses to more than
+    /// <paramref name="maxDecompressedSize"/> bytes.
+    /// </exception>
+    public unsafe byte[] Decompress(int maxDecompressedSize)
+    {
+        if (maxDecompressedSize <= 0)
+            throw new ArgumentOutOfRangeException(nameof(maxDecompressedSize), "Limit must be positive");
+
+        if (!IsBrotliCompressed)
+        {
+            return Data;
+        }
+
+        byte* decompressed;
+        UIntPtr length;
+        fixed (byte* ptr = Data)
+        {
+            var status = NativeMethods.jxl_brotli_decompress(
+                ptr, (UIntPtr)Data.Length, (UIntPtr)maxDecompressedSize, &decompressed, &length);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+
+        try
+        {
+            return new ReadOnlySpan<byte>(decompressed, (int)(uint)length).ToArray();
+        }
+        finally
+        {
+            NativeMethods.jxl_free_buffer(decompressed, length);
+        }
+    }
 }
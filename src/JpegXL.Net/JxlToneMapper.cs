@@ -0,0 +1,80 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license.
+
+using System;
+
+namespace JpegXL.Net;
+
+/// <summary>
+/// HDR to SDR tone mapping for pixel data that did not come from a decoder.
+/// </summary>
+public static class JxlToneMapper
+{
+    /// <summary>
+    /// Tone maps interleaved linear RGB samples in place.
+    /// </summary>
+    /// <param name="rgb">Interleaved R, G, B samples where 1.0 equals <paramref name="sourceNits"/>.</param>
+    /// <param name="method">The tone mapping algorithm.</param>
+    /// <param name="sourceNits">Peak luminance of the input in cd/m² (nits).</param>
+    /// <param name="targetNits">Peak luminance of the target display in cd/m² (nits).</param>
+    /// <param name="luminances">
+    /// The three RGB luminance coefficients of the input primaries, or empty for BT.2020.
+    /// Ignored by <see cref="JxlToneMapMethod.Bt2446aPerceptual"/>.
+    /// </param>
+    /// <exception cref="ArgumentException">
+    /// Thrown if <paramref name="rgb"/> is not a multiple of 3 samples, or
+    /// <paramref name="luminances"/> is neither empty nor 3 values.
+    /// </exception>
+    /// <exception cref="JxlException">Thrown if tone mapping fails.</exception>
+    public static unsafe void ToneMap(
+        Span<float> rgb,
+        JxlToneMapMethod method,
+        float sourceNits,
+        float targetNits,
+        ReadOnlySpan<float> luminances = default)
+    {
+        if (rgb.Length % 3 != 0)
+        {
+            throw new ArgumentException("Sample count must be a multiple of 3.", nameof(rgb));
+        }
+        if (!luminances.IsEmpty && luminances.Length != 3)
+        {
+            throw new ArgumentException("Exactly 3 luminance coefficients are required.", nameof(luminances));
+        }
+
+        fixed (float* dataPtr = rgb)
+        fixed (float* lumPtr = luminances)
+        {
+            var status = NativeMethods.jxl_tone_map_rgb_f32(
+                method, lumPtr, sourceNits, targetNits, dataPtr, (UIntPtr)rgb.Length);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+    }
+    /// <summary>
+    /// Expands interleaved linear SDR RGB samples toward HDR in place.
+    /// </summary>
+    /// <param name="rgb">Interleaved R, G, B samples where 1.0 equals <paramref name="sourceNits"/>.</param>
+    /// <param name="sourceNits">Peak luminance of the SDR input in cd/m² (nits).</param>
+    /// <param name="targetNits">Peak luminance of the HDR output in cd/m² (nits).</param>
+    /// <remarks>
+    /// Inverts <see cref="ToneMap"/> with <see cref="JxlToneMapMethod.Bt2446aLinear"/> and
+    /// BT.2020 luminances: tone mapping from <paramref name="targetNits"/> down to
+    /// <paramref name="sourceNits"/> and then calling this method returns the original samples.
+    /// </remarks>
+    /// <exception cref="ArgumentException">Thrown if <paramref name="rgb"/> is not a multiple of 3 samples.</exception>
+    /// <exception cref="JxlException">Thrown if inverse tone mapping fails.</exception>
+    public static unsafe void InverseToneMap(Span<float> rgb, float sourceNits, float targetNits)
+    {
+        if (rgb.Length % 3 != 0)
+        {
+            throw new ArgumentException("Sample count must be a multiple of 3.", nameof(rgb));
+        }
+
+        fixed (float* dataPtr = rgb)
+        {
+            var status = NativeMethods.jxl_inverse_tone_map_bt2446a(
+                sourceNits, targetNits, dataPtr, (UIntPtr)rgb.Length);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+    }
+}
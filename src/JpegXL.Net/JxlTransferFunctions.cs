@@ -0,0 +1,57 @@
+// Copyright (c) the JPEG XL Project Authors. All rights reserved.
+// Use of this source code is governed by a BSD-style license.
+
+using System;
+
+namespace JpegXL.Net;
+
+/// <summary>
+/// Transfer function conversion for float pixel data, without a CMS.
+/// </summary>
+public static class JxlTransferFunctions
+{
+    /// <summary>
+    /// Re-encodes samples from one transfer function to another in place, e.g. linear to sRGB.
+    /// </summary>
+    /// <remarks>
+    /// Primaries are not converted, so the input and output must share the same primaries.
+    /// </remarks>
+    /// <param name="samples">Color samples to convert (exclude alpha).</param>
+    /// <param name="from">Transfer function the samples are currently encoded with.</param>
+    /// <param name="to">Transfer function to encode the samples with.</param>
+    /// <param name="intensityTarget">Nits represented by linear 1.0; used for PQ.</param>
+    /// <param name="fromGamma">Encoding gamma (e.g. 1/2.2) when <paramref name="from"/> is <see cref="JxlTransferFunctionType.Gamma"/>.</param>
+    /// <param name="toGamma">Encoding gamma (e.g. 1/2.2) when <paramref name="to"/> is <see cref="JxlTransferFunctionType.Gamma"/>.</param>
+    /// <exception cref="JxlException">Thrown if the intensity target or a gamma is invalid.</exception>
+    public static unsafe void Convert(
+        Span<float> samples,
+        JxlTransferFunctionType from,
+        JxlTransferFunctionType to,
+        float intensityTarget = 255f,
+        float fromGamma = 0f,
+        float toGamma = 0f)
+    {
+        fixed (float* dataPtr = samples)
+        {
+            var status = NativeMethods.jxl_apply_transfer_function(
+                dataPtr, (UIntPtr)samples.Length, ToRaw(from, fromGamma), ToRaw(to, toGamma), intensityTarget);
+            JxlDecoder.ThrowIfFailed(status);
+        }
+    }
+
+    private static JxlTransferFunctionRaw ToRaw(JxlTransferFunctionType type, float gamma) => new()
+    {
+        Tag = type switch
+        {
+            JxlTransferFunctionType.Bt709 => JxlTransferFunctionTag.Bt709,
+            JxlTransferFunctionType.Linear => JxlTransferFunctionTag.Linear,
+            JxlTransferFunctionType.Srgb => JxlTransferFunctionTag.Srgb,
+            JxlTransferFunctionType.Pq => JxlTransferFunctionTag.Pq,
+            JxlTransferFunctionType.Dci => JxlTransferFunctionTag.Dci,
+            JxlTransferFunctionType.Hlg => JxlTransferFunctionTag.Hlg,
+            JxlTransferFunctionType.Gamma => JxlTransferFunctionTag.Gamma,
+            _ => throw new ArgumentOutOfRangeException(nameof(type), type, null)
+        },
+        Gamma = gamma
+    };
+}
@@ -54,6 +54,27 @@ namespace JpegXL.Net
         [DllImport(__DllName, EntryPoint = "jxl_decoder_create_with_options", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern NativeDecoderHandle* jxl_decoder_create_with_options(JxlDecodeOptions* options);
 
+        /// <summary>
+        ///  Creates an independent decoder for the same input and configuration.
+        ///
+        ///  The clone gets its own copy of the input (borrowed input is copied too),
+        ///  the options, the pixel format, extra channel data formats and selected
+        ///  channel, and the settings that survive resets: the desired output color
+        ///  profile and soft proofing. It starts fresh at the beginning of the input,
+        ///  not at the source decoder's current position, so several clones can each
+        ///  skip to and decode different frames concurrently on separate threads. The
+        ///  source decoder is unchanged.
+        ///
+        ///  # Returns
+        ///  A new decoder that must be destroyed with `jxl_decoder_destroy`, or null
+        ///  if `decoder` is null or in use by a concurrent call.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_clone", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern NativeDecoderHandle* jxl_decoder_clone(NativeDecoderHandle* decoder);
+
         /// <summary>
         ///  Destroys a decoder instance and frees its resources.
         ///
@@ -67,15 +88,35 @@ namespace JpegXL.Net
         /// <summary>
         ///  Resets the decoder to its initial state, allowing it to decode a new image.
         ///
+        ///  Options and pixel format are kept; any output color profile is cleared.
+        ///
         ///  # Safety
         ///  The decoder pointer must be valid.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "jxl_decoder_reset", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern JxlStatus jxl_decoder_reset(NativeDecoderHandle* decoder);
 
+        /// <summary>
+        ///  Resets the decoder for a new image, keeping its configuration.
+        ///
+        ///  Like `jxl_decoder_reset`, clears input, decoder state and cached metadata,
+        ///  but also keeps the output color profile set with
+        ///  `jxl_decoder_set_output_color_profile`. It is re-applied when the next
+        ///  image reaches `HaveBasicInfo`, so pooled decoders handling same-config
+        ///  images need only one setup. If it cannot be applied to that image (for
+        ///  example, it needs a CMS that is not configured), `jxl_decoder_process`
+        ///  returns `Error`.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_reset_keep_config", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_reset_keep_config(NativeDecoderHandle* decoder);
+
         /// <summary>
         ///  Rewinds the decoder to the beginning of the input without clearing the data buffer.
         ///  This allows re-decoding the same input without calling SetInput again.
+        ///  A channel selected by `jxl_decoder_read_single_channel` stays selected.
         ///
         ///  # Safety
         ///  The decoder pointer must be valid.
@@ -97,6 +138,22 @@ namespace JpegXL.Net
         [DllImport(__DllName, EntryPoint = "jxl_decoder_append_input", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern JxlStatus jxl_decoder_append_input(NativeDecoderHandle* decoder, byte* data, System.UIntPtr size);
 
+        /// <summary>
+        ///  Sets input data that the decoder reads in place, without copying.
+        ///
+        ///  Resets the decoder, then decodes directly from `data`. This avoids holding a
+        ///  second copy of large inputs. `jxl_decoder_append_input` fails with
+        ///  `InvalidState` until the decoder is reset or given new input.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be a valid decoder pointer.
+        ///  - `data` must point to `size` readable bytes.
+        ///  - The buffer must stay valid and unmodified until the decoder is destroyed,
+        ///    reset, or given new input.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_input_borrowed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_set_input_borrowed(NativeDecoderHandle* decoder, byte* data, System.UIntPtr size);
+
         /// <summary>
         ///  Sets input data by reading directly from a file.
         ///
@@ -110,6 +167,63 @@ namespace JpegXL.Net
         [DllImport(__DllName, EntryPoint = "jxl_decoder_set_input_file", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern JxlStatus jxl_decoder_set_input_file(NativeDecoderHandle* decoder, byte* path);
 
+        /// <summary>
+        ///  Gets the number of input bytes the decoder has consumed so far.
+        ///
+        ///  Callers multiplexing a stream can use this to advance their own cursor.
+        ///  Resets to 0 on `jxl_decoder_reset`, `jxl_decoder_rewind` and when new
+        ///  input is set from a file.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_bytes_consumed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_decoder_bytes_consumed(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Checks whether input remains after the bytes consumed so far.
+        ///
+        ///  After `Complete`, this indicates trailing data such as another concatenated
+        ///  image or trailing metadata. The upstream decoder may read ahead while
+        ///  buffering, so short trailing data can already count as consumed.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_has_trailing_data", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_decoder_has_trailing_data(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Computes a CRC32 of the decoder's entire input buffer.
+        ///
+        ///  See `jxl_signature_crc32` for the meaning of `codestream_only`.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_input_crc32", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint jxl_decoder_input_crc32(NativeDecoderHandle* decoder, [MarshalAs(UnmanagedType.U1)] bool codestream_only);
+
+        /// <summary>
+        ///  Advances to the next JPEG XL signature at or after the consumed input,
+        ///  so a concatenated image can be decoded.
+        ///
+        ///  On success the decoder restarts at the signature, as if the input began
+        ///  there. `jxl_decoder_rewind` still returns to the start of the whole input.
+        ///  Codestream signatures are only two bytes, so arbitrary trailing data can
+        ///  produce false matches.
+        ///
+        ///  # Returns
+        ///  - `Success` if a signature was found.
+        ///  - `NeedMoreInput` if no complete signature is in the remaining input.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_skip_to_next_image", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_skip_to_next_image(NativeDecoderHandle* decoder);
+
         /// <summary>
         ///  Processes the current input data and returns the next decoder event.
         ///
@@ -123,12 +237,42 @@ namespace JpegXL.Net
         ///  - `Complete`: All frames decoded, decoding is finished
         ///  - `Error`: Check `jxl_get_last_error` for details
         ///
+        ///  For an image with frames the events are `HaveBasicInfo`, then
+        ///  `HaveFrameHeader`, `NeedOutputBuffer` and `FrameComplete` for each frame,
+        ///  then `Complete`. With the `MetadataOnly` option, `Complete` directly
+        ///  follows `HaveBasicInfo`, with no frame at all. Callers waiting for
+        ///  `NeedOutputBuffer` must treat it as the end, and buffer size queries then
+        ///  return 0. A codestream that ends right after the image header can't be
+        ///  told from a truncated one, so it returns `NeedMoreInput` rather than
+        ///  `Complete` or `Error`. Once returned, `Complete` is
+        ///  returned again by every later call. With the `MaxFrames` option, it
+        ///  follows the last allowed frame even if the image has more.
+        ///
+        ///  A decoder must only be used from one thread at a time. If this or a pixel
+        ///  read is called while another such call on the same decoder is still
+        ///  running, it fails with `Error` and `jxl_get_last_error_status` returns
+        ///  `Busy`; the running call is unaffected.
+        ///
         ///  # Safety
         ///  The decoder pointer must be valid.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "jxl_decoder_process", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern JxlDecoderEvent jxl_decoder_process(NativeDecoderHandle* decoder);
 
+        /// <summary>
+        ///  Continues with the next image of a concatenated multi-image stream.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `Complete`. The decoder
+        ///  restarts at the end of the current image without discarding the buffer,
+        ///  then processes as `jxl_decoder_process` does, so the next event is usually
+        ///  `HaveBasicInfo`, or `NeedMoreInput` if no further image data is buffered yet.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_next_image", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlDecoderEvent jxl_decoder_next_image(NativeDecoderHandle* decoder);
+
         /// <summary>
         ///  Gets the basic image info (streaming API).
         ///
@@ -144,7 +288,9 @@ namespace JpegXL.Net
         /// <summary>
         ///  Gets the current frame header (streaming API).
         ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveFrameHeader`.
+        ///  Valid after `jxl_decoder_process` returns `HaveFrameHeader`, and after the
+        ///  frame's pixels are read until the next frame header. `IsOpaque` is only
+        ///  refined by the alpha scan in the latter case.
         ///
         ///  # Safety
         ///  - `decoder` must be valid.
@@ -153,6 +299,41 @@ namespace JpegXL.Net
         [DllImport(__DllName, EntryPoint = "jxl_decoder_get_frame_header", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern JxlStatus jxl_decoder_get_frame_header(NativeDecoderHandle* decoder, JxlFrameHeader* header);
 
+        /// <summary>
+        ///  Gets the current frame's duration in animation ticks.
+        ///
+        ///  `JxlFrameHeader.DurationMs` is a rounded `f32`; players that schedule
+        ///  frames exactly should use this together with `Animation.TpsNumerator` and
+        ///  `Animation.TpsDenominator` from the basic info, where one tick lasts
+        ///  `TpsDenominator / TpsNumerator` seconds.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `HaveFrameHeader`.
+        ///  Returns 0 if no frame header is available, the image is not animated or
+        ///  the frame is the preview.
+        ///
+        ///  # Safety
+        ///  `decoder` must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_frame_duration_ticks", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint jxl_decoder_get_frame_duration_ticks(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Gets the current frame header with the fields of `JxlFrameHeaderEx`, for
+        ///  compositors and other callers that need more than `JxlFrameHeader`.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `HaveFrameHeader`.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidState` if no frame header is available.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `header` must point to a writable `JxlFrameHeaderEx`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_frame_header_ex", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_frame_header_ex(NativeDecoderHandle* decoder, JxlFrameHeaderEx* header);
+
         /// <summary>
         ///  Gets the current frame's name.
         ///
@@ -168,6 +349,10 @@ namespace JpegXL.Net
         ///  The number of bytes written, or the required buffer size if buffer is null or too small.
         ///  Returns 0 if no frame header is available or the frame has no name.
         ///
+        ///  The name is valid UTF-8 and is only copied whole, so a too-small buffer is
+        ///  left untouched rather than receiving a split multi-byte sequence. Use
+        ///  `jxl_decoder_get_frame_name_utf16_len` to size UTF-16 buffers.
+        ///
         ///  # Safety
         ///  - `decoder` must be valid.
         ///  - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
@@ -175,6 +360,22 @@ namespace JpegXL.Net
         [DllImport(__DllName, EntryPoint = "jxl_decoder_get_frame_name", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern uint jxl_decoder_get_frame_name(NativeDecoderHandle* decoder, byte* buffer, uint buffer_size);
 
+        /// <summary>
+        ///  Gets the length of the current frame's name in UTF-16 code units.
+        ///
+        ///  `jxl_decoder_get_frame_name` and `JxlFrameHeader.NameLength` count UTF-8
+        ///  bytes, which differs from the UTF-16 length for non-ASCII names. Callers
+        ///  converting to UTF-16 strings (e.g. .NET) can size buffers from this.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `HaveFrameHeader`.
+        ///  Returns 0 if no frame header is available or the frame has no name.
+        ///
+        ///  # Safety
+        ///  `decoder` must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_frame_name_utf16_len", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint jxl_decoder_get_frame_name_utf16_len(NativeDecoderHandle* decoder);
+
         /// <summary>
         ///  Decodes pixels into the provided buffer (streaming API).
         ///
@@ -182,6 +383,10 @@ namespace JpegXL.Net
         ///  After successful completion, call `jxl_decoder_process` again to
         ///  get `FrameComplete` or continue with the next frame.
         ///
+        ///  With the `ContinueOnFrameError` option, a frame that fails to decode
+        ///  returns `FrameError` instead of `Error` when it can be skipped; the
+        ///  buffer contents are then unspecified.
+        ///
         ///  # Safety
         ///  - `decoder` must be valid.
         ///  - `buffer` must be valid for writes of `buffer_size` bytes.
@@ -219,6 +424,11 @@ namespace JpegXL.Net
         /// <summary>
         ///  Calculates the required buffer size for a specific extra channel.
         ///
+        ///  Samples are in the channel's data format, see
+        ///  `jxl_decoder_set_extra_channel_format`. Channels stored at reduced
+        ///  resolution (`JxlExtraChannelInfo.DimShift`) are upsampled while decoding,
+        ///  so the buffer always covers the full image.
+        ///
         ///  # Arguments
         ///  * `decoder` - The decoder instance.
         ///  * `index` - The extra channel index (0-based).
@@ -238,32 +448,161 @@ namespace JpegXL.Net
         ///  The first buffer receives color data (RGB/RGBA/etc.), subsequent buffers
         ///  receive extra channels in order. Set buffer to null to skip that channel.
         ///
+        ///  When the color type has alpha, the first alpha channel is the one in the
+        ///  color output and its extra buffer is left untouched, unless the
+        ///  `SeparateFirstAlpha` option is set, in which case the buffer receives a
+        ///  copy of that alpha. Any further alpha channels are written to their own
+        ///  buffers like other extra channels.
+        ///
+        ///  Returns `FrameError` for a skipped frame like `jxl_decoder_read_pixels`.
+        ///
         ///  # Arguments
         ///  * `decoder` - The decoder instance.
         ///  * `color_buffer` - Output buffer for color data.
         ///  * `color_buffer_size` - Size of color buffer in bytes.
         ///  * `extra_buffers` - Array of pointers to extra channel buffers (can contain nulls to skip).
         ///  * `extra_buffer_sizes` - Array of buffer sizes for each extra channel.
-        ///  * `num_extra_buffers` - Number of extra buffers provided.
+        ///  * `num_extra_buffers` - Number of extra buffers provided, at most the
+        ///    number of extra channels; channels past the last buffer are skipped.
+        ///    With no extra channels, this behaves like `jxl_decoder_read_pixels`.
+        ///
+        ///  * `buffers_written` - Optional output array of `1 + num_extra_buffers`
+        ///    flags, set to whether the color buffer and each extra buffer received
+        ///    data. An extra buffer is written only when it is non-null and its
+        ///    channel is decoded, so null buffers, the alpha channel in the color
+        ///    output (without `SeparateFirstAlpha`), and channels not decoded without
+        ///    `DecodeExtraChannels` all report false. All flags are false unless `FrameComplete` is returned.
+        ///    May be null.
+        ///
+        ///  Returns `Error` if more buffers than extra channels are passed, or if a
+        ///  non-null buffer for a channel that is output is smaller than
+        ///  `jxl_decoder_get_extra_channel_buffer_size`.
         ///
         ///  # Safety
         ///  - `decoder` must be valid.
         ///  - `color_buffer` must be valid for writes of `color_buffer_size` bytes.
         ///  - `extra_buffers` must point to `num_extra_buffers` pointers.
         ///  - Each non-null buffer must be valid for writes of its corresponding size.
+        ///  - `buffers_written` must be null or valid for writes of
+        ///    `1 + num_extra_buffers` flags.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "jxl_decoder_read_pixels_with_extra_channels", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern JxlDecoderEvent jxl_decoder_read_pixels_with_extra_channels(NativeDecoderHandle* decoder, byte* color_buffer, System.UIntPtr color_buffer_size, byte** extra_buffers, System.UIntPtr* extra_buffer_sizes, System.UIntPtr num_extra_buffers);
+        public static extern JxlDecoderEvent jxl_decoder_read_pixels_with_extra_channels(NativeDecoderHandle* decoder, byte* color_buffer, System.UIntPtr color_buffer_size, byte** extra_buffers, System.UIntPtr* extra_buffer_sizes, System.UIntPtr num_extra_buffers, bool* buffers_written);
+
+        /// <summary>
+        ///  Decodes a single extra channel, such as alpha, into `buffer`.
+        ///
+        ///  Call this after `jxl_decoder_process` returns `NeedOutputBuffer`, in place
+        ///  of `jxl_decoder_read_pixels`. The channel is written as packed rows in its
+        ///  data format (see `jxl_decoder_set_extra_channel_format`), sized by
+        ///  `jxl_decoder_get_extra_channel_buffer_size`. Use
+        ///  `jxl_decoder_get_extra_channel_info` to find the alpha channel's index.
+        ///
+        ///  A frame's output layout is fixed when its header is read, so this also
+        ///  selects the channel for later frames: from the next frame on, upstream is
+        ///  set up to output only that channel and no color, and frames must be read
+        ///  with this function until the pixel format is set or the decoder is reset;
+        ///  a rewind keeps the selection. The frame
+        ///  already under way still has its color decoded, into scratch space, and
+        ///  the channel must be one it outputs: the first alpha channel when the
+        ///  color type has alpha, or any channel with `DecodeExtraChannels`.
+        ///  Even without color output, upstream may reconstruct color channels that
+        ///  the rendering of the selected channel depends on, so the time saved
+        ///  varies by image.
+        ///
+        ///  Returns `FrameError` for a skipped frame like `jxl_decoder_read_pixels`.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `channel_index` - The extra channel index (0-based).
+        ///  * `buffer` - Output buffer for the channel.
+        ///  * `buffer_size` - Size of `buffer` in bytes.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `buffer` must be valid for writes of `buffer_size` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_read_single_channel", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlDecoderEvent jxl_decoder_read_single_channel(NativeDecoderHandle* decoder, uint channel_index, byte* buffer, System.UIntPtr buffer_size);
 
         /// <summary>
         ///  Sets the desired output pixel format.
         ///
+        ///  Also clears a channel selected by `jxl_decoder_read_single_channel`, so
+        ///  later frames output color again.
+        ///
+        ///  Once the image header is read, a color type the image can't be output in
+        ///  is rejected with `InvalidArgument`: gray output needs a grayscale image, as
+        ///  color images are never converted to gray. A format set before that, such
+        ///  as `JxlDecodeOptions.PixelFormat`, is checked when the first frame is set
+        ///  up instead, where `jxl_decoder_process` fails with the same status.
+        ///
         ///  # Safety
         ///  The decoder pointer must be valid.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "jxl_decoder_set_pixel_format", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern JxlStatus jxl_decoder_set_pixel_format(NativeDecoderHandle* decoder, JxlPixelFormat* format);
 
+        /// <summary>
+        ///  Sets the output pixel format of the preview frame, or clears it when
+        ///  `format` is null so the preview uses the main pixel format.
+        ///
+        ///  Lets a thumbnail be output as e.g. `Uint8` RGBA while the main image is
+        ///  decoded as float. The preview is output as the first frame unless
+        ///  `SkipPreview` is set; `jxl_decoder_get_buffer_size` reports its size in
+        ///  this format while it is the next or current frame. The setting is kept
+        ///  across resets and rewinds, like the main pixel format.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if the image can't be output in the color type.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `format` must be null or point to a valid `JxlPixelFormat`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_preview_pixel_format", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_set_preview_pixel_format(NativeDecoderHandle* decoder, JxlPixelFormat* format);
+
+        /// <summary>
+        ///  Sets the data format of one extra channel, in place of the pixel format's.
+        ///
+        ///  Lets each channel be output at the precision it needs, e.g. a depth map
+        ///  as `Float32` next to a selection mask as `Uint8`. This also applies to
+        ///  `Optional` and `Unknown` channels, whose contents the image doesn't
+        ///  describe. The pixel format's endianness is used. The alpha channel in the
+        ///  color output keeps the pixel format's data format.
+        ///
+        ///  Like `jxl_decoder_set_pixel_format`, this takes effect from the next frame
+        ///  header. The setting is kept across rewinds and cleared by a reset.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidState` if the image header has not been read.
+        ///  - `InvalidArgument` if `index` is out of range.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_extra_channel_format", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_set_extra_channel_format(NativeDecoderHandle* decoder, uint index, JxlDataFormat data_format);
+
+        /// <summary>
+        ///  Gets the effective bit depth of decoded output in the current pixel format.
+        ///
+        ///  Integer formats report at most the source precision, e.g. 10 bits for a
+        ///  10-bit image decoded to `Uint16`; float formats report their own layout.
+        ///  Samples span the format's full range unless the `ScaleToFullRange` option
+        ///  is off, in which case integer samples are at most 2^bits - 1.
+        ///  Must be called after basic info is available (after `HaveBasicInfo` event).
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `bit_depth` must point to a writable `JxlBitDepth`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_output_bit_depth", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_output_bit_depth(NativeDecoderHandle* decoder, JxlBitDepth* bit_depth);
+
         /// <summary>
         ///  Gets the number of extra channels.
         ///
@@ -286,6 +625,13 @@ namespace JpegXL.Net
         /// <summary>
         ///  Calculates the required buffer size for decoded pixels.
         ///
+        ///  With `PreferPreview`, this is the preview's size until the preview frame
+        ///  has been read or skipped. Returns 0 when no frame will be decoded, as with
+        ///  `MetadataOnly`, where `jxl_decoder_process` returns `Complete` right after
+        ///  `HaveBasicInfo`.
+        ///  Also returns 0 if the size overflows `usize`, as the dimensions of a huge
+        ///  or malicious header can on 32-bit targets; reading pixels then fails.
+        ///
         ///  # Safety
         ///  `decoder` must be valid and basic info must be available (after `HaveBasicInfo` event).
         /// </summary>
@@ -293,145 +639,710 @@ namespace JpegXL.Net
         public static extern System.UIntPtr jxl_decoder_get_buffer_size(NativeDecoderHandle* decoder);
 
         /// <summary>
-        ///  Gets the embedded color profile from the image.
-        ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///  Calculates the buffer size for decoded pixels with each row padded to a
+        ///  multiple of `alignment` bytes.
         ///
-        ///  # Arguments
-        ///  * `decoder` - The decoder instance.
-        ///  * `profile_out` - Output for the profile raw data.
-        ///  * `icc_data_out` - Output pointer for ICC data (only set if profile is ICC type).
-        ///  * `handle_out` - Output for the profile handle (for calling helper methods).
+        ///  Use this to size a buffer for a decoder created with `RowAlignment` set to
+        ///  `alignment`; rows then start `stride = size / height` bytes apart. Only
+        ///  row lengths are padded: for aligned rows the buffer itself must also be
+        ///  allocated at that alignment. An `alignment` of 0 or 1 gives the packed
+        ///  size. Returns 0 if the size overflows `usize`.
         ///
         ///  # Safety
-        ///  - `decoder` must be valid.
-        ///  - `profile_out` must point to a writable `JxlColorProfileRaw`.
-        ///  - `icc_data_out` must point to a writable pointer.
-        ///  - `handle_out` must point to a writable pointer.
+        ///  `decoder` must be valid and basic info must be available (after `HaveBasicInfo` event).
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_embedded_color_profile", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern JxlStatus jxl_decoder_get_embedded_color_profile(NativeDecoderHandle* decoder, JxlColorProfileRaw* profile_out, byte** icc_data_out, JxlColorProfileHandle** handle_out);
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_aligned_buffer_size", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_decoder_get_aligned_buffer_size(NativeDecoderHandle* decoder, uint alignment);
 
         /// <summary>
-        ///  Gets the current output color profile.
+        ///  Calculates the buffer size for the decoded preview, in the format set with
+        ///  `jxl_decoder_set_preview_pixel_format` or else the main pixel format.
         ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///  Unlike `jxl_decoder_get_buffer_size`, this is available whichever frame
+        ///  is next, so a thumbnail buffer can be sized right after `HaveBasicInfo`.
+        ///  Returns 0 if the image has no preview, `SkipPreview` is set without
+        ///  `PreferPreview`, or the size overflows `usize`.
         ///
         ///  # Safety
-        ///  Same as `jxl_decoder_get_embedded_color_profile`.
+        ///  `decoder` must be valid and basic info must be available (after `HaveBasicInfo` event).
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_output_color_profile", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern JxlStatus jxl_decoder_get_output_color_profile(NativeDecoderHandle* decoder, JxlColorProfileRaw* profile_out, byte** icc_data_out, JxlColorProfileHandle** handle_out);
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_preview_buffer_size", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_decoder_get_preview_buffer_size(NativeDecoderHandle* decoder);
 
         /// <summary>
-        ///  Sets the output color profile for decoding.
+        ///  Gets the number of pixels clipped at the target peak by tone mapping.
         ///
-        ///  Must be called after `HaveBasicInfo` and before decoding pixels.
+        ///  Counts accumulate over all frames decoded since the decoder was created,
+        ///  reset, or rewound. Requires the `ToneMapClipStats` option; with a
+        ///  non-tone-mapping `CmsType`, or for images that need no tone mapping,
+        ///  both counts stay 0.
         ///
         ///  # Arguments
-        ///  * `decoder` - The decoder instance.
-        ///  * `profile` - The color profile raw data.
-        ///  * `icc_data` - ICC data pointer (required if profile tag is Icc).
+        ///  * `clipped_out` - Receives the number of pixels whose mapped luminance
+        ///    reached the target peak.
+        ///  * `total_out` - Receives the total number of tone-mapped pixels.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidState` if the `ToneMapClipStats` option was not enabled.
         ///
         ///  # Safety
-        ///  - `decoder` must be valid.
-        ///  - `profile` must point to a valid `JxlColorProfileRaw`.
-        ///  - If profile is ICC, `icc_data` must point to `profile.IccLength` bytes.
+        ///  - `decoder` must be a valid decoder pointer.
+        ///  - `clipped_out` and `total_out` must be valid for writes.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_output_color_profile", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern JxlStatus jxl_decoder_set_output_color_profile(NativeDecoderHandle* decoder, JxlColorProfileRaw* profile, byte* icc_data);
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_tone_map_clip_stats", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_tone_map_clip_stats(NativeDecoderHandle* decoder, ulong* clipped_out, ulong* total_out);
 
         /// <summary>
-        ///  Frees a color profile handle.
+        ///  Checks whether the CMS failed and was bypassed with the
+        ///  `CmsErrorFallback` option.
+        ///
+        ///  When true, pixels decoded since the decoder was created, reset, or
+        ///  rewound may be in the image's own color encoding rather than the output
+        ///  color profile, and the CMS error is available from `jxl_get_last_error`.
         ///
         ///  # Safety
-        ///  The handle must have been created by a color profile function.
+        ///  The decoder pointer must be valid.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_free", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern void jxl_color_profile_free(JxlColorProfileHandle* handle);
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_cms_fell_back", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_decoder_cms_fell_back(NativeDecoderHandle* decoder);
 
         /// <summary>
-        ///  Clones a color profile handle.
+        ///  Gets the number of color conversions the CMS has set up since the decoder
+        ///  was created, reset, or rewound.
         ///
-        ///  # Returns
-        ///  A new handle that must be freed with `jxl_color_profile_free`, or null on failure.
+        ///  Images output in their embedded color encoding, such as an sRGB image
+        ///  decoded to sRGB, need no CMS: conversions between profiles with the same
+        ///  color encoding are skipped without building any transform, unless a
+        ///  tone-mapping `CmsType` has HDR input to map. This count shows whether the
+        ///  CMS was bypassed.
         ///
         ///  # Safety
-        ///  The handle must be valid.
+        ///  The decoder pointer must be valid.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_clone", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern JxlColorProfileHandle* jxl_color_profile_clone(JxlColorProfileHandle* handle);
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_cms_setup_count", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern ulong jxl_decoder_get_cms_setup_count(NativeDecoderHandle* decoder);
 
         /// <summary>
-        ///  Attempts to get ICC profile data from a color profile.
+        ///  Decodes only the first frame of an image in a single call.
         ///
-        ///  Returns true if ICC data is available (either native or converted).
+        ///  Intended for viewers that need a still image from a (possibly animated)
+        ///  file. Decoding stops after frame 0; remaining frames are never parsed.
+        ///  Orientation and color management follow `options` exactly as for the
+        ///  streaming API.
         ///
         ///  # Arguments
-        ///  * `handle` - The color profile handle.
-        ///  * `data_out` - Output pointer for ICC data.
-        ///  * `length_out` - Output for ICC data length.
+        ///  * `data` - The complete JXL file contents.
+        ///  * `size` - Size of `data` in bytes.
+        ///  * `options` - Decoder options, or null to use defaults. The output layout
+        ///    is taken from `options.PixelFormat`.
+        ///  * `buffer` - Output buffer for the decoded pixels, or null to query the size.
+        ///  * `buffer_size` - Size of `buffer` in bytes.
+        ///  * `out_info` - Output for the basic image info, or null if not needed.
+        ///
+        ///  # Returns
+        ///  - `Success` if the first frame was decoded into `buffer`.
+        ///  - `BufferTooSmall` if `buffer` is null or smaller than required. `out_info`
+        ///    is still filled, so the required size can be computed from it.
+        ///  - `NeedMoreInput` if `data` is truncated.
+        ///  - `InvalidArgument` if `data` is null or empty.
+        ///  - `Error` on decode failure. Call `jxl_get_last_error` for details.
         ///
         ///  # Safety
-        ///  - `handle` must be valid.
-        ///  - `data_out` and `length_out` must be writable.
+        ///  - `data` must be valid for reads of `size` bytes.
+        ///  - If `options` is not null, it must point to a valid `JxlDecodeOptions` struct.
+        ///  - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
+        ///  - If `out_info` is not null, it must point to a writable `JxlBasicInfoRaw`.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_try_as_icc", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        public static extern bool jxl_color_profile_try_as_icc(JxlColorProfileHandle* handle, byte** data_out, System.UIntPtr* length_out);
+        [DllImport(__DllName, EntryPoint = "jxl_decode_first_frame", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decode_first_frame(byte* data, System.UIntPtr size, JxlDecodeOptions* options, byte* buffer, System.UIntPtr buffer_size, JxlBasicInfoRaw* out_info);
 
         /// <summary>
-        ///  Gets the number of color channels for a profile.
+        ///  Decodes the first frame into crate-allocated memory.
+        ///
+        ///  Like `jxl_decode_first_frame`, but the output buffer is allocated by the
+        ///  library, so callers don't need to query the size first. On success the
+        ///  caller owns the buffer and must release it with `jxl_free_buffer`.
+        ///
+        ///  # Arguments
+        ///  * `data` - The complete JXL file contents.
+        ///  * `size` - Size of `data` in bytes.
+        ///  * `options` - Decoder options, or null to use defaults.
+        ///  * `out_ptr` - Receives a pointer to the decoded pixels.
+        ///  * `out_len` - Receives the length of the pixel buffer in bytes.
+        ///  * `out_info` - Output for the basic image info, or null if not needed.
         ///
         ///  # Returns
-        ///  1 for grayscale, 3 for RGB, 4 for CMYK.
-        /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_channels", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern uint jxl_color_profile_channels(JxlColorProfileHandle* handle);
-
-        /// <summary>
-        ///  Checks if a profile represents a CMYK color space.
+        ///  - `Success` on success. `*out_ptr` and `*out_len` describe the buffer.
+        ///  - `InvalidArgument` if `data` is empty or an output pointer is null.
+        ///  - `NeedMoreInput` if `data` is truncated.
+        ///  - `Error` on decode failure. Call `jxl_get_last_error` for details.
+        ///
+        ///  On failure `*out_ptr` is set to null and `*out_len` to 0.
+        ///
+        ///  # Safety
+        ///  - `data` must be valid for reads of `size` bytes.
+        ///  - If `options` is not null, it must point to a valid `JxlDecodeOptions` struct.
+        ///  - `out_ptr` and `out_len` must be valid for writes.
+        ///  - If `out_info` is not null, it must point to a writable `JxlBasicInfoRaw`.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_is_cmyk", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        public static extern bool jxl_color_profile_is_cmyk(JxlColorProfileHandle* handle);
+        [DllImport(__DllName, EntryPoint = "jxl_decode_alloc", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decode_alloc(byte* data, System.UIntPtr size, JxlDecodeOptions* options, byte** out_ptr, System.UIntPtr* out_len, JxlBasicInfoRaw* out_info);
 
         /// <summary>
-        ///  Checks if the decoder can output to this profile without a CMS.
+        ///  Decodes every frame of an animation in a single call, passing each to
+        ///  `frame_cb`.
+        ///
+        ///  A "play this animation" primitive for media players: frames are decoded in
+        ///  order into an internal buffer, and `frame_cb` is called once per frame
+        ///  with its index, pixels, size and duration in milliseconds, plus
+        ///  `user_data`. Returning false from `frame_cb` stops decoding. Still images
+        ///  produce a single frame with a duration of 0; with `PreferPreview` the
+        ///  preview comes first, as frame 0.
+        ///
+        ///  The pixels follow `options.PixelFormat` with tightly packed rows
+        ///  (`options.RowAlignment` is ignored), and are only valid during the
+        ///  callback. With `ContinueOnFrameError`, a frame that fails to decode is
+        ///  skipped without a callback, and its index is not reused.
+        ///
+        ///  # Arguments
+        ///  * `data` - The complete JXL file contents.
+        ///  * `size` - Size of `data` in bytes.
+        ///  * `options` - Decoder options, or null to use defaults.
+        ///  * `frame_cb` - Called with each decoded frame.
+        ///  * `user_data` - Passed through to `frame_cb`.
+        ///
+        ///  # Returns
+        ///  - `Success` once all frames were delivered, or `frame_cb` returned false.
+        ///  - `InvalidArgument` if `data` is empty or `frame_cb` is null.
+        ///  - `NeedMoreInput` if `data` is truncated. Frames before the cut have been
+        ///    delivered.
+        ///  - `Error` on decode failure. Call `jxl_get_last_error` for details.
+        ///
+        ///  # Safety
+        ///  - `data` must be valid for reads of `size` bytes.
+        ///  - If `options` is not null, it must point to a valid `JxlDecodeOptions` struct.
+        ///  - `frame_cb` must be safe to call with `user_data`, and must not keep the
+        ///    pixel pointer after returning.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_can_output_to", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        public static extern bool jxl_color_profile_can_output_to(JxlColorProfileHandle* handle);
+        [DllImport(__DllName, EntryPoint = "jxl_decode_animation", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decode_animation(byte* data, System.UIntPtr size, JxlDecodeOptions* options, delegate* unmanaged[Cdecl]<uint, byte*, uint, uint, float, void*, bool> frame_cb, void* user_data);
 
         /// <summary>
-        ///  Checks if two profiles represent the same color encoding.
+        ///  Frees a buffer returned by `jxl_decode_alloc`, `jxl_decode_rgba8_srgb`,
+        ///  `jxl_thumbnail`, `jxl_encode_lossless_rgba8` or `jxl_brotli_decompress`.
+        ///
+        ///  # Safety
+        ///  `ptr` and `len` must be exactly the values returned by the allocating call,
+        ///  and the buffer must not be freed twice. Passing a null `ptr` is a no-op.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_same_color_encoding", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        public static extern bool jxl_color_profile_same_color_encoding(JxlColorProfileHandle* handle_a, JxlColorProfileHandle* handle_b);
+        [DllImport(__DllName, EntryPoint = "jxl_free_buffer", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_free_buffer(byte* ptr, System.UIntPtr len);
 
         /// <summary>
-        ///  Creates a copy of a profile with linear transfer function.
+        ///  Decodes a small sRGB RGBA8 preview of an image in a single call.
+        ///
+        ///  Intended for file-manager thumbnailers. The first frame is decoded with
+        ///  default options (orientation applied), converted to sRGB, and box-filtered
+        ///  down to fit within `max_dim`x`max_dim` while preserving the aspect ratio.
+        ///  Averaging happens in linear light, so fine detail keeps its brightness.
+        ///  Images that already fit are returned at full size. The decoder has no
+        ///  DC-only mode, so the whole frame is decoded before downscaling; cost is
+        ///  that of a full first-frame decode.
+        ///
+        ///  On success the caller owns the buffer and must release it with
+        ///  `jxl_free_buffer`, passing `*out_len`.
+        ///
+        ///  # Arguments
+        ///  * `data` - The complete JXL file contents.
+        ///  * `size` - Size of `data` in bytes.
+        ///  * `max_dim` - Maximum width and height of the thumbnail, in pixels.
+        ///  * `out_ptr` - Receives a pointer to the RGBA8 pixels (tightly packed).
+        ///  * `out_w` - Receives the thumbnail width.
+        ///  * `out_h` - Receives the thumbnail height.
+        ///  * `out_len` - Receives the length of the pixel buffer in bytes.
         ///
         ///  # Returns
-        ///  A new handle, or null if not possible (e.g., for ICC profiles).
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if `data` is empty, `max_dim` is 0, or an output
+        ///    pointer is null.
+        ///  - `NeedMoreInput` if `data` is truncated.
+        ///  - `Error` on decode failure, including images that need a CMS to reach
+        ///    sRGB in builds without one. Call `jxl_get_last_error` for details.
+        ///
+        ///  On failure `*out_ptr` is set to null and the other outputs to 0.
+        ///
+        ///  # Safety
+        ///  - `data` must be valid for reads of `size` bytes.
+        ///  - `out_ptr`, `out_w`, `out_h` and `out_len` must be valid for writes.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_with_linear_tf", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern JxlColorProfileHandle* jxl_color_profile_with_linear_tf(JxlColorProfileHandle* handle);
+        [DllImport(__DllName, EntryPoint = "jxl_thumbnail", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_thumbnail(byte* data, System.UIntPtr size, uint max_dim, byte** out_ptr, uint* out_w, uint* out_h, System.UIntPtr* out_len);
 
         /// <summary>
-        ///  Gets the transfer function from a simple color profile.
+        ///  Decodes the first frame to sRGB RGBA8 in a single call.
+        ///
+        ///  A turnkey decode for game and UI loaders that upload textures: whatever
+        ///  the source color space, bit depth or channel layout, the output is 8-bit
+        ///  straight-alpha RGBA in sRGB (gray images are expanded), upright
+        ///  (orientation applied), with tightly packed rows. HDR images are tone
+        ///  mapped if `options.CmsType` selects a tone-mapping CMS.
+        ///
+        ///  On success the caller owns the buffer and must release it with
+        ///  `jxl_free_buffer`, passing `*out_len`.
+        ///
+        ///  # Arguments
+        ///  * `data` - The complete JXL file contents.
+        ///  * `size` - Size of `data` in bytes.
+        ///  * `options` - Decoder options, or null to use defaults. `PixelFormat`,
+        ///    `RowAlignment` and `AdjustOrientation` are ignored, and a `None` CMS
+        ///    is replaced by lcms2.
+        ///  * `out_ptr` - Receives a pointer to the RGBA8 pixels.
+        ///  * `out_w` - Receives the image width.
+        ///  * `out_h` - Receives the image height.
+        ///  * `out_len` - Receives the length of the pixel buffer in bytes.
         ///
         ///  # Returns
-        ///  True if the profile has a transfer function, false otherwise (ICC or XYB).
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if `data` is empty or an output pointer is null.
+        ///  - `NeedMoreInput` if `data` is truncated.
+        ///  - `Error` on decode failure. Call `jxl_get_last_error` for details.
+        ///
+        ///  On failure `*out_ptr` is set to null and the other outputs to 0.
+        ///
+        ///  # Safety
+        ///  - `data` must be valid for reads of `size` bytes.
+        ///  - If `options` is not null, it must point to a valid `JxlDecodeOptions` struct.
+        ///  - `out_ptr`, `out_w`, `out_h` and `out_len` must be valid for writes.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_profile_get_transfer_function", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        public static extern bool jxl_color_profile_get_transfer_function(JxlColorProfileHandle* handle, JxlTransferFunctionRaw* tf_out);
+        [DllImport(__DllName, EntryPoint = "jxl_decode_rgba8_srgb", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decode_rgba8_srgb(byte* data, System.UIntPtr size, JxlDecodeOptions* options, byte** out_ptr, uint* out_w, uint* out_h, System.UIntPtr* out_len);
+
+        /// <summary>
+        ///  Encodes 8-bit RGBA pixels as a lossless JPEG XL file.
+        ///
+        ///  The pixels are written as sRGB with an 8-bit alpha channel in a single
+        ///  modular frame, and decode back bit-exactly. Compression is minimal; this
+        ///  is meant for re-saving decoded or edited pixels, not as a general encoder.
+        ///
+        ///  On success the caller owns the buffer and must release it with
+        ///  `jxl_free_buffer`, passing `*out_len`.
+        ///
+        ///  # Arguments
+        ///  * `data` - RGBA8 pixels, tightly packed, `width * height * 4` bytes.
+        ///  * `width` - Image width in pixels.
+        ///  * `height` - Image height in pixels.
+        ///  * `out_ptr` - Receives a pointer to the encoded JXL file.
+        ///  * `out_len` - Receives the length of the encoded file in bytes.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if `data` or an output pointer is null, or a
+        ///    dimension is 0 or too large.
+        ///  - `Error` if the `encode` feature is not compiled in.
+        ///
+        ///  On failure `*out_ptr` is set to null and `*out_len` to 0.
+        ///
+        ///  # Safety
+        ///  - `data` must be valid for reads of `width * height * 4` bytes.
+        ///  - `out_ptr` and `out_len` must be valid for writes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_encode_lossless_rgba8", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_encode_lossless_rgba8(byte* data, uint width, uint height, byte** out_ptr, System.UIntPtr* out_len);
+
+        /// <summary>
+        ///  Gets the embedded color profile from the image.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `profile_out` - Output for the profile raw data.
+        ///  * `icc_data_out` - Output pointer for ICC data (only set if profile is ICC type).
+        ///  * `handle_out` - Output for the profile handle (for calling helper methods).
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `profile_out` must point to a writable `JxlColorProfileRaw`.
+        ///  - `icc_data_out` must point to a writable pointer.
+        ///  - `handle_out` must point to a writable pointer.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_embedded_color_profile", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_embedded_color_profile(NativeDecoderHandle* decoder, JxlColorProfileRaw* profile_out, byte** icc_data_out, JxlColorProfileHandle** handle_out);
+
+        /// <summary>
+        ///  Gets the simple color encoding of the embedded color profile.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`. Unlike
+        ///  `jxl_decoder_get_embedded_color_profile`, no profile handle is created.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `encoding_out` - Output for the encoding.
+        ///
+        ///  # Returns
+        ///  `Success` with `encoding_out` filled, or `IccProfile` if the image embeds an
+        ///  ICC profile instead; use `jxl_decoder_get_embedded_color_profile` to get
+        ///  its bytes.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `encoding_out` must point to a writable `JxlColorEncodingRaw`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_embedded_encoding", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_embedded_encoding(NativeDecoderHandle* decoder, JxlColorEncodingRaw* encoding_out);
+
+        /// <summary>
+        ///  Gets the transfer function of the embedded color profile.
+        ///
+        ///  A cheap way to tell whether an image is HDR, and so whether it needs tone
+        ///  mapping: PQ and HLG are HDR transfer functions. No profile handle or
+        ///  encoding is built.
+        ///
+        ///  # Returns
+        ///  True with `out` filled for a simple RGB or grayscale profile. False for an
+        ///  ICC or XYB profile, and before `jxl_decoder_process` returns
+        ///  `HaveBasicInfo` or while a frame is being decoded.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `out` must point to a writable `JxlTransferFunctionRaw`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_embedded_transfer_function", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_decoder_get_embedded_transfer_function(NativeDecoderHandle* decoder, JxlTransferFunctionRaw* @out);
+
+        /// <summary>
+        ///  Gets the current output color profile.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///
+        ///  # Safety
+        ///  Same as `jxl_decoder_get_embedded_color_profile`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_output_color_profile", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_output_color_profile(NativeDecoderHandle* decoder, JxlColorProfileRaw* profile_out, byte** icc_data_out, JxlColorProfileHandle** handle_out);
+
+        /// <summary>
+        ///  Checks whether the current image is output in its embedded color profile.
+        ///
+        ///  This holds by default for images stored in their original color space,
+        ///  and after setting an output profile with the same color encoding as the
+        ///  embedded one (see `jxl_color_profile_same_color_encoding`). Such images
+        ///  are then decoded as-is, without any color conversion or CMS, which suits
+        ///  callers that do their own color management. XYB-encoded images are always
+        ///  converted from XYB, but need no CMS when this returns true for a
+        ///  non-ICC embedded profile.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`. Returns
+        ///  false if no image header has been read.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_output_matches_embedded", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_decoder_output_matches_embedded(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Checks whether the current image is stored in the XYB color space.
+        ///
+        ///  XYB images are lossy-encoded in JPEG XL's internal perceptual space and
+        ///  reconstructed into the embedded (or requested output) color space while
+        ///  decoding, rather than stored in their original profile. This is the
+        ///  image header's `xyb_encoded` flag, the inverse of `UsesOriginalProfile`:
+        ///  the embedded color encoding describes the original space either way.
+        ///
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`. Returns
+        ///  false if no image header has been read.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_is_xyb", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_decoder_is_xyb(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Gets the channel count of the embedded color profile.
+        ///
+        ///  A cheaper alternative to `jxl_decoder_get_embedded_color_profile` followed
+        ///  by `jxl_color_profile_channels` when only the count is needed, e.g. to
+        ///  size buffers. Unlike that, it also works while a frame is being decoded.
+        ///
+        ///  # Returns
+        ///  1 for grayscale, 3 for RGB, 4 for CMYK, or 0 before `jxl_decoder_process`
+        ///  returns `HaveBasicInfo`.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_embedded_profile_channels", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint jxl_decoder_get_embedded_profile_channels(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Sets the output color profile for decoding.
+        ///
+        ///  Must be called after `HaveBasicInfo` and before decoding pixels. The
+        ///  profile is kept across `jxl_decoder_reset_keep_config`. To choose the
+        ///  output profile before the header is read, use
+        ///  `jxl_decoder_set_desired_output_color_profile`.
+        ///
+        ///  The profile must be grayscale for grayscale images and color (RGB or
+        ///  CMYK) otherwise; a mismatch returns `InvalidArgument`.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `profile` - The color profile raw data.
+        ///  * `icc_data` - ICC data pointer (required if profile tag is Icc).
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `profile` must point to a valid `JxlColorProfileRaw`.
+        ///  - If profile is ICC, `icc_data` must point to `profile.IccLength` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_output_color_profile", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_set_output_color_profile(NativeDecoderHandle* decoder, JxlColorProfileRaw* profile, byte* icc_data);
+
+        /// <summary>
+        ///  Registers an output color profile to apply to every image automatically.
+        ///
+        ///  Unlike `jxl_decoder_set_output_color_profile`, this can be called in any
+        ///  state, including before the first `jxl_decoder_process`. The profile is
+        ///  applied each time an image header is read, before `HaveBasicInfo` is
+        ///  returned, so pipelines that always convert (e.g. to sRGB) need no
+        ///  round-trip per image. It persists across resets and new input; pass a null
+        ///  `profile` to clear it.
+        ///
+        ///  A profile set with `jxl_decoder_set_output_color_profile` still overrides
+        ///  it for the current image. If the profile cannot be applied to an image (for
+        ///  example, it needs a CMS that is not configured), `jxl_decoder_process`
+        ///  returns `Error`.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `profile` - The color profile raw data, or null to clear.
+        ///  * `icc_data` - ICC data pointer (required if profile tag is Icc).
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `profile` must be null or point to a valid `JxlColorProfileRaw`.
+        ///  - If profile is ICC, `icc_data` must point to `profile.IccLength` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_desired_output_color_profile", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_set_desired_output_color_profile(NativeDecoderHandle* decoder, JxlColorProfileRaw* profile, byte* icc_data);
+
+        /// <summary>
+        ///  Sets the output color profile to a common color space.
+        ///
+        ///  A shorthand for `jxl_decoder_set_output_color_profile` that builds the
+        ///  encoding internally, with the same requirements: it must be called after
+        ///  `HaveBasicInfo` and before decoding pixels, and conversions need a CMS.
+        ///  Grayscale images stay gray, with the color space's transfer function.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_output_named", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_set_output_named(NativeDecoderHandle* decoder, JxlNamedColorSpace named);
+
+        /// <summary>
+        ///  Soft-proofs output through the device described by `profile`.
+        ///
+        ///  The lcms2 CMS then converts to the output color profile by way of the
+        ///  proof device, so the output previews how the image would look there, e.g.
+        ///  on a printer. Pixels the device can't reproduce can be marked with
+        ///  `jxl_decoder_set_gamut_warning_color`. Pass a null `profile` to stop
+        ///  soft proofing.
+        ///
+        ///  Requires `CmsType` `Lcms2`, and only affects images that go through the
+        ///  CMS, i.e. whose output color profile differs from the embedded one. Must
+        ///  be called before the current image starts decoding: before the first
+        ///  `jxl_decoder_process`, or after a reset or rewind. The setting survives
+        ///  resets.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `profile` - The proof device's color profile, or null to clear.
+        ///  * `icc_data` - ICC data pointer (required if profile tag is Icc).
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `profile` must be null or point to a valid `JxlColorProfileRaw`.
+        ///  - If profile is ICC, `icc_data` must point to `profile.IccLength` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_proof_profile", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_set_proof_profile(NativeDecoderHandle* decoder, JxlColorProfileRaw* profile, byte* icc_data);
+
+        /// <summary>
+        ///  Sets the color that marks pixels outside the proof device's gamut.
+        ///
+        ///  Only has an effect while soft proofing with
+        ///  `jxl_decoder_set_proof_profile`. `color` holds one value per output
+        ///  channel, from 0.0 to 1.0 in the output color space; grayscale output uses
+        ///  the first. Pass null to leave out-of-gamut pixels unmarked, the default.
+        ///  Has the same timing requirements as `jxl_decoder_set_proof_profile`.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `color` must be null or point to 3 floats.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_set_gamut_warning_color", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_set_gamut_warning_color(NativeDecoderHandle* decoder, float* color);
+
+        /// <summary>
+        ///  Frees a color profile handle.
+        ///
+        ///  # Safety
+        ///  The handle must have been created by a color profile function.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_free", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_color_profile_free(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Clones a color profile handle.
+        ///
+        ///  # Returns
+        ///  A new handle that must be freed with `jxl_color_profile_free`, or null on failure.
+        ///
+        ///  # Safety
+        ///  The handle must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_clone", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlColorProfileHandle* jxl_color_profile_clone(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Attempts to get ICC profile data from a color profile.
+        ///
+        ///  Returns true if ICC data is available (either native or converted).
+        ///
+        ///  # Arguments
+        ///  * `handle` - The color profile handle.
+        ///  * `data_out` - Output pointer for ICC data.
+        ///  * `length_out` - Output for ICC data length.
+        ///
+        ///  # Safety
+        ///  - `handle` must be valid.
+        ///  - `data_out` and `length_out` must be writable.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_try_as_icc", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_color_profile_try_as_icc(JxlColorProfileHandle* handle, byte** data_out, System.UIntPtr* length_out);
+
+        /// <summary>
+        ///  Returns the size in bytes of the profile's ICC data, without generating it
+        ///  when possible.
+        ///
+        ///  The result is exact for ICC profiles and for any profile whose ICC data has
+        ///  already been generated by `jxl_color_profile_try_as_icc`. For simple RGB and
+        ///  grayscale encodings with a matrix/curve profile, the size is estimated from
+        ///  the tag layout without allocating the profile. XYB, PQ and HLG RGB encodings
+        ///  need LUT-based tags, so their profile is generated and cached and the exact
+        ///  size is returned.
+        ///
+        ///  # Returns
+        ///  The ICC size in bytes, or 0 if no ICC data can be produced.
+        ///
+        ///  # Safety
+        ///  The handle must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_icc_size_hint", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_color_profile_icc_size_hint(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Gets the number of color channels for a profile.
+        ///
+        ///  # Returns
+        ///  1 for grayscale, 3 for RGB, 4 for CMYK.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_channels", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint jxl_color_profile_channels(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Checks if a profile represents a CMYK color space.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_is_cmyk", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_color_profile_is_cmyk(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Checks if a profile describes HDR content.
+        ///
+        ///  True for simple profiles with a PQ or HLG transfer function, and for ICC
+        ///  profiles whose `cicp` tag signals PQ or HLG. Useful to decide whether a
+        ///  tone mapping CMS is needed at all.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_is_hdr", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_color_profile_is_hdr(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Checks if the decoder can output to this profile without a CMS.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_can_output_to", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_color_profile_can_output_to(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Checks if two profiles represent the same color encoding.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_same_color_encoding", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_color_profile_same_color_encoding(JxlColorProfileHandle* handle_a, JxlColorProfileHandle* handle_b);
+
+        /// <summary>
+        ///  Checks if a profile is equivalent to sRGB (RGB or grayscale).
+        ///
+        ///  Unlike `jxl_color_profile_same_color_encoding`, an embedded ICC profile is
+        ///  parsed and compared semantically, so an sRGB ICC profile matches the simple
+        ///  sRGB encoding.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_is_equivalent_srgb", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_color_profile_is_equivalent_srgb(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Checks if two profiles describe the same color space, even if one is an
+        ///  ICC profile and the other a simple encoding.
+        ///
+        ///  ICC profiles are parsed into a simple encoding when they match a known
+        ///  color space; otherwise this falls back to `jxl_color_profile_same_color_encoding`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_is_equivalent", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_color_profile_is_equivalent(JxlColorProfileHandle* handle_a, JxlColorProfileHandle* handle_b);
+
+        /// <summary>
+        ///  Creates a copy of a profile with linear transfer function.
+        ///
+        ///  # Returns
+        ///  A new handle, or null if not possible (e.g., for ICC profiles).
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_with_linear_tf", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlColorProfileHandle* jxl_color_profile_with_linear_tf(JxlColorProfileHandle* handle);
+
+        /// <summary>
+        ///  Gets the transfer function from a color profile.
+        ///
+        ///  For ICC profiles this is a best-effort match: the `cicp` tag is used if
+        ///  present, otherwise the TRC curves are classified as linear, sRGB, BT.709 or
+        ///  a pure gamma.
+        ///
+        ///  # Returns
+        ///  True if a transfer function was found, false for XYB or ICC profiles whose
+        ///  curves cannot be represented.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_profile_get_transfer_function", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_color_profile_get_transfer_function(JxlColorProfileHandle* handle, JxlTransferFunctionRaw* tf_out);
 
         /// <summary>
         ///  Gets the string representation of a color profile.
         ///
+        ///  The string is computed on the first call and cached on the handle, so
+        ///  repeated queries don't allocate.
+        ///
         ///  # Arguments
         ///  * `handle` - The color profile handle.
         ///  * `buffer` - Output buffer for the string, or null to query required size.
@@ -476,27 +1387,127 @@ namespace JpegXL.Net
         /// <summary>
         ///  Creates a standard sRGB color encoding.
         ///
-        ///  # Arguments
-        ///  * `grayscale` - If true, creates grayscale sRGB; otherwise RGB sRGB.
-        ///  * `encoding_out` - Output for the encoding data.
+        ///  # Arguments
+        ///  * `grayscale` - If true, creates grayscale sRGB; otherwise RGB sRGB.
+        ///  * `encoding_out` - Output for the encoding data.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_encoding_srgb", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_color_encoding_srgb([MarshalAs(UnmanagedType.U1)] bool grayscale, JxlColorEncodingRaw* encoding_out);
+
+        /// <summary>
+        ///  Creates a linear sRGB color encoding.
+        ///
+        ///  # Arguments
+        ///  * `grayscale` - If true, creates grayscale linear sRGB; otherwise RGB linear sRGB.
+        ///  * `encoding_out` - Output for the encoding data.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_encoding_linear_srgb", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_color_encoding_linear_srgb([MarshalAs(UnmanagedType.U1)] bool grayscale, JxlColorEncodingRaw* encoding_out);
+
+        /// <summary>
+        ///  Creates a Display P3 color encoding (P3 primaries, D65, sRGB transfer function).
+        ///
+        ///  # Arguments
+        ///  * `encoding_out` - Output for the encoding data.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_encoding_display_p3", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_color_encoding_display_p3(JxlColorEncodingRaw* encoding_out);
+
+        /// <summary>
+        ///  Creates a Rec. 2100 PQ color encoding (BT.2100 primaries, D65, PQ transfer function).
+        ///
+        ///  # Arguments
+        ///  * `encoding_out` - Output for the encoding data.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_encoding_rec2020_pq", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_color_encoding_rec2020_pq(JxlColorEncodingRaw* encoding_out);
+
+        /// <summary>
+        ///  Creates a Rec. 2100 HLG color encoding (BT.2100 primaries, D65, HLG transfer function).
+        ///
+        ///  # Arguments
+        ///  * `encoding_out` - Output for the encoding data.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_encoding_rec2020_hlg", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_color_encoding_rec2020_hlg(JxlColorEncodingRaw* encoding_out);
+
+        /// <summary>
+        ///  Creates a color encoding with a pure power-law transfer function.
+        ///
+        ///  Useful as an output profile for displays that expect a specific gamma
+        ///  (e.g. 2.2) rather than the piecewise sRGB curve. Set it with
+        ///  `jxl_decoder_set_output_color_profile`.
+        ///
+        ///  # Arguments
+        ///  * `grayscale` - If true, creates a grayscale encoding; otherwise RGB.
+        ///  * `gamma` - Display gamma (decoding exponent), e.g. 2.2. The encoding stores
+        ///    its reciprocal, following the JPEG XL convention.
+        ///  * `primaries_tag` - Standard primaries for RGB encodings. Ignored for grayscale.
+        ///  * `encoding_out` - Output for the encoding data. White point is D65.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if `gamma` is not a finite value &gt;= 1, `primaries_tag`
+        ///    is `Chromaticities`, or `encoding_out` is null.
+        ///
+        ///  # Safety
+        ///  `encoding_out` must be a valid pointer to a writable `JxlColorEncodingRaw`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_color_encoding_gamma", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_color_encoding_gamma([MarshalAs(UnmanagedType.U1)] bool grayscale, float gamma, JxlPrimariesTag primaries_tag, JxlColorEncodingRaw* encoding_out);
+
+        /// <summary>
+        ///  Computes a Bradford chromatic adaptation matrix between two white points.
+        ///
+        ///  The matrix converts XYZ values relative to the source white point into
+        ///  XYZ relative to the destination white point (e.g. DCI → D65).
+        ///
+        ///  # Arguments
+        ///  * `src_wx`, `src_wy` - Source white point chromaticity.
+        ///  * `dst_wx`, `dst_wy` - Destination white point chromaticity.
+        ///  * `out_matrix` - Output for the 3x3 matrix in row-major order (9 floats).
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if `out_matrix` is null or a white point is degenerate.
+        ///
+        ///  # Safety
+        ///  `out_matrix` must be valid for writes of 9 `f32` values.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_encoding_srgb", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern void jxl_color_encoding_srgb([MarshalAs(UnmanagedType.U1)] bool grayscale, JxlColorEncodingRaw* encoding_out);
+        [DllImport(__DllName, EntryPoint = "jxl_chromatic_adapt_matrix", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_chromatic_adapt_matrix(float src_wx, float src_wy, float dst_wx, float dst_wy, float* out_matrix);
 
         /// <summary>
-        ///  Creates a linear sRGB color encoding.
+        ///  Reads the metadata boxes that precede the codestream, without decoding
+        ///  the image header.
         ///
-        ///  # Arguments
-        ///  * `grayscale` - If true, creates grayscale linear sRGB; otherwise RGB linear sRGB.
-        ///  * `encoding_out` - Output for the encoding data.
+        ///  For a fast metadata-only scan: this can be called in any state once input
+        ///  is set, including before the first `jxl_decoder_process`. Only container
+        ///  box headers are parsed, up to the first codestream box, and the EXIF, XML
+        ///  and JUMBF boxes found are cached for `jxl_decoder_get_*_box_count` and
+        ///  `jxl_decoder_get_*_box_at`. Bare codestreams have no metadata boxes.
+        ///  Metadata boxes placed after the codestream are not found.
+        ///
+        ///  Box kinds already read (by an earlier scan or after `HaveBasicInfo`) are
+        ///  left as they are, so pointers returned for them stay valid.
+        ///
+        ///  # Returns
+        ///  - `Success` once all boxes before the codestream have been read.
+        ///  - `NeedMoreInput` if the input ends before the codestream; append more
+        ///    and call again.
+        ///  - `Error` if the input is not a JPEG XL file or a box is malformed.
+        ///
+        ///  # Safety
+        ///  The decoder pointer must be valid.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_color_encoding_linear_srgb", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern void jxl_color_encoding_linear_srgb([MarshalAs(UnmanagedType.U1)] bool grayscale, JxlColorEncodingRaw* encoding_out);
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_scan_metadata", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_scan_metadata(NativeDecoderHandle* decoder);
 
         /// <summary>
         ///  Gets the number of EXIF boxes in the image.
         ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+        ///  `jxl_decoder_scan_metadata` succeeds.
         ///
         ///  # Returns
         ///  The number of EXIF boxes, or 0 if none or not accessible.
@@ -510,7 +1521,8 @@ namespace JpegXL.Net
         /// <summary>
         ///  Gets the number of XML/XMP boxes in the image.
         ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+        ///  `jxl_decoder_scan_metadata` succeeds.
         ///
         ///  # Returns
         ///  The number of XML boxes, or 0 if none or not accessible.
@@ -524,7 +1536,8 @@ namespace JpegXL.Net
         /// <summary>
         ///  Gets the number of JUMBF boxes in the image.
         ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+        ///  `jxl_decoder_scan_metadata` succeeds.
         ///
         ///  # Returns
         ///  The number of JUMBF boxes, or 0 if none or not accessible.
@@ -538,8 +1551,10 @@ namespace JpegXL.Net
         /// <summary>
         ///  Gets EXIF data from a specific box by index.
         ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+        ///  `jxl_decoder_scan_metadata` succeeds.
         ///  The returned pointer is valid until the decoder is reset, rewound, or freed.
+        ///  Use `jxl_decoder_copy_exif_box_at` to copy the data instead.
         ///
         ///  # Arguments
         ///  * `decoder` - The decoder instance (mutable for caching).
@@ -550,7 +1565,8 @@ namespace JpegXL.Net
         ///
         ///  # Returns
         ///  - `Success` if EXIF data is available.
-        ///  - `InvalidState` if called before basic info is available.
+        ///  - `InvalidState` if called before basic info is available and without a
+        ///    metadata scan.
         ///  - `InvalidArgument` if index is out of range.
         ///  - `Error` if no EXIF data exists in the image.
         ///
@@ -561,10 +1577,38 @@ namespace JpegXL.Net
         [DllImport(__DllName, EntryPoint = "jxl_decoder_get_exif_box_at", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern JxlStatus jxl_decoder_get_exif_box_at(NativeDecoderHandle* decoder, uint index, byte** data_out, System.UIntPtr* length_out, bool* is_brotli_compressed);
 
+        /// <summary>
+        ///  Copies EXIF data from a specific box by index into a caller buffer.
+        ///
+        ///  A copying alternative to `jxl_decoder_get_exif_box_at` for callers that
+        ///  can't hold on to a pointer into decoder memory, such as garbage-collected
+        ///  languages. The data is only copied whole: a buffer that is null or too
+        ///  small is left untouched and the required size is returned.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance (mutable for caching).
+        ///  * `index` - Zero-based box index.
+        ///  * `buffer` - Output buffer for the EXIF data, or null to query the size.
+        ///  * `buffer_size` - Size of `buffer` in bytes.
+        ///
+        ///  # Returns
+        ///  The size of the box data in bytes, whether or not it was copied. Returns 0
+        ///  in the cases where `jxl_decoder_get_exif_box_at` fails; check
+        ///  `jxl_get_last_error` for the reason. The compression flag is available
+        ///  from `jxl_decoder_get_exif_box_at` with a null `data_out`.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_copy_exif_box_at", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_decoder_copy_exif_box_at(NativeDecoderHandle* decoder, uint index, byte* buffer, System.UIntPtr buffer_size);
+
         /// <summary>
         ///  Gets XML/XMP data from a specific box by index.
         ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+        ///  `jxl_decoder_scan_metadata` succeeds.
         ///  The returned pointer is valid until the decoder is reset, rewound, or freed.
         ///
         ///  # Arguments
@@ -576,7 +1620,8 @@ namespace JpegXL.Net
         ///
         ///  # Returns
         ///  - `Success` if XML data is available.
-        ///  - `InvalidState` if called before basic info is available.
+        ///  - `InvalidState` if called before basic info is available and without a
+        ///    metadata scan.
         ///  - `InvalidArgument` if index is out of range.
         ///  - `Error` if no XML data exists in the image.
         ///
@@ -590,7 +1635,8 @@ namespace JpegXL.Net
         /// <summary>
         ///  Gets JUMBF data from a specific box by index.
         ///
-        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`.
+        ///  Only valid after `jxl_decoder_process` returns `HaveBasicInfo`, or after
+        ///  `jxl_decoder_scan_metadata` succeeds.
         ///  The returned pointer is valid until the decoder is reset, rewound, or freed.
         ///
         ///  # Arguments
@@ -602,7 +1648,8 @@ namespace JpegXL.Net
         ///
         ///  # Returns
         ///  - `Success` if JUMBF data is available.
-        ///  - `InvalidState` if called before basic info is available.
+        ///  - `InvalidState` if called before basic info is available and without a
+        ///    metadata scan.
         ///  - `InvalidArgument` if index is out of range.
         ///  - `Error` if no JUMBF data exists in the image.
         ///
@@ -622,67 +1669,541 @@ namespace JpegXL.Net
         ///  * `decoder` - The decoder instance.
         ///  * `index` - Zero-based box index.
         ///
-        ///  # Returns
-        ///  - `true` if the box was brotli-compressed in the file (brob box).
-        ///  - `false` if uncompressed or if cache not populated.
+        ///  # Returns
+        ///  - `true` if the box was brotli-compressed in the file (brob box).
+        ///  - `false` if uncompressed or if cache not populated.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_is_exif_box_compressed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_decoder_is_exif_box_compressed(NativeDecoderHandle* decoder, uint index);
+
+        /// <summary>
+        ///  Returns whether the XML box at the given index is brotli-compressed.
+        ///
+        ///  Only valid after `jxl_decoder_get_xml_box_at` has been called to populate the cache.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `index` - Zero-based box index.
+        ///
+        ///  # Returns
+        ///  - `true` if the box was brotli-compressed in the file (brob box).
+        ///  - `false` if uncompressed or if cache not populated.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_is_xml_box_compressed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_decoder_is_xml_box_compressed(NativeDecoderHandle* decoder, uint index);
+
+        /// <summary>
+        ///  Returns whether the JUMBF box at the given index is brotli-compressed.
+        ///
+        ///  Only valid after `jxl_decoder_get_jumbf_box_at` has been called to populate the cache.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `index` - Zero-based box index.
+        ///
+        ///  # Returns
+        ///  - `true` if the box was brotli-compressed in the file (brob box).
+        ///  - `false` if uncompressed or if cache not populated.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_is_jumbf_box_compressed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        [return: MarshalAs(UnmanagedType.U1)]
+        public static extern bool jxl_decoder_is_jumbf_box_compressed(NativeDecoderHandle* decoder, uint index);
+
+        /// <summary>
+        ///  Decompresses brotli data, such as the payload of a brotli-compressed
+        ///  metadata box.
+        ///
+        ///  Uses the same decompressor as the decoder's own `brob` box handling.
+        ///  Brotli can expand a small input enormously, so the output is streamed and
+        ///  decompression stops once it exceeds `max_output_size`.
+        ///
+        ///  # Arguments
+        ///  * `src` - Brotli-compressed data.
+        ///  * `src_len` - Length of `src` in bytes.
+        ///  * `max_output_size` - Maximum size of the decompressed data in bytes.
+        ///    0 = no limit.
+        ///  * `out_ptr` - Receives a pointer to the decompressed data. Free it with
+        ///    `jxl_free_buffer`.
+        ///  * `out_len` - Receives the length of the decompressed data in bytes.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if `src` or an output pointer is null.
+        ///  - `BufferTooSmall` if the decompressed data exceeds `max_output_size`.
+        ///  - `Error` if the data is not a complete, valid brotli stream.
+        ///
+        ///  On failure `*out_ptr` is set to null and `*out_len` to 0.
+        ///
+        ///  # Safety
+        ///  - `src` must be valid for reads of `src_len` bytes.
+        ///  - `out_ptr` and `out_len` must be valid for writes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_brotli_decompress", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_brotli_decompress(byte* src, System.UIntPtr src_len, System.UIntPtr max_output_size, byte** out_ptr, System.UIntPtr* out_len);
+
+        /// <summary>
+        ///  Tone maps an interleaved linear RGB buffer in place.
+        ///
+        ///  Applies the crate's HDR→SDR tone mapping without a decoder or CMS,
+        ///  for HDR pixels obtained elsewhere.
+        ///
+        ///  # Arguments
+        ///  * `method` - Tone mapping algorithm.
+        ///  * `luminances` - Pointer to 3 RGB luminance coefficients for the data's
+        ///    primaries, or null for BT.2020. Ignored by `Bt2446aPerceptual`.
+        ///  * `source_nits` - Peak luminance of the input; 1.0 in `data` equals this many nits.
+        ///  * `target_nits` - Peak luminance of the target display.
+        ///  * `data` - Interleaved `[R, G, B, ...]` linear-light samples.
+        ///  * `len` - Number of `f32` values in `data` (must be a multiple of 3).
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if a pointer is null, `len` is not a multiple of 3,
+        ///    or a luminance target is not positive.
+        ///  - `Error` if tone-mapping support is not compiled in.
+        ///
+        ///  # Safety
+        ///  - If `luminances` is not null, it must point to 3 readable `f32` values.
+        ///  - `data` must be valid for reads and writes of `len` `f32` values.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_tone_map_rgb_f32", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_tone_map_rgb_f32(JxlToneMapMethod method, float* luminances, float source_nits, float target_nits, float* data, System.UIntPtr len);
+
+        /// <summary>
+        ///  Expands an interleaved linear SDR RGB buffer toward HDR in place.
+        ///
+        ///  Inverts `jxl_tone_map_rgb_f32` with the `Bt2446aLinear` method: tone
+        ///  mapping from `target_nits` down to `source_nits` and then calling this
+        ///  function returns the original samples, up to float rounding. Luminance
+        ///  uses BT.2020 coefficients.
+        ///
+        ///  # Arguments
+        ///  * `source_nits` - Peak luminance of the SDR input; 1.0 in `data` equals this many nits.
+        ///  * `target_nits` - Peak luminance of the HDR output; 1.0 in the result equals this many nits.
+        ///  * `data` - Interleaved `[R, G, B, ...]` linear-light samples.
+        ///  * `len` - Number of `f32` values in `data` (must be a multiple of 3).
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if `data` is null, `len` is not a multiple of 3,
+        ///    or a luminance target is not positive.
+        ///  - `Error` if tone-mapping support is not compiled in.
+        ///
+        ///  # Safety
+        ///  `data` must be valid for reads and writes of `len` `f32` values.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_inverse_tone_map_bt2446a", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_inverse_tone_map_bt2446a(float source_nits, float target_nits, float* data, System.UIntPtr len);
+
+        /// <summary>
+        ///  Re-encodes a float buffer from one transfer function to another in place.
+        ///
+        ///  Samples are decoded to linear light with `from_tf` and encoded with
+        ///  `to_tf`, without a CMS or output profile. Primaries are left untouched, so
+        ///  this is only correct when the input and output primaries already match.
+        ///
+        ///  # Arguments
+        ///  * `data` - Samples to convert; any channel layout, alpha excluded.
+        ///  * `len` - Number of `f32` values in `data`.
+        ///  * `from_tf` - Transfer function the samples are currently encoded with.
+        ///  * `to_tf` - Transfer function to encode the samples with.
+        ///  * `intensity_target` - Nits represented by linear 1.0, used for PQ.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if `data` is null, `intensity_target` is not positive,
+        ///    or a gamma is outside (0, 1].
+        ///
+        ///  # Safety
+        ///  `data` must be valid for reads and writes of `len` `f32` values.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_apply_transfer_function", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_apply_transfer_function(float* data, System.UIntPtr len, JxlTransferFunctionRaw from_tf, JxlTransferFunctionRaw to_tf, float intensity_target);
+
+        /// <summary>
+        ///  Creates a color transform between two ICC profiles with lcms2.
+        ///
+        ///  The transform is built once and can then be applied to any number of
+        ///  buffers in the input color space, independently of a decoder. Both
+        ///  profiles must have the same channel count, as transforms run in place.
+        ///
+        ///  # Arguments
+        ///  * `input_icc` - ICC profile the samples are in.
+        ///  * `input_len` - Size of `input_icc` in bytes.
+        ///  * `output_icc` - ICC profile to convert the samples to.
+        ///  * `output_len` - Size of `output_icc` in bytes.
+        ///  * `intent` - Rendering intent of the transform.
+        ///
+        ///  # Returns
+        ///  A transform that must be freed with `jxl_cms_transform_destroy`, or null
+        ///  on failure, including in builds without the `cms-lcms2` feature. Call
+        ///  `jxl_get_last_error` for details.
+        ///
+        ///  # Safety
+        ///  `input_icc` and `output_icc` must be valid for reads of `input_len` and
+        ///  `output_len` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_cms_transform_create", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlCmsTransformHandle* jxl_cms_transform_create(byte* input_icc, System.UIntPtr input_len, byte* output_icc, System.UIntPtr output_len, JxlRenderingIntent intent);
+
+        /// <summary>
+        ///  Applies a color transform to interleaved float samples in place.
+        ///
+        ///  A transform may be applied to any number of buffers, but not from
+        ///  several threads at once.
+        ///
+        ///  # Arguments
+        ///  * `transform` - Transform from `jxl_cms_transform_create`.
+        ///  * `data` - Samples to convert, interleaved with the profiles' channel
+        ///    count and nominally in [0, 1].
+        ///  * `len` - Number of `f32` values in `data`.
+        ///
+        ///  # Returns
+        ///  - `Success` on success.
+        ///  - `InvalidArgument` if a pointer is null or `len` is not a multiple of the
+        ///    channel count.
+        ///  - `Error` if the transform fails.
+        ///
+        ///  # Safety
+        ///  - `transform` must be valid and not in use on another thread.
+        ///  - `data` must be valid for reads and writes of `len` `f32` values.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_cms_transform_apply", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_cms_transform_apply(JxlCmsTransformHandle* transform, float* data, System.UIntPtr len);
+
+        /// <summary>
+        ///  Frees a color transform.
+        ///
+        ///  # Safety
+        ///  `transform` must be null or have been created by
+        ///  `jxl_cms_transform_create`, and not be used afterwards.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_cms_transform_destroy", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_cms_transform_destroy(JxlCmsTransformHandle* transform);
+
+        /// <summary>
+        ///  Writes a human-readable snapshot of the decoder state for bug reports.
+        ///
+        ///  The snapshot covers the state machine variant, input offsets, pixel format,
+        ///  CMS type, cached info and options. It contains no image data. The format
+        ///  is not stable and should not be parsed.
+        ///
+        ///  # Returns
+        ///  The length of the snapshot in bytes (UTF-8, not null-terminated). If
+        ///  `buffer` is null or smaller than this, nothing is written.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_debug_state", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_decoder_debug_state(NativeDecoderHandle* decoder, byte* buffer, System.UIntPtr buffer_size);
+
+        /// <summary>
+        ///  Gets the stage of the decoder's state machine.
+        ///
+        ///  Lets callers check preconditions, such as basic info being available,
+        ///  without tracking the events returned so far.
+        ///
+        ///  # Returns
+        ///  The current state, or `Processing` while a decoding call is running on
+        ///  another thread. A null decoder also returns `Processing`, as no call can
+        ///  be made on it.
+        ///
+        ///  # Safety
+        ///  `decoder` must be null or valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_state", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlDecoderStateTag jxl_decoder_get_state(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Gets the error message of the last decoding call on this decoder.
+        ///
+        ///  `jxl_get_last_error` is per thread, so it can't be read from a different
+        ///  thread than the failing call, as happens when an async continuation
+        ///  resumes elsewhere. This copy is kept on the decoder: it is updated by every
+        ///  `jxl_decoder_process`, `jxl_decoder_read_pixels`,
+        ///  `jxl_decoder_read_pixels_with_extra_channels`,
+        ///  `jxl_decoder_read_single_channel` and `jxl_decoder_skip_frame` call, and
+        ///  can be read from any thread. A successful call clears it, and a call
+        ///  rejected as `Busy` leaves it as it is.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `buffer` - Buffer to write the error message to, or null to query the length.
+        ///  * `buffer_size` - Size of the buffer in bytes.
+        ///
+        ///  # Returns
+        ///  The length of the error message (excluding null terminator), or 0 if the
+        ///  last decoding call succeeded. If the buffer is too small, the message is
+        ///  truncated.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - If `buffer` is not null, it must be valid for writes of `buffer_size` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_last_error", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_decoder_get_last_error(NativeDecoderHandle* decoder, byte* buffer, System.UIntPtr buffer_size);
+
+        /// <summary>
+        ///  Gets the status of the last decoding call on this decoder, like
+        ///  `jxl_get_last_error_status` but readable from any thread. See
+        ///  `jxl_decoder_get_last_error`.
+        ///
+        ///  # Safety
+        ///  `decoder` must be valid.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_last_error_status", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_last_error_status(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Gets the SHA-256 digest of the color buffer from the last decoded frame.
+        ///
+        ///  The digest covers the pixel rows written by `jxl_decoder_read_pixels`,
+        ///  without any `RowAlignment` padding, so it depends on the pixel format but
+        ///  not the stride. Intended for
+        ///  comparing against reference hashes in conformance tests. Requires the
+        ///  `sha2` feature.
+        ///
+        ///  # Returns
+        ///  - `Success` with the digest written to `out`.
+        ///  - `InvalidState` if no frame has been decoded since the last reset or rewind.
+        ///  - `Error` if the `sha2` feature is not compiled in.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `out` must be valid for writes of 32 bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_last_frame_hash", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_last_frame_hash(NativeDecoderHandle* decoder, byte* @out);
+
+        /// <summary>
+        ///  Computes a luminance histogram of the last decoded frame.
+        ///
+        ///  Luminance is taken from the color output as written, using BT.709
+        ///  weights for RGB, with integer formats normalized to [0, 1]. The histogram
+        ///  has `bins` equal-width bins over [0, 1]; float values outside the range
+        ///  are counted in the first or last bin. Alpha is ignored. Requires the
+        ///  `LuminanceStats` option, which records the statistics as each frame is
+        ///  decoded, so no further pass over the pixels is needed.
+        ///
+        ///  # Arguments
+        ///  * `decoder` - The decoder instance.
+        ///  * `bins` - Number of bins, from 1 to 65536.
+        ///  * `out` - Receives `bins` pixel counts.
+        ///
+        ///  # Returns
+        ///  - `Success` with the histogram written to `out`.
+        ///  - `InvalidArgument` if `bins` is out of range.
+        ///  - `InvalidState` if the option is not enabled or no frame has been
+        ///    decoded since the last reset or rewind.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `out` must be valid for writes of `bins` values.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_compute_histogram", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_compute_histogram(NativeDecoderHandle* decoder, uint bins, uint* @out);
+
+        /// <summary>
+        ///  Gets the luminance range and mean of the last decoded frame.
+        ///
+        ///  Luminance is computed as for `jxl_decoder_compute_histogram`, but values
+        ///  are not clamped: with a float output format, HDR highlights keep their
+        ///  magnitude, so a linear float output gives the peak to pick a tone-mapping
+        ///  `SourceIntensityTargetOverride` from. NaN and infinite values are left
+        ///  out. Requires the `LuminanceStats` option.
+        ///
+        ///  # Returns
+        ///  - `Success` with the statistics written to `out`.
+        ///  - `InvalidState` if the option is not enabled or no frame has been
+        ///    decoded since the last reset or rewind.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `out` must point to a writable `JxlLuminanceStats`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_luminance_stats", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_luminance_stats(NativeDecoderHandle* decoder, JxlLuminanceStats* @out);
+
+        /// <summary>
+        ///  Gets the bounding box of opaque content in the last decoded frame.
+        ///
+        ///  Covers the pixels whose alpha exceeds the `OpaqueAlphaThreshold` option,
+        ///  in output coordinates, so a sprite can be cropped to it without scanning
+        ///  the buffer again. A frame with no such pixels gives an empty rectangle,
+        ///  and a color type without alpha gives the whole frame. Requires the
+        ///  `OpaqueBounds` option.
+        ///
+        ///  # Returns
+        ///  - `Success` with the bounds written to `out`.
+        ///  - `InvalidState` if the option is not enabled or no frame has been
+        ///    decoded since the last reset or rewind.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `out` must point to a writable `JxlRect`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_opaque_bounds", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_opaque_bounds(NativeDecoderHandle* decoder, JxlRect* @out);
+
+        /// <summary>
+        ///  Gets whether the image has transparency, e.g. to decide whether to draw a
+        ///  checkerboard behind it.
+        ///
+        ///  An image without an alpha extra channel is opaque. With the
+        ///  `DetectTransparency` option, once a frame has been decoded with alpha in
+        ///  the color output, the answer is refined from the pixels: the image is
+        ///  opaque while every frame decoded so far has alpha at the maximum
+        ///  everywhere. Otherwise an alpha channel counts as transparency.
+        ///
+        ///  # Returns
+        ///  - `-1` if unknown: basic info is not yet available or `decoder` is null.
+        ///  - `0` if the image is fully opaque.
+        ///  - `1` if the image has transparency.
+        ///
+        ///  # Safety
+        ///  `decoder` must be valid or null.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_has_transparency", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern int jxl_decoder_has_transparency(NativeDecoderHandle* decoder);
+
+        /// <summary>
+        ///  Gets the time spent in each decoding phase.
+        ///
+        ///  Times accumulate over all `jxl_decoder_process`, `jxl_decoder_read_pixels`
+        ///  and `jxl_decoder_read_pixels_with_extra_channels` calls since the decoder
+        ///  was created, reset, or rewound. Requires the `timings` feature; builds
+        ///  without it carry no timing overhead.
+        ///
+        ///  # Returns
+        ///  - `Success` with the timings written to `out`.
+        ///  - `Error` if the `timings` feature is not compiled in.
+        ///
+        ///  # Safety
+        ///  - `decoder` must be valid.
+        ///  - `out` must point to a writable `JxlDecodeTimings`.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_decoder_get_timings", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_decoder_get_timings(NativeDecoderHandle* decoder, JxlDecodeTimings* @out);
+
+        /// <summary>
+        ///  Returns the number of leading bytes that always suffice for
+        ///  `jxl_signature_check` to return a result other than `NotEnoughBytes`.
+        ///
+        ///  This is the length of the container signature box; a codestream is
+        ///  recognized from its first 2 bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_signature_min_bytes", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_signature_min_bytes();
+
+        /// <summary>
+        ///  Checks if data appears to be a JPEG XL file.
+        ///
+        ///  Only needs the first `jxl_signature_min_bytes` bytes to determine.
+        ///
+        ///  # Arguments
+        ///  * `data` - The start of the file.
+        ///  * `size` - Number of bytes available at `data`.
+        ///  * `box_start_out` - Optional output for where parsing continues: for a
+        ///    `Container`, the offset of the first box after the signature box (12);
+        ///    otherwise 0. May be null.
         ///
         ///  # Safety
-        ///  - `decoder` must be valid.
+        ///  - `data` must be valid for reads of `size` bytes.
+        ///  - If `box_start_out` is not null, it must point to a writable `usize`.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_decoder_is_exif_box_compressed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        public static extern bool jxl_decoder_is_exif_box_compressed(NativeDecoderHandle* decoder, uint index);
+        [DllImport(__DllName, EntryPoint = "jxl_signature_check", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlSignature jxl_signature_check(byte* data, System.UIntPtr size, System.UIntPtr* box_start_out);
 
         /// <summary>
-        ///  Returns whether the XML box at the given index is brotli-compressed.
+        ///  Computes a CRC32 (IEEE) of JPEG XL data, for use as a cheap cache key.
         ///
-        ///  Only valid after `jxl_decoder_get_xml_box_at` has been called to populate the cache.
+        ///  With `codestream_only`, container boxes other than `jxlc`/`jxlp` are
+        ///  skipped, so metadata edits don't change the result and a container hashes
+        ///  the same as its bare codestream. Otherwise all bytes are hashed.
         ///
-        ///  # Arguments
-        ///  * `decoder` - The decoder instance.
-        ///  * `index` - Zero-based box index.
+        ///  # Safety
+        ///  `data` must be valid for reads of `size` bytes.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_signature_crc32", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern uint jxl_signature_crc32(byte* data, System.UIntPtr size, [MarshalAs(UnmanagedType.U1)] bool codestream_only);
+
+        /// <summary>
+        ///  Determines whether JPEG XL data is animated from its image header alone.
+        ///
+        ///  Only the header at the start of the codestream is parsed, so directory
+        ///  scanners can flag animations without creating a decoder. Bare codestreams
+        ///  and containers (including split `jxlp` codestreams) are supported.
         ///
         ///  # Returns
-        ///  - `true` if the box was brotli-compressed in the file (brob box).
-        ///  - `false` if uncompressed or if cache not populated.
+        ///  - `Success` with `out` set if the header was parsed.
+        ///  - `NeedMoreInput` if the header isn't fully contained in `data`.
+        ///  - `InvalidArgument` if `out` is null, or `data` is null with a nonzero
+        ///    `size`.
+        ///  - `Error` if `data` is not JPEG XL or the header is malformed.
         ///
         ///  # Safety
-        ///  - `decoder` must be valid.
+        ///  - `data` must be valid for reads of `size` bytes.
+        ///  - `out` must point to a writable `bool`.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_decoder_is_xml_box_compressed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        public static extern bool jxl_decoder_is_xml_box_compressed(NativeDecoderHandle* decoder, uint index);
+        [DllImport(__DllName, EntryPoint = "jxl_peek_is_animated", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_peek_is_animated(byte* data, System.UIntPtr size, bool* @out);
 
         /// <summary>
-        ///  Returns whether the JUMBF box at the given index is brotli-compressed.
-        ///
-        ///  Only valid after `jxl_decoder_get_jumbf_box_at` has been called to populate the cache.
+        ///  Checks the container structure of complete JPEG XL data before decoding.
         ///
-        ///  # Arguments
-        ///  * `decoder` - The decoder instance.
-        ///  * `index` - Zero-based box index.
+        ///  Only box headers are parsed, so ingestion pipelines can cheaply reject
+        ///  truncated or malformed files with a precise reason instead of a generic
+        ///  decode error later. The codestream itself is not validated. Bare
+        ///  codestreams always pass.
         ///
         ///  # Returns
-        ///  - `true` if the box was brotli-compressed in the file (brob box).
-        ///  - `false` if uncompressed or if cache not populated.
+        ///  - `Success` if the container is well-formed.
+        ///  - `InvalidArgument` if `data` is null or empty.
+        ///  - `Error` otherwise; `jxl_get_last_error` names the offending box and its
+        ///    offset.
         ///
         ///  # Safety
-        ///  - `decoder` must be valid.
+        ///  `data` must be valid for reads of `size` bytes.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_decoder_is_jumbf_box_compressed", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        [return: MarshalAs(UnmanagedType.U1)]
-        public static extern bool jxl_decoder_is_jumbf_box_compressed(NativeDecoderHandle* decoder, uint index);
+        [DllImport(__DllName, EntryPoint = "jxl_validate_container", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_validate_container(byte* data, System.UIntPtr size);
 
         /// <summary>
-        ///  Checks if data appears to be a JPEG XL file.
+        ///  Walks a JUMBF structure, listing its boxes in file order.
         ///
-        ///  Only needs the first 12 bytes to determine.
+        ///  `data` may be a whole `jumb` superbox or a JUMBF box payload as returned
+        ///  by `jxl_decoder_get_jumbf_box_at`. Superbox contents are listed after the
+        ///  superbox, one `Depth` level deeper. Offsets in the entries are relative to
+        ///  `data`, so labels and payloads are read from the caller's buffer.
+        ///
+        ///  Call with `max_entries` of 0 to count the boxes, then again with a buffer
+        ///  of that size.
+        ///
+        ///  # Returns
+        ///  The total number of boxes, which may exceed `max_entries`; only the first
+        ///  `max_entries` are written. Returns 0 if `data` is null, empty or
+        ///  malformed; `jxl_get_last_error` names the offending box and its offset.
         ///
         ///  # Safety
-        ///  `data` must be valid for reads of `size` bytes.
+        ///  - `data` must be valid for reads of `len` bytes.
+        ///  - `out_entries` must be valid for writes of `max_entries` entries, or
+        ///    null if `max_entries` is 0.
         /// </summary>
-        [DllImport(__DllName, EntryPoint = "jxl_signature_check", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
-        public static extern JxlSignature jxl_signature_check(byte* data, System.UIntPtr size);
+        [DllImport(__DllName, EntryPoint = "jxl_jumbf_parse", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern System.UIntPtr jxl_jumbf_parse(byte* data, System.UIntPtr len, JxlJumbfEntry* out_entries, System.UIntPtr max_entries);
 
         /// <summary>
         ///  Gets the last error message.
@@ -702,12 +2223,45 @@ namespace JpegXL.Net
         [DllImport(__DllName, EntryPoint = "jxl_get_last_error", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern System.UIntPtr jxl_get_last_error(byte* buffer, System.UIntPtr buffer_size);
 
+        /// <summary>
+        ///  Gets the status code of the last error.
+        ///
+        ///  Functions that return a `JxlDecoderEvent` report every failure as `Error`;
+        ///  this returns the specific cause, such as `MemoryLimitExceeded`. Other
+        ///  failures report `Error`, and `Success` means no error is set.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_get_last_error_status", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern JxlStatus jxl_get_last_error_status();
+
         /// <summary>
         ///  Clears the last error message.
         /// </summary>
         [DllImport(__DllName, EntryPoint = "jxl_clear_last_error", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
         public static extern void jxl_clear_last_error();
 
+        /// <summary>
+        ///  Sets a callback for diagnostic messages, or clears it when `callback` is
+        ///  null.
+        ///
+        ///  The library reports decode milestones and recoverable problems that don't
+        ///  fail the call, such as a skipped frame, a CMS fallback, or an unknown
+        ///  container box, which helps when tracking down problems in the field. The
+        ///  callback is shared by all decoders and may be called from any thread that
+        ///  decodes.
+        ///
+        ///  # Arguments
+        ///  * `callback` - Called with the level, a null-terminated UTF-8 message and
+        ///    `user_data`. The message is only valid during the call.
+        ///  * `user_data` - Passed through to `callback`.
+        ///
+        ///  # Safety
+        ///  `callback` must be safe to call with `user_data` from any thread until it
+        ///  is replaced or cleared, and must not call back into the decoder that logged.
+        ///  It may call `jxl_set_log_callback`, e.g. to clear itself.
+        /// </summary>
+        [DllImport(__DllName, EntryPoint = "jxl_set_log_callback", CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+        public static extern void jxl_set_log_callback(delegate* unmanaged[Cdecl]<JxlLogLevel, byte*, void*, void> callback, void* user_data);
+
 
     }
 
@@ -824,7 +2378,8 @@ namespace JpegXL.Net
         /// </summary>
         public uint Height;
         /// <summary>
-        ///  Bit depth specification.
+        ///  Bit depth of the source image, independent of the output pixel format.
+        ///  See `jxl_decoder_get_output_bit_depth` for the precision of decoded output.
         /// </summary>
         public JxlBitDepth BitDepth;
         /// <summary>
@@ -848,7 +2403,8 @@ namespace JpegXL.Net
         /// </summary>
         public JxlToneMapping ToneMapping;
         /// <summary>
-        ///  Image orientation.
+        ///  Orientation still to be applied to the output: the header orientation
+        ///  with `AdjustOrientation` off, otherwise `Identity`.
         /// </summary>
         public JxlOrientation Orientation;
         /// <summary>
@@ -867,9 +2423,10 @@ namespace JpegXL.Net
 
     /// <summary>
     ///  Information about an extra channel.
-    ///  Note: jxl-rs API only exposes channel type and alpha_associated.
-    ///  Other fields like bits_per_sample, name, spot_color are in the lower-level
-    ///  ExtraChannelInfo but not exposed through the public API.
+    ///  Note: jxl-rs API only exposes channel type and alpha_associated; `DimShift`
+    ///  is read from the image header. Other fields like bits_per_sample, name,
+    ///  spot_color are in the lower-level ExtraChannelInfo but not exposed through
+    ///  the public API.
     /// </summary>
     [StructLayout(LayoutKind.Sequential)]
     public unsafe partial struct JxlExtraChannelInfo
@@ -882,6 +2439,13 @@ namespace JpegXL.Net
         ///  Whether alpha is associated/premultiplied (only for alpha channels).
         /// </summary>
         [MarshalAs(UnmanagedType.U1)] public bool AlphaAssociated;
+        /// <summary>
+        ///  Log2 of the channel's downsampling factor (0-3): the channel is stored
+        ///  at `ceil(width / 2^DimShift)` x `ceil(height / 2^DimShift)`, e.g. a
+        ///  half-resolution depth map has 1. The decoder upsamples it to the image
+        ///  size, so extra channel buffers are always full size.
+        /// </summary>
+        public uint DimShift;
     }
 
     /// <summary>
@@ -893,7 +2457,8 @@ namespace JpegXL.Net
     public unsafe partial struct JxlFrameHeader
     {
         /// <summary>
-        ///  Frame duration in milliseconds (for animation).
+        ///  Frame duration in milliseconds (for animation). Always 0 for the
+        ///  preview.
         /// </summary>
         public float DurationMs;
         /// <summary>
@@ -908,6 +2473,59 @@ namespace JpegXL.Net
         ///  Frame name length in bytes. Use jxl_decoder_get_frame_name to get the actual name.
         /// </summary>
         public uint NameLength;
+        /// <summary>
+        ///  X origin of the decoded frame on the image canvas, in pixels.
+        ///  jxl-rs extends every frame to the image dimensions (even with coalescing
+        ///  disabled), so the decoded buffer always starts at the canvas origin.
+        /// </summary>
+        public int FrameX0;
+        /// <summary>
+        ///  Y origin of the decoded frame on the image canvas, in pixels.
+        ///  See `FrameX0`.
+        /// </summary>
+        public int FrameY0;
+        /// <summary>
+        ///  Whether this frame is the preview image (see `PreferPreview`).
+        ///  The frame size is then the preview size.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool IsPreview;
+        /// <summary>
+        ///  Whether every pixel of the frame is fully opaque. At
+        ///  `HaveFrameHeader` this comes from the metadata: it is exact when the
+        ///  image has no alpha channel and conservatively `false` otherwise. Once
+        ///  the frame's pixels are read with `DetectTransparency` enabled and
+        ///  alpha in the output format, it reflects a scan of the decoded alpha
+        ///  and is exact.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool IsOpaque;
+    }
+
+    /// <summary>
+    ///  Frame header with fields beyond `JxlFrameHeader`, for compositors and
+    ///  other advanced callers. Get it with `jxl_decoder_get_frame_header_ex`.
+    ///
+    ///  jxl-rs decodes DC frames, reference-only frames and other frames that are
+    ///  never displayed internally, so every header describes a displayed frame
+    ///  or, with `Coalescing` disabled, a layer. Blend and reference slot details
+    ///  are not exposed by jxl-rs.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe partial struct JxlFrameHeaderEx
+    {
+        /// <summary>
+        ///  The fields reported by `jxl_decoder_get_frame_header`.
+        /// </summary>
+        public JxlFrameHeader Header;
+        /// <summary>
+        ///  Frame duration in animation ticks, as returned by
+        ///  `jxl_decoder_get_frame_duration_ticks`.
+        /// </summary>
+        public uint DurationTicks;
+        /// <summary>
+        ///  Zero-based index of the frame in the image, or of the layer when
+        ///  `Coalescing` is disabled. The preview is not counted and reports 0.
+        /// </summary>
+        public uint FrameIndex;
     }
 
     /// <summary>
@@ -946,6 +2564,42 @@ namespace JpegXL.Net
         public ulong JumbfSizeLimit;
     }
 
+    /// <summary>
+    ///  A box found by `jxl_jumbf_parse` in a JUMBF structure.
+    ///  Fields are ordered by size (largest first) to minimize padding.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe partial struct JxlJumbfEntry
+    {
+        /// <summary>
+        ///  Offset of the box payload (after its header) in the parsed data.
+        /// </summary>
+        public ulong Offset;
+        /// <summary>
+        ///  Length of the box payload in bytes.
+        /// </summary>
+        public ulong Length;
+        /// <summary>
+        ///  Offset of the UTF-8 label in the parsed data. Only meaningful when
+        ///  `LabelLength` is non-zero.
+        /// </summary>
+        public ulong LabelOffset;
+        /// <summary>
+        ///  Length of the label in bytes, excluding its null terminator; 0 if
+        ///  the box is unlabeled. Set on `jumb` superboxes and their `jumd`
+        ///  description boxes.
+        /// </summary>
+        public uint LabelLength;
+        /// <summary>
+        ///  Superbox nesting level, 0 for boxes at the top level of the data.
+        /// </summary>
+        public uint Depth;
+        /// <summary>
+        ///  Four-character box type, e.g. `jumb`, `jumd` or `json`.
+        /// </summary>
+        public fixed byte Type[4];
+    }
+
     /// <summary>
     ///  Decoder options.
     ///  All options should be set before decoding begins.
@@ -960,11 +2614,45 @@ namespace JpegXL.Net
         /// </summary>
         public System.UIntPtr PixelLimit;
         /// <summary>
+        ///  Maximum estimated bytes to allocate while decoding a frame: the output
+        ///  buffers plus the decoder's internal working planes. Images over the
+        ///  limit are rejected with `MemoryLimitExceeded` before any frame is
+        ///  decoded.
+        ///  0 = no limit.
+        /// </summary>
+        public System.UIntPtr MemoryLimitBytes;
+        /// <summary>
         ///  Progressive decoding mode.
         /// </summary>
         public JxlProgressiveMode ProgressiveMode;
         /// <summary>
-        ///  Whether to adjust image orientation based on EXIF data.
+        ///  Source peak luminance (nits) assumed by tone mapping.
+        ///  When &gt; 0, replaces the image's `intensity_target`, e.g. for content
+        ///  with missing or incorrect mastering metadata.
+        ///  0 = use the value from the image header.
+        /// </summary>
+        public float SourceIntensityTargetOverride;
+        /// <summary>
+        ///  Red luminance coefficient used by tone mapping.
+        ///  When `ToneMapLuminanceR`, `ToneMapLuminanceG` and `ToneMapLuminanceB`
+        ///  are all 0, coefficients are derived from the image's primaries, which
+        ///  falls back to BT.2020 for ICC profiles. Set them for ICC-profiled
+        ///  content with other primaries (e.g. P3: 0.2290, 0.6917, 0.0793).
+        /// </summary>
+        public float ToneMapLuminanceR;
+        /// <summary>
+        ///  Green luminance coefficient used by tone mapping. See `ToneMapLuminanceR`.
+        /// </summary>
+        public float ToneMapLuminanceG;
+        /// <summary>
+        ///  Blue luminance coefficient used by tone mapping. See `ToneMapLuminanceR`.
+        /// </summary>
+        public float ToneMapLuminanceB;
+        /// <summary>
+        ///  Whether to apply the header orientation, outputting upright pixels.
+        ///  When false, pixels keep their stored layout and size, and
+        ///  `JxlBasicInfoRaw.Orientation` tells the caller how to display them,
+        ///  e.g. as a texture transform.
         /// </summary>
         [MarshalAs(UnmanagedType.U1)] public bool AdjustOrientation;
         /// <summary>
@@ -980,7 +2668,19 @@ namespace JpegXL.Net
         /// </summary>
         [MarshalAs(UnmanagedType.U1)] public bool SkipPreview;
         /// <summary>
+        ///  Whether to output the preview image before the main image, overriding
+        ///  `SkipPreview`. When the image has a preview, the first frame is the
+        ///  preview: its `HaveFrameHeader` reports `IsPreview`, and until it has
+        ///  been read or skipped the buffer sizes are for `Preview_Width` x
+        ///  `Preview_Height` rather than the image size. The image frames follow.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool PreferPreview;
+        /// <summary>
         ///  Whether to use high precision mode for decoding.
+        ///  When false, the render pipeline may use faster, lower-precision
+        ///  approximations (e.g. for XYB→RGB and transfer functions).
+        ///  Differences are typically at most 1-2 code values in 8-bit output,
+        ///  so enable this only for float/16-bit output or reference comparisons.
         /// </summary>
         [MarshalAs(UnmanagedType.U1)] public bool HighPrecision;
         /// <summary>
@@ -988,14 +2688,121 @@ namespace JpegXL.Net
         /// </summary>
         [MarshalAs(UnmanagedType.U1)] public bool PremultiplyAlpha;
         /// <summary>
+        ///  Whether to convert associated (premultiplied) alpha to straight alpha
+        ///  in the output, dividing each color sample by alpha. Only has an effect
+        ///  when the image's alpha is associated and `PremultiplyAlpha` is off;
+        ///  pixels with zero alpha are left unchanged.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool UnpremultiplyAlpha;
+        /// <summary>
+        ///  Whether to replace non-finite samples in float output: NaN becomes 0
+        ///  and ±infinity the largest finite value of the same sign. Applies to
+        ///  `Float32` and `Float16` color output and, with a tone-mapping
+        ///  `CmsType`, to the tone-mapped values before the final conversion.
+        ///  Malformed or extreme HDR content can otherwise yield samples that
+        ///  break GPU uploads.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool SanitizeFloatOutput;
+        /// <summary>
+        ///  Whether integer output is scaled to the full range of the data format
+        ///  when the image has fewer bits per sample, e.g. a 10-bit image decoded
+        ///  to `Uint16` spans 0-65535. Otherwise samples keep the image's
+        ///  magnitude, 0-1023 in that example, and are never scaled beyond the
+        ///  format's width. Float images are always scaled.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool ScaleToFullRange;
+        /// <summary>
         ///  Whether to decode extra channels into separate buffers.
         /// </summary>
         [MarshalAs(UnmanagedType.U1)] public bool DecodeExtraChannels;
         /// <summary>
+        ///  Whether the first alpha channel is also written to its own extra
+        ///  channel buffer when the color type has alpha. By default it is only
+        ///  part of the color output and its extra buffer is left untouched.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool SeparateFirstAlpha;
+        /// <summary>
+        ///  Whether to count pixels clipped at the target peak by tone mapping.
+        ///  Only has an effect with a tone-mapping `CmsType`. Read the counts with
+        ///  `jxl_decoder_get_tone_map_clip_stats`.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool ToneMapClipStats;
+        /// <summary>
+        ///  Whether to record luminance statistics of each decoded frame. Read
+        ///  them with `jxl_decoder_compute_histogram` and
+        ///  `jxl_decoder_get_luminance_stats`.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool LuminanceStats;
+        /// <summary>
+        ///  Whether to record the bounding box of opaque content in each decoded
+        ///  frame, for cropping transparent borders. Read it with
+        ///  `jxl_decoder_get_opaque_bounds`.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool OpaqueBounds;
+        /// <summary>
+        ///  Alpha above which a pixel counts as opaque for `OpaqueBounds`, from 0
+        ///  to 1. The default 0 counts every pixel that isn't fully transparent.
+        /// </summary>
+        public float OpaqueAlphaThreshold;
+        /// <summary>
+        ///  Whether to scan each decoded frame for alpha below the maximum, so
+        ///  `jxl_decoder_has_transparency` can tell an image whose alpha channel
+        ///  is fully opaque from one with visible transparency.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool DetectTransparency;
+        /// <summary>
+        ///  Whether a frame that fails to decode is skipped instead of ending the
+        ///  decode. Pixel reads then return `FrameError` and decoding continues
+        ///  with the next frame. Skipping re-reads the image through the failed
+        ///  frame without output buffers, so it only succeeds when the failure was
+        ///  in producing output; a frame whose bitstream is corrupt can't be
+        ///  skipped, and `Error` is returned as without this option.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool ContinueOnFrameError;
+        /// <summary>
+        ///  Whether a CMS that fails to set up a color conversion is bypassed
+        ///  instead of ending the decode, so viewers can still show the image.
+        ///  Pixels are then output unconverted, in the image's own color encoding,
+        ///  and `jxl_decoder_cms_fell_back` reports the CMS error. Only gray to
+        ///  gray and RGB to RGB conversions can be bypassed.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool CmsErrorFallback;
+        /// <summary>
+        ///  Whether colors outside the output gamut are smoothly compressed into
+        ///  it instead of clipped, e.g. when converting Rec. 2020 content to sRGB.
+        ///  Hue is kept and colors well inside the gamut are unchanged; only the
+        ///  most saturated in-gamut colors shift slightly to make room. Only has
+        ///  an effect with the `Lcms2` `CmsType` and RGB output.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool GamutCompression;
+        /// <summary>
+        ///  Whether to stop after the image header, for indexing and other
+        ///  metadata-only uses. `jxl_decoder_process` returns `Complete` right
+        ///  after `HaveBasicInfo` and never decodes a frame, so no pixel buffer is
+        ///  needed and `MemoryLimitBytes` is not checked. Basic info, color
+        ///  profiles and metadata boxes before the codestream remain available.
+        /// </summary>
+        [MarshalAs(UnmanagedType.U1)] public bool MetadataOnly;
+        /// <summary>
+        ///  Maximum number of frames to decode, 0 = no limit. Once that many
+        ///  frames have been read, a decoded preview included,
+        ///  `jxl_decoder_process` returns `Complete` and the rest of the image is
+        ///  ignored. Bounds the work on untrusted animations with huge numbers of
+        ///  small frames, which `PixelLimit` doesn't catch.
+        /// </summary>
+        public uint MaxFrames;
+        /// <summary>
         ///  Desired output pixel format.
         /// </summary>
         public JxlPixelFormat PixelFormat;
         /// <summary>
+        ///  Pads each color output row to a multiple of this many bytes, e.g. 16
+        ///  or 32 for SIMD post-processing. 0 = rows are tightly packed.
+        ///  `jxl_decoder_get_buffer_size` includes the padding; padding bytes are
+        ///  left untouched. Extra channel buffers are always packed.
+        /// </summary>
+        public uint RowAlignment;
+        /// <summary>
         ///  Options for capturing metadata boxes (EXIF, XML, JUMBF).
         /// </summary>
         public JxlMetadataCaptureOptions MetadataCapture;
@@ -1005,6 +2812,72 @@ namespace JpegXL.Net
         public JxlCmsType CmsType;
     }
 
+    /// <summary>
+    ///  Time spent in each decoding phase, accumulated since the decoder was
+    ///  created, reset, or rewound.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe partial struct JxlDecodeTimings
+    {
+        /// <summary>
+        ///  Milliseconds spent parsing image and frame headers.
+        /// </summary>
+        public double HeaderMs;
+        /// <summary>
+        ///  Milliseconds spent decoding frame pixels. Includes `CmsMs`, since
+        ///  color conversion runs inside the render pipeline.
+        /// </summary>
+        public double FrameDecodeMs;
+        /// <summary>
+        ///  Milliseconds spent in CMS transforms, including tone mapping.
+        /// </summary>
+        public double CmsMs;
+    }
+
+    /// <summary>
+    ///  Luminance range of a decoded frame, see `jxl_decoder_get_luminance_stats`.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe partial struct JxlLuminanceStats
+    {
+        /// <summary>
+        ///  Lowest pixel luminance.
+        /// </summary>
+        public float Min;
+        /// <summary>
+        ///  Highest pixel luminance.
+        /// </summary>
+        public float Max;
+        /// <summary>
+        ///  Mean pixel luminance.
+        /// </summary>
+        public float Mean;
+    }
+
+    /// <summary>
+    ///  Rectangle in output pixel coordinates, see `jxl_decoder_get_opaque_bounds`.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe partial struct JxlRect
+    {
+        /// <summary>
+        ///  Left edge.
+        /// </summary>
+        public uint X;
+        /// <summary>
+        ///  Top edge.
+        /// </summary>
+        public uint Y;
+        /// <summary>
+        ///  Width in pixels.
+        /// </summary>
+        public uint Width;
+        /// <summary>
+        ///  Height in pixels.
+        /// </summary>
+        public uint Height;
+    }
+
     /// <summary>
     ///  Opaque handle to a color profile.
     ///  Must be freed with `jxl_color_profile_free`.
@@ -1015,6 +2888,16 @@ namespace JpegXL.Net
         public fixed byte _private[1];
     }
 
+    /// <summary>
+    ///  Opaque handle to a standalone color transform between two ICC profiles.
+    ///  Must be freed with `jxl_cms_transform_destroy`.
+    /// </summary>
+    [StructLayout(LayoutKind.Sequential)]
+    public unsafe partial struct JxlCmsTransformHandle
+    {
+        public fixed byte _private[1];
+    }
+
     /// <summary>
     ///  White point specification (tagged union).
     /// </summary>
@@ -1170,6 +3053,19 @@ namespace JpegXL.Net
         ///  An I/O error occurred (e.g., file not found).
         /// </summary>
         IoError = 6,
+        /// <summary>
+        ///  Decoding would exceed the `MemoryLimitBytes` option.
+        /// </summary>
+        MemoryLimitExceeded = 7,
+        /// <summary>
+        ///  The color profile is an ICC profile with no simple encoding.
+        /// </summary>
+        IccProfile = 8,
+        /// <summary>
+        ///  The decoder is already in use by a call on another thread. A decoder
+        ///  must only be used from one thread at a time.
+        /// </summary>
+        Busy = 9,
     }
 
     /// <summary>
@@ -1217,7 +3113,8 @@ namespace JpegXL.Net
         /// </summary>
         Rgb = 3,
         /// <summary>
-        ///  Blue, green, red (Windows bitmap order).
+        ///  Blue, green, red (Windows bitmap order). Swizzled by the upstream decoder
+        ///  while writing rows, so no extra pass over the output is needed.
         /// </summary>
         Bgr = 4,
         /// <summary>
@@ -1395,6 +3292,56 @@ namespace JpegXL.Net
         ///  All frames have been decoded. The decoder is finished.
         /// </summary>
         Complete = 6,
+        /// <summary>
+        ///  The current frame failed to decode and was skipped; only returned with
+        ///  the `ContinueOnFrameError` option. Call `jxl_get_last_error` for
+        ///  details, then `jxl_decoder_process` to continue with the next frame.
+        /// </summary>
+        FrameError = 7,
+    }
+
+    /// <summary>
+    ///  Stage of the decoder's state machine, see `jxl_decoder_get_state`.
+    /// </summary>
+    public enum JxlDecoderStateTag : uint
+    {
+        /// <summary>
+        ///  No basic info has been read yet; input and configuration can be set.
+        /// </summary>
+        Initialized = 0,
+        /// <summary>
+        ///  The basic info is available, and the next frame header isn't read
+        ///  yet. Also the state once the image is complete.
+        /// </summary>
+        WithImageInfo = 1,
+        /// <summary>
+        ///  A frame header is available, and pixels can be read.
+        /// </summary>
+        WithFrameInfo = 2,
+        /// <summary>
+        ///  A decoding call is running on the decoder.
+        /// </summary>
+        Processing = 3,
+    }
+
+    /// <summary>
+    ///  Severity of a message passed to the callback set with
+    ///  `jxl_set_log_callback`.
+    /// </summary>
+    public enum JxlLogLevel : uint
+    {
+        /// <summary>
+        ///  Decode progress, such as each frame completing.
+        /// </summary>
+        Debug = 0,
+        /// <summary>
+        ///  Milestones, such as reading the basic info.
+        /// </summary>
+        Info = 1,
+        /// <summary>
+        ///  Recoverable problems, such as a skipped frame or a CMS fallback.
+        /// </summary>
+        Warning = 2,
     }
 
     /// <summary>
@@ -1558,6 +3505,61 @@ namespace JpegXL.Net
         Simple = 1,
     }
 
+    /// <summary>
+    ///  Common output color spaces, see `jxl_decoder_set_output_named`.
+    /// </summary>
+    public enum JxlNamedColorSpace : uint
+    {
+        /// <summary>
+        ///  sRGB (sRGB primaries and transfer function, D65).
+        /// </summary>
+        Srgb = 0,
+        /// <summary>
+        ///  sRGB primaries with a linear transfer function, D65.
+        /// </summary>
+        LinearSrgb = 1,
+        /// <summary>
+        ///  Display P3 (P3 primaries, sRGB transfer function, D65).
+        /// </summary>
+        DisplayP3 = 2,
+        /// <summary>
+        ///  Rec. 2100 PQ (BT.2100 primaries, PQ transfer function, D65).
+        /// </summary>
+        Rec2020Pq = 3,
+        /// <summary>
+        ///  Rec. 2100 HLG (BT.2100 primaries, HLG transfer function, D65).
+        /// </summary>
+        Rec2020Hlg = 4,
+        /// <summary>
+        ///  Adobe RGB (1998) (Adobe primaries, gamma 563/256, D65).
+        /// </summary>
+        AdobeRgb = 5,
+    }
+
+    /// <summary>
+    ///  Tone mapping algorithm for `jxl_tone_map_rgb_f32`.
+    /// </summary>
+    public enum JxlToneMapMethod : uint
+    {
+        /// <summary>
+        ///  BT.2446a in Y'CbCr' domain per ITU-R BT.2446-1 Method A.
+        /// </summary>
+        Bt2446a = 0,
+        /// <summary>
+        ///  BT.2446a curve applied to linear RGB luminance. Fast approximation.
+        /// </summary>
+        Bt2446aLinear = 1,
+        /// <summary>
+        ///  BT.2446a curve in IPTPQc4 perceptual space. Ignores luminance coefficients.
+        /// </summary>
+        Bt2446aPerceptual = 2,
+        /// <summary>
+        ///  Rec. 2408 / BT.2390 PQ-domain knee with gamut mapping (libjxl's default).
+        ///  Output is re-normalized so 1.0 = target peak.
+        /// </summary>
+        Rec2408 = 3,
+    }
+
     /// <summary>
     ///  Color Management System type.
     ///
@@ -1588,9 +3590,21 @@ namespace JpegXL.Net
         Bt2446aLinear = 3,
         /// <summary>
         ///  BT.2446a tone mapping (IPTPQc4 perceptual space) with lcms2 color management.
-        ///  Best color preservation for saturated HDR content.
+        ///  Best color preservation for saturated HDR content. Requires BT.2020
+        ///  primaries; other inputs (including ICC profiles) use `Bt2446aLinear`.
         /// </summary>
         Bt2446aPerceptual = 4,
+        /// <summary>
+        ///  Built-in matrix and transfer function conversion between simple RGB or
+        ///  grayscale encodings. Needs no lcms2; ICC and CMYK profiles are rejected.
+        /// </summary>
+        Builtin = 5,
+        /// <summary>
+        ///  HDR to SDR tone mapping with lcms2 color management, using the build's
+        ///  default method: `Bt2446aPerceptual`, or `Bt2446aLinear` when built with
+        ///  the `tone-map-default-linear` feature.
+        /// </summary>
+        ToneMap = 6,
     }
 
 
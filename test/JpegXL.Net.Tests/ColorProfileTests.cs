@@ -1,3 +1,4 @@
+using System.Runtime.InteropServices;
 using JpegXL.Net;
 
 namespace JpegXL.Net.Tests;
@@ -220,6 +221,39 @@ public class ColorProfileTests
         Assert.IsTrue(profile.IsRgb, "Should be RGB profile");
     }
 
+    [TestMethod]
+    public void GetEmbeddedEncoding_SimpleProfile_ReturnsEncoding()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/p3_lossless.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act
+        var encoding = decoder.GetEmbeddedEncoding();
+
+        // Assert
+        Assert.IsNotNull(encoding);
+        Assert.AreEqual(JxlColorEncodingTag.Rgb, encoding.Value.Tag);
+        Assert.AreEqual(JxlPrimariesTag.P3, encoding.Value.Primaries.Tag);
+    }
+
+    [TestMethod]
+    public void GetEmbeddedEncoding_IccProfile_ReturnsNull()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/with_icc.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act & Assert - callers fall back to the ICC bytes
+        Assert.IsNull(decoder.GetEmbeddedEncoding());
+        using var profile = decoder.GetEmbeddedColorProfile();
+        Assert.IsTrue(profile.IsIcc);
+    }
+
     [TestMethod]
     public void GetOutputColorProfile_AfterReadInfo_ReturnsProfile()
     {
@@ -257,6 +291,359 @@ public class ColorProfileTests
         Assert.IsTrue(outputProfile.SameColorEncoding(srgbProfile));
     }
 
+    [TestMethod]
+    public void GetEmbeddedProfileChannels_GrayRgbAndCmyk_ReturnsChannelCount()
+    {
+        // Arrange
+        var files = new[]
+        {
+            ("TestData/gray_lossless.jxl", 1),
+            ("TestData/3x3_srgb_lossless.jxl", 3),
+            ("TestData/cmyk_layers.jxl", 4),
+        };
+
+        foreach (var (file, expected) in files)
+        {
+            using var decoder = new JxlDecoder();
+            decoder.SetInput(File.ReadAllBytes(file));
+            Assert.AreEqual(0, decoder.GetEmbeddedProfileChannels());
+
+            // Act
+            decoder.ReadInfo();
+
+            // Assert
+            Assert.AreEqual(expected, decoder.GetEmbeddedProfileChannels(), file);
+        }
+    }
+
+    [TestMethod]
+    public void IsXyb_LossyAndLosslessImages_MatchesOriginalProfileFlag()
+    {
+        // Arrange
+        using var lossy = new JxlDecoder();
+        lossy.SetInput(File.ReadAllBytes("TestData/dice.jxl"));
+        using var lossless = new JxlDecoder();
+        lossless.SetInput(File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl"));
+
+        // Act
+        var lossyInfo = lossy.ReadInfo();
+        var losslessInfo = lossless.ReadInfo();
+
+        // Assert
+        Assert.IsTrue(lossy.IsXyb());
+        Assert.IsFalse(lossyInfo.UsesOriginalProfile);
+        Assert.IsFalse(lossless.IsXyb());
+        Assert.IsTrue(losslessInfo.UsesOriginalProfile);
+    }
+
+    [TestMethod]
+    public void SetProofProfile_NarrowGamutDevice_MarksOutOfGamutPixels()
+    {
+        // Arrange - the first row of 3x3_srgb_lossless.jxl is pure red, green and blue
+        using var linear = JxlColorProfile.CreateLinearSrgb();
+        using var narrow = JxlColorProfile.FromEncoding(
+            JxlProfileType.Rgb,
+            whitePoint: JxlWhitePointType.D65,
+            primaries: JxlPrimariesType.Custom,
+            customPrimaries: new JxlCustomPrimaries(0.45f, 0.33f, 0.31f, 0.45f, 0.22f, 0.2f),
+            transferFunction: JxlTransferFunctionType.Srgb);
+        using var decoder = new JxlDecoder();
+        decoder.SetDesiredOutputColorProfile(linear);
+        decoder.SetProofProfile(narrow);
+        decoder.SetGamutWarningColor(0, 1, 0);
+        decoder.SetInput(File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl"));
+        decoder.SetPixelFormat(JxlPixelFormat.Rgb8);
+
+        // Act
+        decoder.ReadInfo();
+        var pixels = decoder.GetPixels();
+
+        // Assert
+        CollectionAssert.AreEqual(new byte[] { 0, 255, 0, 0, 255, 0, 0, 255, 0 }, pixels.Take(9).ToArray());
+        Assert.ThrowsException<JxlException>(() => decoder.SetProofProfile(null));
+    }
+
+    [TestMethod]
+    public void GamutCompression_NarrowGamutOutput_KeepsSamplesInRange()
+    {
+        // Arrange - the first row of 3x3_srgb_lossless.jxl is pure red, green and blue
+        using var narrow = JxlColorProfile.FromEncoding(
+            JxlProfileType.Rgb,
+            whitePoint: JxlWhitePointType.D65,
+            primaries: JxlPrimariesType.Custom,
+            customPrimaries: new JxlCustomPrimaries(0.45f, 0.33f, 0.31f, 0.45f, 0.22f, 0.2f),
+            transferFunction: JxlTransferFunctionType.Srgb);
+        var options = JxlDecodeOptions.Default;
+        options.GamutCompression = true;
+        options.PixelFormat = JxlPixelFormat.Rgba32F;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetDesiredOutputColorProfile(narrow);
+        decoder.SetInput(File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl"));
+
+        // Act
+        decoder.ReadInfo();
+        var samples = MemoryMarshal.Cast<byte, float>(decoder.GetPixels()).ToArray();
+
+        // Assert
+        foreach (var sample in samples)
+        {
+            Assert.IsTrue(sample >= 0f && sample <= 1f, $"Sample {sample} is out of range");
+        }
+        Assert.AreEqual(1f, samples[0], 1e-4f);
+    }
+
+    [TestMethod]
+    public void SetOutputColorSpace_DisplayP3_MatchesCreatedProfile()
+    {
+        // Arrange
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(File.ReadAllBytes("TestData/dice.jxl"));
+        decoder.ReadInfo();
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+
+        // Act
+        decoder.SetOutputColorSpace(JxlNamedColorSpace.DisplayP3);
+
+        // Assert
+        using var outputProfile = decoder.GetOutputColorProfile();
+        Assert.IsTrue(outputProfile.SameColorEncoding(p3));
+    }
+
+    [TestMethod]
+    public void SetOutputColorProfile_EmbeddedIccProfile_DecodesAsIs()
+    {
+        // Arrange - without a CMS, only a passthrough decode can succeed
+        var data = File.ReadAllBytes("TestData/with_icc.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.None;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        Assert.IsTrue(decoder.OutputMatchesEmbedded());
+
+        // Act
+        using var embedded = decoder.GetEmbeddedColorProfile();
+        decoder.SetOutputColorProfile(embedded);
+
+        // Assert
+        Assert.IsTrue(decoder.OutputMatchesEmbedded());
+        var pixels = decoder.GetPixels();
+        Assert.IsTrue(pixels.Length > 0);
+    }
+
+    [TestMethod]
+    public void SetOutputColorProfile_RgbProfileForGrayImage_ThrowsInvalidArgument()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/gray_lossless.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        using var srgb = JxlColorProfile.CreateSrgb();
+
+        // Act
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.SetOutputColorProfile(srgb));
+
+        // Assert
+        Assert.AreEqual(JxlStatus.InvalidArgument, ex.Status);
+        StringAssert.Contains(ex.Message, "grayscale and color profiles");
+    }
+
+    [TestMethod]
+    public void SetOutputColorProfile_NoCmsForLosslessImage_Throws()
+    {
+        // Lossless images keep their original profile, so converting to P3 needs a CMS
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.None;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+        using var srgb = JxlColorProfile.CreateSrgb();
+
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.SetOutputColorProfile(p3));
+        StringAssert.Contains(ex.Message, "requires a CMS");
+        decoder.SetOutputColorProfile(srgb);
+    }
+
+    [TestMethod]
+    public void SetOutputColorProfile_BuiltinCms_ConvertsToDisplayP3()
+    {
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.Builtin;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+        decoder.SetOutputColorProfile(p3);
+        var pixels = decoder.GetPixels();
+
+        Assert.IsTrue(pixels.Length > 0);
+        using var output = decoder.GetOutputColorProfile();
+        Assert.IsTrue(output.SameColorEncoding(p3));
+    }
+
+    [TestMethod]
+    public void CmsErrorFallback_BuiltinCmsWithIccProfile_DecodesUnconverted()
+    {
+        // Arrange - the builtin CMS can't convert from an ICC profile
+        var data = File.ReadAllBytes("TestData/with_icc.jxl");
+        var noCms = JxlDecodeOptions.Default;
+        noCms.CmsType = JxlCmsType.None;
+        byte[] expected;
+        using (var reference = new JxlDecoder(noCms))
+        {
+            reference.SetInput(data);
+            reference.ReadInfo();
+            expected = reference.GetPixels();
+        }
+
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.Builtin;
+        options.CmsErrorFallback = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        decoder.SetOutputColorSpace(JxlNamedColorSpace.Srgb);
+        Assert.IsNull(decoder.GetCmsFallbackError());
+
+        // Act
+        var pixels = decoder.GetPixels();
+
+        // Assert
+        CollectionAssert.AreEqual(expected, pixels);
+        StringAssert.Contains(decoder.GetCmsFallbackError(), "ICC");
+        decoder.Rewind();
+        Assert.IsNull(decoder.GetCmsFallbackError());
+    }
+
+    [TestMethod]
+    public void GetCmsSetupCount_SrgbToSrgb_BypassesCms()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        using var srgb = JxlColorProfile.CreateSrgb();
+        using var decoder = new JxlDecoder();
+        decoder.SetDesiredOutputColorProfile(srgb);
+        decoder.SetInput(data);
+
+        // Act
+        decoder.ReadInfo();
+        decoder.GetPixels();
+
+        // Assert
+        Assert.IsTrue(decoder.OutputMatchesEmbedded());
+        Assert.AreEqual(0UL, decoder.GetCmsSetupCount());
+    }
+
+    [TestMethod]
+    public void ResetKeepConfig_ReappliesOutputColorProfile()
+    {
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.Builtin;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+        decoder.SetOutputColorProfile(p3);
+        var expected = decoder.GetPixels();
+
+        // Act
+        decoder.ResetKeepConfig();
+        decoder.AppendInput(data);
+        decoder.ReadInfo();
+
+        // Assert
+        using var output = decoder.GetOutputColorProfile();
+        Assert.IsTrue(output.SameColorEncoding(p3));
+        CollectionAssert.AreEqual(expected, decoder.GetPixels());
+    }
+
+    [TestMethod]
+    public void Reset_ClearsOutputColorProfile()
+    {
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.Builtin;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+        decoder.SetOutputColorProfile(p3);
+
+        // Act
+        decoder.Reset();
+        decoder.AppendInput(data);
+        decoder.ReadInfo();
+
+        // Assert
+        using var output = decoder.GetOutputColorProfile();
+        Assert.IsFalse(output.SameColorEncoding(p3));
+    }
+
+    [TestMethod]
+    public void SetDesiredOutputColorProfile_BeforeReadInfo_AppliesToEveryImage()
+    {
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.Builtin;
+        using var decoder = new JxlDecoder(options);
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+
+        // Act
+        decoder.SetDesiredOutputColorProfile(p3);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        using var first = decoder.GetOutputColorProfile();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        using var second = decoder.GetOutputColorProfile();
+
+        // Assert
+        Assert.IsTrue(first.SameColorEncoding(p3));
+        Assert.IsTrue(second.SameColorEncoding(p3));
+    }
+
+    [TestMethod]
+    public void SetDesiredOutputColorProfile_Null_Clears()
+    {
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.Builtin;
+        using var decoder = new JxlDecoder(options);
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+        decoder.SetDesiredOutputColorProfile(p3);
+
+        // Act
+        decoder.SetDesiredOutputColorProfile(null);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Assert
+        using var output = decoder.GetOutputColorProfile();
+        Assert.IsFalse(output.SameColorEncoding(p3));
+    }
+
+    [TestMethod]
+    public void SetDesiredOutputColorProfile_NoCms_ReadInfoThrows()
+    {
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.None;
+        using var decoder = new JxlDecoder(options);
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+        decoder.SetDesiredOutputColorProfile(p3);
+        decoder.SetInput(data);
+
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.ReadInfo());
+        StringAssert.Contains(ex.Message, "requires a CMS");
+    }
+
     [TestMethod]
     public void SetOutputColorProfileSrgb_Convenience_Succeeds()
     {
@@ -295,6 +682,87 @@ public class ColorProfileTests
         Assert.AreEqual(JxlTransferFunctionType.Linear, outputProfile.TransferFunctionType);
     }
 
+    [TestMethod]
+    public void StandardProfiles_HaveExpectedPrimariesAndTransfer()
+    {
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+        Assert.AreEqual(JxlPrimariesType.P3, p3.PrimariesType);
+        Assert.AreEqual(JxlTransferFunctionType.Srgb, p3.TransferFunctionType);
+
+        using var pq = JxlColorProfile.CreateRec2020Pq();
+        Assert.AreEqual(JxlPrimariesType.Bt2100, pq.PrimariesType);
+        Assert.AreEqual(JxlTransferFunctionType.Pq, pq.TransferFunctionType);
+
+        using var hlg = JxlColorProfile.CreateRec2020Hlg();
+        Assert.AreEqual(JxlPrimariesType.Bt2100, hlg.PrimariesType);
+        Assert.AreEqual(JxlTransferFunctionType.Hlg, hlg.TransferFunctionType);
+    }
+
+    [TestMethod]
+    public void SetOutputColorProfile_Gamma22_Succeeds()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act
+        using var profile = JxlColorProfile.CreateGamma(2.2f);
+        decoder.SetOutputColorProfile(profile);
+        var pixels = decoder.GetPixels();
+
+        // Assert
+        using var outputProfile = decoder.GetOutputColorProfile();
+        Assert.AreEqual(JxlTransferFunctionType.Gamma, outputProfile.TransferFunctionType);
+        Assert.AreEqual(1f / 2.2f, outputProfile.GammaValue!.Value, 1e-4f);
+        Assert.IsTrue(pixels.Length > 0);
+    }
+
+    [TestMethod]
+    public void CreateGamma_InvalidGamma_Throws()
+    {
+        Assert.ThrowsException<JxlException>(() => JxlColorProfile.CreateGamma(0.5f));
+    }
+
+    [TestMethod]
+    public void GetChromaticAdaptationMatrix_D65ToD50_MatchesBradford()
+    {
+        // Act
+        var m = JxlColorProfile.GetChromaticAdaptationMatrix(0.3127f, 0.3290f, 0.3457f, 0.3585f);
+
+        // Assert - reference Bradford D65 -> D50 matrix
+        float[] expected =
+        {
+            1.0478f, 0.0229f, -0.0501f,
+            0.0295f, 0.9905f, -0.0170f,
+            -0.0092f, 0.0150f, 0.7521f,
+        };
+        Assert.AreEqual(9, m.Length);
+        for (int i = 0; i < 9; i++)
+        {
+            Assert.AreEqual(expected[i], m[i], 2e-3f, $"Element {i}");
+        }
+    }
+
+    [TestMethod]
+    public void GetIccSizeHint_MatchesGeneratedIcc()
+    {
+        using var srgb = JxlColorProfile.CreateSrgb();
+        using var gray = JxlColorProfile.CreateGamma(2.2f, grayscale: true);
+        using var pq = JxlColorProfile.CreateRec2020Pq();
+
+        foreach (var profile in new[] { srgb, gray, pq })
+        {
+            var hint = profile.GetIccSizeHint();
+            var iccData = profile.TryAsIcc();
+
+            Assert.IsNotNull(iccData);
+            Assert.AreEqual(iccData.Length, hint, profile.GetDescription());
+            Assert.AreEqual(iccData.Length, profile.GetIccSizeHint());
+        }
+    }
+
     [TestMethod]
     public void TryAsIcc_SimpleProfile_CanConvertToIcc()
     {
@@ -383,6 +851,36 @@ public class ColorProfileTests
         Assert.IsTrue(profile.IsHdr);
     }
 
+    [TestMethod]
+    public void GetEmbeddedTransferFunction_HdrImage_ReturnsPq()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/hdr_pq_test.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act
+        var transferFunction = decoder.GetEmbeddedTransferFunction();
+
+        // Assert
+        Assert.IsNotNull(transferFunction);
+        Assert.AreEqual(JxlTransferFunctionTag.Pq, transferFunction.Value.Tag);
+    }
+
+    [TestMethod]
+    public void GetEmbeddedTransferFunction_IccProfile_ReturnsNull()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/with_icc.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act & Assert
+        Assert.IsNull(decoder.GetEmbeddedTransferFunction());
+    }
+
     [TestMethod]
     public void HdrHlgImage_HasHlgTransferFunction()
     {
@@ -612,6 +1110,45 @@ public class ColorProfileTests
         Assert.IsTrue(profile.IsHlg);
     }
 
+    [TestMethod]
+    public void IsHdr_IccProfileWithPqCicp_ReturnsTrue()
+    {
+        using var pq = JxlColorProfile.CreateRec2020Pq();
+        using var srgb = JxlColorProfile.CreateSrgb();
+        using var pqIcc = JxlColorProfile.FromIcc(pq.TryAsIcc()!);
+        using var srgbIcc = JxlColorProfile.FromIcc(srgb.TryAsIcc()!);
+
+        Assert.IsTrue(pqIcc.IsIcc);
+        Assert.IsTrue(pqIcc.IsHdr);
+        Assert.IsFalse(srgbIcc.IsHdr);
+    }
+
+    [TestMethod]
+    public void TryGetTransferFunction_IccGammaProfile_DetectsGamma()
+    {
+        // Grayscale ICC profiles carry no cicp tag, so the kTRC curve is classified
+        using var gamma = JxlColorProfile.CreateGamma(2.2f, grayscale: true);
+        using var icc = JxlColorProfile.FromIcc(gamma.TryAsIcc()!);
+
+        Assert.IsTrue(icc.TryGetTransferFunction(out var type, out var value));
+        Assert.AreEqual(JxlTransferFunctionType.Gamma, type);
+        Assert.AreEqual(1f / 2.2f, value, 1e-3f);
+    }
+
+    [TestMethod]
+    public void IsEquivalentTo_IccAndSimpleProfile_MatchSemantically()
+    {
+        using var srgb = JxlColorProfile.CreateSrgb();
+        using var srgbIcc = JxlColorProfile.FromIcc(srgb.TryAsIcc()!);
+        using var p3 = JxlColorProfile.CreateDisplayP3();
+
+        Assert.IsFalse(srgbIcc.SameColorEncoding(srgb));
+        Assert.IsTrue(srgbIcc.IsEquivalentTo(srgb));
+        Assert.IsTrue(srgbIcc.IsEquivalentToSrgb());
+        Assert.IsFalse(srgbIcc.IsEquivalentTo(p3));
+        Assert.IsFalse(p3.IsEquivalentToSrgb());
+    }
+
     [TestMethod]
     public void FromEncoding_DisplayP3_CreatesWideGamutProfile()
     {
@@ -1265,4 +1802,49 @@ public class ColorProfileTests
         Assert.IsNotNull(colorSpace.Value.GreenPrimary, "RGB profile should have green primary");
         Assert.IsNotNull(colorSpace.Value.BluePrimary, "RGB profile should have blue primary");
     }
+
+    [TestMethod]
+    public void CmsTransform_SrgbToLinear_ReusedAcrossBuffers()
+    {
+        // Arrange
+        using var srgb = JxlColorProfile.CreateSrgb();
+        using var linear = srgb.WithLinearTransferFunction();
+        Assert.IsNotNull(linear);
+        var srgbIcc = srgb.TryAsIcc();
+        var linearIcc = linear.TryAsIcc();
+        Assert.IsNotNull(srgbIcc);
+        Assert.IsNotNull(linearIcc);
+        using var transform = JxlCmsTransform.Create(srgbIcc, linearIcc, RenderingIntent.Relative);
+
+        foreach (var value in new[] { 0.5f, 1.0f })
+        {
+            var samples = new[] { value, value, value };
+
+            // Act
+            transform.Apply(samples);
+
+            // Assert
+            var expected = (float)Math.Pow((value + 0.055) / 1.055, 2.4);
+            foreach (var sample in samples)
+            {
+                Assert.AreEqual(expected, sample, 1e-3f);
+            }
+        }
+    }
+
+    [TestMethod]
+    public void CmsTransform_PartialPixel_Throws()
+    {
+        // Arrange
+        using var srgb = JxlColorProfile.CreateSrgb();
+        var srgbIcc = srgb.TryAsIcc();
+        Assert.IsNotNull(srgbIcc);
+        using var transform = JxlCmsTransform.Create(srgbIcc, srgbIcc);
+
+        // Act
+        var ex = Assert.ThrowsException<JxlException>(() => transform.Apply(new float[4]));
+
+        // Assert
+        Assert.AreEqual(JxlStatus.InvalidArgument, ex.Status);
+    }
 }
@@ -124,6 +124,34 @@ public class ExifMetadataTests
         Assert.AreEqual(JxlOrientation.Rotate180, orientation);
     }
 
+    [TestMethod]
+    public void TryGetOrientation_NonZeroTiffOffset_SkipsPadding()
+    {
+        // Arrange - TIFF header starts 8 bytes after the offset prefix
+        var exifData = BuildExifWithOrientation(6, tiffOffset: 8); // Rotate 90 CW
+
+        // Act
+        var success = ExifDataParser.TryGetOrientation(exifData, out var orientation);
+
+        // Assert
+        Assert.IsTrue(success);
+        Assert.AreEqual(JxlOrientation.Rotate90Cw, orientation);
+    }
+
+    [TestMethod]
+    public void TryParse_TiffOffsetPastEnd_ReturnsNull()
+    {
+        // Arrange - offset points beyond the available data
+        var exifData = BuildExifWithOrientation(1);
+        exifData[3] = 0xFF;
+
+        // Act
+        var result = ExifDataParser.TryParse(exifData);
+
+        // Assert
+        Assert.IsNull(result);
+    }
+
     [TestMethod]
     public void TryGetOrientation_NoOrientationTag_ReturnsFalse()
     {
@@ -367,6 +395,45 @@ public class ExifMetadataTests
         Assert.AreEqual("Test JUMBF content", testValue);
     }
 
+    [TestMethod]
+    public void ParseJumbf_ImageWithJumbf_ListsLabeledBoxes()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/single_jumbf.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var jumbf = decoder.Metadata.GetJumbfBox(0)!.Value.Data;
+
+        // Act
+        var boxes = JxlMetadata.ParseJumbf(jumbf);
+
+        // Assert
+        Assert.AreEqual(2, boxes.Length);
+        Assert.AreEqual("jumd", boxes[0].Type);
+        Assert.AreEqual("Test JUMBF content", boxes[0].Label);
+        Assert.AreEqual("json", boxes[1].Type);
+        Assert.AreEqual(0, boxes[1].Depth);
+        Assert.IsNull(boxes[1].Label);
+        var json = System.Text.Encoding.UTF8.GetString(jumbf, boxes[1].Offset, boxes[1].Length);
+        Assert.AreEqual(@"{""test"": ""Test JUMBF content""}", json);
+    }
+
+    [TestMethod]
+    public void ParseJumbf_TruncatedBox_ThrowsWithBoxAndOffset()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/single_jumbf.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var jumbf = decoder.Metadata.GetJumbfBox(0)!.Value.Data;
+
+        // Act & Assert
+        var ex = Assert.ThrowsException<JxlException>(() => JxlMetadata.ParseJumbf(jumbf.AsSpan(0, jumbf.Length - 1)));
+        StringAssert.StartsWith(ex.Message, "Box 'json' at offset");
+    }
+
     [TestMethod]
     public void HasJumbfData_ImageWithoutJumbf_ReturnsFalse()
     {
@@ -876,6 +943,65 @@ public class ExifMetadataTests
         Assert.AreEqual("Test JUMBF content", GetJumbfTestValue(decompressed));
     }
 
+    [TestMethod]
+    public void Decompress_BrotliJumbfBox_MatchesManagedBrotli()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/single_jumbf_brob.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var jumbf = decoder.Metadata.GetJumbfBox(0)!.Value;
+
+        // Act
+        var decompressed = jumbf.Decompress();
+
+        // Assert
+        CollectionAssert.AreEqual(DecompressBrotli(jumbf), decompressed);
+        Assert.AreEqual("Test JUMBF content", GetJumbfTestValue(decompressed));
+    }
+
+    [TestMethod]
+    public void Decompress_OverSizeLimit_ThrowsBufferTooSmall()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/single_jumbf_brob.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var jumbf = decoder.Metadata.GetJumbfBox(0)!.Value;
+        var size = DecompressBrotli(jumbf).Length;
+
+        // Act
+        var decompressed = jumbf.Decompress(size);
+        var ex = Assert.ThrowsException<JxlException>(() => jumbf.Decompress(size - 1));
+
+        // Assert
+        Assert.AreEqual(size, decompressed.Length);
+        Assert.AreEqual(JxlStatus.BufferTooSmall, ex.Status);
+    }
+
+    [TestMethod]
+    public void Decompress_UncompressedBox_ReturnsData()
+    {
+        // Arrange
+        var box = new JxlMetadataBox(new byte[] { 1, 2, 3 }, isBrotliCompressed: false);
+
+        // Act & Assert
+        Assert.AreSame(box.Data, box.Decompress());
+    }
+
+    [TestMethod]
+    public void Decompress_InvalidBrotliData_Throws()
+    {
+        // Arrange
+        var box = new JxlMetadataBox(new byte[] { 1, 2, 3 }, isBrotliCompressed: true);
+
+        // Act & Assert
+        var ex = Assert.ThrowsException<JxlException>(() => box.Decompress());
+        Assert.AreEqual(JxlStatus.Error, ex.Status);
+    }
+
     [TestMethod]
     public void GetExifBox_Uncompressed_ReturnsNotCompressed()
     {
@@ -945,6 +1071,26 @@ public class ExifMetadataTests
         Assert.AreEqual("JUMBF data", GetJumbfTestValue(DecompressBrotli(jumbf.Value)));
     }
 
+    [TestMethod]
+    public void Scan_BeforeReadInfo_ReturnsAllMetadata()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/all_metadata_brob.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+
+        // Act - no header is decoded
+        Assert.IsTrue(decoder.Metadata.Scan());
+
+        // Assert
+        Assert.AreEqual(1, decoder.Metadata.ExifBoxCount);
+        Assert.AreEqual(1, decoder.Metadata.XmlBoxCount);
+        Assert.AreEqual(1, decoder.Metadata.JumbfBoxCount);
+        var exif = decoder.Metadata.GetExifBox(0);
+        Assert.IsNotNull(exif);
+        Assert.AreEqual("EXIF data", GetExifImageDescription(DecompressBrotli(exif.Value)));
+    }
+
     [TestMethod]
     public void MultipleBrotliExifBoxes_AllMarkedCompressed()
     {
@@ -995,6 +1141,24 @@ public class ExifMetadataTests
         Assert.AreEqual("Test EXIF content", GetExifImageDescription(decompressed));
     }
 
+    [TestMethod]
+    public void GetExifData_NonZeroTiffOffset_ParsesFromOffset()
+    {
+        // Arrange - created with create-test-metadata --exif-offset 8
+        var data = File.ReadAllBytes("TestData/exif_tiff_offset.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act
+        var exif = decoder.Metadata.GetExifBox(0);
+
+        // Assert
+        Assert.IsNotNull(exif);
+        Assert.AreEqual(8, exif.Value.Data[3], "Fixture should have a non-zero TIFF offset");
+        Assert.AreEqual("Offset TIFF header", GetExifImageDescription(exif.Value.Data));
+    }
+
     // =========================================
     // Helper methods
     // =========================================
@@ -1063,11 +1227,12 @@ public class ExifMetadataTests
         return data.ToArray();
     }
 
-    private static byte[] BuildExifWithOrientation(ushort orientation)
+    private static byte[] BuildExifWithOrientation(ushort orientation, int tiffOffset = 0)
     {
         var data = new List<byte>();
-        // 4-byte TIFF offset (JXL container prefix)
-        data.AddRange(new byte[] { 0, 0, 0, 0 });
+        // 4-byte big-endian TIFF offset (JXL container prefix), then padding up to the TIFF header
+        data.AddRange(new byte[] { 0, 0, 0, (byte)tiffOffset });
+        data.AddRange(new byte[tiffOffset]);
         // Byte order marker (little endian)
         data.AddRange(new byte[] { 0x49, 0x49 });
         // TIFF magic (42)
@@ -1,3 +1,4 @@
+using System.Runtime.InteropServices;
 using JpegXL.Net;
 
 namespace JpegXL.Net.Tests;
@@ -68,6 +69,23 @@ public class JxlDecoderTests
         Assert.IsTrue(result);
     }
 
+    [TestMethod]
+    public void TryPeekIsAnimated_ReadsHeaderOnly()
+    {
+        // Arrange
+        var animation = File.ReadAllBytes("TestData/animation_lossless.jxl");
+        var still = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+
+        // Act & Assert
+        Assert.IsTrue(JxlImage.TryPeekIsAnimated(animation, out var animated));
+        Assert.IsTrue(animated);
+        Assert.IsTrue(JxlImage.TryPeekIsAnimated(still.AsSpan(0, 10), out animated));
+        Assert.IsFalse(animated);
+        Assert.IsFalse(JxlImage.TryPeekIsAnimated(still.AsSpan(0, 5), out _));
+        Assert.IsFalse(JxlImage.TryPeekIsAnimated(ReadOnlySpan<byte>.Empty, out _));
+        Assert.ThrowsException<JxlException>(() => JxlImage.TryPeekIsAnimated(new byte[16], out _));
+    }
+
     [TestMethod]
     public void IsJxl_WithContainerData_ReturnsTrue()
     {
@@ -133,6 +151,50 @@ public class JxlDecoderTests
         Assert.AreEqual(3 * 3 * 4, image.Pixels.Length); // 3x3 pixels * 4 bytes
     }
 
+    [TestMethod]
+    [DataRow(1, 1, DisplayName = "1x1")]
+    [DataRow(1, 7, DisplayName = "Single column")]
+    [DataRow(7, 1, DisplayName = "Single row")]
+    public void Decode_DegenerateDimensions_MatchesGolden(int width, int height)
+    {
+        // Arrange - lossless fixtures whose golden references are the source pixels
+        var name = $"{width}x{height}_rgba_lossless";
+        var data = File.ReadAllBytes($"TestData/{name}.jxl");
+        var expected = File.ReadAllBytes($"TestData/golden/{name}.raw");
+
+        // Act
+        using var image = JxlImage.Decode(data, JxlPixelFormat.Rgba8);
+
+        // Assert
+        Assert.AreEqual(width, image.Width);
+        Assert.AreEqual(height, image.Height);
+        CollectionAssert.AreEqual(expected, image.GetPixelArray());
+    }
+
+    [TestMethod]
+    public void Decode_AdjustOrientation_ReportsWhatIsLeftToApply()
+    {
+        // Arrange - a 3x2 image stored with a 90 degree clockwise rotation
+        var data = File.ReadAllBytes("TestData/rotate90_lossless.jxl");
+        var stored = File.ReadAllBytes("TestData/golden/rotate90_lossless.raw");
+        var manual = JxlDecodeOptions.Default;
+        manual.AdjustOrientation = false;
+
+        // Act
+        using var upright = JxlImage.Decode(data, JxlPixelFormat.Rgba8, JxlDecodeOptions.Default);
+        using var unrotated = JxlImage.Decode(data, JxlPixelFormat.Rgba8, manual);
+
+        // Assert
+        Assert.AreEqual(2, upright.Width);
+        Assert.AreEqual(3, upright.Height);
+        Assert.AreEqual(JxlOrientation.Identity, upright.BasicInfo.Orientation);
+
+        Assert.AreEqual(3, unrotated.Width);
+        Assert.AreEqual(2, unrotated.Height);
+        Assert.AreEqual(JxlOrientation.Rotate90Cw, unrotated.BasicInfo.Orientation);
+        CollectionAssert.AreEqual(stored, unrotated.GetPixelArray());
+    }
+
     [TestMethod]
     public void Decode_Dice_ReturnsValidImage()
     {
@@ -183,6 +245,77 @@ public class JxlDecoderTests
         Assert.IsFalse(info.IsAnimated, "dice.jxl should not be animated");
     }
 
+    [TestMethod]
+    public void GetOutputBitDepth_10BitSource_ReportsSourcePrecision()
+    {
+        // Arrange - hdr_pq_test.jxl is a 10-bit image
+        var data = File.ReadAllBytes("TestData/hdr_pq_test.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        var info = decoder.ReadInfo();
+
+        // Act
+        decoder.SetPixelFormat(JxlPixelFormat.Rgba16);
+        var u16 = decoder.GetOutputBitDepth();
+        decoder.SetPixelFormat(JxlPixelFormat.Rgba8);
+        var u8 = decoder.GetOutputBitDepth();
+
+        // Assert - basic info reports the source, not the output format
+        Assert.AreEqual(10u, info.BitDepth.BitsPerSample);
+        Assert.IsTrue(u16.IsInteger);
+        Assert.AreEqual(10u, u16.BitsPerSample);
+        Assert.AreEqual(8u, u8.BitsPerSample);
+    }
+
+    [TestMethod]
+    public void ScaleToFullRange_Disabled_KeepsSourceMagnitude()
+    {
+        // Arrange - hdr_pq_test.jxl is a 10-bit image
+        var data = File.ReadAllBytes("TestData/hdr_pq_test.jxl");
+        ushort[] Decode(bool scaleToFullRange)
+        {
+            var options = JxlDecodeOptions.Default;
+            options.PixelFormat = JxlPixelFormat.Rgba16;
+            options.ScaleToFullRange = scaleToFullRange;
+            using var decoder = new JxlDecoder(options);
+            decoder.SetInput(data);
+            decoder.ReadInfo();
+            return MemoryMarshal.Cast<byte, ushort>(decoder.GetPixels()).ToArray();
+        }
+
+        // Act
+        var full = Decode(true);
+        var source = Decode(false);
+
+        // Assert
+        Assert.AreEqual(65535, full.Max());
+        Assert.AreEqual(1023, source.Max());
+        for (var i = 0; i < full.Length; i++)
+        {
+            Assert.AreEqual(full[i], source[i] * 65535.0 / 1023.0, 0.5);
+        }
+    }
+
+    [TestMethod]
+    public void SetPixelFormat_GrayscaleForColorImage_ThrowsInvalidArgument()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var format = JxlPixelFormat.Rgba8;
+        format.ColorType = JxlColorType.Grayscale;
+
+        // Act
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.SetPixelFormat(format));
+
+        // Assert - the previous format stays in effect
+        Assert.AreEqual(JxlStatus.InvalidArgument, ex.Status);
+        StringAssert.Contains(ex.Message, "requires a grayscale image");
+        Assert.AreEqual(3 * 3 * 4, decoder.GetBufferSize());
+    }
+
     [TestMethod]
     public void IsJxl_WithRealJxlFile_ReturnsTrue()
     {
@@ -196,6 +329,239 @@ public class JxlDecoderTests
         Assert.IsTrue(result);
     }
 
+    [TestMethod]
+    public void ComputeCrc32_MatchesStandardCheckValue()
+    {
+        var data = System.Text.Encoding.ASCII.GetBytes("123456789");
+
+        Assert.AreEqual(0xCBF43926u, JxlImage.ComputeCrc32(data));
+        Assert.AreEqual(0u, JxlImage.ComputeCrc32(ReadOnlySpan<byte>.Empty));
+    }
+
+    [TestMethod]
+    public void ValidateContainer_TruncatedBox_ThrowsWithBoxAndOffset()
+    {
+        // Arrange - mixed_compression.jxl has a brob box at offset 88
+        var data = File.ReadAllBytes("TestData/mixed_compression.jxl");
+        JxlImage.ValidateContainer(data);
+
+        // Act
+        var ex = Assert.ThrowsException<JxlException>(() => JxlImage.ValidateContainer(data.AsSpan(0, 100)));
+
+        // Assert
+        Assert.AreEqual(JxlStatus.Error, ex.Status);
+        StringAssert.StartsWith(ex.Message, "Box 'brob' at offset 88 is truncated");
+    }
+
+    [TestMethod]
+    public void GetInputCrc32_CodestreamOnly_IgnoresContainerBoxes()
+    {
+        // Arrange - all_metadata.jxl is a container with metadata boxes
+        var data = File.ReadAllBytes("TestData/all_metadata.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+
+        // Act
+        var full = decoder.GetInputCrc32();
+        var codestream = decoder.GetInputCrc32(codestreamOnly: true);
+
+        // Assert
+        Assert.AreEqual(JxlImage.ComputeCrc32(data), full);
+        Assert.AreEqual(JxlImage.ComputeCrc32(data, codestreamOnly: true), codestream);
+        Assert.AreNotEqual(full, codestream);
+    }
+
+    [TestMethod]
+    public void DecodeThumbnail_ScalesToFitMaxDimension()
+    {
+        // Arrange - dice.jxl is 800x600
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+
+        // Act
+        var pixels = JxlImage.DecodeThumbnail(data, 200, out var width, out var height);
+
+        // Assert
+        Assert.AreEqual(200, width);
+        Assert.AreEqual(150, height);
+        Assert.AreEqual(width * height * 4, pixels.Length);
+    }
+
+    [TestMethod]
+    public void DecodeThumbnail_SmallImage_IsNotUpscaled()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        using var image = JxlImage.Decode(data, JxlPixelFormat.Rgba8);
+
+        // Act
+        var pixels = JxlImage.DecodeThumbnail(data, 256, out var width, out var height);
+
+        // Assert
+        Assert.AreEqual(3, width);
+        Assert.AreEqual(3, height);
+        CollectionAssert.AreEqual(image.GetPixelArray(), pixels);
+    }
+
+    [TestMethod]
+    public void DecodeRgba8Srgb_RotatedImage_IsUpright()
+    {
+        // Arrange - rotate90_lossless.jxl is 3x2 with a 90 degree rotation in its header
+        var data = File.ReadAllBytes("TestData/rotate90_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.AdjustOrientation = false;
+        options.PixelFormat = JxlPixelFormat.Rgb8;
+
+        // Act
+        var pixels = JxlImage.DecodeRgba8Srgb(data, out var width, out var height, options);
+
+        // Assert
+        Assert.AreEqual(2, width);
+        Assert.AreEqual(3, height);
+        Assert.AreEqual(width * height * 4, pixels.Length);
+    }
+
+    [TestMethod]
+    public void DecodeAnimation_PassesEachFrameInOrder()
+    {
+        // Arrange - animation_lossless.jxl has three 8x8 frames of 100 ms
+        var data = File.ReadAllBytes("TestData/animation_lossless.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.SetPixelFormat(JxlPixelFormat.Rgb8);
+        decoder.ReadInfo();
+        var expected = new List<byte[]>();
+        while (decoder.HasMoreFrames() && decoder.Process() == JxlDecoderEvent.HaveFrameHeader)
+        {
+            decoder.Process();
+            var frame = new byte[decoder.GetBufferSize()];
+            decoder.ReadPixels(frame);
+            expected.Add(frame);
+        }
+
+        // Act
+        var frames = new List<(int Index, byte[] Pixels, int Width, int Height, float DurationMs)>();
+        JxlImage.DecodeAnimation(data, JxlPixelFormat.Rgb8, null, (index, pixels, width, height, durationMs) =>
+        {
+            frames.Add((index, pixels.ToArray(), width, height, durationMs));
+            return true;
+        });
+        var firstOnly = 0;
+        JxlImage.DecodeAnimation(data, JxlPixelFormat.Rgb8, null, (_, _, _, _, _) => ++firstOnly < 1);
+
+        // Assert
+        Assert.AreEqual(3, expected.Count);
+        Assert.AreEqual(3, frames.Count);
+        for (var i = 0; i < frames.Count; i++)
+        {
+            Assert.AreEqual(i, frames[i].Index);
+            Assert.AreEqual((8, 8, 100f), (frames[i].Width, frames[i].Height, frames[i].DurationMs));
+            CollectionAssert.AreEqual(expected[i], frames[i].Pixels);
+        }
+        Assert.AreEqual(1, firstOnly);
+    }
+
+    [TestMethod]
+    public void Clone_DecodesFramesInParallel()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/animation_lossless.jxl");
+        var expected = new List<byte[]>();
+        JxlImage.DecodeAnimation(data, JxlPixelFormat.Rgb8, null, (_, pixels, _, _, _) =>
+        {
+            expected.Add(pixels.ToArray());
+            return true;
+        });
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.SetPixelFormat(JxlPixelFormat.Rgb8);
+        decoder.ReadInfo();
+        var clones = Enumerable.Range(0, 3).Select(_ => decoder.Clone()).ToArray();
+
+        // Act
+        var frames = new byte[3][];
+        Parallel.For(0, clones.Length, i =>
+        {
+            using var clone = clones[i];
+            clone.ReadInfo();
+            clone.SeekToFrame(i);
+            frames[i] = new byte[clone.GetBufferSize()];
+            clone.ReadPixels(frames[i]);
+        });
+
+        // Assert
+        Assert.AreEqual(3, expected.Count);
+        for (var i = 0; i < frames.Length; i++)
+        {
+            CollectionAssert.AreEqual(expected[i], frames[i]);
+        }
+    }
+
+    [TestMethod]
+    public void DecodeAnimation_CallbackException_StopsAndPropagates()
+    {
+        var data = File.ReadAllBytes("TestData/animation_lossless.jxl");
+        var calls = 0;
+
+        Assert.ThrowsException<InvalidOperationException>(() => JxlImage.DecodeAnimation(
+            data, JxlPixelFormat.Rgb8, null, (_, _, _, _, _) =>
+            {
+                calls++;
+                throw new InvalidOperationException();
+            }));
+        Assert.AreEqual(1, calls);
+    }
+
+    [TestMethod]
+    public void SetLogHandler_ReceivesDecodeMilestones()
+    {
+        // Arrange - the handler is global, so other tests may log to it too
+        var data = File.ReadAllBytes("TestData/1x1_rgba_lossless.jxl");
+        var messages = new System.Collections.Concurrent.ConcurrentQueue<(JxlLogLevel, string)>();
+        JxlImage.SetLogHandler((level, message) => messages.Enqueue((level, message)));
+
+        // Act
+        try
+        {
+            using var image = JxlImage.Decode(data, JxlPixelFormat.Rgba8);
+        }
+        finally
+        {
+            JxlImage.SetLogHandler(null);
+        }
+
+        // Assert
+        CollectionAssert.Contains(messages.ToArray(), (JxlLogLevel.Info, "Read basic info: 1x1, 1 extra channels"));
+        CollectionAssert.Contains(messages.ToArray(), (JxlLogLevel.Debug, "Decoded frame 0"));
+    }
+
+    [TestMethod]
+    public void EncodeLossless_RoundTripsDecodedPixels()
+    {
+        // Arrange - dice.jxl has transparency and spans several groups
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        using var original = JxlImage.Decode(data, JxlPixelFormat.Rgba8);
+        var pixels = original.GetPixelArray();
+
+        // Act
+        byte[] encoded;
+        try
+        {
+            encoded = JxlImage.EncodeLossless(pixels, original.Width, original.Height);
+        }
+        catch (JxlException ex) when (ex.Message.Contains("not compiled in"))
+        {
+            Assert.Inconclusive("Native library was built without the encode feature");
+            return;
+        }
+        using var decoded = JxlImage.Decode(encoded, JxlPixelFormat.Rgba8);
+
+        // Assert
+        Assert.IsTrue(JxlImage.IsJxl(encoded));
+        Assert.AreEqual(original.Width, decoded.Width);
+        Assert.AreEqual(original.Height, decoded.Height);
+        CollectionAssert.AreEqual(pixels, decoded.GetPixelArray());
+    }
+
     [TestMethod]
     public void Decode_WithPremultiplyAlpha_PremultipliesCorrectly()
     {
@@ -254,6 +620,96 @@ public class JxlDecoderTests
         Assert.IsTrue(foundSemiTransparent, "No semi-transparent pixels found in dice.jxl");
     }
 
+    [TestMethod]
+    public void Decode_WithUnpremultiplyAlpha_StraightAlphaSourceUnchanged()
+    {
+        // Arrange - dice.jxl has straight (unassociated) alpha
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        var format = JxlPixelFormat.Rgba8;
+
+        // Act
+        using var defaultImage = JxlImage.Decode(data, format, JxlDecodeOptions.Default);
+        var options = JxlDecodeOptions.Default;
+        options.UnpremultiplyAlpha = true;
+        using var unpremulImage = JxlImage.Decode(data, format, options);
+
+        // Assert
+        Assert.IsFalse(defaultImage.BasicInfo.AlphaPremultiplied);
+        CollectionAssert.AreEqual(defaultImage.GetPixelArray(), unpremulImage.GetPixelArray());
+    }
+
+    [TestMethod]
+    public void Decode_Bgra8Premultiplied_SwapsChannelsAndScalesByAlpha()
+    {
+        // Arrange - the GDI layout: B,G,R,A with premultiplied color
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+
+        // Act
+        using var rgbaImage = JxlImage.Decode(data, JxlPixelFormat.Rgba8,
+            new JxlDecodeOptions { PremultiplyAlpha = false });
+        using var bgraImage = JxlImage.Decode(data, JxlPixelFormat.Bgra8,
+            new JxlDecodeOptions { PremultiplyAlpha = true });
+
+        var rgba = rgbaImage.GetPixelArray();
+        var bgra = bgraImage.GetPixelArray();
+
+        // Assert - every pixel matches the straight RGBA decode reordered and premultiplied
+        Assert.AreEqual(rgba.Length, bgra.Length);
+        int semiTransparent = 0;
+        for (int i = 0; i < rgba.Length; i += 4)
+        {
+            byte a = rgba[i + 3];
+            Assert.AreEqual(a, bgra[i + 3], $"Alpha mismatch at pixel {i / 4}");
+            if (a > 0 && a < 255)
+            {
+                semiTransparent++;
+            }
+
+            for (int c = 0; c < 3; c++)
+            {
+                int expected = (rgba[i + 2 - c] * a + 127) / 255;
+                Assert.IsTrue(Math.Abs(bgra[i + c] - expected) <= 1,
+                    $"Channel {c} mismatch at pixel {i / 4}: expected {expected}, got {bgra[i + c]}");
+            }
+        }
+
+        Assert.IsTrue(semiTransparent > 0, "No semi-transparent pixels found in dice.jxl");
+    }
+
+    [TestMethod]
+    public void Decode_WithHighPrecision_MatchesFastPathWithinTolerance()
+    {
+        // Arrange - dice.jxl is lossy (XYB), so the precision toggle affects the render pipeline
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        var format = JxlPixelFormat.Rgba8;
+        var optionsFast = JxlDecodeOptions.Default;
+        optionsFast.HighPrecision = false;
+        var optionsPrecise = JxlDecodeOptions.Default;
+        optionsPrecise.HighPrecision = true;
+
+        // Act
+        using var fastImage = JxlImage.Decode(data, format, optionsFast);
+        using var preciseImage = JxlImage.Decode(data, format, optionsPrecise);
+
+        var fastPixels = fastImage.GetPixelArray();
+        var precisePixels = preciseImage.GetPixelArray();
+
+        // Assert - both paths must agree to within a couple of 8-bit code values
+        Assert.AreEqual(fastPixels.Length, precisePixels.Length);
+
+        int maxDiff = 0;
+        int differingSamples = 0;
+        for (int i = 0; i < fastPixels.Length; i++)
+        {
+            int diff = Math.Abs(fastPixels[i] - precisePixels[i]);
+            if (diff > 0) differingSamples++;
+            maxDiff = Math.Max(maxDiff, diff);
+        }
+
+        Console.WriteLine($"HighPrecision: {differingSamples} of {fastPixels.Length} samples differ, max diff {maxDiff}");
+        Assert.IsTrue(maxDiff <= 2, $"HighPrecision output deviates too much from fast path: max diff {maxDiff}");
+    }
+
     [TestMethod]
     public void StreamingDecode_WithChunkedInput_DecodesSuccessfully()
     {
@@ -429,6 +885,42 @@ public class JxlDecoderTests
         Assert.IsTrue(frameDurations.All(d => d > 0), "All frame durations should be positive");
     }
 
+    [TestMethod]
+    public void GetFrameDurationTicks_MatchesDurationMs()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/animation_spline.jxl");
+
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        var animation = decoder.ReadInfo().Animation!.Value;
+
+        // Act & Assert - ticks converted with the TPS fraction reproduce DurationMs
+        int frameCount = 0;
+        var evt = decoder.Process();
+        while (evt != JxlDecoderEvent.Complete)
+        {
+            if (evt == JxlDecoderEvent.HaveFrameHeader)
+            {
+                uint ticks = decoder.GetFrameDurationTicks();
+                double expectedMs = ticks * 1000.0 * animation.TpsDenominator / animation.TpsNumerator;
+
+                Assert.IsTrue(ticks > 0, $"Frame {frameCount} should have a positive duration");
+                Assert.AreEqual(expectedMs, decoder.GetFrameHeader().DurationMs, 0.01,
+                    $"Frame {frameCount} duration mismatch");
+                frameCount++;
+            }
+            else if (evt == JxlDecoderEvent.NeedOutputBuffer)
+            {
+                decoder.SkipFrame();
+            }
+
+            evt = decoder.Process();
+        }
+
+        Assert.IsTrue(frameCount > 1, $"Expected multiple frames, got {frameCount}");
+    }
+
     [TestMethod]
     public void ExtraChannelDecode_ReadsExtraChannelsInfo()
     {
@@ -459,6 +951,36 @@ public class JxlDecoderTests
             "First extra channel should be Alpha");
     }
 
+    [TestMethod]
+    public void ExtraChannelInfo_FullResolutionChannel_ReportsNoDownsampling()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/spot.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        var info = decoder.ReadInfo();
+
+        // Act
+        var channelInfo = decoder.GetExtraChannelInfo(info.ExtraChannels.Count - 1);
+
+        // Assert
+        Assert.AreEqual(0u, channelInfo.DimShift);
+        Assert.AreEqual(1, channelInfo.DownsampleFactor);
+        Assert.AreEqual(((int)info.Size.Width, (int)info.Size.Height),
+            channelInfo.GetStoredSize((int)info.Size.Width, (int)info.Size.Height));
+    }
+
+    [TestMethod]
+    public void ExtraChannelInfo_GetStoredSize_RoundsUp()
+    {
+        // Arrange
+        var channelInfo = new JxlExtraChannelInfo { ChannelType = JxlExtraChannelType.Depth, DimShift = 2 };
+
+        // Act & Assert
+        Assert.AreEqual(4, channelInfo.DownsampleFactor);
+        Assert.AreEqual((3, 1), channelInfo.GetStoredSize(9, 4));
+    }
+
     [TestMethod]
     public void ExtraChannelDecode_DecodesExtraChannelsToSeparateBuffers()
     {
@@ -547,149 +1069,911 @@ public class JxlDecoderTests
     }
 
     [TestMethod]
-    public void ParseFrameMetadata_ReturnsAnimationInfo()
+    public void ExtraChannelDecode_ImageWithoutExtraChannels_ActsLikeReadPixels()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.DecodeExtraChannels = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        var info = decoder.ReadInfo();
+        Assert.AreEqual(0, info.ExtraChannels.Count);
+
+        var evt = decoder.Process();
+        while (evt != JxlDecoderEvent.NeedOutputBuffer)
+        {
+            evt = decoder.Process();
+        }
+        var colorBuffer = new byte[decoder.GetBufferSize()];
+
+        // Act & Assert - a buffer for a missing channel throws, none decodes normally
+        var ex = Assert.ThrowsException<JxlException>(
+            () => decoder.ReadPixelsWithExtraChannels(colorBuffer, new byte[]?[] { new byte[9] }));
+        StringAssert.Contains(ex.Message, "extra channel buffers");
+        evt = decoder.ReadPixelsWithExtraChannels(colorBuffer, Span<byte[]?>.Empty);
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, evt);
+        using var reference = new JxlDecoder();
+        reference.SetInput(data);
+        reference.ReadInfo();
+        CollectionAssert.AreEqual(reference.GetPixels(), colorBuffer);
+    }
+
+    [TestMethod]
+    public void GetLastDecodeError_ReadableFromAnotherThread()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var evt = decoder.Process();
+        while (evt != JxlDecoderEvent.NeedOutputBuffer)
+        {
+            evt = decoder.Process();
+        }
+        Assert.ThrowsException<JxlException>(() => decoder.ReadPixels(new byte[1]));
+
+        // Act
+        var error = Task.Run(() => decoder.GetLastDecodeError()).Result;
+
+        // Assert
+        Assert.IsNotNull(error);
+        Assert.AreEqual(JxlStatus.Error, error.Status);
+        StringAssert.StartsWith(error.Message, "Buffer too small");
+        decoder.ReadPixels(new byte[decoder.GetBufferSize()]);
+        Assert.IsNull(decoder.GetLastDecodeError());
+    }
+
+    [TestMethod]
+    public void ExtraChannelDecode_UndersizedBuffer_ThrowsWithRequiredSize()
+    {
+        // Arrange - the second alpha channel gets its own buffer with RGBA output
+        var data = File.ReadAllBytes("TestData/two_alpha_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.DecodeExtraChannels = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        var evt = decoder.Process();
+        while (evt != JxlDecoderEvent.NeedOutputBuffer)
+        {
+            evt = decoder.Process();
+        }
+        var colorBuffer = new byte[decoder.GetBufferSize()];
+        var required = (int)decoder.GetExtraChannelBufferSize(1);
+
+        // Act & Assert
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.ReadPixelsWithExtraChannels(
+            colorBuffer, new byte[]?[] { null, new byte[required - 1] }));
+        StringAssert.Contains(ex.Message, "Extra channel buffer 1 too small");
+        StringAssert.Contains(ex.Message, $"{required} required");
+
+        // The frame can still be read with a correctly sized buffer
+        evt = decoder.ReadPixelsWithExtraChannels(
+            colorBuffer, new byte[]?[] { null, new byte[required] });
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, evt);
+    }
+
+    [TestMethod]
+    public void ExtraChannelDecode_ReportsWrittenBuffers()
+    {
+        // Arrange - the first alpha channel is folded into RGBA, the second is skipped
+        var data = File.ReadAllBytes("TestData/two_alpha_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.DecodeExtraChannels = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        var evt = decoder.Process();
+        while (evt != JxlDecoderEvent.NeedOutputBuffer)
+        {
+            evt = decoder.Process();
+        }
+        var colorBuffer = new byte[decoder.GetBufferSize()];
+        var alphaBuffer = new byte[decoder.GetExtraChannelBufferSize(0)];
+        var written = new bool[3];
+
+        // Act
+        evt = decoder.ReadPixelsWithExtraChannels(
+            colorBuffer, new byte[]?[] { alphaBuffer, null }, written);
+
+        // Assert
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, evt);
+        CollectionAssert.AreEqual(new[] { true, false, false }, written);
+    }
+
+    [TestMethod]
+    public void ExtraChannelDecode_SeparateFirstAlpha_MatchesColorAlpha()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/two_alpha_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.DecodeExtraChannels = true;
+        options.SeparateFirstAlpha = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        var evt = decoder.Process();
+        while (evt != JxlDecoderEvent.NeedOutputBuffer)
+        {
+            evt = decoder.Process();
+        }
+        var colorBuffer = new byte[decoder.GetBufferSize()];
+        var alphaBuffer = new byte[decoder.GetExtraChannelBufferSize(0)];
+        var written = new bool[2];
+
+        // Act
+        evt = decoder.ReadPixelsWithExtraChannels(colorBuffer, new byte[]?[] { alphaBuffer }, written);
+
+        // Assert
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, evt);
+        CollectionAssert.AreEqual(new[] { true, true }, written);
+        var colorAlpha = Enumerable.Range(0, alphaBuffer.Length).Select(i => colorBuffer[i * 4 + 3]).ToArray();
+        CollectionAssert.AreEqual(colorAlpha, alphaBuffer);
+    }
+
+    [TestMethod]
+    public void ExtraChannelDecode_SetExtraChannelFormat_OutputsFloat()
+    {
+        // Arrange - the second alpha channel as Float32 next to RGBA8 color
+        var data = File.ReadAllBytes("TestData/two_alpha_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.DecodeExtraChannels = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var pixels = decoder.GetBufferSize() / 4;
+
+        // Act
+        decoder.SetExtraChannelFormat(1, JxlDataFormat.Float32);
+        var evt = decoder.Process();
+        while (evt != JxlDecoderEvent.NeedOutputBuffer)
+        {
+            evt = decoder.Process();
+        }
+        var colorBuffer = new byte[decoder.GetBufferSize()];
+        var depthBuffer = new byte[decoder.GetExtraChannelBufferSize(1)];
+        evt = decoder.ReadPixelsWithExtraChannels(colorBuffer, new byte[]?[] { null, depthBuffer });
+
+        // Assert - samples match the channel decoded as Uint8
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, evt);
+        Assert.AreEqual(pixels * sizeof(float), depthBuffer.Length);
+        using var reference = new JxlDecoder(options);
+        reference.SetInput(data);
+        reference.ReadInfo();
+        evt = reference.Process();
+        while (evt != JxlDecoderEvent.NeedOutputBuffer)
+        {
+            evt = reference.Process();
+        }
+        var expected = new byte[reference.GetExtraChannelBufferSize(1)];
+        reference.ReadPixelsWithExtraChannels(new byte[reference.GetBufferSize()], new byte[]?[] { null, expected });
+        var samples = MemoryMarshal.Cast<byte, float>(depthBuffer).ToArray();
+        for (var i = 0; i < pixels; i++)
+        {
+            Assert.AreEqual(expected[i] / 255f, samples[i], 1e-3f);
+        }
+        Assert.ThrowsException<JxlException>(() => decoder.SetExtraChannelFormat(2, JxlDataFormat.Float32));
+    }
+
+    [TestMethod]
+    public void ParseFrameMetadata_ReturnsAnimationInfo()
+    {
+        // Arrange - use animated test file
+        var data = File.ReadAllBytes("TestData/animation_spline.jxl");
+
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+
+        // Act
+        var metadata = decoder.ParseFrameMetadata();
+        var basicInfo = decoder.BasicInfo;
+
+        // Assert
+        Assert.IsNotNull(basicInfo, "BasicInfo should be available after ParseFrameMetadata");
+        Assert.IsTrue(basicInfo.IsAnimated, "Should be animated");
+        Assert.IsTrue(metadata.FrameCount > 1, $"Expected multiple frames, got {metadata.FrameCount}");
+        Assert.AreEqual(metadata.Frames.Count, metadata.FrameCount, "FrameCount should match Frames.Count");
+        Assert.IsTrue(metadata.GetTotalDurationMs() > 0, "Should have positive total duration");
+        Assert.IsNull(metadata.FrameNames, "FrameNames should be null when includeNames is false");
+
+        // All frames should have valid dimensions
+        foreach (var frame in metadata.Frames)
+        {
+            Assert.IsTrue(frame.FrameWidth > 0, "Frame should have valid width");
+            Assert.IsTrue(frame.FrameHeight > 0, "Frame should have valid height");
+        }
+
+        // Verify frame durations are consistent (all should be equal for this test file)
+        var firstDuration = metadata.Frames[0].DurationMs;
+        Assert.IsTrue(metadata.Frames.All(f => Math.Abs(f.DurationMs - firstDuration) < 0.01f),
+            "All frames should have equal duration in animation_spline.jxl");
+    }
+
+    [TestMethod]
+    public void ParseFrameMetadata_StaticImage_ReturnsSingleFrame()
+    {
+        // Arrange - use static test file
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+
+        // Act
+        var metadata = decoder.ParseFrameMetadata();
+        var basicInfo = decoder.BasicInfo;
+
+        // Assert
+        Assert.IsNotNull(basicInfo, "BasicInfo should be available after ParseFrameMetadata");
+        Assert.IsFalse(basicInfo.IsAnimated, "Should not be animated");
+        Assert.AreEqual(1, metadata.FrameCount, "Static image should have 1 frame");
+        Assert.AreEqual(0, metadata.Frames[0].DurationMs, "Static image frame should have 0 duration");
+
+        // Verify dimensions are valid for dice.jxl
+        Assert.IsTrue(basicInfo.Size.Width > 0 && basicInfo.Size.Height > 0,
+            "Should have valid dimensions");
+    }
+
+    [TestMethod]
+    public void ParseFrameMetadata_MaxFramesLimit_ThrowsWhenExceeded()
+    {
+        // Arrange - use animated test file
+        var data = File.ReadAllBytes("TestData/animation_spline.jxl");
+
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+
+        // Act & Assert - limit to 2 frames should throw since animation has more
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.ParseFrameMetadata(maxFrames: 2));
+        Assert.IsTrue(ex.Message.Contains("exceeded limit"), $"Expected limit exceeded message, got: {ex.Message}");
+    }
+
+    [TestMethod]
+    public void ToneMapping_HdrPqFile_HasHighIntensityTarget()
+    {
+        // Arrange - HDR PQ (Perceptual Quantizer) test file
+        var data = File.ReadAllBytes("TestData/hdr_pq_test.jxl");
+
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+
+        // Act
+        var info = decoder.ReadInfo();
+
+        // Assert - HDR files should have IntensityTarget > 255 nits (SDR max)
+        Assert.IsTrue(info.ToneMapping.IntensityTarget > 255f,
+            $"HDR PQ file should have IntensityTarget > 255, got {info.ToneMapping.IntensityTarget}");
+        Assert.IsTrue(info.IsHdr, "IsHdr should be true for HDR PQ file");
+
+        // PQ content typically has intensity target in range 1000-10000 nits
+        Assert.IsTrue(info.ToneMapping.IntensityTarget >= 1000f && info.ToneMapping.IntensityTarget <= 10000f,
+            $"HDR PQ IntensityTarget should be in typical PQ range (1000-10000), got {info.ToneMapping.IntensityTarget}");
+
+        // MinNits should be non-negative
+        Assert.IsTrue(info.ToneMapping.MinNits >= 0f,
+            $"MinNits should be non-negative, got {info.ToneMapping.MinNits}");
+    }
+
+    [TestMethod]
+    public void ToneMapping_HdrHlgFile_HasHighIntensityTarget()
+    {
+        // Arrange - HDR HLG (Hybrid Log-Gamma) test file
+        var data = File.ReadAllBytes("TestData/hdr_hlg_test.jxl");
+
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+
+        // Act
+        var info = decoder.ReadInfo();
+
+        // Assert - HDR files should have IntensityTarget > 255 nits (SDR max)
+        Assert.IsTrue(info.ToneMapping.IntensityTarget > 255f,
+            $"HDR HLG file should have IntensityTarget > 255, got {info.ToneMapping.IntensityTarget}");
+        Assert.IsTrue(info.IsHdr, "IsHdr should be true for HDR HLG file");
+
+        // HLG typically uses 1000 nits as reference
+        Assert.IsTrue(info.ToneMapping.IntensityTarget >= 1000f,
+            $"HDR HLG IntensityTarget should be >= 1000 nits, got {info.ToneMapping.IntensityTarget}");
+
+        // MinNits should be non-negative
+        Assert.IsTrue(info.ToneMapping.MinNits >= 0f,
+            $"MinNits should be non-negative, got {info.ToneMapping.MinNits}");
+    }
+
+    [TestMethod]
+    public void ToneMapping_SdrFile_HasStandardIntensityTarget()
+    {
+        // Arrange - Standard SDR file
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+
+        // Act
+        var info = decoder.ReadInfo();
+
+        // Assert - SDR files should have IntensityTarget <= 255 nits
+        Assert.IsTrue(info.ToneMapping.IntensityTarget <= 255f,
+            $"SDR file should have IntensityTarget <= 255, got {info.ToneMapping.IntensityTarget}");
+        Assert.IsFalse(info.IsHdr, "IsHdr should be false for SDR file");
+
+        // SDR typically uses ~80-100 nits
+        Assert.IsTrue(info.ToneMapping.IntensityTarget >= 0f,
+            $"IntensityTarget should be non-negative, got {info.ToneMapping.IntensityTarget}");
+    }
+
+    [TestMethod]
+    public void ToneMapping_GenericCmsType_UsesDefaultMethod()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/hdr_pq_test.jxl");
+        byte[] Decode(JxlCmsType cmsType)
+        {
+            var options = JxlDecodeOptions.Default;
+            options.CmsType = cmsType;
+            using var decoder = new JxlDecoder(options);
+            decoder.SetInput(data);
+            decoder.ReadInfo();
+            using var srgb = JxlColorProfile.CreateSrgb();
+            decoder.SetOutputColorProfile(srgb);
+            return decoder.GetPixels();
+        }
+
+        // Act
+        var generic = Decode(JxlCmsType.ToneMap);
+        var perceptual = Decode(JxlCmsType.Bt2446aPerceptual);
+
+        // Assert - the default build uses Bt2446aPerceptual
+        CollectionAssert.AreEqual(perceptual, generic);
+    }
+
+    [TestMethod]
+    public void ToneMapping_ClipStats_CountsTonemappedPixels()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/hdr_pq_test.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.CmsType = JxlCmsType.Bt2446a;
+        options.ToneMapClipStats = true;
+
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        using var srgb = JxlColorProfile.CreateSrgb();
+        decoder.SetOutputColorProfile(srgb);
+
+        // Act
+        decoder.GetPixels();
+        var (clipped, total) = decoder.GetToneMapClipStats();
+
+        // Assert
+        Assert.IsTrue(total > 0, "HDR image should have tone-mapped pixels");
+        Assert.IsTrue(clipped <= total, $"Clipped ({clipped}) should not exceed total ({total})");
+
+        decoder.Rewind();
+        Assert.AreEqual((0UL, 0UL), decoder.GetToneMapClipStats());
+    }
+
+    [TestMethod]
+    public void ReadSingleChannel_ReturnsAlphaPlane()
+    {
+        // Arrange - dice.jxl has transparency
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        using var rgbaImage = JxlImage.Decode(data, JxlPixelFormat.Rgba8);
+        var rgba = rgbaImage.GetPixelArray();
+
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.SetPixelFormat(JxlPixelFormat.Rgba8);
+        JxlDecoderEvent evt;
+        while ((evt = decoder.Process()) != JxlDecoderEvent.NeedOutputBuffer)
+        {
+            Assert.AreNotEqual(JxlDecoderEvent.Complete, evt);
+        }
+        var alphaIndex = Enumerable.Range(0, decoder.GetExtraChannelCount())
+            .First(i => decoder.GetExtraChannelInfo(i).ChannelType == JxlExtraChannelType.Alpha);
+
+        // Act
+        var alpha = new byte[(int)decoder.GetExtraChannelBufferSize((uint)alphaIndex)];
+        evt = decoder.ReadSingleChannel(alphaIndex, alpha);
+
+        // Assert
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, evt);
+        Assert.AreEqual(rgba.Length / 4, alpha.Length);
+        for (int i = 0; i < alpha.Length; i++)
+        {
+            Assert.AreEqual(rgba[i * 4 + 3], alpha[i], $"Alpha mismatch at pixel {i}");
+        }
+    }
+
+    [TestMethod]
+    public void ComputeHistogram_CountsEveryPixel()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.LuminanceStats = true;
+
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        var info = decoder.ReadInfo();
+        Assert.ThrowsException<JxlException>(() => decoder.ComputeHistogram(16));
+
+        // Act
+        decoder.GetPixels();
+        var histogram = decoder.ComputeHistogram(16);
+
+        // Assert
+        Assert.AreEqual(16, histogram.Length);
+        Assert.AreEqual((long)info.Size.Width * (long)info.Size.Height, histogram.Sum(c => (long)c));
+        Assert.ThrowsException<ArgumentOutOfRangeException>(() => decoder.ComputeHistogram(0));
+    }
+
+    [TestMethod]
+    public void GetLuminanceStats_MeanWithinRange()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.LuminanceStats = true;
+
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        Assert.ThrowsException<JxlException>(() => decoder.GetLuminanceStats());
+
+        // Act
+        decoder.GetPixels();
+        var stats = decoder.GetLuminanceStats();
+
+        // Assert
+        Assert.IsTrue(stats.Min >= 0f);
+        Assert.IsTrue(stats.Max <= 1f);
+        Assert.IsTrue(stats.Min <= stats.Mean && stats.Mean <= stats.Max);
+    }
+
+    [TestMethod]
+    public void GetOpaqueBounds_CropsFadedColumns()
+    {
+        // Arrange - the first alpha fades from 255 on the left to 199 on the right
+        var data = File.ReadAllBytes("TestData/two_alpha_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.OpaqueBounds = true;
+        options.OpaqueAlphaThreshold = 0.9f;
+
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        var info = decoder.ReadInfo();
+        Assert.ThrowsException<JxlException>(() => decoder.GetOpaqueBounds());
+
+        // Act
+        var pixels = decoder.GetPixels();
+        var bounds = decoder.GetOpaqueBounds();
+
+        // Assert - matches a scan of the alpha samples
+        var width = (int)info.Size.Width;
+        var opaque = Enumerable.Range(0, pixels.Length / 4)
+            .Where(i => pixels[i * 4 + 3] / 255f > 0.9f)
+            .ToArray();
+        Assert.IsTrue(opaque.Length > 0 && opaque.Length < pixels.Length / 4);
+        Assert.AreEqual((uint)opaque.Min(i => i % width), bounds.X);
+        Assert.AreEqual((uint)opaque.Min(i => i / width), bounds.Y);
+        Assert.AreEqual((uint)(opaque.Max(i => i % width) + 1), bounds.X + bounds.Width);
+        Assert.AreEqual((uint)(opaque.Max(i => i / width) + 1), bounds.Y + bounds.Height);
+    }
+
+    [TestMethod]
+    public void HasTransparency_OpaqueAlphaChannel_FalseAfterDecode()
+    {
+        // Arrange - rotate90_lossless has an alpha channel that is 255 everywhere
+        var data = File.ReadAllBytes("TestData/rotate90_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.DetectTransparency = true;
+
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        Assert.IsNull(decoder.HasTransparency());
+        decoder.ReadInfo();
+        Assert.AreEqual(true, decoder.HasTransparency());
+
+        // Act
+        decoder.GetPixels();
+
+        // Assert
+        Assert.AreEqual(false, decoder.HasTransparency());
+    }
+
+    [TestMethod]
+    public void GetFrameHeader_OpaqueAlphaChannel_IsOpaqueAfterDecode()
+    {
+        // Arrange - rotate90_lossless has an alpha channel that is 255 everywhere
+        var data = File.ReadAllBytes("TestData/rotate90_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.DetectTransparency = true;
+
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, decoder.Process());
+        Assert.IsFalse(decoder.GetFrameHeader().IsOpaque);
+
+        // Act
+        Assert.AreEqual(JxlDecoderEvent.NeedOutputBuffer, decoder.Process());
+        var buffer = new byte[decoder.GetBufferSize()];
+        decoder.ReadPixels(buffer);
+
+        // Assert - the alpha scan shows the frame is opaque
+        Assert.IsTrue(decoder.GetFrameHeader().IsOpaque);
+    }
+
+    [TestMethod]
+    public void GetState_FollowsDecodeProgress()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        Assert.AreEqual(JxlDecoderStateTag.Initialized, decoder.GetState());
+
+        // Act & Assert
+        decoder.ReadInfo();
+        Assert.AreEqual(JxlDecoderStateTag.WithImageInfo, decoder.GetState());
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, decoder.Process());
+        Assert.AreEqual(JxlDecoderStateTag.WithFrameInfo, decoder.GetState());
+    }
+
+    [TestMethod]
+    public void ToneMapping_SourceIntensityTargetOverride_ChangesOutput()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/hdr_pq_test.jxl");
+
+        byte[] Decode(float sourceOverride)
+        {
+            var options = JxlDecodeOptions.Default;
+            options.CmsType = JxlCmsType.Bt2446a;
+            options.SourceIntensityTargetOverride = sourceOverride;
+
+            using var decoder = new JxlDecoder(options);
+            decoder.SetInput(data);
+            decoder.ReadInfo();
+            using var srgb = JxlColorProfile.CreateSrgb();
+            decoder.SetOutputColorProfile(srgb);
+            return decoder.GetPixels();
+        }
+
+        // Act
+        var auto = Decode(0f);
+        var overridden = Decode(1000f);
+
+        // Assert
+        Assert.AreEqual(auto.Length, overridden.Length);
+        CollectionAssert.AreNotEqual(auto, overridden,
+            "Overriding the source peak should change the tone-mapped output");
+    }
+
+    [TestMethod]
+    public void ToneMapper_Rec2408_CompressesHighlights()
+    {
+        // Arrange - source peak white and a black pixel
+        var rgb = new float[] { 1f, 1f, 1f, 0f, 0f, 0f };
+
+        // Act
+        JxlToneMapper.ToneMap(rgb, JxlToneMapMethod.Rec2408, 4000f, 203f);
+
+        // Assert - output is normalized to the target peak
+        foreach (var v in rgb)
+        {
+            Assert.IsTrue(v >= 0f && v <= 1.0001f, $"Sample should be within [0, 1], got {v}");
+        }
+        Assert.IsTrue(rgb[0] > 0.9f, $"Source peak should map near target peak, got {rgb[0]}");
+        Assert.AreEqual(0f, rgb[3], 1e-4f);
+    }
+
+    [TestMethod]
+    public void ToneMapper_InverseBt2446a_RoundTrips()
+    {
+        // Arrange
+        var original = new float[] { 0.8f, 0.4f, 0.1f, 0.01f, 0.02f, 0.005f, 0f, 0f, 0f };
+        var rgb = (float[])original.Clone();
+
+        // Act
+        JxlToneMapper.ToneMap(rgb, JxlToneMapMethod.Bt2446aLinear, 1000f, 203f);
+        JxlToneMapper.InverseToneMap(rgb, 203f, 1000f);
+
+        // Assert
+        for (int i = 0; i < rgb.Length; i++)
+        {
+            Assert.AreEqual(original[i], rgb[i], 1e-3f * Math.Max(original[i], 1e-3f),
+                $"Sample {i} should round-trip");
+        }
+    }
+
+    [TestMethod]
+    public void ToneMapper_InvalidLength_Throws()
+    {
+        var rgb = new float[4];
+
+        Assert.ThrowsException<ArgumentException>(
+            () => JxlToneMapper.ToneMap(rgb, JxlToneMapMethod.Bt2446a, 1000f, 203f));
+    }
+
+    [TestMethod]
+    public void TransferFunctions_LinearToSrgb_RoundTrips()
+    {
+        var samples = new[] { 0f, 0.2140f, 1f };
+
+        JxlTransferFunctions.Convert(samples, JxlTransferFunctionType.Linear, JxlTransferFunctionType.Srgb);
+        Assert.AreEqual(0.5f, samples[1], 1e-3f);
+        Assert.AreEqual(1f, samples[2], 1e-3f);
+
+        JxlTransferFunctions.Convert(samples, JxlTransferFunctionType.Srgb, JxlTransferFunctionType.Linear);
+        Assert.AreEqual(0.2140f, samples[1], 1e-3f);
+    }
+
+    [TestMethod]
+    public void TransferFunctions_InvalidIntensityTarget_Throws()
+    {
+        var samples = new float[3];
+
+        Assert.ThrowsException<JxlException>(() => JxlTransferFunctions.Convert(
+            samples, JxlTransferFunctionType.Pq, JxlTransferFunctionType.Linear, intensityTarget: 0f));
+    }
+
+    [TestMethod]
+    public void ToneMapping_ClipStats_ThrowsWhenNotEnabled()
+    {
+        using var decoder = new JxlDecoder();
+
+        Assert.ThrowsException<JxlException>(() => decoder.GetToneMapClipStats());
+    }
+
+    [TestMethod]
+    public void MemoryLimit_TooSmall_ThrowsMemoryLimitExceeded()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.MemoryLimitBytes = (UIntPtr)1000;
+
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+
+        // Act & Assert
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.ReadInfo());
+        Assert.AreEqual(JxlStatus.MemoryLimitExceeded, ex.Status);
+    }
+
+    [TestMethod]
+    public void MemoryLimit_LargeEnough_DecodesNormally()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.MemoryLimitBytes = (UIntPtr)(256 * 1024 * 1024);
+
+        // Act
+        using var image = JxlImage.Decode(data, JxlPixelFormat.Rgba8, options);
+
+        // Assert
+        Assert.IsTrue(image.Width > 0);
+    }
+
+    [TestMethod]
+    public void BytesConsumed_AdvancesWithDecodeAndResetsOnRewind()
     {
-        // Arrange - use animated test file
-        var data = File.ReadAllBytes("TestData/animation_spline.jxl");
-
+        // Arrange
+        var data = File.ReadAllBytes("TestData/dice.jxl");
         using var decoder = new JxlDecoder();
         decoder.SetInput(data);
+        Assert.AreEqual(0L, decoder.GetBytesConsumed());
 
         // Act
-        var metadata = decoder.ParseFrameMetadata();
-        var basicInfo = decoder.BasicInfo;
+        decoder.ReadInfo();
+        var afterHeader = decoder.GetBytesConsumed();
+        decoder.GetPixels();
+        var afterPixels = decoder.GetBytesConsumed();
 
         // Assert
-        Assert.IsNotNull(basicInfo, "BasicInfo should be available after ParseFrameMetadata");
-        Assert.IsTrue(basicInfo.IsAnimated, "Should be animated");
-        Assert.IsTrue(metadata.FrameCount > 1, $"Expected multiple frames, got {metadata.FrameCount}");
-        Assert.AreEqual(metadata.Frames.Count, metadata.FrameCount, "FrameCount should match Frames.Count");
-        Assert.IsTrue(metadata.GetTotalDurationMs() > 0, "Should have positive total duration");
-        Assert.IsNull(metadata.FrameNames, "FrameNames should be null when includeNames is false");
+        Assert.IsTrue(afterHeader > 0, "Header parsing should consume input");
+        Assert.IsTrue(afterPixels > afterHeader, "Pixel decoding should consume more input");
+        Assert.IsTrue(afterPixels <= data.Length);
 
-        // All frames should have valid dimensions
-        foreach (var frame in metadata.Frames)
-        {
-            Assert.IsTrue(frame.FrameWidth > 0, "Frame should have valid width");
-            Assert.IsTrue(frame.FrameHeight > 0, "Frame should have valid height");
-        }
-
-        // Verify frame durations are consistent (all should be equal for this test file)
-        var firstDuration = metadata.Frames[0].DurationMs;
-        Assert.IsTrue(metadata.Frames.All(f => Math.Abs(f.DurationMs - firstDuration) < 0.01f),
-            "All frames should have equal duration in animation_spline.jxl");
+        decoder.Rewind();
+        Assert.AreEqual(0L, decoder.GetBytesConsumed());
     }
 
     [TestMethod]
-    public void ParseFrameMetadata_StaticImage_ReturnsSingleFrame()
+    public void SetInputBorrowed_DecodesSameAsCopiedInput()
     {
-        // Arrange - use static test file
+        // Arrange
         var data = File.ReadAllBytes("TestData/dice.jxl");
 
-        using var decoder = new JxlDecoder();
-        decoder.SetInput(data);
+        using var copied = new JxlDecoder();
+        copied.SetInput(data);
+        copied.ReadInfo();
+        var expected = copied.GetPixels();
 
         // Act
-        var metadata = decoder.ParseFrameMetadata();
-        var basicInfo = decoder.BasicInfo;
+        using var borrowed = new JxlDecoder();
+        borrowed.SetInputBorrowed(data);
+        borrowed.ReadInfo();
+        var actual = borrowed.GetPixels();
 
         // Assert
-        Assert.IsNotNull(basicInfo, "BasicInfo should be available after ParseFrameMetadata");
-        Assert.IsFalse(basicInfo.IsAnimated, "Should not be animated");
-        Assert.AreEqual(1, metadata.FrameCount, "Static image should have 1 frame");
-        Assert.AreEqual(0, metadata.Frames[0].DurationMs, "Static image frame should have 0 duration");
+        CollectionAssert.AreEqual(expected, actual);
 
-        // Verify dimensions are valid for dice.jxl
-        Assert.IsTrue(basicInfo.Size.Width > 0 && basicInfo.Size.Height > 0,
-            "Should have valid dimensions");
+        var ex = Assert.ThrowsException<JxlException>(() => borrowed.AppendInput(new byte[] { 0 }));
+        Assert.AreEqual(JxlStatus.InvalidState, ex.Status);
     }
 
     [TestMethod]
-    public void ParseFrameMetadata_MaxFramesLimit_ThrowsWhenExceeded()
+    public void SkipToNextImage_SkipsLeadingJunk()
     {
-        // Arrange - use animated test file
-        var data = File.ReadAllBytes("TestData/animation_spline.jxl");
+        // Arrange
+        var image = File.ReadAllBytes("TestData/dice.jxl");
+        var junk = System.Text.Encoding.ASCII.GetBytes("not an image");
+        var data = junk.Concat(image).ToArray();
+
+        using var expected = new JxlDecoder();
+        expected.SetInput(image);
+        var expectedInfo = expected.ReadInfo();
 
         using var decoder = new JxlDecoder();
         decoder.SetInput(data);
+        Assert.IsTrue(decoder.HasTrailingData());
 
-        // Act & Assert - limit to 2 frames should throw since animation has more
-        var ex = Assert.ThrowsException<JxlException>(() => decoder.ParseFrameMetadata(maxFrames: 2));
-        Assert.IsTrue(ex.Message.Contains("exceeded limit"), $"Expected limit exceeded message, got: {ex.Message}");
+        // Act
+        var found = decoder.SkipToNextImage();
+
+        // Assert
+        Assert.IsTrue(found);
+        Assert.AreEqual((long)junk.Length, decoder.GetBytesConsumed());
+        var info = decoder.ReadInfo();
+        Assert.AreEqual(expectedInfo.Size, info.Size);
     }
 
     [TestMethod]
-    public void ToneMapping_HdrPqFile_HasHighIntensityTarget()
+    public void SkipToNextImage_AfterLastImage_ReturnsFalse()
     {
-        // Arrange - HDR PQ (Perceptual Quantizer) test file
-        var data = File.ReadAllBytes("TestData/hdr_pq_test.jxl");
+        // Arrange
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        decoder.GetPixels();
+
+        // Act & Assert
+        Assert.IsFalse(decoder.HasTrailingData());
+        Assert.IsFalse(decoder.SkipToNextImage());
+    }
+
+    [TestMethod]
+    public void NextImage_ConcatenatedStream_DecodesBothImages()
+    {
+        // Arrange
+        var first = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var second = File.ReadAllBytes("TestData/dice.jxl");
+        var data = first.Concat(second).ToArray();
+
+        using var reference = new JxlDecoder();
+        reference.SetInput(second);
+        var expectedInfo = reference.ReadInfo();
+        var expectedPixels = reference.GetPixels();
 
         using var decoder = new JxlDecoder();
         decoder.SetInput(data);
 
         // Act
-        var info = decoder.ReadInfo();
-
-        // Assert - HDR files should have IntensityTarget > 255 nits (SDR max)
-        Assert.IsTrue(info.ToneMapping.IntensityTarget > 255f,
-            $"HDR PQ file should have IntensityTarget > 255, got {info.ToneMapping.IntensityTarget}");
-        Assert.IsTrue(info.IsHdr, "IsHdr should be true for HDR PQ file");
+        var firstInfo = decoder.ReadInfo();
+        decoder.GetPixels();
+        Assert.AreEqual(JxlDecoderEvent.Complete, decoder.Process());
 
-        // PQ content typically has intensity target in range 1000-10000 nits
-        Assert.IsTrue(info.ToneMapping.IntensityTarget >= 1000f && info.ToneMapping.IntensityTarget <= 10000f,
-            $"HDR PQ IntensityTarget should be in typical PQ range (1000-10000), got {info.ToneMapping.IntensityTarget}");
+        var evt = decoder.NextImage();
+        var secondInfo = decoder.GetBasicInfo();
+        var secondPixels = decoder.GetPixels();
 
-        // MinNits should be non-negative
-        Assert.IsTrue(info.ToneMapping.MinNits >= 0f,
-            $"MinNits should be non-negative, got {info.ToneMapping.MinNits}");
+        // Assert
+        Assert.AreEqual((nuint)3, firstInfo.Size.Width);
+        Assert.AreEqual(JxlDecoderEvent.HaveBasicInfo, evt);
+        Assert.AreEqual(expectedInfo.Size, secondInfo.Size);
+        CollectionAssert.AreEqual(expectedPixels, secondPixels);
     }
 
     [TestMethod]
-    public void ToneMapping_HdrHlgFile_HasHighIntensityTarget()
+    public void GetDebugState_DescribesDecoder()
     {
-        // Arrange - HDR HLG (Hybrid Log-Gamma) test file
-        var data = File.ReadAllBytes("TestData/hdr_hlg_test.jxl");
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        using var decoder = new JxlDecoder();
+        StringAssert.Contains(decoder.GetDebugState(), "state: Initialized");
+
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var state = decoder.GetDebugState();
+
+        StringAssert.Contains(state, "state: WithImageInfo");
+        StringAssert.Contains(state, $"input: {data.Length} bytes");
+        StringAssert.Contains(state, "basic info: 800x600");
+    }
 
+    [TestMethod]
+    public void GetLastFrameHash_MatchesSha256OfPixels()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
         using var decoder = new JxlDecoder();
         decoder.SetInput(data);
+        decoder.ReadInfo();
+        var pixels = decoder.GetPixels();
 
         // Act
-        var info = decoder.ReadInfo();
-
-        // Assert - HDR files should have IntensityTarget > 255 nits (SDR max)
-        Assert.IsTrue(info.ToneMapping.IntensityTarget > 255f,
-            $"HDR HLG file should have IntensityTarget > 255, got {info.ToneMapping.IntensityTarget}");
-        Assert.IsTrue(info.IsHdr, "IsHdr should be true for HDR HLG file");
+        byte[] hash;
+        try
+        {
+            hash = decoder.GetLastFrameHash();
+        }
+        catch (JxlException ex) when (ex.Message.Contains("not compiled in"))
+        {
+            Assert.Inconclusive("Native library was built without the sha2 feature");
+            return;
+        }
 
-        // HLG typically uses 1000 nits as reference
-        Assert.IsTrue(info.ToneMapping.IntensityTarget >= 1000f,
-            $"HDR HLG IntensityTarget should be >= 1000 nits, got {info.ToneMapping.IntensityTarget}");
+        // Assert
+        CollectionAssert.AreEqual(System.Security.Cryptography.SHA256.HashData(pixels), hash);
 
-        // MinNits should be non-negative
-        Assert.IsTrue(info.ToneMapping.MinNits >= 0f,
-            $"MinNits should be non-negative, got {info.ToneMapping.MinNits}");
+        decoder.Rewind();
+        var rewound = Assert.ThrowsException<JxlException>(() => decoder.GetLastFrameHash());
+        Assert.AreEqual(JxlStatus.InvalidState, rewound.Status);
     }
 
     [TestMethod]
-    public void ToneMapping_SdrFile_HasStandardIntensityTarget()
+    public void GetTimings_AccumulatePerPhaseAndResetOnRewind()
     {
-        // Arrange - Standard SDR file
+        // Arrange
         var data = File.ReadAllBytes("TestData/dice.jxl");
-
         using var decoder = new JxlDecoder();
         decoder.SetInput(data);
+        decoder.ReadInfo();
+        decoder.GetPixels();
 
         // Act
-        var info = decoder.ReadInfo();
+        JxlDecodeTimings timings;
+        try
+        {
+            timings = decoder.GetTimings();
+        }
+        catch (JxlException ex) when (ex.Message.Contains("not compiled in"))
+        {
+            Assert.Inconclusive("Native library was built without the timings feature");
+            return;
+        }
 
-        // Assert - SDR files should have IntensityTarget <= 255 nits
-        Assert.IsTrue(info.ToneMapping.IntensityTarget <= 255f,
-            $"SDR file should have IntensityTarget <= 255, got {info.ToneMapping.IntensityTarget}");
-        Assert.IsFalse(info.IsHdr, "IsHdr should be false for SDR file");
+        // Assert
+        Assert.IsTrue(timings.HeaderMs > 0);
+        Assert.IsTrue(timings.FrameDecodeMs > 0);
+        Assert.IsTrue(timings.FrameDecodeMs >= timings.CmsMs);
+
+        decoder.Rewind();
+        var rewound = decoder.GetTimings();
+        Assert.AreEqual(0.0, rewound.HeaderMs);
+        Assert.AreEqual(0.0, rewound.FrameDecodeMs);
+        Assert.AreEqual(0.0, rewound.CmsMs);
+    }
 
-        // SDR typically uses ~80-100 nits
-        Assert.IsTrue(info.ToneMapping.IntensityTarget >= 0f,
-            $"IntensityTarget should be non-negative, got {info.ToneMapping.IntensityTarget}");
+    [TestMethod]
+    public void NextImage_BeforeComplete_Throws()
+    {
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.NextImage());
+        Assert.AreEqual(JxlStatus.InvalidState, ex.Status);
     }
 
     [TestMethod]
@@ -981,6 +2265,25 @@ public class JxlDecoderTests
             "Container signature with trailing data should still return Container");
     }
 
+    [TestMethod]
+    public void CheckSignature_Container_ReportsBoxStartAfterSignature()
+    {
+        var data = File.ReadAllBytes("TestData/all_metadata.jxl");
+        Assert.AreEqual(JxlSignature.Container, JxlImage.CheckSignature(data, out var boxStart));
+
+        // The next box is ftyp, immediately after the signature box
+        Assert.AreEqual(JxlImage.SignatureMinBytes, boxStart);
+        Assert.AreEqual("ftyp", System.Text.Encoding.ASCII.GetString(data, boxStart + 4, 4));
+    }
+
+    [TestMethod]
+    public void CheckSignature_Codestream_ReportsBoxStartZero()
+    {
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        Assert.AreEqual(JxlSignature.Codestream, JxlImage.CheckSignature(data, out var boxStart));
+        Assert.AreEqual(0, boxStart);
+    }
+
     // =========================================================================
     // Error Handling Tests
     // =========================================================================
@@ -1188,4 +2491,240 @@ public class JxlDecoderTests
         Assert.AreEqual(13u, header.NameLength, $"Expected NameLength=13, got {header.NameLength}");
         Assert.AreEqual("TestFrameName", frameName, $"Expected 'TestFrameName', got '{frameName}'");
     }
+
+    [TestMethod]
+    public void GetFrameNameLength_MatchesFrameNameLength()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/named_frame_test.jxl");
+        using var decoder = new JxlDecoder();
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var evt = decoder.Process();
+        while (evt != JxlDecoderEvent.HaveFrameHeader && evt != JxlDecoderEvent.Complete)
+        {
+            evt = decoder.Process();
+        }
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, evt);
+
+        // Act
+        var length = decoder.GetFrameNameLength();
+
+        // Assert
+        Assert.AreEqual(decoder.GetFrameName().Length, length);
+        Assert.AreEqual(13, length);
+    }
+
+    [TestMethod]
+    public void PreferPreview_OutputsPreviewBeforeImage()
+    {
+        // Arrange - preview_lossless.jxl is 16x16 with a 4x4 preview
+        var data = File.ReadAllBytes("TestData/preview_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.PreferPreview = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        var info = decoder.ReadInfo();
+
+        // Act & Assert - the preview frame comes first, sized to the preview
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, decoder.Process());
+        var previewHeader = decoder.GetFrameHeader();
+        Assert.IsTrue(previewHeader.IsPreview);
+        Assert.AreEqual((uint)info.PreviewSize!.Value.Width, previewHeader.FrameWidth);
+        Assert.AreEqual((uint)info.PreviewSize!.Value.Height, previewHeader.FrameHeight);
+        Assert.AreEqual(4 * 4 * 4, decoder.GetBufferSize());
+
+        Assert.AreEqual(JxlDecoderEvent.NeedOutputBuffer, decoder.Process());
+        var preview = new byte[decoder.GetBufferSize()];
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, decoder.ReadPixels(preview));
+
+        // Then the image itself at full size
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, decoder.Process());
+        var imageHeader = decoder.GetFrameHeader();
+        Assert.IsFalse(imageHeader.IsPreview);
+        Assert.AreEqual(16u, imageHeader.FrameWidth);
+        Assert.AreEqual(16 * 16 * 4, decoder.GetBufferSize());
+    }
+
+    [TestMethod]
+    public void SetPreviewPixelFormat_PreviewAndImageUseOwnFormats()
+    {
+        // Arrange - a 4x4 preview as Rgb8 ahead of the 16x16 image as Rgba32F
+        var data = File.ReadAllBytes("TestData/preview_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.PreferPreview = true;
+        options.PixelFormat = JxlPixelFormat.Rgba32F;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.SetPreviewPixelFormat(JxlPixelFormat.Rgb8);
+        decoder.ReadInfo();
+
+        // Act & Assert
+        Assert.AreEqual(4 * 4 * 3, decoder.GetPreviewBufferSize());
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, decoder.Process());
+        Assert.AreEqual(JxlDecoderEvent.NeedOutputBuffer, decoder.Process());
+        Assert.AreEqual(4 * 4 * 3, decoder.GetBufferSize());
+        var preview = new byte[decoder.GetBufferSize()];
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, decoder.ReadPixels(preview));
+
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, decoder.Process());
+        Assert.AreEqual(16 * 16 * 4 * sizeof(float), decoder.GetBufferSize());
+    }
+
+    [TestMethod]
+    public void PreferPreview_AnimatedImage_OutputsPreviewThenEachFrame()
+    {
+        // Arrange - preview_animation_lossless.jxl has a 4x4 preview and three 8x8 frames
+        var data = File.ReadAllBytes("TestData/preview_animation_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.PreferPreview = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act
+        var headers = new List<JxlFrameHeader>();
+        var ticks = new List<uint>();
+        JxlDecoderEvent evt;
+        while ((evt = decoder.Process()) == JxlDecoderEvent.HaveFrameHeader)
+        {
+            headers.Add(decoder.GetFrameHeader());
+            ticks.Add(decoder.GetFrameDurationTicks());
+            Assert.AreEqual(JxlDecoderEvent.NeedOutputBuffer, decoder.Process());
+            var pixels = new byte[decoder.GetBufferSize()];
+            Assert.AreEqual(JxlDecoderEvent.FrameComplete, decoder.ReadPixels(pixels));
+        }
+
+        // Assert - the preview has no duration, and Complete follows the last frame
+        Assert.AreEqual(JxlDecoderEvent.Complete, evt);
+        Assert.IsFalse(decoder.HasMoreFrames());
+        CollectionAssert.AreEqual(new[] { true, false, false, false }, headers.Select(h => h.IsPreview).ToArray());
+        CollectionAssert.AreEqual(new[] { 4u, 8u, 8u, 8u }, headers.Select(h => h.FrameWidth).ToArray());
+        CollectionAssert.AreEqual(new[] { 0f, 100f, 100f, 100f }, headers.Select(h => h.DurationMs).ToArray());
+        CollectionAssert.AreEqual(new[] { 0u, 10u, 10u, 10u }, ticks);
+    }
+
+    [TestMethod]
+    public void RowAlignment_PadsStrideToAlignedBufferSize()
+    {
+        // Arrange - 3x3 RGBA8 rows are 12 bytes, padded to 32
+        var data = File.ReadAllBytes("TestData/3x3_srgb_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.RowAlignment = 32;
+
+        using (var decoder = new JxlDecoder(options))
+        {
+            decoder.SetInput(data);
+            decoder.ReadInfo();
+
+            // Act & Assert - the decoder's buffer size includes the padding
+            Assert.AreEqual(decoder.GetAlignedBufferSize(32), decoder.GetBufferSize());
+            Assert.AreEqual(3 * 32, decoder.GetBufferSize());
+            Assert.AreEqual(3 * 3 * 4, decoder.GetAlignedBufferSize(0));
+        }
+
+        using var aligned = JxlImage.Decode(data, options);
+        using var packed = JxlImage.Decode(data);
+        Assert.AreEqual(32, aligned.Stride);
+        for (int y = 0; y < aligned.Height; y++)
+        {
+            CollectionAssert.AreEqual(
+                packed.Pixels.Slice(y * packed.Stride, 12).ToArray(),
+                aligned.Pixels.Slice(y * aligned.Stride, 12).ToArray(),
+                $"Row {y} differs");
+        }
+    }
+
+    [TestMethod]
+    public void ContinueOnFrameError_CorruptBitstream_StillThrows()
+    {
+        // Arrange - the second frame's sections are damaged, so it can't be skipped either
+        var data = File.ReadAllBytes("TestData/corrupt_frame.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.ContinueOnFrameError = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+        var buffer = new byte[decoder.GetBufferSize()];
+
+        // Act & Assert - the first frame decodes, the second throws
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, decoder.Process());
+        Assert.AreEqual(JxlDecoderEvent.NeedOutputBuffer, decoder.Process());
+        Assert.AreEqual(JxlDecoderEvent.FrameComplete, decoder.ReadPixels(buffer));
+
+        Assert.AreEqual(JxlDecoderEvent.HaveFrameHeader, decoder.Process());
+        Assert.AreEqual(JxlDecoderEvent.NeedOutputBuffer, decoder.Process());
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.ReadPixels(buffer));
+        StringAssert.Contains(ex.Message, "Pixel decode error");
+    }
+
+    [TestMethod]
+    public void MetadataOnly_CompletesAfterHeader()
+    {
+        // Arrange
+        var data = File.ReadAllBytes("TestData/all_metadata.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.MetadataOnly = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+
+        // Act
+        var info = decoder.ReadInfo();
+
+        // Assert - metadata is readable, and no frame is decoded
+        Assert.IsTrue(info.Width > 0);
+        Assert.AreEqual(1, decoder.Metadata.ExifBoxCount);
+        Assert.IsNotNull(decoder.Metadata.GetXmlBox(0));
+        Assert.IsFalse(decoder.HasMoreFrames());
+        Assert.AreEqual(JxlDecoderEvent.Complete, decoder.Process());
+    }
+
+    [TestMethod]
+    public void MaxFrames_CompletesAfterLimit()
+    {
+        // Arrange - the animation has three frames
+        var data = File.ReadAllBytes("TestData/animation_lossless.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.MaxFrames = 2;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act
+        var frames = 0;
+        var buffer = new byte[decoder.GetBufferSize()];
+        JxlDecoderEvent evt;
+        while ((evt = decoder.Process()) != JxlDecoderEvent.Complete)
+        {
+            if (evt == JxlDecoderEvent.NeedOutputBuffer)
+            {
+                Assert.AreEqual(JxlDecoderEvent.FrameComplete, decoder.ReadPixels(buffer));
+                frames++;
+            }
+        }
+
+        // Assert
+        Assert.AreEqual(2, frames);
+        Assert.IsFalse(decoder.HasMoreFrames());
+    }
+
+    [TestMethod]
+    public void GetPixels_NoFrames_ThrowsInvalidStateWithZeroBufferSize()
+    {
+        // Arrange - metadata-only decoding completes without any frame
+        var data = File.ReadAllBytes("TestData/dice.jxl");
+        var options = JxlDecodeOptions.Default;
+        options.MetadataOnly = true;
+        using var decoder = new JxlDecoder(options);
+        decoder.SetInput(data);
+        decoder.ReadInfo();
+
+        // Act
+        var bufferSize = decoder.GetBufferSize();
+        var ex = Assert.ThrowsException<JxlException>(() => decoder.GetPixels());
+
+        // Assert
+        Assert.AreEqual(0, bufferSize);
+        Assert.AreEqual(JxlStatus.InvalidState, ex.Status);
+    }
 }